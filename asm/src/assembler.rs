@@ -1,30 +1,485 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use crate::{
-    error::SyntaxError,
-    instruction::{Instruction, Opcode},
-    token::{RawToken, Token, TokenKind},
+    error::{expected_delimiter, syntax_error, unexpected_token, warning, Diagnostic},
+    instruction::{ends_block, extra_cycles, AddressMode, Instruction, Opcode},
+    lint::{Lint, Warnings},
+    object::{self, Export, Object, Relocation, Target},
+    token::{LitKind, OpKind, RawToken, Token, TokenKind},
     utils::*,
 };
 
+/// An operand expression, kept as tokens until every symbol is known.
+type Expr<'a> = &'a [Token<'a>];
+
+/// Maps label and symbol names to their values.
+type SymbolTable<'a> = HashMap<&'a str, u32>;
+
 enum IRCode<'a> {
-    Label(&'a str),
-    Symbol(&'a str),
-    Opcode(&'static Opcode, &'a [Token<'a>]),
-    Expression(Vec<Token<'a>>),
-    Value(u32),
+    /// Switches to the segment at the given index.
+    Segment(usize),
+    /// Moves the location counter.
+    Org(u16),
+    /// An instruction and its operand, if it takes one, and whether absolute
+    /// addressing was forced with `a:`.
+    Opcode(&'a Token<'a>, Opcode, Option<Expr<'a>>, bool),
+    /// A single byte given by an expression.
+    Expression(Expr<'a>),
+    /// A little endian word given by an expression.
+    Word(Expr<'a>),
+    /// Bytes known in the first pass, such as string literals.
+    Bytes(&'a Token<'a>, Vec<u8>),
+    /// A label at the location counter, which starts a block of code.
+    Label(&'a Token<'a>),
+}
+
+/// The name of the segment code goes in until a `.segment` directive.
+const DEFAULT_SEGMENT: &str = "default";
+
+/// The name of the encoding strings are in until an `.enc` directive.
+const DEFAULT_ENCODING: &str = "ascii";
+
+/// How the characters of strings are turned into bytes, set up with `.charmap`.
+/// Characters which are not mapped are emitted as their UTF-8 bytes, so an
+/// empty map leaves ASCII as it is.
+type CharMap = HashMap<char, u8>;
+
+/// The amounts a segment is moved by to find which values depend on where it is
+/// placed. The second is not a multiple of 256, so taking the low or high byte
+/// of an address is noticed.
+const RELOCATION_PROBES: [u32; 2] = [0x10000, 0x101];
+
+struct Segment<'a> {
+    name: &'a str,
+    /// Whether the segment has a fixed address. The default segment always does,
+    /// other segments are relocatable until they are given one with `.org`.
+    absolute: bool,
+    /// The location counter, an offset if the segment is relocatable.
+    loc: u32,
+    /// Whether anything has been emitted yet.
+    emitted: bool,
+    /// Whether a label has been defined in the segment.
+    labeled: bool,
+}
+
+impl<'a> Segment<'a> {
+    fn new(name: &'a str, absolute: bool) -> Self {
+        Self {
+            name,
+            absolute,
+            loc: 0,
+            emitted: false,
+            labeled: false,
+        }
+    }
+}
+
+/// The zero page addresses `.zpvar` allocates from, which are all of zero page
+/// unless set with `.zppool`.
+struct ZeroPagePool<'a> {
+    start: u32,
+    end: u32,
+    /// The next address to allocate.
+    next: u32,
+    /// The `.zppool` directive which set the pool.
+    directive: Option<&'a Token<'a>>,
+}
+
+/// The output of the first pass.
+struct Program<'a> {
+    ir: Vec<IRCode<'a>>,
+    /// The symbols with a known value.
+    symbols: SymbolTable<'a>,
+    /// The symbols whose value depends on where the linker places a segment, or on
+    /// another object, along with their offset.
+    relative: HashMap<&'a str, (Target, u32)>,
+    /// Where each symbol was defined.
+    definitions: HashMap<&'a str, &'a Token<'a>>,
+    labels: Vec<&'a Token<'a>>,
+    /// The names used in any expression.
+    referenced: HashSet<&'a str>,
+    segments: Vec<Segment<'a>>,
+    /// The index of the segment being assembled.
+    segment: usize,
+    encodings: HashMap<&'a str, CharMap>,
+    /// The name of the encoding strings are emitted in.
+    encoding: &'a str,
+    imports: Vec<&'a str>,
+    exports: Vec<&'a Token<'a>>,
+    /// The lone `a` operands which were taken to be the accumulator.
+    accumulators: Vec<&'a Token<'a>>,
+    pool: ZeroPagePool<'a>,
+    /// The variables allocated with `.zpvar`.
+    zero_page: Vec<object::Allocation>,
+    /// Whether the program is assembled to an object, which may leave addresses to
+    /// the linker.
+    object: bool,
+}
+
+impl<'a> Program<'a> {
+    fn new(object: bool) -> Self {
+        Self {
+            ir: vec![],
+            symbols: SymbolTable::new(),
+            relative: HashMap::new(),
+            definitions: HashMap::new(),
+            labels: vec![],
+            referenced: HashSet::new(),
+            segments: vec![Segment::new(DEFAULT_SEGMENT, true)],
+            segment: 0,
+            encodings: HashMap::from([(DEFAULT_ENCODING, CharMap::new())]),
+            encoding: DEFAULT_ENCODING,
+            imports: vec![],
+            exports: vec![],
+            accumulators: vec![],
+            pool: ZeroPagePool {
+                start: 0,
+                end: 0x100,
+                next: 0,
+                directive: None,
+            },
+            zero_page: vec![],
+            object,
+        }
+    }
+
+    fn current(&mut self) -> &mut Segment<'a> {
+        &mut self.segments[self.segment]
+    }
+
+    fn check_undefined(&self, name: &'a Token<'a>) -> Result<(), Diagnostic> {
+        let key = name.name;
+        if let Some(previous) = self.definitions.get(key) {
+            let reason = format!("'{}' is already defined", key);
+            let error = syntax_error(&name.source, reason)
+                .with_label(&previous.source, "previously defined here".to_string());
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, name: &'a Token<'a>, value: u32) -> Result<(), Diagnostic> {
+        self.check_undefined(name)?;
+        self.definitions.insert(name.name, name);
+        self.symbols.insert(name.name, value);
+        Ok(())
+    }
+
+    fn define_relative(
+        &mut self,
+        name: &'a Token<'a>,
+        target: Target,
+        offset: u32,
+    ) -> Result<(), Diagnostic> {
+        self.check_undefined(name)?;
+        self.definitions.insert(name.name, name);
+        self.relative.insert(name.name, (target, offset));
+        Ok(())
+    }
+
+    /// Defines a label at the location counter.
+    fn define_label(&mut self, name: &'a Token<'a>) -> Result<(), Diagnostic> {
+        self.check_placed(name)?;
+        self.labels.push(name);
+        self.ir.push(IRCode::Label(name));
+        let segment = self.current();
+        segment.labeled = true;
+        if segment.absolute {
+            let loc = segment.loc;
+            return self.define(name, loc);
+        }
+        let loc = segment.loc;
+        self.define_relative(name, Target::Segment(self.segment), loc)
+    }
+
+    /// Ensures the current segment has an address, unless it can be left to the
+    /// linker.
+    fn check_placed(&self, token: &'a Token<'a>) -> Result<(), Diagnostic> {
+        let segment = &self.segments[self.segment];
+        if segment.absolute || self.object {
+            return Ok(());
+        }
+        let reason = format!(
+            "segment '{}' needs an address from '.org' unless assembling an object",
+            segment.name
+        );
+        Err(syntax_error(&token.source, reason))
+    }
+
+    /// Returns the bytes of `string` in the current encoding.
+    fn encode(&self, string: &str) -> Vec<u8> {
+        let map = &self.encodings[self.encoding];
+        let mut bytes = vec![];
+        for c in string.chars() {
+            match map.get(&c) {
+                Some(byte) => bytes.push(*byte),
+                None => bytes.extend(c.to_string().as_bytes()),
+            }
+        }
+        bytes
+    }
+
+    /// Notes the names used in `expr`.
+    fn refer(&mut self, expr: Expr<'a>) {
+        let names = expr.iter().filter(|t| t.kind.is_identifier());
+        self.referenced.extend(names.map(|t| t.name));
+    }
+
+    fn emit(
+        &mut self,
+        token: &'a Token<'a>,
+        code: IRCode<'a>,
+        size: u32,
+    ) -> Result<(), Diagnostic> {
+        self.check_placed(token)?;
+        match code {
+            IRCode::Opcode(_, _, Some(expr), _) | IRCode::Expression(expr) | IRCode::Word(expr) => {
+                self.refer(expr)
+            }
+            _ => {}
+        }
+        let segment = self.current();
+        segment.loc += size;
+        segment.emitted = true;
+        if segment.loc > 0x10000 {
+            let reason = "program runs past the end of memory".to_string();
+            return Err(syntax_error(&token.source, reason));
+        }
+        self.ir.push(code);
+        Ok(())
+    }
+
+    /// Evaluates an expression, along with the segment or import it is relative
+    /// to, if any.
+    ///
+    /// A value is relative to a segment if moving the segment moves the value by
+    /// the same amount, so `label + 1` is relative but the distance between two
+    /// labels in one segment is not. Values which move in any other way, such as
+    /// `label * 2`, cannot be relocated.
+    fn resolve(
+        &self,
+        context: &'a Token<'a>,
+        expr: Expr<'a>,
+    ) -> Result<(u32, Option<Target>), Diagnostic> {
+        let names = expr
+            .iter()
+            .filter(|t| t.kind.is_identifier())
+            .map(|t| t.name)
+            .collect::<Vec<_>>();
+        let mut targets = names
+            .iter()
+            .filter_map(|name| self.relative.get(name).map(|(target, _)| *target))
+            .collect::<Vec<_>>();
+        targets.dedup();
+
+        let evaluate_moved = |moved: Option<Target>, probe: u32| {
+            let mut symbols = SymbolTable::new();
+            for name in names.iter() {
+                if let Some(value) = self.symbols.get(name) {
+                    symbols.insert(name, *value);
+                } else if let Some((target, offset)) = self.relative.get(name) {
+                    let probe = if Some(*target) == moved { probe } else { 0 };
+                    symbols.insert(name, offset.wrapping_add(probe));
+                }
+            }
+            evaluate(context, expr, &symbols)
+        };
+
+        let value = evaluate_moved(None, 0)?;
+        let mut relative_to = None;
+        for target in targets {
+            let mut moved = [0; RELOCATION_PROBES.len()];
+            for (moved, probe) in moved.iter_mut().zip(RELOCATION_PROBES) {
+                *moved = evaluate_moved(Some(target), probe)?.wrapping_sub(value);
+            }
+            match moved {
+                [0, 0] => {}
+                RELOCATION_PROBES if relative_to.is_none() => relative_to = Some(target),
+                _ => {
+                    let reason = "expression cannot be relocated".to_string();
+                    return Err(syntax_error(&expr[0].source, reason));
+                }
+            }
+        }
+        Ok((value, relative_to))
+    }
+
+    /// Evaluates a word, which may be left for the linker to relocate.
+    fn resolve_word(
+        &self,
+        context: &'a Token<'a>,
+        expr: Expr<'a>,
+    ) -> Result<(u16, Option<Target>), Diagnostic> {
+        match self.resolve(context, expr)? {
+            (value, Some(Target::ZeroPage)) if value > 0xff => Err(outside_zero_page(expr, value)),
+            // the linker adds the address modulo 64K
+            (value, Some(target)) => Ok((value as u16, Some(target))),
+            (value, None) if !fits(value, 16) => {
+                let reason = format!("value {} does not fit in a word", format_value(value));
+                Err(syntax_error(&expr[0].source, reason))
+            }
+            (value, None) => Ok((value as u16, None)),
+        }
+    }
+
+    /// Evaluates a byte, which can only be left for the linker to relocate if it
+    /// is a zero page address.
+    fn resolve_byte(
+        &self,
+        context: &'a Token<'a>,
+        expr: Expr<'a>,
+    ) -> Result<(u8, Option<Target>), Diagnostic> {
+        match self.resolve(context, expr)? {
+            (value, Some(Target::ZeroPage)) if value > 0xff => Err(outside_zero_page(expr, value)),
+            (value, Some(Target::ZeroPage)) => Ok((value as u8, Some(Target::ZeroPage))),
+            (_, Some(_)) => {
+                let reason = "a relocatable address does not fit in a byte".to_string();
+                Err(syntax_error(&expr[0].source, reason))
+            }
+            (value, None) if !fits(value, 8) => {
+                let reason = format!("value {} does not fit in a byte", format_value(value));
+                Err(syntax_error(&expr[0].source, reason))
+            }
+            (value, None) => Ok((value as u8, None)),
+        }
+    }
+}
+
+/// Returns the error for an offset from a relocatable zero page variable which
+/// leaves zero page.
+fn outside_zero_page(expr: Expr, value: u32) -> Diagnostic {
+    let reason = format!(
+        "zero page address ${:x} past the zero page variables is not in zero page",
+        value
+    );
+    syntax_error(&expr[0].source, reason)
+}
+
+/// Returns whether a value fits in `bits`, either as an unsigned number or as a
+/// negative one in two's complement, so `-1` fits in a byte as `$ff`.
+fn fits(value: u32, bits: u32) -> bool {
+    let signed = value as i32;
+    value < 1 << bits || (signed < 0 && signed >= -(1 << (bits - 1)))
+}
+
+/// Formats a value for a diagnostic, as a negative number if it was one.
+fn format_value(value: u32) -> String {
+    match value as i32 {
+        signed if signed < 0 => signed.to_string(),
+        _ => format!("${:x}", value),
+    }
+}
+
+/// The shapes an instruction operand can take.
+enum Operand<'a> {
+    None,
+    /// A lone `a`, which is the accumulator for instructions with an accumulator
+    /// mode, and otherwise an address named `a`.
+    Accumulator(Expr<'a>),
+    Immediate(Expr<'a>),
+    /// A zero page, absolute or relative address, optionally indexed.
+    Direct(Expr<'a>, Option<Index>),
+    /// An address prefixed with `a:`, which is always absolute even if it fits
+    /// in zero page.
+    Absolute(Expr<'a>, Option<Index>),
+    /// A parenthesized address, which is a plain expression for instructions
+    /// without an indirect mode.
+    Indirect(Expr<'a>),
+    IndirectX(Expr<'a>),
+    IndirectY(Expr<'a>),
+}
+
+#[derive(Clone, Copy)]
+enum Index {
+    X,
+    Y,
 }
 
 //
 //
 //
 
-pub fn assemble<'a>(tokens: &'a [RawToken<'a>]) -> Result<Vec<u8>, SyntaxError> {
+/// The output of the assembler.
+pub struct Assembly {
+    pub object: Object,
+    /// The bytes emitted for each source line, in the order they were emitted.
+    /// Addresses in relocatable segments are offsets.
+    pub listing: Vec<ListingEntry>,
+    /// The labels and symbols which fit in an address, with those exported
+    /// public.
+    pub symbols: cpu::SymbolTable,
+    /// The cycles of each instruction, in blocks of straight-line code.
+    pub cycles: Vec<CycleBlock>,
+    pub stats: Stats,
+}
+
+/// Figures about the assembled program, to keep track of its size.
+pub struct Stats {
+    /// The bytes emitted into each segment, by name. Gaps left by `.org` are not
+    /// counted.
+    pub segments: Vec<(String, usize)>,
+    /// The number of labels and symbols defined.
+    pub symbols: usize,
+    /// The zero page addresses used by instructions in a zero page mode,
+    /// including the high byte of indirect pointers.
+    pub zero_page: BTreeSet<u8>,
+    /// The longest branch forward and the longest back, by their offset.
+    pub forward: Option<Branch>,
+    pub backward: Option<Branch>,
+}
+
+/// A branch and how far it goes.
+pub struct Branch {
+    /// The source line the branch is on.
+    pub line: usize,
+    pub offset: i8,
+}
+
+pub struct ListingEntry {
+    /// The source line the bytes came from.
+    pub line: usize,
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// A run of instructions which are run one after the other, up to the next
+/// label or an instruction which may jump elsewhere.
+pub struct CycleBlock {
+    /// The label the block starts at, unless it starts after a jump.
+    pub label: Option<String>,
+    pub entries: Vec<CycleEntry>,
+}
+
+/// The cycles an instruction takes.
+pub struct CycleEntry {
+    /// The source line the instruction came from.
+    pub line: usize,
+    pub address: u16,
+    pub cycles: u8,
+    /// The most cycles a page crossing or taken branch can add. A branch within
+    /// a page at a known address can only add one.
+    pub extra: u8,
+}
+
+/// Assembles a preprocessed token stream into an object.
+///
+/// Unless `object` is set, every segment must be given an address with `.org`
+/// and nothing can be imported, so the object links on its own. The default
+/// segment starts at zero if the program does not set an address, and any gaps
+/// left by later `.org` directives are filled with zeros.
+pub fn assemble<'a>(
+    tokens: &'a [RawToken<'a>],
+    object: bool,
+    warnings: &mut Warnings,
+) -> Result<Assembly, Diagnostic> {
     let tokens = process_raw_tokens(tokens);
 
-    let ir = assembler_pass_one(&mut &tokens[..])?;
-    let bytes = assembler_pass_two(&mut &ir[..])?;
-    Ok(vec![])
+    let program = assembler_pass_one(&mut &tokens[..], object)?;
+    let assembly = assembler_pass_two(&program, warnings)?;
+    lint_labels(&program, warnings);
+    lint_accumulators(&program, warnings);
+    lint_zero_page(&program, &assembly.object, warnings);
+    Ok(assembly)
 }
 
 fn process_raw_tokens<'a>(raw_tokens: &'a [RawToken<'a>]) -> Vec<Token<'a>> {
@@ -35,19 +490,315 @@ fn process_raw_tokens<'a>(raw_tokens: &'a [RawToken<'a>]) -> Vec<Token<'a>> {
             out_tokens.push(token)
         }
     }
+    for line in out_tokens.split_mut(|t| t.kind.is_newline()) {
+        find_local_labels(line);
+    }
+    name_local_labels(&mut out_tokens);
     out_tokens
 }
 
+/// Returns whether a name is a local label, which starts with `.` or `@`.
+fn is_local(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with('@')
+}
+
+/// Marks the local labels in a line which were lexed as directives.
+///
+/// A directive can only start a statement, so `.name` is a local label when it
+/// is defined with a colon or is used anywhere after the start of a statement.
+fn find_local_labels(line: &mut [Token]) {
+    if let [first, second, ..] = line {
+        if first.kind.is_directive() && second.kind.is_colon() {
+            first.kind = TokenKind::Identifier;
+        }
+    }
+    let statement = match line {
+        _ if !is_label(line) => 0,
+        [_, colon, ..] if colon.kind.is_colon() => 2,
+        _ => 1,
+    };
+    for token in line.iter_mut().skip(statement + 1) {
+        if token.kind.is_directive() {
+            token.kind = TokenKind::Identifier;
+        }
+    }
+}
+
+/// Names each local label after the last label which is not local, so that
+/// `.loop` after `copy:` is `copy.loop`. Local labels before any other label
+/// keep their own name.
+fn name_local_labels(tokens: &mut [Token]) {
+    let mut scope = "";
+    for line in tokens.split_mut(|t| t.kind.is_newline()) {
+        if is_label(line) && !is_definition(line) && !is_local(line[0].name) {
+            scope = line[0].name;
+        }
+        for token in line.iter_mut() {
+            if token.kind.is_identifier() && is_local(token.name) {
+                let name = format!("{}{}", scope, token.name);
+                token.name = Box::leak(name.into_boxed_str());
+            }
+        }
+    }
+}
+
 /// The first assembler pass which produces an IR output.
-fn assembler_pass_one<'f, 'a>(
-    tokens: &'f mut &'a [Token<'a>],
-) -> Result<Vec<IRCode<'a>>, SyntaxError> {
-    Ok(vec![])
+///
+/// Every label is assigned an address here, so instruction sizes must be known
+/// without looking ahead. An address operand uses zero page addressing only if
+/// its value is already known to fit, which means forward references are
+/// always assembled as absolute addresses.
+/// Symbols in a relocatable segment are never known in this pass, so they are
+/// always assembled as absolute addresses too. An operand prefixed with `a:` is
+/// absolute whatever its value.
+fn assembler_pass_one<'a>(
+    tokens: &mut &'a [Token<'a>],
+    object: bool,
+) -> Result<Program<'a>, Diagnostic> {
+    let mut program = Program::new(object);
+    while !tokens.is_empty() {
+        let mut line = take_while(tokens, |t| !t.kind.is_newline());
+        take_one(tokens);
+        parse_line(&mut line, &mut program)?;
+    }
+    Ok(program)
+}
+
+/// The second assembler pass which produces the final output.
+fn assembler_pass_two<'a>(
+    program: &Program<'a>,
+    warnings: &mut Warnings,
+) -> Result<Assembly, Diagnostic> {
+    let mut segments = program
+        .segments
+        .iter()
+        .map(|segment| object::Segment {
+            name: segment.name.to_string(),
+            address: None,
+            data: vec![],
+            relocations: vec![],
+        })
+        .collect::<Vec<_>>();
+    let mut locs = vec![0u32; segments.len()];
+    let mut current = 0;
+    let mut listing = Vec::<ListingEntry>::new();
+    let mut cycles = Vec::<CycleBlock>::new();
+    let mut in_block = false;
+    let mut emitted = vec![0; segments.len()];
+    let mut zero_page = BTreeSet::new();
+    let mut forward = None::<Branch>;
+    let mut backward = None::<Branch>;
+
+    for code in program.ir.iter() {
+        let loc = locs[current];
+        let (token, bytes, relocation) = match code {
+            IRCode::Segment(index) => {
+                current = *index;
+                in_block = false;
+                continue;
+            }
+            IRCode::Org(address) => {
+                locs[current] = *address as u32;
+                in_block = false;
+                continue;
+            }
+            IRCode::Label(token) => {
+                cycles.push(CycleBlock {
+                    label: Some(token.name.to_string()),
+                    entries: vec![],
+                });
+                in_block = true;
+                continue;
+            }
+            IRCode::Opcode(token, opcode, expr, forced) => {
+                let (bytes, relocation) =
+                    encode_instruction(token, opcode, *expr, loc, current, program)?;
+                if relocation.is_none() && !forced {
+                    lint_instruction(token, opcode, &bytes, warnings);
+                }
+
+                if !in_block {
+                    cycles.push(CycleBlock {
+                        label: None,
+                        entries: vec![],
+                    });
+                }
+                let mut extra = extra_cycles(opcode);
+                if opcode.mode == AddressMode::Relative && program.segments[current].absolute {
+                    let next = loc + bytes.len() as u32;
+                    let target = next.wrapping_add(bytes[1] as i8 as u32);
+                    if (next ^ target) & 0xff00 == 0 {
+                        extra = 1;
+                    }
+                }
+                cycles.last_mut().unwrap().entries.push(CycleEntry {
+                    line: source_line(token),
+                    address: loc as u16,
+                    cycles: opcode.cycles,
+                    extra,
+                });
+                in_block = !ends_block(opcode);
+
+                match opcode.mode {
+                    AddressMode::ZeroPage | AddressMode::ZeroPageX | AddressMode::ZeroPageY => {
+                        zero_page.insert(bytes[1]);
+                    }
+                    AddressMode::IndirectX
+                    | AddressMode::IndirectY
+                    | AddressMode::ZeroPageIndirect => {
+                        zero_page.extend([bytes[1], bytes[1].wrapping_add(1)]);
+                    }
+                    AddressMode::Relative => {
+                        let offset = bytes[1] as i8;
+                        let longest = if offset < 0 {
+                            &mut backward
+                        } else {
+                            &mut forward
+                        };
+                        if longest
+                            .as_ref()
+                            .is_none_or(|b| b.offset.unsigned_abs() < offset.unsigned_abs())
+                        {
+                            *longest = Some(Branch {
+                                line: source_line(token),
+                                offset,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+
+                (*token, bytes, relocation.map(|target| (1, target)))
+            }
+            IRCode::Expression(expr) => {
+                let (value, relocation) = program.resolve_byte(&expr[0], expr)?;
+                (&expr[0], vec![value], relocation.map(|target| (0, target)))
+            }
+            IRCode::Word(expr) => {
+                let (value, relocation) = program.resolve_word(&expr[0], expr)?;
+                let bytes = value.to_le_bytes().to_vec();
+                (&expr[0], bytes, relocation.map(|target| (0, target)))
+            }
+            IRCode::Bytes(token, bytes) => (*token, bytes.clone(), None),
+        };
+
+        let segment = &mut segments[current];
+        let base = if program.segments[current].absolute {
+            *segment.address.get_or_insert(loc as u16) as u32
+        } else {
+            0
+        };
+        let offset = (loc - base) as usize;
+        if segment.data.len() < offset {
+            segment.data.resize(offset, 0);
+        }
+        if let Some((at, target)) = relocation {
+            segment.relocations.push(Relocation {
+                offset: (offset + at) as u16,
+                target,
+            });
+        }
+        segment.data.extend(bytes.iter());
+        emitted[current] += bytes.len();
+
+        let line = source_line(token);
+        match listing.last_mut() {
+            Some(last)
+                if last.line == line && last.address as u32 + last.bytes.len() as u32 == loc =>
+            {
+                last.bytes.extend(bytes.iter());
+            }
+            _ => listing.push(ListingEntry {
+                line,
+                address: loc as u16,
+                bytes: bytes.clone(),
+            }),
+        }
+        locs[current] += bytes.len() as u32;
+    }
+
+    let mut exports = vec![];
+    for token in program.exports.iter() {
+        let name = token.name;
+        let (target, value) = match (program.symbols.get(name), program.relative.get(name)) {
+            (Some(value), _) => (None, *value),
+            (_, Some((Target::Segment(index), offset))) => (Some(Target::Segment(*index)), *offset),
+            (_, Some((Target::ZeroPage, offset))) => (Some(Target::ZeroPage), *offset),
+            (_, Some((Target::Import(_), _))) => {
+                let reason = format!("cannot export the imported symbol '{}'", name);
+                return Err(syntax_error(&token.source, reason));
+            }
+            (None, None) => {
+                let reason = format!("cannot export undefined symbol '{}'", name);
+                return Err(syntax_error(&token.source, reason));
+            }
+        };
+        if value > 0xffff {
+            let reason = format!("exported value ${:x} does not fit in a word", value);
+            return Err(syntax_error(&token.source, reason));
+        }
+        exports.push(Export {
+            name: name.to_string(),
+            target,
+            value: value as u16,
+        });
+    }
+
+    let mut names: Vec<_> = program.symbols.iter().collect();
+    names.sort();
+    let mut symbols = cpu::SymbolTable::new();
+    for (name, value) in names {
+        let address = match u16::try_from(*value) {
+            Ok(address) => address,
+            Err(_) => continue,
+        };
+        if program.exports.iter().any(|token| token.name == *name) {
+            symbols.insert_public(*name, address);
+        } else {
+            symbols.insert(*name, address);
+        }
+    }
+
+    let object = Object {
+        segments,
+        imports: program
+            .imports
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        exports,
+        zero_page: program.zero_page.clone(),
+    };
+    cycles.retain(|block| !block.entries.is_empty());
+    let stats = Stats {
+        segments: program
+            .segments
+            .iter()
+            .zip(emitted)
+            .map(|(segment, bytes)| (segment.name.to_string(), bytes))
+            .collect(),
+        symbols: program.definitions.len(),
+        zero_page,
+        forward,
+        backward,
+    };
+    Ok(Assembly {
+        object,
+        listing,
+        symbols,
+        cycles,
+        stats,
+    })
 }
 
-/// The second assembler pass which produces the final binary output.
-fn assembler_pass_two<'f, 'a>(ir: &'f mut &'a [IRCode<'a>]) -> Result<Vec<u8>, SyntaxError> {
-    Ok(vec![])
+/// Returns the source line a token is on. Tokens from a macro expansion are on
+/// the line that used the macro.
+fn source_line(token: &Token) -> usize {
+    let mut source = &token.source;
+    while let Some(origin) = source.origin {
+        source = origin;
+    }
+    source.start_loc().loc.line
 }
 
 //
@@ -57,26 +808,44 @@ fn assembler_pass_two<'f, 'a>(ir: &'f mut &'a [IRCode<'a>]) -> Result<Vec<u8>, S
 /*
 
 line    = org-directive
+        | segment-directive
+        | import-directive
+        | export-directive
         | eq-directive
         | db-directive
+        | dw-directive
         | instruction
         ;
 
 
-org-directive   = ".org" number;
-eq-directive    = symbol ".eq" number;
-db-directive    = [label] (".db" | ".bytes") literal {',' literal};
+org-directive       = ".org" number;
+segment-directive   = ".segment" identifier;
+import-directive    = ".import" symbol {',' symbol};
+export-directive    = ".export" symbol {',' symbol};
+eq-directive        = symbol ".eq" number;
+db-directive        = [label] (".db" | ".bytes" | ".ascii") literal {',' literal};
+dw-directive        = [label] (".dw" | ".word") value-expr {',' value-expr};
+enc-directive       = ".enc" identifier;
+charmap-directive   = ".charmap" [value-expr [',' value-expr] ',' value-expr];
+zppool-directive    = ".zppool" value-expr ',' value-expr;
+zpvar-directive     = ".zpvar" symbol [',' value-expr];
 
 instruction     = [label] mnemonic operand;
 
 operand         =
-                | '#' (number | character);
-                |
+                | 'a'
+                | '#' value-expr
+                | value-expr [',' ('x' | 'y')]
+                | 'a' ':' value-expr [',' ('x' | 'y')]
+                | '(' value-expr ')'
+                | '(' value-expr ',' 'x' ')'
+                | '(' value-expr ')' ',' 'y'
                 ;
 
-value-expr      = '(' value-expr ')'
-                | '-' value-expr
-                | value-lit operator value-expr
+value-expr      = value-term {operator value-term};
+value-term      = '(' value-expr ')'
+                | ('-' | '+' | '~' | '!' | '<' | '>') value-term
+                | value-lit
                 ;
 
 value-lit       = symbol | character | number;
@@ -94,36 +863,892 @@ character  = <built-in>
 string     = <built-in>
 operator   = <built-in>
 
+*/
 
-    lda #
+fn parse_line<'a>(line: &mut &'a [Token<'a>], program: &mut Program<'a>) -> Result<(), Diagnostic> {
+    let first = match line.first() {
+        Some(token) => token,
+        None => return Ok(()),
+    };
 
-*/
+    // symbol definition
+    if is_definition(line) {
+        *line = &line[2..];
+        program.refer(line);
+        return match program.resolve(first, line)? {
+            (value, Some(target)) => program.define_relative(first, target, value),
+            (value, None) => program.define(first, value),
+        };
+    }
 
-fn parse_line<'f, 'a>(line: &'f mut &'a [Token<'a>]) -> Result<Vec<IRCode<'a>>, SyntaxError> {
-    if line.is_empty() {
-        return Ok(vec![]);
+    if is_label(line) {
+        take_one(line);
+        take_if(line, |t| t.kind.is_colon());
+        program.define_label(first)?;
     }
 
-    let token = take_one(line).unwrap();
+    let token = match take_one(line) {
+        Some(token) => token,
+        None => return Ok(()),
+    };
     match token.kind {
-        TokenKind::Identifier => {
-            let name = token.value();
-            if let Some(instr) = Instruction::find_by_name(name) {}
+        TokenKind::Directive => parse_directive(token, line, program),
+        TokenKind::Identifier => parse_instruction(token, line, program),
+        _ => Err(unexpected_token(token, "")),
+    }
+}
+
+/// Returns whether a line defines a symbol with `.eq`.
+fn is_definition(line: &[Token]) -> bool {
+    match line {
+        [name, eq, ..] => {
+            name.kind.is_identifier() && eq.kind.is_directive() && eq.source.value() == ".eq"
         }
-        _ => {}
+        _ => false,
     }
+}
+
+/// Returns whether a line starts with a label, which only needs a colon when
+/// it could be mistaken for an instruction.
+fn is_label(line: &[Token]) -> bool {
+    let first = match line.first() {
+        Some(first) if first.kind.is_identifier() => first,
+        _ => return false,
+    };
+    match line.get(1) {
+        Some(t) if t.kind.is_colon() => true,
+        _ if find_instruction(first).is_some() => false,
+        Some(t) if t.kind.is_identifier() => find_instruction(t).is_some(),
+        Some(t) => t.kind.is_directive(),
+        None => true,
+    }
+}
 
-    let first = take_one(line).unwrap();
-    if first.is_identifier() {}
+fn parse_directive<'a>(
+    directive: &'a Token<'a>,
+    args: &mut &'a [Token<'a>],
+    program: &mut Program<'a>,
+) -> Result<(), Diagnostic> {
+    match directive.source.value() {
+        ".org" => {
+            program.refer(args);
+            let address = evaluate(directive, args, &program.symbols)?;
+            if address > 0xffff {
+                let reason = format!("origin ${:x} is outside of memory", address);
+                return Err(syntax_error(&directive.source, reason));
+            }
+            let segment = program.current();
+            if !segment.absolute && (segment.emitted || segment.labeled) {
+                let reason = format!(
+                    "'.org' must come first in the relocatable segment '{}'",
+                    segment.name
+                );
+                return Err(syntax_error(&directive.source, reason));
+            }
+            if segment.emitted && address < segment.loc {
+                let reason = format!(
+                    "origin ${:04x} is behind the current location ${:04x}",
+                    address, segment.loc
+                );
+                return Err(syntax_error(&directive.source, reason));
+            }
+            segment.absolute = true;
+            segment.loc = address;
+            program.ir.push(IRCode::Org(address as u16));
+            Ok(())
+        }
+        ".segment" => {
+            let name = match args {
+                [name] if name.kind.is_identifier() => name.source.value(),
+                [] => return Err(unexpected_token(directive, "'.segment', expected a name")),
+                [_, token, ..] | [token] => return Err(unexpected_token(token, "'.segment'")),
+            };
+            if name == object::ZERO_PAGE {
+                let reason = format!(
+                    "segment name '{}' is reserved for zero page variables",
+                    name
+                );
+                return Err(syntax_error(&args[0].source, reason));
+            }
+            *args = &[];
+            program.segment = match program.segments.iter().position(|s| s.name == name) {
+                Some(index) => index,
+                None => {
+                    program.segments.push(Segment::new(name, false));
+                    program.segments.len() - 1
+                }
+            };
+            program.ir.push(IRCode::Segment(program.segment));
+            Ok(())
+        }
+        ".import" | ".export" => {
+            let import = directive.source.value() == ".import";
+            if import && !program.object {
+                let reason = "symbols can only be imported when assembling an object".to_string();
+                return Err(syntax_error(&directive.source, reason));
+            }
+            loop {
+                let name = match take_one(args) {
+                    Some(name) if name.kind.is_identifier() => name,
+                    Some(token) => return Err(unexpected_token(token, "symbol list")),
+                    None => return Err(unexpected_token(directive, "symbol list")),
+                };
+                if import {
+                    let target = Target::Import(program.imports.len());
+                    program.define_relative(name, target, 0)?;
+                    program.imports.push(name.name);
+                } else {
+                    program.exports.push(name);
+                }
 
-    //
-    Ok(vec![])
+                match take_one(args) {
+                    None => return Ok(()),
+                    Some(comma) if comma.kind.is_comma() => continue,
+                    Some(token) => return Err(unexpected_token(token, "symbol list")),
+                }
+            }
+        }
+        ".enc" => {
+            let name = match args {
+                [name] if name.kind.is_identifier() => name.source.value(),
+                [] => return Err(unexpected_token(directive, "'.enc', expected a name")),
+                [_, token, ..] | [token] => return Err(unexpected_token(token, "'.enc'")),
+            };
+            *args = &[];
+            program.encodings.entry(name).or_default();
+            program.encoding = name;
+            Ok(())
+        }
+        ".charmap" => parse_charmap(directive, args, program),
+        ".zppool" => parse_zero_page_pool(directive, args, program),
+        ".zpvar" => parse_zero_page_variable(directive, args, program),
+        ".db" | ".bytes" | ".ascii" | ".dw" | ".word" => {
+            if args.is_empty() {
+                let reason = format!("'{}' requires at least one value", directive.source.value());
+                return Err(syntax_error(&directive.source, reason));
+            }
+            let words = matches!(directive.source.value(), ".dw" | ".word");
+            loop {
+                let item = take_while(args, |t| !t.kind.is_comma());
+                match item {
+                    [] => {
+                        let token = args.first().unwrap_or(directive);
+                        let context = if words { "word list" } else { "byte list" };
+                        return Err(unexpected_token(token, context));
+                    }
+                    [token @ Token {
+                        kind: TokenKind::Literal(LitKind::String(value)),
+                        ..
+                    }] if !words => {
+                        let bytes = program.encode(value);
+                        let size = bytes.len() as u32;
+                        program.emit(token, IRCode::Bytes(token, bytes), size)?;
+                    }
+                    _ if words => program.emit(&item[0], IRCode::Word(item), 2)?,
+                    _ => program.emit(&item[0], IRCode::Expression(item), 1)?,
+                }
+                if take_one(args).is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        ".eq" => {
+            let reason = "'.eq' must follow the symbol it defines".to_string();
+            Err(syntax_error(&directive.source, reason))
+        }
+        name => {
+            let reason = format!("unknown directive '{}'", name);
+            Err(syntax_error(&directive.source, reason))
+        }
+    }
+}
+
+/// Sets the zero page addresses `.zpvar` allocates from, up to but not
+/// including the end.
+/// ```text
+///     .zppool $80, $100
+/// ```
+/// The pool can only be set before anything is allocated from it.
+fn parse_zero_page_pool<'a>(
+    directive: &'a Token<'a>,
+    args: &mut &'a [Token<'a>],
+    program: &mut Program<'a>,
+) -> Result<(), Diagnostic> {
+    if let Some(allocation) = program.zero_page.first() {
+        let reason = format!(
+            "'.zppool' must come before any '.zpvar', '{}' is already allocated",
+            allocation.name
+        );
+        return Err(syntax_error(&directive.source, reason));
+    }
+    let start = take_while(args, |t| !t.kind.is_comma());
+    if start.is_empty() || take_one(args).is_none() || args.is_empty() {
+        let token = args.first().unwrap_or(directive);
+        return Err(unexpected_token(
+            token,
+            "'.zppool', expected a start and an end",
+        ));
+    }
+    let end = std::mem::take(args);
+    program.refer(start);
+    program.refer(end);
+    let (start, end) = (
+        evaluate(directive, start, &program.symbols)?,
+        evaluate(directive, end, &program.symbols)?,
+    );
+    if start >= end || end > 0x100 {
+        let reason = format!(
+            "zero page pool from ${:02x} to ${:x} is empty or not in zero page",
+            start, end
+        );
+        return Err(syntax_error(&directive.source, reason));
+    }
+    program.pool = ZeroPagePool {
+        start,
+        end,
+        next: start,
+        directive: Some(directive),
+    };
+    Ok(())
 }
 
-// fn parse_instruction<'f, 'a>()
+/// Allocates a variable of one or more bytes from the zero page pool and defines
+/// its name as the address.
+/// ```text
+///     .zpvar ptr, 2
+///     .zpvar count
+/// ```
+/// Variables are allocated one after another in the order they are given. When
+/// assembling an object without a `.zppool`, the addresses are left for the
+/// linker to place along with the variables of the other objects.
+fn parse_zero_page_variable<'a>(
+    directive: &'a Token<'a>,
+    args: &mut &'a [Token<'a>],
+    program: &mut Program<'a>,
+) -> Result<(), Diagnostic> {
+    let name = match take_one(args) {
+        Some(name) if name.kind.is_identifier() => name,
+        Some(token) => return Err(unexpected_token(token, "'.zpvar', expected a name")),
+        None => return Err(unexpected_token(directive, "'.zpvar', expected a name")),
+    };
+    let size = match take_one(args) {
+        None => 1,
+        Some(comma) if comma.kind.is_comma() && !args.is_empty() => {
+            let expr = std::mem::take(args);
+            program.refer(expr);
+            let size = evaluate(directive, expr, &program.symbols)?;
+            if size == 0 || size > 0x100 {
+                let reason = format!("zero page variable size {} is not 1 to 256 bytes", size);
+                return Err(syntax_error(&expr[0].source, reason));
+            }
+            size
+        }
+        Some(token) => return Err(unexpected_token(token, "'.zpvar', expected a size")),
+    };
 
-// fn parse_expression<'f, 'a>(tokens: &'f mut &'a [Token<'a>]) -> Result<>
+    let pool = &program.pool;
+    if pool.next + size > pool.end {
+        let bytes = |n: u32| match n {
+            1 => "1 byte".to_string(),
+            n => format!("{} bytes", n),
+        };
+        let reason = format!(
+            "zero page pool is full, '{}' needs {} and {} are left",
+            name.name,
+            bytes(size),
+            pool.end - pool.next
+        );
+        let mut error = syntax_error(&name.source, reason).with_note(format!(
+            "the pool is ${:02x}-${:02x}, set it with '.zppool'",
+            pool.start,
+            pool.end - 1
+        ));
+        if let Some(pool) = pool.directive {
+            error = error.with_label(&pool.source, "pool set here".to_string());
+        }
+        return Err(error);
+    }
+    // without a pool of its own, an object leaves placing its variables to the
+    // linker
+    let relocatable = program.object && pool.directive.is_none();
+    let address = pool.next;
+    if relocatable {
+        program.define_relative(name, Target::ZeroPage, address)?;
+    } else {
+        program.define(name, address)?;
+    }
+    program.pool.next += size;
+    program.zero_page.push(object::Allocation {
+        name: name.name.to_string(),
+        address: address as u8,
+        size: size as u16,
+        relocatable,
+    });
+    Ok(())
+}
 
-// immediate '#<number>'
+/// Maps characters to bytes in the current encoding.
+/// ```text
+///     .charmap 'a', $01               ; one character
+///     .charmap 'a', 'z', $01          ; a range of characters to consecutive bytes
+///     .charmap                        ; forget every mapping
+/// ```
+/// Only strings are translated, character literals in expressions are not.
+fn parse_charmap<'a>(
+    directive: &'a Token<'a>,
+    args: &mut &'a [Token<'a>],
+    program: &mut Program<'a>,
+) -> Result<(), Diagnostic> {
+    let mut values = vec![];
+    while !args.is_empty() {
+        let item = take_while(args, |t| !t.kind.is_comma());
+        if item.is_empty() {
+            let token = args.first().unwrap_or(directive);
+            return Err(unexpected_token(token, "'.charmap'"));
+        }
+        program.refer(item);
+        values.push((item, evaluate(directive, item, &program.symbols)?));
+        if take_one(args).is_some() && args.is_empty() {
+            return Err(unexpected_token(directive, "'.charmap', expected a value"));
+        }
+    }
 
-//
+    let (first, last, (byte_expr, byte)) = match values[..] {
+        [] => {
+            program.encodings.insert(program.encoding, CharMap::new());
+            return Ok(());
+        }
+        [first, byte] => (first, first, byte),
+        [first, last, byte] => (first, last, byte),
+        _ => {
+            let reason = "'.charmap' takes a character, a range or nothing".to_string();
+            return Err(syntax_error(&directive.source, reason));
+        }
+    };
+    let character = |(expr, value): (Expr<'a>, u32)| {
+        char::from_u32(value).ok_or_else(|| {
+            let reason = format!("${:x} is not a character", value);
+            syntax_error(&expr[0].source, reason)
+        })
+    };
+    let (first, last) = (character(first)?, character(last)?);
+    let len = (last as u32).checked_sub(first as u32).map(|len| len + 1);
+    match len {
+        Some(len) if byte + len <= 0x100 => {}
+        Some(_) => {
+            let reason = "the characters are mapped past byte $ff".to_string();
+            return Err(syntax_error(&byte_expr[0].source, reason));
+        }
+        None => {
+            let reason = format!("'{}' comes after '{}'", first, last);
+            return Err(syntax_error(&directive.source, reason));
+        }
+    }
+
+    let map = program.encodings.get_mut(program.encoding).unwrap();
+    for (c, byte) in (first..=last).zip(byte..) {
+        map.insert(c, byte as u8);
+    }
+    Ok(())
+}
+
+fn parse_instruction<'a>(
+    mnemonic: &'a Token<'a>,
+    args: &mut &'a [Token<'a>],
+    program: &mut Program<'a>,
+) -> Result<(), Diagnostic> {
+    let instr = match find_instruction(mnemonic) {
+        Some(instr) => instr,
+        None => {
+            let reason = format!("unknown instruction '{}'", mnemonic.source.value());
+            return Err(syntax_error(&mnemonic.source, reason));
+        }
+    };
+
+    let operand = parse_operand(args)?;
+    *args = &[];
+
+    // relocatable zero page variables are known to stay in zero page
+    let in_zero_page = |expr: Expr<'a>| {
+        program
+            .resolve(mnemonic, expr)
+            .is_ok_and(|(value, target)| {
+                value <= 0xff && matches!(target, None | Some(Target::ZeroPage))
+            })
+    };
+    let (opcode, expr) = match select_opcode(&instr, &operand, in_zero_page) {
+        Some(selected) => selected,
+        None => {
+            let reason = match operand {
+                Operand::Indirect(_) => format!(
+                    "'{}' has no indirect addressing mode on the NMOS 6502",
+                    instr.name
+                ),
+                _ => format!("invalid addressing mode for '{}'", instr.name),
+            };
+            return Err(syntax_error(&mnemonic.source, reason));
+        }
+    };
+    if let Operand::Accumulator(operand) = operand {
+        if opcode.mode == AddressMode::Accumulator {
+            program.accumulators.push(&operand[0]);
+        }
+    }
+    let forced = matches!(operand, Operand::Absolute(..));
+    let code = IRCode::Opcode(mnemonic, opcode, expr, forced);
+    program.emit(mnemonic, code, opcode.bytes as u32)
+}
+
+fn parse_operand<'a>(args: Expr<'a>) -> Result<Operand<'a>, Diagnostic> {
+    if args.is_empty() {
+        return Ok(Operand::None);
+    }
+    if let [token] = args {
+        if token.kind.is_identifier() && token.source.value().eq_ignore_ascii_case("a") {
+            return Ok(Operand::Accumulator(args));
+        }
+    }
+    if let [prefix, colon, rest @ ..] = args {
+        if prefix.kind.is_identifier()
+            && prefix.source.value().eq_ignore_ascii_case("a")
+            && colon.kind.is_colon()
+        {
+            return match parse_operand(rest)? {
+                Operand::Direct(expr, index) => Ok(Operand::Absolute(expr, index)),
+                Operand::Accumulator(expr) | Operand::Indirect(expr) => {
+                    Ok(Operand::Absolute(expr, None))
+                }
+                _ => {
+                    let reason = "'a:' must be followed by an address".to_string();
+                    Err(syntax_error(&colon.source, reason))
+                }
+            };
+        }
+    }
+    if args[0].kind.is_hash() {
+        if args.len() == 1 {
+            return Err(unexpected_token(&args[0], "operand"));
+        }
+        return Ok(Operand::Immediate(&args[1..]));
+    }
+
+    if let Some((base, index)) = split_index(args)? {
+        return match (unwrap_parens(base), index) {
+            (Some(inner), Index::Y) => Ok(Operand::IndirectY(inner)),
+            _ => Ok(Operand::Direct(base, Some(index))),
+        };
+    }
+    if let Some(inner) = unwrap_parens(args) {
+        if let Some((base, index)) = split_index(inner)? {
+            return match index {
+                Index::X => Ok(Operand::IndirectX(base)),
+                Index::Y => Err(unexpected_token(&inner[base.len() + 1], "operand")),
+            };
+        }
+        return Ok(Operand::Indirect(args));
+    }
+    Ok(Operand::Direct(args, None))
+}
+
+/// Splits an operand at a trailing `,x` or `,y` index.
+fn split_index<'a>(args: Expr<'a>) -> Result<Option<(Expr<'a>, Index)>, Diagnostic> {
+    let mut depth = 0;
+    let mut comma = None;
+    for (i, token) in args.iter().enumerate() {
+        match token.kind {
+            TokenKind::LParen => depth += 1,
+            TokenKind::RParen => depth -= 1,
+            TokenKind::Comma if depth == 0 => comma = Some(i),
+            _ => {}
+        }
+    }
+
+    let i = match comma {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    if i == 0 {
+        return Err(unexpected_token(&args[0], "operand"));
+    }
+    let index = match &args[i + 1..] {
+        [token] if token.source.value().eq_ignore_ascii_case("x") => Index::X,
+        [token] if token.source.value().eq_ignore_ascii_case("y") => Index::Y,
+        [] => return Err(unexpected_token(&args[i], "operand")),
+        [token, ..] => return Err(unexpected_token(token, "operand")),
+    };
+    Ok(Some((&args[..i], index)))
+}
+
+/// Returns the inside of an expression if it is entirely wrapped in parentheses.
+fn unwrap_parens<'a>(expr: Expr<'a>) -> Option<Expr<'a>> {
+    if expr.len() < 2 || !expr[0].kind.is_lparen() {
+        return None;
+    }
+
+    let mut depth = 0;
+    for (i, token) in expr.iter().enumerate() {
+        match token.kind {
+            TokenKind::LParen => depth += 1,
+            TokenKind::RParen => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return if i == expr.len() - 1 {
+                Some(&expr[1..i])
+            } else {
+                None
+            };
+        }
+    }
+    None
+}
+
+fn find_instruction(token: &Token) -> Option<Instruction> {
+    Instruction::find_by_name(&token.source.value().to_ascii_lowercase())
+}
+
+/// Picks the opcode for an operand and returns it with the expression it encodes.
+/// Addresses are assembled to zero page addressing if `in_zero_page` knows them
+/// to be in zero page.
+fn select_opcode<'a>(
+    instr: &Instruction,
+    operand: &Operand<'a>,
+    in_zero_page: impl Fn(Expr<'a>) -> bool,
+) -> Option<(Opcode, Option<Expr<'a>>)> {
+    let find = |mode: AddressMode| instr.opcodes.iter().copied().find(|op| op.mode == mode);
+    let address = |expr: Expr<'a>, zero_page: AddressMode, absolute: AddressMode| {
+        let opcode = if in_zero_page(expr) {
+            find(zero_page).or_else(|| find(absolute))
+        } else {
+            find(absolute).or_else(|| find(zero_page))
+        };
+        opcode.map(|op| (op, Some(expr)))
+    };
+
+    match *operand {
+        Operand::None => find(AddressMode::Implied)
+            .or_else(|| find(AddressMode::Accumulator))
+            .map(|op| (op, None)),
+        Operand::Accumulator(expr) => match find(AddressMode::Accumulator) {
+            Some(op) => Some((op, None)),
+            None => address(expr, AddressMode::ZeroPage, AddressMode::Absolute),
+        },
+        Operand::Immediate(expr) => find(AddressMode::Immediate).map(|op| (op, Some(expr))),
+        Operand::Direct(expr, None) => match find(AddressMode::Relative) {
+            Some(op) => Some((op, Some(expr))),
+            None => address(expr, AddressMode::ZeroPage, AddressMode::Absolute),
+        },
+        Operand::Direct(expr, Some(Index::X)) => {
+            address(expr, AddressMode::ZeroPageX, AddressMode::AbsoluteX)
+        }
+        Operand::Direct(expr, Some(Index::Y)) => {
+            address(expr, AddressMode::ZeroPageY, AddressMode::AbsoluteY)
+        }
+        Operand::Absolute(expr, None) => find(AddressMode::Absolute).map(|op| (op, Some(expr))),
+        Operand::Absolute(expr, Some(Index::X)) => {
+            find(AddressMode::AbsoluteX).map(|op| (op, Some(expr)))
+        }
+        Operand::Absolute(expr, Some(Index::Y)) => {
+            find(AddressMode::AbsoluteY).map(|op| (op, Some(expr)))
+        }
+        Operand::Indirect(expr) => find(AddressMode::Indirect).map(|op| (op, unwrap_parens(expr))),
+        Operand::IndirectX(expr) => find(AddressMode::IndirectX).map(|op| (op, Some(expr))),
+        Operand::IndirectY(expr) => find(AddressMode::IndirectY).map(|op| (op, Some(expr))),
+    }
+}
+
+/// Encodes an instruction at `address` in the segment at index `segment`, and
+/// returns the target of its operand if the linker has to relocate it.
+fn encode_instruction<'a>(
+    mnemonic: &'a Token<'a>,
+    opcode: &Opcode,
+    expr: Option<Expr<'a>>,
+    address: u32,
+    segment: usize,
+    program: &Program<'a>,
+) -> Result<(Vec<u8>, Option<Target>), Diagnostic> {
+    let mut bytes = vec![opcode.value];
+    let expr = match expr {
+        Some(expr) => expr,
+        None => return Ok((bytes, None)),
+    };
+
+    if opcode.mode == AddressMode::Relative {
+        let (target, relative_to) = program.resolve(mnemonic, expr)?;
+        let absolute = program.segments[segment].absolute;
+        let same_segment = match relative_to {
+            None => absolute,
+            Some(Target::Segment(index)) => !absolute && index == segment,
+            Some(Target::Import(_) | Target::ZeroPage) => false,
+        };
+        if !same_segment {
+            let reason = "branch target is not in the same segment".to_string();
+            return Err(syntax_error(&expr[0].source, reason));
+        }
+        let offset = target as i64 - (address + opcode.bytes as u32) as i64;
+        if !(-128..=127).contains(&offset) {
+            let reason = format!("branch target ${:04x} is out of range", target);
+            return Err(syntax_error(&expr[0].source, reason));
+        }
+        bytes.push(offset as u8);
+        Ok((bytes, None))
+    } else if opcode.bytes == 2 {
+        let (value, relocation) = program.resolve_byte(mnemonic, expr)?;
+        bytes.push(value);
+        Ok((bytes, relocation))
+    } else {
+        let (value, relocation) = program.resolve_word(mnemonic, expr)?;
+        bytes.extend(value.to_le_bytes());
+        Ok((bytes, relocation))
+    }
+}
+
+/// Warns about an encoded instruction which does not do what it seems to.
+fn lint_instruction(mnemonic: &Token, opcode: &Opcode, bytes: &[u8], warnings: &mut Warnings) {
+    let zero_page = match opcode.mode {
+        AddressMode::Relative if bytes[1] == 0 => {
+            let reason = "branch to the next instruction has no effect".to_string();
+            warnings.warn(Lint::BranchToNext, warning(&mnemonic.source, reason));
+            return;
+        }
+        AddressMode::Absolute => AddressMode::ZeroPage,
+        AddressMode::AbsoluteX => AddressMode::ZeroPageX,
+        AddressMode::AbsoluteY => AddressMode::ZeroPageY,
+        _ => return,
+    };
+    let has_zero_page = find_instruction(mnemonic)
+        .is_some_and(|instr| instr.opcodes.iter().any(|op| op.mode == zero_page));
+    if bytes[2] == 0 && has_zero_page {
+        let reason = format!(
+            "absolute address ${:04x} fits in zero page",
+            u16::from_le_bytes([bytes[1], bytes[2]])
+        );
+        let diagnostic = warning(&mnemonic.source, reason)
+            .with_note(
+                "addresses used before they are defined are assembled as absolute".to_string(),
+            )
+            .with_note("prefix the address with 'a:' if it is meant to be absolute".to_string());
+        warnings.warn(Lint::AbsoluteZeroPage, diagnostic);
+    }
+}
+
+/// Warns about labels which are never used.
+fn lint_labels(program: &Program, warnings: &mut Warnings) {
+    for label in program.labels.iter() {
+        let name = label.name;
+        let exported = program.exports.iter().any(|t| t.name == name);
+        if !exported && !program.referenced.contains(name) {
+            let reason = format!("label '{}' is never used", name);
+            warnings.warn(Lint::UnusedLabel, warning(&label.source, reason));
+        }
+    }
+}
+
+/// Warns about a lone `a` taken to be the accumulator when a symbol is also
+/// named `a`.
+fn lint_accumulators(program: &Program, warnings: &mut Warnings) {
+    for token in program.accumulators.iter() {
+        let definition = match program.definitions.get(token.name) {
+            Some(definition) => definition,
+            None => continue,
+        };
+        let reason = format!(
+            "'{}' is the accumulator here, not the symbol '{}'",
+            token.name, token.name
+        );
+        let diagnostic = warning(&token.source, reason)
+            .with_label(&definition.source, "symbol defined here".to_string())
+            .with_note(format!(
+                "write '{}+0' to use the symbol as an address",
+                token.name
+            ));
+        warnings.warn(Lint::AccumulatorLabel, diagnostic);
+    }
+}
+
+/// Warns about zero page variables at addresses a segment with an address loads
+/// bytes into, which the program would overwrite its variables with.
+fn lint_zero_page(program: &Program, object: &Object, warnings: &mut Warnings) {
+    for allocation in object.zero_page.iter().filter(|a| !a.relocatable) {
+        let start = u32::from(allocation.address);
+        let end = start + u32::from(allocation.size);
+        let segment = object.segments.iter().find(|segment| {
+            segment.address.is_some_and(|address| {
+                let address = u32::from(address);
+                address < end && start < address + segment.data.len() as u32
+            })
+        });
+        let (segment, token) = match (segment, program.definitions.get(&*allocation.name)) {
+            (Some(segment), Some(token)) => (segment, token),
+            _ => continue,
+        };
+        let reason = format!(
+            "zero page variable '{}' at ${:02x}-${:02x} overlaps the bytes of segment '{}'",
+            allocation.name,
+            start,
+            end - 1,
+            segment.name
+        );
+        let diagnostic = warning(&token.source, reason)
+            .with_note("set the addresses variables are allocated from with '.zppool'".to_string());
+        warnings.warn(Lint::ZeroPageOverlap, diagnostic);
+    }
+}
+
+/// Evaluates an expression which may not refer to any symbols, such as the
+/// condition of a `%if`.
+pub fn evaluate_constant<'a>(context: &'a Token<'a>, expr: Expr<'a>) -> Result<u32, Diagnostic> {
+    evaluate(context, expr, &SymbolTable::new())
+}
+
+/// Evaluates an expression against the symbols defined so far.
+///
+/// Errors about a missing expression are reported at `context`.
+fn evaluate<'a>(
+    context: &'a Token<'a>,
+    expr: Expr<'a>,
+    symbols: &SymbolTable,
+) -> Result<u32, Diagnostic> {
+    let mut tokens = expr;
+    let value = evaluate_binary(context, &mut tokens, 0, symbols, false)?;
+    if let Some(token) = tokens.first() {
+        return Err(unexpected_token(token, "expression"));
+    }
+    Ok(value)
+}
+
+/// Evaluates operators of at least `min_precedence` by precedence climbing.
+///
+/// The right side of `&&` and `||` is `skipped` when the left side decides the
+/// result, as in `%defined(SIZE) && SIZE > 4`, so undefined symbols in it are
+/// zero and dividing by zero is not an error.
+fn evaluate_binary<'a>(
+    context: &'a Token<'a>,
+    tokens: &mut Expr<'a>,
+    min_precedence: u8,
+    symbols: &SymbolTable,
+    skipped: bool,
+) -> Result<u32, Diagnostic> {
+    let mut lhs = evaluate_term(context, tokens, symbols, skipped)?;
+    while let Some(token) = tokens.first() {
+        let op = match token.kind {
+            TokenKind::Operator(op) => op,
+            _ => break,
+        };
+        let precedence = match binary_precedence(op) {
+            Some(precedence) if precedence >= min_precedence => precedence,
+            _ => break,
+        };
+
+        take_one(tokens);
+        let skip_rhs = match op {
+            OpKind::LogicalAnd => skipped || lhs == 0,
+            OpKind::LogicalOr => skipped || lhs != 0,
+            _ => skipped,
+        };
+        let rhs = evaluate_binary(token, tokens, precedence + 1, symbols, skip_rhs)?;
+        lhs = match op {
+            OpKind::Add => lhs.wrapping_add(rhs),
+            OpKind::Sub => lhs.wrapping_sub(rhs),
+            OpKind::Mul => lhs.wrapping_mul(rhs),
+            OpKind::Div | OpKind::Mod if rhs == 0 && skipped => 0,
+            OpKind::Div | OpKind::Mod if rhs == 0 => {
+                let reason = "division by zero".to_string();
+                return Err(syntax_error(&token.source, reason));
+            }
+            OpKind::Div => lhs / rhs,
+            OpKind::Mod => lhs % rhs,
+            OpKind::And => lhs & rhs,
+            OpKind::Or => lhs | rhs,
+            OpKind::Xor => lhs ^ rhs,
+            OpKind::Shl => lhs.checked_shl(rhs).unwrap_or(0),
+            OpKind::Shr => lhs.checked_shr(rhs).unwrap_or(0),
+            OpKind::Eq => (lhs == rhs) as u32,
+            OpKind::Ne => (lhs != rhs) as u32,
+            OpKind::Lt => (lhs < rhs) as u32,
+            OpKind::Le => (lhs <= rhs) as u32,
+            OpKind::Gt => (lhs > rhs) as u32,
+            OpKind::Ge => (lhs >= rhs) as u32,
+            OpKind::LogicalAnd => (lhs != 0 && rhs != 0) as u32,
+            OpKind::LogicalOr => (lhs != 0 || rhs != 0) as u32,
+            OpKind::Not | OpKind::LogicalNot => unreachable!(),
+        };
+    }
+    Ok(lhs)
+}
+
+/// Evaluates a single value, a parenthesized expression or a unary operator.
+fn evaluate_term<'a>(
+    context: &'a Token<'a>,
+    tokens: &mut Expr<'a>,
+    symbols: &SymbolTable,
+    skipped: bool,
+) -> Result<u32, Diagnostic> {
+    let token = match take_one(tokens) {
+        Some(token) => token,
+        None => {
+            let reason = "expected an expression".to_string();
+            return Err(syntax_error(&context.source, reason));
+        }
+    };
+
+    match &token.kind {
+        TokenKind::LParen => {
+            let value = evaluate_binary(token, tokens, 0, symbols, skipped)?;
+            if take_if(tokens, |t| t.kind.is_rparen()).is_none() {
+                return Err(expected_delimiter(")", token, "expression"));
+            }
+            Ok(value)
+        }
+        TokenKind::Operator(
+            op @ (OpKind::Add
+            | OpKind::Sub
+            | OpKind::Not
+            | OpKind::LogicalNot
+            | OpKind::Lt
+            | OpKind::Gt),
+        ) => {
+            let value = evaluate_term(token, tokens, symbols, skipped)?;
+            Ok(match op {
+                OpKind::Sub => value.wrapping_neg(),
+                OpKind::Not => !value,
+                OpKind::LogicalNot => (value == 0) as u32,
+                // the low and high bytes of an address
+                OpKind::Lt => value & 0xff,
+                OpKind::Gt => (value >> 8) & 0xff,
+                _ => value,
+            })
+        }
+        TokenKind::Literal(LitKind::Number(value)) => Ok(*value),
+        TokenKind::Literal(LitKind::Char(value)) => Ok(*value as u32),
+        TokenKind::Identifier => {
+            let name = token.name;
+            match symbols.get(name) {
+                Some(value) => Ok(*value),
+                None if skipped => Ok(0),
+                None => {
+                    let reason = format!("undefined symbol '{}'", name);
+                    Err(syntax_error(&token.source, reason))
+                }
+            }
+        }
+        _ => Err(unexpected_token(token, "expression")),
+    }
+}
+
+/// Returns the binding strength of a binary operator, which follows C.
+fn binary_precedence(op: OpKind) -> Option<u8> {
+    match op {
+        OpKind::LogicalOr => Some(1),
+        OpKind::LogicalAnd => Some(2),
+        OpKind::Or => Some(3),
+        OpKind::Xor => Some(4),
+        OpKind::And => Some(5),
+        OpKind::Eq | OpKind::Ne => Some(6),
+        OpKind::Lt | OpKind::Le | OpKind::Gt | OpKind::Ge => Some(7),
+        OpKind::Shl | OpKind::Shr => Some(8),
+        OpKind::Add | OpKind::Sub => Some(9),
+        OpKind::Mul | OpKind::Div | OpKind::Mod => Some(10),
+        OpKind::Not | OpKind::LogicalNot => None,
+    }
+}
@@ -2,74 +2,336 @@ use std::error::Error;
 
 use ansi_term::Color;
 
-use crate::source::Loc;
+use crate::source::{LineColumn, SourceRef};
 use crate::token::TokenLike;
 
-pub struct SyntaxError {
-    loc_reason: String,
-    context: String,
-    marker: String,
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The source cannot be assembled.
+    Error,
+    /// The source assembles, but probably not to what was meant.
+    Warning,
+    /// Something worth knowing about the source.
+    Note,
 }
 
-impl SyntaxError {
-    pub fn new(location: Loc, reason: String) -> Self {
-        let file = &location.file;
-        let line = location.loc.line;
-        let col = location.loc.column;
-        let line_no_str = line.to_string();
-        let line_str = file.get_source_line(line).unwrap();
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
 
-        let offset = &line_str[..col - 1]
+    fn color(&self) -> Color {
+        match self {
+            Severity::Error => Color::Red,
+            Severity::Warning => Color::Yellow,
+            Severity::Note => Color::Cyan,
+        }
+    }
+}
+
+/// How diagnostics are written for the user, chosen with `--error-format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Messages with an excerpt of the source, for people.
+    Text,
+    /// One JSON object per line, for editors and CI.
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("unknown error format '{}'", name)),
+        }
+    }
+}
+
+/// A span of source shown with a [`Diagnostic`].
+struct Label {
+    file: String,
+    begin: LineColumn,
+    end: LineColumn,
+    /// The source line the span begins on.
+    line: String,
+    /// Whether this is where the diagnostic is, rather than somewhere related.
+    primary: bool,
+    message: Option<String>,
+}
+
+impl Label {
+    fn new(source: &SourceRef, primary: bool, message: Option<String>) -> Self {
+        let loc = source.span_loc();
+        let line = loc.file.get_source_line(loc.begin.line).unwrap();
+        Self {
+            file: loc.file.name().to_string(),
+            begin: loc.begin,
+            end: loc.end,
+            line: line.to_string(),
+            primary,
+            message,
+        }
+    }
+
+    /// Returns the source line and a marker under the span, which stops at the
+    /// end of the line.
+    fn excerpt(&self) -> (String, String) {
+        let line_no_str = self.begin.line.to_string();
+        let offset = &self.line[..self.begin.column - 1]
             .chars()
             .map(|c| match c {
                 c if c.is_ascii_graphic() => ' ',
                 _ => c,
             })
             .collect::<String>();
+        let width = if self.end.line == self.begin.line {
+            self.end.column.saturating_sub(self.begin.column)
+        } else {
+            self.line.len() + 1 - self.begin.column
+        };
 
-        let loc_reason = format!("{}: {}", location, reason);
-        let context = format!("{} | {}", line_no_str, Color::White.bold().paint(line_str));
+        let context = format!(
+            "{} | {}",
+            line_no_str,
+            Color::White.bold().paint(&self.line)
+        );
         let marker = format!(
             "{}{}{}",
             " ".repeat(line_no_str.len() + 3),
             offset,
-            Color::Blue.paint("^")
+            Color::Blue.paint("^".repeat(width.max(1)))
         );
+        (context, marker)
+    }
+
+    fn to_json(&self) -> String {
+        let message = match &self.message {
+            Some(message) => json_string(message),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"file\":{},\"line_start\":{},\"column_start\":{},\"line_end\":{},\"column_end\":{},\"primary\":{},\"label\":{}}}",
+            json_string(&self.file),
+            self.begin.line,
+            self.begin.column,
+            self.end.line,
+            self.end.column,
+            self.primary,
+            message
+        )
+    }
+}
 
+/// A problem found in the source, with the spans it is about.
+///
+/// Most diagnostics have a primary span, where the problem is, and may point at
+/// related spans such as an earlier definition or the macro calls the problem
+/// was expanded from. Problems outside the source, like a file which cannot be
+/// read, have no spans at all.
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    /// The name of the lint which reported the diagnostic, if one did.
+    code: Option<&'static str>,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: String) -> Self {
         Self {
-            loc_reason,
-            context,
-            marker,
+            severity,
+            message,
+            code: None,
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn error(message: String) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: String) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Tags the diagnostic with the lint which reported it.
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Sets where the problem is.
+    pub fn with_primary(mut self, source: &SourceRef) -> Self {
+        self.labels.retain(|label| !label.primary);
+        self.labels.insert(0, Label::new(source, true, None));
+        self
+    }
+
+    /// Points at a related span, such as where a name was first defined.
+    pub fn with_label(mut self, source: &SourceRef, message: String) -> Self {
+        self.labels.push(Label::new(source, false, Some(message)));
+        self
+    }
+
+    /// Adds a note shown after the spans, such as how to fix the problem.
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    /// Adds a label for each macro call or `%include` that `source` was expanded
+    /// from.
+    pub fn expanded_from(mut self, source: &SourceRef) -> Self {
+        let mut origin = source.origin;
+        while let Some(call) = origin {
+            let label = match call.value() {
+                "%include" => "included from here",
+                _ => "expanded from here",
+            };
+            self = self.with_label(call, label.to_string());
+            origin = call.origin;
         }
+        self
+    }
+
+    /// Formats the diagnostic as a single line of JSON.
+    /// ```text
+    ///     {"severity":"error","code":null,"message":"...","spans":[{"file":
+    ///      "main.s","line_start":3,"column_start":5,"line_end":3,"column_end":8,
+    ///      "primary":true,"label":null}],"notes":[]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let spans = self.labels.iter().map(Label::to_json).collect::<Vec<_>>();
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| json_string(note))
+            .collect::<Vec<_>>();
+        let code = match self.code {
+            Some(code) => json_string(code),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"severity\":\"{}\",\"code\":{},\"message\":{},\"spans\":[{}],\"notes\":[{}]}}",
+            self.severity.name(),
+            code,
+            json_string(&self.message),
+            spans.join(","),
+            notes.join(",")
+        )
+    }
+
+    /// Returns the diagnostic as it is shown in `format`.
+    pub fn render(&self, format: ErrorFormat) -> String {
+        match format {
+            ErrorFormat::Text => self.to_string(),
+            ErrorFormat::Json => self.to_json(),
+        }
+    }
+}
+
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Self::error(message)
     }
 }
 
-impl std::fmt::Display for SyntaxError {
+impl std::fmt::Display for Diagnostic {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\n{}\n{}", self.loc_reason, self.context, self.marker)
+        let color = self.severity.color();
+        write!(f, "{}: ", color.bold().paint(self.severity.name()))?;
+        match self.labels.iter().find(|label| label.primary) {
+            Some(label) => write!(f, "{}: {}: {}", label.file, label.begin, self.message)?,
+            None => write!(f, "{}", self.message)?,
+        }
+        match (self.code, self.severity) {
+            (Some(code), Severity::Error) => write!(f, " [-Werror={}]", code)?,
+            (Some(code), _) => write!(f, " [-W{}]", code)?,
+            (None, _) => {}
+        }
+
+        for label in self.labels.iter() {
+            let (context, marker) = label.excerpt();
+            if let Some(message) = &label.message {
+                write!(f, "\n{}: {}: {}", label.file, label.begin, message)?;
+            }
+            write!(f, "\n{}\n{}", context, marker)?;
+        }
+        for note in self.notes.iter() {
+            write!(
+                f,
+                "\n{}: {}",
+                Severity::Note.color().bold().paint("note"),
+                note
+            )?;
+        }
+        Ok(())
     }
 }
 
-impl std::fmt::Debug for SyntaxError {
+impl std::fmt::Debug for Diagnostic {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\n{}\n{}", self.loc_reason, self.context, self.marker)
+        std::fmt::Display::fmt(self, f)
     }
 }
 
-impl Error for SyntaxError {}
+impl Error for Diagnostic {}
 
 //
 
-pub fn syntax_error<'a>(loc: Loc<'a>, reason: String) -> SyntaxError {
-    SyntaxError::new(loc, reason)
+/// Returns `string` as a quoted JSON string.
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Returns an error at `source`, along with the macro calls it was expanded
+/// from.
+pub fn syntax_error(source: &SourceRef, reason: String) -> Diagnostic {
+    Diagnostic::error(reason)
+        .with_primary(source)
+        .expanded_from(source)
+}
+
+/// Returns a warning at `source`, along with the macro calls it was expanded
+/// from.
+pub fn warning(source: &SourceRef, reason: String) -> Diagnostic {
+    Diagnostic::warning(reason)
+        .with_primary(source)
+        .expanded_from(source)
 }
 
-pub fn unexpected_token<'a, T>(token: &'a T, context: &str) -> SyntaxError
+pub fn unexpected_token<'a, T>(token: &'a T, context: &str) -> Diagnostic
 where
     T: TokenLike<'a>,
 {
-    let loc = token.source().start_loc();
-    let reason = if context.len() > 0 {
+    let reason = if !context.is_empty() {
         format!(
             "unexpected token '{}' in {}",
             token.source().value(),
@@ -79,15 +341,14 @@ where
         format!("unexpected token '{}'", token.source().value())
     };
 
-    SyntaxError::new(loc, reason)
+    syntax_error(token.source(), reason)
 }
 
-pub fn expected_delimiter<'a, 'b, T>(closing: &str, opening: &'a T, context: &str) -> SyntaxError
+pub fn expected_delimiter<'a, 'b, T>(closing: &str, opening: &'a T, context: &str) -> Diagnostic
 where
     T: TokenLike<'a>,
 {
-    let loc = opening.source().start_loc();
-    let reason = if context.len() > 0 {
+    let reason = if !context.is_empty() {
         format!(
             "expected '{}' to end opening '{}' in {}",
             closing,
@@ -102,7 +363,7 @@ where
         )
     };
 
-    SyntaxError::new(loc, reason)
+    syntax_error(opening.source(), reason)
 }
 
 // macro_rules! unexpected_token {
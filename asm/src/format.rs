@@ -0,0 +1,130 @@
+use crate::instruction::Instruction;
+use crate::source::File;
+use crate::token::RawToken;
+
+/// The column instructions, directives and the comments between them start at.
+const INSTRUCTION_COLUMN: usize = 8;
+
+/// The column comments after code start at.
+const COMMENT_COLUMN: usize = 32;
+
+/// Reprints assembly source with labels, instructions and comments aligned in
+/// columns.
+/// ```text
+///     loop:   lda table,x             ; comment
+///             bne loop
+///     %define COUNT 4
+/// ```
+/// Labels and preprocessor directives start lines, everything else is indented.
+/// Tokens are kept as they are written, but any run of whitespace between them
+/// becomes a single space, so macros mean the same once formatted. Lines which
+/// are continued with a backslash are joined. Formatting formatted source does
+/// not change it.
+pub fn format_source(file: &File) -> String {
+    let tokens = file.lex_tokens();
+    let mut lines = tokens
+        .split(|t| t.is_newline())
+        .map(format_line)
+        .collect::<Vec<_>>();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    let mut output = String::new();
+    for line in lines {
+        output += &line;
+        output += "\n";
+    }
+    output
+}
+
+/// Formats the tokens of a single line.
+fn format_line(line: &[RawToken]) -> String {
+    let indented = line.first().is_some_and(|t| t.is_whitespace());
+    let (code, comment) = match line.split_last() {
+        Some((last, code)) if last.is_comment() => (code, Some(last.source.value())),
+        _ => (line, None),
+    };
+    let code = trim(code);
+
+    // the index of each token which is not whitespace
+    let significant = code
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !t.is_whitespace())
+        .collect::<Vec<_>>();
+    let is_instruction = |t: &RawToken| {
+        t.is_identifier()
+            && Instruction::find_by_name(&t.source.value().to_ascii_lowercase()).is_some()
+    };
+    // the label, and the index of the code after it
+    let label = match significant[..] {
+        [(_, name), (colon, t), ..]
+            if (name.is_identifier() || name.is_directive()) && t.is_colon() =>
+        {
+            Some((format!("{}:", name.source.value()), colon + 1))
+        }
+        // without a colon only a name which starts the line is a label
+        [(_, name), ref rest @ ..]
+            if name.is_identifier() && !indented && !is_instruction(name) =>
+        {
+            match rest.first() {
+                Some((_, next)) if !next.is_directive() && !is_instruction(next) => None,
+                _ => Some((name.source.value().to_string(), 1)),
+            }
+        }
+        _ => None,
+    };
+
+    let mut text = match label {
+        Some((label, end)) => {
+            let rest = join(trim(&code[end..]));
+            if rest.is_empty() {
+                label
+            } else {
+                format!("{} {}", pad(label, INSTRUCTION_COLUMN - 1), rest)
+            }
+        }
+        None if code.first().is_some_and(|t| t.is_preprocessor()) => join(code),
+        None if code.is_empty() && !indented => String::new(),
+        None => format!("{}{}", " ".repeat(INSTRUCTION_COLUMN), join(code)),
+    };
+
+    if let Some(comment) = comment {
+        if text.trim().is_empty() {
+            text += comment;
+        } else {
+            text = format!("{} {}", pad(text, COMMENT_COLUMN - 1), comment);
+        }
+    }
+    text.trim_end().to_string()
+}
+
+/// Returns `tokens` without leading or trailing whitespace.
+fn trim<'b, 'a>(tokens: &'b [RawToken<'a>]) -> &'b [RawToken<'a>] {
+    let start = tokens.iter().position(|t| !t.is_whitespace());
+    let end = tokens.iter().rposition(|t| !t.is_whitespace());
+    match (start, end) {
+        (Some(start), Some(end)) => &tokens[start..end + 1],
+        _ => &[],
+    }
+}
+
+/// Joins tokens as they are written, with each run of whitespace as one space.
+fn join(tokens: &[RawToken]) -> String {
+    tokens
+        .iter()
+        .map(|t| {
+            if t.is_whitespace() {
+                " "
+            } else {
+                t.source.value()
+            }
+        })
+        .collect()
+}
+
+/// Pads `text` with spaces to `width` characters.
+fn pad(text: String, width: usize) -> String {
+    format!("{:<width$}", text, width = width)
+}
@@ -0,0 +1,315 @@
+//! Combines object files into a single image.
+//!
+//! Relocatable segments are placed by a memory map which gives each segment name
+//! the region it is loaded into:
+//! ```text
+//!     ; segment  start   end
+//!     code       $1000   $2000
+//!     data       $0200   $0400
+//!     entry      start
+//! ```
+//! Segments of the same name are placed one after another in the order the
+//! objects are given. The end of a region is optional. `entry` names the symbol
+//! or address execution starts from, which otherwise is the lowest address in
+//! the image.
+//!
+//! Zero page variables from a pool set with `.zppool` keep the addresses the
+//! assembler gave them, and the linker only checks that no two objects were
+//! given the same bytes. The variables of an object without a pool are placed
+//! together at the lowest free addresses, after those with a pool and any
+//! segment loaded into zero page.
+
+use std::collections::HashMap;
+
+use system::Image;
+
+use crate::object::{parse_address, Object, Target};
+
+/// A region of memory that segments of one name are placed in.
+struct Region {
+    name: String,
+    start: u16,
+    end: Option<u16>,
+}
+
+#[derive(Default)]
+pub struct MemoryMap {
+    regions: Vec<Region>,
+    entry: Option<String>,
+}
+
+impl MemoryMap {
+    /// Reads a memory map.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut map = MemoryMap::default();
+        for (index, line) in text.lines().enumerate() {
+            let fields = line
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            let malformed = || format!("malformed line {}", index + 1);
+
+            match fields[..] {
+                [] => {}
+                ["entry", entry] => map.entry = Some(entry.to_string()),
+                [name, start] | [name, start, _] => {
+                    let start = parse_address(start).ok_or_else(malformed)?;
+                    let end = match fields.get(2) {
+                        Some(end) => Some(parse_address(end).ok_or_else(malformed)?),
+                        None => None,
+                    };
+                    map.regions.push(Region {
+                        name: name.to_string(),
+                        start,
+                        end,
+                    });
+                }
+                _ => return Err(malformed()),
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// The output of the linker.
+pub struct Linked {
+    pub image: Image,
+    /// Every exported symbol, all of which are public.
+    pub symbols: cpu::SymbolTable,
+}
+
+/// Links `objects`, each given with the name of the file it came from.
+pub fn link(objects: &[(String, Object)], map: &MemoryMap) -> Result<Linked, String> {
+    // place every segment
+    let mut cursors = map
+        .regions
+        .iter()
+        .map(|region| u32::from(region.start))
+        .collect::<Vec<_>>();
+    let mut bases = Vec::<Vec<u16>>::new();
+    for (file, object) in objects.iter() {
+        let mut object_bases = vec![];
+        for segment in object.segments.iter() {
+            let base = match segment.address {
+                Some(address) => address,
+                None => match map.regions.iter().position(|r| r.name == segment.name) {
+                    Some(index) => {
+                        let base = cursors[index];
+                        let end = map.regions[index].end.map_or(0x10000, u32::from);
+                        cursors[index] += segment.data.len() as u32;
+                        if cursors[index] > end {
+                            return Err(format!(
+                                "{}: segment '{}' does not fit below ${:04x}",
+                                file, segment.name, end
+                            ));
+                        }
+                        base as u16
+                    }
+                    None if segment.data.is_empty() => 0,
+                    None => {
+                        return Err(format!(
+                            "{}: segment '{}' is not in the memory map",
+                            file, segment.name
+                        ));
+                    }
+                },
+            };
+            if u32::from(base) + segment.data.len() as u32 > 0x10000 {
+                return Err(format!(
+                    "{}: segment '{}' runs past the end of memory",
+                    file, segment.name
+                ));
+            }
+            object_bases.push(base);
+        }
+        bases.push(object_bases);
+    }
+    let zero_page = place_zero_page(objects, &bases)?;
+
+    // collect the exported symbols
+    let mut globals = HashMap::<&str, (u16, &str)>::new();
+    for (((file, object), bases), zero_page) in objects.iter().zip(bases.iter()).zip(&zero_page) {
+        for export in object.exports.iter() {
+            let base = match export.target {
+                Some(Target::Segment(index)) => bases[index],
+                Some(Target::ZeroPage) => u16::from(*zero_page),
+                Some(Target::Import(_)) | None => 0,
+            };
+            let value = base.wrapping_add(export.value);
+            if let Some((_, other)) = globals.insert(&export.name, (value, file)) {
+                return Err(format!(
+                    "{}: '{}' is already exported by {}",
+                    file, export.name, other
+                ));
+            }
+        }
+    }
+
+    // apply relocations
+    let mut segments = vec![];
+    for (((file, object), bases), zero_page) in objects.iter().zip(bases.iter()).zip(&zero_page) {
+        for (segment, base) in object.segments.iter().zip(bases.iter()) {
+            let mut data = segment.data.clone();
+            for relocation in segment.relocations.iter() {
+                let offset = usize::from(relocation.offset);
+                let outside = || {
+                    format!(
+                        "{}: relocation at ${:04x} is outside of segment '{}'",
+                        file, relocation.offset, segment.name
+                    )
+                };
+                // zero page addresses are only ever a byte
+                if relocation.target == Target::ZeroPage {
+                    let byte = data.get_mut(offset).ok_or_else(outside)?;
+                    *byte = byte.checked_add(*zero_page).ok_or_else(|| {
+                        format!(
+                            "{}: zero page address at ${:04x} of segment '{}' is past zero page",
+                            file, relocation.offset, segment.name
+                        )
+                    })?;
+                    continue;
+                }
+                let value = match relocation.target {
+                    Target::Segment(index) => bases[index],
+                    Target::Import(index) => {
+                        let name = &object.imports[index];
+                        match globals.get(name.as_str()) {
+                            Some((value, _)) => *value,
+                            None => return Err(format!("{}: undefined import '{}'", file, name)),
+                        }
+                    }
+                    Target::ZeroPage => unreachable!(),
+                };
+                if offset + 2 > data.len() {
+                    return Err(outside());
+                }
+                let word = u16::from_le_bytes([data[offset], data[offset + 1]]);
+                data[offset..offset + 2].copy_from_slice(&word.wrapping_add(value).to_le_bytes());
+            }
+            if !data.is_empty() {
+                segments.push((*base, data, file, &segment.name));
+            }
+        }
+    }
+
+    segments.sort_by_key(|(base, ..)| *base);
+    for pair in segments.windows(2) {
+        let (base, data, file, name) = &pair[0];
+        let (next, _, next_file, next_name) = &pair[1];
+        if usize::from(*base) + data.len() > usize::from(*next) {
+            return Err(format!(
+                "segment '{}' of {} overlaps segment '{}' of {} at ${:04x}",
+                name, file, next_name, next_file, next
+            ));
+        }
+    }
+
+    let start = match &map.entry {
+        Some(entry) => match globals.get(entry.as_str()) {
+            Some((value, _)) => Some(*value),
+            None => Some(parse_address(entry).ok_or_else(|| {
+                format!("entry '{}' is not an exported symbol or address", entry)
+            })?),
+        },
+        None => segments.first().map(|(base, ..)| *base),
+    };
+
+    let mut symbols = cpu::SymbolTable::new();
+    let mut names = globals.iter().collect::<Vec<_>>();
+    names.sort();
+    for (name, (value, _)) in names {
+        symbols.insert_public(*name, *value);
+    }
+
+    Ok(Linked {
+        image: Image {
+            segments: segments
+                .into_iter()
+                .map(|(base, data, ..)| (base, data))
+                .collect(),
+            start,
+        },
+        symbols,
+    })
+}
+
+/// Places the zero page variables of every object and returns the address each
+/// object's relocatable variables start at. Variables may not overlap each other,
+/// and the relocatable ones are placed clear of the segments, placed at `bases`.
+fn place_zero_page(objects: &[(String, Object)], bases: &[Vec<u16>]) -> Result<Vec<u8>, String> {
+    let mut allocated = vec![];
+    for (file, object) in objects.iter() {
+        for allocation in object.zero_page.iter().filter(|a| !a.relocatable) {
+            let start = u32::from(allocation.address);
+            let end = start + u32::from(allocation.size);
+            if let Some((other_file, other_start, other_end, other)) =
+                find_overlap(&allocated, start, end)
+            {
+                return Err(format!(
+                    "{}: zero page ${:02x}-${:02x} of '{}' overlaps ${:02x}-${:02x} of {} in {}",
+                    file,
+                    start,
+                    end - 1,
+                    allocation.name,
+                    other_start,
+                    other_end - 1,
+                    other,
+                    other_file
+                ));
+            }
+            allocated.push((file, start, end, format!("'{}'", allocation.name)));
+        }
+    }
+
+    // relocatable variables also keep clear of the segments loaded into zero page
+    for ((file, object), bases) in objects.iter().zip(bases.iter()) {
+        for (segment, base) in object.segments.iter().zip(bases.iter()) {
+            let start = u32::from(*base);
+            let end = (start + segment.data.len() as u32).min(0x100);
+            if start < end {
+                allocated.push((file, start, end, format!("segment '{}'", segment.name)));
+            }
+        }
+    }
+
+    let mut placed = vec![];
+    for (file, object) in objects.iter() {
+        let size = object
+            .zero_page
+            .iter()
+            .filter(|allocation| allocation.relocatable)
+            .map(|allocation| u32::from(allocation.address) + u32::from(allocation.size))
+            .max()
+            .unwrap_or(0);
+        if size == 0 {
+            placed.push(0);
+            continue;
+        }
+        let base = (0..=0x100 - size)
+            .find(|base| find_overlap(&allocated, *base, base + size).is_none())
+            .ok_or_else(|| {
+                format!(
+                    "{}: zero page has no room left for {} bytes of variables",
+                    file, size
+                )
+            })?;
+        allocated.push((file, base, base + size, "zero page variables".to_string()));
+        placed.push(base as u8);
+    }
+    Ok(placed)
+}
+
+/// Returns the allocation in zero page which overlaps `start` up to `end`, if
+/// any.
+fn find_overlap<'a>(
+    allocated: &[(&'a String, u32, u32, String)],
+    start: u32,
+    end: u32,
+) -> Option<(&'a String, u32, u32, String)> {
+    allocated
+        .iter()
+        .find(|(_, other_start, other_end, _)| start < *other_end && *other_start < end)
+        .cloned()
+}
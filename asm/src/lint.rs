@@ -0,0 +1,143 @@
+use crate::error::{Diagnostic, Severity};
+
+/// A check for source which assembles, but probably not to what was meant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lint {
+    /// An absolute address which fits in zero page, because it was used before
+    /// it was defined.
+    AbsoluteZeroPage,
+    /// A branch to the instruction after it, which does nothing either way.
+    BranchToNext,
+    /// A label which nothing refers to or exports.
+    UnusedLabel,
+    /// A macro defined again with the same parameters, which replaces it.
+    RedefinedMacro,
+    /// A lone `a` operand taken to be the accumulator, when a symbol is also
+    /// named `a`.
+    AccumulatorLabel,
+    /// A zero page variable at addresses the program loads bytes into.
+    ZeroPageOverlap,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::AbsoluteZeroPage,
+        Lint::BranchToNext,
+        Lint::UnusedLabel,
+        Lint::RedefinedMacro,
+        Lint::AccumulatorLabel,
+        Lint::ZeroPageOverlap,
+    ];
+
+    /// Returns the name used with `-W` on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::AbsoluteZeroPage => "absolute-zero-page",
+            Lint::BranchToNext => "branch-to-next",
+            Lint::UnusedLabel => "unused-label",
+            Lint::RedefinedMacro => "redefined-macro",
+            Lint::AccumulatorLabel => "accumulator-label",
+            Lint::ZeroPageOverlap => "zero-page-overlap",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Lint::ALL
+            .into_iter()
+            .find(|lint| lint.name() == name)
+            .ok_or_else(|| format!("unknown warning '{}'", name))
+    }
+}
+
+/// Which lints are checked, and whether they fail the build.
+#[derive(Clone, Debug)]
+pub struct LintOptions {
+    enabled: Vec<Lint>,
+    /// Whether warnings are reported as errors.
+    werror: bool,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            enabled: Lint::ALL.to_vec(),
+            werror: false,
+        }
+    }
+}
+
+impl LintOptions {
+    /// Applies a `-W` option, one of:
+    /// ```text
+    ///     -W<name>      check a lint
+    ///     -Wno-<name>   do not check a lint
+    ///     -Werror       report warnings as errors
+    ///     -w            do not check any lint
+    /// ```
+    pub fn apply(&mut self, option: &str) -> Result<(), String> {
+        match option {
+            "-w" => self.enabled.clear(),
+            "-Werror" => self.werror = true,
+            _ => {
+                let name = option.strip_prefix("-W").unwrap_or(option);
+                match name.strip_prefix("no-") {
+                    Some(name) => {
+                        let lint = Lint::parse(name)?;
+                        self.enabled.retain(|enabled| *enabled != lint);
+                    }
+                    None => {
+                        let lint = Lint::parse(name)?;
+                        if !self.enabled.contains(&lint) {
+                            self.enabled.push(lint);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The warnings found while assembling.
+pub struct Warnings {
+    options: LintOptions,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Warnings {
+    pub fn new(options: LintOptions) -> Self {
+        Self {
+            options,
+            diagnostics: vec![],
+        }
+    }
+
+    /// Returns whether `lint` is checked, for checks which are costly to make.
+    pub fn is_enabled(&self, lint: Lint) -> bool {
+        self.options.enabled.contains(&lint)
+    }
+
+    /// Reports `diagnostic` if `lint` is checked.
+    pub fn warn(&mut self, lint: Lint, diagnostic: Diagnostic) {
+        if !self.is_enabled(lint) {
+            return;
+        }
+        let severity = if self.options.werror {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        let diagnostic = diagnostic.with_severity(severity).with_code(lint.name());
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Returns whether a warning was reported as an error.
+    pub fn has_errors(&self) -> bool {
+        self.options.werror && !self.diagnostics.is_empty()
+    }
+
+    /// Returns the warnings in the order they were found, and forgets them.
+    pub fn take(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
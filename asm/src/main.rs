@@ -1,64 +1,619 @@
 mod assembler;
 mod error;
+mod format;
 mod instruction;
+mod linker;
+mod lint;
+mod object;
 mod preprocessor;
 mod source;
 mod token;
 mod utils;
 
-use colored::*;
-use indoc::indoc;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use crate::preprocessor::preprocess;
+use system::Image;
+
+use crate::assembler::{assemble, CycleBlock, ListingEntry, Stats};
+use crate::error::{Diagnostic, ErrorFormat};
+use crate::format::format_source;
+use crate::linker::{link, MemoryMap};
+use crate::lint::{LintOptions, Warnings};
+use crate::object::{Allocation, Object};
+use crate::preprocessor::{preprocess, Includes};
 use crate::source::{File, SourceMap};
 use crate::token::tokens;
 
-static SOURCE: &str = indoc! {"
-%define STACK $0100
-%define add(a)    ((a) + STACK)
-%define add(a, b) (add(a) + b)
-
-    add((1), 2)
-    sta STACK
-    hello 123 ; comment
-    
-LABEL: .db 0xa
-    sta STACK
-"};
-
-fn run(file: &File) -> Result<(), String> {
-    let raw_tokens = file.lex_tokens();
-    println!("{}", "original".green());
+static USAGE: &str = "usage: asm [options] <input>
+       asm link [link options] <objects...>
+       asm fmt [--check] <inputs...>
+
+options:
+    -o <path>           write the output to <path> (default: <input>.o)
+    -f, --format <fmt>  write the output as bin, ihex, srec or obj (default: bin)
+    -l <path>           write a listing to <path>
+    -s <path>           write the symbol table to <path>, in which exported
+                        symbols are public
+    -c <path>           write the cycles of each instruction to <path>, with
+                        totals for each block of straight-line code
+    -D <name>[=<value>] define a constant before assembling (default value: 1)
+    -I <dir>            look for %include and .incbin files in <dir>, after the
+                        directory of the including file and before the
+                        directories in ASMPATH
+    --stats             print the bytes in each segment, the number of symbols,
+                        the zero page used, the longest branches and how often
+                        each macro was expanded
+    -E                  write the preprocessed source instead of assembling it,
+                        to <path> if given with -o or else to stdout
+    -W<warning>         check for a warning, all are checked by default:
+                        absolute-zero-page, branch-to-next, unused-label,
+                        redefined-macro, accumulator-label,
+                        zero-page-overlap
+    -Wno-<warning>      do not check for a warning
+    -Werror             report warnings as errors
+    -w                  do not check for any warning
+    --error-format <fmt>
+                        write errors as text or json, one object per line (default: text)
+    -h                  print this message
+
+link options:
+    -o <path>           write the image to <path> (default: a.out)
+    -f, --format <fmt>  write the image as bin, ihex or srec (default: bin)
+    -m <path>           place relocatable segments by the memory map in <path>
+    -s <path>           write the exported symbols to <path>
+    --error-format <fmt>
+                        write errors as text or json, one object per line (default: text)
+
+fmt options:
+    --check             list the inputs which are not formatted instead of
+                        formatting them, and fail if there are any";
+
+/// The format of the assembled output.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// The bytes from the lowest address assembled to the highest.
+    Binary,
+    /// Intel HEX records, which keep the load address.
+    IntelHex,
+    /// Motorola S-records, which keep the load address.
+    SRecord,
+    /// An object to be linked with `asm link`.
+    Object,
+}
+
+impl Format {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "bin" => Ok(Format::Binary),
+            "ihex" => Ok(Format::IntelHex),
+            "srec" => Ok(Format::SRecord),
+            "obj" => Ok(Format::Object),
+            _ => Err(format!("unknown output format '{}'", name)),
+        }
+    }
+}
+
+/// The command line options.
+struct Options {
+    input: String,
+    output: String,
+    format: Format,
+    listing: Option<String>,
+    symbols: Option<String>,
+    cycles: Option<String>,
+    defines: Vec<(String, String)>,
+    /// The directories given with `-I`, in order.
+    include_dirs: Vec<PathBuf>,
+    /// Whether to print figures about the program after assembling it.
+    stats: bool,
+    /// Whether to stop after preprocessing, and write the source out.
+    preprocess_only: bool,
+    lints: LintOptions,
+    error_format: ErrorFormat,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut input = None;
+        let mut output = None;
+        let mut format = Format::Binary;
+        let mut listing = None;
+        let mut symbols = None;
+        let mut cycles = None;
+        let mut defines = vec![];
+        let mut include_dirs = vec![];
+        let mut stats = false;
+        let mut preprocess_only = false;
+        let mut lints = LintOptions::default();
+        let mut error_format = ErrorFormat::Text;
+
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| format!("missing value for option '{}'", flag))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                "-o" => output = Some(value("-o")?),
+                "-f" | "--format" => format = Format::parse(&value(&arg)?)?,
+                "-l" => listing = Some(value("-l")?),
+                "-s" => symbols = Some(value("-s")?),
+                "-c" => cycles = Some(value("-c")?),
+                "-D" => defines.push(parse_define(&value("-D")?)?),
+                "-I" => include_dirs.push(PathBuf::from(value("-I")?)),
+                "-E" => preprocess_only = true,
+                "--stats" => stats = true,
+                "--error-format" => error_format = ErrorFormat::parse(&value(&arg)?)?,
+                arg if arg.starts_with("-D") => defines.push(parse_define(&arg[2..])?),
+                arg if arg.starts_with("-I") => include_dirs.push(PathBuf::from(&arg[2..])),
+                arg if arg.starts_with("-W") || arg == "-w" => lints.apply(arg)?,
+                arg if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option '{}'\n\n{}", arg, USAGE));
+                }
+                _ if input.is_some() => {
+                    return Err(format!("unexpected argument '{}'\n\n{}", arg, USAGE));
+                }
+                _ => input = Some(arg),
+            }
+        }
+
+        let input = input.ok_or_else(|| USAGE.to_string())?;
+        let output = output.unwrap_or_else(|| {
+            if preprocess_only {
+                return "-".to_string();
+            }
+            Path::new(&input)
+                .with_extension("o")
+                .to_string_lossy()
+                .into_owned()
+        });
+        Ok(Self {
+            input,
+            output,
+            format,
+            listing,
+            symbols,
+            cycles,
+            defines,
+            include_dirs,
+            stats,
+            preprocess_only,
+            lints,
+            error_format,
+        })
+    }
+}
+
+/// Parses a `name[=value]` predefine.
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    let (name, value) = define.split_once('=').unwrap_or((define, "1"));
+    let valid = name
+        .chars()
+        .enumerate()
+        .all(|(index, c)| c.is_ascii_alphabetic() || c == '_' || (index > 0 && c.is_ascii_digit()));
+    if name.is_empty() || !valid {
+        return Err(format!("invalid name in define '{}'", define));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// The command line options of `asm link`.
+struct LinkOptions {
+    inputs: Vec<String>,
+    output: String,
+    format: Format,
+    map: Option<String>,
+    symbols: Option<String>,
+    error_format: ErrorFormat,
+}
+
+impl LinkOptions {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut inputs = vec![];
+        let mut output = None;
+        let mut format = Format::Binary;
+        let mut map = None;
+        let mut symbols = None;
+        let mut error_format = ErrorFormat::Text;
+
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| format!("missing value for option '{}'", flag))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                "-o" => output = Some(value("-o")?),
+                "-f" | "--format" => format = Format::parse(&value(&arg)?)?,
+                "-m" => map = Some(value("-m")?),
+                "-s" => symbols = Some(value("-s")?),
+                "--error-format" => error_format = ErrorFormat::parse(&value(&arg)?)?,
+                arg if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option '{}'\n\n{}", arg, USAGE));
+                }
+                _ => inputs.push(arg),
+            }
+        }
+
+        if inputs.is_empty() {
+            return Err(USAGE.to_string());
+        }
+        if format == Format::Object {
+            return Err("objects cannot be linked into an object".to_string());
+        }
+        Ok(Self {
+            inputs,
+            output: output.unwrap_or_else(|| "a.out".to_string()),
+            format,
+            map,
+            symbols,
+            error_format,
+        })
+    }
+}
+
+/// The command line options of `asm fmt`.
+struct FmtOptions {
+    inputs: Vec<String>,
+    /// Whether to only check that the inputs are formatted.
+    check: bool,
+}
+
+impl FmtOptions {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut inputs = vec![];
+        let mut check = false;
+        for arg in args {
+            match arg.as_str() {
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                "--check" => check = true,
+                arg if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option '{}'\n\n{}", arg, USAGE));
+                }
+                _ => inputs.push(arg),
+            }
+        }
+
+        if inputs.is_empty() {
+            return Err(USAGE.to_string());
+        }
+        Ok(Self { inputs, check })
+    }
+}
+
+/// Formats a listing of the source next to the bytes it assembled to.
+///
+/// Each line shows the line number, the address and up to three bytes. Lines
+/// which emit more than that, or which are repeated, continue on the following
+/// rows. The variables allocated with `.zpvar` follow the source, with those the
+/// linker places given as an offset:
+/// ```text
+///     zero page:
+///         0080  ptr, 2 bytes
+///         0082  count, 1 byte
+///         +000  tmp, 1 byte
+/// ```
+fn format_listing(file: &File, entries: &[ListingEntry], zero_page: &[Allocation]) -> String {
+    let mut by_line = HashMap::<usize, Vec<&ListingEntry>>::new();
+    for entry in entries.iter() {
+        by_line.entry(entry.line).or_default().push(entry);
+    }
+
+    let mut listing = String::new();
     for line in 1..file.line_count() + 1 {
-        println!("{:3} │ {}", line, file.get_source_line(line).unwrap());
+        let source = file.get_source_line(line).unwrap();
+        let mut rows = vec![];
+        for entry in by_line.remove(&line).unwrap_or_default() {
+            for (index, chunk) in entry.bytes.chunks(3).enumerate() {
+                let hex = chunk
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<Vec<_>>();
+                let address = entry.address as usize + index * 3;
+                rows.push(format!("{:04x}  {:<8}", address, hex.join(" ")));
+            }
+        }
+
+        let mut rows = rows.into_iter();
+        let first = rows.next().unwrap_or_else(|| " ".repeat(14));
+        listing += format!("{:5}  {}  {}", line, first, source).trim_end();
+        listing += "\n";
+        for row in rows {
+            listing += &format!("{:5}  {}\n", "", row.trim_end());
+        }
+    }
+
+    if !zero_page.is_empty() {
+        listing += "\nzero page:\n";
+    }
+    for allocation in zero_page.iter() {
+        let unit = if allocation.size == 1 {
+            "byte"
+        } else {
+            "bytes"
+        };
+        let address = if allocation.relocatable {
+            format!("+{:03x}", allocation.address)
+        } else {
+            format!("{:04x}", allocation.address)
+        };
+        listing += &format!(
+            "    {}  {}, {} {}\n",
+            address, allocation.name, allocation.size, unit
+        );
     }
+    listing
+}
+
+/// Formats the cycles each instruction takes, next to its source.
+/// ```text
+///     loop:
+///         5  0202  4+1   loop:   lda table,x
+///         6  0205  2+2   bne loop
+///                  6+3   cycles
+/// ```
+/// The extra cycles are the most a page crossing or a taken branch can add. A
+/// block ends at a label or an instruction which may jump, and is followed by
+/// its total.
+fn format_cycles(file: &File, blocks: &[CycleBlock]) -> String {
+    let format = |cycles: u32, extra: u32| match extra {
+        0 => cycles.to_string(),
+        _ => format!("{}+{}", cycles, extra),
+    };
+
+    let mut report = String::new();
+    for block in blocks.iter() {
+        if let Some(label) = &block.label {
+            report += &format!("{}:\n", label);
+        }
+        for entry in block.entries.iter() {
+            let source = file.get_source_line(entry.line).unwrap_or("").trim();
+            let cycles = format(entry.cycles as u32, entry.extra as u32);
+            report += &format!(
+                "{:5}  {:04x}  {:<5} {}\n",
+                entry.line, entry.address, cycles, source
+            );
+        }
+        let cycles = block.entries.iter().map(|e| e.cycles as u32).sum();
+        let extra = block.entries.iter().map(|e| e.extra as u32).sum();
+        report += &format!("{:13}{:<5} cycles\n\n", "", format(cycles, extra));
+    }
+    report
+}
 
-    // println!("{}", "tokens:".blue());
-    // for token in tokens.iter() {
-    //     if let Some(orig) = token.source.origin {
-    //         let span = orig.span_loc();
-    //         println!("{:?}: {:?} <{}>", token.kind(), token.source.value(), span);
-    //     } else {
-    //         println!("{:?}: {:?}", token.kind(), token.source.value());
-    //     }
-    // }
+/// Formats figures about an assembled program, one to a line so they can be
+/// compared between versions of it.
+/// ```text
+///     segment code: 412 bytes
+///     symbols: 38
+///     zero page: 6 bytes
+///     longest forward branch: 24 bytes at line 40
+///     longest backward branch: 117 bytes at line 95
+///     macro PUSH_ALL: 3 expansions
+/// ```
+/// Segments nothing was emitted into are left out.
+fn format_stats(stats: &Stats, expansions: &BTreeMap<String, usize>) -> String {
+    let count = |n: usize, unit: &str| match n {
+        1 => format!("1 {}", unit),
+        n => format!("{} {}s", n, unit),
+    };
 
-    let out_tokens = preprocess(&raw_tokens, vec![]).map_err(|err| format!("{:?}", err))?;
-    let result = tokens::to_string(&out_tokens);
-    println!("{}", "preprocessed:".green());
-    for (index, line) in result.split("\n").enumerate() {
-        println!("{:3} │ {}", index + 1, line);
+    let mut report = String::new();
+    for (name, bytes) in stats.segments.iter().filter(|(_, bytes)| *bytes > 0) {
+        report += &format!("segment {}: {}\n", name, count(*bytes, "byte"));
+    }
+    report += &format!("symbols: {}\n", stats.symbols);
+    report += &format!("zero page: {}\n", count(stats.zero_page.len(), "byte"));
+    for (direction, branch) in [("forward", &stats.forward), ("backward", &stats.backward)] {
+        if let Some(branch) = branch {
+            let distance = count(branch.offset.unsigned_abs() as usize, "byte");
+            report += &format!(
+                "longest {} branch: {} at line {}\n",
+                direction, distance, branch.line
+            );
+        }
     }
+    for (name, n) in expansions.iter() {
+        report += &format!("macro {}: {}\n", name, count(*n, "expansion"));
+    }
+    report
+}
+
+fn run(options: &Options, warnings: &mut Warnings) -> Result<(), Diagnostic> {
+    let mut source_map = SourceMap::new();
+    let preamble = options
+        .defines
+        .iter()
+        .map(|(name, value)| format!("%define {} {}\n", name, value))
+        .collect::<String>();
+    let predefs = File::new("<command line>".to_string(), preamble);
+    let file = source_map
+        .add_from_path(&options.input)
+        .map_err(|err| format!("{}: {}", options.input, err))?;
+
+    let mut raw_tokens = predefs.lex_tokens();
+    raw_tokens.extend(file.lex_tokens());
 
-    println!();
+    let mut search = options.include_dirs.clone();
+    if let Some(path) = std::env::var_os("ASMPATH") {
+        search.extend(std::env::split_paths(&path).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    let mut includes = Includes::new(&options.input, search);
+    let preprocessed = preprocess(&raw_tokens, vec![], &mut includes, warnings)?;
+    if options.preprocess_only {
+        let source = tokens::to_string(&preprocessed.tokens);
+        if options.output == "-" {
+            print!("{}", source);
+        } else {
+            fs::write(&options.output, source)
+                .map_err(|err| format!("{}: {}", options.output, err))?;
+        }
+        return Ok(());
+    }
+    let object = options.format == Format::Object;
+    let assembly = assemble(&preprocessed.tokens, object, warnings)?;
+    if warnings.has_errors() {
+        return Err(Diagnostic::error(
+            "warnings are treated as errors (-Werror)".to_string(),
+        ));
+    }
+
+    let zero_page = assembly.object.zero_page.clone();
+    if object {
+        fs::write(&options.output, assembly.object.to_string())
+            .map_err(|err| format!("{}: {}", options.output, err))?;
+    } else {
+        // everything has an address, so the object links on its own
+        let objects = [(options.input.clone(), assembly.object)];
+        let linked = link(&objects, &MemoryMap::default())?;
+        write_image(&options.output, options.format, &linked.image)?;
+    }
+    if let Some(path) = &options.listing {
+        fs::write(path, format_listing(file, &assembly.listing, &zero_page))
+            .map_err(|err| format!("{}: {}", path, err))?;
+    }
+    if let Some(path) = &options.symbols {
+        fs::write(path, assembly.symbols.to_string())
+            .map_err(|err| format!("{}: {}", path, err))?;
+    }
+    if let Some(path) = &options.cycles {
+        fs::write(path, format_cycles(file, &assembly.cycles))
+            .map_err(|err| format!("{}: {}", path, err))?;
+    }
+    if options.stats {
+        print!(
+            "{}",
+            format_stats(&assembly.stats, &preprocessed.expansions)
+        );
+    }
     Ok(())
 }
 
+fn run_link(options: &LinkOptions) -> Result<(), Diagnostic> {
+    let map = match &options.map {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+            MemoryMap::parse(&text).map_err(|err| format!("{}: {}", path, err))?
+        }
+        None => MemoryMap::default(),
+    };
+    let mut objects = vec![];
+    for path in options.inputs.iter() {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        let object = Object::parse(&text).map_err(|err| format!("{}: {}", path, err))?;
+        objects.push((path.clone(), object));
+    }
+
+    let linked = link(&objects, &map)?;
+    write_image(&options.output, options.format, &linked.image)?;
+    if let Some(path) = &options.symbols {
+        fs::write(path, linked.symbols.to_string()).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    Ok(())
+}
+
+/// Formats each input in place, or lists those which are not formatted.
+fn run_fmt(options: &FmtOptions) -> Result<(), String> {
+    let mut unformatted = 0;
+    for path in options.inputs.iter() {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        let formatted = format_source(&File::new(path.clone(), text.clone()));
+        if formatted == text {
+            continue;
+        }
+        if options.check {
+            println!("{}", path);
+            unformatted += 1;
+        } else {
+            fs::write(path, formatted).map_err(|err| format!("{}: {}", path, err))?;
+        }
+    }
+    match unformatted {
+        0 => Ok(()),
+        1 => Err("1 file is not formatted".to_string()),
+        n => Err(format!("{} files are not formatted", n)),
+    }
+}
+
+/// Writes a linked image in one of the image formats.
+fn write_image(path: &str, format: Format, image: &Image) -> Result<(), String> {
+    let output = match format {
+        Format::Binary => flatten(image),
+        Format::IntelHex => image.to_ihex().into_bytes(),
+        Format::SRecord => image.to_srec().into_bytes(),
+        Format::Object => unreachable!("objects are not images"),
+    };
+    fs::write(path, output).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Returns the bytes from the lowest address in the image to the highest, with
+/// any gaps filled with zeros.
+fn flatten(image: &Image) -> Vec<u8> {
+    let base = match image.segments.iter().map(|(address, _)| *address).min() {
+        Some(base) => usize::from(base),
+        None => return vec![],
+    };
+    let mut bytes = vec![];
+    for (address, data) in image.segments.iter() {
+        let offset = usize::from(*address) - base;
+        if bytes.len() < offset + data.len() {
+            bytes.resize(offset + data.len(), 0);
+        }
+        bytes[offset..offset + data.len()].copy_from_slice(data);
+    }
+    bytes
+}
+
 fn main() {
-    let mut source_map = SourceMap::new();
-    let file = source_map.add_from_string("<source>", SOURCE);
-    if let Err(error) = run(&file) {
-        println!("{}", error);
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "fmt") {
+        let options = match FmtOptions::parse(args.skip(1)) {
+            Ok(options) => options,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        };
+        if let Err(error) = run_fmt(&options) {
+            eprintln!("{}", Diagnostic::error(error));
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+    if args.peek().is_some_and(|arg| arg == "link") {
+        let options = match LinkOptions::parse(args.skip(1)) {
+            Ok(options) => options,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        };
+        if let Err(error) = run_link(&options) {
+            eprintln!("{}", error.render(options.error_format));
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let mut warnings = Warnings::new(options.lints.clone());
+    let result = run(&options, &mut warnings);
+    for warning in warnings.take() {
+        eprintln!("{}", warning.render(options.error_format));
+    }
+    if let Err(error) = result {
+        eprintln!("{}", error.render(options.error_format));
         std::process::exit(1);
     }
     std::process::exit(0);
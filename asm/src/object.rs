@@ -0,0 +1,266 @@
+//! The object files written by `-f obj` and read by the linker.
+//!
+//! An object is a text file with one record per line:
+//! ```text
+//!     import print
+//!     segment code
+//!     data 20000060
+//!     reloc $0001 import print
+//!     segment default $1000
+//!     data 4c0000
+//!     reloc $0001 segment code
+//!     export start $0000 code
+//!     export width $0028
+//!     zpvar ptr $0080 2
+//!     zpvar count $0000 1 zeropage
+//!     reloc $0004 zeropage
+//! ```
+//! `data` and `reloc` records belong to the segment above them. A segment with an
+//! address is absolute, any other segment is placed by the linker. A relocation
+//! adds the final address of a segment or imported symbol to the little endian
+//! word at an offset into its segment. A `zpvar` record gives the address and
+//! size of a variable allocated in zero page with `.zpvar`, which the linker
+//! checks no other object uses. Variables marked `zeropage` are instead an
+//! offset into the object's zero page variables, which the linker places, and a
+//! `zeropage` relocation adds where it placed them to the byte at the offset.
+//! An export may be relative to them the same way. Blank lines and anything
+//! after a `;` are ignored.
+
+use std::fmt::Write;
+
+/// The name relocations and exports use for the zero page variables of an
+/// object, which no segment can have.
+pub const ZERO_PAGE: &str = "zeropage";
+
+/// What a relocation or symbol is relative to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// The start of a segment of the same object, by index.
+    Segment(usize),
+    /// An imported symbol, by index.
+    Import(usize),
+    /// The relocatable zero page variables of the same object.
+    ZeroPage,
+}
+
+pub struct Segment {
+    pub name: String,
+    /// The address of an absolute segment.
+    pub address: Option<u16>,
+    pub data: Vec<u8>,
+    pub relocations: Vec<Relocation>,
+}
+
+/// A word to relocate, or a single byte for a relocation against the zero page.
+pub struct Relocation {
+    /// The offset of the word to relocate from the start of its segment.
+    pub offset: u16,
+    pub target: Target,
+}
+
+pub struct Export {
+    pub name: String,
+    /// The segment or zero page variables the value is an offset into, or `None`
+    /// if it is absolute.
+    pub target: Option<Target>,
+    pub value: u16,
+}
+
+/// A variable allocated in zero page with `.zpvar`.
+#[derive(Clone)]
+pub struct Allocation {
+    pub name: String,
+    pub address: u8,
+    pub size: u16,
+    /// Whether the address is an offset into the object's zero page variables
+    /// rather than fixed by `.zppool`.
+    pub relocatable: bool,
+}
+
+#[derive(Default)]
+pub struct Object {
+    pub segments: Vec<Segment>,
+    pub imports: Vec<String>,
+    pub exports: Vec<Export>,
+    /// The zero page variables, in the order they were allocated.
+    pub zero_page: Vec<Allocation>,
+}
+
+/// The bytes written on each `data` record.
+const DATA_SIZE: usize = 32;
+
+impl Object {
+    /// Reads an object file.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut object = Object::default();
+        // segments may be relocated against segments further down
+        let mut pending = Vec::<(usize, usize, usize, &str)>::new();
+        for (index, line) in text.lines().enumerate() {
+            let fields = line
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            let malformed = || format!("malformed record on line {}", index + 1);
+            let segment_index = |name: &str| object.segments.iter().position(|s| s.name == name);
+
+            match fields[..] {
+                [] => {}
+                ["import", name] => object.imports.push(name.to_string()),
+                ["segment", ZERO_PAGE] | ["segment", ZERO_PAGE, _] => return Err(malformed()),
+                ["segment", name] | ["segment", name, _] => {
+                    let address = match fields.get(2) {
+                        Some(address) => Some(parse_address(address).ok_or_else(malformed)?),
+                        None => None,
+                    };
+                    object.segments.push(Segment {
+                        name: name.to_string(),
+                        address,
+                        data: vec![],
+                        relocations: vec![],
+                    });
+                }
+                ["data", bytes] => {
+                    let segment = object.segments.last_mut().ok_or_else(malformed)?;
+                    if !bytes.len().is_multiple_of(2) || !bytes.is_ascii() {
+                        return Err(malformed());
+                    }
+                    for i in (0..bytes.len()).step_by(2) {
+                        let byte = u8::from_str_radix(&bytes[i..i + 2], 16);
+                        segment.data.push(byte.map_err(|_| malformed())?);
+                    }
+                }
+                ["reloc", offset, ZERO_PAGE] => {
+                    let offset = parse_address(offset).ok_or_else(malformed)?;
+                    let segment = object.segments.last_mut().ok_or_else(malformed)?;
+                    segment.relocations.push(Relocation {
+                        offset,
+                        target: Target::ZeroPage,
+                    });
+                }
+                ["reloc", offset, kind, name] => {
+                    let offset = parse_address(offset).ok_or_else(malformed)?;
+                    let current = object.segments.len().checked_sub(1).ok_or_else(malformed)?;
+                    let relocation = object.segments[current].relocations.len();
+                    let target = match kind {
+                        "segment" => {
+                            pending.push((current, relocation, index + 1, name));
+                            Target::Segment(current)
+                        }
+                        "import" => object
+                            .imports
+                            .iter()
+                            .position(|i| i == name)
+                            .map(Target::Import)
+                            .ok_or_else(malformed)?,
+                        _ => return Err(malformed()),
+                    };
+                    let relocations = &mut object.segments[current].relocations;
+                    relocations.push(Relocation { offset, target });
+                }
+                ["export", name, value] | ["export", name, value, _] => {
+                    let value = parse_address(value).ok_or_else(malformed)?;
+                    let target = match fields.get(3) {
+                        Some(&ZERO_PAGE) => Some(Target::ZeroPage),
+                        Some(segment) => Some(Target::Segment(
+                            segment_index(segment).ok_or_else(malformed)?,
+                        )),
+                        None => None,
+                    };
+                    object.exports.push(Export {
+                        name: name.to_string(),
+                        target,
+                        value,
+                    });
+                }
+                ["zpvar", name, address, size] | ["zpvar", name, address, size, ZERO_PAGE] => {
+                    let address = parse_address(address).ok_or_else(malformed)?;
+                    let address = u8::try_from(address).map_err(|_| malformed())?;
+                    let size = size.parse::<u16>().map_err(|_| malformed())?;
+                    if size == 0 || u32::from(address) + u32::from(size) > 0x100 {
+                        return Err(malformed());
+                    }
+                    object.zero_page.push(Allocation {
+                        name: name.to_string(),
+                        address,
+                        size,
+                        relocatable: fields.len() == 5,
+                    });
+                }
+                _ => return Err(malformed()),
+            }
+        }
+
+        for (segment, relocation, line, name) in pending {
+            let index = object.segments.iter().position(|s| s.name == name);
+            let index = index.ok_or_else(|| format!("malformed record on line {}", line))?;
+            object.segments[segment].relocations[relocation].target = Target::Segment(index);
+        }
+        Ok(object)
+    }
+}
+
+/// Formats the object as an object file.
+impl std::fmt::Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "; rs6502 object")?;
+        for name in self.imports.iter() {
+            writeln!(f, "import {}", name)?;
+        }
+        for segment in self.segments.iter() {
+            match segment.address {
+                Some(address) => writeln!(f, "segment {} ${:04x}", segment.name, address)?,
+                None => writeln!(f, "segment {}", segment.name)?,
+            }
+            for chunk in segment.data.chunks(DATA_SIZE) {
+                let mut bytes = String::new();
+                for byte in chunk {
+                    write!(bytes, "{:02x}", byte)?;
+                }
+                writeln!(f, "data {}", bytes)?;
+            }
+            for relocation in segment.relocations.iter() {
+                let target = match relocation.target {
+                    Target::Segment(index) => format!("segment {}", self.segments[index].name),
+                    Target::Import(index) => format!("import {}", self.imports[index]),
+                    Target::ZeroPage => ZERO_PAGE.to_string(),
+                };
+                writeln!(f, "reloc ${:04x} {}", relocation.offset, target)?;
+            }
+        }
+        for export in self.exports.iter() {
+            let relative = match export.target {
+                Some(Target::Segment(index)) => format!(" {}", self.segments[index].name),
+                Some(Target::ZeroPage) => format!(" {}", ZERO_PAGE),
+                Some(Target::Import(_)) | None => String::new(),
+            };
+            writeln!(
+                f,
+                "export {} ${:04x}{}",
+                export.name, export.value, relative
+            )?;
+        }
+        for allocation in self.zero_page.iter() {
+            let relative = if allocation.relocatable {
+                format!(" {}", ZERO_PAGE)
+            } else {
+                String::new()
+            };
+            writeln!(
+                f,
+                "zpvar {} ${:04x} {}{}",
+                allocation.name, allocation.address, allocation.size, relative
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a `$`-prefixed hex or a decimal address.
+pub fn parse_address(text: &str) -> Option<u16> {
+    match text.strip_prefix('$') {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -1,55 +1,60 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
+    assembler::evaluate_constant,
     error,
-    error::SyntaxError,
-    source::SourceRef,
-    token::{RawToken, RawTokenKind, TokenLike},
+    error::Diagnostic,
+    lint::{Lint, Warnings},
+    source::{File, SourceRef},
+    token::{tokens, RawToken, RawTokenKind, Token, TokenLike},
     utils::*,
 };
 
 const RECURSION_LIMIT: usize = 10;
 
+/// The most times a `%rep` block can be repeated.
+const REPEAT_LIMIT: u32 = 0x10000;
+
 pub struct Macro<'a> {
     pub name: &'a str,
     pub params: Option<Vec<&'a str>>,
+    /// Whether the last parameter collects any remaining arguments.
+    pub variadic: bool,
     pub def: Vec<MacroToken<'a>>,
+    /// The name in the `%define` of the macro, unless it was predefined.
+    pub token: Option<&'a RawToken<'a>>,
 }
 
 impl<'a> Macro<'a> {
     pub fn new(name: &'a str, params: Option<Vec<&'a str>>, def: Vec<MacroToken<'a>>) -> Self {
-        Self { name, params, def }
+        Self {
+            name,
+            params,
+            variadic: false,
+            def,
+            token: None,
+        }
     }
 
     pub fn new_constant(name: &'a str, def: Vec<&'a RawToken<'a>>) -> Self {
         let params = None;
-        let def = def
-            .into_iter()
-            .map(|t| MacroToken::Token(t))
-            .collect::<Vec<_>>();
+        let def = def.into_iter().map(MacroToken::Token).collect::<Vec<_>>();
 
-        Macro { name, params, def }
-    }
-
-    pub fn new_function(name: &'a str, params: Vec<&'a str>, def: Vec<&'a RawToken<'a>>) -> Self {
-        let def = def
-            .iter()
-            .map(|t| {
-                if params.contains(&t.source.value()) {
-                    MacroToken::Parameter(t)
-                } else {
-                    MacroToken::Token(t)
-                }
-            })
-            .collect::<Vec<_>>();
-        let params = Some(params);
-
-        Macro { name, params, def }
+        Macro::new(name, params, def)
     }
 }
 
 pub enum MacroToken<'a> {
     Parameter(&'a RawToken<'a>),
+    /// A parameter written as `%str(name)`, replaced by its argument as a string.
+    Stringize(&'a RawToken<'a>),
+    /// The `##` operator, which joins the tokens on either side into one.
+    Concat,
     Token(&'a RawToken<'a>),
 }
 
@@ -58,6 +63,8 @@ impl std::fmt::Debug for MacroToken<'_> {
         match self {
             MacroToken::Token(t) => write!(f, "{:?}", t),
             MacroToken::Parameter(t) => write!(f, "{:?}", t),
+            MacroToken::Stringize(t) => write!(f, "Stringize({:?})", t),
+            MacroToken::Concat => write!(f, "Concat"),
         }
     }
 }
@@ -77,10 +84,13 @@ impl std::fmt::Debug for MacroToken<'_> {
 ///     test(2, 3)
 ///     test(4, 5, 6)
 /// ```
+/// A set can also hold one variadic definition, which is used when no overload takes
+/// exactly the number of arguments given but the variadic form accepts them.
 pub struct MacroSet<'a> {
     pub name: &'a str,
     constant: Option<Vec<MacroToken<'a>>>,
     overloads: Vec<(Vec<&'a str>, Vec<MacroToken<'a>>)>,
+    variadic: Option<(Vec<&'a str>, Vec<MacroToken<'a>>)>,
 }
 
 impl<'a> MacroSet<'a> {
@@ -90,16 +100,18 @@ impl<'a> MacroSet<'a> {
             name,
             constant: None,
             overloads: vec![],
+            variadic: None,
         }
     }
 
     /// Adds a new macro definition to the set.
     ///
     /// If any definition already exists with the same number of parameters it is
-    /// replaced. This method will panic if the name of `macro` does not match the
-    /// name of this `MacroSet`.
-    pub fn add(&mut self, params: Option<Vec<&'a str>>, def: Vec<MacroToken<'a>>) {
-        if let Some(p) = params {
+    /// replaced. A variadic definition replaces the previous variadic definition.
+    pub fn add(&mut self, params: Option<Vec<&'a str>>, variadic: bool, def: Vec<MacroToken<'a>>) {
+        if let Some(p) = params.as_ref().filter(|_| variadic) {
+            self.variadic = Some((p.clone(), def));
+        } else if let Some(p) = params {
             match self
                 .overloads
                 .binary_search_by_key(&p.len(), |(a, _)| a.len())
@@ -114,14 +126,9 @@ impl<'a> MacroSet<'a> {
         }
     }
 
-    /// Returns whether the set has a macro constant definition.
-    pub fn has_constant(&self) -> bool {
-        self.constant.is_some()
-    }
-
     /// Returns whether the set has any macro function overloads.
     pub fn has_overloads(&self) -> bool {
-        !self.overloads.is_empty()
+        !self.overloads.is_empty() || self.variadic.is_some()
     }
 
     /// Returns the constant definition for this macro should it exist.
@@ -130,11 +137,22 @@ impl<'a> MacroSet<'a> {
     }
 
     /// Returns the definition for the given overload form if it exists.
-    pub fn get_overload<'b>(&'b self, args: usize) -> Option<&(Vec<&'a str>, Vec<MacroToken<'a>>)> {
+    ///
+    /// An overload taking exactly `args` arguments is preferred over the variadic
+    /// definition, which takes one less than its parameter count or more.
+    pub fn get_overload<'b>(
+        &'b self,
+        args: usize,
+    ) -> Option<&'b (Vec<&'a str>, Vec<MacroToken<'a>>)> {
         self.overloads
             .iter()
             .position(|(c, _)| c.len() == args)
-            .map(|i| (&self.overloads[i]))
+            .map(|i| &self.overloads[i])
+            .or_else(|| {
+                self.variadic
+                    .as_ref()
+                    .filter(|(params, _)| args + 1 >= params.len())
+            })
     }
 }
 
@@ -142,15 +160,19 @@ impl std::fmt::Debug for MacroSet<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let constant = match &self.constant {
             Some(def) => format!("  {:?}\n", def),
-            None => format!(""),
+            None => String::new(),
         };
         let overloads = self
             .overloads
             .iter()
             .map(|(params, def)| format!("  ({}) => {:?}\n", params.join(", "), def))
             .collect::<String>();
+        let variadic = match &self.variadic {
+            Some((params, def)) => format!("  ({}...) => {:?}\n", params.join(", "), def),
+            None => String::new(),
+        };
 
-        write!(f, "{}\n{}{}", self.name, constant, overloads)
+        write!(f, "{}\n{}{}{}", self.name, constant, overloads, variadic)
     }
 }
 
@@ -158,30 +180,111 @@ impl std::fmt::Debug for MacroSet<'_> {
 ///
 /// This is a simple convinience wrapper around a [HashMap] that provides helpful
 /// functions for inserting and retrieveing macro definitions.
-pub struct MacroTable<'a>(HashMap<&'a str, MacroSet<'a>>);
+pub struct MacroTable<'a> {
+    sets: HashMap<&'a str, MacroSet<'a>>,
+    /// Where each definition in the sets was made, by name, parameter count and
+    /// whether it is variadic.
+    defined: HashMap<(&'a str, Option<usize>, bool), &'a RawToken<'a>>,
+    /// How many times each macro has been expanded, by name, which outlives the
+    /// definitions.
+    expansions: BTreeMap<String, usize>,
+}
 
 impl<'a> MacroTable<'a> {
     /// Returns a new empty `MacroTable`.
     pub fn new() -> Self {
-        Self(HashMap::<&'a str, MacroSet<'a>>::new())
+        Self {
+            sets: HashMap::<&'a str, MacroSet<'a>>::new(),
+            defined: HashMap::new(),
+            expansions: BTreeMap::new(),
+        }
     }
 
     /// Returns the [`MacroSet`] for `name` if it exists.
     pub fn get<'b>(&'b self, name: &str) -> Option<&'b MacroSet<'a>> {
-        self.0.get(name)
+        self.sets.get(name)
     }
 
     /// Returns whether `name` has a corresponding [`MacroSet`] in the map.
     pub fn has_name(&self, name: &str) -> bool {
-        self.0.contains_key(name)
+        self.sets.contains_key(name)
     }
 
     /// Adds the given macro definition to the existing [`MacroSet`] or inserts a new one.
-    pub fn add_macro(&mut self, def: Macro<'a>) {
-        self.0
+    ///
+    /// Returns where the definition it replaces was made, if it replaces one which
+    /// was defined in the source.
+    pub fn add_macro(&mut self, def: Macro<'a>) -> Option<&'a RawToken<'a>> {
+        let key = (def.name, def.params.as_ref().map(Vec::len), def.variadic);
+        let previous = match def.token {
+            Some(token) => self.defined.insert(key, token),
+            None => self.defined.remove(&key),
+        };
+        self.sets
             .entry(def.name)
             .or_insert(MacroSet::new(def.name))
-            .add(def.params, def.def)
+            .add(def.params, def.variadic, def.def);
+        previous
+    }
+
+    /// Counts an expansion of the macro `name`.
+    fn expanded(&mut self, name: &str) {
+        *self.expansions.entry(name.to_string()).or_default() += 1;
+    }
+
+    /// Removes every definition of `name` and returns whether there were any.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.defined.retain(|(defined, _, _), _| *defined != name);
+        self.sets.remove(name).is_some()
+    }
+}
+
+/// Where `%include` and `.incbin` look for files.
+///
+/// A relative name is looked for next to the file which names it, and then in
+/// each search directory in order. The first file found is used.
+pub struct Includes {
+    search: Vec<PathBuf>,
+    /// The files being preprocessed, outermost first, to find include cycles.
+    stack: Vec<PathBuf>,
+}
+
+impl Includes {
+    /// Returns the includes of the file at `input`, which are looked for in
+    /// `search` after the directory of the including file.
+    pub fn new(input: &str, search: Vec<PathBuf>) -> Self {
+        Self {
+            search,
+            stack: vec![PathBuf::from(input)],
+        }
+    }
+
+    /// Finds the file a directive names, or reports every path which was tried.
+    fn resolve(&self, name: &RawToken, file: &str) -> Result<PathBuf, Diagnostic> {
+        let path = Path::new(file);
+        let dir = Path::new(name.source.file.name())
+            .parent()
+            .unwrap_or(Path::new(""));
+        let mut candidates = Vec::<PathBuf>::new();
+        if path.is_absolute() {
+            candidates.push(path.to_path_buf());
+        }
+        for dir in std::iter::once(dir).chain(self.search.iter().map(PathBuf::as_path)) {
+            let candidate = dir.join(path);
+            if !path.is_absolute() && !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+
+        if let Some(found) = candidates.iter().find(|candidate| candidate.is_file()) {
+            return Ok(found.clone());
+        }
+        let reason = format!("cannot find '{}'", file);
+        let mut error = error::syntax_error(&name.source, reason);
+        for candidate in candidates.iter() {
+            error = error.with_note(format!("looked for '{}'", candidate.display()));
+        }
+        Err(error)
     }
 }
 
@@ -189,35 +292,104 @@ impl<'a> MacroTable<'a> {
 //
 //
 
+/// The output of the preprocessor.
+pub struct Preprocessed<'a> {
+    pub tokens: Vec<RawToken<'a>>,
+    /// How many times each macro was expanded, by name.
+    pub expansions: BTreeMap<String, usize>,
+}
+
 pub fn preprocess<'a>(
     tokens: &'a [RawToken<'a>],
     predefs: Vec<Macro<'a>>,
-) -> Result<Vec<RawToken<'a>>, SyntaxError> {
-    if tokens.is_empty() {
-        return Ok(vec![]);
-    }
-
-    let mut tokens = &tokens[..];
+    includes: &mut Includes,
+    warnings: &mut Warnings,
+) -> Result<Preprocessed<'a>, Diagnostic> {
+    let mut tokens = tokens;
     let mut defs = MacroTable::new();
     for def in predefs {
         defs.add_macro(def);
     }
 
-    preprocess_tokens(&mut tokens, &mut defs)
+    let tokens = preprocess_tokens(&mut tokens, &mut defs, includes, warnings)?;
+    Ok(Preprocessed {
+        tokens,
+        expansions: defs.expansions,
+    })
 }
 
 fn preprocess_tokens<'f, 'a>(
     tokens: &'f mut &'a [RawToken<'a>],
     defs: &'f mut MacroTable<'a>,
-) -> Result<Vec<RawToken<'a>>, SyntaxError> {
+    includes: &mut Includes,
+    warnings: &mut Warnings,
+) -> Result<Vec<RawToken<'a>>, Diagnostic> {
     if tokens.is_empty() {
         return Ok(vec![]);
     }
 
     let mut out_tokens = Vec::<RawToken<'a>>::with_capacity(tokens.len());
+    let mut conditionals = Vec::<Conditional<'a>>::new();
     while let Some(token) = take_one(tokens) {
         let kind = &token.kind;
         let range = &token.source;
+        let active = conditionals.last().is_none_or(|c| c.active);
+        if kind.is_preprocessor() {
+            // drop the leading '%'
+            let directive = &range.value()[1..];
+            match directive {
+                // opens a conditional block
+                "if" | "ifdef" | "ifndef" => {
+                    let condition = if active {
+                        preprocess_condition(token, tokens, defs)?
+                    } else {
+                        // a block nested in a skipped block is never evaluated
+                        take_while(tokens, is_not_eol);
+                        skip_eol(tokens);
+                        false
+                    };
+                    conditionals.push(Conditional {
+                        token,
+                        enclosing: active,
+                        condition,
+                        has_else: false,
+                        active: active && condition,
+                    });
+                    continue;
+                }
+                "else" => {
+                    let conditional = match conditionals.last_mut() {
+                        Some(conditional) if !conditional.has_else => conditional,
+                        Some(_) => {
+                            let reason = "duplicate '%else' in conditional block".to_string();
+                            return Err(error::syntax_error(range, reason));
+                        }
+                        None => {
+                            let reason = "'%else' without a matching '%if'".to_string();
+                            return Err(error::syntax_error(range, reason));
+                        }
+                    };
+                    conditional.has_else = true;
+                    conditional.active = conditional.enclosing && !conditional.condition;
+                    preprocess_end_of_directive(tokens)?;
+                    continue;
+                }
+                "endif" => {
+                    if conditionals.pop().is_none() {
+                        let reason = "'%endif' without a matching '%if'".to_string();
+                        return Err(error::syntax_error(range, reason));
+                    }
+                    preprocess_end_of_directive(tokens)?;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if !active {
+            continue;
+        }
+
         match kind {
             RawTokenKind::PreProcessor => {
                 // drop the leading '%'
@@ -227,10 +399,55 @@ fn preprocess_tokens<'f, 'a>(
                     "define" => {
                         if let Some(def) = preprocess_define(tokens)? {
                             // TODO: check if macro is defined with and without parameters
-                            defs.add_macro(def);
+                            let token = def.token.unwrap();
+                            if let Some(previous) = defs.add_macro(def) {
+                                let reason =
+                                    format!("macro '{}' is redefined", token.source.value());
+                                let warning = error::warning(&token.source, reason).with_label(
+                                    &previous.source,
+                                    "previously defined here".to_string(),
+                                );
+                                warnings.warn(Lint::RedefinedMacro, warning);
+                            }
                         }
                         continue;
                     }
+                    // defines a macro as the value of an expression
+                    "assign" => {
+                        let def = preprocess_assign(token, tokens, defs)?;
+                        defs.add_macro(def);
+                        continue;
+                    }
+                    // repeats a block of lines
+                    "rep" => {
+                        let repeated = preprocess_repeat(token, tokens, defs, includes, warnings)?;
+                        out_tokens.extend(repeated);
+                        continue;
+                    }
+                    "endrep" => {
+                        let reason = "'%endrep' without a matching '%rep'".to_string();
+                        return Err(error::syntax_error(range, reason));
+                    }
+                    // preprocesses another file in place of the line
+                    "include" => {
+                        let included = preprocess_include(token, tokens, includes)?;
+                        includes.stack.push(included.0);
+                        let mut included = included.1;
+                        out_tokens.extend(preprocess_tokens(
+                            &mut included,
+                            defs,
+                            includes,
+                            warnings,
+                        )?);
+                        includes.stack.pop();
+                        continue;
+                    }
+                    // removes a macro, which is fine if it was never defined
+                    "undef" => {
+                        let name = preprocess_undef(token, tokens)?;
+                        defs.remove(name);
+                        continue;
+                    }
                     _ => {}
                 }
             }
@@ -238,11 +455,14 @@ fn preprocess_tokens<'f, 'a>(
                 let value = range.value();
                 if defs.has_name(value) {
                     let expanded = expand_macro(token, tokens, defs)?;
-                    out_tokens.extend(expanded.into_iter());
+                    out_tokens.extend(expanded);
                 } else {
                     out_tokens.push(token.clone())
                 }
             }
+            RawTokenKind::Directive if range.value() == ".incbin" => {
+                out_tokens.extend(preprocess_incbin(token, tokens, includes)?);
+            }
             RawTokenKind::Comment => {
                 // ignore
             }
@@ -250,9 +470,374 @@ fn preprocess_tokens<'f, 'a>(
         }
     }
 
+    if let Some(conditional) = conditionals.last() {
+        let source = &conditional.token.source;
+        let reason = "unterminated conditional block, expected '%endif'".to_string();
+        return Err(error::syntax_error(source, reason));
+    }
+
+    Ok(out_tokens)
+}
+
+/// An open conditional block.
+struct Conditional<'a> {
+    /// The directive which opened the block.
+    token: &'a RawToken<'a>,
+    /// Whether the code around the block is being kept.
+    enclosing: bool,
+    /// The result of the condition.
+    condition: bool,
+    has_else: bool,
+    /// Whether the code in the current branch is being kept.
+    active: bool,
+}
+
+/// Parses and evaluates the condition of a conditional directive.
+///
+/// ```text
+///     %ifdef name
+///     %ifndef name
+///     %if <const-expr>
+/// ```
+/// The expression of an `%if` is macro expanded and is true when it is non-zero.
+/// In it, `%defined(name)` is 1 if `name` is a macro and 0 if not, and the
+/// comparison and logical operators are 1 when true and 0 when false.
+/// ```text
+///     %if %defined(SIZE) && (SIZE == 4 || SIZE >= 16)
+/// ```
+fn preprocess_condition<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    defs: &mut MacroTable<'a>,
+) -> Result<bool, Diagnostic> {
+    skip_whitespace(tokens);
+    let name = &directive.source.value()[1..];
+    if name == "if" {
+        let value = preprocess_expression(directive, tokens, defs)?;
+        return Ok(value != 0);
+    }
+
+    let macro_name = match take_if(tokens, |t| t.is_identifier()) {
+        Some(token) => token.source.value(),
+        None => {
+            let source = &tokens.first().unwrap_or(directive).source;
+            let reason = "expected macro name".to_string();
+            return Err(error::syntax_error(source, reason));
+        }
+    };
+    preprocess_end_of_directive(tokens)?;
+    Ok(defs.has_name(macro_name) == (name == "ifdef"))
+}
+
+/// Macro expands and evaluates the constant expression which ends a directive.
+fn preprocess_expression<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    defs: &mut MacroTable<'a>,
+) -> Result<u32, Diagnostic> {
+    let mut line = take_while(tokens, is_not_eol);
+    skip_eol(tokens);
+    let mut expanded = Vec::<RawToken<'a>>::new();
+    while let Some(token) = take_one(&mut line) {
+        if token.is_preprocessor() && token.source.value() == "%defined" {
+            let name = preprocess_defined(token, &mut line)?;
+            let value = if defs.has_name(name) { "1" } else { "0" };
+            expanded.extend(synthesize(value, expansion_origin(token)));
+        } else if token.is_identifier() && defs.has_name(token.source.value()) {
+            expanded.extend(expand_macro(token, &mut line, defs)?);
+        } else {
+            expanded.push(token.clone());
+        }
+    }
+
+    let expr = expanded
+        .iter()
+        .filter_map(Token::from_raw_token)
+        .collect::<Vec<_>>();
+    if expr.is_empty() {
+        let reason = "expected an expression".to_string();
+        return Err(error::syntax_error(&directive.source, reason));
+    }
+    evaluate_constant(&expr[0], &expr)
+}
+
+/// Parses an `%assign`, which defines a macro constant as the value of an
+/// expression.
+/// ```text
+///     %assign SCREEN BASE + $400
+///     %assign count count + 1
+/// ```
+/// The expression is macro expanded and evaluated when the directive is reached,
+/// so unlike a `%define` the macro keeps its value when the macros it used are
+/// changed, and it can be assigned again from its own value.
+fn preprocess_assign<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    defs: &mut MacroTable<'a>,
+) -> Result<Macro<'a>, Diagnostic> {
+    skip_whitespace(tokens);
+    let name = match take_if(tokens, |t| t.is_identifier()) {
+        Some(token) => token,
+        None => {
+            let source = &tokens.first().unwrap_or(directive).source;
+            let reason = "expected macro name".to_string();
+            return Err(error::syntax_error(source, reason));
+        }
+    };
+    let value = preprocess_expression(directive, tokens, defs)?;
+    Ok(number_macro(name, value))
+}
+
+/// Returns a macro constant named by `name` which is replaced by `value`.
+fn number_macro<'a>(name: &'a RawToken<'a>, value: u32) -> Macro<'a> {
+    // the value is used wherever the macro is, so it lives as long as the source
+    let def = synthesize(&value.to_string(), expansion_origin(name));
+    let def: &'a [RawToken<'a>] = Box::leak(def.into_boxed_slice());
+    Macro::new_constant(name.source.value(), def.iter().collect())
+}
+
+/// Expands a `%rep` block, which is preprocessed once for each repetition.
+/// ```text
+///     %rep 4, i
+///         .db i * i
+///     %endrep
+/// ```
+/// If a name follows the count it is defined as a macro constant holding the
+/// number of the repetition, from zero, and removed after the block. Blocks can
+/// be nested, and any conditional blocks in them must end in the same block.
+fn preprocess_repeat<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    defs: &mut MacroTable<'a>,
+    includes: &mut Includes,
+    warnings: &mut Warnings,
+) -> Result<Vec<RawToken<'a>>, Diagnostic> {
+    skip_whitespace(tokens);
+    let header = take_while(tokens, is_not_eol);
+    skip_eol(tokens);
+
+    // the counter follows the last comma outside of any macro call
+    let mut depth = 0;
+    let mut comma = None;
+    for (i, token) in header.iter().enumerate() {
+        match token.kind {
+            RawTokenKind::LParen => depth += 1,
+            RawTokenKind::RParen => depth -= 1,
+            RawTokenKind::Comma if depth == 0 => comma = Some(i),
+            _ => {}
+        }
+    }
+    let (mut expr, counter) = match comma {
+        Some(i) => {
+            let mut rest = &header[i + 1..];
+            skip_whitespace(&mut rest);
+            let name = match take_if(&mut rest, |t| t.is_identifier()) {
+                Some(name) => name,
+                None => {
+                    let source = &rest.first().unwrap_or(&header[i]).source;
+                    let reason = "expected a counter name".to_string();
+                    return Err(error::syntax_error(source, reason));
+                }
+            };
+            preprocess_end_of_directive(&mut rest)?;
+            (&header[..i], Some(name))
+        }
+        None => (header, None),
+    };
+    let count = preprocess_expression(directive, &mut expr, defs)?;
+    // expressions wrap around, so a negative count would otherwise be huge
+    if (count as i32) < 0 {
+        let reason = format!("repeat count {} is negative", count as i32);
+        return Err(error::syntax_error(&directive.source, reason));
+    }
+    if count > REPEAT_LIMIT {
+        let reason = format!(
+            "repeat count {} is over the limit of {}",
+            count, REPEAT_LIMIT
+        );
+        return Err(error::syntax_error(&directive.source, reason));
+    }
+
+    let mut depth = 0;
+    let body = take_while(tokens, |t| match t.source.value() {
+        "%rep" if t.is_preprocessor() => {
+            depth += 1;
+            true
+        }
+        "%endrep" if t.is_preprocessor() => {
+            depth -= 1;
+            depth >= 0
+        }
+        _ => true,
+    });
+    if take_one(tokens).is_none() {
+        let reason = "unterminated repeat block, expected '%endrep'".to_string();
+        return Err(error::syntax_error(&directive.source, reason));
+    }
+    preprocess_end_of_directive(tokens)?;
+
+    let mut out_tokens = Vec::<RawToken<'a>>::new();
+    for i in 0..count {
+        if let Some(name) = counter {
+            defs.add_macro(number_macro(name, i));
+        }
+        out_tokens.extend(preprocess_tokens(&mut &body[..], defs, includes, warnings)?);
+    }
+    if let Some(name) = counter {
+        defs.remove(name.source.value());
+    }
     Ok(out_tokens)
 }
 
+/// Reads the file an `%include` names, and returns its path along with its
+/// tokens.
+/// ```text
+///     %include "macros.inc"
+/// ```
+/// The tokens refer back to the directive, so the lines they assemble to are
+/// listed on the line of the `%include`.
+fn preprocess_include<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    includes: &Includes,
+) -> Result<(PathBuf, &'a [RawToken<'a>]), Diagnostic> {
+    let (name, path) = preprocess_file_name(directive, tokens, includes)?;
+    preprocess_end_of_directive(tokens)?;
+
+    let canonical = fs::canonicalize(&path).ok();
+    let including = |file: &PathBuf| fs::canonicalize(file).ok() == canonical;
+    if let Some(start) = includes.stack.iter().position(including) {
+        let cycle = includes.stack[start..]
+            .iter()
+            .chain(std::iter::once(&path))
+            .map(|file| file.display().to_string())
+            .collect::<Vec<_>>();
+        let reason = format!("include cycle: {}", cycle.join(" -> "));
+        return Err(error::syntax_error(&name.source, reason));
+    }
+
+    let file = File::include(&path).map_err(|err| {
+        let reason = format!("{}: {}", path.display(), err);
+        error::syntax_error(&name.source, reason)
+    })?;
+    let origin = expansion_origin(directive);
+    let mut included = file
+        .lex_tokens()
+        .into_iter()
+        .map(|t| RawToken {
+            kind: t.kind,
+            source: SourceRef::new_from_origin(t.source.file, t.source.span, origin),
+        })
+        .collect::<Vec<_>>();
+    // the line after the directive must not join the last line of the file
+    if !included.last().is_none_or(|t| t.is_newline()) {
+        included.extend(synthesize("\n", origin));
+    }
+    Ok((path, Box::leak(included.into_boxed_slice())))
+}
+
+/// Replaces an `.incbin` with the bytes of the file it names.
+/// ```text
+///     font:   .incbin "font.bin"
+/// ```
+fn preprocess_incbin<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    includes: &Includes,
+) -> Result<Vec<RawToken<'a>>, Diagnostic> {
+    let (name, path) = preprocess_file_name(directive, tokens, includes)?;
+    let bytes = fs::read(&path).map_err(|err| {
+        let reason = format!("{}: {}", path.display(), err);
+        error::syntax_error(&name.source, reason)
+    })?;
+    if bytes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let values = bytes
+        .iter()
+        .map(|byte| format!("${:02x}", byte))
+        .collect::<Vec<_>>();
+    Ok(synthesize(
+        &format!(".db {}", values.join(", ")),
+        expansion_origin(directive),
+    ))
+}
+
+/// Parses the quoted name of the file a directive reads, and finds it.
+fn preprocess_file_name<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    includes: &Includes,
+) -> Result<(&'a RawToken<'a>, PathBuf), Diagnostic> {
+    skip_whitespace(tokens);
+    let (name, file) = match take_one(tokens) {
+        Some(
+            name @ RawToken {
+                kind: RawTokenKind::String(file),
+                ..
+            },
+        ) => (name, file),
+        _ => {
+            let reason = format!(
+                "expected a file name in quotes after '{}'",
+                directive.source.value()
+            );
+            return Err(error::syntax_error(&directive.source, reason));
+        }
+    };
+    let path = includes.resolve(name, file)?;
+    Ok((name, path))
+}
+
+/// Parses the name in a `%defined(name)` operator.
+fn preprocess_defined<'a>(
+    operator: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+) -> Result<&'a str, Diagnostic> {
+    let expected = || {
+        let source = &operator.source;
+        let reason = "expected a macro name in '%defined(...)'".to_string();
+        error::syntax_error(source, reason)
+    };
+
+    skip_whitespace(tokens);
+    take_if(tokens, |t| t.is_lparen()).ok_or_else(expected)?;
+    skip_whitespace(tokens);
+    let name = take_if(tokens, |t| t.is_identifier()).ok_or_else(expected)?;
+    skip_whitespace(tokens);
+    take_if(tokens, |t| t.is_rparen()).ok_or_else(expected)?;
+    Ok(name.source.value())
+}
+
+/// Parses the name of the macro an `%undef` removes.
+fn preprocess_undef<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+) -> Result<&'a str, Diagnostic> {
+    skip_whitespace(tokens);
+    let name = match take_if(tokens, |t| t.is_identifier()) {
+        Some(token) => token.source.value(),
+        None => {
+            let source = &tokens.first().unwrap_or(directive).source;
+            let reason = "expected macro name".to_string();
+            return Err(error::syntax_error(source, reason));
+        }
+    };
+    preprocess_end_of_directive(tokens)?;
+    Ok(name)
+}
+
+/// Ensures nothing but whitespace or a comment follows a directive.
+fn preprocess_end_of_directive(tokens: &mut &[RawToken]) -> Result<(), Diagnostic> {
+    skip_whitespace(tokens);
+    if let Some(token) = take_if(tokens, is_not_eol) {
+        return Err(error::unexpected_token(token, "preprocessor directive"));
+    }
+    skip_eol(tokens);
+    Ok(())
+}
+
 /// Parses a preprocessor macro definition.
 ///
 /// A macro definition can either be a constant or function. All macro forms terminate
@@ -271,11 +856,15 @@ fn preprocess_tokens<'f, 'a>(
 ///     %define name()
 ///     %define name(a)
 ///     %define name(a, b) (a + b)
+///     %define name(a, rest...) a, rest
 /// ```
+/// A trailing `...` makes the last parameter variadic, so it takes the remaining
+/// arguments separated by commas. In the body of a macro function, `%str(a)` is
+/// replaced by the argument of `a` as a string and `##` joins the tokens on either
+/// side of it into one.
+///
 /// *note* - If a space follows the macro name it will be interpreted as a constant.
-fn preprocess_define<'f, 'a>(
-    tokens: &'f mut &'a [RawToken<'a>],
-) -> Result<Option<Macro<'a>>, SyntaxError> {
+fn preprocess_define<'a>(tokens: &mut &'a [RawToken<'a>]) -> Result<Option<Macro<'a>>, Diagnostic> {
     skip_whitespace(tokens);
 
     if tokens.first().map(is_eol).unwrap_or(true) {
@@ -284,55 +873,55 @@ fn preprocess_define<'f, 'a>(
         return Ok(None);
     }
 
-    let name = take_one(tokens).unwrap();
-    if !name.is_identifier() {
-        let loc = name.source.start_loc();
-        let reason = format!("expected macro name");
-        return Err(SyntaxError::new(loc, reason));
-    }
-
-    let name = name.source.value();
-    if let Some(token) = tokens.first() {
-        if is_eol(token) {
-            // empty macro
-            return Ok(Some(Macro::new(name, None, vec![])));
-        } else if token.is_whitespace() {
-            // macro constant
-            return preprocess_define_const(name, tokens).map(|r| Some(r));
-        } else if token.is_lparen() {
-            // macro function
-            return preprocess_define_func(name, tokens).map(|r| Some(r));
-        } else {
-            // unexpected token
-            let loc = token.source.start_loc();
-            let reason = format!("unexpected token");
-            return Err(SyntaxError::new(loc, reason));
-        }
+    let token = take_one(tokens).unwrap();
+    if !token.is_identifier() {
+        let source = &token.source;
+        let reason = "expected macro name".to_string();
+        return Err(error::syntax_error(source, reason));
     }
 
-    Ok(Some(Macro::new(name, None, vec![])))
+    let name = token.source.value();
+    let def = match tokens.first() {
+        // empty macro
+        None => Macro::new(name, None, vec![]),
+        Some(next) if is_eol(next) => Macro::new(name, None, vec![]),
+        // macro constant
+        Some(next) if next.is_whitespace() => preprocess_define_const(name, tokens)?,
+        // macro function
+        Some(next) if next.is_lparen() => preprocess_define_func(name, tokens)?,
+        Some(next) => {
+            // unexpected token
+            let source = &next.source;
+            let reason = "unexpected token".to_string();
+            return Err(error::syntax_error(source, reason));
+        }
+    };
+    Ok(Some(Macro {
+        token: Some(token),
+        ..def
+    }))
 }
 
 /// Parses a preprocessor macro constant definition.
-fn preprocess_define_const<'f, 'a>(
+fn preprocess_define_const<'a>(
     name: &'a str,
-    tokens: &'f mut &'a [RawToken<'a>],
-) -> Result<Macro<'a>, SyntaxError> {
+    tokens: &mut &'a [RawToken<'a>],
+) -> Result<Macro<'a>, Diagnostic> {
     skip_whitespace(tokens);
 
     // parse definition
     let def = take_while(tokens, is_not_eol);
     skip_eol(tokens);
 
-    let def = def.iter().map(|t| MacroToken::Token(t)).collect::<Vec<_>>();
+    let def = def.iter().map(MacroToken::Token).collect::<Vec<_>>();
     Ok(Macro::new(name, None, def))
 }
 
 /// Parses a preprocessor macro function definition.
-fn preprocess_define_func<'f, 'a>(
+fn preprocess_define_func<'a>(
     name: &'a str,
-    tokens: &'f mut &'a [RawToken<'a>],
-) -> Result<Macro<'a>, SyntaxError> {
+    tokens: &mut &'a [RawToken<'a>],
+) -> Result<Macro<'a>, Diagnostic> {
     // skip the '(' token
     let lparen = take_one(tokens).unwrap();
     assert!(lparen.is_lparen());
@@ -340,6 +929,7 @@ fn preprocess_define_func<'f, 'a>(
     // parse params
     skip_whitespace(tokens);
     let mut params = Vec::<&str>::new();
+    let mut variadic = false;
     'outer: while let Some(param) = take_if(tokens, is_not_eol) {
         if param.is_rparen() {
             break;
@@ -356,6 +946,23 @@ fn preprocess_define_func<'f, 'a>(
         params.push(param_name);
 
         skip_whitespace(tokens);
+        if take_if(tokens, |t| t.is_ellipsis()).is_some() {
+            // a variadic parameter must be the last one
+            variadic = true;
+            skip_whitespace(tokens);
+            match take_if(tokens, is_not_eol) {
+                Some(next) if next.is_rparen() => break 'outer,
+                Some(next) => {
+                    let err = error::unexpected_token(next, "macro parameter list");
+                    return Err(err);
+                }
+                None => {
+                    let err = error::expected_delimiter(")", lparen, "macro parameter list");
+                    return Err(err);
+                }
+            }
+        }
+
         match take_if(tokens, is_not_eol) {
             Some(next) => {
                 if next.is_rparen() {
@@ -377,21 +984,59 @@ fn preprocess_define_func<'f, 'a>(
 
     // parse definition
     skip_whitespace(tokens);
-    let def = take_while(tokens, is_not_eol);
+    let mut def_tokens = take_while(tokens, is_not_eol);
     skip_eol(tokens);
 
-    let def = def
-        .iter()
-        .map(|t| {
-            if params.contains(&t.source.value()) {
-                MacroToken::Parameter(t)
-            } else {
-                MacroToken::Token(t)
+    let mut def = Vec::<MacroToken<'a>>::with_capacity(def_tokens.len());
+    while let Some(t) = take_one(&mut def_tokens) {
+        if t.is_preprocessor() && t.source.value() == "%str" {
+            def.push(preprocess_stringize(t, &mut def_tokens, &params)?);
+        } else if t.is_concat() {
+            // the operands of '##' are joined without the whitespace around it
+            while matches!(def.last(), Some(MacroToken::Token(prev)) if prev.is_whitespace()) {
+                def.pop();
             }
-        })
-        .collect::<Vec<_>>();
+            skip_whitespace(&mut def_tokens);
+            if def.is_empty() || def_tokens.is_empty() {
+                let source = &t.source;
+                let reason = "'##' cannot be at either end of a macro definition".to_string();
+                return Err(error::syntax_error(source, reason));
+            }
+            def.push(MacroToken::Concat);
+        } else if params.contains(&t.source.value()) {
+            def.push(MacroToken::Parameter(t));
+        } else {
+            def.push(MacroToken::Token(t));
+        }
+    }
 
-    Ok(Macro::new(name, Some(params), def))
+    Ok(Macro {
+        name,
+        params: Some(params),
+        variadic,
+        def,
+        token: None,
+    })
+}
+
+/// Parses the parameter of a `%str(name)` operator.
+fn preprocess_stringize<'a>(
+    directive: &'a RawToken<'a>,
+    tokens: &mut &'a [RawToken<'a>],
+    params: &[&'a str],
+) -> Result<MacroToken<'a>, Diagnostic> {
+    let expected = || {
+        let source = &directive.source;
+        let reason = "expected a macro parameter in '%str(...)'".to_string();
+        error::syntax_error(source, reason)
+    };
+
+    take_if(tokens, |t| t.is_lparen()).ok_or_else(expected)?;
+    skip_whitespace(tokens);
+    let param = take_if(tokens, |t| params.contains(&t.source.value())).ok_or_else(expected)?;
+    skip_whitespace(tokens);
+    take_if(tokens, |t| t.is_rparen()).ok_or_else(expected)?;
+    Ok(MacroToken::Stringize(param))
 }
 
 /// Fully expands a preprocessor macro into its final replacement.
@@ -404,8 +1049,8 @@ fn preprocess_define_func<'f, 'a>(
 fn expand_macro<'f, 'a, 'b>(
     token: &'a RawToken<'a>,
     tokens: &'f mut &'b [RawToken<'a>],
-    defs: &'f MacroTable<'a>,
-) -> Result<Vec<RawToken<'a>>, SyntaxError> {
+    defs: &'f mut MacroTable<'a>,
+) -> Result<Vec<RawToken<'a>>, Diagnostic> {
     assert!(token.is_identifier());
     let name = token.source.value().to_owned();
     let macroset = defs.get(&name).unwrap();
@@ -414,17 +1059,18 @@ fn expand_macro<'f, 'a, 'b>(
     if expanded.is_none() {
         return Ok(vec![token.clone()]);
     }
+    defs.expanded(&name);
 
     let mut out_tokens = Vec::<RawToken<'a>>::new();
     let mut working = vec![Rc::new(expanded.unwrap())];
-    'outer: while let Some(temp) = working.last().map(|v| Rc::clone(v)) {
+    'outer: while let Some(temp) = working.last().map(Rc::clone) {
         if working.len() > RECURSION_LIMIT {
-            let loc = token.source.start_loc();
+            let source = &token.source;
             let reason = format!(
                 "recursion limit reached during expansion of macro '{}'",
                 name
             );
-            let err = error::syntax_error(loc, reason);
+            let err = error::syntax_error(source, reason);
             return Err(err);
         }
 
@@ -434,6 +1080,7 @@ fn expand_macro<'f, 'a, 'b>(
             if token.is_identifier() && defs.has_name(value) {
                 let macroset = defs.get(value).unwrap();
                 if let Some(expanded) = expand_macro_once(token, tokens, macroset)? {
+                    defs.expanded(value);
                     let index = working.len() - 1;
                     working[index] = Rc::new(tokens.to_vec());
                     working.push(Rc::new(expanded));
@@ -455,7 +1102,7 @@ fn expand_macro_once<'f, 'a, 'b>(
     token: &'b RawToken<'a>,
     tokens: &'f mut &'b [RawToken<'a>],
     defs: &'f MacroSet<'a>,
-) -> Result<Option<Vec<RawToken<'a>>>, SyntaxError> {
+) -> Result<Option<Vec<RawToken<'a>>>, Diagnostic> {
     assert!(token.is_identifier());
 
     // check to see if this could be a macro function
@@ -466,11 +1113,15 @@ fn expand_macro_once<'f, 'a, 'b>(
         // this might be a function call
         let args = collect_macro_args(lparen, tokens)?;
         if let Some((params, def)) = defs.get_overload(args.len()) {
-            Ok(Some(expand_macro_func(token, args, params, def)))
+            expand_macro_func(token, args, params, def).map(Some)
         } else {
             // no matching overload
-            // TODO: print warning?
-            panic!("invalid macro call")
+            let reason = format!(
+                "no definition of macro '{}' takes {} arguments",
+                defs.name,
+                args.len()
+            );
+            Err(error::syntax_error(&token.source, reason))
         }
     } else if let Some(def) = defs.get_constant() {
         Ok(Some(expand_macro_const(token, def)))
@@ -480,19 +1131,22 @@ fn expand_macro_once<'f, 'a, 'b>(
 }
 
 /// Expands a constant macro definition.
-fn expand_macro_const<'f, 'a, 'b>(
+fn expand_macro_const<'a, 'b>(
     token: &'b RawToken<'a>,
     def: &'b Vec<MacroToken<'a>>,
 ) -> Vec<RawToken<'a>> {
     assert!(token.is_identifier());
 
+    let origin = expansion_origin(token);
     let tokens = def
         .iter()
         .map(|t| match t {
-            MacroToken::Parameter(_) => panic!("unexpected parameter in macro constant"),
+            MacroToken::Parameter(_) | MacroToken::Stringize(_) | MacroToken::Concat => {
+                panic!("unexpected parameter in macro constant")
+            }
             MacroToken::Token(t) => {
                 let kind = t.kind.clone();
-                let source = SourceRef::new(t.file(), t.source.span);
+                let source = SourceRef::new_from_origin(t.file(), t.source.span, origin);
                 RawToken { kind, source }
             }
         })
@@ -502,48 +1156,121 @@ fn expand_macro_const<'f, 'a, 'b>(
 }
 
 /// Expands a function macro definition.
+///
+/// The last parameter takes every argument from its position on, so for a variadic
+/// macro it expands to the remaining arguments separated by commas.
 fn expand_macro_func<'f, 'a, 'b>(
     token: &'b RawToken<'a>,
     args: Vec<&'b [RawToken<'a>]>,
     params: &'f Vec<&'a str>,
     def: &'b Vec<MacroToken<'a>>,
-) -> Vec<RawToken<'a>> {
+) -> Result<Vec<RawToken<'a>>, Diagnostic> {
     assert!(token.is_identifier());
-    assert!(args.len() == params.len());
+    assert!(args.len() + 1 >= params.len());
+
+    let origin = expansion_origin(token);
+    let argument = |name: &str| -> Vec<RawToken<'a>> {
+        let index = params.iter().position(|p| *p == name).unwrap();
+        let args = if index + 1 == params.len() {
+            &args[index..]
+        } else {
+            &args[index..=index]
+        };
+
+        let mut tokens = Vec::<RawToken<'a>>::new();
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                tokens.extend(synthesize(", ", origin));
+            }
+            // arguments keep their own location, they were written at the call
+            tokens.extend(arg.iter().cloned());
+        }
+        tokens
+    };
 
     let mut tokens = Vec::<RawToken<'a>>::with_capacity(def.len());
+    let mut paste = None;
     for t in def {
+        let start = tokens.len();
         match t {
             MacroToken::Parameter(def_tok) => {
-                let index = params
-                    .iter()
-                    .position(|p| *p == def_tok.source.value())
-                    .unwrap();
-
-                for arg_tok in args[index] {
-                    let kind = arg_tok.kind.clone();
-                    let file = arg_tok.file();
-                    let source = SourceRef::new(file, arg_tok.source.span);
-                    tokens.push(RawToken { kind, source })
+                tokens.extend(argument(def_tok.source.value()));
+            }
+            MacroToken::Stringize(def_tok) => {
+                let value = tokens::to_string(&argument(def_tok.source.value()));
+                let value = format!("\"{}\"", value.trim());
+                let string = synthesize(&value, origin);
+                if !matches!(&string[..], [t] if t.is_literal()) {
+                    let source = &token.source;
+                    let reason = format!("cannot make a string from {}", value);
+                    return Err(error::syntax_error(source, reason));
                 }
+                tokens.extend(string);
+            }
+            MacroToken::Concat => {
+                paste = Some(start);
+                continue;
             }
             MacroToken::Token(def_tok) => {
                 let kind = def_tok.kind.clone();
                 let file = def_tok.file();
-                let source = SourceRef::new(file, def_tok.source.span);
+                let source = SourceRef::new_from_origin(file, def_tok.source.span, origin);
                 tokens.push(RawToken { kind, source })
             }
         }
+
+        // join the last token before '##' with the first token after it
+        if let Some(index) = paste.take() {
+            if index == 0 || index == tokens.len() {
+                continue;
+            }
+            let value = format!(
+                "{}{}",
+                tokens[index - 1].source.value(),
+                tokens[index].source.value()
+            );
+            let mut pasted = synthesize(&value, origin);
+            if pasted.len() != 1 {
+                let source = &token.source;
+                let reason = format!("pasting with '##' does not form a valid token: {}", value);
+                return Err(error::syntax_error(source, reason));
+            }
+            tokens.splice(index - 1..=index, pasted.pop());
+        }
     }
 
-    tokens
+    Ok(tokens)
+}
+
+/// Returns the location that tokens expanded from the macro called at `call` refer
+/// back to.
+///
+/// The location of the call is kept along with its own origin, so a macro used by
+/// another macro produces a chain of calls leading back to the source file. Each
+/// location lives until the program exits, like the tokens which refer to it.
+fn expansion_origin<'a>(call: &RawToken<'a>) -> &'a SourceRef<'a> {
+    Box::leak(Box::new(call.source.clone()))
+}
+
+/// Lexes text produced during a macro expansion.
+///
+/// The new tokens have no place in a source file, so they point back at the call.
+fn synthesize<'a>(text: &str, origin: &'a SourceRef<'a>) -> Vec<RawToken<'a>> {
+    File::from_macro(text.to_string())
+        .lex_tokens()
+        .into_iter()
+        .map(|t| RawToken {
+            kind: t.kind,
+            source: SourceRef::new_from_origin(t.source.file, t.source.span, origin),
+        })
+        .collect()
 }
 
 /// Collects the arguments that will be used in a macro expansion.
-fn collect_macro_args<'f, 'a, 'b>(
+fn collect_macro_args<'a, 'b>(
     lparen: &'b RawToken<'a>,
-    tokens: &'f mut &'b [RawToken<'a>],
-) -> Result<Vec<&'b [RawToken<'a>]>, SyntaxError> {
+    tokens: &mut &'b [RawToken<'a>],
+) -> Result<Vec<&'b [RawToken<'a>]>, Diagnostic> {
     assert!(lparen.is_lparen());
 
     let mut args = Vec::<&[RawToken]>::new();
@@ -571,9 +1298,9 @@ fn collect_macro_args<'f, 'a, 'b>(
     Ok(args)
 }
 
-fn take_macro_arg<'f, 'a, 'b>(
-    tokens: &'f mut &'b [RawToken<'a>],
-) -> Result<&'b [RawToken<'a>], SyntaxError> {
+fn take_macro_arg<'a, 'b>(
+    tokens: &mut &'b [RawToken<'a>],
+) -> Result<&'b [RawToken<'a>], Diagnostic> {
     skip_whitespace(tokens);
 
     let mut parens = Vec::<&RawToken>::new();
@@ -610,15 +1337,15 @@ fn is_not_eol<'f, 'a>(token: &'f RawToken<'a>) -> bool {
     !(token.is_comment() || token.is_newline())
 }
 
-fn skip_eol<'f, 'a>(tokens: &'f mut &'a [RawToken]) {
-    if let Some(_) = take_if(tokens, |t| t.is_comment()) {
+fn skip_eol(tokens: &mut &[RawToken]) {
+    if take_if(tokens, |t| t.is_comment()).is_some() {
         take_if(tokens, |t| t.is_newline());
     } else {
         take_if(tokens, |t| t.is_newline());
     }
 }
 
-fn skip_whitespace<'f, 'a, 'b>(rest: &'f mut &'b [RawToken<'a>]) -> Option<&'b RawToken<'a>> {
+fn skip_whitespace<'a, 'b>(rest: &mut &'b [RawToken<'a>]) -> Option<&'b RawToken<'a>> {
     let mut prev = rest.first();
     while let Some(token) = rest.first() {
         if !token.is_whitespace() {
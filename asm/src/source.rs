@@ -1,15 +1,9 @@
-use std::{borrow::Cow, collections::HashMap, fs, io, io::Read, iter::Iterator, ops, ops::Deref};
+use std::{collections::HashMap, fs, io, io::Read, iter::Iterator, ops, ops::Deref, path::Path};
 
-use lazy_static::lazy_static;
 use logos::Logos;
 
 use crate::token::{RawToken, RawTokenKind};
 
-lazy_static! {
-    static ref EMPTY_FILE: File = File::new("<empty>".to_string(), "".to_string());
-    static ref MACRO_FILE: File = File::new("<macro>".to_string(), "".to_string());
-}
-
 pub struct SourceMap {
     /// A map between file names and source file.
     files: HashMap<String, File>,
@@ -22,12 +16,6 @@ impl SourceMap {
         }
     }
 
-    pub fn add_from_string(&mut self, name: &str, source: &str) -> &File {
-        let file = File::new(name.to_owned(), source.to_owned());
-        self.files.insert(name.to_owned(), file);
-        self.files.get(name).unwrap()
-    }
-
     pub fn add_from_path<'a>(&'a mut self, path: &'a str) -> Result<&'a File, io::Error> {
         let mut source_file = fs::File::open(path)?;
         let mut source = String::new();
@@ -45,8 +33,22 @@ pub struct File {
 }
 
 impl File {
-    pub fn empty() -> &'static Self {
-        &EMPTY_FILE
+    /// Returns a file holding text made by a macro expansion, such as a pasted token.
+    ///
+    /// The file lives until the program exits so tokens can borrow from it like any
+    /// other source file.
+    pub fn from_macro(source: String) -> &'static Self {
+        Box::leak(Box::new(Self::new("<macro>".to_string(), source)))
+    }
+
+    /// Reads a file included by another.
+    ///
+    /// The file lives until the program exits, like the files holding macro
+    /// expansions.
+    pub fn include(path: &Path) -> Result<&'static Self, io::Error> {
+        let source = fs::read_to_string(path)?;
+        let file = Self::new(path.to_string_lossy().into_owned(), source);
+        Ok(Box::leak(Box::new(file)))
     }
 
     pub fn new(name: String, source: String) -> Self {
@@ -62,10 +64,6 @@ impl File {
         &self.name
     }
 
-    pub fn source(&self) -> &str {
-        &self.source
-    }
-
     pub fn line_count(&self) -> usize {
         self.lines.len()
     }
@@ -96,14 +94,6 @@ impl File {
         }
     }
 
-    pub fn get_line_span(&self, line: usize) -> Option<Span> {
-        if line < self.lines.len() {
-            Some(self.lines[line])
-        } else {
-            None
-        }
-    }
-
     pub fn lookup_by_index<'a>(&'a self, index: usize) -> Option<Loc<'a>> {
         let line = self
             .lines
@@ -133,7 +123,6 @@ impl File {
         let lexer = RawTokenKind::lexer(&self.source);
         lexer
             .spanned()
-            .into_iter()
             .map(|(kind, span)| {
                 let span = Span::from(span);
                 let source = self.get_source_ref(span).unwrap();
@@ -144,7 +133,7 @@ impl File {
 
     //
 
-    fn parse_to_lines<'a>(source: &String) -> Vec<Span> {
+    fn parse_to_lines(source: &str) -> Vec<Span> {
         let source_ptr = source.as_ptr() as usize;
         source
             .split("\n")
@@ -198,10 +187,6 @@ impl<'a> SourceRef<'a> {
     pub fn start_loc(&self) -> Loc<'a> {
         self.file.lookup_by_index(self.span.start).unwrap()
     }
-
-    pub fn end_loc(&self) -> Loc<'a> {
-        self.file.lookup_by_index(self.span.end).unwrap()
-    }
 }
 
 impl std::fmt::Debug for SourceRef<'_> {
@@ -244,11 +229,11 @@ impl Span {
     }
 }
 
-impl Into<ops::Range<usize>> for Span {
-    fn into(self) -> ops::Range<usize> {
+impl From<Span> for ops::Range<usize> {
+    fn from(val: Span) -> Self {
         ops::Range {
-            start: self.start,
-            end: self.end,
+            start: val.start,
+            end: val.end,
         }
     }
 }
@@ -328,59 +313,6 @@ impl std::fmt::Display for LineColumn {
     }
 }
 
-//
-//
-//
-
-/// A copy-on-write string.
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub struct StrRef<'a>(Cow<'a, str>);
-
-impl<'a> Deref for StrRef<'a> {
-    type Target = Cow<'a, str>;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl<'a, S> From<S> for StrRef<'a>
-where
-    S: Into<&'a str>,
-{
-    fn from(string: S) -> Self {
-        Self {
-            0: Cow::Borrowed(string.into()),
-        }
-    }
-}
-
-impl<'a> PartialEq<str> for StrRef<'a> {
-    fn eq(&self, other: &str) -> bool {
-        self.deref() == other
-    }
-}
-
-impl std::fmt::Display for StrRef<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.deref())
-    }
-}
-
-impl std::fmt::Debug for StrRef<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let string = self.deref();
-        let escaped = string
-            .chars()
-            .fold(String::with_capacity(string.len()), |acc, c| match c {
-                '\n' => acc + "\\n",
-                '\t' => acc + "\\t",
-                ' ' => acc + "⎕",
-                _ => format!("{}{}", acc, c),
-            });
-        write!(f, "{}", escaped)
-    }
-}
-
 //
 
 pub struct SliceView<'a, T> {
@@ -423,7 +355,7 @@ impl<'a, T> SliceView<'a, T> {
         }
     }
 
-    pub fn drop_while<'b, F>(&'b mut self, pred: F) -> &'b [T]
+    pub fn drop_while<F>(&mut self, pred: F) -> &[T]
     where
         F: Fn(&T) -> bool,
     {
@@ -455,8 +387,8 @@ impl<'a, T> From<&'a [T]> for SliceView<'a, T> {
     }
 }
 
-impl<'a, T> Into<&'a [T]> for SliceView<'a, T> {
-    fn into(self) -> &'a [T] {
-        self.slice
+impl<'a, T> From<SliceView<'a, T>> for &'a [T] {
+    fn from(val: SliceView<'a, T>) -> Self {
+        val.slice
     }
 }
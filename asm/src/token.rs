@@ -3,12 +3,12 @@ use std::ops::Deref;
 use logos::{Lexer, Logos};
 
 use crate::source::File;
-use crate::source::{SourceRef, Span};
+use crate::source::SourceRef;
 
 pub trait TokenLike<'source>: Sized {
     type Kind: ?Sized + Clone + PartialEq;
 
-    fn kind<'a>(&'a self) -> &'a Self::Kind;
+    fn kind(&self) -> &Self::Kind;
     fn source<'a>(&'a self) -> &'a SourceRef<'source>;
     fn file<'a>(&'a self) -> &'source File;
 
@@ -20,7 +20,7 @@ macro_rules! impl_TokenLike {
         impl<'source> TokenLike<'source> for $token<'source> {
             type Kind = $kind;
 
-            fn kind<'a>(&'a self) -> &'a Self::Kind {
+            fn kind(&self) -> &Self::Kind {
                 &self.kind
             }
 
@@ -47,21 +47,21 @@ macro_rules! impl_TokenLike {
 pub struct Token<'source> {
     pub kind: TokenKind,
     pub source: SourceRef<'source>,
+    /// The name the token refers to, which is its text except for a local label,
+    /// which is named after the label it belongs to.
+    pub name: &'source str,
 }
 
 impl<'source> Token<'source> {
     pub fn from_raw_token<'a>(token: &'a RawToken<'source>) -> Option<Token<'source>> {
         if let Some(kind) = TokenKind::from_raw_token(&token.kind) {
             let source = token.source.clone();
-            Some(Token { kind, source })
+            let name = source.value();
+            Some(Token { kind, source, name })
         } else {
             None
         }
     }
-
-    pub fn value(&'source self) -> &'source str {
-        self.source.value()
-    }
 }
 
 impl_TokenLike!(Token, TokenKind);
@@ -93,13 +93,13 @@ pub enum TokenKind {
 }
 
 impl TokenKind {
-    pub fn from_raw_token<'a>(token: &RawTokenKind) -> Option<Self> {
+    pub fn from_raw_token(token: &RawTokenKind) -> Option<Self> {
         match token {
             RawTokenKind::Directive => Some(Self::Directive),
             RawTokenKind::Identifier => Some(Self::Identifier),
 
-            RawTokenKind::Number(v) => Some(Self::Literal(LitKind::Number(v.clone()))),
-            RawTokenKind::Char(v) => Some(Self::Literal(LitKind::Char(v.clone()))),
+            RawTokenKind::Number(v) => Some(Self::Literal(LitKind::Number(*v))),
+            RawTokenKind::Char(v) => Some(Self::Literal(LitKind::Char(*v))),
             RawTokenKind::String(v) => Some(Self::Literal(LitKind::String(v.clone()))),
 
             RawTokenKind::Add => Some(Self::Operator(OpKind::Add)),
@@ -113,6 +113,15 @@ impl TokenKind {
             RawTokenKind::Xor => Some(Self::Operator(OpKind::Xor)),
             RawTokenKind::Shl => Some(Self::Operator(OpKind::Shl)),
             RawTokenKind::Shr => Some(Self::Operator(OpKind::Shr)),
+            RawTokenKind::Eq => Some(Self::Operator(OpKind::Eq)),
+            RawTokenKind::Ne => Some(Self::Operator(OpKind::Ne)),
+            RawTokenKind::Lt => Some(Self::Operator(OpKind::Lt)),
+            RawTokenKind::Le => Some(Self::Operator(OpKind::Le)),
+            RawTokenKind::Gt => Some(Self::Operator(OpKind::Gt)),
+            RawTokenKind::Ge => Some(Self::Operator(OpKind::Ge)),
+            RawTokenKind::LogicalAnd => Some(Self::Operator(OpKind::LogicalAnd)),
+            RawTokenKind::LogicalOr => Some(Self::Operator(OpKind::LogicalOr)),
+            RawTokenKind::LogicalNot => Some(Self::Operator(OpKind::LogicalNot)),
 
             RawTokenKind::Comma => Some(Self::Comma),
             RawTokenKind::Colon => Some(Self::Colon),
@@ -134,14 +143,6 @@ impl TokenKind {
         matches!(self, TokenKind::Identifier)
     }
 
-    pub fn is_literal(&self) -> bool {
-        matches!(self, TokenKind::Literal(_))
-    }
-
-    pub fn is_operator(&self) -> bool {
-        matches!(self, TokenKind::Operator(_))
-    }
-
     pub fn is_comma(&self) -> bool {
         matches!(self, TokenKind::Comma)
     }
@@ -187,6 +188,16 @@ pub enum OpKind {
     Xor, // ^
     Shl, // <<
     Shr, // >>
+
+    Eq,         // ==
+    Ne,         // !=
+    Lt,         // <
+    Le,         // <=
+    Gt,         // >
+    Ge,         // >=
+    LogicalAnd, // &&
+    LogicalOr,  // ||
+    LogicalNot, // !
 }
 
 //
@@ -242,10 +253,14 @@ pub enum RawTokenKind {
     #[regex(r"%[a-z]+")]
     PreProcessor,
 
-    #[regex(r"\.[a-z]+")]
+    /// A directive, or a local label such as `.loop`, which the assembler tells
+    /// apart by where it is used.
+    #[regex(r"\.[a-zA-Z_][a-zA-Z0-9_]*")]
     Directive,
 
-    #[regex(r"[a-zA-Z]+")]
+    /// A name, or a local label such as `@loop`.
+    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
+    #[regex(r"@[a-zA-Z0-9_]+")]
     Identifier,
 
     /* literals */
@@ -282,6 +297,24 @@ pub enum RawTokenKind {
     Shl,
     #[token(">>")]
     Shr,
+    #[token("==")]
+    Eq,
+    #[token("!=")]
+    Ne,
+    #[token("<")]
+    Lt,
+    #[token("<=")]
+    Le,
+    #[token(">")]
+    Gt,
+    #[token(">=")]
+    Ge,
+    #[token("&&")]
+    LogicalAnd,
+    #[token("||")]
+    LogicalOr,
+    #[token("!")]
+    LogicalNot,
 
     /* punctuation */
     #[token(",")]
@@ -290,6 +323,10 @@ pub enum RawTokenKind {
     Colon,
     #[token("#")]
     Hash,
+    #[token("##")]
+    Concat,
+    #[token("...")]
+    Ellipsis,
 
     /* delimiters */
     #[token("(")]
@@ -329,27 +366,22 @@ impl RawTokenKind {
         )
     }
 
-    pub fn is_operator(&self) -> bool {
-        matches!(
-            self,
-            RawTokenKind::Add
-                | RawTokenKind::Sub
-                | RawTokenKind::Mul
-                | RawTokenKind::Div
-                | RawTokenKind::Mod
-                | RawTokenKind::Not
-                | RawTokenKind::And
-                | RawTokenKind::Or
-                | RawTokenKind::Xor
-                | RawTokenKind::Shl
-                | RawTokenKind::Shr
-        )
-    }
-
     pub fn is_comma(&self) -> bool {
         matches!(self, RawTokenKind::Comma)
     }
 
+    pub fn is_colon(&self) -> bool {
+        matches!(self, RawTokenKind::Colon)
+    }
+
+    pub fn is_concat(&self) -> bool {
+        matches!(self, RawTokenKind::Concat)
+    }
+
+    pub fn is_ellipsis(&self) -> bool {
+        matches!(self, RawTokenKind::Ellipsis)
+    }
+
     pub fn is_lparen(&self) -> bool {
         matches!(self, RawTokenKind::LParen)
     }
@@ -378,22 +410,53 @@ impl RawTokenKind {
 pub mod tokens {
     use super::*;
 
-    pub fn from_string<'a>(source: &'a str) -> Vec<RawToken<'a>> {
-        let lexer = RawTokenKind::lexer(source);
-        lexer
-            .spanned()
-            .into_iter()
-            .map(|(kind, span)| {
-                let span = Span::from(span);
-                let range: std::ops::Range<usize> = span.into();
-                let source = SourceRef::new(File::empty(), span);
-                RawToken { kind, source }
-            })
-            .collect()
+    /// Lexes a string which is not from a source file.
+    ///
+    /// The tokens need a file to borrow their text from, so the string is kept
+    /// in one which lives until the program exits.
+    #[cfg(test)]
+    pub fn from_string(source: &str) -> Vec<RawToken<'static>> {
+        let file = File::new("<string>".to_string(), source.to_string());
+        Box::leak(Box::new(file)).lex_tokens()
+    }
+
+    /// Writes tokens back out as source which lexes to the same tokens.
+    ///
+    /// Tokens which are next to each other in a file are written as they are,
+    /// so lexed source comes back unchanged. Tokens from different places, such
+    /// as a macro expansion and the text around it, are separated by a space
+    /// where they would otherwise lex as one token.
+    pub fn to_string<'a>(tokens: &[RawToken<'a>]) -> String {
+        let mut output = String::new();
+        let mut previous: Option<&RawToken<'a>> = None;
+        for token in tokens.iter() {
+            if let Some(previous) = previous {
+                if !is_adjacent(previous, token) && !is_separate(previous, token) {
+                    output.push(' ');
+                }
+            }
+            output += token.source.value();
+            previous = Some(token);
+        }
+        output
+    }
+
+    /// Returns whether `next` directly follows `token` in the same file.
+    fn is_adjacent(token: &RawToken, next: &RawToken) -> bool {
+        std::ptr::eq(token.source.file, next.source.file)
+            && token.source.span.end == next.source.span.start
     }
 
-    pub fn to_string<'a>(tokens: &Vec<RawToken<'a>>) -> String {
-        tokens.iter().map(|t| t.source.value()).collect::<String>()
+    /// Returns whether `token` and `next` lex as the same two tokens when they
+    /// are written together.
+    fn is_separate(token: &RawToken, next: &RawToken) -> bool {
+        if token.is_whitespace() || token.is_newline() || next.is_whitespace() || next.is_newline()
+        {
+            return true;
+        }
+        let text = format!("{}{}", token.source.value(), next.source.value());
+        let kinds = RawTokenKind::lexer(&text).collect::<Vec<_>>();
+        kinds == [token.kind.clone(), next.kind.clone()]
     }
 }
 
@@ -402,26 +465,26 @@ pub mod tokens {
 fn conv_bin(lex: &mut Lexer<RawTokenKind>) -> Option<u32> {
     // ex. 0b111
     let slice = lex.slice();
-    return u32::from_str_radix(&slice[2..slice.len()], 2).ok();
+    u32::from_str_radix(&slice[2..slice.len()], 2).ok()
 }
 
 fn conv_oct(lex: &mut Lexer<RawTokenKind>) -> Option<u32> {
     // ex. 0o123
     let slice = lex.slice();
-    return u32::from_str_radix(&slice[2..slice.len()], 8).ok();
+    u32::from_str_radix(&slice[2..slice.len()], 8).ok()
 }
 
 fn conv_dec(lex: &mut Lexer<RawTokenKind>) -> Option<u32> {
     // ex. 123
     let slice = lex.slice();
-    return u32::from_str_radix(slice, 10).ok();
+    slice.parse::<u32>().ok()
 }
 
 fn conv_hex(lex: &mut Lexer<RawTokenKind>) -> Option<u32> {
     // ex. 0xABC or $ABC
     let slice = lex.slice();
     let start_char = if slice.starts_with("$") { 1 } else { 2 };
-    return u32::from_str_radix(&slice[start_char..], 16).ok();
+    u32::from_str_radix(&slice[start_char..], 16).ok()
 }
 
 fn conv_char(lex: &mut Lexer<RawTokenKind>) -> Option<char> {
@@ -435,3 +498,144 @@ fn conv_string(lex: &mut Lexer<RawTokenKind>) -> Option<String> {
     let slice = lex.slice();
     Some(slice[1..slice.len() - 1].to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the kind and text of each token in `source`, without whitespace.
+    fn lex(source: &str) -> Vec<(RawTokenKind, &'static str)> {
+        tokens::from_string(source)
+            .into_iter()
+            .filter(|t| !t.is_whitespace())
+            .map(|t| (t.kind, t.source.value()))
+            .collect()
+    }
+
+    #[test]
+    fn identifiers_have_digits_and_underscores() {
+        assert_eq!(
+            lex("loop1 my_label _start"),
+            [
+                (RawTokenKind::Identifier, "loop1"),
+                (RawTokenKind::Identifier, "my_label"),
+                (RawTokenKind::Identifier, "_start"),
+            ]
+        );
+        // a number is not the start of an identifier
+        assert_eq!(
+            lex("1abc"),
+            [
+                (RawTokenKind::Number(1), "1"),
+                (RawTokenKind::Identifier, "abc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn local_labels() {
+        assert_eq!(
+            lex("@loop: bne .next_1"),
+            [
+                (RawTokenKind::Identifier, "@loop"),
+                (RawTokenKind::Colon, ":"),
+                (RawTokenKind::Identifier, "bne"),
+                (RawTokenKind::Directive, ".next_1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn identifier_with_trailing_digit() {
+        assert_eq!(lex("loop1"), [(RawTokenKind::Identifier, "loop1")]);
+        assert_eq!(lex("a1b2c3"), [(RawTokenKind::Identifier, "a1b2c3")]);
+    }
+
+    #[test]
+    fn identifier_with_underscore() {
+        assert_eq!(lex("my_label"), [(RawTokenKind::Identifier, "my_label")]);
+        assert_eq!(
+            lex("my__label_"),
+            [(RawTokenKind::Identifier, "my__label_")]
+        );
+    }
+
+    #[test]
+    fn identifier_with_leading_underscore() {
+        assert_eq!(lex("_start"), [(RawTokenKind::Identifier, "_start")]);
+        assert_eq!(lex("_"), [(RawTokenKind::Identifier, "_")]);
+        assert_eq!(
+            lex("_start:"),
+            [
+                (RawTokenKind::Identifier, "_start"),
+                (RawTokenKind::Colon, ":"),
+            ]
+        );
+    }
+
+    #[test]
+    fn dot_local_label() {
+        // the lexer can't tell a local label from a directive; the assembler
+        // sorts them out by position
+        assert_eq!(lex(".local"), [(RawTokenKind::Directive, ".local")]);
+        assert_eq!(
+            lex(".local: jmp .local"),
+            [
+                (RawTokenKind::Directive, ".local"),
+                (RawTokenKind::Colon, ":"),
+                (RawTokenKind::Identifier, "jmp"),
+                (RawTokenKind::Directive, ".local"),
+            ]
+        );
+        assert_eq!(lex("._2"), [(RawTokenKind::Directive, "._2")]);
+    }
+
+    #[test]
+    fn at_local_label() {
+        assert_eq!(lex("@local"), [(RawTokenKind::Identifier, "@local")]);
+        assert_eq!(
+            lex("@local: jmp @local"),
+            [
+                (RawTokenKind::Identifier, "@local"),
+                (RawTokenKind::Colon, ":"),
+                (RawTokenKind::Identifier, "jmp"),
+                (RawTokenKind::Identifier, "@local"),
+            ]
+        );
+        // digits may follow the `@` straight away
+        assert_eq!(lex("@1"), [(RawTokenKind::Identifier, "@1")]);
+    }
+
+    #[test]
+    fn leading_digit_is_not_an_identifier() {
+        assert_eq!(
+            lex("9_x"),
+            [
+                (RawTokenKind::Number(9), "9"),
+                (RawTokenKind::Identifier, "_x"),
+            ]
+        );
+        assert_eq!(
+            lex("12loop"),
+            [
+                (RawTokenKind::Number(12), "12"),
+                (RawTokenKind::Identifier, "loop"),
+            ]
+        );
+        assert_ne!(lex("1abc")[0].0, RawTokenKind::Identifier);
+    }
+
+    #[test]
+    fn to_string_keeps_expanded_tokens_apart() {
+        let source = "lda #1";
+        let mut tokens = tokens::from_string(source);
+        assert_eq!(tokens::to_string(&tokens), source);
+
+        // tokens which are not next to each other are written with a space
+        // only where they would otherwise run together
+        let name = tokens::from_string("abc");
+        tokens.insert(1, name[0].clone());
+        tokens.insert(1, name[0].clone());
+        assert_eq!(tokens::to_string(&tokens), "lda abc abc #1");
+    }
+}
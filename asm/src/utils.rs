@@ -33,7 +33,7 @@ where
 {
     let mut index = 0;
     while let Some(token) = tokens[index..].first() {
-        if !pred(&token) {
+        if !pred(token) {
             break;
         }
         index += 1;
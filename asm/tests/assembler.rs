@@ -0,0 +1,191 @@
+//! The bytes the two assembler passes emit.
+
+mod common;
+
+use std::fs;
+
+use common::Scratch;
+
+use cpu::Cpu;
+use system::{Bus, Memory};
+
+#[test]
+fn labels_resolve_forward_and_backward() {
+    let scratch = Scratch::new("labels");
+    let bytes = scratch.assemble(
+        "    .org $1000
+start:
+    jmp end
+    jsr start
+loop:
+    dex
+    bne loop
+    beq end
+end:
+    rts
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0x4c, 0x0b, 0x10, // jmp end
+            0x20, 0x00, 0x10, // jsr start
+            0xca, // dex
+            0xd0, 0xfd, // bne loop
+            0xf0, 0x00, // beq end
+            0x60, // rts
+        ]
+    );
+}
+
+#[test]
+fn every_addressing_mode() {
+    let scratch = Scratch::new("addressing-modes");
+    let bytes = scratch.assemble(
+        "    .org $1000
+    nop
+    asl a
+    lda #$12
+    lda $12
+    lda $12,x
+    ldx $12,y
+    lda $1234
+    lda $1234,x
+    lda $1234,y
+    jmp ($1234)
+    lda ($12,x)
+    lda ($12),y
+    bne next
+next:
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xea, // implied
+            0x0a, // accumulator
+            0xa9, 0x12, // immediate
+            0xa5, 0x12, // zero page
+            0xb5, 0x12, // zero page,x
+            0xb6, 0x12, // zero page,y
+            0xad, 0x34, 0x12, // absolute
+            0xbd, 0x34, 0x12, // absolute,x
+            0xb9, 0x34, 0x12, // absolute,y
+            0x6c, 0x34, 0x12, // indirect
+            0xa1, 0x12, // (zero page,x)
+            0xb1, 0x12, // (zero page),y
+            0xd0, 0x00, // relative
+        ]
+    );
+}
+
+#[test]
+fn zero_page_addresses_use_zero_page_modes() {
+    let scratch = Scratch::new("zero-page");
+    let bytes = scratch.assemble(
+        "zp  .eq $20
+    .org $1000
+    lda $ff
+    lda $100
+    lda zp
+    sta zp,x
+    lda $10,y
+    jmp $0010
+    lda later
+later .eq $30
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xa5, 0xff, // lda $ff
+            0xad, 0x00, 0x01, // lda $100
+            0xa5, 0x20, // lda zp
+            0x95, 0x20, // sta zp,x
+            0xb9, 0x10, 0x00, // lda has no zero page,y
+            0x4c, 0x10, 0x00, // jmp has no zero page
+            0xad, 0x30, 0x00, // not known to be in zero page in the first pass
+        ]
+    );
+}
+
+#[test]
+fn zero_page_labels_use_zero_page_modes() {
+    let scratch = Scratch::new("zero-page-labels");
+    let bytes = scratch.assemble(
+        "    .org $00
+var:
+    .db 0
+    inc var
+    inc code
+code:
+",
+    );
+    assert_eq!(bytes, [0x00, 0xe6, 0x00, 0xee, 0x06, 0x00]);
+}
+
+#[test]
+fn undefined_labels_are_errors() {
+    let scratch = Scratch::new("undefined");
+    let stderr = scratch.reject("    .org $1000\n    jmp nowhere\n");
+    assert!(stderr.contains("undefined symbol 'nowhere'"), "{}", stderr);
+
+    let stderr = scratch.reject("    .org $1000\n    bne nowhere\n");
+    assert!(stderr.contains("undefined symbol 'nowhere'"), "{}", stderr);
+
+    let stderr = scratch.reject("    .org $1000\n    .dw nowhere\n");
+    assert!(stderr.contains("undefined symbol 'nowhere'"), "{}", stderr);
+}
+
+#[test]
+fn only_jmp_has_an_indirect_mode() {
+    let scratch = Scratch::new("indirect");
+    let stderr = scratch.reject("    .org $1000\n    lda ($10)\n");
+    assert!(
+        stderr.contains("'lda' has no indirect addressing mode on the NMOS 6502"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("    .org $1000\n    sta ($1234)\n");
+    assert!(
+        stderr.contains("'sta' has no indirect addressing mode on the NMOS 6502"),
+        "{}",
+        stderr
+    );
+
+    // parentheses which don't wrap the whole operand only group
+    let bytes = scratch.assemble("    .org $1000\n    lda ($10)+1\n    jmp ($10)\n");
+    assert_eq!(bytes, [0xa5, 0x11, 0x6c, 0x10, 0x00]);
+}
+
+#[test]
+fn binaries_load_with_load_rom_and_run() {
+    let scratch = Scratch::new("load-rom");
+    let bytes = scratch.assemble(
+        "    .org $1000
+    lda #(2+3)*4
+    clc
+    adc #2+3*4
+    sta $10
+    sed
+",
+    );
+    assert_eq!(
+        bytes,
+        [0xa9, 0x14, 0x18, 0x69, 0x0e, 0x85, 0x10, 0xf8],
+        "operands are evaluated with precedence"
+    );
+
+    let mut mem = Memory::new();
+    let mut rom = fs::File::open(scratch.path().join("main.bin")).unwrap();
+    assert_eq!(mem.load_rom(Some(0x1000), &mut rom).unwrap(), 0x1000);
+    mem.set_reset_vector(0x1000).unwrap();
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    while !cpu.status.get_decimal_mode() {
+        cpu.step_instruction(&mut mem);
+    }
+    assert_eq!(mem.read(0x10), 0x22);
+}
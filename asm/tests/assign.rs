@@ -0,0 +1,135 @@
+//! Macros defined as the value of an expression with `%assign`.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn assign_evaluates_its_expression() {
+    let scratch = Scratch::new("assign");
+    let bytes = scratch.assemble(
+        "%define BASE $1000
+%assign SCREEN BASE + $400
+%assign COLS 8 * 5
+    .dw SCREEN
+    .db COLS
+",
+    );
+    assert_eq!(bytes, [0x00, 0x14, 40]);
+}
+
+#[test]
+fn assigned_values_outlive_their_macros() {
+    let scratch = Scratch::new("assign-value");
+    // a %define would expand to BASE + $400 where it is used
+    let bytes = scratch.assemble(
+        "%define BASE $1000
+%assign SCREEN BASE + $400
+%undef BASE
+%define BASE $2000
+    .dw SCREEN
+",
+    );
+    assert_eq!(bytes, [0x00, 0x14]);
+}
+
+#[test]
+fn assigned_macros_work_in_conditions() {
+    let scratch = Scratch::new("assign-if");
+    let bytes = scratch.assemble(
+        "%assign SIZE 2 * 8
+%if SIZE == 16
+    .db 1
+%else
+    .db 2
+%endif
+",
+    );
+    assert_eq!(bytes, [1]);
+}
+
+#[test]
+fn malformed_assigns_are_errors() {
+    let scratch = Scratch::new("assign-errors");
+    let stderr = scratch.reject("%assign\n");
+    assert!(stderr.contains("expected macro name"), "{}", stderr);
+
+    let stderr = scratch.reject("%assign 3 4\n");
+    assert!(stderr.contains("expected macro name"), "{}", stderr);
+
+    let stderr = scratch.reject("%assign X\n");
+    assert!(stderr.contains("expected an expression"), "{}", stderr);
+
+    let stderr = scratch.reject("%assign X 1 2\n");
+    assert!(
+        stderr.contains("unexpected token '2' in expression"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%assign X NOPE\n");
+    assert!(stderr.contains("undefined symbol 'NOPE'"), "{}", stderr);
+
+    let stderr = scratch.reject("%assign X 1 / 0\n");
+    assert!(stderr.contains("division by zero"), "{}", stderr);
+}
+
+#[test]
+fn macros_can_be_assigned_again() {
+    let scratch = Scratch::new("assign-again");
+    let bytes = scratch.assemble(
+        "%assign X 1
+    .db X
+%assign X 7
+    .db X
+%assign X X + 1
+    .db X
+%assign X X * X
+    .db X
+",
+    );
+    assert_eq!(bytes, [1, 7, 8, 64]);
+}
+
+#[test]
+fn assigned_values_build_on_earlier_ones() {
+    let scratch = Scratch::new("assign-earlier");
+    let bytes = scratch.assemble(
+        "%assign WIDTH 40
+%assign HEIGHT 25
+%assign CELLS WIDTH * HEIGHT
+%assign LAST CELLS - 1
+%assign WIDTH 80
+    .dw CELLS, LAST
+    .db WIDTH
+",
+    );
+    assert_eq!(bytes, [0xe8, 0x03, 0xe7, 0x03, 80]);
+}
+
+#[test]
+fn assign_counts_in_a_repeat_block() {
+    let scratch = Scratch::new("assign-count");
+    let bytes = scratch.assemble(
+        "%assign N 1
+%rep 4
+    .db N
+%assign N N * 2
+%endrep
+",
+    );
+    assert_eq!(bytes, [1, 2, 4, 8]);
+}
+
+#[test]
+fn assign_replaces_a_define() {
+    let scratch = Scratch::new("assign-define");
+    let bytes = scratch.assemble(
+        "%define X 2 + 3
+%assign X X * 2
+    .db X
+",
+    );
+    // the define is expanded before it is replaced, without grouping
+    assert_eq!(bytes, [8]);
+}
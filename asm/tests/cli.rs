@@ -0,0 +1,109 @@
+//! The command line of `asm`: reading the input, writing the output and
+//! listing, and defining constants.
+
+mod common;
+
+use common::Scratch;
+
+const SOURCE: &str = "    .org $1000
+    lda #VALUE
+    rts
+";
+
+#[test]
+fn a_file_is_assembled_to_the_output() {
+    let scratch = Scratch::new("cli");
+    scratch.write("main.asm", SOURCE);
+
+    let run = scratch.run(&["main.asm", "-D", "VALUE=7", "-o", "out.bin"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(run.stderr, "");
+    assert_eq!(scratch.read("out.bin"), [0xa9, 0x07, 0x60]);
+}
+
+#[test]
+fn the_output_is_named_after_the_input_by_default() {
+    let scratch = Scratch::new("cli-default-output");
+    scratch.write("main.asm", SOURCE);
+
+    // a define without a value is 1
+    let run = scratch.run(&["main.asm", "-D", "VALUE"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.o"), [0xa9, 0x01, 0x60]);
+}
+
+#[test]
+fn a_listing_shows_the_address_and_bytes_of_each_line() {
+    let scratch = Scratch::new("cli-listing");
+    scratch.write("main.asm", SOURCE);
+
+    let run = scratch.run(&["main.asm", "-DVALUE=$20", "-o", "out.bin", "-l", "out.lst"]);
+    assert!(run.success, "{}", run.stderr);
+    let listing = String::from_utf8(scratch.read("out.lst")).unwrap();
+    assert_eq!(
+        listing,
+        "    1                      .org $1000
+    2  1000  a9 20         lda #VALUE
+    3  1002  60            rts
+    4
+"
+    );
+}
+
+#[test]
+fn bad_command_lines_are_errors() {
+    let scratch = Scratch::new("cli-errors");
+    scratch.write("main.asm", SOURCE);
+
+    let run = scratch.run(&[]);
+    assert!(!run.success);
+    assert!(run.stderr.contains("usage: asm"), "{}", run.stderr);
+
+    let run = scratch.run(&["main.asm", "--bogus"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("unknown option '--bogus'"),
+        "{}",
+        run.stderr
+    );
+
+    let run = scratch.run(&["main.asm", "-D"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("missing value for option '-D'"),
+        "{}",
+        run.stderr
+    );
+
+    let run = scratch.run(&["main.asm", "-D", "1X=2"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("invalid name in define '1X=2'"),
+        "{}",
+        run.stderr
+    );
+
+    let run = scratch.run(&["missing.asm"]);
+    assert!(!run.success);
+    assert!(run.stderr.contains("missing.asm: "), "{}", run.stderr);
+}
+
+#[test]
+fn errors_in_defines_point_at_the_command_line() {
+    let scratch = Scratch::new("cli-define-errors");
+    scratch.write("main.asm", SOURCE);
+
+    let run = scratch.run(&["main.asm", "-D", "VALUE=zz"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr
+            .contains("<command line>: 1:15: undefined symbol 'zz'"),
+        "{}",
+        run.stderr
+    );
+    assert!(
+        run.stderr.contains("main.asm: 2:10: expanded from here"),
+        "{}",
+        run.stderr
+    );
+}
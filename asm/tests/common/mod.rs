@@ -0,0 +1,85 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What a run of `asm` left behind.
+pub struct Run {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// A directory of its own for a test to write sources into and run `asm` in.
+/// It is removed when the test is done with it.
+pub struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("asm-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes a file, making the directories it is in.
+    pub fn write(&self, name: &str, text: &str) {
+        let path = self.dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    pub fn read(&self, name: &str) -> Vec<u8> {
+        fs::read(self.dir.join(name)).unwrap()
+    }
+
+    /// Runs `asm` in the directory with `args`.
+    pub fn run(&self, args: &[&str]) -> Run {
+        self.run_with(args, &[])
+    }
+
+    /// Runs `asm` in the directory with `args` and extra environment variables.
+    pub fn run_with(&self, args: &[&str], env: &[(&str, &str)]) -> Run {
+        let output = Command::new(env!("CARGO_BIN_EXE_asm"))
+            .current_dir(&self.dir)
+            .args(args)
+            .env_remove("ASMPATH")
+            .envs(env.iter().copied())
+            .output()
+            .unwrap();
+        Run {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    /// Assembles `source` to a binary and returns its bytes, or panics with the
+    /// errors.
+    pub fn assemble(&self, source: &str) -> Vec<u8> {
+        self.write("main.asm", source);
+        let run = self.run(&["main.asm", "-o", "main.bin"]);
+        assert!(run.success, "{}", run.stderr);
+        self.read("main.bin")
+    }
+
+    /// Assembles `source`, which must fail, and returns the errors.
+    pub fn reject(&self, source: &str) -> String {
+        self.write("main.asm", source);
+        let run = self.run(&["main.asm", "-o", "main.bin"]);
+        assert!(!run.success, "assembled without errors");
+        run.stderr
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,225 @@
+//! Conditional blocks: `%if`, `%ifdef`, `%ifndef`, `%else` and `%endif`, along
+//! with `%undef` and `%defined()`.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn if_assembles_one_branch() {
+    let scratch = Scratch::new("if");
+    let bytes = scratch.assemble(
+        "%if 1
+    .db 1
+%else
+    .db 2
+%endif
+%if 2 - 2
+    .db 3
+%else
+    .db 4
+%endif
+%if 0
+    .db 5
+%endif
+",
+    );
+    assert_eq!(bytes, [1, 4]);
+}
+
+#[test]
+fn ifdef_and_ifndef_test_for_macros() {
+    let scratch = Scratch::new("ifdef");
+    let source = "%define SMALL
+%ifdef SMALL
+    .db 1
+%else
+    .db 2
+%endif
+%ifndef SMALL
+    .db 3
+%endif
+%ifdef LARGE
+    .db 4
+%else
+    .db 5
+%endif
+%ifndef LARGE
+    .db 6
+%endif
+";
+    assert_eq!(scratch.assemble(source), [1, 5, 6]);
+
+    scratch.write("main.asm", source);
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-D", "LARGE"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [1, 4]);
+}
+
+#[test]
+fn blocks_nest() {
+    let scratch = Scratch::new("if-nested");
+    let bytes = scratch.assemble(
+        "%if 1
+    %if 0
+        .db 1
+    %else
+        .db 2
+        %ifdef NOPE
+            .db 3
+        %else
+            .db 4
+        %endif
+    %endif
+%else
+    %if 1
+        .db 5
+    %else
+        .db 6
+    %endif
+%endif
+",
+    );
+    assert_eq!(bytes, [2, 4]);
+}
+
+#[test]
+fn blocks_in_skipped_blocks_are_not_evaluated() {
+    let scratch = Scratch::new("if-skipped");
+    let bytes = scratch.assemble(
+        "%if 0
+    %if NOPE / 0
+        .db 1
+    %else
+        .db 2
+    %endif
+%endif
+    .db 3
+",
+    );
+    assert_eq!(bytes, [3]);
+}
+
+#[test]
+fn undef_removes_a_macro() {
+    let scratch = Scratch::new("undef");
+    let bytes = scratch.assemble(
+        "%define SIZE 4
+%undef SIZE
+%ifdef SIZE
+    .db 1
+%else
+    .db 2
+%endif
+%undef NEVER_DEFINED
+%define SIZE 8
+    .db SIZE
+",
+    );
+    assert_eq!(bytes, [2, 8]);
+}
+
+#[test]
+fn defined_is_one_for_macros() {
+    let scratch = Scratch::new("defined");
+    let bytes = scratch.assemble(
+        "%define SIZE 16
+%if %defined(SIZE) && (SIZE == 4 || SIZE >= 16)
+    .db 1
+%endif
+%if %defined(NOPE) && NOPE > 4
+    .db 2
+%endif
+%if !%defined(NOPE)
+    .db 3
+%endif
+%undef SIZE
+%if %defined( SIZE )
+    .db 4
+%else
+    .db 5
+%endif
+",
+    );
+    assert_eq!(bytes, [1, 3, 5]);
+}
+
+#[test]
+fn undefined_symbols_in_if_are_errors() {
+    let scratch = Scratch::new("if-undefined");
+    let stderr = scratch.reject("%if NOPE\n    .db 1\n%endif\n");
+    assert!(stderr.contains("undefined symbol 'NOPE'"), "{}", stderr);
+
+    let stderr = scratch.reject("%if\n%endif\n");
+    assert!(stderr.contains("expected an expression"), "{}", stderr);
+
+    let stderr = scratch.reject("%if %defined NOPE\n%endif\n");
+    assert!(
+        stderr.contains("expected a macro name in '%defined(...)'"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%ifdef\n%endif\n");
+    assert!(stderr.contains("expected macro name"), "{}", stderr);
+
+    let stderr = scratch.reject("%undef 4\n");
+    assert!(stderr.contains("expected macro name"), "{}", stderr);
+}
+
+#[test]
+fn unbalanced_blocks_are_errors() {
+    let scratch = Scratch::new("if-unbalanced");
+    let stderr = scratch.reject("%else\n");
+    assert!(
+        stderr.contains("'%else' without a matching '%if'"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%endif\n");
+    assert!(
+        stderr.contains("'%endif' without a matching '%if'"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%if 1\n%else\n%else\n%endif\n");
+    assert!(
+        stderr.contains("duplicate '%else' in conditional block"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%if 1\n%if 0\n%endif\n");
+    assert!(
+        stderr.contains("unterminated conditional block, expected '%endif'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn conditions_compare_values() {
+    let scratch = Scratch::new("if-compare");
+    let bytes = scratch.assemble(
+        "%assign SIZE 8
+%if SIZE == 8
+    .db 1
+%endif
+%if SIZE != 8
+    .db 2
+%endif
+%if SIZE < 16 && SIZE > 4
+    .db 3
+%endif
+%if SIZE <= 7 || SIZE >= 9
+    .db 4
+%endif
+%if !(SIZE & 1)
+    .db 5
+%endif
+",
+    );
+    assert_eq!(bytes, [1, 3, 5]);
+}
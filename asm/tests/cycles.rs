@@ -0,0 +1,120 @@
+//! The cycle report written with `-c`, and the branches it counts.
+
+mod common;
+
+use common::Scratch;
+
+/// Assembles `source` with a cycle report and returns the report.
+fn cycles(scratch: &Scratch, source: &str) -> String {
+    scratch.write("main.asm", source);
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-c", "main.cyc", "-w"]);
+    assert!(run.success, "{}", run.stderr);
+    String::from_utf8(scratch.read("main.cyc")).unwrap()
+}
+
+#[test]
+fn blocks_of_straight_line_code_are_totalled() {
+    let scratch = Scratch::new("cycles");
+    let report = cycles(
+        &scratch,
+        "    .org $1000
+start:
+    ldx #0
+loop:
+    lda $2000,x
+    sta $10
+    inx
+    bne loop
+    rts
+",
+    );
+    assert_eq!(
+        report,
+        "start:
+    3  1000  2     ldx #0
+             2     cycles
+
+loop:
+    5  1002  4+1   lda $2000,x
+    6  1005  3     sta $10
+    7  1007  2     inx
+    8  1008  2+1   bne loop
+             11+2  cycles
+
+    9  100a  6     rts
+             6     cycles
+
+"
+    );
+}
+
+#[test]
+fn branches_across_a_page_can_take_two_more_cycles() {
+    let scratch = Scratch::new("cycles-page");
+    let report = cycles(
+        &scratch,
+        "    .org $10fc
+loop:
+    dex
+    nop
+    nop
+    bne loop
+    jmp loop
+",
+    );
+    assert!(
+        report.contains("    6  10ff  2+2   bne loop\n"),
+        "{}",
+        report
+    );
+    assert!(report.contains("             8+2   cycles\n"), "{}", report);
+}
+
+#[test]
+fn branches_reach_127_bytes_forward_and_128_back() {
+    let scratch = Scratch::new("branch-range");
+    let nops = |n: usize| "    nop\n".repeat(n);
+
+    let source = format!("    .org $1000\n    bne far\n{}far:\n", nops(127));
+    let bytes = scratch.assemble(&source);
+    assert_eq!(bytes[..2], [0xd0, 0x7f]);
+
+    let source = format!("    .org $1000\nback:\n{}    bne back\n", nops(126));
+    let bytes = scratch.assemble(&source);
+    assert_eq!(bytes[126..], [0xd0, 0x80]);
+}
+
+#[test]
+fn branches_out_of_range_are_errors() {
+    let scratch = Scratch::new("branch-range-errors");
+    let nops = |n: usize| "    nop\n".repeat(n);
+
+    let source = format!("    .org $1000\n    bne far\n{}far:\n", nops(128));
+    let stderr = scratch.reject(&source);
+    assert!(
+        stderr.contains("2:9: branch target $1082 is out of range"),
+        "{}",
+        stderr
+    );
+
+    let source = format!("    .org $1000\nback:\n{}    bne back\n", nops(127));
+    let stderr = scratch.reject(&source);
+    assert!(
+        stderr.contains("130:9: branch target $1000 is out of range"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn the_report_needs_a_path() {
+    let scratch = Scratch::new("cycles-errors");
+    scratch.write("main.asm", "    nop\n");
+    let run = scratch.run(&["main.asm", "-c"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("missing value for option '-c'"),
+        "{}",
+        run.stderr
+    );
+}
@@ -0,0 +1,163 @@
+//! Errors and warnings written as text or as JSON with `--error-format`.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn errors_are_written_as_json() {
+    let scratch = Scratch::new("json-error");
+    scratch.write("main.asm", "    .org $1000\n    lda nope\n");
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "--error-format", "json"]);
+    assert!(!run.success);
+    assert_eq!(
+        run.stderr,
+        concat!(
+            r#"{"severity":"error","code":null,"message":"undefined symbol 'nope'","#,
+            r#""spans":[{"file":"main.asm","line_start":2,"column_start":9,"line_end":2,"#,
+            r#""column_end":13,"primary":true,"label":null}],"notes":[]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn warnings_have_their_lint_as_the_code() {
+    let scratch = Scratch::new("json-warning");
+    scratch.write(
+        "main.asm",
+        "%define X 1
+%define X 2
+    .org $1000
+start:
+    .db X
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "--error-format", "json"]);
+    assert!(run.success, "{}", run.stderr);
+    let lines = run.stderr.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            concat!(
+                r#"{"severity":"warning","code":"redefined-macro","#,
+                r#""message":"macro 'X' is redefined","#,
+                r#""spans":[{"file":"main.asm","line_start":2,"column_start":9,"line_end":2,"#,
+                r#""column_end":10,"primary":true,"label":null},"#,
+                r#"{"file":"main.asm","line_start":1,"column_start":9,"line_end":1,"#,
+                r#""column_end":10,"primary":false,"label":"previously defined here"}],"#,
+                r#""notes":[]}"#
+            ),
+            concat!(
+                r#"{"severity":"warning","code":"unused-label","#,
+                r#""message":"label 'start' is never used","#,
+                r#""spans":[{"file":"main.asm","line_start":4,"column_start":1,"line_end":4,"#,
+                r#""column_end":6,"primary":true,"label":null}],"notes":[]}"#
+            ),
+        ]
+    );
+
+    // with -Werror they are errors, and one more says why
+    let run = scratch.run(&[
+        "main.asm",
+        "-o",
+        "main.bin",
+        "--error-format",
+        "json",
+        "-Werror",
+    ]);
+    assert!(!run.success);
+    let lines = run.stderr.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", run.stderr);
+    assert!(lines[..2]
+        .iter()
+        .all(|line| line.starts_with(r#"{"severity":"error","code":""#)));
+    assert_eq!(
+        lines[2],
+        concat!(
+            r#"{"severity":"error","code":null,"#,
+            r#""message":"warnings are treated as errors (-Werror)","spans":[],"notes":[]}"#
+        )
+    );
+}
+
+#[test]
+fn json_has_notes_and_expansions() {
+    let scratch = Scratch::new("json-notes");
+    scratch.write("main.asm", "%include \"gone.inc\"\n");
+    let run = scratch.run(&["main.asm", "--error-format", "json"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains(r#""message":"cannot find 'gone.inc'""#),
+        "{}",
+        run.stderr
+    );
+    assert!(
+        run.stderr.contains(r#""notes":["looked for '"#),
+        "{}",
+        run.stderr
+    );
+
+    scratch.write("main.asm", "%define M(x) .db x / 0\n    M(1)\n");
+    let run = scratch.run(&["main.asm", "--error-format", "json"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains(concat!(
+            r#"{"file":"main.asm","line_start":2,"column_start":5,"line_end":2,"#,
+            r#""column_end":6,"primary":false,"label":"expanded from here"}"#
+        )),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn json_strings_are_escaped() {
+    let scratch = Scratch::new("json-escape");
+    scratch.write("main.asm", "    .db \"a\\\"b\n");
+    let run = scratch.run(&["main.asm", "--error-format", "json"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr
+            .contains(r#""message":"unexpected token '\"a\\\"' in expression""#),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn text_errors_show_the_line() {
+    let scratch = Scratch::new("text-error");
+    let stderr = scratch.reject("    .org $1000\n    lda nope\n");
+    assert!(
+        stderr.contains("main.asm: 2:9: undefined symbol 'nope'"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("2 | "), "{}", stderr);
+    assert!(stderr.contains("    lda nope"), "{}", stderr);
+}
+
+#[test]
+fn unknown_error_formats_are_errors() {
+    let scratch = Scratch::new("error-format");
+    scratch.write("main.asm", "    nop\n");
+    let run = scratch.run(&["main.asm", "--error-format", "xml"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("unknown error format 'xml'"),
+        "{}",
+        run.stderr
+    );
+
+    let run = scratch.run(&["link", "--error-format", "json", "nothere.o"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr
+            .starts_with(r#"{"severity":"error","code":null,"message":"nothere.o: "#),
+        "{}",
+        run.stderr
+    );
+}
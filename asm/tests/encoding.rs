@@ -0,0 +1,70 @@
+//! Strings translated with `.enc` and `.charmap`.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn strings_are_translated_in_the_current_encoding() {
+    let scratch = Scratch::new("charmap");
+    let bytes = scratch.assemble(
+        "    .enc screen
+    .charmap 'A', 'Z', $01
+    .charmap ' ', $20
+    .ascii \"HI Z\"
+    .enc ascii
+    .ascii \"HI\"
+    .enc screen
+    .ascii \"A\"
+",
+    );
+    assert_eq!(bytes, [0x08, 0x09, 0x20, 0x1a, b'H', b'I', 0x01]);
+}
+
+#[test]
+fn character_literals_are_not_translated() {
+    let scratch = Scratch::new("charmap-literal");
+    let bytes = scratch.assemble(
+        "    .enc screen
+    .charmap 'A', $01
+    .ascii \"A\"
+    .db 'A'
+",
+    );
+    assert_eq!(bytes, [0x01, b'A']);
+}
+
+#[test]
+fn an_empty_charmap_forgets_the_mappings() {
+    let scratch = Scratch::new("charmap-reset");
+    let bytes = scratch.assemble(
+        "    .charmap 'a', 'c', $61 - $20
+    .ascii \"abc\"
+    .charmap
+    .ascii \"abc\"
+",
+    );
+    assert_eq!(bytes, *b"ABCabc");
+}
+
+#[test]
+fn malformed_charmaps_are_errors() {
+    let scratch = Scratch::new("charmap-errors");
+    let cases = [
+        ("    .charmap 'b', 'a', 1\n", "'b' comes after 'a'"),
+        (
+            "    .charmap 'a', 'b', $ff\n",
+            "the characters are mapped past byte $ff",
+        ),
+        (
+            "    .charmap 1, 2, 3, 4\n",
+            "'.charmap' takes a character, a range or nothing",
+        ),
+        ("    .charmap 'a',\n", "in '.charmap', expected a value"),
+        ("    .enc\n", "in '.enc', expected a name"),
+    ];
+    for (source, error) in cases {
+        let stderr = scratch.reject(source);
+        assert!(stderr.contains(error), "{}", stderr);
+    }
+}
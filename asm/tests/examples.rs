@@ -0,0 +1,145 @@
+//! Assembles the example programs with `asm`, runs them on a full machine and
+//! checks what they print and leave behind in memory. This is the one harness
+//! for the programs in `example/`.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use common::Scratch;
+
+use cpu::Cpu;
+use system::device::{Device, StdoutDevice};
+use system::{Bus, Image, MachineConfig, Memory};
+
+/// Instructions to run before a program is considered hung.
+const WATCHDOG: usize = 100_000;
+
+/// A console at the stdout device address which captures output.
+struct Console {
+    output: Arc<Mutex<String>>,
+}
+
+impl Device for Console {
+    fn read(&self, _: u16) -> u8 {
+        return 0;
+    }
+
+    fn write(&mut self, _: u16, data: u8) {
+        self.output.lock().unwrap().push(data as char);
+    }
+}
+
+/// Assembles `example/<name>.asm` to Intel HEX and returns the image.
+fn assemble(name: &str) -> Image {
+    let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../example")
+        .join(format!("{}.asm", name));
+    let scratch = Scratch::new(&format!("example-{}", name));
+    let run = scratch.run(&[
+        source.to_str().unwrap(),
+        "-w",
+        "-f",
+        "ihex",
+        "-o",
+        "out.hex",
+    ]);
+    assert!(run.success, "{}", run.stderr);
+
+    let text = String::from_utf8(scratch.read("out.hex")).unwrap();
+    return Image::parse_ihex(&text).unwrap();
+}
+
+/// Assembles an example and loads it into a machine which starts it, and
+/// returns the memory map along with the captured console output.
+fn boot(name: &str) -> (Memory<'static>, Arc<Mutex<String>>) {
+    let image = assemble(name);
+    let output = Arc::new(Mutex::new(String::new()));
+    let console = Console {
+        output: Arc::clone(&output),
+    };
+
+    let mut mem = MachineConfig::new()
+        .device("console", StdoutDevice::RANGE, console)
+        .reset_vector(image.start.unwrap())
+        .build()
+        .unwrap();
+    mem.load_image(&image).unwrap();
+    return (mem, output);
+}
+
+/// Runs until the program signals completion by setting the decimal flag.
+fn run_to_completion(cpu: &mut Cpu, mem: &mut Memory) {
+    cpu.power_on(mem);
+    for _ in 0..WATCHDOG {
+        mem.set_cycle(cpu.cycles());
+        cpu.step_instruction(mem);
+        if cpu.status.get_decimal_mode() {
+            return;
+        }
+    }
+    panic!("program did not finish within {} instructions", WATCHDOG);
+}
+
+#[test]
+fn fibonacci() {
+    let (mut mem, _) = boot("fib");
+    mem.write(0x99, 11);
+    let mut cpu = Cpu::new();
+    run_to_completion(&mut cpu, &mut mem);
+
+    assert_eq!(mem.read(0x104), 89);
+}
+
+#[test]
+fn hello() {
+    let (mut mem, output) = boot("hello");
+    let mut cpu = Cpu::new();
+    run_to_completion(&mut cpu, &mut mem);
+
+    assert_eq!(output.lock().unwrap().as_str(), "Hello\n");
+}
+
+#[test]
+fn prints_a_string() {
+    let (mut mem, output) = boot("print");
+    let mut cpu = Cpu::new();
+    run_to_completion(&mut cpu, &mut mem);
+
+    assert_eq!(output.lock().unwrap().as_str(), "Hello, world!\n");
+}
+
+#[test]
+fn interrupt_handler_returns() {
+    let (mut mem, output) = boot("irq");
+    let mut cpu = Cpu::new();
+    run_to_completion(&mut cpu, &mut mem);
+
+    assert_eq!(output.lock().unwrap().as_str(), "12\n");
+    // the handler returned with the stack as it was
+    assert_eq!(cpu.registers.sp.get(), 0xff);
+}
+
+#[test]
+fn prebuilt_images_match_their_sources() {
+    // the emulator boots these when it is given no program
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../example");
+    for name in ["hello", "fib"] {
+        let scratch = Scratch::new(&format!("prebuilt-{}", name));
+        let source = dir.join(format!("{}.asm", name));
+        let run = scratch.run(&[source.to_str().unwrap(), "-w", "-o", "out.bin"]);
+        assert!(run.success, "{}", run.stderr);
+
+        let prebuilt = fs::read(dir.join(format!("{}.o", name))).unwrap();
+        assert_eq!(
+            scratch.read("out.bin"),
+            prebuilt,
+            "example/{}.o is out of date with {}.asm",
+            name,
+            name
+        );
+    }
+}
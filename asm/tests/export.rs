@@ -0,0 +1,72 @@
+//! Exported symbols, which are public in the symbol file.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn exported_symbols_are_public() {
+    let scratch = Scratch::new("export");
+    scratch.write(
+        "main.asm",
+        "IO_BASE .eq $a000
+    .export IO_BASE, start
+    .org $1000
+start:
+    sta IO_BASE
+loop:
+    jmp loop
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-s", "main.sym"]);
+    assert!(run.success, "{}", run.stderr);
+    let symbols = String::from_utf8(scratch.read("main.sym")).unwrap();
+    assert_eq!(
+        symbols,
+        "$1003 loop
+
+[public]
+$1000 start
+$a000 IO_BASE
+"
+    );
+
+    let table = cpu::SymbolTable::parse(&symbols).unwrap();
+    assert_eq!(table.public_address("IO_BASE"), Some(0xA000));
+    assert_eq!(table.public_address("loop"), None);
+}
+
+#[test]
+fn linked_symbols_are_all_public() {
+    let scratch = Scratch::new("export-link");
+    scratch.write(
+        "main.asm",
+        "    .export start
+    .segment code
+start:
+    rts
+",
+    );
+
+    scratch.write("memory.map", "code $0200\n");
+
+    let run = scratch.run(&["main.asm", "-f", "obj", "-o", "main.o"]);
+    assert!(run.success, "{}", run.stderr);
+    let args = [
+        "link",
+        "main.o",
+        "-m",
+        "memory.map",
+        "-o",
+        "main.bin",
+        "-s",
+        "main.sym",
+    ];
+    let run = scratch.run(&args);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(
+        String::from_utf8(scratch.read("main.sym")).unwrap(),
+        "[public]\n$0200 start\n"
+    );
+}
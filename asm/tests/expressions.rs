@@ -0,0 +1,246 @@
+//! Operators in assembler expressions and the macros which build them.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn binary_operators_follow_c_precedence() {
+    let scratch = Scratch::new("precedence");
+    let bytes = scratch.assemble(
+        "    .db 2 + 3 * 4, (2 + 3) * 4, 10 - 4 - 3, 64 / 4 / 2, 17 % 5 + 1
+    .db 1 << 2 + 1, $f0 | $0f & $3c, $ff ^ $0f | $01, 1 + 2 << 1
+    .db 6 & 3 == 3, 1 | 2 ^ 3
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            14, 20, 3, 8, 3, // arithmetic
+            8, 0xfc, 0xf1, 6, // shifts and bitwise
+            0, 1, // comparisons bind tighter than bitwise operators
+        ]
+    );
+}
+
+#[test]
+fn low_and_high_bytes_of_a_value() {
+    let scratch = Scratch::new("low-high");
+    let bytes = scratch.assemble(
+        "    .org $12ff
+start:
+    lda #<start
+    ldx #>start
+    lda #>start + 1
+    lda #<(start + 1)
+    lda #>(start + 1)
+    .db <$abcd, >$abcd
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xa9, 0xff, // lda #<start
+            0xa2, 0x12, // ldx #>start
+            0xa9, 0x13, // the operator binds tighter than +
+            0xa9, 0x00, // lda #<(start + 1)
+            0xa9, 0x13, // lda #>(start + 1)
+            0xcd, 0xab, // .db <$abcd, >$abcd
+        ]
+    );
+
+    let stderr = scratch.reject("    lda #<$12ff + 1\n");
+    assert!(
+        stderr.contains("value $100 does not fit in a byte"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn low_and_high_bytes_are_not_comparisons() {
+    let scratch = Scratch::new("low-high-compare");
+    let bytes = scratch.assemble("    .db 1 < 2, 2 > 1, 1 < >$0200, <$0102 > 1\n");
+    assert_eq!(bytes, [1, 1, 1, 1]);
+}
+
+#[test]
+fn variadic_macros_take_the_rest_of_the_arguments() {
+    let scratch = Scratch::new("variadic");
+    let bytes = scratch.assemble(
+        "%define bytes(first, rest...) .db first + 1, rest
+%define quote(words...) %str(words)
+    bytes(1, 2, 3)
+    bytes(5, 1)
+    .ascii quote(a, x)
+",
+    );
+    assert_eq!(bytes, [2, 2, 3, 6, 1, b'a', b',', b' ', b'x']);
+}
+
+#[test]
+fn pasted_tokens_form_names() {
+    let scratch = Scratch::new("paste");
+    let bytes = scratch.assemble(
+        "%define cat(a, b) a##b
+lab1 .eq 5
+    .db cat(lab, 1), cat(1, 2)
+",
+    );
+    assert_eq!(bytes, [5, 12]);
+}
+
+#[test]
+fn values_out_of_range_are_errors() {
+    let scratch = Scratch::new("range");
+    assert_eq!(
+        scratch.assemble("    .db -128, 255\n    .dw -32768, $ffff\n"),
+        [0x80, 0xff, 0x00, 0x80, 0xff, 0xff]
+    );
+
+    let cases = [
+        ("    .db 256\n", "1:9: value $100 does not fit in a byte"),
+        ("    .db -129\n", "1:9: value -129 does not fit in a byte"),
+        ("    lda #256\n", "1:10: value $100 does not fit in a byte"),
+        (
+            "    .dw $10000\n",
+            "1:9: value $10000 does not fit in a word",
+        ),
+        (
+            "    .dw -32769\n",
+            "1:9: value -32769 does not fit in a word",
+        ),
+        (
+            "    lda $10000\n",
+            "1:9: value $10000 does not fit in a word",
+        ),
+    ];
+    for (source, error) in cases {
+        let stderr = scratch.reject(source);
+        assert!(stderr.contains(error), "{}", stderr);
+    }
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    let scratch = Scratch::new("division-by-zero");
+    let cases = [
+        ("    .db 1 / 0\n", "1:11: division by zero"),
+        ("    .db 1 % 0\n", "1:11: division by zero"),
+        ("    lda #10 / (5 - 5)\n", "1:13: division by zero"),
+        ("%assign X 1 / 0\n", "1:13: division by zero"),
+        ("%if 1 / 0\n%endif\n", "1:7: division by zero"),
+    ];
+    for (source, error) in cases {
+        let stderr = scratch.reject(source);
+        assert!(stderr.contains(error), "{}", stderr);
+    }
+
+    // unless the other side of a logical operator already decided the result
+    assert_eq!(scratch.assemble("    .db 0 && 1 / 0, 1 || 1 % 0\n"), [0, 1]);
+}
+
+#[test]
+fn errors_in_macros_point_back_at_each_expansion() {
+    let scratch = Scratch::new("expansion-origin");
+    let stderr = scratch.reject(
+        "%define inner(x) .db x / 0
+%define outer(x) inner(x)
+    outer(1)
+",
+    );
+    let lines = stderr
+        .lines()
+        .filter(|line| line.contains("division by zero") || line.contains("expanded from here"))
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", stderr);
+    assert!(
+        lines[0].ends_with("main.asm: 1:24: division by zero"),
+        "{}",
+        stderr
+    );
+    assert_eq!(lines[1], "main.asm: 2:18: expanded from here");
+    assert_eq!(lines[2], "main.asm: 3:5: expanded from here");
+}
+
+#[test]
+fn unary_minus_wraps_to_twos_complement() {
+    let scratch = Scratch::new("unary-minus");
+    let bytes = scratch.assemble(
+        "    .db -1, -128, - 2 * 3, -(2 + 3), --4, +5
+    .db ~0, ~$0f & $ff
+    .dw -1, -2
+    lda #-1
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xff, 0x80, 0xfa, 0xfb, 0x04, 0x05, // minus and plus
+            0xff, 0xf0, // bitwise not
+            0xff, 0xff, 0xfe, 0xff, // words
+            0xa9, 0xff, // lda #-1
+        ]
+    );
+}
+
+#[test]
+fn comparisons_and_logical_operators_are_zero_or_one() {
+    let scratch = Scratch::new("comparisons");
+    let bytes = scratch.assemble(
+        "    .db 1 == 1, 1 != 1, 2 < 3, 3 <= 3, 4 > 5, 5 >= 5
+    .db 1 && 0, 1 || 0, 5 && 7, !0, !7
+    .db 1 + 1 == 2 && 3 > 2, 0 || 2 == 2, !(1 < 2)
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            1, 0, 1, 1, 0, 1, // comparisons
+            0, 1, 1, 1, 0, // logical operators
+            1, 1, 0, // mixed with arithmetic
+        ]
+    );
+}
+
+#[test]
+fn unary_operators_nest_and_mix_with_binary_ones() {
+    let scratch = Scratch::new("unary");
+    let bytes = scratch.assemble(
+        "    .db -5, +5, ~5, -~5, ~-5, 1 - -1, 2 * -1
+    .dw ~$1234, -$8000
+    lda #~$0f
+    ldx #-128
+    cpy #+255
+    lda -1
+    lda -2,x
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xfb, 0x05, 0xfa, 0x06, 0x04, 0x02, 0xfe, // bytes
+            0xcb, 0xed, 0x00, 0x80, // words
+            0xa9, 0xf0, // lda #~$0f
+            0xa2, 0x80, // ldx #-128
+            0xc0, 0xff, // cpy #+255
+            0xad, 0xff, 0xff, // a negative address wraps to the top of memory
+            0xbd, 0xfe, 0xff, // lda -2,x
+        ]
+    );
+}
+
+#[test]
+fn unary_operators_need_an_operand() {
+    let scratch = Scratch::new("unary-errors");
+    let cases = [
+        ("    .db -\n", "1:9: expected an expression"),
+        ("    .db 5 -\n", "1:11: expected an expression"),
+        ("    lda #~\n", "expected an expression"),
+        ("    lda #-129\n", "1:10: value -129 does not fit in a byte"),
+    ];
+    for (source, error) in cases {
+        let stderr = scratch.reject(source);
+        assert!(stderr.contains(error), "{}", stderr);
+    }
+}
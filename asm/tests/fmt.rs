@@ -0,0 +1,101 @@
+//! `asm fmt`, which aligns source in columns.
+
+mod common;
+
+use common::Scratch;
+
+const UNFORMATTED: &str = "start: lda #1 ; load
+  %define X 2
+loop:
+      dex
+ bne loop   ;again
+.db 1,2
+value .eq 3
+
+
+";
+
+const FORMATTED: &str = "start:  lda #1                  ; load
+%define X 2
+loop:
+        dex
+        bne loop                ;again
+        .db 1,2
+value   .eq 3
+";
+
+#[test]
+fn source_is_aligned_in_columns() {
+    let scratch = Scratch::new("fmt");
+    scratch.write("main.asm", UNFORMATTED);
+
+    let run = scratch.run(&["fmt", "main.asm"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(
+        String::from_utf8(scratch.read("main.asm")).unwrap(),
+        FORMATTED
+    );
+
+    // formatting formatted source does not change it
+    let run = scratch.run(&["fmt", "main.asm"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(
+        String::from_utf8(scratch.read("main.asm")).unwrap(),
+        FORMATTED
+    );
+}
+
+#[test]
+fn formatting_keeps_the_meaning() {
+    let scratch = Scratch::new("fmt-meaning");
+    let source = "%define PAIR(a, b)   .db a,   b
+ .org $1000
+start: PAIR(1, 2)
+    lda #<start ; low
+  .dw start, \\
+  start + 1
+";
+    let before = scratch.assemble(source);
+    let run = scratch.run(&["fmt", "main.asm"]);
+    assert!(run.success, "{}", run.stderr);
+    let formatted = String::from_utf8(scratch.read("main.asm")).unwrap();
+    assert_ne!(formatted, source);
+    assert_eq!(scratch.assemble(&formatted), before);
+}
+
+#[test]
+fn check_lists_the_unformatted_files() {
+    let scratch = Scratch::new("fmt-check");
+    scratch.write("good.asm", FORMATTED);
+    scratch.write("bad.asm", UNFORMATTED);
+
+    let run = scratch.run(&["fmt", "--check", "good.asm", "bad.asm"]);
+    assert!(!run.success);
+    assert_eq!(run.stdout, "bad.asm\n");
+    assert!(
+        run.stderr.contains("1 file is not formatted"),
+        "{}",
+        run.stderr
+    );
+    // nothing is written
+    assert_eq!(
+        String::from_utf8(scratch.read("bad.asm")).unwrap(),
+        UNFORMATTED
+    );
+
+    let run = scratch.run(&["fmt", "--check", "good.asm"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(run.stdout, "");
+}
+
+#[test]
+fn fmt_needs_files_which_exist() {
+    let scratch = Scratch::new("fmt-errors");
+    let run = scratch.run(&["fmt"]);
+    assert!(!run.success);
+    assert!(run.stderr.contains("usage: asm"), "{}", run.stderr);
+
+    let run = scratch.run(&["fmt", "missing.asm"]);
+    assert!(!run.success);
+    assert!(run.stderr.contains("missing.asm: "), "{}", run.stderr);
+}
@@ -0,0 +1,146 @@
+//! Files read with `%include` and `.incbin`, and where they are looked for.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn includes_are_found_next_to_the_including_file_first() {
+    let scratch = Scratch::new("include-order");
+    scratch.write("main.asm", "    .org $1000\n%include \"lib/outer.inc\"\n");
+    scratch.write("lib/outer.inc", "%include \"value.inc\"\n");
+    scratch.write("lib/value.inc", "    .db 1\n");
+    scratch.write("first/value.inc", "    .db 2\n");
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-I", "first"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [1]);
+}
+
+#[test]
+fn search_directories_are_tried_in_order() {
+    let scratch = Scratch::new("include-search");
+    scratch.write("main.asm", "    .org $1000\n%include \"value.inc\"\n");
+    scratch.write("first/value.inc", "    .db 1\n");
+    scratch.write("second/value.inc", "    .db 2\n");
+    scratch.write("env/value.inc", "    .db 3\n");
+    scratch.write("env/other.inc", "    .db 4\n");
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-Isecond", "-I", "first"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [2]);
+
+    // ASMPATH comes after -I
+    let env = [("ASMPATH", "env")];
+    let run = scratch.run_with(&["main.asm", "-o", "main.bin", "-I", "first"], &env);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [1]);
+
+    scratch.write("main.asm", "    .org $1000\n%include \"other.inc\"\n");
+    let run = scratch.run_with(&["main.asm", "-o", "main.bin", "-I", "first"], &env);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [4]);
+}
+
+#[test]
+fn missing_files_list_the_paths_tried() {
+    let scratch = Scratch::new("include-missing");
+    scratch.write("main.asm", "%include \"missing.inc\"\n");
+
+    let env = [("ASMPATH", "env")];
+    let run = scratch.run_with(&["main.asm", "-o", "main.bin", "-I", "lib"], &env);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("cannot find 'missing.inc'"),
+        "{}",
+        run.stderr
+    );
+    let tried = run
+        .stderr
+        .lines()
+        .filter(|line| line.contains("looked for"))
+        .collect::<Vec<_>>();
+    assert_eq!(tried.len(), 3, "{}", run.stderr);
+    assert!(tried[0].ends_with("looked for 'missing.inc'"));
+    assert!(tried[1].ends_with("looked for 'lib/missing.inc'"));
+    assert!(tried[2].ends_with("looked for 'env/missing.inc'"));
+}
+
+#[test]
+fn include_cycles_are_errors() {
+    let scratch = Scratch::new("include-cycle");
+    scratch.write("main.asm", "%include \"a.inc\"\n");
+    scratch.write("a.inc", "%include \"b.inc\"\n");
+    scratch.write("b.inc", "%include \"a.inc\"\n");
+
+    let stderr = scratch.reject("%include \"a.inc\"\n");
+    assert!(
+        stderr.contains("include cycle: a.inc -> b.inc -> a.inc"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%include \"main.asm\"\n");
+    assert!(
+        stderr.contains("include cycle: main.asm -> main.asm"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn included_lines_are_listed_on_the_include() {
+    let scratch = Scratch::new("include-listing");
+    scratch.write(
+        "main.asm",
+        "    .org $1000\n%include \"code.inc\"\n    rts\n",
+    );
+    // no newline at the end
+    scratch.write("code.inc", "%define VALUE 7\n    lda #VALUE");
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-l", "main.lst"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [0xa9, 0x07, 0x60]);
+    let listing = String::from_utf8(scratch.read("main.lst")).unwrap();
+    assert!(
+        listing.contains("    2  1000  a9 07     %include \"code.inc\"\n"),
+        "{}",
+        listing
+    );
+}
+
+#[test]
+fn errors_in_included_files_point_back_at_the_include() {
+    let scratch = Scratch::new("include-error");
+    scratch.write("bad.inc", "    lda #\n");
+
+    let stderr = scratch.reject("    .org $1000\n%include \"bad.inc\"\n");
+    assert!(stderr.contains("bad.inc: 1:"), "{}", stderr);
+    assert!(
+        stderr.contains("main.asm: 2:1: included from here"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn incbin_emits_the_bytes_of_a_file() {
+    let scratch = Scratch::new("incbin");
+    scratch.write("data/font.bin", "AB\n");
+    scratch.write("empty.bin", "");
+
+    scratch.write(
+        "main.asm",
+        "    .org $1000\nfont: .incbin \"font.bin\"\n    .incbin \"empty.bin\"\n    .dw font\n",
+    );
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-I", "data"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [0x41, 0x42, 0x0a, 0x00, 0x10]);
+
+    let stderr = scratch.reject("    .incbin font.bin\n");
+    assert!(
+        stderr.contains("expected a file name in quotes after '.incbin'"),
+        "{}",
+        stderr
+    );
+}
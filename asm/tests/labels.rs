@@ -0,0 +1,191 @@
+//! Labels, and expressions which use them.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn operands_mix_labels_and_constants() {
+    let scratch = Scratch::new("label-arithmetic");
+    let bytes = scratch.assemble(
+        "    .org $1000
+start:
+    lda table + 2,x
+    jmp (vectors + 4)
+    .dw end - start
+    .word table, end - 1
+table:
+    .db 1, 2, 3
+vectors:
+    .dw 0, 0, start
+end:
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xbd, 0x0e, 0x10, // lda table + 2,x
+            0x6c, 0x13, 0x10, // jmp (vectors + 4)
+            0x15, 0x00, // .dw end - start
+            0x0c, 0x10, 0x14, 0x10, // .word table, end - 1
+            0x01, 0x02, 0x03, // table
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x10, // vectors
+        ]
+    );
+}
+
+#[test]
+fn words_use_labels_defined_later() {
+    let scratch = Scratch::new("label-later");
+    let bytes = scratch.assemble(
+        "    .org $1000
+    .dw later * 2, end - here
+later .eq $10
+here:
+    nop
+    nop
+end:
+",
+    );
+    assert_eq!(bytes, [0x20, 0x00, 0x02, 0x00, 0xea, 0xea]);
+}
+
+#[test]
+fn label_arithmetic_is_checked() {
+    let scratch = Scratch::new("label-arithmetic-errors");
+    let stderr = scratch.reject("    .org $1000\n    .dw nope + 1\n");
+    assert!(stderr.contains("undefined symbol 'nope'"), "{}", stderr);
+
+    let stderr = scratch.reject("    .org $1000\n    .dw $10000\n");
+    assert!(
+        stderr.contains("value $10000 does not fit in a word"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("    .org $ff00\nstart:\n    .dw start * 2\n");
+    assert!(
+        stderr.contains("value $1fe00 does not fit in a word"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn word_tables_of_label_arithmetic() {
+    let scratch = Scratch::new("label-words");
+    let bytes = scratch.assemble(
+        "    .org $2000
+table:
+    .dw first - 1, second - 1
+    .dw (end - table) / 2, end - table == 11
+first:
+    rts
+second:
+    .dw <second | >first << 8
+end:
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0x07, 0x20, 0x08, 0x20, // addresses for rts to return to
+            0x05, 0x00, 0x01, 0x00, // sizes and comparisons
+            0x60, // first
+            0x09, 0x20, // the bytes of two labels put together
+        ]
+    );
+}
+
+#[test]
+fn local_labels_belong_to_the_label_before_them() {
+    let scratch = Scratch::new("local-labels");
+    let bytes = scratch.assemble(
+        "    .org $1000
+first:
+    ldx #2
+@loop:
+    dex
+    bne @loop
+    beq @done
+    nop
+@done:
+    rts
+second:
+    ldy #2
+@loop:
+    dey
+    bne @loop
+    jmp first
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xa2, 0x02, 0xca, 0xd0, 0xfd, 0xf0, 0x01, 0xea, 0x60, // first
+            0xa0, 0x02, 0x88, 0xd0, 0xfd, 0x4c, 0x00, 0x10, // second
+        ]
+    );
+}
+
+#[test]
+fn dot_local_labels_are_scoped_too() {
+    let scratch = Scratch::new("dot-local-labels");
+    let bytes = scratch.assemble(
+        "    .org $1000
+.start:
+    nop
+first:
+.loop:
+    bne .loop
+second:
+.loop:
+    bne .loop
+    jmp first
+    jmp .loop
+",
+    );
+    assert_eq!(
+        bytes,
+        [0xea, 0xd0, 0xfe, 0xd0, 0xfe, 0x4c, 0x01, 0x10, 0x4c, 0x03, 0x10]
+    );
+}
+
+#[test]
+fn local_labels_are_not_seen_from_other_scopes() {
+    let scratch = Scratch::new("local-label-scope");
+    let stderr = scratch.reject(
+        "    .org $1000
+first:
+@loop:
+    nop
+second:
+    bne @loop
+",
+    );
+    assert!(
+        stderr.contains("6:9: undefined symbol 'second@loop'"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject(
+        "    .org $1000
+first:
+@x:
+    nop
+@x:
+    jmp @x
+",
+    );
+    assert!(
+        stderr.contains("5:1: 'first@x' is already defined"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("3:1: previously defined here"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,241 @@
+//! Object files, and linking them into an image with `asm link`.
+
+mod common;
+
+use common::Scratch;
+
+use system::Image;
+
+const MAIN: &str = "    .import print
+    .export start
+    .segment code
+start:
+    lda msg
+    jsr print
+    jmp start
+    .segment data
+msg:
+    .dw msg
+";
+
+const LIB: &str = "    .export print
+    .segment code
+print:
+    rts
+";
+
+const MEMORY_MAP: &str = "; segment  start   end
+code       $1000   $1100
+data       $0200
+entry      start
+";
+
+/// Writes and assembles `main.o` and `lib.o`.
+fn objects(scratch: &Scratch) {
+    scratch.write("main.asm", MAIN);
+    scratch.write("lib.asm", LIB);
+    for name in ["main", "lib"] {
+        let source = format!("{}.asm", name);
+        let object = format!("{}.o", name);
+        let run = scratch.run(&[&source, "-f", "obj", "-o", &object]);
+        assert!(run.success, "{}", run.stderr);
+    }
+}
+
+/// Links `objects` with `map` and returns the errors.
+fn link_error(scratch: &Scratch, objects: &[&str], map: &str) -> String {
+    scratch.write("test.map", map);
+    let mut args = vec!["link"];
+    args.extend_from_slice(objects);
+    args.extend_from_slice(&["-m", "test.map", "-o", "out.bin"]);
+    let run = scratch.run(&args);
+    assert!(!run.success, "linked without errors");
+    run.stderr
+}
+
+#[test]
+fn objects_keep_relocations_and_symbols() {
+    let scratch = Scratch::new("object");
+    objects(&scratch);
+    assert_eq!(
+        String::from_utf8(scratch.read("main.o")).unwrap(),
+        "; rs6502 object
+import print
+segment default
+segment code
+data ad00002000004c0000
+reloc $0001 segment data
+reloc $0004 import print
+reloc $0007 segment code
+segment data
+data 0000
+reloc $0000 segment data
+export start $0000 code
+"
+    );
+}
+
+#[test]
+fn objects_are_linked_by_the_memory_map() {
+    let scratch = Scratch::new("link");
+    objects(&scratch);
+    scratch.write("memory.map", MEMORY_MAP);
+
+    let args = [
+        "link",
+        "main.o",
+        "lib.o",
+        "-m",
+        "memory.map",
+        "-f",
+        "ihex",
+        "-o",
+        "out.hex",
+    ];
+    let run = scratch.run(&args);
+    assert!(run.success, "{}", run.stderr);
+    let text = String::from_utf8(scratch.read("out.hex")).unwrap();
+    let image = Image::parse_ihex(&text).unwrap();
+    assert_eq!(image.start, Some(0x1000));
+    assert_eq!(
+        image.segments,
+        [
+            (0x0200, vec![0x00, 0x02]),
+            (
+                0x1000,
+                vec![
+                    0xad, 0x00, 0x02, // lda msg
+                    0x20, 0x09, 0x10, // jsr print
+                    0x4c, 0x00, 0x10, // jmp start
+                    0x60, // print, after main's code
+                ]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn segments_of_a_name_follow_the_order_of_the_objects() {
+    let scratch = Scratch::new("link-order");
+    objects(&scratch);
+    scratch.write("memory.map", MEMORY_MAP);
+
+    let args = [
+        "link",
+        "lib.o",
+        "main.o",
+        "-m",
+        "memory.map",
+        "-f",
+        "ihex",
+        "-o",
+        "out.hex",
+    ];
+    let run = scratch.run(&args);
+    assert!(run.success, "{}", run.stderr);
+    let text = String::from_utf8(scratch.read("out.hex")).unwrap();
+    let image = Image::parse_ihex(&text).unwrap();
+    assert_eq!(image.start, Some(0x1001));
+    assert_eq!(
+        image.segments[1],
+        (
+            0x1000,
+            vec![0x60, 0xad, 0x00, 0x02, 0x20, 0x00, 0x10, 0x4c, 0x01, 0x10]
+        )
+    );
+}
+
+#[test]
+fn linking_needs_every_symbol_and_segment() {
+    let scratch = Scratch::new("link-errors");
+    objects(&scratch);
+
+    let stderr = link_error(&scratch, &["main.o"], MEMORY_MAP);
+    assert!(
+        stderr.contains("main.o: undefined import 'print'"),
+        "{}",
+        stderr
+    );
+
+    let stderr = link_error(&scratch, &["main.o", "lib.o", "lib.o"], MEMORY_MAP);
+    assert!(
+        stderr.contains("lib.o: 'print' is already exported by lib.o"),
+        "{}",
+        stderr
+    );
+
+    let stderr = link_error(&scratch, &["main.o", "lib.o"], "data $0200\n");
+    assert!(
+        stderr.contains("main.o: segment 'code' is not in the memory map"),
+        "{}",
+        stderr
+    );
+
+    let stderr = link_error(
+        &scratch,
+        &["main.o", "lib.o"],
+        "code $1000\ndata $0200\nentry nowhere\n",
+    );
+    assert!(
+        stderr.contains("entry 'nowhere' is not an exported symbol or address"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn segments_must_fit_their_regions() {
+    let scratch = Scratch::new("link-regions");
+    objects(&scratch);
+
+    let stderr = link_error(
+        &scratch,
+        &["main.o", "lib.o"],
+        "code $1000 $1005\ndata $0200\n",
+    );
+    assert!(
+        stderr.contains("main.o: segment 'code' does not fit below $1005"),
+        "{}",
+        stderr
+    );
+
+    let stderr = link_error(&scratch, &["main.o", "lib.o"], "code $0200\ndata $0200\n");
+    assert!(
+        stderr.contains("segment 'code' of main.o overlaps segment 'data' of main.o at $0200"),
+        "{}",
+        stderr
+    );
+
+    let stderr = link_error(&scratch, &["main.o", "lib.o"], "code\n");
+    assert!(stderr.contains("test.map: malformed line 1"), "{}", stderr);
+}
+
+#[test]
+fn only_whole_addresses_are_relocated() {
+    let scratch = Scratch::new("link-bytes");
+    scratch.write("main.asm", "    .segment code\nstart:\n    lda #<start\n");
+    let run = scratch.run(&["main.asm", "-f", "obj", "-o", "main.o"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("3:10: expression cannot be relocated"),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn objects_must_be_well_formed() {
+    let scratch = Scratch::new("link-malformed");
+    scratch.write("bad.o", "garbage\n");
+    let run = scratch.run(&["link", "bad.o"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr.contains("bad.o: malformed record on line 1"),
+        "{}",
+        run.stderr
+    );
+
+    let run = scratch.run(&["link"]);
+    assert!(!run.success);
+    assert!(run.stderr.contains("usage: asm"), "{}", run.stderr);
+}
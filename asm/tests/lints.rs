@@ -0,0 +1,129 @@
+//! Warnings about suspicious code, and the options which control them.
+
+mod common;
+
+use common::Scratch;
+
+const SOURCE: &str = "    .org $1000
+start:
+    bne next
+next:
+    lda later
+    jmp start
+unused:
+    rts
+later .eq $30
+";
+
+/// Assembles `SOURCE` with `options` and returns the warnings.
+fn warnings(scratch: &Scratch, options: &[&str]) -> String {
+    scratch.write("main.asm", SOURCE);
+    let mut args = vec!["main.asm", "-o", "main.bin"];
+    args.extend_from_slice(options);
+    let run = scratch.run(&args);
+    assert!(run.success, "{}", run.stderr);
+    run.stderr
+}
+
+#[test]
+fn suspicious_code_is_flagged() {
+    let scratch = Scratch::new("lints");
+    let stderr = warnings(&scratch, &[]);
+    assert!(
+        stderr.contains("3:5: branch to the next instruction has no effect [-Wbranch-to-next]"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("7:1: label 'unused' is never used [-Wunused-label]"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("[-Wabsolute-zero-page]"), "{}", stderr);
+    assert_eq!(stderr.matches("warning").count(), 3, "{}", stderr);
+    assert_eq!(
+        scratch.read("main.bin"),
+        [0xd0, 0x00, 0xad, 0x30, 0x00, 0x4c, 0x00, 0x10, 0x60]
+    );
+}
+
+#[test]
+fn redefined_macros_are_flagged() {
+    let scratch = Scratch::new("lints-macro");
+    scratch.write("main.asm", "%define X 1\n%define X 2\n    .db X\n");
+    let run = scratch.run(&["main.asm", "-o", "main.bin"]);
+    assert!(run.success, "{}", run.stderr);
+    assert!(
+        run.stderr
+            .contains("2:9: macro 'X' is redefined [-Wredefined-macro]"),
+        "{}",
+        run.stderr
+    );
+    assert!(
+        run.stderr.contains("1:9: previously defined here"),
+        "{}",
+        run.stderr
+    );
+    assert_eq!(scratch.read("main.bin"), [2]);
+}
+
+#[test]
+fn redefined_labels_are_errors() {
+    let scratch = Scratch::new("lints-label");
+    let stderr = scratch.reject("    .org $1000\nx:\n    nop\nx:\n    jmp x\n");
+    assert!(stderr.contains("4:1: 'x' is already defined"), "{}", stderr);
+    assert!(
+        stderr.contains("2:1: previously defined here"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn warnings_can_be_turned_off() {
+    let scratch = Scratch::new("lints-off");
+    let stderr = warnings(&scratch, &["-Wno-branch-to-next", "-Wno-unused-label"]);
+    assert!(!stderr.contains("branch-to-next"), "{}", stderr);
+    assert!(!stderr.contains("unused-label"), "{}", stderr);
+    assert_eq!(stderr.matches("warning").count(), 1, "{}", stderr);
+
+    assert_eq!(warnings(&scratch, &["-w"]), "");
+
+    // a warning can be turned back on after all are turned off
+    let stderr = warnings(&scratch, &["-w", "-Wunused-label"]);
+    assert!(stderr.contains("[-Wunused-label]"), "{}", stderr);
+    assert_eq!(stderr.matches("warning").count(), 1, "{}", stderr);
+}
+
+#[test]
+fn werror_fails_the_build() {
+    let scratch = Scratch::new("lints-werror");
+    scratch.write("main.asm", SOURCE);
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-Werror"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr
+            .contains("warnings are treated as errors (-Werror)"),
+        "{}",
+        run.stderr
+    );
+
+    // without any warnings left -Werror has nothing to fail on
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-Werror", "-w"]);
+    assert!(run.success, "{}", run.stderr);
+}
+
+#[test]
+fn unknown_warnings_are_errors() {
+    let scratch = Scratch::new("lints-unknown");
+    scratch.write("main.asm", SOURCE);
+    for option in ["-Wbogus", "-Wno-bogus"] {
+        let run = scratch.run(&["main.asm", "-o", "main.bin", option]);
+        assert!(!run.success);
+        assert!(
+            run.stderr.contains("unknown warning 'bogus'"),
+            "{}",
+            run.stderr
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Operands which could be read more than one way.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn a_lone_a_is_the_accumulator_where_there_is_one() {
+    let scratch = Scratch::new("accumulator");
+    let bytes = scratch.assemble(
+        "    .org $1000
+    asl a
+    lsr A
+    rol a
+",
+    );
+    assert_eq!(bytes, [0x0a, 0x4a, 0x2a]);
+}
+
+#[test]
+fn a_lone_a_is_a_symbol_where_there_is_no_accumulator() {
+    let scratch = Scratch::new("accumulator-symbol");
+    let bytes = scratch.assemble(
+        "    .org $1000
+    lda a
+    jmp a
+a:  rts
+",
+    );
+    assert_eq!(bytes, [0xad, 0x06, 0x10, 0x4c, 0x06, 0x10, 0x60]);
+}
+
+#[test]
+fn a_symbol_named_a_is_flagged_where_a_is_the_accumulator() {
+    let scratch = Scratch::new("accumulator-label");
+    scratch.write(
+        "main.asm",
+        "    .org $10
+a:  nop
+    asl a
+    asl a+0
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [0xea, 0x0a, 0x06, 0x10]);
+    assert!(run
+        .stderr
+        .contains("'a' is the accumulator here, not the symbol 'a'"));
+    assert!(run.stderr.contains("write 'a+0' to use the symbol"));
+    assert_eq!(run.stderr.matches("warning").count(), 1, "{}", run.stderr);
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-Wno-accumulator-label"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(run.stderr, "");
+}
+
+#[test]
+fn an_a_prefix_forces_absolute_addressing() {
+    let scratch = Scratch::new("forced-absolute");
+    let bytes = scratch.assemble(
+        "zp  .eq $20
+    .org $1000
+    lda $0010
+    lda a:$10
+    lda a:$10,x
+    ldx a:$10,y
+    sta A:zp
+    jmp a:$1000
+",
+    );
+    assert_eq!(
+        bytes,
+        [
+            0xa5, 0x10, // lda $0010 is still promoted
+            0xad, 0x10, 0x00, // lda a:$10
+            0xbd, 0x10, 0x00, // lda a:$10,x
+            0xbe, 0x10, 0x00, // ldx a:$10,y
+            0x8d, 0x20, 0x00, // sta A:zp
+            0x4c, 0x00, 0x10, // jmp a:$1000
+        ]
+    );
+}
+
+#[test]
+fn forced_absolute_addresses_are_not_flagged() {
+    let scratch = Scratch::new("forced-absolute-lint");
+    scratch.write(
+        "main.asm",
+        "zp  .eq $20
+    .org $1000
+    lda a:zp
+    lda later
+later .eq $30
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(
+        run.stderr.matches("fits in zero page").count(),
+        1,
+        "{}",
+        run.stderr
+    );
+    assert!(run.stderr.contains("absolute address $0030"));
+}
+
+#[test]
+fn an_a_prefix_needs_an_absolute_mode() {
+    let scratch = Scratch::new("forced-absolute-errors");
+    let stderr = scratch.reject("    lda a:#$10\n");
+    assert!(
+        stderr.contains("'a:' must be followed by an address"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("    lda a:($10),y\n");
+    assert!(
+        stderr.contains("'a:' must be followed by an address"),
+        "{}",
+        stderr
+    );
+
+    // stx has zero page,y but no absolute,y
+    let stderr = scratch.reject("    stx a:$10,y\n");
+    assert!(
+        stderr.contains("invalid addressing mode for 'stx'"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,87 @@
+//! Writing the preprocessed source with `-E`.
+
+mod common;
+
+use common::Scratch;
+
+const SOURCE: &str = "%define X 2 + 3
+%define DB(v) .db v
+    .org $1000 ; where
+start:  lda #X
+    DB(X*2)
+%rep 2, i
+    .db i
+%endrep
+    jmp start
+";
+
+#[test]
+fn preprocessed_source_keeps_its_spacing() {
+    let scratch = Scratch::new("preprocess");
+    scratch.write("main.asm", SOURCE);
+
+    let run = scratch.run(&["main.asm", "-E"]);
+    assert!(run.success, "{}", run.stderr);
+    // comments are dropped, but the spacing before them is kept
+    assert_eq!(
+        run.stdout,
+        "    .org $1000 \n\
+start:  lda #2 + 3
+    .db 2 + 3*2
+    .db 0
+    .db 1
+    jmp start
+"
+    );
+}
+
+#[test]
+fn preprocessed_source_is_written_to_the_output() {
+    let scratch = Scratch::new("preprocess-output");
+    scratch.write("main.asm", SOURCE);
+
+    let run = scratch.run(&["main.asm", "-E", "-o", "main.i"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(run.stdout, "");
+    let preprocessed = String::from_utf8(scratch.read("main.i")).unwrap();
+    assert!(
+        preprocessed.starts_with("    .org $1000"),
+        "{}",
+        preprocessed
+    );
+
+    // and it assembles to the same bytes as the source
+    let from_source = scratch.assemble(SOURCE);
+    assert_eq!(scratch.assemble(&preprocessed), from_source);
+}
+
+#[test]
+fn expanded_tokens_do_not_run_together() {
+    let scratch = Scratch::new("preprocess-spacing");
+    scratch.write(
+        "main.asm",
+        "%define NAME abc
+%define PAIR(a, b) a b
+    .db PAIR(NAME,NAME)
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-E"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(run.stdout, "    .db abc abc\n");
+}
+
+#[test]
+fn preprocessor_errors_are_reported() {
+    let scratch = Scratch::new("preprocess-errors");
+    scratch.write("main.asm", "%if 1\n    nop\n");
+    let run = scratch.run(&["main.asm", "-E"]);
+    assert!(!run.success);
+    assert_eq!(run.stdout, "");
+    assert!(
+        run.stderr
+            .contains("unterminated conditional block, expected '%endif'"),
+        "{}",
+        run.stderr
+    );
+}
@@ -0,0 +1,121 @@
+//! Blocks repeated with `%rep` and `%endrep`.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn blocks_are_repeated() {
+    let scratch = Scratch::new("rep");
+    let bytes = scratch.assemble(
+        "%rep 3
+    .db 7
+%endrep
+%rep 0
+    .db 1
+%endrep
+%rep 2 * 2
+    nop
+%endrep
+",
+    );
+    assert_eq!(bytes, [7, 7, 7, 0xea, 0xea, 0xea, 0xea]);
+}
+
+#[test]
+fn the_counter_counts_from_zero() {
+    let scratch = Scratch::new("rep-counter");
+    let bytes = scratch.assemble(
+        "%rep 4, i
+    .db i * i
+%endrep
+",
+    );
+    assert_eq!(bytes, [0, 1, 4, 9]);
+}
+
+#[test]
+fn the_counter_is_removed_after_the_block() {
+    let scratch = Scratch::new("rep-counter-scope");
+    let stderr = scratch.reject("%rep 2, i\n%endrep\n    .db i\n");
+    assert!(stderr.contains("undefined symbol 'i'"), "{}", stderr);
+}
+
+#[test]
+fn blocks_nest() {
+    let scratch = Scratch::new("rep-nested");
+    let bytes = scratch.assemble(
+        "%rep 2, i
+%rep 3, j
+    .db i * 10 + j
+%endrep
+%endrep
+",
+    );
+    assert_eq!(bytes, [0, 1, 2, 10, 11, 12]);
+}
+
+#[test]
+fn conditions_are_evaluated_in_each_repetition() {
+    let scratch = Scratch::new("rep-if");
+    let bytes = scratch.assemble(
+        "%rep 4, i
+%if i % 2
+    .db i
+%endif
+%endrep
+",
+    );
+    assert_eq!(bytes, [1, 3]);
+
+    let stderr = scratch.reject("%rep 1\n%if 1\n%endrep\n%endif\n");
+    assert!(
+        stderr.contains("unterminated conditional block, expected '%endif'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn counts_must_be_in_range() {
+    let scratch = Scratch::new("rep-count");
+    let stderr = scratch.reject("%rep -1\n    .db 1\n%endrep\n");
+    assert!(stderr.contains("repeat count -1 is negative"), "{}", stderr);
+
+    let stderr = scratch.reject("%rep 2 - 3\n%endrep\n");
+    assert!(stderr.contains("repeat count -1 is negative"), "{}", stderr);
+
+    let stderr = scratch.reject("%rep 70000\n%endrep\n");
+    assert!(
+        stderr.contains("repeat count 70000 is over the limit of 65536"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn malformed_blocks_are_errors() {
+    let scratch = Scratch::new("rep-errors");
+    let stderr = scratch.reject("%rep 2\n    nop\n");
+    assert!(
+        stderr.contains("unterminated repeat block, expected '%endrep'"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%endrep\n");
+    assert!(
+        stderr.contains("'%endrep' without a matching '%rep'"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("%rep 2,\n%endrep\n");
+    assert!(stderr.contains("expected a counter name"), "{}", stderr);
+
+    let stderr = scratch.reject("%rep 2, 5\n%endrep\n");
+    assert!(stderr.contains("expected a counter name"), "{}", stderr);
+
+    let stderr = scratch.reject("%rep\n%endrep\n");
+    assert!(stderr.contains("expected an expression"), "{}", stderr);
+}
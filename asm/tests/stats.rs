@@ -0,0 +1,77 @@
+//! The figures printed with `--stats`.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn stats_cover_the_whole_program() {
+    let scratch = Scratch::new("stats");
+    scratch.write(
+        "main.asm",
+        "%define PTR $20
+%define BUMP(x) inc x
+    .org $1000
+start:
+    lda (PTR),y
+    sta $30,x
+    BUMP($40)
+    BUMP($40)
+    beq done
+    nop
+    bne start
+done:
+    rts
+    .segment data
+    .org $2000
+    .db 1, 2, 3
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "--stats"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(
+        run.stdout,
+        "segment default: 14 bytes
+segment data: 3 bytes
+symbols: 2
+zero page: 4 bytes
+longest forward branch: 3 bytes at line 9
+longest backward branch: 13 bytes at line 11
+macro BUMP: 2 expansions
+macro PTR: 1 expansion
+"
+    );
+}
+
+#[test]
+fn empty_segments_and_missing_branches_are_left_out() {
+    let scratch = Scratch::new("stats-empty");
+    scratch.write(
+        "main.asm",
+        "    .segment code
+    .org $8000
+    rts
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "--stats"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(
+        run.stdout,
+        "segment code: 1 byte
+symbols: 0
+zero page: 0 bytes
+"
+    );
+}
+
+#[test]
+fn nothing_is_printed_without_the_option() {
+    let scratch = Scratch::new("stats-off");
+    scratch.write("main.asm", "    .org $8000\n    rts\n");
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(run.stdout, "");
+}
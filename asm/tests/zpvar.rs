@@ -0,0 +1,253 @@
+//! Zero page variables allocated with `.zpvar`.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn variables_are_allocated_in_order() {
+    let scratch = Scratch::new("zpvar");
+    scratch.write(
+        "main.asm",
+        "    .zppool $80, $100
+    .zpvar ptr, 2
+    .zpvar count
+    .org $1000
+    lda (ptr),y
+    inc count
+",
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-l", "main.lst"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("main.bin"), [0xb1, 0x80, 0xe6, 0x82]);
+    let listing = String::from_utf8(scratch.read("main.lst")).unwrap();
+    assert!(
+        listing.ends_with("\nzero page:\n    0080  ptr, 2 bytes\n    0082  count, 1 byte\n"),
+        "{}",
+        listing
+    );
+}
+
+#[test]
+fn the_pool_is_all_of_zero_page_by_default() {
+    let scratch = Scratch::new("zpvar-default");
+    let bytes = scratch.assemble(
+        "    .zpvar table, 255
+    .zpvar last
+    .org $1000
+    sta last
+",
+    );
+    assert_eq!(bytes, [0x85, 0xff]);
+
+    let stderr = scratch.reject("    .zpvar table, 256\n    .zpvar more\n");
+    assert!(
+        stderr.contains("zero page pool is full, 'more' needs 1 byte and 0 are left"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn overflowing_the_pool_is_an_error() {
+    let scratch = Scratch::new("zpvar-full");
+    let stderr = scratch.reject(
+        "    .zppool $f0, $100
+    .zpvar ptr, 2
+    .zpvar buffer, 16
+",
+    );
+    assert!(
+        stderr.contains("zero page pool is full, 'buffer' needs 16 bytes and 14 are left"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("pool set here"), "{}", stderr);
+    assert!(
+        stderr.contains("the pool is $f0-$ff, set it with '.zppool'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn malformed_pools_and_variables_are_errors() {
+    let scratch = Scratch::new("zpvar-errors");
+    let stderr = scratch.reject("    .zpvar ptr\n    .zppool $80, $100\n");
+    assert!(
+        stderr.contains("'.zppool' must come before any '.zpvar', 'ptr' is already allocated"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("    .zppool $80, $180\n");
+    assert!(
+        stderr.contains("zero page pool from $80 to $180 is empty or not in zero page"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("    .zpvar ptr, 0\n");
+    assert!(
+        stderr.contains("zero page variable size 0 is not 1 to 256 bytes"),
+        "{}",
+        stderr
+    );
+
+    let stderr = scratch.reject("ptr .eq $10\n    .zpvar ptr\n");
+    assert!(stderr.contains("'ptr' is already defined"), "{}", stderr);
+}
+
+#[test]
+fn linked_objects_cannot_share_zero_page() {
+    let scratch = Scratch::new("zpvar-link");
+    scratch.write("memory.map", "code $1000\n");
+    scratch.write(
+        "a.asm",
+        "    .zppool $80, $90
+    .zpvar ptr, 2
+    .segment code
+    lda (ptr),y
+",
+    );
+    scratch.write(
+        "b.asm",
+        "    .zppool $90, $a0
+    .zpvar count
+    .segment code
+    inc count
+",
+    );
+    scratch.write(
+        "c.asm",
+        "    .zppool $81, $90
+    .zpvar flags
+    .segment code
+    inc flags
+",
+    );
+    for name in ["a", "b", "c"] {
+        let input = format!("{}.asm", name);
+        let output = format!("{}.o", name);
+        let run = scratch.run(&[&input, "-f", "obj", "-o", &output]);
+        assert!(run.success, "{}", run.stderr);
+    }
+    let object = String::from_utf8(scratch.read("a.o")).unwrap();
+    assert!(object.contains("\nzpvar ptr $0080 2\n"), "{}", object);
+
+    let run = scratch.run(&["link", "a.o", "b.o", "-m", "memory.map", "-o", "ab.bin"]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(scratch.read("ab.bin"), [0xb1, 0x80, 0xe6, 0x90]);
+
+    let run = scratch.run(&["link", "a.o", "c.o", "-m", "memory.map", "-o", "ac.bin"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr
+            .contains("c.o: zero page $81-$81 of 'flags' overlaps $80-$81 of 'ptr' in a.o"),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn the_linker_places_variables_of_objects_without_a_pool() {
+    let scratch = Scratch::new("zpvar-place");
+    scratch.write("memory.map", "code $1000\n");
+    scratch.write(
+        "a.asm",
+        "    .zppool $00, $02
+    .zpvar fixed, 2
+    .segment code
+    inc fixed
+",
+    );
+    scratch.write(
+        "b.asm",
+        "    .zpvar ptr, 2
+    .export ptr
+    .segment code
+    lda (ptr),y
+    sta ptr+1
+    .dw ptr
+",
+    );
+    scratch.write(
+        "c.asm",
+        "    .zpvar tmp
+    .import ptr
+    .segment code
+    inc tmp
+    .db tmp
+    lda ptr
+",
+    );
+    for name in ["a", "b", "c"] {
+        let input = format!("{}.asm", name);
+        let output = format!("{}.o", name);
+        let run = scratch.run(&[&input, "-f", "obj", "-o", &output]);
+        assert!(run.success, "{}", run.stderr);
+    }
+    let object = String::from_utf8(scratch.read("b.o")).unwrap();
+    assert!(
+        object.contains("\nzpvar ptr $0000 2 zeropage\n"),
+        "{}",
+        object
+    );
+    assert!(object.contains("\nreloc $0001 zeropage\n"), "{}", object);
+
+    let run = scratch.run(&[
+        "link",
+        "a.o",
+        "b.o",
+        "c.o",
+        "-m",
+        "memory.map",
+        "-o",
+        "abc.bin",
+    ]);
+    assert!(run.success, "{}", run.stderr);
+    assert_eq!(
+        scratch.read("abc.bin"),
+        [
+            0xe6, 0x00, // inc fixed
+            0xb1, 0x02, 0x85, 0x03, 0x02, 0x00, // lda (ptr),y; sta ptr+1; .dw ptr
+            0xe6, 0x04, 0x04, 0xad, 0x02, 0x00, // inc tmp; .db tmp; lda ptr
+        ]
+    );
+
+    // only a program on its own knows where its variables are
+    let bytes = scratch.assemble("    .zpvar tmp\n    .org $1000\n    lda tmp+$100\n");
+    assert_eq!(bytes, [0xad, 0x00, 0x01]);
+    scratch.write(
+        "d.asm",
+        "    .zpvar tmp\n    .segment code\n    lda tmp+$100\n",
+    );
+    let run = scratch.run(&["d.asm", "-f", "obj", "-o", "d.o"]);
+    assert!(!run.success);
+    assert!(
+        run.stderr
+            .contains("zero page address $100 past the zero page variables is not in zero page"),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn variables_overlapping_the_program_are_a_warning() {
+    let scratch = Scratch::new("zpvar-overlap");
+    scratch.write("main.asm", "    .zpvar tmp\n    inc tmp\n");
+    let run = scratch.run(&["main.asm", "-o", "main.bin"]);
+    assert!(run.success, "{}", run.stderr);
+    assert!(
+        run.stderr.contains(
+            "zero page variable 'tmp' at $00-$00 overlaps the bytes of segment 'default'"
+        ),
+        "{}",
+        run.stderr
+    );
+
+    let run = scratch.run(&["main.asm", "-o", "main.bin", "-Wno-zero-page-overlap"]);
+    assert!(run.success, "{}", run.stderr);
+    assert!(!run.stderr.contains("warning"), "{}", run.stderr);
+}
@@ -0,0 +1,110 @@
+use std::ops::Range;
+
+/// One bit for each address in memory.
+#[derive(Clone, PartialEq, Eq)]
+struct Bitmap(Box<[u64; 0x10000 / 64]>);
+
+impl Bitmap {
+    fn new() -> Self {
+        Self(Box::new([0; 0x10000 / 64]))
+    }
+
+    fn get(&self, address: u16) -> bool {
+        return self.0[usize::from(address / 64)] & (1 << (address % 64)) != 0;
+    }
+
+    fn set(&mut self, address: u16) {
+        self.0[usize::from(address / 64)] |= 1 << (address % 64);
+    }
+
+    fn count(&self) -> usize {
+        return self.0.iter().map(|word| word.count_ones() as usize).sum();
+    }
+}
+
+/// The addresses a program has executed, read and written while running under
+/// a [`crate::Debugger`] with coverage enabled.
+///
+/// The opcode and operand bytes of every instruction count as executed rather
+/// than read. Opcode fetches of interrupted instructions do not count at all.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Coverage {
+    executed: Bitmap,
+    read: Bitmap,
+    written: Bitmap,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            executed: Bitmap::new(),
+            read: Bitmap::new(),
+            written: Bitmap::new(),
+        }
+    }
+
+    pub fn is_executed(&self, address: u16) -> bool {
+        return self.executed.get(address);
+    }
+
+    pub fn is_read(&self, address: u16) -> bool {
+        return self.read.get(address);
+    }
+
+    pub fn is_written(&self, address: u16) -> bool {
+        return self.written.get(address);
+    }
+
+    /// Returns how many addresses in `range` were executed.
+    pub fn executed_in(&self, range: Range<u16>) -> usize {
+        return range.filter(|address| self.executed.get(*address)).count();
+    }
+
+    /// Returns the runs of addresses in `range` which were never executed, such
+    /// as the parts of a ROM no test reached.
+    pub fn unexecuted(&self, range: Range<u16>) -> Vec<Range<u16>> {
+        let mut runs: Vec<Range<u16>> = vec![];
+        for address in range.filter(|address| !self.executed.get(*address)) {
+            match runs.last_mut() {
+                Some(run) if run.end == address => run.end += 1,
+                _ => runs.push(address..address + 1),
+            }
+        }
+        return runs;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    //
+
+    pub(crate) fn execute(&mut self, address: u16) {
+        self.executed.set(address);
+    }
+
+    pub(crate) fn read(&mut self, address: u16) {
+        self.read.set(address);
+    }
+
+    pub(crate) fn write(&mut self, address: u16) {
+        self.written.set(address);
+    }
+}
+
+impl std::fmt::Debug for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f
+            .debug_struct("Coverage")
+            .field("executed", &self.executed.count())
+            .field("read", &self.read.count())
+            .field("written", &self.written.count())
+            .finish();
+    }
+}
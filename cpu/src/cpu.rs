@@ -1,18 +1,89 @@
-use crate::microcode::{ucode_reset, Context, MicroOp};
+use crate::microcode::{ucode_irq, ucode_nmi, ucode_reset, Context, MicroOp};
 use crate::opcode;
-use crate::registers::{Registers, StatusFlags};
+use crate::profile::Profile;
+use crate::registers::{Register, Registers, StatusFlags};
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::trace::{Trace, TracedBus, Tracer};
 use crate::utility;
 use crate::Bus;
 
+/// The CPU model being emulated.
+///
+/// The models differ in their instruction set and a few hardware quirks:
+///   - The 65C02 adds `BRA`, `PHX`/`PHY`/`PLX`/`PLY`, `STZ`, `TRB`/`TSB`,
+///     `INC A`/`DEC A`, more `BIT` modes, `JMP (abs,X)` and the `(zp)`
+///     addressing mode. The undocumented NMOS opcodes are NOPs on the 65C02.
+///   - `JMP ($xxFF)` reads the high byte of the target from `$xx00` on the NMOS
+///     part. The 65C02 reads it from the next page and takes one more cycle.
+///   - The 65C02 clears the decimal flag when it takes an interrupt or a `BRK`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CpuVariant {
+    #[default]
+    Nmos6502,
+    Cmos65C02,
+}
+
+/// An opcode with no defined behavior which locked up the CPU, like the `KIL`
+/// opcodes of the NMOS part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Jam {
+    pub opcode: u8,
+    /// The address the opcode was fetched from.
+    pub pc: u16,
+}
+
+impl std::fmt::Display for Jam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(
+            f,
+            "cpu jammed by opcode ${:02x} at ${:04x}",
+            self.opcode, self.pc
+        );
+    }
+}
+
+/// Why [`Cpu::run_until_pc_stable`] stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunResult {
+    /// An instruction left the PC at this address.
+    Trapped(u16),
+    /// The CPU jammed. Its PC stays put as well, but it is not a trap.
+    Jammed(Jam),
+    /// The instruction limit was reached first.
+    LimitReached,
+}
+
+/// What the pipeline being executed was decoded from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Sequence {
+    Opcode(u8),
+    Irq,
+    Nmi,
+}
+
 pub struct Cpu {
     pub registers: Registers,
     pub status: StatusFlags,
     pub pins: Pins,
 
     cycle: u64,
+    variant: CpuVariant,
+    accurate_bus: bool,
+    nmi_line: bool,
+    nmi_pending: bool,
+    jam: Option<Jam>,
     index: usize,
     ctx: Context,
     pipeline: Option<&'static [MicroOp]>,
+    sequence: Sequence,
+    tracer: Option<Tracer>,
+    profile: Option<Profile>,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cpu {
@@ -20,6 +91,9 @@ impl Cpu {
     pub const RES_VECTOR: u16 = 0xFFFC;
     pub const IRQ_VECTOR: u16 = 0xFFFE;
 
+    const STATE_MAGIC: &'static [u8; 4] = b"6502";
+    const STATE_VERSION: u8 = 1;
+
     pub fn new() -> Self {
         Self {
             registers: Registers::new(),
@@ -27,17 +101,132 @@ impl Cpu {
             pins: Pins::from(Pins::IRQ | Pins::NMI | Pins::SYNC),
 
             cycle: 0,
+            variant: CpuVariant::Nmos6502,
+            accurate_bus: false,
+            nmi_line: true,
+            nmi_pending: false,
+            jam: None,
             index: 0,
             ctx: Context::new(),
             pipeline: None,
+            sequence: Sequence::Opcode(0),
+            tracer: None,
+            profile: None,
         }
     }
 
-    pub fn reset(&mut self, bus: &mut dyn Bus) {
+    /// Returns a builder for a CPU in a given state, without running the reset
+    /// sequence.
+    pub fn builder() -> CpuBuilder {
+        return CpuBuilder::new();
+    }
+
+    /// Returns a CPU which emulates the given model.
+    pub fn with_variant(variant: CpuVariant) -> Self {
+        let mut cpu = Self::new();
+        cpu.variant = variant;
+        return cpu;
+    }
+
+    /// Returns the number of cycles elapsed since power-on.
+    pub fn cycles(&self) -> u64 {
+        return self.cycle;
+    }
+
+    pub fn variant(&self) -> CpuVariant {
+        return self.variant;
+    }
+
+    /// Sets the CPU model whose quirks are emulated.
+    pub fn set_variant(&mut self, variant: CpuVariant) {
+        self.variant = variant;
+    }
+
+    /// Returns whether the dummy bus reads of the real hardware are performed.
+    pub fn accurate_bus(&self) -> bool {
+        return self.accurate_bus;
+    }
+
+    /// Enables or disables the dummy bus reads the real hardware performs in
+    /// its internal cycles, such as the read of the next byte by implied
+    /// instructions, of the stack by pulls and of the unfixed address by
+    /// indexed accesses. With it every cycle makes one bus access. These reads
+    /// are visible to devices, so they are off by default.
+    pub fn set_accurate_bus(&mut self, enabled: bool) {
+        self.accurate_bus = enabled;
+    }
+
+    /// Drives the (active low) IRQ pin. The interrupt is serviced at the next
+    /// instruction boundary for as long as the line is asserted and the I flag is
+    /// clear.
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.pins = self.pins.with_irq(!asserted);
+    }
+
+    /// Drives the (active low) NMI pin. The interrupt is edge triggered, so it is
+    /// serviced once each time the line goes from released to asserted.
+    pub fn set_nmi_line(&mut self, asserted: bool) {
+        self.pins = self.pins.with_nmi(!asserted);
+    }
+
+    /// Returns the opcode which locked up the CPU, if any.
+    ///
+    /// A jammed CPU ignores interrupts and only lets the clock run until it is
+    /// reset.
+    pub fn jammed(&self) -> Option<Jam> {
+        return self.jam;
+    }
+
+    /// Registers a trace which is told about every instruction and bus access
+    /// from now on. It replaces any trace registered before.
+    pub fn set_trace(&mut self, trace: impl Trace + Send + 'static) {
+        self.tracer = Some(Tracer::new(Box::new(trace)));
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.tracer = None;
+    }
+
+    /// Starts counting the instructions run and the cycles they take, by address
+    /// and by mnemonic. Anything counted before is discarded.
+    pub fn start_profiling(&mut self) {
+        self.profile = Some(Profile::new());
+    }
+
+    /// Stops profiling and returns what was counted.
+    pub fn stop_profiling(&mut self) -> Option<Profile> {
+        return self.profile.take();
+    }
+
+    /// Returns what has been counted so far, if profiling.
+    pub fn profile(&self) -> Option<&Profile> {
+        return self.profile.as_ref();
+    }
+
+    /// Performs a power-on (hard) reset.
+    ///
+    /// All registers, flags and pins are cleared and the cycle counter is zeroed
+    /// before running the normal reset sequence.
+    pub fn power_on(&mut self, bus: &mut dyn Bus) {
+        self.registers = Registers::new();
+        self.status = StatusFlags::new();
+        self.pins = Pins::from(Pins::IRQ | Pins::NMI | Pins::SYNC);
         self.cycle = 0;
+        self.nmi_line = true;
+        self.reset(bus);
+    }
+
+    /// Performs a soft reset.
+    ///
+    /// Only the reset sequence is run: the A, X and Y registers are preserved, the
+    /// stack pointer is decremented by three and the PC is loaded from the reset
+    /// vector.
+    pub fn reset(&mut self, bus: &mut dyn Bus) {
         self.index = 0;
         self.ctx = Context::new();
         self.pipeline = None;
+        self.nmi_pending = false;
+        self.jam = None;
 
         let mut ctx = Context::new();
         let ops = ucode_reset();
@@ -47,7 +236,116 @@ impl Cpu {
         }
     }
 
-    pub fn step_instruction(&mut self, bus: &mut dyn Bus) {
+    /// Saves the complete state of the CPU, including an instruction which is
+    /// only partly executed. The registered trace is not part of the state.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(Self::STATE_MAGIC, Self::STATE_VERSION);
+        state.u8(self.registers.acc.get());
+        state.u8(self.registers.x.get());
+        state.u8(self.registers.y.get());
+        state.u8(self.registers.sp.get());
+        state.u16(self.registers.pc.get());
+        state.u8(self.status.get_raw());
+        state.u8(self.pins.get_raw());
+        state.u64(self.cycle);
+        state.u8(match self.variant {
+            CpuVariant::Nmos6502 => 0,
+            CpuVariant::Cmos65C02 => 1,
+        });
+        state.bool(self.accurate_bus);
+        state.bool(self.nmi_line);
+        state.bool(self.nmi_pending);
+
+        // the pipeline is saved as what it was decoded from
+        match (self.jam, self.pipeline, self.sequence) {
+            (Some(jam), _, _) => state.bytes(&[4, jam.opcode]),
+            (None, None, _) => state.bytes(&[0, 0]),
+            (None, Some(_), Sequence::Opcode(opcode)) => state.bytes(&[1, opcode]),
+            (None, Some(_), Sequence::Irq) => state.bytes(&[2, 0]),
+            (None, Some(_), Sequence::Nmi) => state.bytes(&[3, 0]),
+        }
+        state.u8(self.index as u8);
+        state.u8(self.ctx.temp.get());
+        state.bytes(&self.ctx.stack);
+        state.u8(self.ctx.ptr);
+        return state.finish();
+    }
+
+    /// Restores a state saved with [`Cpu::save_state`]. The CPU is left
+    /// untouched if the state cannot be restored.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (mut state, _) = StateReader::new(state, Self::STATE_MAGIC, Self::STATE_VERSION)?;
+        let mut registers = Registers::new();
+        registers.acc.set(state.u8()?);
+        registers.x.set(state.u8()?);
+        registers.y.set(state.u8()?);
+        registers.sp.set(state.u8()?);
+        registers.pc.set(state.u16()?);
+        let mut status = StatusFlags::new();
+        status.set_raw(state.u8()?);
+        let pins = Pins::from(state.u8()?);
+        let cycle = state.u64()?;
+        let variant = match state.u8()? {
+            0 => CpuVariant::Nmos6502,
+            1 => CpuVariant::Cmos65C02,
+            _ => return Err(StateError::Invalid("cpu variant")),
+        };
+        let accurate_bus = state.bool()?;
+        let nmi_line = state.bool()?;
+        let nmi_pending = state.bool()?;
+
+        let mut jam = None;
+        let (pipeline, sequence) = match (state.u8()?, state.u8()?) {
+            (0, _) => (None, Sequence::Opcode(0)),
+            (1, opcode) => match opcode::decode_instruction(variant, opcode) {
+                Some(ucode) => (Some(ucode), Sequence::Opcode(opcode)),
+                None => return Err(StateError::Invalid("pipeline")),
+            },
+            (2, _) => (Some(ucode_irq()), Sequence::Irq),
+            (3, _) => (Some(ucode_nmi()), Sequence::Nmi),
+            (4, opcode) => {
+                let pc = registers.pc.get();
+                jam = Some(Jam { opcode, pc });
+                (None, Sequence::Opcode(opcode))
+            }
+            _ => return Err(StateError::Invalid("pipeline")),
+        };
+        let index = state.u8()? as usize;
+        if pipeline.is_some_and(|pipeline| index >= pipeline.len()) {
+            return Err(StateError::Invalid("pipeline index"));
+        }
+        let mut ctx = Context::new();
+        ctx.temp = Register::new(state.u8()?);
+        let stack = state.bytes(ctx.stack.len())?;
+        ctx.stack.copy_from_slice(stack);
+        ctx.ptr = state.u8()?;
+        if ctx.ptr as usize > ctx.stack.len() {
+            return Err(StateError::Invalid("context"));
+        }
+        state.finish()?;
+
+        self.registers = registers;
+        self.status = status;
+        self.pins = pins;
+        self.cycle = cycle;
+        self.variant = variant;
+        self.accurate_bus = accurate_bus;
+        self.nmi_line = nmi_line;
+        self.nmi_pending = nmi_pending;
+        self.jam = jam;
+        self.pipeline = pipeline;
+        self.sequence = sequence;
+        self.index = index;
+        self.ctx = ctx;
+        return Ok(());
+    }
+
+    /// Runs until the end of the current instruction and returns the number of
+    /// cycles it took, including any page crossing or branch penalties.
+    ///
+    /// A jammed CPU only lets one cycle pass, see [`Cpu::jammed`].
+    pub fn step_instruction(&mut self, bus: &mut dyn Bus) -> u64 {
+        let start = self.cycle;
         if self.pipeline.is_none() {
             self.step_cycle(bus); // fetch next instruction
         }
@@ -55,32 +353,127 @@ impl Cpu {
         while self.pipeline.is_some() {
             self.step_cycle(bus);
         }
+        return self.cycle - start;
+    }
+
+    /// Runs whole instructions until at least `cycles` cycles have elapsed and
+    /// returns by how many cycles the budget was overshot. The overshoot is meant
+    /// to be taken off the next time slice.
+    pub fn run_for_cycles(&mut self, bus: &mut dyn Bus, cycles: u64) -> u64 {
+        let end = self.cycle + cycles;
+        while self.cycle < end {
+            self.step_instruction(bus);
+        }
+        return self.cycle - end;
+    }
+
+    /// Runs whole instructions until `done` returns true and returns the number of
+    /// cycles that elapsed. `done` is checked at every instruction boundary,
+    /// starting before the first instruction.
+    pub fn run_until(&mut self, bus: &mut dyn Bus, mut done: impl FnMut(&Cpu) -> bool) -> u64 {
+        let start = self.cycle;
+        while !done(self) {
+            self.step_instruction(bus);
+        }
+        return self.cycle - start;
+    }
+
+    /// Runs whole instructions until one leaves the PC where it was and returns
+    /// that address. This is how test ROMs signal they are done, with a `jmp *`
+    /// or a branch to itself. Stops early if the CPU is or becomes jammed.
+    pub fn run_until_pc_stable(&mut self, bus: &mut dyn Bus, max_instructions: u64) -> RunResult {
+        for _ in 0..max_instructions {
+            let pc = self.registers.pc.get();
+            self.step_instruction(bus);
+            if let Some(jam) = self.jam {
+                return RunResult::Jammed(jam);
+            }
+            if self.registers.pc.get() == pc {
+                return RunResult::Trapped(pc);
+            }
+        }
+        return RunResult::LimitReached;
     }
 
     pub fn step_cycle(&mut self, bus: &mut dyn Bus) {
-        self.cycle(bus);
+        if self.jam.is_some() {
+            // the clock keeps running but nothing else happens
+            self.cycle += 1;
+            return;
+        }
+
+        let mut tracer = match self.tracer.take() {
+            Some(tracer) => tracer,
+            None => {
+                self.cycle(bus);
+                return;
+            }
+        };
+
+        if self.pipeline.is_none() {
+            tracer.begin(self);
+        }
+        self.cycle(&mut TracedBus::new(bus, &mut tracer, self.cycle));
+        if self.pipeline.is_none() {
+            tracer.finish();
+        }
+        self.tracer = Some(tracer);
+    }
+
+    /// Returns whether the previous instruction has completed and the next one
+    /// has not been fetched yet. It is false while an instruction is mid-flight
+    /// after [`Cpu::step_cycle`].
+    pub fn at_instruction_boundary(&self) -> bool {
+        return self.pipeline.is_none();
     }
 
     //
 
     fn cycle(&mut self, bus: &mut dyn Bus) {
+        // the nmi line is edge triggered so it has to be watched every cycle
+        let nmi = self.pins.get_nmi();
+        if self.nmi_line && !nmi {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = nmi;
+
         if self.pipeline.is_none() {
+            if let Some((ucode, sequence)) = self.poll_interrupts() {
+                // the opcode fetch is performed but its result is ignored
+                if self.accurate_bus {
+                    bus.read(self.registers.pc.get());
+                }
+
+                self.ctx = Context::new();
+                self.index = 0;
+                self.pipeline = Some(ucode);
+                self.sequence = sequence;
+                self.cycle += 1;
+                return;
+            }
+
             // fetch & decode next instruction
             let pc = self.registers.pc.get();
-            self.registers.pc.set(pc + 1); // increment pc
+            let op = bus.fetch(pc);
+            if let Some(profile) = self.profile.as_mut() {
+                profile.begin(pc, self.cycle);
+            }
+            self.cycle += 1;
 
-            let op = bus.read(pc);
-            let ucode = opcode::decode_instruction(op);
-            // println!(
-            //     "opcode: {} [{:02x}]",
-            //     opcode::decode_instruction_to_string(op),
-            //     op
-            // );
+            let ucode = match opcode::decode_instruction(self.variant, op) {
+                Some(ucode) => ucode,
+                None => {
+                    // the pc is left on the opcode
+                    self.jam = Some(Jam { opcode: op, pc });
+                    return;
+                }
+            };
+            self.registers.pc.set(pc.wrapping_add(1)); // increment pc
 
             self.ctx = Context::new();
             self.index = 0;
             self.pipeline = Some(ucode);
-            self.cycle += 1;
+            self.sequence = Sequence::Opcode(op);
             return;
         }
 
@@ -109,6 +502,107 @@ impl Cpu {
                 break;
             }
         }
+
+        if let (None, Some(profile), Sequence::Opcode(op)) =
+            (self.pipeline, self.profile.as_mut(), self.sequence)
+        {
+            profile.finish(self.variant, op, self.cycle);
+        }
+    }
+
+    /// Returns the interrupt sequence to run instead of the next instruction, if
+    /// any. NMI takes priority over IRQ.
+    fn poll_interrupts(&mut self) -> Option<(&'static [MicroOp], Sequence)> {
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            return Some((ucode_nmi(), Sequence::Nmi));
+        }
+        if !self.pins.get_irq() && !self.status.get_irq_disable() {
+            return Some((ucode_irq(), Sequence::Irq));
+        }
+        return None;
+    }
+}
+
+/// Builds a [`Cpu`] with its registers already set, as if it had been reset and
+/// then run up to some point. Tests use it to start an instruction from a known
+/// state.
+///
+/// Unless set, the registers are zero, SP is $FD and only the I and B flags are
+/// set, as after the reset sequence.
+pub struct CpuBuilder {
+    registers: Registers,
+    status: StatusFlags,
+    variant: CpuVariant,
+    accurate_bus: bool,
+}
+
+impl Default for CpuBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CpuBuilder {
+    pub fn new() -> Self {
+        let mut registers = Registers::new();
+        registers.sp.set(0xFD);
+        Self {
+            registers,
+            status: StatusFlags::from(StatusFlags::INTERRUPT | StatusFlags::BREAK),
+            variant: CpuVariant::Nmos6502,
+            accurate_bus: false,
+        }
+    }
+
+    pub fn a(mut self, value: u8) -> Self {
+        self.registers.acc.set(value);
+        self
+    }
+
+    pub fn x(mut self, value: u8) -> Self {
+        self.registers.x.set(value);
+        self
+    }
+
+    pub fn y(mut self, value: u8) -> Self {
+        self.registers.y.set(value);
+        self
+    }
+
+    pub fn sp(mut self, value: u8) -> Self {
+        self.registers.sp.set(value);
+        self
+    }
+
+    pub fn pc(mut self, value: u16) -> Self {
+        self.registers.pc.set(value);
+        self
+    }
+
+    /// Sets the raw status register, `NV-BDIZC` from bit 7 down.
+    pub fn status(mut self, value: u8) -> Self {
+        self.status.set_raw(value);
+        self
+    }
+
+    pub fn variant(mut self, variant: CpuVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// See [`Cpu::set_accurate_bus`].
+    pub fn accurate_bus(mut self, enabled: bool) -> Self {
+        self.accurate_bus = enabled;
+        self
+    }
+
+    pub fn build(self) -> Cpu {
+        let mut cpu = Cpu::with_variant(self.variant);
+        cpu.registers = self.registers;
+        cpu.status = self.status;
+        cpu.accurate_bus = self.accurate_bus;
+        return cpu;
     }
 }
 
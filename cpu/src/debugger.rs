@@ -0,0 +1,382 @@
+//! Breakpoints and watchpoints, which can be saved to a breakpoint file so a
+//! debugging session can be resumed later. The file has one point per line:
+//! ```text
+//!     break $1000
+//!     watch write $0010
+//!     watch access $a000 ; the console
+//! ```
+//! Blank lines and anything after a `;` are ignored.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+
+use crate::coverage::Coverage;
+use crate::cpu::{Cpu, CpuVariant};
+use crate::opcode;
+use crate::Bus;
+
+/// The kind of bus access a watchpoint stops on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watch {
+    Read,
+    Write,
+    /// Either a read or a write.
+    Access,
+}
+
+/// A line of a breakpoint file which could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreakpointError {
+    /// The line number, starting at 1.
+    pub line: usize,
+}
+
+impl std::fmt::Display for BreakpointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "malformed breakpoint on line {}", self.line);
+    }
+}
+
+impl std::error::Error for BreakpointError {}
+
+/// The reason a [`Debugger`] stopped execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hit {
+    /// The next instruction is at a breakpoint. It has not been run yet.
+    Breakpoint(u16),
+    /// The instruction which just ran read from a watched address.
+    Read { address: u16, data: u8 },
+    /// The instruction which just ran wrote to a watched address.
+    Write { address: u16, data: u8 },
+}
+
+/// The outcome of a single [`Debugger::step_instruction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepResult {
+    /// The instruction ran to completion in the given number of cycles.
+    Completed(u64),
+    Hit(Hit),
+}
+
+/// Breakpoints on the PC and watchpoints on bus addresses.
+///
+/// Stepping through the debugger stops before an instruction at a breakpoint
+/// is run, and after an instruction which touched a watched address. Stepping
+/// again after a breakpoint runs the instruction, so a program can be resumed
+/// from where it stopped. Opcode fetches do not trigger watchpoints.
+///
+/// A temporary breakpoint stops once and is then removed. [`Debugger::run_to`]
+/// uses one to run to an address.
+///
+/// The debugger can also record the [`Coverage`] of the instructions stepped
+/// through it.
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    temporary: BTreeSet<u16>,
+    reads: BTreeSet<u16>,
+    writes: BTreeSet<u16>,
+    /// The breakpoint the last step stopped on.
+    resume: Option<u16>,
+    coverage: Option<Coverage>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Removes a breakpoint and returns whether it was set.
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        return self.breakpoints.remove(&address);
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        return self.breakpoints.iter().copied();
+    }
+
+    /// Adds a breakpoint which is removed the first time it stops execution.
+    pub fn add_temporary_breakpoint(&mut self, address: u16) {
+        self.temporary.insert(address);
+    }
+
+    /// Removes a temporary breakpoint and returns whether it was set.
+    pub fn remove_temporary_breakpoint(&mut self, address: u16) -> bool {
+        return self.temporary.remove(&address);
+    }
+
+    pub fn temporary_breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        return self.temporary.iter().copied();
+    }
+
+    pub fn add_watchpoint(&mut self, address: u16, watch: Watch) {
+        if watch != Watch::Write {
+            self.reads.insert(address);
+        }
+        if watch != Watch::Read {
+            self.writes.insert(address);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, address: u16, watch: Watch) {
+        if watch != Watch::Write {
+            self.reads.remove(&address);
+        }
+        if watch != Watch::Read {
+            self.writes.remove(&address);
+        }
+    }
+
+    /// Returns every watchpoint, ordered by address.
+    pub fn watchpoints(&self) -> impl Iterator<Item = (u16, Watch)> + '_ {
+        let addresses: BTreeSet<u16> = self.reads.union(&self.writes).copied().collect();
+        return addresses.into_iter().map(|address| {
+            let watch = match (
+                self.reads.contains(&address),
+                self.writes.contains(&address),
+            ) {
+                (true, true) => Watch::Access,
+                (true, false) => Watch::Read,
+                (_, _) => Watch::Write,
+            };
+            (address, watch)
+        });
+    }
+
+    /// Formats the breakpoints and watchpoints as a breakpoint file. Temporary
+    /// breakpoints are left out.
+    pub fn save(&self) -> String {
+        let mut text = String::new();
+        for address in self.breakpoints() {
+            text.push_str(&format!("break ${:04x}\n", address));
+        }
+        for (address, watch) in self.watchpoints() {
+            let watch = match watch {
+                Watch::Read => "read",
+                Watch::Write => "write",
+                Watch::Access => "access",
+            };
+            text.push_str(&format!("watch {} ${:04x}\n", watch, address));
+        }
+        return text;
+    }
+
+    /// Replaces the breakpoints and watchpoints with those of a breakpoint
+    /// file. Nothing is changed if the file cannot be read.
+    pub fn load(&mut self, text: &str) -> Result<(), BreakpointError> {
+        let mut loaded = Debugger::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = BreakpointError { line: index + 1 };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let address = |word: &str| {
+                let address = word.strip_prefix('$').ok_or(error.clone())?;
+                return u16::from_str_radix(address, 16).map_err(|_| error.clone());
+            };
+            match words[..] {
+                ["break", word] => loaded.add_breakpoint(address(word)?),
+                ["watch", watch, word] => {
+                    let watch = match watch {
+                        "read" => Watch::Read,
+                        "write" => Watch::Write,
+                        "access" => Watch::Access,
+                        _ => return Err(error),
+                    };
+                    loaded.add_watchpoint(address(word)?, watch);
+                }
+                _ => return Err(error),
+            }
+        }
+
+        self.breakpoints = loaded.breakpoints;
+        self.reads = loaded.reads;
+        self.writes = loaded.writes;
+        return Ok(());
+    }
+
+    /// Removes all breakpoints and watchpoints.
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.temporary.clear();
+        self.reads.clear();
+        self.writes.clear();
+        self.resume = None;
+    }
+
+    /// Starts recording the addresses executed, read and written. Anything
+    /// recorded before is discarded.
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    /// Stops recording coverage and returns what was recorded.
+    pub fn stop_coverage(&mut self) -> Option<Coverage> {
+        return self.coverage.take();
+    }
+
+    /// Returns the coverage recorded so far, if recording.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        return self.coverage.as_ref();
+    }
+
+    /// Runs the next instruction unless it is at a breakpoint.
+    pub fn step_instruction(&mut self, cpu: &mut Cpu, bus: &mut dyn Bus) -> StepResult {
+        if cpu.at_instruction_boundary() {
+            let pc = cpu.registers.pc.get();
+            let temporary = self.temporary.contains(&pc);
+            if self.resume.take() != Some(pc) && (temporary || self.breakpoints.contains(&pc)) {
+                self.temporary.remove(&pc);
+                self.resume = Some(pc);
+                return StepResult::Hit(Hit::Breakpoint(pc));
+            }
+        }
+
+        if self.reads.is_empty() && self.writes.is_empty() && self.coverage.is_none() {
+            return StepResult::Completed(cpu.step_instruction(bus));
+        }
+
+        let coverage = self.coverage.take().map(RefCell::new);
+        let mut bus = WatchedBus {
+            bus,
+            debugger: self,
+            variant: cpu.variant(),
+            hit: Cell::new(None),
+            operands: Cell::new((0, 0)),
+            coverage,
+        };
+        let cycles = cpu.step_instruction(&mut bus);
+        let WatchedBus { hit, coverage, .. } = bus;
+        self.coverage = coverage.map(RefCell::into_inner);
+        return match hit.get() {
+            Some(hit) => StepResult::Hit(hit),
+            None => StepResult::Completed(cycles),
+        };
+    }
+
+    /// Runs instructions until one stops execution and returns why.
+    ///
+    /// Running resumes from the current instruction, so a breakpoint on the PC
+    /// it starts from is not hit until it is reached again. Returns `None` if
+    /// the CPU jams or `max_instructions` run first.
+    pub fn run(&mut self, cpu: &mut Cpu, bus: &mut dyn Bus, max_instructions: u64) -> Option<Hit> {
+        return self.run_with(cpu, bus, max_instructions, |_, _| {});
+    }
+
+    /// Like [`Debugger::run`], but calls `before` ahead of every instruction,
+    /// e.g. to tick devices and set the IRQ line.
+    pub fn run_with<B: Bus + ?Sized>(
+        &mut self,
+        cpu: &mut Cpu,
+        mut bus: &mut B,
+        max_instructions: u64,
+        mut before: impl FnMut(&mut Cpu, &mut B),
+    ) -> Option<Hit> {
+        if cpu.at_instruction_boundary() {
+            self.resume = Some(cpu.registers.pc.get());
+        }
+        for _ in 0..max_instructions {
+            before(cpu, bus);
+            if let StepResult::Hit(hit) = self.step_instruction(cpu, &mut bus) {
+                return Some(hit);
+            }
+            if cpu.jammed().is_some() {
+                return None;
+            }
+        }
+        return None;
+    }
+
+    /// Runs until the next instruction is at `address`, like [`Debugger::run`]
+    /// with a temporary breakpoint there. The breakpoint is removed whatever
+    /// execution stops for, so an earlier breakpoint or watchpoint leaves no
+    /// trace of it.
+    pub fn run_to(
+        &mut self,
+        cpu: &mut Cpu,
+        bus: &mut dyn Bus,
+        address: u16,
+        max_instructions: u64,
+    ) -> Option<Hit> {
+        return self.run_to_with(cpu, bus, address, max_instructions, |_, _| {});
+    }
+
+    /// Like [`Debugger::run_to`], but calls `before` ahead of every
+    /// instruction.
+    pub fn run_to_with<B: Bus + ?Sized>(
+        &mut self,
+        cpu: &mut Cpu,
+        bus: &mut B,
+        address: u16,
+        max_instructions: u64,
+        before: impl FnMut(&mut Cpu, &mut B),
+    ) -> Option<Hit> {
+        let added = self.temporary.insert(address);
+        let hit = self.run_with(cpu, bus, max_instructions, before);
+        if added {
+            self.temporary.remove(&address);
+        }
+        return hit;
+    }
+}
+
+/// A bus which records the first access to a watched address, and the coverage
+/// of every access.
+struct WatchedBus<'a> {
+    bus: &'a mut dyn Bus,
+    debugger: &'a Debugger,
+    variant: CpuVariant,
+    hit: Cell<Option<Hit>>,
+    /// The address and number of the operand bytes of the last opcode fetched.
+    operands: Cell<(u16, u16)>,
+    coverage: Option<RefCell<Coverage>>,
+}
+
+impl Bus for WatchedBus<'_> {
+    fn read(&self, address: u16) -> u8 {
+        let data = self.bus.read(address);
+        if self.hit.get().is_none() && self.debugger.reads.contains(&address) {
+            self.hit.set(Some(Hit::Read { address, data }));
+        }
+        if let Some(coverage) = self.coverage.as_ref() {
+            let (start, len) = self.operands.get();
+            if address.wrapping_sub(start) >= len {
+                coverage.borrow_mut().read(address);
+            }
+        }
+        return data;
+    }
+
+    fn fetch(&self, address: u16) -> u8 {
+        let data = self.bus.fetch(address);
+        if let Some(coverage) = self.coverage.as_ref() {
+            let bytes = opcode::opcode_table(self.variant)[data as usize]
+                .bytes
+                .max(1);
+            let mut coverage = coverage.borrow_mut();
+            for offset in 0..u16::from(bytes) {
+                coverage.execute(address.wrapping_add(offset));
+            }
+            self.operands
+                .set((address.wrapping_add(1), u16::from(bytes) - 1));
+        }
+        return data;
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.bus.write(address, data);
+        if self.hit.get().is_none() && self.debugger.writes.contains(&address) {
+            self.hit.set(Some(Hit::Write { address, data }));
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.get_mut().write(address);
+        }
+    }
+}
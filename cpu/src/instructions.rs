@@ -1,4 +1,4 @@
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, CpuVariant};
 use crate::microcode::Context;
 use crate::registers::StatusFlags;
 use crate::utility;
@@ -28,17 +28,102 @@ impl Value {
         return Value(result as u8, status.with_overflow(b));
     }
 
+    /// Adds `rhs` and the carry, setting C on an unsigned and V on a signed
+    /// overflow.
     fn carrying_add(self, rhs: u8) -> Self {
         let Value(lhs, status) = self;
         let (result, carry) = utility::borrowing_add(lhs, rhs, status.get_carry());
-        return Value(result, status.with_carry(carry));
+        let overflow = (!(lhs ^ rhs) & (lhs ^ result) & 0x80) != 0;
+        return Value(result, status.with_carry(carry).with_overflow(overflow));
     }
 
+    /// Subtracts `rhs` and the inverted carry, clearing C on an unsigned borrow
+    /// and setting V on a signed overflow.
     fn borrowing_sub(self, rhs: u8) -> Self {
         let Value(lhs, status) = self;
         let borrow = !status.get_carry(); // invert carry
-        let (result, overflow) = utility::borrowing_sub(lhs as i8, rhs as i8, borrow);
-        return Value(result as u8, status.with_overflow(overflow));
+        let (result, borrowed) = utility::borrowing_sub(lhs, rhs, borrow);
+        let overflow = ((lhs ^ rhs) & (lhs ^ result) & 0x80) != 0;
+        return Value(result, status.with_carry(!borrowed).with_overflow(overflow));
+    }
+
+    /// Adds `rhs` and the carry as two BCD digits, with the flags of the NMOS
+    /// part. C is the decimal carry, Z is set from the binary sum and N and V
+    /// from the sum before the high digit is adjusted.
+    fn decimal_add(self, rhs: u8) -> Self {
+        let Value(lhs, status) = self;
+        let carry = status.get_carry();
+        let (binary, _) = utility::borrowing_add(lhs, rhs, carry);
+
+        let mut lo = u16::from(lhs & 0x0F) + u16::from(rhs & 0x0F) + u16::from(carry);
+        let mut hi = u16::from(lhs >> 4) + u16::from(rhs >> 4);
+        if lo > 0x09 {
+            lo = (lo + 0x06) & 0x0F;
+            hi += 1;
+        }
+
+        let unadjusted = ((hi << 4) | lo) as u8;
+        let overflow = (!(lhs ^ rhs) & (lhs ^ unadjusted) & 0x80) != 0;
+        if hi > 0x09 {
+            hi += 0x06;
+        }
+
+        let result = ((hi << 4) | lo) as u8;
+        return Value(
+            result,
+            status
+                .with_carry(hi > 0x0F)
+                .with_overflow(overflow)
+                .with_zero(binary == 0)
+                .with_negative((unadjusted as i8) < 0),
+        );
+    }
+
+    /// Subtracts `rhs` and the inverted carry as two BCD digits. The flags are
+    /// those of the binary subtraction, as on the NMOS part. The models only
+    /// differ in the result for digits which are not valid BCD, since the 65C02
+    /// adjusts the whole byte where the NMOS part adjusts each digit.
+    fn decimal_sub(self, rhs: u8, variant: CpuVariant) -> Self {
+        let Value(lhs, status) = self;
+        let borrow = i16::from(!status.get_carry());
+        let (_, status) = Value(lhs, status)
+            .borrowing_sub(rhs)
+            .update_zn_flags()
+            .unwrap();
+
+        let mut lo = i16::from(lhs & 0x0F) - i16::from(rhs & 0x0F) - borrow;
+        let result = match variant {
+            CpuVariant::Nmos6502 => {
+                let mut hi = i16::from(lhs >> 4) - i16::from(rhs >> 4);
+                if lo < 0 {
+                    lo -= 0x06;
+                    hi -= 1;
+                }
+                if hi < 0 {
+                    hi -= 0x06;
+                }
+                (hi << 4) | (lo & 0x0F)
+            }
+            CpuVariant::Cmos65C02 => {
+                let mut result = i16::from(lhs) - i16::from(rhs) - borrow;
+                if result < 0 {
+                    result -= 0x60;
+                }
+                if lo < 0 {
+                    result -= 0x06;
+                }
+                result
+            }
+        };
+        return Value(result as u8, status);
+    }
+
+    /// Compares with `rhs` like a subtraction without borrow, which sets C when
+    /// the value is at least `rhs`. V is not affected.
+    fn compare(self, rhs: u8) -> Self {
+        let Value(lhs, status) = self;
+        let result = lhs.wrapping_sub(rhs);
+        return Value(result, status.with_carry(lhs >= rhs)).update_zn_flags();
     }
 
     fn update_value<F: Fn(u8) -> u8>(self, f: F) -> Self {
@@ -51,11 +136,6 @@ impl Value {
         return Value(value, f(status));
     }
 
-    fn update_v_flag(self) -> Self {
-        let Value(value, status) = self;
-        return Value(value, status.with_overflow(value & 0x80 != 0));
-    }
-
     fn update_z_flag(self) -> Self {
         let Value(value, status) = self;
         return Value(value, status.with_zero(value == 0));
@@ -70,16 +150,12 @@ impl Value {
                 .with_negative((value as i8) < 0),
         );
     }
+}
 
-    fn update_zv_flags(self) -> Self {
-        let Value(value, status) = self;
-        return Value(
-            value,
-            status
-                .with_zero(value == 0)
-                .with_overflow(value & 0x80 != 0),
-        );
-    }
+/// Sets Z and N from a value loaded into or transferred between registers.
+fn update_zn_flags(cpu: &mut Cpu, value: u8) {
+    let (_, status) = Value::new(value, cpu.status).update_zn_flags().unwrap();
+    cpu.status.replace(status);
 }
 
 // Addressing Modes:
@@ -109,14 +185,20 @@ impl Value {
 /// Absolute,Y   | 0x79   | 3     | 4 (+1)
 /// (Indirect,X) | 0x61   | 2     | 6
 /// (Indirect),Y | 0x71   | 2     | 5 (+1)
+///
+/// In decimal mode the operands are BCD. The 65C02 takes one more cycle and sets
+/// N and Z from the result, which the NMOS part does not.
 pub fn adc_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let acc = cpu.registers.acc.get();
     let value = ctx.pop();
 
-    let (result, status) = Value::new(acc, cpu.status)
-        .carrying_add(value)
-        .update_zv_flags()
-        .unwrap();
+    let sum = Value::new(acc, cpu.status);
+    let (result, status) = match (cpu.status.get_decimal_mode(), cpu.variant()) {
+        (false, _) => sum.carrying_add(value).update_zn_flags(),
+        (true, CpuVariant::Nmos6502) => sum.decimal_add(value),
+        (true, CpuVariant::Cmos65C02) => sum.decimal_add(value).update_zn_flags(),
+    }
+    .unwrap();
 
     cpu.registers.acc.set(result);
     cpu.status.replace(status);
@@ -142,7 +224,7 @@ pub fn and_impl(cpu: &mut Cpu, ctx: &mut Context) {
 
     let (result, status) = Value::new(acc, cpu.status)
         .update_value(|v| v & value)
-        .update_zv_flags()
+        .update_zn_flags()
         .unwrap();
 
     cpu.registers.acc.set(result);
@@ -168,7 +250,6 @@ pub fn asl_impl(cpu: &mut Cpu, ctx: &mut Context) {
         .update_value(|v| v << 1)
         .update_status(|s| s.with_carry(carry))
         .update_zn_flags()
-        .update_v_flag()
         .unwrap();
 
     cpu.status.replace(status);
@@ -268,10 +349,22 @@ pub fn bpl_impl(cpu: &mut Cpu, ctx: &mut Context) {
 
 /// BRK - Force Break
 ///
+/// interrupt,
+/// push PC+2, push SR
+///
 /// address mode | opcode | bytes | cycles
 /// -------------+--------+-------+-------
 /// Implied      | 0x00   | 1     | 7
-pub fn brk_impl(cpu: &mut Cpu, ctx: &mut Context) {}
+pub fn brk_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    // the pushed status always has the break and unused bits set
+    let status = cpu.status.get_raw() | StatusFlags::BREAK | 0x20;
+    ctx.push(status);
+
+    cpu.status.replace(cpu.status.with_irq_disable(true));
+    if cpu.variant() == CpuVariant::Cmos65C02 {
+        cpu.status.replace(cpu.status.with_decimal_mode(false));
+    }
+}
 
 /// BVC - Branch on Overflow Clear
 ///
@@ -357,10 +450,7 @@ pub fn cmp_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let acc = cpu.registers.acc.get();
     let value = ctx.pop();
 
-    let (_, status) = Value::new(acc, cpu.status)
-        .safe_sub(value)
-        .update_zn_flags()
-        .unwrap();
+    let (_, status) = Value::new(acc, cpu.status).compare(value).unwrap();
 
     cpu.status.replace(status);
 }
@@ -378,10 +468,7 @@ pub fn cpx_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let x = cpu.registers.x.get();
     let value = ctx.pop();
 
-    let (_, status) = Value::new(x, cpu.status)
-        .safe_sub(value)
-        .update_zn_flags()
-        .unwrap();
+    let (_, status) = Value::new(x, cpu.status).compare(value).unwrap();
 
     cpu.status.replace(status);
 }
@@ -399,10 +486,7 @@ pub fn cpy_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let y = cpu.registers.y.get();
     let value = ctx.pop();
 
-    let (_, status) = Value::new(y, cpu.status)
-        .safe_sub(value)
-        .update_zn_flags()
-        .unwrap();
+    let (_, status) = Value::new(y, cpu.status).compare(value).unwrap();
 
     cpu.status.replace(status);
 }
@@ -605,6 +689,7 @@ pub fn jsr_impl(_: &mut Cpu, _: &mut Context) {}
 pub fn lda_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let data = ctx.pop();
     cpu.registers.acc.set(data);
+    update_zn_flags(cpu, data);
 }
 
 /// LDX - Load Index X with Memory
@@ -621,6 +706,7 @@ pub fn lda_impl(cpu: &mut Cpu, ctx: &mut Context) {
 pub fn ldx_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let data = ctx.pop();
     cpu.registers.x.set(data);
+    update_zn_flags(cpu, data);
 }
 
 /// LDY - Load Index Y with Memory
@@ -637,6 +723,7 @@ pub fn ldx_impl(cpu: &mut Cpu, ctx: &mut Context) {
 pub fn ldy_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let data = ctx.pop();
     cpu.registers.y.set(data);
+    update_zn_flags(cpu, data);
 }
 
 /// LSR - Shift One Bit Right (Memory or Accumulator)
@@ -718,7 +805,8 @@ pub fn pha_impl(cpu: &mut Cpu, ctx: &mut Context) {
 /// -------------+--------+-------+-------
 /// Implied      | 0x08   | 1     | 3
 pub fn php_impl(cpu: &mut Cpu, ctx: &mut Context) {
-    let status = cpu.status.get_raw();
+    // like brk, the pushed status always has the break and unused bits set
+    let status = cpu.status.get_raw() | StatusFlags::BREAK | 0x20;
     ctx.push(status);
 }
 
@@ -732,6 +820,7 @@ pub fn php_impl(cpu: &mut Cpu, ctx: &mut Context) {
 pub fn pla_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let acc = ctx.pop();
     cpu.registers.acc.set(acc);
+    update_zn_flags(cpu, acc);
 }
 
 /// PLP - Pull Processor Status from Stack
@@ -761,8 +850,10 @@ pub fn rol_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let value = ctx.pop();
     let carry = (value & 0x80) != 0;
 
+    let carry_in = cpu.status.get_carry() as u8;
+
     let (result, status) = Value::new(value, cpu.status)
-        .update_value(|v| v.rotate_left(1))
+        .update_value(|v| (v << 1) | carry_in)
         .update_status(|s| s.with_carry(carry))
         .update_zn_flags()
         .unwrap();
@@ -786,8 +877,10 @@ pub fn ror_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let value = ctx.pop();
     let carry = (value & 0x1) != 0;
 
+    let carry_in = cpu.status.get_carry() as u8;
+
     let (result, status) = Value::new(value, cpu.status)
-        .update_value(|v| v.rotate_right(1))
+        .update_value(|v| (v >> 1) | (carry_in << 7))
         .update_status(|s| s.with_carry(carry))
         .update_zn_flags()
         .unwrap();
@@ -840,15 +933,22 @@ pub fn rts_impl(_: &mut Cpu, _: &mut Context) {
 /// Absolute,Y   | 0xF9   | 3     | 4
 /// (Indirect,X) | 0xE1   | 2     | 6
 /// (Indirect),Y | 0xF1   | 2     | 5
+///
+/// In decimal mode the operands are BCD. The 65C02 takes one more cycle and sets
+/// N and Z from the result, which the NMOS part does not.
 pub fn sbc_impl(cpu: &mut Cpu, ctx: &mut Context) {
     let acc = cpu.registers.acc.get();
     let value = ctx.pop();
 
-    let (result, status) = Value::new(acc, cpu.status)
-        .borrowing_sub(value)
-        .update_zn_flags()
-        .update_v_flag()
-        .unwrap();
+    let difference = Value::new(acc, cpu.status);
+    let (result, status) = match (cpu.status.get_decimal_mode(), cpu.variant()) {
+        (false, _) => difference.borrowing_sub(value).update_zn_flags(),
+        (true, CpuVariant::Nmos6502) => difference.decimal_sub(value, CpuVariant::Nmos6502),
+        (true, CpuVariant::Cmos65C02) => difference
+            .decimal_sub(value, CpuVariant::Cmos65C02)
+            .update_zn_flags(),
+    }
+    .unwrap();
 
     cpu.registers.acc.set(result);
     cpu.status.replace(status);
@@ -943,6 +1043,7 @@ pub fn sty_impl(cpu: &mut Cpu, ctx: &mut Context) {
 pub fn tax_impl(cpu: &mut Cpu, _: &mut Context) {
     let acc = cpu.registers.acc.get();
     cpu.registers.x.set(acc);
+    update_zn_flags(cpu, acc);
 }
 
 /// TAY - Transfer Accumulator to Index Y
@@ -955,6 +1056,7 @@ pub fn tax_impl(cpu: &mut Cpu, _: &mut Context) {
 pub fn tay_impl(cpu: &mut Cpu, _: &mut Context) {
     let acc = cpu.registers.acc.get();
     cpu.registers.y.set(acc);
+    update_zn_flags(cpu, acc);
 }
 
 /// TSX - Transfer Stack Pointer to Index X
@@ -967,6 +1069,7 @@ pub fn tay_impl(cpu: &mut Cpu, _: &mut Context) {
 pub fn tsx_impl(cpu: &mut Cpu, _: &mut Context) {
     let sp = cpu.registers.sp.get();
     cpu.registers.x.set(sp);
+    update_zn_flags(cpu, sp);
 }
 
 /// TXA - Transfer Index X to Accumulator
@@ -977,8 +1080,9 @@ pub fn tsx_impl(cpu: &mut Cpu, _: &mut Context) {
 /// -------------+--------+-------+-------
 /// Implied      | 0x8A   | 1     | 2
 pub fn txa_impl(cpu: &mut Cpu, _: &mut Context) {
-    let sp = cpu.registers.sp.get();
-    cpu.registers.acc.set(sp);
+    let x = cpu.registers.x.get();
+    cpu.registers.acc.set(x);
+    update_zn_flags(cpu, x);
 }
 
 /// TXS - Transfer Index X to Stack Register
@@ -1002,5 +1106,348 @@ pub fn txs_impl(cpu: &mut Cpu, _: &mut Context) {
 /// Implied      | 0x98   | 1     | 2
 pub fn tya_impl(cpu: &mut Cpu, _: &mut Context) {
     let y = cpu.registers.y.get();
-    cpu.registers.sp.set(y);
+    cpu.registers.acc.set(y);
+    update_zn_flags(cpu, y);
+}
+
+//
+// Undocumented Instructions
+//
+// The read-modify-write combinations are built from the documented operations
+// they are made of, with the modified value fed to the second operation.
+
+/// Copies the value on top of the context stack so it can be consumed by a
+/// second operation.
+fn dup(ctx: &mut Context) {
+    let value = ctx.peek(0);
+    ctx.push(value);
+}
+
+/// DCP - Decrement Memory then Compare with Accumulator
+///
+/// M - 1 -> M, A - M
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0xC7   | 2     | 5
+/// Zero Page,X  | 0xD7   | 2     | 6
+/// Absolute     | 0xCF   | 3     | 6
+/// Absolute,X   | 0xDF   | 3     | 7
+/// Absolute,Y   | 0xDB   | 3     | 7
+/// (Indirect,X) | 0xC3   | 2     | 8
+/// (Indirect),Y | 0xD3   | 2     | 8
+pub fn dcp_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    dec_impl(cpu, ctx);
+    dup(ctx);
+    cmp_impl(cpu, ctx);
+}
+
+/// ISC - Increment Memory then Subtract from Accumulator with Borrow
+///
+/// M + 1 -> M, A - M - C -> A
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0xE7   | 2     | 5
+/// Zero Page,X  | 0xF7   | 2     | 6
+/// Absolute     | 0xEF   | 3     | 6
+/// Absolute,X   | 0xFF   | 3     | 7
+/// Absolute,Y   | 0xFB   | 3     | 7
+/// (Indirect,X) | 0xE3   | 2     | 8
+/// (Indirect),Y | 0xF3   | 2     | 8
+pub fn isc_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    inc_impl(cpu, ctx);
+    dup(ctx);
+    sbc_impl(cpu, ctx);
+}
+
+/// LAX - Load Accumulator and Index X with Memory
+///
+/// M -> A -> X
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0xA7   | 2     | 3
+/// Zero Page,Y  | 0xB7   | 2     | 4
+/// Absolute     | 0xAF   | 3     | 4
+/// Absolute,Y   | 0xBF   | 3     | 4 (+1)
+/// (Indirect,X) | 0xA3   | 2     | 6
+/// (Indirect),Y | 0xB3   | 2     | 5 (+1)
+pub fn lax_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    dup(ctx);
+    lda_impl(cpu, ctx);
+    ldx_impl(cpu, ctx);
+}
+
+/// NOP - No Operation (reads and ignores an operand)
+///
+/// address mode | opcode                       | bytes | cycles
+/// -------------+------------------------------+-------+-------
+/// Immediate    | 0x80, 0x82, 0x89, 0xC2, 0xE2 | 2     | 2
+/// Zero Page    | 0x04, 0x44, 0x64             | 2     | 3
+/// Zero Page,X  | 0x14, 0x34, 0x54, 0x74, ...  | 2     | 4
+/// Absolute     | 0x0C                         | 3     | 4
+/// Absolute,X   | 0x1C, 0x3C, 0x5C, 0x7C, ...  | 3     | 4 (+1)
+pub fn nop_load_impl(_: &mut Cpu, ctx: &mut Context) {
+    ctx.pop();
+}
+
+/// RLA - Rotate Memory Left then AND with Accumulator
+///
+/// C <- [76543210] <- C, A AND M -> A
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x27   | 2     | 5
+/// Zero Page,X  | 0x37   | 2     | 6
+/// Absolute     | 0x2F   | 3     | 6
+/// Absolute,X   | 0x3F   | 3     | 7
+/// Absolute,Y   | 0x3B   | 3     | 7
+/// (Indirect,X) | 0x23   | 2     | 8
+/// (Indirect),Y | 0x33   | 2     | 8
+pub fn rla_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    rol_impl(cpu, ctx);
+    dup(ctx);
+    and_impl(cpu, ctx);
+}
+
+/// RRA - Rotate Memory Right then Add to Accumulator with Carry
+///
+/// C -> [76543210] -> C, A + M + C -> A
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x67   | 2     | 5
+/// Zero Page,X  | 0x77   | 2     | 6
+/// Absolute     | 0x6F   | 3     | 6
+/// Absolute,X   | 0x7F   | 3     | 7
+/// Absolute,Y   | 0x7B   | 3     | 7
+/// (Indirect,X) | 0x63   | 2     | 8
+/// (Indirect),Y | 0x73   | 2     | 8
+pub fn rra_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    ror_impl(cpu, ctx);
+    dup(ctx);
+    adc_impl(cpu, ctx);
+}
+
+/// SAX - Store Accumulator AND Index X in Memory
+///
+/// A AND X -> M
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x87   | 2     | 3
+/// Zero Page,Y  | 0x97   | 2     | 4
+/// Absolute     | 0x8F   | 3     | 4
+/// (Indirect,X) | 0x83   | 2     | 6
+pub fn sax_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let acc = cpu.registers.acc.get();
+    let x = cpu.registers.x.get();
+    ctx.push(acc & x);
+}
+
+/// SLO - Shift Memory Left then OR with Accumulator
+///
+/// M * 2 -> M, A OR M -> A
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x07   | 2     | 5
+/// Zero Page,X  | 0x17   | 2     | 6
+/// Absolute     | 0x0F   | 3     | 6
+/// Absolute,X   | 0x1F   | 3     | 7
+/// Absolute,Y   | 0x1B   | 3     | 7
+/// (Indirect,X) | 0x03   | 2     | 8
+/// (Indirect),Y | 0x13   | 2     | 8
+pub fn slo_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    asl_impl(cpu, ctx);
+    dup(ctx);
+    ora_impl(cpu, ctx);
+}
+
+/// SRE - Shift Memory Right then EOR with Accumulator
+///
+/// 0 -> [76543210] -> C, A EOR M -> A
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x47   | 2     | 5
+/// Zero Page,X  | 0x57   | 2     | 6
+/// Absolute     | 0x4F   | 3     | 6
+/// Absolute,X   | 0x5F   | 3     | 7
+/// Absolute,Y   | 0x5B   | 3     | 7
+/// (Indirect,X) | 0x43   | 2     | 8
+/// (Indirect),Y | 0x53   | 2     | 8
+pub fn sre_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    lsr_impl(cpu, ctx);
+    dup(ctx);
+    eor_impl(cpu, ctx);
+}
+
+//
+// 65C02 Instructions
+//
+
+/// BIT - Test Bits in Memory with Accumulator (immediate)
+///
+/// A AND M
+///
+/// Only the zero flag is affected as there is no memory value to take N and V
+/// from.
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Immediate    | 0x89   | 2     | 2
+pub fn bit_immediate_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let acc = cpu.registers.acc.get();
+    let value = ctx.pop();
+
+    cpu.status.replace(cpu.status.with_zero(acc & value == 0));
+}
+
+/// BRA - Branch Always
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Relative     | 0x80   | 2     | 3 (+1)
+pub fn bra_impl(_: &mut Cpu, ctx: &mut Context) {
+    ctx.push(1);
+}
+
+/// DEC - Decrement Accumulator by One
+///
+/// A - 1 -> A
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Accumulator  | 0x3A   | 1     | 2
+pub fn dec_acc_impl(cpu: &mut Cpu, _: &mut Context) {
+    let acc = cpu.registers.acc.get();
+
+    let (result, status) = Value::new(acc.wrapping_sub(1), cpu.status)
+        .update_zn_flags()
+        .unwrap();
+
+    cpu.registers.acc.set(result);
+    cpu.status.replace(status);
+}
+
+/// INC - Increment Accumulator by One
+///
+/// A + 1 -> A
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Accumulator  | 0x1A   | 1     | 2
+pub fn inc_acc_impl(cpu: &mut Cpu, _: &mut Context) {
+    let acc = cpu.registers.acc.get();
+
+    let (result, status) = Value::new(acc.wrapping_add(1), cpu.status)
+        .update_zn_flags()
+        .unwrap();
+
+    cpu.registers.acc.set(result);
+    cpu.status.replace(status);
+}
+
+/// PHX - Push Index X on Stack
+///
+/// X -> stack
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Implied      | 0xDA   | 1     | 3
+pub fn phx_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let x = cpu.registers.x.get();
+    ctx.push(x);
+}
+
+/// PHY - Push Index Y on Stack
+///
+/// Y -> stack
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Implied      | 0x5A   | 1     | 3
+pub fn phy_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let y = cpu.registers.y.get();
+    ctx.push(y);
+}
+
+/// PLX - Pull Index X from Stack
+///
+/// stack -> X
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Implied      | 0xFA   | 1     | 4
+pub fn plx_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let value = ctx.pop();
+
+    let (result, status) = Value::new(value, cpu.status).update_zn_flags().unwrap();
+
+    cpu.registers.x.set(result);
+    cpu.status.replace(status);
+}
+
+/// PLY - Pull Index Y from Stack
+///
+/// stack -> Y
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Implied      | 0x7A   | 1     | 4
+pub fn ply_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let value = ctx.pop();
+
+    let (result, status) = Value::new(value, cpu.status).update_zn_flags().unwrap();
+
+    cpu.registers.y.set(result);
+    cpu.status.replace(status);
+}
+
+/// STZ - Store Zero in Memory
+///
+/// 0 -> M
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x64   | 2     | 3
+/// Zero Page,X  | 0x74   | 2     | 4
+/// Absolute     | 0x9C   | 3     | 4
+/// Absolute,X   | 0x9E   | 3     | 5
+pub fn stz_impl(_: &mut Cpu, ctx: &mut Context) {
+    ctx.push(0);
+}
+
+/// TRB - Test and Reset Memory Bits with Accumulator
+///
+/// A AND M -> Z, (NOT A) AND M -> M
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x14   | 2     | 5
+/// Absolute     | 0x1C   | 3     | 6
+pub fn trb_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let acc = cpu.registers.acc.get();
+    let value = ctx.pop();
+
+    cpu.status.replace(cpu.status.with_zero(acc & value == 0));
+    ctx.push(value & !acc);
+}
+
+/// TSB - Test and Set Memory Bits with Accumulator
+///
+/// A AND M -> Z, A OR M -> M
+///
+/// address mode | opcode | bytes | cycles
+/// -------------+--------+-------+-------
+/// Zero Page    | 0x04   | 2     | 5
+/// Absolute     | 0x0C   | 3     | 6
+pub fn tsb_impl(cpu: &mut Cpu, ctx: &mut Context) {
+    let acc = cpu.registers.acc.get();
+    let value = ctx.pop();
+
+    cpu.status.replace(cpu.status.with_zero(acc & value == 0));
+    ctx.push(value | acc);
 }
@@ -1,13 +1,53 @@
+#![allow(clippy::needless_return)]
+
+mod coverage;
 mod cpu;
+mod debugger;
 mod instructions;
 mod microcode;
 mod opcode;
+mod profile;
 mod registers;
+mod state;
+mod symbols;
+mod trace;
 mod utility;
 
-pub use cpu::Cpu;
+pub use coverage::Coverage;
+pub use cpu::{Cpu, CpuBuilder, CpuVariant, Jam, Pins, RunResult};
+pub use debugger::{BreakpointError, Debugger, Hit, StepResult, Watch};
+pub use opcode::{
+    disassemble, find_opcode, opcode_info, opcodes, opcodes_by_mnemonic, opcodes_by_mode,
+    AddressMode, OpcodeInfo,
+};
+pub use profile::{ExecutionStats, Profile};
+pub use registers::{Register, Registers, StatusFlags};
+pub use state::{StateError, StateReader, StateWriter};
+pub use symbols::{SymbolError, SymbolTable};
+pub use trace::{InstructionTrace, Trace, TraceLogger};
 
 pub trait Bus {
-    fn read<'a>(&'a self, address: u16) -> u8;
-    fn write<'a>(&'a mut self, address: u16, data: u8);
+    fn read(&self, address: u16) -> u8;
+    fn write(&mut self, address: u16, data: u8);
+
+    /// Reads an opcode byte. This is the cycle in which the CPU drives the SYNC
+    /// pin high.
+    fn fetch(&self, address: u16) -> u8 {
+        self.read(address)
+    }
+}
+
+/// Lets a borrowed bus be used wherever a bus is.
+impl<B: Bus + ?Sized> Bus for &mut B {
+    fn read(&self, address: u16) -> u8 {
+        (**self).read(address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        (**self).write(address, data)
+    }
+
+    fn fetch(&self, address: u16) -> u8 {
+        (**self).fetch(address)
+    }
 }
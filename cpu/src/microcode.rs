@@ -1,4 +1,4 @@
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, CpuVariant};
 use crate::registers::Register;
 use crate::Bus;
 
@@ -46,12 +46,18 @@ impl Context {
 
 #[derive(Clone, Copy)]
 pub enum MicroOp {
-    Unimplemented,
-
     /// Empty cycle (1 cycle)
     EmptyCycle,
     /// Empty ignored cycle (0 cycles)
     EmptyNoCycle,
+    /// Reads and discards the byte at the address when the cpu models dummy bus accesses (1 cycle)
+    DummyRead(u16),
+    /// Reads and discards the byte at the address in the PC register when the cpu models dummy bus
+    /// accesses, without incrementing it (1 cycle)
+    DummyReadPC,
+    /// Reads and discards the byte at the address pointed to by SP when the cpu models dummy bus
+    /// accesses, without changing it (1 cycle)
+    DummyReadSP,
     /// Loads the byte at the address in the PC register then increments it by one (1 cycle)
     LoadIncrPC,
     /// Pops a value off the context and stores it at the address pointed to by SP. Then decrement SP by one (1 cycle)
@@ -61,6 +67,8 @@ pub enum MicroOp {
 
     /// Pushes the contents of the accumulator onto the context stack (0 cycles)
     PushAcc,
+    /// Pops a value off the context stack and moves it into the accumulator (0 cycles)
+    PopAcc,
     /// Pushes a zero-byte onto the context stack (0 cycles)
     PushZero,
     /// Pushes the low order byte of the PC register onto the context stack (0 cycles)
@@ -76,6 +84,15 @@ pub enum MicroOp {
     PeekLoadAddress,
     /// Pops a value, followed by a hi and lo byte off the context stack and stores it at the address (1 cycle)
     PopStoreAddress,
+    /// Peeks a value, followed by a hi and lo byte off the context stack and stores it at the address (1 cycle)
+    PeekStoreAddress,
+    /// The spare cycle of a read-modify-write. The NMOS part writes the unmodified value back, as
+    /// PeekStoreAddress, where the 65C02 reads the address again as a dummy read (1 cycle)
+    ModifyCycle,
+    /// The cycle the 65C02 takes to adjust the result of ADC and SBC in decimal mode, which reads
+    /// the next opcode as a dummy read. The NMOS part adjusts it for free (1 cycle on the 65C02 in
+    /// decimal mode, otherwise 0 cycles)
+    DecimalCycle,
 
     /// Pops a byte off the context stack and moves it into the temp register (0 cycles)
     PopTemp,
@@ -93,9 +110,6 @@ pub enum MicroOp {
 impl MicroOp {
     pub fn execute(self, cpu: &mut Cpu, ctx: &mut Context, bus: &mut dyn Bus) -> u8 {
         match self {
-            MicroOp::Unimplemented => {
-                panic!("instruction unimplemented");
-            }
             MicroOp::EmptyCycle => {
                 // single-cycle ignored micro op
                 return 1;
@@ -104,24 +118,38 @@ impl MicroOp {
                 // zero-cycle ignored micro op
                 return 0;
             }
+            MicroOp::DummyRead(address) => {
+                if cpu.accurate_bus() {
+                    bus.read(address);
+                }
+                return 1;
+            }
+            MicroOp::DummyReadPC => {
+                let pc = cpu.registers.pc.get();
+                return MicroOp::DummyRead(pc).execute(cpu, ctx, bus);
+            }
+            MicroOp::DummyReadSP => {
+                let sp = cpu.registers.sp.get();
+                return MicroOp::DummyRead(u16::from_le_bytes([sp, 0x01])).execute(cpu, ctx, bus);
+            }
             MicroOp::LoadIncrPC => {
                 let pc = cpu.registers.pc.get();
                 let value = bus.read(pc);
                 ctx.push(value);
-                cpu.registers.pc.set(pc + 1);
+                cpu.registers.pc.set(pc.wrapping_add(1));
                 return 1;
             }
             MicroOp::StoreDecrSP => {
                 let sp = cpu.registers.sp.get();
-                let address = u16::from_le_bytes([00, sp]);
+                let address = u16::from_le_bytes([sp, 0x01]);
                 let value = ctx.pop();
                 bus.write(address, value);
-                cpu.registers.sp.set(sp - 1);
+                cpu.registers.sp.set(sp.wrapping_sub(1));
                 return 1;
             }
             MicroOp::IncrLoadSP => {
-                let sp = cpu.registers.sp.get() + 1;
-                let address = u16::from_le_bytes([00, sp]);
+                let sp = cpu.registers.sp.get().wrapping_add(1);
+                let address = u16::from_le_bytes([sp, 0x01]);
                 cpu.registers.sp.set(sp);
                 let value = bus.read(address);
                 ctx.push(value);
@@ -133,6 +161,11 @@ impl MicroOp {
                 ctx.push(value);
                 return 0;
             }
+            MicroOp::PopAcc => {
+                let value = ctx.pop();
+                cpu.registers.acc.set(value);
+                return 0;
+            }
             MicroOp::PushZero => {
                 ctx.push(0);
                 return 0;
@@ -183,6 +216,30 @@ impl MicroOp {
                 bus.write(address, value);
                 return 1;
             }
+            MicroOp::PeekStoreAddress => {
+                let value = ctx.peek(0);
+                let hi = ctx.peek(1);
+                let lo = ctx.peek(2);
+
+                let address = u16::from_le_bytes([lo, hi]);
+                bus.write(address, value);
+                return 1;
+            }
+            MicroOp::ModifyCycle => {
+                if cpu.variant() == CpuVariant::Cmos65C02 {
+                    let hi = ctx.peek(1);
+                    let lo = ctx.peek(2);
+                    return MicroOp::DummyRead(u16::from_le_bytes([lo, hi])).execute(cpu, ctx, bus);
+                }
+                return MicroOp::PeekStoreAddress.execute(cpu, ctx, bus);
+            }
+            MicroOp::DecimalCycle => {
+                if cpu.variant() == CpuVariant::Cmos65C02 && cpu.status.get_decimal_mode() {
+                    let pc = cpu.registers.pc.get();
+                    return MicroOp::DummyRead(pc).execute(cpu, ctx, bus);
+                }
+                return 0;
+            }
 
             //
             MicroOp::PopTemp => {
@@ -197,7 +254,7 @@ impl MicroOp {
             }
             MicroOp::IncrTemp => {
                 let value = ctx.temp.get();
-                ctx.temp.set(value + 1);
+                ctx.temp.set(value.wrapping_add(1));
                 return 0;
             }
             MicroOp::AddTempX => {
@@ -218,6 +275,17 @@ impl MicroOp {
     }
 }
 
+/// The cycle an indexed access spends fixing up the high order byte of its
+/// address. The nmos part reads the address with the unfixed high order byte,
+/// where the 65C02 reads the last operand byte again when the index crossed a
+/// page.
+pub fn fixup_cycle(cpu: &Cpu, lo: u8, bah: u8, hi: u8) -> MicroOp {
+    if cpu.variant() == CpuVariant::Cmos65C02 && hi != bah {
+        return MicroOp::DummyRead(cpu.registers.pc.get().wrapping_sub(1));
+    }
+    return MicroOp::DummyRead(u16::from_le_bytes([lo, bah]));
+}
+
 //
 // Core CPU Routines
 //
@@ -228,6 +296,11 @@ pub fn ucode_reset() -> &'static [MicroOp] {
             cpu.status.set(0);
             cpu.status = cpu.status.with_irq_disable(true).with_brk_command(true)
         }),
+        MicroOp::Execute(|cpu, _| {
+            // the reset sequence performs three suppressed stack pushes
+            let sp = cpu.registers.sp.get();
+            cpu.registers.sp.set(sp.wrapping_sub(3));
+        }),
         MicroOp::Execute(|_, ctx| {
             let [lo, hi] = Cpu::RES_VECTOR.to_le_bytes();
             ctx.push(lo);
@@ -250,6 +323,50 @@ pub fn ucode_reset() -> &'static [MicroOp] {
     ];
 }
 
+/// The 7-cycle sequence shared by hardware interrupts. The status is pushed with
+/// the break flag clear.
+macro_rules! hardware_interrupt {
+    ($vector: expr) => {
+        &[
+            MicroOp::DummyReadPC, // read the next opcode again
+            MicroOp::PushPCH,     // push PC hi byte onto context
+            MicroOp::StoreDecrSP, // store data on cpu stack
+            MicroOp::PushPCL,     // push PC lo byte onto context
+            MicroOp::StoreDecrSP, // store data on cpu stack
+            MicroOp::Execute(|cpu, ctx| {
+                let status = cpu.status.with_brk_command(false).get_raw() | 0x20;
+                ctx.push(status);
+                cpu.status.replace(cpu.status.with_irq_disable(true));
+                if cpu.variant() == CpuVariant::Cmos65C02 {
+                    cpu.status.replace(cpu.status.with_decimal_mode(false));
+                }
+            }),
+            MicroOp::StoreDecrSP, // store data on cpu stack
+            MicroOp::Execute(|_, ctx| {
+                let [lo, hi] = ($vector).to_le_bytes();
+                ctx.push(lo);
+                ctx.push(hi);
+            }),
+            MicroOp::PopLoadAddress, // fetch low order byte of interrupt vector
+            MicroOp::Execute(|_, ctx| {
+                let [lo, hi] = ($vector + 1).to_le_bytes();
+                ctx.push(lo);
+                ctx.push(hi);
+            }),
+            MicroOp::PopLoadAddress, // fetch high order byte of interrupt vector
+            MicroOp::PopJump,        // jump to interrupt handler
+        ]
+    };
+}
+
+pub fn ucode_irq() -> &'static [MicroOp] {
+    return hardware_interrupt!(Cpu::IRQ_VECTOR);
+}
+
+pub fn ucode_nmi() -> &'static [MicroOp] {
+    return hardware_interrupt!(Cpu::NMI_VECTOR);
+}
+
 //
 // Single Byte Instructions
 //
@@ -257,7 +374,7 @@ pub fn ucode_reset() -> &'static [MicroOp] {
 macro_rules! single_byte_implied {
     ($func: ident) => {
         &[
-            MicroOp::EmptyCycle, // pause
+            MicroOp::DummyReadPC, // read the next byte and discard it
             MicroOp::Execute($func),
         ]
     };
@@ -267,58 +384,74 @@ pub(crate) use single_byte_implied;
 macro_rules! single_byte_accumulator {
     ($func: ident) => {
         &[
-            MicroOp::EmptyCycle, // pause
-            MicroOp::PushAcc,    // push acc as data
+            MicroOp::DummyReadPC, // read the next byte and discard it
+            MicroOp::PushAcc,     // push acc as data
             MicroOp::Execute($func),
+            MicroOp::PopAcc, // move the result into acc
         ]
     };
 }
 pub(crate) use single_byte_accumulator;
 
+macro_rules! single_cycle_implied {
+    () => {
+        &[
+            MicroOp::EmptyNoCycle, // the opcode fetch is the only cycle
+        ]
+    };
+}
+pub(crate) use single_cycle_implied;
+
 //
 // Internal Execution on Memory Data
 //
 
 macro_rules! load_immediate {
-    ($func: ident) => {
+    ($func: ident $(, $tail: expr)*) => {
         &[
             MicroOp::LoadIncrPC, // fetch data
             MicroOp::Execute($func),
+            $($tail,)*
         ]
     };
 }
 pub(crate) use load_immediate;
 
 macro_rules! load_zero_page {
-    ($func: ident) => {
+    ($func: ident $(, $tail: expr)*) => {
         &[
             MicroOp::LoadIncrPC,     // fetch low order effective address byte
             MicroOp::PushZero,       // push implied 0 high order address byte
             MicroOp::PopLoadAddress, // fetch data
             MicroOp::Execute($func),
+            $($tail,)*
         ]
     };
 }
 pub(crate) use load_zero_page;
 
 macro_rules! load_absolute {
-    ($func: ident) => {
+    ($func: ident $(, $tail: expr)*) => {
         &[
             MicroOp::LoadIncrPC,     // fetch low order address byte
             MicroOp::LoadIncrPC,     // fetch high order address byte
             MicroOp::PopLoadAddress, // fetch data
             MicroOp::Execute($func),
+            $($tail,)*
         ]
     };
 }
 pub(crate) use load_absolute;
 
 macro_rules! load_indirect_x {
-    ($func: ident) => {
+    ($func: ident $(, $tail: expr)*) => {
         &[
             MicroOp::LoadIncrPC, // fetch page zero base address
             MicroOp::PopTemp,    // temp = bal
-            MicroOp::EmptyCycle, // pause for one cycle
+            MicroOp::Evaluate(|_, ctx| {
+                // the base address is read while x is added to it
+                return MicroOp::DummyRead(ctx.temp.get() as u16);
+            }),
             //
             MicroOp::AddTempX,       // temp = bal + x
             MicroOp::PushTemp,       // push temp onto stack
@@ -332,13 +465,14 @@ macro_rules! load_indirect_x {
             //
             MicroOp::PopLoadAddress, // fetch data
             MicroOp::Execute($func),
+            $($tail,)*
         ]
     };
 }
 pub(crate) use load_indirect_x;
 
 macro_rules! load_indirect_y {
-    ($func: ident) => {
+    ($func: ident $(, $tail: expr)*) => {
         &[
             MicroOp::LoadIncrPC, // fetch page zero indirect address
             MicroOp::PopTemp,    // temp = ial
@@ -353,18 +487,18 @@ macro_rules! load_indirect_y {
             MicroOp::PopLoadAddress, // fetch high order address byte of base address
             //
             MicroOp::Evaluate(|cpu, ctx| {
-                let bal = ctx.pop();
                 let bah = ctx.pop();
+                let bal = ctx.pop();
 
                 let (lo, carry) = cpu.registers.y.safe_add(bal);
-                let hi = bah + (carry as u8);
+                let hi = bah.wrapping_add(carry as u8);
 
+                ctx.push(lo);
+                ctx.push(hi);
                 if hi != bah {
                     // crosses page boundary, we must spend one more cycle
                     // to fetch the data from the next page
-                    ctx.push(lo);
-                    ctx.push(hi);
-                    return MicroOp::EmptyCycle;
+                    return MicroOp::DummyRead(u16::from_le_bytes([lo, bah]));
                 }
 
                 // doesn't cross page boundary so we can shorten this
@@ -383,13 +517,37 @@ macro_rules! load_indirect_y {
             }),
             //
             MicroOp::Execute($func),
+            $($tail,)*
         ]
     };
 }
 pub(crate) use load_indirect_y;
 
+macro_rules! load_zero_page_indirect {
+    ($func: ident $(, $tail: expr)*) => {
+        &[
+            MicroOp::LoadIncrPC, // fetch page zero indirect address
+            MicroOp::PopTemp,    // temp = ial
+            //
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch low order address byte
+            //
+            MicroOp::IncrTemp,       // temp = ial + 1
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch high order address byte
+            //
+            MicroOp::PopLoadAddress, // fetch data
+            MicroOp::Execute($func),
+            $($tail,)*
+        ]
+    };
+}
+pub(crate) use load_zero_page_indirect;
+
 macro_rules! load_absolute_indexed {
-    ($func: ident, $register: ident) => {
+    ($func: ident, $register: ident $(, $tail: expr)*) => {
         &[
             MicroOp::LoadIncrPC, // fetch low order byte of base address
             MicroOp::LoadIncrPC, // fetch high order byte of base address
@@ -398,14 +556,14 @@ macro_rules! load_absolute_indexed {
                 let bal = ctx.pop();
 
                 let (lo, carry) = cpu.registers.$register.safe_add(bal);
-                let hi = bah + (carry as u8);
+                let hi = bah.wrapping_add(carry as u8);
 
+                ctx.push(lo);
+                ctx.push(hi);
                 if hi != bah {
                     // crosses page boundary, we must spend one more cycle
                     // to fetch the data from the next page
-                    ctx.push(lo);
-                    ctx.push(hi);
-                    return MicroOp::EmptyCycle;
+                    return $crate::microcode::fixup_cycle(cpu, lo, bah, hi);
                 }
 
                 // doesn't cross page boundary so we can shorten this
@@ -424,16 +582,20 @@ macro_rules! load_absolute_indexed {
             }),
             //
             MicroOp::Execute($func),
+            $($tail,)*
         ]
     };
 }
 pub(crate) use load_absolute_indexed;
 
 macro_rules! load_zero_page_indexed {
-    ($func: ident, $register: ident) => {
+    ($func: ident, $register: ident $(, $tail: expr)*) => {
         &[
             MicroOp::LoadIncrPC, // fetch page zero base address
-            MicroOp::EmptyCycle, // pause for one cycle
+            MicroOp::Evaluate(|_, ctx| {
+                // the base address is read while the index is added to it
+                return MicroOp::DummyRead(ctx.peek(0) as u16);
+            }),
             MicroOp::Evaluate(|cpu, ctx| {
                 let bal = ctx.pop();
 
@@ -446,6 +608,7 @@ macro_rules! load_zero_page_indexed {
             }),
             //
             MicroOp::Execute($func),
+            $($tail,)*
         ]
     };
 }
@@ -484,7 +647,10 @@ macro_rules! store_indirect_x {
         &[
             MicroOp::LoadIncrPC, // fetch page zero base address
             MicroOp::PopTemp,    // temp = bal
-            MicroOp::EmptyCycle, // pause for one cycle
+            MicroOp::Evaluate(|_, ctx| {
+                // the base address is read while x is added to it
+                return MicroOp::DummyRead(ctx.temp.get() as u16);
+            }),
             //
             MicroOp::AddTempX,       // temp = bal + x
             MicroOp::PushTemp,       // push temp onto stack
@@ -519,15 +685,15 @@ macro_rules! store_indirect_y {
             MicroOp::PopLoadAddress, // fetch high order address byte of base address
             //
             MicroOp::Evaluate(|cpu, ctx| {
-                let bal = ctx.pop();
                 let bah = ctx.pop();
+                let bal = ctx.pop();
 
                 let (lo, carry) = cpu.registers.y.safe_add(bal);
-                let hi = bah + (carry as u8);
+                let hi = bah.wrapping_add(carry as u8);
 
                 ctx.push(lo);
                 ctx.push(hi);
-                return MicroOp::EmptyCycle; // pause one cycle
+                return MicroOp::DummyRead(u16::from_le_bytes([lo, bah])); // read the unfixed address
             }),
             //
             MicroOp::Execute($func),
@@ -537,6 +703,28 @@ macro_rules! store_indirect_y {
 }
 pub(crate) use store_indirect_y;
 
+macro_rules! store_zero_page_indirect {
+    ($func: ident) => {
+        &[
+            MicroOp::LoadIncrPC, // fetch page zero indirect address
+            MicroOp::PopTemp,    // temp = ial
+            //
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch low order address byte
+            //
+            MicroOp::IncrTemp,       // temp = ial + 1
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch high order address byte
+            //
+            MicroOp::Execute($func),
+            MicroOp::PopStoreAddress, // store data
+        ]
+    };
+}
+pub(crate) use store_zero_page_indirect;
+
 macro_rules! store_absolute_indexed {
     ($func: ident, $register: ident) => {
         &[
@@ -548,11 +736,11 @@ macro_rules! store_absolute_indexed {
                 let bal = ctx.pop();
 
                 let (lo, carry) = cpu.registers.$register.safe_add(bal);
-                let hi = bah + (carry as u8);
+                let hi = bah.wrapping_add(carry as u8);
 
                 ctx.push(lo);
                 ctx.push(hi);
-                return MicroOp::EmptyCycle; // pause for one cycle
+                return $crate::microcode::fixup_cycle(cpu, lo, bah, hi); // read the unfixed address
             }),
             //
             MicroOp::Execute($func),
@@ -566,7 +754,10 @@ macro_rules! store_zero_page_indexed {
     ($func: ident, $register: ident) => {
         &[
             MicroOp::LoadIncrPC, // fetch page zero base address
-            MicroOp::EmptyCycle, // pause for one cycle
+            MicroOp::Evaluate(|_, ctx| {
+                // the base address is read while the index is added to it
+                return MicroOp::DummyRead(ctx.peek(0) as u16);
+            }),
             //
             MicroOp::Evaluate(|cpu, ctx| {
                 let bal = ctx.pop();
@@ -592,13 +783,11 @@ pub(crate) use store_zero_page_indexed;
 macro_rules! load_store_zero_page {
     ($func: ident) => {
         &[
-            MicroOp::LoadIncrPC, // fetch page zero page address
-            MicroOp::PushTemp,   // temp = adl
-            //
-            MicroOp::PopTemp,         // push address lo byte
+            MicroOp::LoadIncrPC,      // fetch page zero address
             MicroOp::PushZero,        // push implied hi zero byte
             MicroOp::PeekLoadAddress, // fetch data
-            MicroOp::Execute($func),
+            MicroOp::ModifyCycle,     // write the unmodified data back or reread it
+            MicroOp::Execute($func),  //
             MicroOp::PopStoreAddress, // store data
         ]
     };
@@ -611,6 +800,7 @@ macro_rules! load_store_absolute {
             MicroOp::LoadIncrPC,      // fetch low order address byte
             MicroOp::LoadIncrPC,      // fetch high order address byte
             MicroOp::PeekLoadAddress, // fetch data
+            MicroOp::ModifyCycle,     // write the unmodified data back or reread it
             MicroOp::Execute($func),  //
             MicroOp::PopStoreAddress, // store data
         ]
@@ -621,14 +811,17 @@ pub(crate) use load_store_absolute;
 macro_rules! load_store_zero_page_x {
     ($func: ident) => {
         &[
-            MicroOp::LoadIncrPC,      // fetch page zero base address
-            MicroOp::EmptyCycle,      // pause
-            MicroOp::PopTemp,         // temp = bal
+            MicroOp::LoadIncrPC, // fetch page zero base address
+            MicroOp::PopTemp,    // temp = bal
+            MicroOp::Evaluate(|_, ctx| {
+                // the base address is read while x is added to it
+                return MicroOp::DummyRead(ctx.temp.get() as u16);
+            }),
             MicroOp::AddTempX,        // temp = bal + x
             MicroOp::PushTemp,        // push lo address byte to stack
             MicroOp::PushZero,        // push hi zero address byte
-            MicroOp::EmptyCycle,      // pause
             MicroOp::PeekLoadAddress, // fetch data
+            MicroOp::ModifyCycle,     // write the unmodified data back or reread it
             MicroOp::Execute($func),  //
             MicroOp::PopStoreAddress, // store data
         ]
@@ -636,38 +829,129 @@ macro_rules! load_store_zero_page_x {
 }
 pub(crate) use load_store_zero_page_x;
 
-macro_rules! load_store_absolute_x {
+macro_rules! load_store_absolute_indexed {
+    ($func: ident, $register: ident) => {
+        &[
+            MicroOp::LoadIncrPC, // fetch low order address byte
+            MicroOp::LoadIncrPC, // fetch high order address byte
+            MicroOp::Evaluate(|cpu, ctx| {
+                let bah = ctx.pop();
+                let bal = ctx.pop();
+
+                let (lo, carry) = cpu.registers.$register.safe_add(bal);
+                let hi = bah.wrapping_add(carry as u8);
+
+                ctx.push(lo);
+                ctx.push(hi);
+                // the high order byte is fixed up while the unfixed address is
+                // read, whether or not the index crossed a page
+                return $crate::microcode::fixup_cycle(cpu, lo, bah, hi);
+            }),
+            MicroOp::PeekLoadAddress, // fetch data
+            MicroOp::ModifyCycle,     // write the unmodified data back or reread it
+            MicroOp::Execute($func),  //
+            MicroOp::PopStoreAddress, // store data
+        ]
+    };
+}
+pub(crate) use load_store_absolute_indexed;
+
+macro_rules! load_store_absolute_x_short {
     ($func: ident) => {
         &[
             MicroOp::LoadIncrPC, // fetch low order address byte
             MicroOp::LoadIncrPC, // fetch high order address byte
-            MicroOp::EmptyCycle, // pause
             MicroOp::Evaluate(|cpu, ctx| {
                 let bah = ctx.pop();
                 let bal = ctx.pop();
 
                 let (lo, carry) = cpu.registers.x.safe_add(bal);
-                let hi = bah + (carry as u8);
+                let hi = bah.wrapping_add(carry as u8);
 
                 ctx.push(lo);
                 ctx.push(hi);
-                return MicroOp::PeekLoadAddress; // fetch data
+                if hi != bah {
+                    // crosses page boundary, the high order byte is fixed
+                    // up in one more cycle
+                    return $crate::microcode::fixup_cycle(cpu, lo, bah, hi);
+                }
+
+                // the 65C02 shifts skip the fix-up cycle on the same page
+                return MicroOp::EmptyNoCycle;
+            }),
+            MicroOp::PeekLoadAddress, // fetch data
+            MicroOp::ModifyCycle,     // reread the data
+            MicroOp::Execute($func),  //
+            MicroOp::PopStoreAddress, // store data
+        ]
+    };
+}
+pub(crate) use load_store_absolute_x_short;
+
+macro_rules! load_store_indirect_x {
+    ($func: ident) => {
+        &[
+            MicroOp::LoadIncrPC, // fetch page zero base address
+            MicroOp::PopTemp,    // temp = bal
+            MicroOp::Evaluate(|_, ctx| {
+                // the base address is read while x is added to it
+                return MicroOp::DummyRead(ctx.temp.get() as u16);
+            }),
+            //
+            MicroOp::AddTempX,       // temp = bal + x
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch low order address byte
+            //
+            MicroOp::IncrTemp,       // temp = bal + x + 1
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch high order address byte
+            //
+            MicroOp::PeekLoadAddress, // fetch data
+            MicroOp::ModifyCycle,     // write the unmodified data back or reread it
+            MicroOp::Execute($func),  //
+            MicroOp::PopStoreAddress, // store data
+        ]
+    };
+}
+pub(crate) use load_store_indirect_x;
+
+macro_rules! load_store_indirect_y {
+    ($func: ident) => {
+        &[
+            MicroOp::LoadIncrPC, // fetch page zero indirect address
+            MicroOp::PopTemp,    // temp = ial
+            //
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch low order address byte of base address
+            //
+            MicroOp::IncrTemp,       // temp = ial + 1
+            MicroOp::PushTemp,       // push temp onto stack
+            MicroOp::PushZero,       // push hi zero byte
+            MicroOp::PopLoadAddress, // fetch high order address byte of base address
+            //
+            MicroOp::Evaluate(|cpu, ctx| {
+                let bah = ctx.pop();
+                let bal = ctx.pop();
+
+                let (lo, carry) = cpu.registers.y.safe_add(bal);
+                let hi = bah.wrapping_add(carry as u8);
+
+                ctx.push(lo);
+                ctx.push(hi);
+                return MicroOp::DummyRead(u16::from_le_bytes([lo, bah])); // read the unfixed address
             }),
-            MicroOp::EmptyCycle, // pause
-            MicroOp::PopStoreAddress,
             //
-            MicroOp::PopTemp,         // temp = bal
-            MicroOp::AddTempX,        // temp = bal + x
-            MicroOp::PushTemp,        // push lo address byte to stack
-            MicroOp::PushZero,        // push hi zero address byte
-            MicroOp::EmptyCycle,      // pause
             MicroOp::PeekLoadAddress, // fetch data
+            MicroOp::ModifyCycle,     // write the unmodified data back or reread it
             MicroOp::Execute($func),  //
-            MicroOp::PopLoadAddress,  // store data
+            MicroOp::PopStoreAddress, // store data
         ]
     };
 }
-pub(crate) use load_store_absolute_x;
+pub(crate) use load_store_indirect_y;
 
 //
 // Miscellaneous Operations
@@ -676,7 +960,7 @@ pub(crate) use load_store_absolute_x;
 macro_rules! push_implied {
     ($func: ident) => {
         &[
-            MicroOp::EmptyCycle,     // pause
+            MicroOp::DummyReadPC,    // read the next byte and discard it
             MicroOp::Execute($func), //
             MicroOp::StoreDecrSP,    // store data
         ]
@@ -687,8 +971,8 @@ pub(crate) use push_implied;
 macro_rules! pull_implied {
     ($func: ident) => {
         &[
-            MicroOp::EmptyCycle,     // pause
-            MicroOp::EmptyCycle,     // pause
+            MicroOp::DummyReadPC,    // read the next byte and discard it
+            MicroOp::DummyReadSP,    // read the stack while SP is incremented
             MicroOp::IncrLoadSP,     // fetch data from stack
             MicroOp::Execute($func), //
         ]
@@ -698,7 +982,29 @@ pub(crate) use pull_implied;
 
 macro_rules! break_implied {
     ($func: ident) => {
-        &[MicroOp::Unimplemented]
+        &[
+            MicroOp::LoadIncrPC,     // fetch padding byte
+            MicroOp::PopTemp,        // discard it
+            MicroOp::PushPCH,        // push PC hi byte onto context
+            MicroOp::StoreDecrSP,    // store data on cpu stack
+            MicroOp::PushPCL,        // push PC lo byte onto context
+            MicroOp::StoreDecrSP,    // store data on cpu stack
+            MicroOp::Execute($func), // push status
+            MicroOp::StoreDecrSP,    // store data on cpu stack
+            MicroOp::Execute(|_, ctx| {
+                let [lo, hi] = $crate::cpu::Cpu::IRQ_VECTOR.to_le_bytes();
+                ctx.push(lo);
+                ctx.push(hi);
+            }),
+            MicroOp::PopLoadAddress, // fetch low order byte of interrupt vector
+            MicroOp::Execute(|_, ctx| {
+                let [lo, hi] = ($crate::cpu::Cpu::IRQ_VECTOR + 1).to_le_bytes();
+                ctx.push(lo);
+                ctx.push(hi);
+            }),
+            MicroOp::PopLoadAddress, // fetch high order byte of interrupt vector
+            MicroOp::PopJump,        // jump to interrupt handler
+        ]
     };
 }
 pub(crate) use break_implied;
@@ -707,7 +1013,7 @@ macro_rules! jump_to_subroutine_absolute {
     ($func: ident) => {
         &[
             MicroOp::LoadIncrPC,     // fetch low order byte of subroutine address
-            MicroOp::EmptyCycle,     // pause
+            MicroOp::DummyReadSP,    // read the stack and discard it
             MicroOp::PushPCH,        // push PC hi byte onto context
             MicroOp::StoreDecrSP,    // store data on cpu stack
             MicroOp::PushPCL,        // push PC lo byte onto context
@@ -723,16 +1029,24 @@ pub(crate) use jump_to_subroutine_absolute;
 macro_rules! return_from_subroutine_implied {
     ($func: ident) => {
         &[
-            MicroOp::EmptyCycle,     // pause
-            MicroOp::EmptyCycle,     // pause
+            MicroOp::DummyReadPC,    // read the next byte and discard it
+            MicroOp::DummyReadSP,    // read the stack while SP is incremented
             MicroOp::IncrLoadSP,     // pull PCL from stack
-            MicroOp::PopTemp,        // temp = PCL
-            MicroOp::IncrTemp,       // temp = PCL +1
-            MicroOp::PushTemp,       //
             MicroOp::IncrLoadSP,     // pull PCH from stack
             MicroOp::Execute($func), //
-            MicroOp::EmptyCycle,     // pause
-            MicroOp::PopJump,        // jump to return address
+            MicroOp::Evaluate(|_, ctx| {
+                let hi = ctx.pop();
+                let lo = ctx.pop();
+
+                // the pushed address points at the last byte of the jsr, which
+                // is read again while the PC is incremented
+                let address = u16::from_le_bytes([lo, hi]);
+                let [lo, hi] = address.wrapping_add(1).to_le_bytes();
+                ctx.push(lo);
+                ctx.push(hi);
+                return MicroOp::DummyRead(address);
+            }),
+            MicroOp::PopJump, // jump to return address
         ]
     };
 }
@@ -741,8 +1055,8 @@ pub(crate) use return_from_subroutine_implied;
 macro_rules! return_from_interrupt_implied {
     ($func: ident) => {
         &[
-            MicroOp::EmptyCycle,     // pause
-            MicroOp::EmptyCycle,     // pause
+            MicroOp::DummyReadPC,    // read the next byte and discard it
+            MicroOp::DummyReadSP,    // read the stack while SP is incremented
             MicroOp::IncrLoadSP,     // pull P from stack
             MicroOp::IncrLoadSP,     // pull PCL from stack
             MicroOp::IncrLoadSP,     // pull PCH from stack
@@ -771,18 +1085,35 @@ macro_rules! jump_indirect {
             MicroOp::LoadIncrPC,      // fetch low order byte of indirect address
             MicroOp::LoadIncrPC,      // fetch high order byte of indirect address
             MicroOp::PeekLoadAddress, // fetch low order byte of jump address
-            MicroOp::Evaluate(|_, ctx| {
+            MicroOp::Evaluate(|cpu, ctx| {
                 let lo = ctx.pop();
 
                 let iah = ctx.pop();
                 let ial = ctx.pop();
 
                 ctx.push(lo);
-                ctx.push(ial + 1);
-                ctx.push(iah);
+                if cpu.variant() == $crate::cpu::CpuVariant::Cmos65C02 {
+                    // the high order byte is read from the next page after
+                    // one more cycle
+                    let [lo, hi] = u16::from_le_bytes([ial, iah]).wrapping_add(1).to_le_bytes();
+                    ctx.push(lo);
+                    ctx.push(hi);
+                    return MicroOp::EmptyCycle;
+                }
 
+                // the nmos part does not carry into the high order byte
+                ctx.push(ial.wrapping_add(1));
+                ctx.push(iah);
                 return MicroOp::PopLoadAddress; // fetch high order byte of jump address
             }),
+            MicroOp::Evaluate(|_, ctx| {
+                if ctx.size() == 3 {
+                    return MicroOp::PopLoadAddress; // fetch high order byte of jump address
+                }
+
+                // the high order byte has already been loaded
+                return MicroOp::EmptyNoCycle;
+            }),
             MicroOp::Execute($func), //
             MicroOp::PopJump,        // jump to address
         ]
@@ -790,6 +1121,58 @@ macro_rules! jump_indirect {
 }
 pub(crate) use jump_indirect;
 
+macro_rules! jump_absolute_indirect_x {
+    ($func: ident) => {
+        &[
+            MicroOp::LoadIncrPC, // fetch low order byte of base address
+            MicroOp::LoadIncrPC, // fetch high order byte of base address
+            MicroOp::Evaluate(|cpu, ctx| {
+                let bah = ctx.pop();
+                let bal = ctx.pop();
+
+                let x = cpu.registers.x.get() as u16;
+                let [lo, hi] = u16::from_le_bytes([bal, bah]).wrapping_add(x).to_le_bytes();
+                ctx.push(lo);
+                ctx.push(hi);
+                return MicroOp::EmptyCycle; // pause
+            }),
+            MicroOp::PeekLoadAddress, // fetch low order byte of jump address
+            MicroOp::Evaluate(|_, ctx| {
+                let lo = ctx.pop();
+
+                let iah = ctx.pop();
+                let ial = ctx.pop();
+
+                ctx.push(lo);
+                let [ial, iah] = u16::from_le_bytes([ial, iah]).wrapping_add(1).to_le_bytes();
+                ctx.push(ial);
+                ctx.push(iah);
+                return MicroOp::PopLoadAddress; // fetch high order byte of jump address
+            }),
+            MicroOp::Execute($func), //
+            MicroOp::PopJump,        // jump to address
+        ]
+    };
+}
+pub(crate) use jump_absolute_indirect_x;
+
+macro_rules! nop_absolute_long {
+    () => {
+        &[
+            MicroOp::LoadIncrPC, // fetch low order address byte
+            MicroOp::LoadIncrPC, // fetch high order address byte
+            MicroOp::PopTemp,    // discard it
+            MicroOp::PopTemp,    // discard it
+            MicroOp::EmptyCycle, // pause
+            MicroOp::EmptyCycle, // pause
+            MicroOp::EmptyCycle, // pause
+            MicroOp::EmptyCycle, // pause
+            MicroOp::EmptyCycle, // pause
+        ]
+    };
+}
+pub(crate) use nop_absolute_long;
+
 macro_rules! branch_relative {
     ($func: ident) => {
         &[
@@ -805,31 +1188,23 @@ macro_rules! branch_relative {
                     return MicroOp::EmptyNoCycle;
                 }
 
-                let pcl = cpu.registers.pc.get_lo_byte();
-                let pch = cpu.registers.pc.get_hi_byte();
-
-                let lo: u8;
-                let overflow: bool;
-                if offset >= 0 {
-                    (lo, overflow) = pcl.overflowing_add(offset as u8);
-                } else {
-                    (lo, overflow) = pcl.overflowing_sub(offset.unsigned_abs());
-                }
+                let pc = cpu.registers.pc.get();
+                let target = pc.wrapping_add(offset as u16);
+                let [lo, hi] = target.to_le_bytes();
 
-                if overflow {
-                    // branch crosses page boundary
-                    let hi = pch.wrapping_add(overflow as u8);
+                if hi != cpu.registers.pc.get_hi_byte() {
+                    // branch crosses page boundary, the high order byte
+                    // is fixed up in the next cycle
                     ctx.push(lo);
                     ctx.push(hi);
-                    return MicroOp::EmptyCycle; // pause
+                    return MicroOp::DummyRead(pc); // fetch next opcode
                 }
 
                 // branch doesnt cross page boundary
-                ctx.push(lo);
-                ctx.push(pch);
-                return MicroOp::PopJump;
+                cpu.registers.pc.set(target);
+                return MicroOp::DummyRead(pc); // fetch next opcode
             }),
-            MicroOp::Evaluate(|_, ctx| {
+            MicroOp::Evaluate(|cpu, ctx| {
                 if ctx.temp.get() == 0 {
                     // branch was skipped
                     return MicroOp::EmptyNoCycle;
@@ -841,7 +1216,11 @@ macro_rules! branch_relative {
                 }
 
                 // the branch was taken and it crossed a page boundary
-                return MicroOp::PopJump;
+                let hi = ctx.pop();
+                let lo = ctx.pop();
+                let pch = cpu.registers.pc.get_hi_byte();
+                cpu.registers.pc.set(u16::from_le_bytes([lo, hi]));
+                return MicroOp::DummyRead(u16::from_le_bytes([lo, pch])); // fetch from wrong page
             }),
         ]
     };
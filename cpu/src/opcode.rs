@@ -1,5 +1,7 @@
+use crate::cpu::CpuVariant;
 use crate::instructions::*;
 use crate::microcode::*;
+use crate::symbols::SymbolTable;
 
 macro_rules! opcode {
     ($value: expr) => {
@@ -25,7 +27,8 @@ macro_rules! opcode {
     };
 }
 
-#[derive(Clone, Copy)]
+/// How an instruction finds its operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AddressMode {
     Accumulator,
     Absolute,
@@ -36,13 +39,18 @@ pub enum AddressMode {
     Indirect,
     IndirectX,
     IndirectY,
+    /// `(zp)`, 65C02 only.
+    ZeroPageIndirect,
+    /// `(abs,X)`, 65C02 only.
+    AbsoluteIndirectX,
     Relative,
     ZeroPage,
     ZeroPageX,
     ZeroPageY,
 }
 
-#[derive(Clone)]
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
 pub struct Opcode {
     pub value: u8,
     pub mnemonic: &'static str,
@@ -57,47 +65,47 @@ pub struct Opcode {
 pub const OPCODES: [Opcode; 256] = [
     // 0x00 - 0x0F
     opcode!(0x00, "BRK", AddressMode::Implied, 1, 7, break_implied!(brk_impl)),
-    opcode!(0x01, "ORA", AddressMode::IndirectX, 2, 5, load_indirect_x!(ora_impl)),
+    opcode!(0x01, "ORA", AddressMode::IndirectX, 2, 6, load_indirect_x!(ora_impl)),
     opcode!(0x02),
-    opcode!(0x03),
-    opcode!(0x04),
+    opcode!(0x03, "SLO", AddressMode::IndirectX, 2, 8, load_store_indirect_x!(slo_impl)),
+    opcode!(0x04, "NOP", AddressMode::ZeroPage, 2, 3, load_zero_page!(nop_load_impl)),
     opcode!(0x05, "ORA", AddressMode::ZeroPage, 2, 3, load_zero_page!(ora_impl)),
     opcode!(0x06, "ASL", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(asl_impl)),
-    opcode!(0x07),
+    opcode!(0x07, "SLO", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(slo_impl)),
     opcode!(0x08, "PHP", AddressMode::Implied, 1, 3, push_implied!(php_impl)),
     opcode!(0x09, "ORA", AddressMode::Immediate, 2, 2, load_immediate!(ora_impl)),
     opcode!(0x0A, "ASL", AddressMode::Accumulator, 1, 2, single_byte_accumulator!(asl_impl)),
     opcode!(0x0B),
-    opcode!(0x0C),
-    opcode!(0x0D, "ORA", AddressMode::Absolute, 3, 4, load_zero_page!(ora_impl)),
+    opcode!(0x0C, "NOP", AddressMode::Absolute, 3, 4, load_absolute!(nop_load_impl)),
+    opcode!(0x0D, "ORA", AddressMode::Absolute, 3, 4, load_absolute!(ora_impl)),
     opcode!(0x0E, "ASL", AddressMode::Absolute, 3, 6, load_store_absolute!(asl_impl)),
-    opcode!(0x0F),
+    opcode!(0x0F, "SLO", AddressMode::Absolute, 3, 6, load_store_absolute!(slo_impl)),
     // 0x10 - 0x1F
     opcode!(0x10, "BPL", AddressMode::Relative, 2, 2, branch_relative!(bpl_impl)),
     opcode!(0x11, "ORA", AddressMode::IndirectY, 2, 5, load_indirect_y!(ora_impl)),
     opcode!(0x12),
-    opcode!(0x13),
-    opcode!(0x14),
+    opcode!(0x13, "SLO", AddressMode::IndirectY, 2, 8, load_store_indirect_y!(slo_impl)),
+    opcode!(0x14, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
     opcode!(0x15, "ORA", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(ora_impl, x)),
     opcode!(0x16, "ASL", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(asl_impl)),
-    opcode!(0x17),
+    opcode!(0x17, "SLO", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(slo_impl)),
     opcode!(0x18, "CLC", AddressMode::Implied, 1, 2, single_byte_implied!(clc_impl)),
     opcode!(0x19, "ORA", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(ora_impl, y)),
-    opcode!(0x1A),
-    opcode!(0x1B),
-    opcode!(0x1C),
+    opcode!(0x1A, "NOP", AddressMode::Implied, 1, 2, single_byte_implied!(nop_impl)),
+    opcode!(0x1B, "SLO", AddressMode::AbsoluteY, 3, 7, load_store_absolute_indexed!(slo_impl, y)),
+    opcode!(0x1C, "NOP", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(nop_load_impl, x)),
     opcode!(0x1D, "ORA", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(ora_impl, x)),
-    opcode!(0x1E, "ASL", AddressMode::AbsoluteX, 3, 7, load_store_absolute_x!(asl_impl)),
-    opcode!(0x1F),
+    opcode!(0x1E, "ASL", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(asl_impl, x)),
+    opcode!(0x1F, "SLO", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(slo_impl, x)),
     // 0x20 - 0x2F
     opcode!(0x20, "JSR", AddressMode::Absolute, 3, 6, jump_to_subroutine_absolute!(jsr_impl)),
     opcode!(0x21, "AND", AddressMode::IndirectX, 2, 6, load_indirect_x!(and_impl)),
     opcode!(0x22),
-    opcode!(0x23),
+    opcode!(0x23, "RLA", AddressMode::IndirectX, 2, 8, load_store_indirect_x!(rla_impl)),
     opcode!(0x24, "BIT", AddressMode::ZeroPage, 2, 3, load_zero_page!(bit_impl)),
     opcode!(0x25, "AND", AddressMode::ZeroPage, 2, 3, load_zero_page!(and_impl)),
-    opcode!(0x26, "ROL", AddressMode::ZeroPage, 2, 5, load_zero_page!(rol_impl)),
-    opcode!(0x27),
+    opcode!(0x26, "ROL", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(rol_impl)),
+    opcode!(0x27, "RLA", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(rla_impl)),
     opcode!(0x28, "PLP", AddressMode::Implied, 1, 4, pull_implied!(plp_impl)),
     opcode!(0x29, "AND", AddressMode::Immediate, 2, 2, load_immediate!(and_impl)),
     opcode!(0x2A, "ROL", AddressMode::Accumulator, 1, 2, single_byte_accumulator!(rol_impl)),
@@ -105,33 +113,33 @@ pub const OPCODES: [Opcode; 256] = [
     opcode!(0x2C, "BIT", AddressMode::Absolute, 3, 4, load_absolute!(bit_impl)),
     opcode!(0x2D, "AND", AddressMode::Absolute, 3, 4, load_absolute!(and_impl)),
     opcode!(0x2E, "ROL", AddressMode::Absolute, 3, 6, load_store_absolute!(rol_impl)),
-    opcode!(0x2F),
+    opcode!(0x2F, "RLA", AddressMode::Absolute, 3, 6, load_store_absolute!(rla_impl)),
     // 0x30 - 0x3F
     opcode!(0x30, "BMI", AddressMode::Relative, 2, 2, branch_relative!(bmi_impl)),
     opcode!(0x31, "AND", AddressMode::IndirectY, 2, 5, load_indirect_y!(and_impl)),
     opcode!(0x32),
-    opcode!(0x33),
-    opcode!(0x34),
+    opcode!(0x33, "RLA", AddressMode::IndirectY, 2, 8, load_store_indirect_y!(rla_impl)),
+    opcode!(0x34, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
     opcode!(0x35, "AND", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(and_impl, x)),
     opcode!(0x36, "ROL", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(rol_impl)),
-    opcode!(0x37),
+    opcode!(0x37, "RLA", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(rla_impl)),
     opcode!(0x38, "SEC", AddressMode::Implied, 1, 2, single_byte_implied!(sec_impl)),
     opcode!(0x39, "AND", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(and_impl, y)),
-    opcode!(0x3A),
-    opcode!(0x3B),
-    opcode!(0x3C),
+    opcode!(0x3A, "NOP", AddressMode::Implied, 1, 2, single_byte_implied!(nop_impl)),
+    opcode!(0x3B, "RLA", AddressMode::AbsoluteY, 3, 7, load_store_absolute_indexed!(rla_impl, y)),
+    opcode!(0x3C, "NOP", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(nop_load_impl, x)),
     opcode!(0x3D, "AND", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(and_impl, x)),
-    opcode!(0x3E, "ROL", AddressMode::AbsoluteX, 3, 7, load_store_absolute_x!(rol_impl)),
-    opcode!(0x3F),
+    opcode!(0x3E, "ROL", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(rol_impl, x)),
+    opcode!(0x3F, "RLA", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(rla_impl, x)),
     // 0x40 - 0x4F
     opcode!(0x40, "RTI", AddressMode::Implied, 1, 6, return_from_interrupt_implied!(rti_impl)),
     opcode!(0x41, "EOR", AddressMode::IndirectX, 2, 6, load_indirect_x!(eor_impl)),
     opcode!(0x42),
-    opcode!(0x43),
-    opcode!(0x44),
+    opcode!(0x43, "SRE", AddressMode::IndirectX, 2, 8, load_store_indirect_x!(sre_impl)),
+    opcode!(0x44, "NOP", AddressMode::ZeroPage, 2, 3, load_zero_page!(nop_load_impl)),
     opcode!(0x45, "EOR", AddressMode::ZeroPage, 2, 3, load_zero_page!(eor_impl)),
     opcode!(0x46, "LSR", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(lsr_impl)),
-    opcode!(0x47),
+    opcode!(0x47, "SRE", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(sre_impl)),
     opcode!(0x48, "PHA", AddressMode::Implied, 1, 3, push_implied!(pha_impl)),
     opcode!(0x49, "EOR", AddressMode::Immediate, 2, 2, load_immediate!(eor_impl)),
     opcode!(0x4A, "LSR", AddressMode::Accumulator, 1, 2, single_byte_accumulator!(lsr_impl)),
@@ -139,75 +147,75 @@ pub const OPCODES: [Opcode; 256] = [
     opcode!(0x4C, "JMP", AddressMode::Absolute, 3, 3, jump_absolute!(jmp_impl)),
     opcode!(0x4D, "EOR", AddressMode::Absolute, 3, 4, load_absolute!(eor_impl)),
     opcode!(0x4E, "LSR", AddressMode::Absolute, 3, 6, load_store_absolute!(lsr_impl)),
-    opcode!(0x4F),
+    opcode!(0x4F, "SRE", AddressMode::Absolute, 3, 6, load_store_absolute!(sre_impl)),
     // 0x50 - 0x5F
     opcode!(0x50, "BVC", AddressMode::Relative, 2, 2, branch_relative!(bvc_impl)),
     opcode!(0x51, "EOR", AddressMode::IndirectY, 2, 5, load_indirect_y!(eor_impl)),
     opcode!(0x52),
-    opcode!(0x53),
-    opcode!(0x54),
+    opcode!(0x53, "SRE", AddressMode::IndirectY, 2, 8, load_store_indirect_y!(sre_impl)),
+    opcode!(0x54, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
     opcode!(0x55, "EOR", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(eor_impl, x)),
     opcode!(0x56, "LSR", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(lsr_impl)),
-    opcode!(0x57),
+    opcode!(0x57, "SRE", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(sre_impl)),
     opcode!(0x58, "CLI", AddressMode::Implied, 1, 2, single_byte_implied!(cli_impl)),
     opcode!(0x59, "EOR", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(eor_impl, y)),
-    opcode!(0x5A),
-    opcode!(0x5B),
-    opcode!(0x5C),
+    opcode!(0x5A, "NOP", AddressMode::Implied, 1, 2, single_byte_implied!(nop_impl)),
+    opcode!(0x5B, "SRE", AddressMode::AbsoluteY, 3, 7, load_store_absolute_indexed!(sre_impl, y)),
+    opcode!(0x5C, "NOP", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(nop_load_impl, x)),
     opcode!(0x5D, "EOR", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(eor_impl, x)),
-    opcode!(0x5E, "LSR", AddressMode::AbsoluteX, 3, 7, load_store_absolute_x!(lsr_impl)),
-    opcode!(0x5F),
+    opcode!(0x5E, "LSR", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(lsr_impl, x)),
+    opcode!(0x5F, "SRE", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(sre_impl, x)),
     // 0x60 - 0x6F
     opcode!(0x60, "RTS", AddressMode::Implied, 1, 6, return_from_subroutine_implied!(rts_impl)),
-    opcode!(0x61, "ADC", AddressMode::IndirectX, 2, 6, load_indirect_x!(adc_impl)),
+    opcode!(0x61, "ADC", AddressMode::IndirectX, 2, 6, load_indirect_x!(adc_impl, MicroOp::DecimalCycle)),
     opcode!(0x62),
-    opcode!(0x63),
-    opcode!(0x64),
-    opcode!(0x65, "ADC", AddressMode::ZeroPage, 2, 3, load_zero_page!(adc_impl)),
+    opcode!(0x63, "RRA", AddressMode::IndirectX, 2, 8, load_store_indirect_x!(rra_impl)),
+    opcode!(0x64, "NOP", AddressMode::ZeroPage, 2, 3, load_zero_page!(nop_load_impl)),
+    opcode!(0x65, "ADC", AddressMode::ZeroPage, 2, 3, load_zero_page!(adc_impl, MicroOp::DecimalCycle)),
     opcode!(0x66, "ROR", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(ror_impl)),
-    opcode!(0x67),
+    opcode!(0x67, "RRA", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(rra_impl)),
     opcode!(0x68, "PLA", AddressMode::Implied, 1, 4, pull_implied!(pla_impl)),
-    opcode!(0x69, "ADC", AddressMode::Immediate, 2, 2, load_immediate!(adc_impl)),
+    opcode!(0x69, "ADC", AddressMode::Immediate, 2, 2, load_immediate!(adc_impl, MicroOp::DecimalCycle)),
     opcode!(0x6A, "ROR", AddressMode::Accumulator, 1, 2, single_byte_accumulator!(ror_impl)),
     opcode!(0x6B),
     opcode!(0x6C, "JMP", AddressMode::Indirect, 3, 5, jump_indirect!(jmp_impl)),
-    opcode!(0x6D, "ADC", AddressMode::Absolute, 3, 4, load_absolute!(adc_impl)),
+    opcode!(0x6D, "ADC", AddressMode::Absolute, 3, 4, load_absolute!(adc_impl, MicroOp::DecimalCycle)),
     opcode!(0x6E, "ROR", AddressMode::Absolute, 3, 6, load_store_absolute!(ror_impl)),
-    opcode!(0x6F),
+    opcode!(0x6F, "RRA", AddressMode::Absolute, 3, 6, load_store_absolute!(rra_impl)),
     // 0x70 - 0x7F
     opcode!(0x70, "BVS", AddressMode::Relative, 2, 2, branch_relative!(bvs_impl)),
-    opcode!(0x71, "ADC", AddressMode::IndirectY, 2, 5, load_indirect_y!(adc_impl)),
+    opcode!(0x71, "ADC", AddressMode::IndirectY, 2, 5, load_indirect_y!(adc_impl, MicroOp::DecimalCycle)),
     opcode!(0x72),
-    opcode!(0x73),
-    opcode!(0x74),
-    opcode!(0x75, "ADC", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(adc_impl, x)),
+    opcode!(0x73, "RRA", AddressMode::IndirectY, 2, 8, load_store_indirect_y!(rra_impl)),
+    opcode!(0x74, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
+    opcode!(0x75, "ADC", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(adc_impl, x, MicroOp::DecimalCycle)),
     opcode!(0x76, "ROR", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(ror_impl)),
-    opcode!(0x77),
+    opcode!(0x77, "RRA", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(rra_impl)),
     opcode!(0x78, "SEI", AddressMode::Implied, 1, 2, single_byte_implied!(sei_impl)),
-    opcode!(0x79, "ADC", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(adc_impl, y)),
-    opcode!(0x7A),
-    opcode!(0x7B),
-    opcode!(0x7C),
-    opcode!(0x7D, "ADC", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(adc_impl, x)),
-    opcode!(0x7E, "ROR", AddressMode::AbsoluteX, 3, 7, load_store_absolute_x!(ror_impl)),
-    opcode!(0x7F),
+    opcode!(0x79, "ADC", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(adc_impl, y, MicroOp::DecimalCycle)),
+    opcode!(0x7A, "NOP", AddressMode::Implied, 1, 2, single_byte_implied!(nop_impl)),
+    opcode!(0x7B, "RRA", AddressMode::AbsoluteY, 3, 7, load_store_absolute_indexed!(rra_impl, y)),
+    opcode!(0x7C, "NOP", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(nop_load_impl, x)),
+    opcode!(0x7D, "ADC", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(adc_impl, x, MicroOp::DecimalCycle)),
+    opcode!(0x7E, "ROR", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(ror_impl, x)),
+    opcode!(0x7F, "RRA", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(rra_impl, x)),
     // 0x80 - 0x8F
-    opcode!(0x80),
+    opcode!(0x80, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
     opcode!(0x81, "STA", AddressMode::IndirectX, 2, 6, store_indirect_x!(sta_impl)),
-    opcode!(0x82),
-    opcode!(0x83),
+    opcode!(0x82, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0x83, "SAX", AddressMode::IndirectX, 2, 6, store_indirect_x!(sax_impl)),
     opcode!(0x84, "STY", AddressMode::ZeroPage, 2, 3, store_zero_page!(sty_impl)),
     opcode!(0x85, "STA", AddressMode::ZeroPage, 2, 3, store_zero_page!(sta_impl)),
     opcode!(0x86, "STX", AddressMode::ZeroPage, 2, 3, store_zero_page!(stx_impl)),
-    opcode!(0x87),
+    opcode!(0x87, "SAX", AddressMode::ZeroPage, 2, 3, store_zero_page!(sax_impl)),
     opcode!(0x88, "DEY", AddressMode::Implied, 1, 2, single_byte_implied!(dey_impl)),
-    opcode!(0x89),
+    opcode!(0x89, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
     opcode!(0x8A, "TXA", AddressMode::Implied, 1, 2, single_byte_implied!(txa_impl)),
     opcode!(0x8B),
     opcode!(0x8C, "STY", AddressMode::Absolute, 3, 4, store_absolute!(sty_impl)),
     opcode!(0x8D, "STA", AddressMode::Absolute, 3, 4, store_absolute!(sta_impl)),
     opcode!(0x8E, "STX", AddressMode::Absolute, 3, 4, store_absolute!(stx_impl)),
-    opcode!(0x8F),
+    opcode!(0x8F, "SAX", AddressMode::Absolute, 3, 4, store_absolute!(sax_impl)),
     // 0x90 - 0x9F
     opcode!(0x90, "BCC", AddressMode::Relative, 2, 2, branch_relative!(bcc_impl)),
     opcode!(0x91, "STA", AddressMode::IndirectY, 2, 6, store_indirect_y!(sta_impl)),
@@ -216,7 +224,7 @@ pub const OPCODES: [Opcode; 256] = [
     opcode!(0x94, "STY", AddressMode::ZeroPageX, 2, 4, store_zero_page_indexed!(sty_impl, x)),
     opcode!(0x95, "STA", AddressMode::ZeroPageX, 2, 4, store_zero_page_indexed!(sta_impl, x)),
     opcode!(0x96, "STX", AddressMode::ZeroPageY, 2, 4, store_zero_page_indexed!(stx_impl, y)),
-    opcode!(0x97),
+    opcode!(0x97, "SAX", AddressMode::ZeroPageY, 2, 4, store_zero_page_indexed!(sax_impl, y)),
     opcode!(0x98, "TYA", AddressMode::Implied, 1, 2, single_byte_implied!(tya_impl)),
     opcode!(0x99, "STA", AddressMode::AbsoluteY, 3, 5, store_absolute_indexed!(sta_impl, y)),
     opcode!(0x9A, "TXS", AddressMode::Implied, 1, 2, single_byte_implied!(txs_impl)),
@@ -229,11 +237,11 @@ pub const OPCODES: [Opcode; 256] = [
     opcode!(0xA0, "LDY", AddressMode::Immediate, 2, 2, load_immediate!(ldy_impl)),
     opcode!(0xA1, "LDA", AddressMode::IndirectX, 2, 6, load_indirect_x!(lda_impl)),
     opcode!(0xA2, "LDX", AddressMode::Immediate, 2, 2, load_immediate!(ldx_impl)),
-    opcode!(0xA3),
+    opcode!(0xA3, "LAX", AddressMode::IndirectX, 2, 6, load_indirect_x!(lax_impl)),
     opcode!(0xA4, "LDY", AddressMode::ZeroPage, 2, 3, load_zero_page!(ldy_impl)),
     opcode!(0xA5, "LDA", AddressMode::ZeroPage, 2, 3, load_zero_page!(lda_impl)),
     opcode!(0xA6, "LDX", AddressMode::ZeroPage, 2, 3, load_zero_page!(ldx_impl)),
-    opcode!(0xA7),
+    opcode!(0xA7, "LAX", AddressMode::ZeroPage, 2, 3, load_zero_page!(lax_impl)),
     opcode!(0xA8, "TAY", AddressMode::Implied, 1, 2, single_byte_implied!(tay_impl)),
     opcode!(0xA9, "LDA", AddressMode::Immediate, 2, 2, load_immediate!(lda_impl)),
     opcode!(0xAA, "TAX", AddressMode::Implied, 1, 2, single_byte_implied!(tax_impl)),
@@ -241,16 +249,16 @@ pub const OPCODES: [Opcode; 256] = [
     opcode!(0xAC, "LDY", AddressMode::Absolute, 3, 4, load_absolute!(ldy_impl)),
     opcode!(0xAD, "LDA", AddressMode::Absolute, 3, 4, load_absolute!(lda_impl)),
     opcode!(0xAE, "LDX", AddressMode::Absolute, 3, 4, load_absolute!(ldx_impl)),
-    opcode!(0xAF),
+    opcode!(0xAF, "LAX", AddressMode::Absolute, 3, 4, load_absolute!(lax_impl)),
     // 0xB0 - 0xBF
     opcode!(0xB0, "BCS", AddressMode::Relative, 2, 2, branch_relative!(bcs_impl)),
     opcode!(0xB1, "LDA", AddressMode::IndirectY, 2, 5, load_indirect_y!(lda_impl)),
     opcode!(0xB2),
-    opcode!(0xB3),
+    opcode!(0xB3, "LAX", AddressMode::IndirectY, 2, 5, load_indirect_y!(lax_impl)),
     opcode!(0xB4, "LDY", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(ldy_impl, x)),
     opcode!(0xB5, "LDA", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(lda_impl, x)),
     opcode!(0xB6, "LDX", AddressMode::ZeroPageY, 2, 4, load_zero_page_indexed!(ldx_impl, y)),
-    opcode!(0xB7),
+    opcode!(0xB7, "LAX", AddressMode::ZeroPageY, 2, 4, load_zero_page_indexed!(lax_impl, y)),
     opcode!(0xB8, "CLV", AddressMode::Implied, 1, 2, single_byte_implied!(clv_impl)),
     opcode!(0xB9, "LDA", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(lda_impl, y)),
     opcode!(0xBA, "TSX", AddressMode::Implied, 1, 2, single_byte_implied!(tsx_impl)),
@@ -258,16 +266,16 @@ pub const OPCODES: [Opcode; 256] = [
     opcode!(0xBC, "LDY", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(ldy_impl, x)),
     opcode!(0xBD, "LDA", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(lda_impl, x)),
     opcode!(0xBE, "LDX", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(ldx_impl, y)),
-    opcode!(0xBF),
+    opcode!(0xBF, "LAX", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(lax_impl, y)),
     // 0xC0 - 0xCF
-    opcode!(0xC0, "CPY", AddressMode::ZeroPage, 2, 3, load_zero_page!(cpy_impl)),
+    opcode!(0xC0, "CPY", AddressMode::Immediate, 2, 2, load_immediate!(cpy_impl)),
     opcode!(0xC1, "CMP", AddressMode::IndirectX, 2, 6, load_indirect_x!(cmp_impl)),
-    opcode!(0xC2),
-    opcode!(0xC3),
-    opcode!(0xC4, "CPY", AddressMode::Immediate, 2, 2, load_immediate!(cpy_impl)),
+    opcode!(0xC2, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0xC3, "DCP", AddressMode::IndirectX, 2, 8, load_store_indirect_x!(dcp_impl)),
+    opcode!(0xC4, "CPY", AddressMode::ZeroPage, 2, 3, load_zero_page!(cpy_impl)),
     opcode!(0xC5, "CMP", AddressMode::ZeroPage, 2, 3, load_zero_page!(cmp_impl)),
     opcode!(0xC6, "DEC", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(dec_impl)),
-    opcode!(0xC7),
+    opcode!(0xC7, "DCP", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(dcp_impl)),
     opcode!(0xC8, "INY", AddressMode::Implied, 1, 2, single_byte_implied!(iny_impl)),
     opcode!(0xC9, "CMP", AddressMode::Immediate, 2, 2, load_immediate!(cmp_impl)),
     opcode!(0xCA, "DEX", AddressMode::Implied, 1, 2, single_byte_implied!(dex_impl)),
@@ -275,76 +283,307 @@ pub const OPCODES: [Opcode; 256] = [
     opcode!(0xCC, "CPY", AddressMode::Absolute, 3, 4, load_absolute!(cpy_impl)),
     opcode!(0xCD, "CMP", AddressMode::Absolute, 3, 4, load_absolute!(cmp_impl)),
     opcode!(0xCE, "DEC", AddressMode::Absolute, 3, 6, load_store_absolute!(dec_impl)),
-    opcode!(0xCF),
+    opcode!(0xCF, "DCP", AddressMode::Absolute, 3, 6, load_store_absolute!(dcp_impl)),
     // 0xD0 - 0xDF
     opcode!(0xD0, "BNE", AddressMode::Relative, 2, 2, branch_relative!(bne_impl)),
     opcode!(0xD1, "CMP", AddressMode::IndirectY, 2, 5, load_indirect_y!(cmp_impl)),
     opcode!(0xD2),
-    opcode!(0xD3),
-    opcode!(0xD4),
+    opcode!(0xD3, "DCP", AddressMode::IndirectY, 2, 8, load_store_indirect_y!(dcp_impl)),
+    opcode!(0xD4, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
     opcode!(0xD5, "CMP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(cmp_impl, x)),
     opcode!(0xD6, "DEC", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(dec_impl)),
-    opcode!(0xD7),
+    opcode!(0xD7, "DCP", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(dcp_impl)),
     opcode!(0xD8, "CLD", AddressMode::Implied, 1, 2, single_byte_implied!(cld_impl)),
     opcode!(0xD9, "CMP", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(cmp_impl, y)),
-    opcode!(0xDA),
-    opcode!(0xDB),
-    opcode!(0xDC),
+    opcode!(0xDA, "NOP", AddressMode::Implied, 1, 2, single_byte_implied!(nop_impl)),
+    opcode!(0xDB, "DCP", AddressMode::AbsoluteY, 3, 7, load_store_absolute_indexed!(dcp_impl, y)),
+    opcode!(0xDC, "NOP", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(nop_load_impl, x)),
     opcode!(0xDD, "CMP", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(cmp_impl, x)),
-    opcode!(0xDE, "DEC", AddressMode::AbsoluteX, 3, 7, load_store_absolute_x!(dec_impl)),
-    opcode!(0xDF),
+    opcode!(0xDE, "DEC", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(dec_impl, x)),
+    opcode!(0xDF, "DCP", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(dcp_impl, x)),
     // 0xE0 - 0xEF
     opcode!(0xE0, "CPX", AddressMode::Immediate, 2, 2, load_immediate!(cpx_impl)),
-    opcode!(0xE1, "SBC", AddressMode::IndirectX, 2, 6, load_indirect_x!(sbc_impl)),
-    opcode!(0xE2),
-    opcode!(0xE3),
+    opcode!(0xE1, "SBC", AddressMode::IndirectX, 2, 6, load_indirect_x!(sbc_impl, MicroOp::DecimalCycle)),
+    opcode!(0xE2, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0xE3, "ISC", AddressMode::IndirectX, 2, 8, load_store_indirect_x!(isc_impl)),
     opcode!(0xE4, "CPX", AddressMode::ZeroPage, 2, 3, load_zero_page!(cpx_impl)),
-    opcode!(0xE5, "SBC", AddressMode::ZeroPage, 2, 3, load_zero_page!(sbc_impl)),
+    opcode!(0xE5, "SBC", AddressMode::ZeroPage, 2, 3, load_zero_page!(sbc_impl, MicroOp::DecimalCycle)),
     opcode!(0xE6, "INC", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(inc_impl)),
-    opcode!(0xE7),
+    opcode!(0xE7, "ISC", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(isc_impl)),
     opcode!(0xE8, "INX", AddressMode::Implied, 1, 2, single_byte_implied!(inx_impl)),
-    opcode!(0xE9, "SBC", AddressMode::Immediate, 2, 2, load_immediate!(sbc_impl)),
+    opcode!(0xE9, "SBC", AddressMode::Immediate, 2, 2, load_immediate!(sbc_impl, MicroOp::DecimalCycle)),
     opcode!(0xEA, "NOP", AddressMode::Implied, 1, 2, single_byte_implied!(nop_impl)),
-    opcode!(0xEB),
+    opcode!(0xEB, "SBC", AddressMode::Immediate, 2, 2, load_immediate!(sbc_impl)),
     opcode!(0xEC, "CPX", AddressMode::Absolute, 3, 4, load_absolute!(cpx_impl)),
-    opcode!(0xED, "SBC", AddressMode::Absolute, 3, 4, load_absolute!(sbc_impl)),
+    opcode!(0xED, "SBC", AddressMode::Absolute, 3, 4, load_absolute!(sbc_impl, MicroOp::DecimalCycle)),
     opcode!(0xEE, "INC", AddressMode::Absolute, 3, 6, load_store_absolute!(inc_impl)),
-    opcode!(0xEF),
+    opcode!(0xEF, "ISC", AddressMode::Absolute, 3, 6, load_store_absolute!(isc_impl)),
     // 0xF0 - 0xFF
     opcode!(0xF0, "BEQ", AddressMode::Relative, 2, 2, branch_relative!(beq_impl)),
-    opcode!(0xF1, "SBC", AddressMode::IndirectY, 2, 5, load_indirect_y!(sbc_impl)),
+    opcode!(0xF1, "SBC", AddressMode::IndirectY, 2, 5, load_indirect_y!(sbc_impl, MicroOp::DecimalCycle)),
     opcode!(0xF2),
-    opcode!(0xF3),
-    opcode!(0xF4),
-    opcode!(0xF5, "SBC", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(sbc_impl, x)),
+    opcode!(0xF3, "ISC", AddressMode::IndirectY, 2, 8, load_store_indirect_y!(isc_impl)),
+    opcode!(0xF4, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
+    opcode!(0xF5, "SBC", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(sbc_impl, x, MicroOp::DecimalCycle)),
     opcode!(0xF6, "INC", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(inc_impl)),
-    opcode!(0xF7),
+    opcode!(0xF7, "ISC", AddressMode::ZeroPageX, 2, 6, load_store_zero_page_x!(isc_impl)),
     opcode!(0xF8, "SED", AddressMode::Implied, 1, 2, single_byte_implied!(sed_impl)),
-    opcode!(0xF9, "SBC", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(sbc_impl, y)),
-    opcode!(0xFA),
-    opcode!(0xFB),
-    opcode!(0xFC),
-    opcode!(0xFD, "SBC", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(sbc_impl, x)),
-    opcode!(0xFE, "INC", AddressMode::AbsoluteX, 3, 7, load_store_absolute_x!(inc_impl)),
-    opcode!(0xFF),
+    opcode!(0xF9, "SBC", AddressMode::AbsoluteY, 3, 4, load_absolute_indexed!(sbc_impl, y, MicroOp::DecimalCycle)),
+    opcode!(0xFA, "NOP", AddressMode::Implied, 1, 2, single_byte_implied!(nop_impl)),
+    opcode!(0xFB, "ISC", AddressMode::AbsoluteY, 3, 7, load_store_absolute_indexed!(isc_impl, y)),
+    opcode!(0xFC, "NOP", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(nop_load_impl, x)),
+    opcode!(0xFD, "SBC", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(sbc_impl, x, MicroOp::DecimalCycle)),
+    opcode!(0xFE, "INC", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(inc_impl, x)),
+    opcode!(0xFF, "ISC", AddressMode::AbsoluteX, 3, 7, load_store_absolute_indexed!(isc_impl, x)),
+];
+
+/// The 65C02 table: the NMOS table with the new instructions added and every
+/// undocumented opcode turned into a NOP of the same length. The Rockwell and
+/// WDC bit instructions in the `$x7` and `$xF` columns are not emulated.
+pub const CMOS_OPCODES: [Opcode; 256] = {
+    let mut table = OPCODES;
+    let mut value = 0;
+    while value < table.len() {
+        // the $x3, $x7, $xB and $xF columns are single cycle nops
+        if value & 0x03 == 0x03 {
+            table[value] = opcode!(
+                value as u8,
+                "NOP",
+                AddressMode::Implied,
+                1,
+                1,
+                single_cycle_implied!()
+            );
+        }
+        value += 1;
+    }
+
+    let mut i = 0;
+    while i < CMOS_CHANGES.len() {
+        let opcode = CMOS_CHANGES[i];
+        table[opcode.value as usize] = opcode;
+        i += 1;
+    }
+    table
+};
+
+#[rustfmt::skip]
+const CMOS_CHANGES: [Opcode; 46] = [
+    // new instructions
+    opcode!(0x04, "TSB", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(tsb_impl)),
+    opcode!(0x0C, "TSB", AddressMode::Absolute, 3, 6, load_store_absolute!(tsb_impl)),
+    opcode!(0x12, "ORA", AddressMode::ZeroPageIndirect, 2, 5, load_zero_page_indirect!(ora_impl)),
+    opcode!(0x14, "TRB", AddressMode::ZeroPage, 2, 5, load_store_zero_page!(trb_impl)),
+    opcode!(0x1A, "INC", AddressMode::Accumulator, 1, 2, single_byte_implied!(inc_acc_impl)),
+    opcode!(0x1C, "TRB", AddressMode::Absolute, 3, 6, load_store_absolute!(trb_impl)),
+    opcode!(0x32, "AND", AddressMode::ZeroPageIndirect, 2, 5, load_zero_page_indirect!(and_impl)),
+    opcode!(0x34, "BIT", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(bit_impl, x)),
+    opcode!(0x3A, "DEC", AddressMode::Accumulator, 1, 2, single_byte_implied!(dec_acc_impl)),
+    opcode!(0x3C, "BIT", AddressMode::AbsoluteX, 3, 4, load_absolute_indexed!(bit_impl, x)),
+    opcode!(0x52, "EOR", AddressMode::ZeroPageIndirect, 2, 5, load_zero_page_indirect!(eor_impl)),
+    opcode!(0x5A, "PHY", AddressMode::Implied, 1, 3, push_implied!(phy_impl)),
+    opcode!(0x64, "STZ", AddressMode::ZeroPage, 2, 3, store_zero_page!(stz_impl)),
+    opcode!(0x72, "ADC", AddressMode::ZeroPageIndirect, 2, 5, load_zero_page_indirect!(adc_impl, MicroOp::DecimalCycle)),
+    opcode!(0x74, "STZ", AddressMode::ZeroPageX, 2, 4, store_zero_page_indexed!(stz_impl, x)),
+    opcode!(0x7A, "PLY", AddressMode::Implied, 1, 4, pull_implied!(ply_impl)),
+    opcode!(0x7C, "JMP", AddressMode::AbsoluteIndirectX, 3, 6, jump_absolute_indirect_x!(jmp_impl)),
+    opcode!(0x80, "BRA", AddressMode::Relative, 2, 3, branch_relative!(bra_impl)),
+    opcode!(0x89, "BIT", AddressMode::Immediate, 2, 2, load_immediate!(bit_immediate_impl)),
+    opcode!(0x92, "STA", AddressMode::ZeroPageIndirect, 2, 5, store_zero_page_indirect!(sta_impl)),
+    opcode!(0x9C, "STZ", AddressMode::Absolute, 3, 4, store_absolute!(stz_impl)),
+    opcode!(0x9E, "STZ", AddressMode::AbsoluteX, 3, 5, store_absolute_indexed!(stz_impl, x)),
+    opcode!(0xB2, "LDA", AddressMode::ZeroPageIndirect, 2, 5, load_zero_page_indirect!(lda_impl)),
+    opcode!(0xD2, "CMP", AddressMode::ZeroPageIndirect, 2, 5, load_zero_page_indirect!(cmp_impl)),
+    opcode!(0xDA, "PHX", AddressMode::Implied, 1, 3, push_implied!(phx_impl)),
+    opcode!(0xF2, "SBC", AddressMode::ZeroPageIndirect, 2, 5, load_zero_page_indirect!(sbc_impl, MicroOp::DecimalCycle)),
+    opcode!(0xFA, "PLX", AddressMode::Implied, 1, 4, pull_implied!(plx_impl)),
+    // the fixed page crossing of jmp (abs) takes a cycle more
+    opcode!(0x6C, "JMP", AddressMode::Indirect, 3, 6, jump_indirect!(jmp_impl)),
+    // the shifts and rotates only take the fix-up cycle of abs,x on a page crossing
+    opcode!(0x1E, "ASL", AddressMode::AbsoluteX, 3, 6, load_store_absolute_x_short!(asl_impl)),
+    opcode!(0x3E, "ROL", AddressMode::AbsoluteX, 3, 6, load_store_absolute_x_short!(rol_impl)),
+    opcode!(0x5E, "LSR", AddressMode::AbsoluteX, 3, 6, load_store_absolute_x_short!(lsr_impl)),
+    opcode!(0x7E, "ROR", AddressMode::AbsoluteX, 3, 6, load_store_absolute_x_short!(ror_impl)),
+    // nops
+    opcode!(0x02, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0x22, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0x42, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0x44, "NOP", AddressMode::ZeroPage, 2, 3, load_zero_page!(nop_load_impl)),
+    opcode!(0x54, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
+    opcode!(0x5C, "NOP", AddressMode::Absolute, 3, 8, nop_absolute_long!()),
+    opcode!(0x62, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0x82, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0xC2, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0xD4, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
+    opcode!(0xDC, "NOP", AddressMode::Absolute, 3, 4, load_absolute!(nop_load_impl)),
+    opcode!(0xE2, "NOP", AddressMode::Immediate, 2, 2, load_immediate!(nop_load_impl)),
+    opcode!(0xF4, "NOP", AddressMode::ZeroPageX, 2, 4, load_zero_page_indexed!(nop_load_impl, x)),
+    opcode!(0xFC, "NOP", AddressMode::Absolute, 3, 4, load_absolute!(nop_load_impl)),
 ];
 
-pub fn decode_instruction(opcode: u8) -> &'static [MicroOp] {
-    let decoded = &OPCODES[opcode as usize];
+/// Returns the opcode table of a CPU model.
+pub fn opcode_table(variant: CpuVariant) -> &'static [Opcode; 256] {
+    return match variant {
+        CpuVariant::Nmos6502 => &OPCODES,
+        CpuVariant::Cmos65C02 => &CMOS_OPCODES,
+    };
+}
+
+/// Returns the microcode for an opcode, or `None` for the opcodes which jam the
+/// CPU. These are the `KIL` opcodes and the unstable undocumented ones, which
+/// are not modelled.
+pub fn decode_instruction(variant: CpuVariant, opcode: u8) -> Option<&'static [MicroOp]> {
+    return opcode_table(variant)[opcode as usize].ucode;
+}
+
+/// The opcodes of the NMOS part which are in the data sheet. The others are the
+/// undocumented opcodes, which are side effects of how the instruction decoder
+/// works and which assemblers do not emit.
+#[rustfmt::skip]
+const DOCUMENTED: [u8; 151] = [
+    0x00, 0x01, 0x05, 0x06, 0x08, 0x09, 0x0A, 0x0D, 0x0E,
+    0x10, 0x11, 0x15, 0x16, 0x18, 0x19, 0x1D, 0x1E,
+    0x20, 0x21, 0x24, 0x25, 0x26, 0x28, 0x29, 0x2A, 0x2C, 0x2D, 0x2E,
+    0x30, 0x31, 0x35, 0x36, 0x38, 0x39, 0x3D, 0x3E,
+    0x40, 0x41, 0x45, 0x46, 0x48, 0x49, 0x4A, 0x4C, 0x4D, 0x4E,
+    0x50, 0x51, 0x55, 0x56, 0x58, 0x59, 0x5D, 0x5E,
+    0x60, 0x61, 0x65, 0x66, 0x68, 0x69, 0x6A, 0x6C, 0x6D, 0x6E,
+    0x70, 0x71, 0x75, 0x76, 0x78, 0x79, 0x7D, 0x7E,
+    0x81, 0x84, 0x85, 0x86, 0x88, 0x8A, 0x8C, 0x8D, 0x8E,
+    0x90, 0x91, 0x94, 0x95, 0x96, 0x98, 0x99, 0x9A, 0x9D,
+    0xA0, 0xA1, 0xA2, 0xA4, 0xA5, 0xA6, 0xA8, 0xA9, 0xAA, 0xAC, 0xAD, 0xAE,
+    0xB0, 0xB1, 0xB4, 0xB5, 0xB6, 0xB8, 0xB9, 0xBA, 0xBC, 0xBD, 0xBE,
+    0xC0, 0xC1, 0xC4, 0xC5, 0xC6, 0xC8, 0xC9, 0xCA, 0xCC, 0xCD, 0xCE,
+    0xD0, 0xD1, 0xD5, 0xD6, 0xD8, 0xD9, 0xDD, 0xDE,
+    0xE0, 0xE1, 0xE4, 0xE5, 0xE6, 0xE8, 0xE9, 0xEA, 0xEC, 0xED, 0xEE,
+    0xF0, 0xF1, 0xF5, 0xF6, 0xF8, 0xF9, 0xFD, 0xFE,
+];
+
+/// Returns whether an opcode is in the data sheet of a CPU model. On the 65C02
+/// that is the NMOS instructions and the new ones, but not the NOPs which took
+/// the place of the undocumented opcodes.
+fn is_documented(variant: CpuVariant, opcode: u8) -> bool {
+    if DOCUMENTED.contains(&opcode) {
+        return true;
+    }
+    return variant == CpuVariant::Cmos65C02
+        && CMOS_CHANGES
+            .iter()
+            .any(|change| change.value == opcode && change.mnemonic != "NOP");
+}
+
+/// What the opcode table documents about an opcode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub value: u8,
+    pub mnemonic: &'static str,
+    pub mode: AddressMode,
+    /// The length of the instruction, including the opcode.
+    pub bytes: u8,
+    /// The cycles the instruction takes, before any page crossing or branch
+    /// penalties.
+    pub cycles: u8,
+    /// Whether the opcode is in the data sheet, rather than an undocumented one.
+    pub documented: bool,
+}
+
+/// Returns the documented length and timing of an opcode, or `None` for the
+/// opcodes which jam the CPU.
+pub fn opcode_info(variant: CpuVariant, opcode: u8) -> Option<OpcodeInfo> {
+    let decoded = &opcode_table(variant)[opcode as usize];
+    decoded.ucode?;
+    return Some(OpcodeInfo {
+        value: decoded.value,
+        mnemonic: decoded.mnemonic,
+        mode: decoded.mode,
+        bytes: decoded.bytes,
+        cycles: decoded.cycles,
+        documented: is_documented(variant, decoded.value),
+    });
+}
+
+/// Returns every opcode of a CPU model which does not jam it, in order.
+pub fn opcodes(variant: CpuVariant) -> impl Iterator<Item = OpcodeInfo> {
+    return (0..=255).filter_map(move |opcode| opcode_info(variant, opcode));
+}
+
+/// Returns the opcodes of an instruction, given by its mnemonic in any case.
+pub fn opcodes_by_mnemonic(
+    variant: CpuVariant,
+    mnemonic: &str,
+) -> impl Iterator<Item = OpcodeInfo> + '_ {
+    return opcodes(variant).filter(move |info| info.mnemonic.eq_ignore_ascii_case(mnemonic));
+}
+
+/// Returns the opcodes which use an addressing mode.
+pub fn opcodes_by_mode(variant: CpuVariant, mode: AddressMode) -> impl Iterator<Item = OpcodeInfo> {
+    return opcodes(variant).filter(move |info| info.mode == mode);
+}
+
+/// Returns the opcode which encodes an instruction in an addressing mode. Where
+/// an undocumented opcode duplicates a documented one, like `SBC #` at $EB, the
+/// documented one is returned.
+pub fn find_opcode(variant: CpuVariant, mnemonic: &str, mode: AddressMode) -> Option<OpcodeInfo> {
+    let mut matching = opcodes_by_mnemonic(variant, mnemonic).filter(|info| info.mode == mode);
+    let first = matching.next()?;
+    if first.documented {
+        return Some(first);
+    }
+    return Some(matching.find(|info| info.documented).unwrap_or(first));
+}
+
+#[allow(dead_code)]
+pub fn decode_instruction_to_string(variant: CpuVariant, opcode: u8) -> &'static str {
+    let decoded = &opcode_table(variant)[opcode as usize];
     if decoded.ucode.is_none() {
-        // invalid instruction
-        panic!("invalid opcode")
+        // unknown instruction
+        return "???";
     }
 
-    return &OPCODES[opcode as usize].ucode.unwrap();
+    return decoded.mnemonic;
 }
 
-pub fn decode_instruction_to_string(opcode: u8) -> &'static str {
-    let decoded = &OPCODES[opcode as usize];
+/// Formats an instruction at `pc` in assembly syntax. Addresses which have a
+/// name in `symbols` are shown by name.
+pub fn disassemble(
+    variant: CpuVariant,
+    pc: u16,
+    opcode: u8,
+    operands: &[u8],
+    symbols: Option<&SymbolTable>,
+) -> String {
+    let decoded = &opcode_table(variant)[opcode as usize];
     if decoded.ucode.is_none() {
-        // invalid instruction
-        panic!("invalid opcode")
+        return "???".to_string();
     }
 
-    return &OPCODES[opcode as usize].mnemonic;
+    let byte = operands.first().copied().unwrap_or(0);
+    let word = u16::from_le_bytes([byte, operands.get(1).copied().unwrap_or(0)]);
+    let name = |address: u16, hex: String| match symbols.and_then(|s| s.name(address)) {
+        Some(name) => name.to_string(),
+        None => hex,
+    };
+    let zp = name(byte as u16, format!("${:02X}", byte));
+    let abs = name(word, format!("${:04X}", word));
+    let operand = match decoded.mode {
+        AddressMode::Accumulator => "A".to_string(),
+        AddressMode::Absolute => abs,
+        AddressMode::AbsoluteX => format!("{},X", abs),
+        AddressMode::AbsoluteY => format!("{},Y", abs),
+        AddressMode::Immediate => format!("#${:02X}", byte),
+        AddressMode::Implied => return decoded.mnemonic.to_string(),
+        AddressMode::Indirect => format!("({})", abs),
+        AddressMode::IndirectX => format!("({},X)", zp),
+        AddressMode::IndirectY => format!("({}),Y", zp),
+        AddressMode::ZeroPageIndirect => format!("({})", zp),
+        AddressMode::AbsoluteIndirectX => format!("({},X)", abs),
+        AddressMode::Relative => {
+            let target = pc.wrapping_add(2).wrapping_add(byte as i8 as u16);
+            name(target, format!("${:04X}", target))
+        }
+        AddressMode::ZeroPage => zp,
+        AddressMode::ZeroPageX => format!("{},X", zp),
+        AddressMode::ZeroPageY => format!("{},Y", zp),
+    };
+    return format!("{} {}", decoded.mnemonic, operand);
 }
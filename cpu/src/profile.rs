@@ -0,0 +1,118 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::cpu::CpuVariant;
+use crate::opcode;
+
+/// How many times some instructions ran and the cycles they took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionStats {
+    pub instructions: u64,
+    pub cycles: u64,
+}
+
+impl ExecutionStats {
+    fn add(&mut self, cycles: u64) {
+        self.instructions += 1;
+        self.cycles += cycles;
+    }
+}
+
+/// The instructions a [`crate::Cpu`] ran while profiling, bucketed by the
+/// address they were fetched from and by mnemonic.
+///
+/// Cycles include page crossing and branch penalties. Interrupt sequences are
+/// not instructions and are not counted, though a `BRK` is.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    by_address: BTreeMap<u16, ExecutionStats>,
+    by_mnemonic: HashMap<&'static str, ExecutionStats>,
+    total: ExecutionStats,
+    /// The address and first cycle of the instruction being run.
+    current: Option<(u16, u64)>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the stats of every instruction run.
+    pub fn total(&self) -> ExecutionStats {
+        return self.total;
+    }
+
+    /// Returns the stats of the instructions run from `address`.
+    pub fn at(&self, address: u16) -> ExecutionStats {
+        return self.by_address.get(&address).copied().unwrap_or_default();
+    }
+
+    /// Returns the addresses instructions ran from, the most cycles first.
+    pub fn by_address(&self) -> Vec<(u16, ExecutionStats)> {
+        let mut entries: Vec<_> = self.by_address.iter().map(|(a, s)| (*a, *s)).collect();
+        entries.sort_by(|(a, lhs), (b, rhs)| rhs.cycles.cmp(&lhs.cycles).then(a.cmp(b)));
+        return entries;
+    }
+
+    /// Returns the mnemonics of the instructions run, the most cycles first.
+    pub fn by_mnemonic(&self) -> Vec<(&'static str, ExecutionStats)> {
+        let mut entries: Vec<_> = self.by_mnemonic.iter().map(|(m, s)| (*m, *s)).collect();
+        entries.sort_by(|(a, lhs), (b, rhs)| rhs.cycles.cmp(&lhs.cycles).then(a.cmp(b)));
+        return entries;
+    }
+
+    /// Formats the `rows` hottest addresses and mnemonics as two tables, with
+    /// their share of the cycles.
+    /// ```text
+    ///     address  instructions      cycles       %
+    ///     $0203               3           8    44.4
+    /// ```
+    pub fn report(&self, rows: usize) -> String {
+        let addresses = self.by_address().into_iter().take(rows);
+        let mnemonics = self.by_mnemonic().into_iter().take(rows);
+        return [
+            self.table(
+                "address",
+                addresses.map(|(a, s)| (format!("${:04x}", a), s)),
+            ),
+            self.table("mnemonic", mnemonics.map(|(m, s)| (m.to_string(), s))),
+        ]
+        .join("\n");
+    }
+
+    //
+
+    /// Notes that the opcode at `pc` was fetched in `cycle`.
+    pub(crate) fn begin(&mut self, pc: u16, cycle: u64) {
+        self.current = Some((pc, cycle));
+    }
+
+    /// Counts the instruction begun last, which completed before `cycle`.
+    pub(crate) fn finish(&mut self, variant: CpuVariant, opcode: u8, cycle: u64) {
+        let Some((pc, start)) = self.current.take() else {
+            return;
+        };
+        let cycles = cycle - start;
+        let mnemonic = opcode::opcode_table(variant)[opcode as usize].mnemonic;
+        self.by_address.entry(pc).or_default().add(cycles);
+        self.by_mnemonic.entry(mnemonic).or_default().add(cycles);
+        self.total.add(cycles);
+    }
+
+    fn table(&self, key: &str, entries: impl Iterator<Item = (String, ExecutionStats)>) -> String {
+        let mut text = format!(
+            "{:<8} {:>12} {:>11} {:>7}\n",
+            key, "instructions", "cycles", "%"
+        );
+        for (key, stats) in entries {
+            let share = 100.0 * stats.cycles as f64 / self.total.cycles.max(1) as f64;
+            writeln!(
+                text,
+                "{:<8} {:>12} {:>11} {:>7.1}",
+                key, stats.instructions, stats.cycles, share
+            )
+            .unwrap();
+        }
+        return text;
+    }
+}
@@ -1,7 +1,5 @@
 use crate::utility::bitset;
 
-use num_traits;
-
 #[derive(Clone, Copy)]
 pub struct Register<T>(T);
 
@@ -10,7 +8,7 @@ where
     T: Copy,
 {
     pub fn new(value: T) -> Self {
-        Self { 0: value }
+        Self(value)
     }
 
     pub fn get(self) -> T {
@@ -82,6 +80,12 @@ pub struct Registers {
     pub pc: Register<u16>,
 }
 
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Registers {
     pub fn new() -> Self {
         Self {
@@ -0,0 +1,141 @@
+//! The binary format used for saved states.
+//!
+//! Every state starts with a four byte magic which identifies what was saved
+//! and a version byte. The fields that follow are fixed size and little endian.
+
+/// A problem found while restoring a saved state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StateError {
+    /// The data is not a saved state of the expected kind.
+    BadMagic,
+    /// The state was saved in a format version this build does not know.
+    UnsupportedVersion(u8),
+    /// The data ends before the state is complete.
+    Truncated,
+    /// The data continues after the state is complete.
+    TrailingData,
+    /// A field holds a value which cannot be restored.
+    Invalid(&'static str),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a saved state"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported state version {}", version)
+            }
+            StateError::Truncated => write!(f, "saved state is truncated"),
+            StateError::TrailingData => write!(f, "unexpected data after saved state"),
+            StateError::Invalid(field) => write!(f, "saved state has an invalid {}", field),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Builds a saved state.
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new(magic: &[u8; 4], version: u8) -> Self {
+        let mut data = magic.to_vec();
+        data.push(version);
+        Self { data }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        return self.data;
+    }
+}
+
+/// Reads the fields of a saved state in the order they were written.
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    /// Checks the header of `data` and returns the reader along with the
+    /// version the state was saved in. Versions above `latest` are rejected.
+    pub fn new(data: &'a [u8], magic: &[u8; 4], latest: u8) -> Result<(Self, u8), StateError> {
+        if data.len() < 5 {
+            return Err(StateError::Truncated);
+        }
+        if &data[..4] != magic {
+            return Err(StateError::BadMagic);
+        }
+        let version = data[4];
+        if version == 0 || version > latest {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        return Ok((Self { data: &data[5..] }, version));
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        return Ok(self.bytes(1)?[0]);
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        return match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid("flag")),
+        };
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        return Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()));
+    }
+
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        return Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()));
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        return Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()));
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        return Ok(bytes);
+    }
+
+    /// Checks that the whole state was read.
+    pub fn finish(self) -> Result<(), StateError> {
+        if !self.data.is_empty() {
+            return Err(StateError::TrailingData);
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,157 @@
+//! Names for addresses, shared by the assembler which produces them and the
+//! tools which print addresses.
+//!
+//! A symbol file has one symbol per line, with the address first:
+//! ```text
+//!     $1000 start
+//!     $a000 STDOUT
+//! ```
+//! Blank lines and anything after a `;` are ignored. Symbols after a `[public]`
+//! line are public, which are those a program exports for other tools, such as
+//! a machine profile, to refer to by name:
+//! ```text
+//!     $1000 start
+//!
+//!     [public]
+//!     $a000 IO_BASE
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A line of a symbol file which could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolError {
+    /// The line number, starting at 1.
+    pub line: usize,
+}
+
+impl std::fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "malformed symbol on line {}", self.line);
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    names: BTreeMap<String, u16>,
+    /// The name shown for each address. When several symbols share an address
+    /// the first one inserted is used.
+    addresses: BTreeMap<u16, String>,
+    public: BTreeSet<String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a symbol file.
+    pub fn parse(text: &str) -> Result<Self, SymbolError> {
+        let mut table = Self::new();
+        let mut public = false;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = SymbolError { line: index + 1 };
+            if line == "[public]" {
+                public = true;
+                continue;
+            }
+            let (address, name) = line.split_once(char::is_whitespace).ok_or(error.clone())?;
+            let address = address.strip_prefix('$').ok_or(error.clone())?;
+            let address = u16::from_str_radix(address, 16).map_err(|_| error.clone())?;
+            let name = name.trim();
+            if name.contains(char::is_whitespace) {
+                return Err(error);
+            }
+            if public {
+                table.insert_public(name, address);
+            } else {
+                table.insert(name, address);
+            }
+        }
+        return Ok(table);
+    }
+
+    /// Defines `name`, replacing any address it had before.
+    pub fn insert(&mut self, name: impl Into<String>, address: u16) {
+        let name = name.into();
+        if let Some(old) = self.names.insert(name.clone(), address) {
+            if self.addresses.get(&old) == Some(&name) {
+                self.addresses.remove(&old);
+                if let Some((other, _)) = self.names.iter().find(|(_, a)| **a == old) {
+                    self.addresses.insert(old, other.clone());
+                }
+            }
+        }
+        self.addresses.entry(address).or_insert(name);
+    }
+
+    /// Defines `name` as a public symbol.
+    pub fn insert_public(&mut self, name: impl Into<String>, address: u16) {
+        let name = name.into();
+        self.insert(name.clone(), address);
+        self.public.insert(name);
+    }
+
+    pub fn address(&self, name: &str) -> Option<u16> {
+        return self.names.get(name).copied();
+    }
+
+    /// Returns the address of `name` if it is a public symbol.
+    pub fn public_address(&self, name: &str) -> Option<u16> {
+        return self.address(name).filter(|_| self.is_public(name));
+    }
+
+    pub fn is_public(&self, name: &str) -> bool {
+        return self.public.contains(name);
+    }
+
+    /// Returns the name to show for `address`.
+    pub fn name(&self, address: u16) -> Option<&str> {
+        return self.addresses.get(&address).map(String::as_str);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.names.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.names.is_empty();
+    }
+
+    /// Returns every symbol, ordered by address and then by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u16)> {
+        let mut symbols: Vec<(&str, u16)> = self
+            .names
+            .iter()
+            .map(|(name, address)| (name.as_str(), *address))
+            .collect();
+        symbols.sort_by_key(|(name, address)| (*address, *name));
+        return symbols.into_iter();
+    }
+}
+
+/// Formats the table as a symbol file.
+impl std::fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, address) in self.iter().filter(|(name, _)| !self.is_public(name)) {
+            writeln!(f, "${:04x} {}", address, name)?;
+        }
+        if !self.public.is_empty() {
+            if self.public.len() < self.len() {
+                writeln!(f)?;
+            }
+            writeln!(f, "[public]")?;
+            for (name, address) in self.iter().filter(|(name, _)| self.is_public(name)) {
+                writeln!(f, "${:04x} {}", address, name)?;
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,217 @@
+use std::cell::RefCell;
+use std::io::Write;
+
+use crate::cpu::{Cpu, CpuVariant};
+use crate::opcode;
+use crate::symbols::SymbolTable;
+use crate::Bus;
+
+/// An executed instruction along with the state of the CPU before it ran.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionTrace {
+    /// The cycle in which the opcode was fetched.
+    pub cycle: u64,
+    /// The CPU model, which decides how the opcode is decoded.
+    pub variant: CpuVariant,
+    pub pc: u16,
+    pub opcode: u8,
+    pub operands: Vec<u8>,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub status: u8,
+}
+
+impl InstructionTrace {
+    /// Returns the instruction in assembly syntax, like `LDA ($10),Y`.
+    pub fn disassemble(&self) -> String {
+        return opcode::disassemble(self.variant, self.pc, self.opcode, &self.operands, None);
+    }
+
+    /// Returns the instruction in assembly syntax with the addresses named in
+    /// `symbols` shown by name, like `JSR print_char`.
+    pub fn disassemble_with(&self, symbols: &SymbolTable) -> String {
+        return opcode::disassemble(
+            self.variant,
+            self.pc,
+            self.opcode,
+            &self.operands,
+            Some(symbols),
+        );
+    }
+}
+
+/// Formats the trace as a line of a standard emulator log.
+/// ```text
+/// C000  A9 01     LDA #$01        A:00 X:00 Y:00 P:24 SP:FD CYC:7
+/// ```
+impl std::fmt::Display for InstructionTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bytes = std::iter::once(self.opcode)
+            .chain(self.operands.iter().copied())
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        return write!(
+            f,
+            "{:04X}  {:<8}  {:<14}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            self.pc,
+            bytes,
+            self.disassemble(),
+            self.a,
+            self.x,
+            self.y,
+            self.status,
+            self.sp,
+            self.cycle
+        );
+    }
+}
+
+/// Receives what the CPU does while it runs.
+///
+/// Any `FnMut(&InstructionTrace)` closure is a trace which ignores bus activity.
+pub trait Trace {
+    /// Called after each instruction completes.
+    fn instruction(&mut self, trace: &InstructionTrace);
+
+    /// Called for every bus access the CPU makes, in the cycle it is made.
+    fn bus_access(&mut self, _cycle: u64, _address: u16, _data: u8, _write: bool) {}
+}
+
+impl<F> Trace for F
+where
+    F: FnMut(&InstructionTrace),
+{
+    fn instruction(&mut self, trace: &InstructionTrace) {
+        self(trace);
+    }
+}
+
+/// A trace which writes one log line per instruction.
+pub struct TraceLogger<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TraceLogger<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        return self.writer;
+    }
+}
+
+impl<W: Write> Trace for TraceLogger<W> {
+    fn instruction(&mut self, trace: &InstructionTrace) {
+        // a broken log should not stop the emulator
+        let _ = writeln!(self.writer, "{}", trace);
+    }
+}
+
+//
+
+/// The trace registered on a [`Cpu`] and the instruction being recorded.
+pub(crate) struct Tracer {
+    trace: Box<dyn Trace + Send>,
+    /// The registers at the last instruction boundary.
+    boundary: Option<InstructionTrace>,
+    current: Option<InstructionTrace>,
+}
+
+impl Tracer {
+    pub fn new(trace: Box<dyn Trace + Send>) -> Self {
+        Self {
+            trace,
+            boundary: None,
+            current: None,
+        }
+    }
+
+    /// Records the state of the CPU before it starts its next instruction.
+    pub fn begin(&mut self, cpu: &Cpu) {
+        self.boundary = Some(InstructionTrace {
+            cycle: cpu.cycles(),
+            variant: cpu.variant(),
+            pc: cpu.registers.pc.get(),
+            opcode: 0,
+            operands: vec![],
+            a: cpu.registers.acc.get(),
+            x: cpu.registers.x.get(),
+            y: cpu.registers.y.get(),
+            sp: cpu.registers.sp.get(),
+            status: cpu.status.get_raw(),
+        });
+    }
+
+    /// Reports the instruction once it has completed.
+    pub fn finish(&mut self) {
+        if let Some(current) = self.current.take() {
+            self.trace.instruction(&current);
+        }
+    }
+
+    fn fetch(&mut self, address: u16, opcode: u8) {
+        if let Some(mut trace) = self.boundary.take() {
+            trace.pc = address;
+            trace.opcode = opcode;
+            self.current = Some(trace);
+        }
+    }
+
+    fn read(&mut self, address: u16, data: u8) {
+        if let Some(current) = self.current.as_mut() {
+            let len = opcode::opcode_table(current.variant)[current.opcode as usize]
+                .bytes
+                .max(1) as usize
+                - 1;
+            let next = current.pc.wrapping_add(current.operands.len() as u16 + 1);
+            if current.operands.len() < len && address == next {
+                current.operands.push(data);
+            }
+        }
+    }
+}
+
+/// A bus which reports every access to a [`Tracer`].
+pub(crate) struct TracedBus<'a> {
+    bus: &'a mut dyn Bus,
+    tracer: RefCell<&'a mut Tracer>,
+    cycle: u64,
+}
+
+impl<'a> TracedBus<'a> {
+    pub fn new(bus: &'a mut dyn Bus, tracer: &'a mut Tracer, cycle: u64) -> Self {
+        Self {
+            bus,
+            tracer: RefCell::new(tracer),
+            cycle,
+        }
+    }
+}
+
+impl Bus for TracedBus<'_> {
+    fn read(&self, address: u16) -> u8 {
+        let data = self.bus.read(address);
+        let mut tracer = self.tracer.borrow_mut();
+        tracer.read(address, data);
+        tracer.trace.bus_access(self.cycle, address, data, false);
+        return data;
+    }
+
+    fn fetch(&self, address: u16) -> u8 {
+        let data = self.bus.fetch(address);
+        let mut tracer = self.tracer.borrow_mut();
+        tracer.fetch(address, data);
+        tracer.trace.bus_access(self.cycle, address, data, false);
+        return data;
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.bus.write(address, data);
+        let tracer = self.tracer.get_mut();
+        tracer.trace.bus_access(self.cycle, address, data, true);
+    }
+}
@@ -25,6 +25,12 @@ macro_rules! bitset {
         $(#[$attribute])*
         $vis struct $name($type);
 
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
         #[allow(dead_code)]
         impl $name {
             $vis fn new() -> Self {
@@ -0,0 +1,708 @@
+//! Edge cases for every addressing mode: operands at page boundaries, index
+//! registers at $FF, zero page pointers at $FF, code at the end of memory and
+//! the stack pointer at both ends of page one.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, setup_at, step, Access};
+use cpu::CpuVariant;
+
+//
+// Zero Page Indexed
+//
+
+#[test]
+fn zero_page_x_wraps_within_zero_page() {
+    // eor $80,x
+    let (mut cpu, mut bus) = setup(&[0x55, 0x80]);
+    bus.ram[0x007F] = 0x80;
+    bus.ram[0x017F] = 0x01;
+    cpu.registers.x.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(cpu.registers.acc.get(), 0x80);
+    assert!(cpu.status.get_negative());
+    assert!(!cpu.status.get_zero());
+    assert!(bus.touched(0x007F));
+    assert!(!bus.touched(0x017F));
+}
+
+#[test]
+fn zero_page_y_wraps_within_zero_page() {
+    // ldx $ff,y
+    let (mut cpu, mut bus) = setup(&[0xB6, 0xFF]);
+    bus.ram[0x0001] = 0x42;
+    cpu.registers.y.set(0x02);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(cpu.registers.x.get(), 0x42);
+    assert!(!bus.touched(0x0101));
+}
+
+#[test]
+fn store_zero_page_x_wraps_within_zero_page() {
+    // sta $80,x
+    let (mut cpu, mut bus) = setup(&[0x95, 0x80]);
+    cpu.registers.acc.set(0x5A);
+    cpu.registers.x.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(bus.writes(), vec![0x007F]);
+    assert_eq!(bus.ram[0x007F], 0x5A);
+}
+
+#[test]
+fn store_zero_page_y_wraps_within_zero_page() {
+    // stx $ff,y
+    let (mut cpu, mut bus) = setup(&[0x96, 0xFF]);
+    cpu.registers.x.set(0x33);
+    cpu.registers.y.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(bus.writes(), vec![0x00FE]);
+    assert_eq!(bus.ram[0x00FE], 0x33);
+}
+
+//
+// Absolute Indexed
+//
+
+#[test]
+fn absolute_x_without_page_cross() {
+    // eor $1200,x
+    let (mut cpu, mut bus) = setup(&[0x5D, 0x00, 0x12]);
+    bus.ram[0x12FF] = 0x00;
+    cpu.registers.x.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(cpu.registers.acc.get(), 0x00);
+    assert!(cpu.status.get_zero());
+    assert!(bus.touched(0x12FF));
+}
+
+#[test]
+fn absolute_x_page_cross_takes_extra_cycle() {
+    // eor $12ff,x
+    let (mut cpu, mut bus) = setup(&[0x5D, 0xFF, 0x12]);
+    bus.ram[0x1300] = 0x7F;
+    cpu.registers.x.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(cpu.registers.acc.get(), 0x7F);
+    assert!(!cpu.status.get_negative());
+    assert!(bus.touched(0x1300));
+    assert_eq!(cpu.registers.pc.get(), 0x0203);
+}
+
+#[test]
+fn absolute_y_wraps_at_end_of_memory() {
+    // eor $ffff,y
+    let (mut cpu, mut bus) = setup(&[0x59, 0xFF, 0xFF]);
+    bus.ram[0x0000] = 0x81;
+    cpu.registers.y.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(cpu.registers.acc.get(), 0x81);
+    assert!(cpu.status.get_negative());
+    assert!(bus.touched(0x0000));
+}
+
+#[test]
+fn store_absolute_x_page_cross() {
+    // sta $12ff,x
+    let (mut cpu, mut bus) = setup(&[0x9D, 0xFF, 0x12]);
+    cpu.registers.acc.set(0x99);
+    cpu.registers.x.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(bus.writes(), vec![0x1300]);
+    assert_eq!(bus.ram[0x1300], 0x99);
+}
+
+#[test]
+fn store_absolute_y_wraps_at_end_of_memory() {
+    // sta $ffff,y
+    let (mut cpu, mut bus) = setup(&[0x99, 0xFF, 0xFF]);
+    cpu.registers.acc.set(0x24);
+    cpu.registers.y.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(bus.writes(), vec![0x00FE]);
+    assert_eq!(bus.ram[0x00FE], 0x24);
+}
+
+//
+// Indexed Indirect
+//
+
+#[test]
+fn indirect_x_pointer_wraps_within_zero_page() {
+    // eor ($00,x)
+    let (mut cpu, mut bus) = setup(&[0x41, 0x00]);
+    bus.ram[0x00FF] = 0x34;
+    bus.ram[0x0000] = 0x12;
+    bus.ram[0x0100] = 0x56;
+    bus.ram[0x1234] = 0x01;
+    cpu.registers.x.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.acc.get(), 0x01);
+    assert!(bus.touched(0x1234));
+    assert!(!bus.touched(0x0100));
+}
+
+#[test]
+fn indirect_x_index_wraps_within_zero_page() {
+    // eor ($80,x)
+    let (mut cpu, mut bus) = setup(&[0x41, 0x80]);
+    bus.ram[0x007F] = 0x00;
+    bus.ram[0x0080] = 0x30;
+    bus.ram[0x3000] = 0xF0;
+    cpu.registers.x.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.acc.get(), 0xF0);
+    assert!(!bus.touched(0x017F));
+}
+
+#[test]
+fn store_indirect_x_pointer_wraps_within_zero_page() {
+    // sta ($ff,x)
+    let (mut cpu, mut bus) = setup(&[0x81, 0xFF]);
+    bus.ram[0x00FF] = 0x00;
+    bus.ram[0x0000] = 0x40;
+    cpu.registers.acc.set(0x66);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(bus.writes(), vec![0x4000]);
+    assert_eq!(bus.ram[0x4000], 0x66);
+}
+
+//
+// Indirect Indexed
+//
+
+#[test]
+fn indirect_y_pointer_wraps_within_zero_page() {
+    // eor ($ff),y
+    let (mut cpu, mut bus) = setup(&[0x51, 0xFF]);
+    bus.ram[0x00FF] = 0x00;
+    bus.ram[0x0000] = 0x30;
+    bus.ram[0x0100] = 0x40;
+    bus.ram[0x3010] = 0x08;
+    cpu.registers.y.set(0x10);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(cpu.registers.acc.get(), 0x08);
+    assert!(!bus.touched(0x0100));
+}
+
+#[test]
+fn indirect_y_page_cross_takes_extra_cycle() {
+    // eor ($10),y
+    let (mut cpu, mut bus) = setup(&[0x51, 0x10]);
+    bus.ram[0x0010] = 0xFF;
+    bus.ram[0x0011] = 0x12;
+    bus.ram[0x13FE] = 0x80;
+    cpu.registers.y.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.acc.get(), 0x80);
+    assert!(cpu.status.get_negative());
+    assert!(bus.touched(0x13FE));
+}
+
+#[test]
+fn indirect_y_wraps_at_end_of_memory() {
+    // eor ($10),y
+    let (mut cpu, mut bus) = setup(&[0x51, 0x10]);
+    bus.ram[0x0010] = 0xFF;
+    bus.ram[0x0011] = 0xFF;
+    bus.ram[0x0000] = 0x11;
+    cpu.registers.y.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.acc.get(), 0x11);
+}
+
+#[test]
+fn store_indirect_y_pointer_wraps_within_zero_page() {
+    // sta ($ff),y
+    let (mut cpu, mut bus) = setup(&[0x91, 0xFF]);
+    bus.ram[0x00FF] = 0xFF;
+    bus.ram[0x0000] = 0x20;
+    cpu.registers.acc.set(0x77);
+    cpu.registers.y.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(bus.writes(), vec![0x21FE]);
+    assert_eq!(bus.ram[0x21FE], 0x77);
+}
+
+//
+// Read-Modify-Write
+//
+
+#[test]
+fn rmw_zero_page_x_wraps_within_zero_page() {
+    // inc $ff,x
+    let (mut cpu, mut bus) = setup(&[0xF6, 0xFF]);
+    bus.ram[0x0000] = 0xFF;
+    cpu.registers.x.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(bus.ram[0x0000], 0x00);
+    assert!(cpu.status.get_zero());
+    assert_eq!(bus.writes(), vec![0x0000, 0x0000]);
+    assert!(!bus.touched(0x0100));
+}
+
+#[test]
+fn rmw_absolute_x_wraps_at_end_of_memory() {
+    // asl $ffff,x
+    let (mut cpu, mut bus) = setup(&[0x1E, 0xFF, 0xFF]);
+    bus.ram[0x0000] = 0x81;
+    cpu.registers.x.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(bus.ram[0x0000], 0x02);
+    assert!(cpu.status.get_carry());
+    assert_eq!(bus.writes(), vec![0x0000, 0x0000]);
+}
+
+#[test]
+fn rmw_absolute_x_instructions() {
+    // (opcode, carry in, before, after, carry out)
+    let cases = [
+        (0x1E, false, 0x81, 0x02, true),  // asl
+        (0x5E, false, 0x81, 0x40, true),  // lsr
+        (0x3E, true, 0x80, 0x01, true),   // rol
+        (0x7E, true, 0x01, 0x80, true),   // ror
+        (0xFE, false, 0x7F, 0x80, false), // inc
+        (0xDE, true, 0x00, 0xFF, true),   // dec
+    ];
+    for (opcode, carry, before, after, carry_out) in cases {
+        // without and with a page cross
+        for (x, address) in [(0x01, 0x12F1), (0x20, 0x1310)] {
+            let (mut cpu, mut bus) = setup(&[opcode, 0xF0, 0x12]);
+            bus.ram[address] = before;
+            cpu.registers.x.set(x);
+            cpu.status = cpu.status.with_carry(carry);
+
+            assert_eq!(step(&mut cpu, &mut bus), 7, "${:02x},x={}", opcode, x);
+            assert_eq!(bus.ram[address], after, "${:02x},x={}", opcode, x);
+            assert_eq!(cpu.status.get_carry(), carry_out, "${:02x}", opcode);
+            let writes: Vec<_> = bus
+                .take_log()
+                .into_iter()
+                .filter(|access| matches!(access, Access::Write(..)))
+                .collect();
+            assert_eq!(
+                writes,
+                vec![
+                    Access::Write(address as u16, before),
+                    Access::Write(address as u16, after)
+                ]
+            );
+        }
+    }
+}
+
+#[test]
+fn rmw_absolute_x_dummy_reads_unfixed_address() {
+    // inc $12f0,x
+    let (mut cpu, mut bus) = setup(&[0xFE, 0xF0, 0x12]);
+    bus.ram[0x1310] = 0x41;
+    cpu.registers.x.set(0x20);
+    cpu.set_accurate_bus(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0200, 0xFE),
+            Access::Read(0x0201, 0xF0),
+            Access::Read(0x0202, 0x12),
+            Access::Read(0x1210, 0x00),
+            Access::Read(0x1310, 0x41),
+            Access::Write(0x1310, 0x41),
+            Access::Write(0x1310, 0x42),
+        ]
+    );
+}
+
+//
+// Program Counter
+//
+
+#[test]
+fn single_byte_instruction_at_end_of_memory() {
+    // nop
+    let (mut cpu, mut bus) = setup_at(0xFFFF, &[0xEA]);
+
+    assert_eq!(step(&mut cpu, &mut bus), 2);
+    assert_eq!(cpu.registers.pc.get(), 0x0000);
+}
+
+#[test]
+fn operand_fetch_wraps_at_end_of_memory() {
+    // lda #$42
+    let (mut cpu, mut bus) = setup_at(0xFFFF, &[0xA9, 0x42]);
+
+    assert_eq!(step(&mut cpu, &mut bus), 2);
+    assert_eq!(cpu.registers.acc.get(), 0x42);
+    assert_eq!(cpu.registers.pc.get(), 0x0001);
+    assert!(bus.touched(0x0000));
+}
+
+#[test]
+fn jsr_and_rts_at_end_of_memory() {
+    // jsr $0300 with its last byte at $0000 ... rts
+    let (mut cpu, mut bus) = setup_at(0xFFFE, &[0x20, 0x00, 0x03]);
+    bus.ram[0x0300] = 0x60;
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x0300);
+    assert_eq!(bus.ram[0x01FD], 0x00);
+    assert_eq!(bus.ram[0x01FC], 0x00);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x0001);
+}
+
+//
+// Stack
+//
+
+#[test]
+fn push_wraps_stack_pointer_at_bottom() {
+    // pha
+    let (mut cpu, mut bus) = setup(&[0x48]);
+    cpu.registers.acc.set(0xAB);
+    cpu.registers.sp.set(0x00);
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(bus.take_log().last(), Some(&Access::Write(0x0100, 0xAB)));
+    assert_eq!(cpu.registers.sp.get(), 0xFF);
+}
+
+#[test]
+fn pull_wraps_stack_pointer_at_top() {
+    // pla
+    let (mut cpu, mut bus) = setup(&[0x68]);
+    bus.ram[0x0100] = 0xCD;
+    cpu.registers.sp.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(cpu.registers.acc.get(), 0xCD);
+    assert_eq!(cpu.registers.sp.get(), 0x00);
+}
+
+#[test]
+fn brk_pushes_across_stack_wrap() {
+    // brk
+    let (mut cpu, mut bus) = setup(&[0x00, 0x00]);
+    bus.load(0xFFFE, &[0x00, 0x03]);
+    cpu.registers.sp.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(cpu.registers.pc.get(), 0x0300);
+    assert_eq!(cpu.registers.sp.get(), 0xFE);
+    assert_eq!(bus.writes(), vec![0x0101, 0x0100, 0x01FF]);
+    assert_eq!(bus.ram[0x0101], 0x02);
+    assert_eq!(bus.ram[0x0100], 0x02);
+}
+
+#[test]
+fn jsr_and_rts_across_stack_wrap() {
+    // jsr $0300 ... rts
+    let (mut cpu, mut bus) = setup(&[0x20, 0x00, 0x03]);
+    bus.ram[0x0300] = 0x60;
+    cpu.registers.sp.set(0x00);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x0300);
+    assert_eq!(cpu.registers.sp.get(), 0xFE);
+    assert_eq!(bus.writes(), vec![0x0100, 0x01FF]);
+    assert_eq!(bus.ram[0x0100], 0x02);
+    assert_eq!(bus.ram[0x01FF], 0x02);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x0203);
+    assert_eq!(cpu.registers.sp.get(), 0x00);
+}
+
+#[test]
+fn rts_return_address_crosses_page() {
+    // jsr $0300 placed so that the pushed return address is $02ff
+    let (mut cpu, mut bus) = setup_at(0x02FD, &[0x20, 0x00, 0x03]);
+    bus.ram[0x0300] = 0x60;
+    cpu.registers.sp.set(0xFF);
+
+    step(&mut cpu, &mut bus);
+    assert_eq!(bus.ram[0x01FF], 0x02);
+    assert_eq!(bus.ram[0x01FE], 0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x0300);
+}
+
+//
+// Relative
+//
+
+#[test]
+fn branch_not_taken() {
+    // beq +$10
+    let (mut cpu, mut bus) = setup(&[0xF0, 0x10]);
+    cpu.status = cpu.status.with_zero(false);
+
+    assert_eq!(step(&mut cpu, &mut bus), 2);
+    assert_eq!(cpu.registers.pc.get(), 0x0202);
+}
+
+#[test]
+fn branch_taken_within_page() {
+    // beq +$10
+    let (mut cpu, mut bus) = setup(&[0xF0, 0x10]);
+    cpu.status = cpu.status.with_zero(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(cpu.registers.pc.get(), 0x0212);
+}
+
+#[test]
+fn branch_forward_across_page() {
+    // beq +$10
+    let (mut cpu, mut bus) = setup_at(0x02F0, &[0xF0, 0x10]);
+    cpu.status = cpu.status.with_zero(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(cpu.registers.pc.get(), 0x0302);
+}
+
+#[test]
+fn branch_backward_across_page() {
+    // bne -$10
+    let (mut cpu, mut bus) = setup_at(0x0300, &[0xD0, 0xF0]);
+    cpu.status = cpu.status.with_zero(false);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(cpu.registers.pc.get(), 0x02F2);
+}
+
+#[test]
+fn branch_backward_to_self() {
+    // bne *
+    let (mut cpu, mut bus) = setup(&[0xD0, 0xFE]);
+    cpu.status = cpu.status.with_zero(false);
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(cpu.registers.pc.get(), 0x0200);
+}
+
+#[test]
+fn branch_taken_dummy_reads_next_opcode() {
+    // beq +$10
+    let (mut cpu, mut bus) = setup(&[0xF0, 0x10]);
+    cpu.set_accurate_bus(true);
+    cpu.status = cpu.status.with_zero(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0200, 0xF0),
+            Access::Read(0x0201, 0x10),
+            Access::Read(0x0202, 0x00),
+        ]
+    );
+}
+
+#[test]
+fn branch_across_page_dummy_reads_wrong_page() {
+    // bne -$10
+    let (mut cpu, mut bus) = setup_at(0x0300, &[0xD0, 0xF0]);
+    cpu.set_accurate_bus(true);
+    cpu.status = cpu.status.with_zero(false);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0300, 0xD0),
+            Access::Read(0x0301, 0xF0),
+            Access::Read(0x0302, 0x00),
+            Access::Read(0x03F2, 0x00),
+        ]
+    );
+    assert_eq!(cpu.registers.pc.get(), 0x02F2);
+}
+
+#[test]
+fn branch_dummy_reads_are_off_by_default() {
+    // beq +$10
+    let (mut cpu, mut bus) = setup(&[0xF0, 0x10]);
+    cpu.status = cpu.status.with_zero(true);
+
+    step(&mut cpu, &mut bus);
+    assert_eq!(bus.take_log().len(), 2);
+}
+
+//
+// Absolute Indirect
+//
+
+#[test]
+fn jump_indirect() {
+    // jmp ($1234)
+    let (mut cpu, mut bus) = setup(&[0x6C, 0x34, 0x12]);
+    bus.load(0x1234, &[0x00, 0x80]);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(cpu.registers.pc.get(), 0x8000);
+}
+
+#[test]
+fn jump_indirect_wraps_within_page_on_nmos() {
+    // jmp ($12ff)
+    let (mut cpu, mut bus) = setup(&[0x6C, 0xFF, 0x12]);
+    bus.ram[0x12FF] = 0x00;
+    bus.ram[0x1200] = 0x80;
+    bus.ram[0x1300] = 0x90;
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(cpu.registers.pc.get(), 0x8000);
+}
+
+#[test]
+fn jump_indirect_pointer_at_end_of_memory() {
+    // jmp ($ffff)
+    for (variant, target) in [
+        (CpuVariant::Nmos6502, 0x8000),
+        (CpuVariant::Cmos65C02, 0x9000),
+    ] {
+        let (mut cpu, mut bus) = setup(&[0x6C, 0xFF, 0xFF]);
+        bus.ram[0xFFFF] = 0x00;
+        bus.ram[0xFF00] = 0x80;
+        bus.ram[0x0000] = 0x90;
+        cpu.set_variant(variant);
+
+        step(&mut cpu, &mut bus);
+        assert_eq!(cpu.registers.pc.get(), target, "{:?}", variant);
+    }
+}
+
+#[test]
+fn jump_indirect_crosses_page_on_cmos() {
+    // jmp ($12ff)
+    let (mut cpu, mut bus) = setup(&[0x6C, 0xFF, 0x12]);
+    bus.ram[0x12FF] = 0x00;
+    bus.ram[0x1200] = 0x80;
+    bus.ram[0x1300] = 0x90;
+    cpu.set_variant(CpuVariant::Cmos65C02);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x9000);
+}
+
+//
+// Dummy Reads
+//
+
+#[test]
+fn implied_dummy_reads_next_byte() {
+    // tax
+    let (mut cpu, mut bus) = setup(&[0xAA, 0x55]);
+    cpu.set_accurate_bus(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 2);
+    assert_eq!(
+        bus.take_log(),
+        vec![Access::Read(0x0200, 0xAA), Access::Read(0x0201, 0x55)]
+    );
+    assert_eq!(cpu.registers.pc.get(), 0x0201);
+}
+
+#[test]
+fn zero_page_x_dummy_reads_base_address() {
+    // lda $80,x
+    let (mut cpu, mut bus) = setup(&[0xB5, 0x80]);
+    bus.ram[0x0080] = 0x11;
+    bus.ram[0x0081] = 0x22;
+    cpu.registers.x.set(0x01);
+    cpu.set_accurate_bus(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0200, 0xB5),
+            Access::Read(0x0201, 0x80),
+            Access::Read(0x0080, 0x11),
+            Access::Read(0x0081, 0x22),
+        ]
+    );
+}
+
+#[test]
+fn absolute_x_page_cross_dummy_reads_unfixed_address() {
+    // lda $1010,x
+    let (mut cpu, mut bus) = setup(&[0xBD, 0x10, 0x10]);
+    bus.ram[0x1008] = 0x11;
+    bus.ram[0x1108] = 0x22;
+    cpu.registers.x.set(0xF8);
+    cpu.set_accurate_bus(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0200, 0xBD),
+            Access::Read(0x0201, 0x10),
+            Access::Read(0x0202, 0x10),
+            Access::Read(0x1008, 0x11),
+            Access::Read(0x1108, 0x22),
+        ]
+    );
+}
+
+#[test]
+fn stack_instructions_dummy_read_next_byte_and_stack() {
+    // pha; pla
+    let (mut cpu, mut bus) = setup(&[0x48, 0x68, 0xEA]);
+    cpu.registers.acc.set(0xAB);
+    cpu.registers.sp.set(0xFD);
+    cpu.set_accurate_bus(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0200, 0x48),
+            Access::Read(0x0201, 0x68),
+            Access::Write(0x01FD, 0xAB),
+        ]
+    );
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0201, 0x68),
+            Access::Read(0x0202, 0xEA),
+            Access::Read(0x01FC, 0x00),
+            Access::Read(0x01FD, 0xAB),
+        ]
+    );
+}
+
+#[test]
+fn internal_cycles_do_not_touch_the_bus_by_default() {
+    // tax
+    let (mut cpu, mut bus) = setup(&[0xAA]);
+
+    step(&mut cpu, &mut bus);
+    assert_eq!(bus.take_log().len(), 1);
+}
@@ -0,0 +1,46 @@
+//! Building a CPU in a known state.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::TestBus;
+use cpu::{Cpu, CpuVariant};
+
+#[test]
+fn builder_sets_the_initial_state() {
+    let cpu = Cpu::builder()
+        .a(0x01)
+        .x(0x02)
+        .y(0x03)
+        .sp(0xF0)
+        .pc(0x1234)
+        .status(0xC3)
+        .variant(CpuVariant::Cmos65C02)
+        .build();
+
+    assert_eq!(cpu.registers.acc.get(), 0x01);
+    assert_eq!(cpu.registers.x.get(), 0x02);
+    assert_eq!(cpu.registers.y.get(), 0x03);
+    assert_eq!(cpu.registers.sp.get(), 0xF0);
+    assert_eq!(cpu.registers.pc.get(), 0x1234);
+    assert_eq!(cpu.status.get_raw(), 0xC3);
+    assert_eq!(cpu.variant(), CpuVariant::Cmos65C02);
+    assert_eq!(cpu.cycles(), 0);
+}
+
+#[test]
+fn built_cpu_runs_from_its_pc() {
+    // inx
+    let mut bus = TestBus::new();
+    bus.load(0x4000, &[0xE8]);
+    let mut cpu = Cpu::builder().pc(0x4000).x(0x41).build();
+    assert!(cpu.at_instruction_boundary());
+    assert!(cpu.status.get_irq_disable());
+
+    cpu.step_cycle(&mut bus);
+    assert!(!cpu.at_instruction_boundary());
+    cpu.step_instruction(&mut bus);
+    assert!(cpu.at_instruction_boundary());
+    assert_eq!(cpu.registers.x.get(), 0x42);
+    assert_eq!(cpu.cycles(), 2);
+}
@@ -0,0 +1,249 @@
+//! The instructions and addressing modes added by the 65C02.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, step, Access, TestBus, ORIGIN};
+use cpu::{Cpu, CpuVariant};
+
+/// Returns a powered-on 65C02 with `program` loaded at [`ORIGIN`].
+fn setup_cmos(program: &[u8]) -> (Cpu, TestBus) {
+    let (mut cpu, bus) = setup(program);
+    cpu.set_variant(CpuVariant::Cmos65C02);
+    return (cpu, bus);
+}
+
+//
+// Stack
+//
+
+#[test]
+fn phx_and_ply_move_index_registers_through_the_stack() {
+    // phx; ply
+    let (mut cpu, mut bus) = setup_cmos(&[0xDA, 0x7A]);
+    cpu.registers.x.set(0x80);
+    let sp = cpu.registers.sp.get();
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(bus.ram[0x0100 + sp as usize], 0x80);
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(cpu.registers.y.get(), 0x80);
+    assert!(cpu.status.get_negative());
+    assert!(!cpu.status.get_zero());
+}
+
+//
+// Stores and Bit Operations
+//
+
+#[test]
+fn stz_clears_memory() {
+    // stz $10; stz $1234,x
+    let (mut cpu, mut bus) = setup_cmos(&[0x64, 0x10, 0x9E, 0x34, 0x12]);
+    bus.ram[0x0010] = 0xFF;
+    bus.ram[0x1236] = 0xFF;
+    cpu.registers.x.set(0x02);
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(bus.ram[0x0010], 0x00);
+    assert_eq!(bus.ram[0x1236], 0x00);
+}
+
+#[test]
+fn tsb_and_trb_set_and_reset_bits() {
+    // tsb $10; trb $20
+    let (mut cpu, mut bus) = setup_cmos(&[0x04, 0x10, 0x14, 0x20]);
+    bus.ram[0x0010] = 0xF0;
+    bus.ram[0x0020] = 0xFF;
+    cpu.registers.acc.set(0x0F);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(bus.ram[0x0010], 0xFF);
+    assert!(cpu.status.get_zero());
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(bus.ram[0x0020], 0xF0);
+    assert!(!cpu.status.get_zero());
+}
+
+#[test]
+fn bit_immediate_only_sets_zero() {
+    // bit #$c0
+    let (mut cpu, mut bus) = setup_cmos(&[0x89, 0xC0]);
+    cpu.registers.acc.set(0x3F);
+
+    assert_eq!(step(&mut cpu, &mut bus), 2);
+    assert!(cpu.status.get_zero());
+    assert!(!cpu.status.get_negative());
+    assert!(!cpu.status.get_overflow());
+}
+
+#[test]
+fn inc_and_dec_accumulator() {
+    // inc a; dec a; dec a
+    let (mut cpu, mut bus) = setup_cmos(&[0x1A, 0x3A, 0x3A]);
+
+    assert_eq!(step(&mut cpu, &mut bus), 2);
+    assert_eq!(cpu.registers.acc.get(), 0x01);
+    step(&mut cpu, &mut bus);
+    assert!(cpu.status.get_zero());
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.acc.get(), 0xFF);
+    assert!(cpu.status.get_negative());
+}
+
+//
+// Read-Modify-Write
+//
+
+#[test]
+fn rmw_rereads_instead_of_writing_twice() {
+    // inc $1234
+    let program = [0xEE, 0x34, 0x12];
+    let expected = |spare| {
+        vec![
+            Access::Read(0x0200, 0xEE),
+            Access::Read(0x0201, 0x34),
+            Access::Read(0x0202, 0x12),
+            Access::Read(0x1234, 0x41),
+            spare,
+            Access::Write(0x1234, 0x42),
+        ]
+    };
+
+    let (mut cpu, mut bus) = setup(&program);
+    cpu.set_accurate_bus(true);
+    bus.ram[0x1234] = 0x41;
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(bus.take_log(), expected(Access::Write(0x1234, 0x41)));
+
+    let (mut cpu, mut bus) = setup_cmos(&program);
+    cpu.set_accurate_bus(true);
+    bus.ram[0x1234] = 0x41;
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(bus.take_log(), expected(Access::Read(0x1234, 0x41)));
+}
+
+#[test]
+fn shifts_absolute_x_only_take_the_fixup_cycle_across_a_page() {
+    // asl, rol, lsr and ror $12f0,x
+    for opcode in [0x1E, 0x3E, 0x5E, 0x7E] {
+        for (x, cycles) in [(0x01, 6), (0x20, 7)] {
+            let (mut cpu, mut bus) = setup_cmos(&[opcode, 0xF0, 0x12]);
+            cpu.registers.x.set(x);
+            assert_eq!(step(&mut cpu, &mut bus), cycles, "${:02x},x{}", opcode, x);
+            assert_eq!(cpu.registers.pc.get(), 0x0203);
+        }
+
+        // the nmos part always takes it
+        let (mut cpu, mut bus) = setup(&[opcode, 0xF0, 0x12]);
+        cpu.registers.x.set(0x01);
+        assert_eq!(step(&mut cpu, &mut bus), 7, "${:02x}", opcode);
+    }
+}
+
+#[test]
+fn inc_and_dec_absolute_x_always_take_the_fixup_cycle() {
+    // inc, dec $12f0,x
+    for opcode in [0xFE, 0xDE] {
+        let (mut cpu, mut bus) = setup_cmos(&[opcode, 0xF0, 0x12]);
+        cpu.registers.x.set(0x01);
+        assert_eq!(step(&mut cpu, &mut bus), 7, "${:02x}", opcode);
+    }
+}
+
+#[test]
+fn shift_absolute_x_across_a_page_rereads_the_operand() {
+    // asl $12f0,x
+    let (mut cpu, mut bus) = setup_cmos(&[0x1E, 0xF0, 0x12]);
+    bus.ram[0x1310] = 0x41;
+    cpu.registers.x.set(0x20);
+    cpu.set_accurate_bus(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(
+        bus.take_log(),
+        vec![
+            Access::Read(0x0200, 0x1E),
+            Access::Read(0x0201, 0xF0),
+            Access::Read(0x0202, 0x12),
+            Access::Read(0x0202, 0x12),
+            Access::Read(0x1310, 0x41),
+            Access::Read(0x1310, 0x41),
+            Access::Write(0x1310, 0x82),
+        ]
+    );
+}
+
+//
+// Addressing Modes
+//
+
+#[test]
+fn zero_page_indirect_loads_and_stores() {
+    // lda ($10); sta ($12)
+    let (mut cpu, mut bus) = setup_cmos(&[0xB2, 0x10, 0x92, 0x12]);
+    bus.load(0x0010, &[0x00, 0x30, 0x00, 0x40]);
+    bus.ram[0x3000] = 0x5A;
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(cpu.registers.acc.get(), 0x5A);
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(bus.ram[0x4000], 0x5A);
+}
+
+//
+// Jumps and Branches
+//
+
+#[test]
+fn bra_always_branches() {
+    // bra +4
+    let (mut cpu, mut bus) = setup_cmos(&[0x80, 0x04]);
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(cpu.registers.pc.get(), ORIGIN + 6);
+}
+
+#[test]
+fn jmp_absolute_indirect_x() {
+    // jmp ($1000,x)
+    let (mut cpu, mut bus) = setup_cmos(&[0x7C, 0x00, 0x10]);
+    bus.load(0x1004, &[0x34, 0x12]);
+    cpu.registers.x.set(0x04);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x1234);
+}
+
+//
+// Undocumented Opcodes
+//
+
+#[test]
+fn undocumented_nmos_opcodes_are_nops() {
+    // slo ($10,x) on the nmos part; lax $10
+    let (mut cpu, mut bus) = setup_cmos(&[0x03, 0xA7, 0x10]);
+    bus.ram[0x0010] = 0x5A;
+
+    assert_eq!(step(&mut cpu, &mut bus), 1);
+    assert_eq!(cpu.registers.pc.get(), ORIGIN + 1);
+    assert_eq!(step(&mut cpu, &mut bus), 1);
+    assert_eq!(cpu.registers.acc.get(), 0x00);
+    assert_eq!(bus.writes(), vec![]);
+}
+
+#[test]
+fn nmos_keeps_its_opcodes() {
+    // stz $10 is a nop $10 on the nmos part
+    let (mut cpu, mut bus) = setup(&[0x64, 0x10]);
+    bus.ram[0x0010] = 0xFF;
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(bus.ram[0x0010], 0xFF);
+    assert_eq!(
+        Cpu::with_variant(CpuVariant::Cmos65C02).variant(),
+        CpuVariant::Cmos65C02
+    );
+}
@@ -0,0 +1,95 @@
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+
+use cpu::{Bus, Cpu};
+
+/// The address test programs are loaded at.
+pub const ORIGIN: u16 = 0x0200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read(u16, u8),
+    Write(u16, u8),
+}
+
+/// A flat 64K RAM bus which records every access.
+pub struct TestBus {
+    pub ram: Box<[u8; 0x10000]>,
+    log: RefCell<Vec<Access>>,
+}
+
+impl TestBus {
+    pub fn new() -> Self {
+        Self {
+            ram: Box::new([0; 0x10000]),
+            log: RefCell::new(vec![]),
+        }
+    }
+
+    /// Copies `bytes` into memory starting at `address`, wrapping at $FFFF.
+    pub fn load(&mut self, address: u16, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.ram[address.wrapping_add(i as u16) as usize] = *byte;
+        }
+    }
+
+    pub fn take_log(&self) -> Vec<Access> {
+        return self.log.take();
+    }
+
+    /// Returns the addresses of all recorded writes.
+    pub fn writes(&self) -> Vec<u16> {
+        return self
+            .log
+            .borrow()
+            .iter()
+            .filter_map(|access| match *access {
+                Access::Write(address, _) => Some(address),
+                _ => None,
+            })
+            .collect();
+    }
+
+    /// Returns whether `address` was read or written since the log was last taken.
+    pub fn touched(&self, address: u16) -> bool {
+        return self.log.borrow().iter().any(|access| match *access {
+            Access::Read(a, _) | Access::Write(a, _) => a == address,
+        });
+    }
+}
+
+impl Bus for TestBus {
+    fn read(&self, address: u16) -> u8 {
+        let data = self.ram[address as usize];
+        self.log.borrow_mut().push(Access::Read(address, data));
+        return data;
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.ram[address as usize] = data;
+        self.log.borrow_mut().push(Access::Write(address, data));
+    }
+}
+
+/// Returns a powered-on CPU with `program` loaded at `origin` and the reset
+/// vector pointing at it. The bus log is cleared.
+pub fn setup_at(origin: u16, program: &[u8]) -> (Cpu, TestBus) {
+    let mut bus = TestBus::new();
+    bus.load(origin, program);
+    bus.load(Cpu::RES_VECTOR, &origin.to_le_bytes());
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut bus);
+    bus.take_log();
+    return (cpu, bus);
+}
+
+pub fn setup(program: &[u8]) -> (Cpu, TestBus) {
+    return setup_at(ORIGIN, program);
+}
+
+/// Executes one instruction and returns the number of cycles it took.
+pub fn step(cpu: &mut Cpu, bus: &mut TestBus) -> u64 {
+    return cpu.step_instruction(bus);
+}
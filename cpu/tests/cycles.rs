@@ -0,0 +1,204 @@
+//! Runs every opcode from random register and memory states and checks that the
+//! microcode takes the cycles, and moves the PC by the bytes, documented in the
+//! opcode table.
+//!
+//! Reads with an indexed address take a cycle more when the index crosses a
+//! page, and branches take one more when taken and another when the target is
+//! on a different page. Stores and read-modify-write instructions always take
+//! the documented cycles, except for the 65C02 shifts and rotates with an
+//! absolute,X address, which skip the fix-up cycle like reads. The 65C02 takes
+//! one more for ADC and SBC in decimal mode.
+//!
+//! `CYCLE_FUZZ_SEED` picks a different sequence of states and
+//! `CYCLE_FUZZ_TRIALS` sets the number of states each opcode is run from.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use std::env;
+
+use common::TestBus;
+use cpu::{opcode_info, AddressMode, Cpu, CpuVariant, OpcodeInfo};
+
+const DEFAULT_SEED: u64 = 0x6502_6502_6502_6502;
+const DEFAULT_TRIALS: usize = 32;
+
+/// The instructions which only read their operand, and so can skip the fix-up
+/// cycle of an indexed address which stays on its page.
+const READS: [&str; 13] = [
+    "ADC", "AND", "BIT", "CMP", "EOR", "LAS", "LAX", "LDA", "LDX", "LDY", "NOP", "ORA", "SBC",
+];
+
+/// The 65C02 read-modify-write instructions which only take the fix-up cycle of
+/// an absolute,X address when the index crosses a page.
+const CMOS_SHIFTS: [&str; 4] = ["ASL", "LSR", "ROL", "ROR"];
+
+/// The instructions which do not continue at the next instruction.
+const JUMPS: [&str; 5] = ["BRK", "JMP", "JSR", "RTI", "RTS"];
+
+/// A xorshift generator, so that failures can be reproduced from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        return self.0;
+    }
+
+    fn byte(&mut self) -> u8 {
+        return self.next() as u8;
+    }
+
+    fn word(&mut self) -> u16 {
+        return self.next() as u16;
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    return match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid value for {}", name)),
+        Err(_) => default,
+    };
+}
+
+fn crosses_page(base: u16, index: u8) -> bool {
+    return base & 0xFF00 != base.wrapping_add(index as u16) & 0xFF00;
+}
+
+/// Returns whether a branch is taken with the flags in `status`.
+fn branch_taken(mnemonic: &str, status: u8) -> bool {
+    let flag = |bit: u8| status & (1 << bit) != 0;
+    return match mnemonic {
+        "BPL" => !flag(7),
+        "BMI" => flag(7),
+        "BVC" => !flag(6),
+        "BVS" => flag(6),
+        "BCC" => !flag(0),
+        "BCS" => flag(0),
+        "BNE" => !flag(1),
+        "BEQ" => flag(1),
+        "BRA" => true,
+        _ => panic!("{} is not a branch", mnemonic),
+    };
+}
+
+/// Returns the cycles an instruction at `pc` should take in the state of `cpu`
+/// and `bus`, going by the opcode table.
+fn expected_cycles(info: &OpcodeInfo, cpu: &Cpu, bus: &TestBus, pc: u16) -> u64 {
+    let mut cycles = info.cycles as u64;
+    let operand = bus.ram[pc.wrapping_add(1) as usize];
+    let word = u16::from_le_bytes([operand, bus.ram[pc.wrapping_add(2) as usize]]);
+    let (x, y) = (cpu.registers.x.get(), cpu.registers.y.get());
+    let read = READS.contains(&info.mnemonic)
+        || (cpu.variant() == CpuVariant::Cmos65C02 && CMOS_SHIFTS.contains(&info.mnemonic));
+
+    match info.mode {
+        AddressMode::AbsoluteX if read && crosses_page(word, x) => cycles += 1,
+        AddressMode::AbsoluteY if read && crosses_page(word, y) => cycles += 1,
+        AddressMode::IndirectY if read => {
+            let pointer = u16::from_le_bytes([
+                bus.ram[operand as usize],
+                bus.ram[operand.wrapping_add(1) as usize],
+            ]);
+            if crosses_page(pointer, y) {
+                cycles += 1;
+            }
+        }
+        AddressMode::Relative if branch_taken(info.mnemonic, cpu.status.get_raw()) => {
+            // the table already counts the branch of BRA as taken
+            if info.mnemonic != "BRA" {
+                cycles += 1;
+            }
+            let next = pc.wrapping_add(2);
+            let target = next.wrapping_add(operand as i8 as u16);
+            if next & 0xFF00 != target & 0xFF00 {
+                cycles += 1;
+            }
+        }
+        _ => {}
+    }
+
+    let decimal = cpu.status.get_decimal_mode();
+    if cpu.variant() == CpuVariant::Cmos65C02 && decimal && ["ADC", "SBC"].contains(&info.mnemonic)
+    {
+        cycles += 1;
+    }
+    return cycles;
+}
+
+/// Runs every opcode of `variant` from random states and returns a description
+/// of each one which did not match the table.
+fn fuzz(variant: CpuVariant, rng: &mut Rng, trials: usize) -> Vec<String> {
+    let mut failures = vec![];
+    for opcode in 0..=255u8 {
+        let Some(info) = opcode_info(variant, opcode) else {
+            continue;
+        };
+
+        let mut bus = TestBus::new();
+        for byte in bus.ram.iter_mut() {
+            *byte = rng.byte();
+        }
+
+        for _ in 0..trials {
+            let pc = rng.word();
+            bus.load(pc, &[opcode, rng.byte(), rng.byte()]);
+            let mut cpu = Cpu::builder()
+                .variant(variant)
+                .a(rng.byte())
+                .x(rng.byte())
+                .y(rng.byte())
+                .sp(rng.byte())
+                .pc(pc)
+                .status(rng.byte())
+                .build();
+
+            let expected = expected_cycles(&info, &cpu, &bus, pc);
+            let cycles = cpu.step_instruction(&mut bus);
+            bus.take_log();
+            if cycles != expected {
+                failures.push(format!(
+                    "{:?} ${:02x} {} {:?} at ${:04x}: took {} cycles, expected {}",
+                    variant, opcode, info.mnemonic, info.mode, pc, cycles, expected
+                ));
+                break;
+            }
+
+            let next = cpu.registers.pc.get();
+            let moves = info.mode != AddressMode::Relative && !JUMPS.contains(&info.mnemonic);
+            if moves && next != pc.wrapping_add(info.bytes as u16) {
+                failures.push(format!(
+                    "{:?} ${:02x} {} {:?} at ${:04x}: moved the pc by {}, expected {}",
+                    variant,
+                    opcode,
+                    info.mnemonic,
+                    info.mode,
+                    pc,
+                    next.wrapping_sub(pc),
+                    info.bytes
+                ));
+                break;
+            }
+        }
+    }
+    return failures;
+}
+
+#[test]
+fn microcode_matches_the_opcode_table() {
+    let mut rng = Rng(env_or("CYCLE_FUZZ_SEED", DEFAULT_SEED).max(1));
+    let trials = env_or("CYCLE_FUZZ_TRIALS", DEFAULT_TRIALS);
+
+    let mut failures = fuzz(CpuVariant::Nmos6502, &mut rng, trials);
+    failures.extend(fuzz(CpuVariant::Cmos65C02, &mut rng, trials));
+    assert!(
+        failures.is_empty(),
+        "{} opcodes do not match the table:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
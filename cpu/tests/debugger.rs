@@ -0,0 +1,355 @@
+//! Breakpoints, watchpoints, breakpoint files and coverage.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, ORIGIN};
+use cpu::{BreakpointError, Debugger, Hit, StepResult, Watch};
+
+#[test]
+fn breakpoint_stops_before_the_instruction() {
+    // lda #$01; lda #$02
+    let (mut cpu, mut bus) = setup(&[0xA9, 0x01, 0xA9, 0x02]);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(ORIGIN + 2);
+
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Completed(2)
+    );
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Hit(Hit::Breakpoint(ORIGIN + 2))
+    );
+    assert_eq!(cpu.registers.acc.get(), 0x01);
+    assert_eq!(cpu.registers.pc.get(), ORIGIN + 2);
+}
+
+#[test]
+fn stepping_resumes_past_a_breakpoint() {
+    // loop: inx; jmp loop
+    let (mut cpu, mut bus) = setup(&[0xE8, 0x4C, 0x00, 0x02]);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(ORIGIN);
+
+    let hit = StepResult::Hit(Hit::Breakpoint(ORIGIN));
+    assert_eq!(debugger.step_instruction(&mut cpu, &mut bus), hit);
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Completed(2)
+    );
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Completed(3)
+    );
+    assert_eq!(debugger.step_instruction(&mut cpu, &mut bus), hit);
+    assert_eq!(cpu.registers.x.get(), 0x01);
+
+    assert!(debugger.remove_breakpoint(ORIGIN));
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Completed(2)
+    );
+}
+
+#[test]
+fn watchpoints_stop_after_the_access() {
+    // lda $10; sta $10; sta $11
+    let (mut cpu, mut bus) = setup(&[0xA5, 0x10, 0x85, 0x10, 0x85, 0x11]);
+    bus.ram[0x0010] = 0x42;
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(0x0010, Watch::Write);
+    debugger.add_watchpoint(0x0011, Watch::Access);
+
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Completed(3)
+    );
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Hit(Hit::Write {
+            address: 0x0010,
+            data: 0x42
+        })
+    );
+    assert_eq!(cpu.registers.pc.get(), ORIGIN + 4);
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Hit(Hit::Write {
+            address: 0x0011,
+            data: 0x42
+        })
+    );
+}
+
+#[test]
+fn read_watchpoints_ignore_opcode_fetches() {
+    // lda $0200
+    let (mut cpu, mut bus) = setup(&[0xAD, 0x00, 0x02]);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(ORIGIN, Watch::Read);
+
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Hit(Hit::Read {
+            address: ORIGIN,
+            data: 0xAD
+        })
+    );
+
+    debugger.remove_watchpoint(ORIGIN, Watch::Read);
+    cpu.registers.pc.set(ORIGIN);
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Completed(4)
+    );
+}
+
+#[test]
+fn coverage_records_every_kind_of_access() {
+    // lda $0300; sta $0301; bne +1; nop; nop
+    let (mut cpu, mut bus) = setup(&[0xAD, 0x00, 0x03, 0x8D, 0x01, 0x03, 0xD0, 0x01, 0xEA, 0xEA]);
+    bus.ram[0x0300] = 0x42;
+    let mut debugger = Debugger::new();
+    debugger.start_coverage();
+    for _ in 0..4 {
+        debugger.step_instruction(&mut cpu, &mut bus);
+    }
+
+    let coverage = debugger.coverage().unwrap();
+    assert_eq!(coverage.executed_in(ORIGIN..ORIGIN + 10), 9);
+    assert_eq!(
+        coverage.unexecuted(ORIGIN..ORIGIN + 10),
+        vec![ORIGIN + 8..ORIGIN + 9]
+    );
+    assert!(coverage.is_read(0x0300));
+    assert!(!coverage.is_written(0x0300));
+    assert!(coverage.is_written(0x0301));
+    // operands are executed rather than read
+    assert!(coverage.is_executed(ORIGIN + 1));
+    assert!(!coverage.is_read(ORIGIN + 1));
+}
+
+#[test]
+fn coverage_is_only_recorded_while_started() {
+    // nop; nop
+    let (mut cpu, mut bus) = setup(&[0xEA, 0xEA]);
+    let mut debugger = Debugger::new();
+    debugger.step_instruction(&mut cpu, &mut bus);
+    assert!(debugger.coverage().is_none());
+
+    debugger.start_coverage();
+    debugger.step_instruction(&mut cpu, &mut bus);
+    let coverage = debugger.stop_coverage().unwrap();
+    assert_eq!(
+        coverage.unexecuted(ORIGIN..ORIGIN + 2),
+        vec![ORIGIN..ORIGIN + 1]
+    );
+    assert!(debugger.coverage().is_none());
+}
+
+#[test]
+fn temporary_breakpoints_stop_once() {
+    // loop: inx; jmp loop
+    let (mut cpu, mut bus) = setup(&[0xE8, 0x4C, 0x00, 0x02]);
+    let mut debugger = Debugger::new();
+    debugger.add_temporary_breakpoint(ORIGIN + 1);
+
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Completed(2)
+    );
+    assert_eq!(
+        debugger.step_instruction(&mut cpu, &mut bus),
+        StepResult::Hit(Hit::Breakpoint(ORIGIN + 1))
+    );
+    assert_eq!(debugger.temporary_breakpoints().count(), 0);
+    for _ in 0..4 {
+        assert!(matches!(
+            debugger.step_instruction(&mut cpu, &mut bus),
+            StepResult::Completed(_)
+        ));
+    }
+    assert_eq!(cpu.registers.x.get(), 0x03);
+}
+
+#[test]
+fn run_to_stops_at_the_address() {
+    // loop: inx; cpx #$05; bne loop; nop
+    let (mut cpu, mut bus) = setup(&[0xE8, 0xE0, 0x05, 0xD0, 0xFB, 0xEA]);
+    let mut debugger = Debugger::new();
+
+    assert_eq!(
+        debugger.run_to(&mut cpu, &mut bus, ORIGIN + 5, 100),
+        Some(Hit::Breakpoint(ORIGIN + 5))
+    );
+    assert_eq!(cpu.registers.x.get(), 0x05);
+    assert_eq!(debugger.temporary_breakpoints().count(), 0);
+
+    // the starting pc is only hit once it is reached again
+    let (mut cpu, mut bus) = setup(&[0xE8, 0xE0, 0x05, 0xD0, 0xFB, 0xEA]);
+    debugger.add_breakpoint(ORIGIN);
+    assert_eq!(
+        debugger.run_to(&mut cpu, &mut bus, ORIGIN, 100),
+        Some(Hit::Breakpoint(ORIGIN))
+    );
+    assert_eq!(cpu.registers.x.get(), 0x01);
+}
+
+#[test]
+fn run_to_clears_its_breakpoint_on_any_stop() {
+    // loop: inx; stx $10; cpx #$05; bne loop; nop
+    let program = [0xE8, 0x86, 0x10, 0xE0, 0x05, 0xD0, 0xF9, 0xEA];
+    let (mut cpu, mut bus) = setup(&program);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(0x0010, Watch::Write);
+
+    assert_eq!(
+        debugger.run_to(&mut cpu, &mut bus, ORIGIN + 7, 100),
+        Some(Hit::Write {
+            address: 0x0010,
+            data: 0x01
+        })
+    );
+    assert_eq!(debugger.temporary_breakpoints().count(), 0);
+
+    // an earlier breakpoint
+    debugger.clear();
+    debugger.add_breakpoint(ORIGIN + 3);
+    assert_eq!(
+        debugger.run_to(&mut cpu, &mut bus, ORIGIN + 7, 100),
+        Some(Hit::Breakpoint(ORIGIN + 3))
+    );
+    assert_eq!(debugger.temporary_breakpoints().count(), 0);
+
+    // running out of instructions
+    debugger.clear();
+    assert_eq!(debugger.run_to(&mut cpu, &mut bus, ORIGIN + 7, 2), None);
+    assert_eq!(debugger.temporary_breakpoints().count(), 0);
+    assert_eq!(debugger.run(&mut cpu, &mut bus, 100), None);
+    assert_eq!(cpu.registers.x.get(), 0x05);
+}
+
+#[test]
+fn run_to_keeps_a_temporary_breakpoint_set_before() {
+    // inx; inx; inx
+    let (mut cpu, mut bus) = setup(&[0xE8, 0xE8, 0xE8]);
+    let mut debugger = Debugger::new();
+    debugger.add_temporary_breakpoint(ORIGIN + 2);
+    debugger.add_breakpoint(ORIGIN + 1);
+
+    assert_eq!(
+        debugger.run_to(&mut cpu, &mut bus, ORIGIN + 2, 100),
+        Some(Hit::Breakpoint(ORIGIN + 1))
+    );
+    assert_eq!(
+        debugger.temporary_breakpoints().collect::<Vec<_>>(),
+        [ORIGIN + 2]
+    );
+}
+
+#[test]
+fn running_stops_when_the_cpu_jams() {
+    // inx; jam
+    let (mut cpu, mut bus) = setup(&[0xE8, 0x02]);
+    let mut debugger = Debugger::new();
+
+    assert_eq!(debugger.run_to(&mut cpu, &mut bus, 0x1000, 1_000_000), None);
+    assert!(cpu.jammed().is_some());
+    assert_eq!(debugger.temporary_breakpoints().count(), 0);
+}
+
+#[test]
+fn breakpoint_files_round_trip() {
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x1008);
+    debugger.add_breakpoint(0x1000);
+    debugger.add_temporary_breakpoint(0x2000);
+    debugger.add_watchpoint(0x0010, Watch::Write);
+    debugger.add_watchpoint(0x0011, Watch::Read);
+    debugger.add_watchpoint(0xA000, Watch::Access);
+
+    let text = debugger.save();
+    assert_eq!(
+        text,
+        "break $1000\nbreak $1008\nwatch write $0010\nwatch read $0011\nwatch access $a000\n"
+    );
+
+    let mut loaded = Debugger::new();
+    loaded.add_breakpoint(0x3000);
+    loaded.load(&text).unwrap();
+    assert_eq!(loaded.breakpoints().collect::<Vec<_>>(), [0x1000, 0x1008]);
+    assert_eq!(
+        loaded.watchpoints().collect::<Vec<_>>(),
+        [
+            (0x0010, Watch::Write),
+            (0x0011, Watch::Read),
+            (0xA000, Watch::Access)
+        ]
+    );
+    assert_eq!(loaded.temporary_breakpoints().count(), 0);
+    assert_eq!(loaded.save(), text);
+
+    // the loaded points stop execution
+    // sta $10
+    let (mut cpu, mut bus) = setup(&[0x85, 0x10]);
+    assert_eq!(
+        loaded.step_instruction(&mut cpu, &mut bus),
+        StepResult::Hit(Hit::Write {
+            address: 0x0010,
+            data: 0x00
+        })
+    );
+}
+
+#[test]
+fn breakpoint_files_allow_comments() {
+    let mut debugger = Debugger::new();
+    let text = "; session\n\nbreak $1000 ; entry\n  watch read $00ff\n";
+    debugger.load(text).unwrap();
+    assert_eq!(debugger.save(), "break $1000\nwatch read $00ff\n");
+}
+
+#[test]
+fn malformed_breakpoint_files_change_nothing() {
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x1000);
+
+    for (text, line) in [
+        ("break 1000\n", 1),
+        ("break $1000\nbreak\n", 2),
+        ("break $10000\n", 1),
+        ("watch $1000\n", 1),
+        ("watch exec $1000\n", 1),
+        ("\nstep $1000\n", 2),
+    ] {
+        assert_eq!(
+            debugger.load(text),
+            Err(BreakpointError { line }),
+            "{}",
+            text
+        );
+    }
+    assert_eq!(debugger.save(), "break $1000\n");
+    assert_eq!(
+        BreakpointError { line: 3 }.to_string(),
+        "malformed breakpoint on line 3"
+    );
+}
+
+#[test]
+fn run_with_calls_back_before_every_instruction() {
+    // inx; inx; inx
+    let (mut cpu, mut bus) = setup(&[0xE8, 0xE8, 0xE8]);
+    let mut debugger = Debugger::new();
+    let mut pcs = vec![];
+
+    let hit = debugger.run_to_with(&mut cpu, &mut bus, ORIGIN + 2, 100, |cpu, bus| {
+        pcs.push(cpu.registers.pc.get());
+        bus.ram[0x0010] += 1;
+    });
+    assert_eq!(hit, Some(Hit::Breakpoint(ORIGIN + 2)));
+    // the call before the breakpoint stops does not run an instruction
+    assert_eq!(pcs, [ORIGIN, ORIGIN + 1, ORIGIN + 2]);
+    assert_eq!(bus.ram[0x0010], 3);
+}
@@ -0,0 +1,267 @@
+//! ADC and SBC in decimal mode on both models.
+//!
+//! Every pair of operands is checked against the sequences in the appendices
+//! of Bruce Clark's "Decimal Mode" tutorial on 6502.org, which give the result
+//! and flags of each model for valid and invalid BCD digits alike.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{step, TestBus, ORIGIN};
+use cpu::{Cpu, CpuVariant};
+
+const CARRY: u8 = 0x01;
+const ZERO: u8 = 0x02;
+const DECIMAL: u8 = 0x08;
+const OVERFLOW: u8 = 0x40;
+const NEGATIVE: u8 = 0x80;
+
+const ADC: u8 = 0x69;
+const SBC: u8 = 0xE9;
+
+/// Runs `opcode #operand` in decimal mode and returns A, the N, V, Z and C
+/// flags, and the number of cycles taken.
+fn run(
+    variant: CpuVariant,
+    bus: &mut TestBus,
+    opcode: u8,
+    a: u8,
+    operand: u8,
+    carry: bool,
+) -> (u8, u8, u64) {
+    bus.load(ORIGIN, &[opcode, operand]);
+    let mut cpu = Cpu::builder()
+        .variant(variant)
+        .a(a)
+        .pc(ORIGIN)
+        .status(DECIMAL | if carry { CARRY } else { 0 })
+        .build();
+    let cycles = step(&mut cpu, bus);
+    bus.take_log();
+
+    let flags = cpu.status.get_raw() & (NEGATIVE | OVERFLOW | ZERO | CARRY);
+    return (cpu.registers.acc.get(), flags, cycles);
+}
+
+/// Returns the N and Z flags of `value`.
+fn nz(value: i32) -> u8 {
+    let mut flags = 0;
+    if value & 0xFF == 0 {
+        flags |= ZERO;
+    }
+    if value & 0x80 != 0 {
+        flags |= NEGATIVE;
+    }
+    return flags;
+}
+
+/// The result and flags of a decimal ADC, from sequences 1 and 2 of appendix A.
+fn expected_adc(variant: CpuVariant, a: u8, b: u8, carry: bool) -> (u8, u8) {
+    let (a, b, c) = (i32::from(a), i32::from(b), i32::from(carry));
+
+    // sequence 1
+    let mut al = (a & 0x0F) + (b & 0x0F) + c;
+    if al >= 0x0A {
+        al = ((al + 0x06) & 0x0F) + 0x10;
+    }
+    let mut sum = (a & 0xF0) + (b & 0xF0) + al;
+    if sum >= 0xA0 {
+        sum += 0x60;
+    }
+
+    // sequence 2, in signed arithmetic
+    let signed = i32::from((a & 0xF0) as u8 as i8) + i32::from((b & 0xF0) as u8 as i8) + al;
+
+    let mut flags = if sum >= 0x100 { CARRY } else { 0 };
+    if !(-128..=127).contains(&signed) {
+        flags |= OVERFLOW;
+    }
+    flags |= match variant {
+        // N comes from sequence 2 and Z from the binary sum
+        CpuVariant::Nmos6502 => (nz(signed) & NEGATIVE) | (nz(a + b + c) & ZERO),
+        CpuVariant::Cmos65C02 => nz(sum),
+    };
+    return (sum as u8, flags);
+}
+
+/// The result and flags of a decimal SBC, from sequence 3 of appendix B for the
+/// NMOS part and sequence 4 for the 65C02.
+fn expected_sbc(variant: CpuVariant, a: u8, b: u8, carry: bool) -> (u8, u8) {
+    let (a, b, c) = (i32::from(a), i32::from(b), i32::from(carry));
+    let binary = a - b + c - 1;
+
+    let mut al = (a & 0x0F) - (b & 0x0F) + c - 1;
+    let difference = match variant {
+        CpuVariant::Nmos6502 => {
+            if al < 0 {
+                al = ((al - 0x06) & 0x0F) - 0x10;
+            }
+            let mut difference = (a & 0xF0) - (b & 0xF0) + al;
+            if difference < 0 {
+                difference -= 0x60;
+            }
+            difference
+        }
+        CpuVariant::Cmos65C02 => {
+            let mut difference = binary;
+            if difference < 0 {
+                difference -= 0x60;
+            }
+            if al < 0 {
+                difference -= 0x06;
+            }
+            difference
+        }
+    };
+
+    // C and V are those of the binary subtraction on both models
+    let signed = i32::from(a as u8 as i8) - i32::from(b as u8 as i8) + c - 1;
+    let mut flags = if binary >= 0 { CARRY } else { 0 };
+    if !(-128..=127).contains(&signed) {
+        flags |= OVERFLOW;
+    }
+    flags |= match variant {
+        CpuVariant::Nmos6502 => nz(binary),
+        CpuVariant::Cmos65C02 => nz(difference),
+    };
+    return (difference as u8, flags);
+}
+
+/// Checks every operand pair and carry of `opcode` against `expected`.
+fn check_all(variant: CpuVariant, opcode: u8, expected: fn(CpuVariant, u8, u8, bool) -> (u8, u8)) {
+    let mut bus = TestBus::new();
+    for a in 0..=0xFF {
+        for b in 0..=0xFF {
+            for carry in [false, true] {
+                let (result, flags, _) = run(variant, &mut bus, opcode, a, b, carry);
+                assert_eq!(
+                    (result, flags),
+                    expected(variant, a, b, carry),
+                    "{:?} ${:02x} with A=${:02x}, M=${:02x}, C={}",
+                    variant,
+                    opcode,
+                    a,
+                    b,
+                    carry as u8
+                );
+            }
+        }
+    }
+}
+
+fn bcd(value: u8) -> u8 {
+    return (value / 10) << 4 | (value % 10);
+}
+
+#[test]
+fn nmos_adc_matches_for_every_operand() {
+    check_all(CpuVariant::Nmos6502, ADC, expected_adc);
+}
+
+#[test]
+fn nmos_sbc_matches_for_every_operand() {
+    check_all(CpuVariant::Nmos6502, SBC, expected_sbc);
+}
+
+#[test]
+fn cmos_adc_matches_for_every_operand() {
+    check_all(CpuVariant::Cmos65C02, ADC, expected_adc);
+}
+
+#[test]
+fn cmos_sbc_matches_for_every_operand() {
+    check_all(CpuVariant::Cmos65C02, SBC, expected_sbc);
+}
+
+#[test]
+fn valid_bcd_gives_the_decimal_result() {
+    let mut bus = TestBus::new();
+    for variant in [CpuVariant::Nmos6502, CpuVariant::Cmos65C02] {
+        for x in 0..100 {
+            for y in 0..100 {
+                for carry in [false, true] {
+                    let sum = x + y + carry as u8;
+                    let (result, flags, _) = run(variant, &mut bus, ADC, bcd(x), bcd(y), carry);
+                    assert_eq!(result, bcd(sum % 100), "{} + {} + {}", x, y, carry);
+                    assert_eq!(flags & CARRY != 0, sum >= 100);
+
+                    let difference = i32::from(x) - i32::from(y) - i32::from(!carry);
+                    let (result, flags, _) = run(variant, &mut bus, SBC, bcd(x), bcd(y), carry);
+                    let expected = bcd(difference.rem_euclid(100) as u8);
+                    assert_eq!(result, expected, "{} - {} - {}", x, y, !carry);
+                    assert_eq!(flags & CARRY != 0, difference >= 0);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn only_the_cmos_flags_follow_the_result() {
+    // 99 + 1 wraps to 00 with a carry
+    let mut bus = TestBus::new();
+    assert_eq!(
+        run(CpuVariant::Nmos6502, &mut bus, ADC, 0x99, 0x01, false),
+        (0x00, NEGATIVE | CARRY, 2)
+    );
+    assert_eq!(
+        run(CpuVariant::Cmos65C02, &mut bus, ADC, 0x99, 0x01, false),
+        (0x00, ZERO | CARRY, 3)
+    );
+
+    // 00 - 1 wraps to 99 with a borrow
+    assert_eq!(
+        run(CpuVariant::Nmos6502, &mut bus, SBC, 0x00, 0x01, true),
+        (0x99, NEGATIVE, 2)
+    );
+    assert_eq!(
+        run(CpuVariant::Cmos65C02, &mut bus, SBC, 0x00, 0x01, true),
+        (0x99, NEGATIVE, 3)
+    );
+}
+
+#[test]
+fn the_cmos_decimal_cycle_reads_the_next_opcode() {
+    // adc $10; sed; adc $10
+    let mut bus = TestBus::new();
+    bus.load(ORIGIN, &[0x65, 0x10, 0xF8, 0x65, 0x10]);
+    let mut cpu = Cpu::builder()
+        .variant(CpuVariant::Cmos65C02)
+        .pc(ORIGIN)
+        .accurate_bus(true)
+        .build();
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    step(&mut cpu, &mut bus);
+    bus.take_log();
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(
+        bus.take_log(),
+        [
+            common::Access::Read(ORIGIN + 3, 0x65),
+            common::Access::Read(ORIGIN + 4, 0x10),
+            common::Access::Read(0x0010, 0x00),
+            common::Access::Read(ORIGIN + 5, 0x00),
+        ]
+    );
+
+    // the nmos part takes no extra cycle
+    let mut cpu = Cpu::builder().pc(ORIGIN + 3).status(DECIMAL).build();
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+}
+
+#[test]
+fn rra_and_isc_add_and_subtract_in_decimal() {
+    // sed; rra $10; isc $11
+    let mut bus = TestBus::new();
+    bus.load(ORIGIN, &[0xF8, 0x67, 0x10, 0xE7, 0x11]);
+    bus.ram[0x10] = 0x12; // rotated right to $09
+    bus.ram[0x11] = 0x04; // incremented to $05
+    let mut cpu = Cpu::builder().a(0x01).pc(ORIGIN).build();
+
+    step(&mut cpu, &mut bus);
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.acc.get(), 0x10);
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.acc.get(), 0x04);
+}
@@ -0,0 +1,451 @@
+//! Truth tables of the N, V, Z and C flags for each class of instruction.
+//!
+//! Each row runs one instruction and gives the flags set before and after it as
+//! a subset of `NVZC`, so a flag which should be left alone is checked both set
+//! and clear.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, step};
+use cpu::Cpu;
+
+/// The zero page address operands and read-modify-write values are kept at.
+const OPERAND: u16 = 0x0010;
+
+/// Where an instruction takes its input from and leaves its result.
+#[derive(Clone, Copy, Debug)]
+enum Reg {
+    A,
+    X,
+    Y,
+    Sp,
+    /// The byte at [`OPERAND`].
+    M,
+}
+
+/// How the operand of an instruction is given.
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    Implied,
+    Immediate,
+    /// The operand is read from [`OPERAND`], unless the input is already there.
+    ZeroPage,
+}
+
+/// A class of instructions with the same addressing and data flow.
+struct Class {
+    mode: Mode,
+    from: Reg,
+    to: Reg,
+}
+
+/// `opcode` run with `input` in the input register and `operand` as its operand.
+struct Row {
+    opcode: u8,
+    input: u8,
+    operand: u8,
+    before: &'static str,
+    result: u8,
+    after: &'static str,
+}
+
+const fn row(
+    opcode: u8,
+    input: u8,
+    operand: u8,
+    before: &'static str,
+    result: u8,
+    after: &'static str,
+) -> Row {
+    return Row {
+        opcode,
+        input,
+        operand,
+        before,
+        result,
+        after,
+    };
+}
+
+fn status(flags: &str) -> u8 {
+    let mut status = 0x20;
+    for flag in flags.chars() {
+        status |= match flag {
+            'N' => 0x80,
+            'V' => 0x40,
+            'Z' => 0x02,
+            'C' => 0x01,
+            _ => panic!("unknown flag '{}'", flag),
+        };
+    }
+    return status;
+}
+
+fn flags(cpu: &Cpu) -> String {
+    let mut flags = String::new();
+    for (set, name) in [
+        (cpu.status.get_negative(), 'N'),
+        (cpu.status.get_overflow(), 'V'),
+        (cpu.status.get_zero(), 'Z'),
+        (cpu.status.get_carry(), 'C'),
+    ] {
+        if set {
+            flags.push(name);
+        }
+    }
+    return flags;
+}
+
+fn get(cpu: &Cpu, ram: &[u8], reg: Reg) -> u8 {
+    return match reg {
+        Reg::A => cpu.registers.acc.get(),
+        Reg::X => cpu.registers.x.get(),
+        Reg::Y => cpu.registers.y.get(),
+        Reg::Sp => cpu.registers.sp.get(),
+        Reg::M => ram[OPERAND as usize],
+    };
+}
+
+fn check(class: Class, rows: &[Row]) {
+    for row in rows {
+        let program = match class.mode {
+            Mode::Implied => vec![row.opcode],
+            Mode::Immediate => vec![row.opcode, row.operand],
+            Mode::ZeroPage => vec![row.opcode, OPERAND as u8],
+        };
+        let (mut cpu, mut bus) = setup(&program);
+        bus.ram[OPERAND as usize] = row.operand;
+        match class.from {
+            Reg::A => cpu.registers.acc.set(row.input),
+            Reg::X => cpu.registers.x.set(row.input),
+            Reg::Y => cpu.registers.y.set(row.input),
+            Reg::Sp => cpu.registers.sp.set(row.input),
+            Reg::M => bus.ram[OPERAND as usize] = row.input,
+        }
+        cpu.status.set_raw(status(row.before));
+        step(&mut cpu, &mut bus);
+
+        let name = format!(
+            "${:02x} with {:?}=${:02x}, operand ${:02x} and flags '{}'",
+            row.opcode, class.from, row.input, row.operand, row.before
+        );
+        assert_eq!(get(&cpu, &bus.ram[..], class.to), row.result, "{}", name);
+        assert_eq!(flags(&cpu), row.after, "{}", name);
+    }
+}
+
+#[test]
+fn adc() {
+    #[rustfmt::skip]
+    let rows = [
+        row(0x69, 0x00, 0x00, "",     0x00, "Z"),
+        row(0x69, 0x01, 0x01, "",     0x02, ""),
+        row(0x69, 0x01, 0x01, "C",    0x03, ""),
+        row(0x69, 0x7F, 0x01, "",     0x80, "NV"),
+        row(0x69, 0x3F, 0x40, "C",    0x80, "NV"),
+        row(0x69, 0xFF, 0x01, "",     0x00, "ZC"),
+        row(0x69, 0xFF, 0x00, "C",    0x00, "ZC"),
+        row(0x69, 0x80, 0x80, "",     0x00, "VZC"),
+        row(0x69, 0x80, 0xFF, "",     0x7F, "VC"),
+        row(0x69, 0xF0, 0x20, "NV",   0x10, "C"),
+    ];
+    check(
+        Class {
+            mode: Mode::Immediate,
+            from: Reg::A,
+            to: Reg::A,
+        },
+        &rows,
+    );
+}
+
+#[test]
+fn sbc() {
+    #[rustfmt::skip]
+    let rows = [
+        row(0xE9, 0x05, 0x03, "C",    0x02, "C"),
+        row(0xE9, 0x05, 0x03, "",     0x01, "C"),
+        row(0xE9, 0x05, 0x05, "C",    0x00, "ZC"),
+        row(0xE9, 0x03, 0x05, "C",    0xFE, "N"),
+        row(0xE9, 0x00, 0x00, "",     0xFF, "N"),
+        row(0xE9, 0x80, 0x01, "C",    0x7F, "VC"),
+        row(0xE9, 0x7F, 0xFF, "C",    0x80, "NV"),
+        row(0xE9, 0x40, 0x10, "NVZC", 0x30, "C"),
+    ];
+    check(
+        Class {
+            mode: Mode::Immediate,
+            from: Reg::A,
+            to: Reg::A,
+        },
+        &rows,
+    );
+}
+
+#[test]
+fn logical() {
+    // AND, ORA and EOR only set N and Z
+    #[rustfmt::skip]
+    let rows = [
+        row(0x29, 0xF0, 0x0F, "",     0x00, "Z"),
+        row(0x29, 0xF0, 0x80, "VC",   0x80, "NVC"),
+        row(0x29, 0xFF, 0x01, "NZ",   0x01, ""),
+        row(0x09, 0x00, 0x00, "VC",   0x00, "VZC"),
+        row(0x09, 0x01, 0x80, "Z",    0x81, "N"),
+        row(0x49, 0xFF, 0xFF, "N",    0x00, "Z"),
+        row(0x49, 0x0F, 0xF0, "V",    0xFF, "NV"),
+    ];
+    check(
+        Class {
+            mode: Mode::Immediate,
+            from: Reg::A,
+            to: Reg::A,
+        },
+        &rows,
+    );
+}
+
+#[test]
+fn compare() {
+    // C is set when the register is at least the operand, and V is left alone
+    #[rustfmt::skip]
+    let rows = [
+        row(0xC9, 0x05, 0x03, "",     0x05, "C"),
+        row(0xC9, 0x05, 0x05, "",     0x05, "ZC"),
+        row(0xC9, 0x03, 0x05, "C",    0x03, "N"),
+        row(0xC9, 0x80, 0x01, "V",    0x80, "VC"),
+        row(0xC9, 0x01, 0xFF, "Z",    0x01, ""),
+        row(0xC9, 0xFF, 0x00, "",     0xFF, "NC"),
+    ];
+    check(
+        Class {
+            mode: Mode::Immediate,
+            from: Reg::A,
+            to: Reg::A,
+        },
+        &rows,
+    );
+
+    #[rustfmt::skip]
+    let rows = [
+        row(0xE0, 0x10, 0x10, "V",    0x10, "VZC"),
+        row(0xE0, 0x10, 0x20, "",     0x10, "N"),
+    ];
+    check(
+        Class {
+            mode: Mode::Immediate,
+            from: Reg::X,
+            to: Reg::X,
+        },
+        &rows,
+    );
+
+    #[rustfmt::skip]
+    let rows = [
+        row(0xC0, 0x20, 0x10, "",     0x20, "C"),
+        row(0xC0, 0x00, 0x01, "C",    0x00, "N"),
+    ];
+    check(
+        Class {
+            mode: Mode::Immediate,
+            from: Reg::Y,
+            to: Reg::Y,
+        },
+        &rows,
+    );
+}
+
+#[test]
+fn bit() {
+    // N and V are copied from memory, Z is set from A AND M
+    #[rustfmt::skip]
+    let rows = [
+        row(0x24, 0xFF, 0xC0, "",     0xFF, "NV"),
+        row(0x24, 0x3F, 0xC0, "C",    0x3F, "NVZC"),
+        row(0x24, 0x01, 0x01, "NVZ",  0x01, ""),
+        row(0x24, 0x00, 0x40, "",     0x00, "VZ"),
+    ];
+    check(
+        Class {
+            mode: Mode::ZeroPage,
+            from: Reg::A,
+            to: Reg::A,
+        },
+        &rows,
+    );
+}
+
+#[test]
+fn loads() {
+    // loads only set N and Z
+    for (opcode, reg) in [(0xA9, Reg::A), (0xA2, Reg::X), (0xA0, Reg::Y)] {
+        #[rustfmt::skip]
+        let rows = [
+            row(opcode, 0x55, 0x00, "",     0x00, "Z"),
+            row(opcode, 0x55, 0x80, "VC",   0x80, "NVC"),
+            row(opcode, 0x55, 0x01, "NZ",   0x01, ""),
+        ];
+        check(
+            Class {
+                mode: Mode::Immediate,
+                from: reg,
+                to: reg,
+            },
+            &rows,
+        );
+    }
+}
+
+#[test]
+fn transfers() {
+    // every transfer but TXS sets N and Z
+    for (opcode, from, to) in [
+        (0xAA, Reg::A, Reg::X),
+        (0xA8, Reg::A, Reg::Y),
+        (0x8A, Reg::X, Reg::A),
+        (0x98, Reg::Y, Reg::A),
+        (0xBA, Reg::Sp, Reg::X),
+    ] {
+        #[rustfmt::skip]
+        let rows = [
+            row(opcode, 0x00, 0x00, "",     0x00, "Z"),
+            row(opcode, 0x80, 0x00, "VC",   0x80, "NVC"),
+            row(opcode, 0x7F, 0x00, "NZ",   0x7F, ""),
+        ];
+        check(
+            Class {
+                mode: Mode::Implied,
+                from,
+                to,
+            },
+            &rows,
+        );
+    }
+
+    #[rustfmt::skip]
+    let rows = [
+        row(0x9A, 0x00, 0x00, "N",    0x00, "N"),
+        row(0x9A, 0x80, 0x00, "Z",    0x80, "Z"),
+    ];
+    check(
+        Class {
+            mode: Mode::Implied,
+            from: Reg::X,
+            to: Reg::Sp,
+        },
+        &rows,
+    );
+}
+
+#[test]
+fn shifts() {
+    // shifts and rotates set N, Z and C and leave V alone, in A and in memory
+    #[rustfmt::skip]
+    let rows = [
+        // asl
+        row(0x0A, 0x81, 0x00, "",     0x02, "C"),
+        row(0x0A, 0x40, 0x00, "C",    0x80, "N"),
+        row(0x0A, 0x80, 0x00, "V",    0x00, "VZC"),
+        // lsr
+        row(0x4A, 0x01, 0x00, "N",    0x00, "ZC"),
+        row(0x4A, 0x80, 0x00, "C",    0x40, ""),
+        // rol
+        row(0x2A, 0x80, 0x00, "",     0x00, "ZC"),
+        row(0x2A, 0x80, 0x00, "C",    0x01, "C"),
+        row(0x2A, 0x40, 0x00, "V",    0x80, "NV"),
+        // ror
+        row(0x6A, 0x01, 0x00, "",     0x00, "ZC"),
+        row(0x6A, 0x01, 0x00, "C",    0x80, "NC"),
+        row(0x6A, 0x02, 0x00, "N",    0x01, ""),
+    ];
+    check(
+        Class {
+            mode: Mode::Implied,
+            from: Reg::A,
+            to: Reg::A,
+        },
+        &rows,
+    );
+
+    #[rustfmt::skip]
+    let rows = [
+        row(0x06, 0x81, 0x00, "",     0x02, "C"),
+        row(0x46, 0x01, 0x00, "N",    0x00, "ZC"),
+        row(0x26, 0x80, 0x00, "C",    0x01, "C"),
+        row(0x66, 0x01, 0x00, "C",    0x80, "NC"),
+    ];
+    check(
+        Class {
+            mode: Mode::ZeroPage,
+            from: Reg::M,
+            to: Reg::M,
+        },
+        &rows,
+    );
+}
+
+#[test]
+fn increments() {
+    // increments and decrements only set N and Z
+    #[rustfmt::skip]
+    let rows = [
+        row(0xE6, 0xFF, 0x00, "",     0x00, "Z"),
+        row(0xE6, 0x7F, 0x00, "VC",   0x80, "NVC"),
+        row(0xC6, 0x01, 0x00, "C",    0x00, "ZC"),
+        row(0xC6, 0x00, 0x00, "V",    0xFF, "NV"),
+        row(0xC6, 0x80, 0x00, "N",    0x7F, ""),
+    ];
+    check(
+        Class {
+            mode: Mode::ZeroPage,
+            from: Reg::M,
+            to: Reg::M,
+        },
+        &rows,
+    );
+
+    for (inc, dec, reg) in [(0xE8, 0xCA, Reg::X), (0xC8, 0x88, Reg::Y)] {
+        #[rustfmt::skip]
+        let rows = [
+            row(inc, 0xFF, 0x00, "C",    0x00, "ZC"),
+            row(inc, 0x7F, 0x00, "V",    0x80, "NV"),
+            row(dec, 0x00, 0x00, "",     0xFF, "N"),
+            row(dec, 0x01, 0x00, "VC",   0x00, "VZC"),
+        ];
+        check(
+            Class {
+                mode: Mode::Implied,
+                from: reg,
+                to: reg,
+            },
+            &rows,
+        );
+    }
+}
+
+#[test]
+fn pulls() {
+    // pla
+    let (mut cpu, mut bus) = setup(&[0x68, 0x68]);
+    bus.load(0x01FE, &[0x00, 0x80]);
+    cpu.status.set_raw(status("VC"));
+
+    step(&mut cpu, &mut bus);
+    assert_eq!(flags(&cpu), "VZC");
+    step(&mut cpu, &mut bus);
+    assert_eq!(
+        (cpu.registers.acc.get(), flags(&cpu)),
+        (0x80, "NVC".to_string())
+    );
+}
+
+#[test]
+fn php_pushes_break_and_unused_bits() {
+    // php
+    let (mut cpu, mut bus) = setup(&[0x08]);
+    cpu.status.set_raw(0xC3);
+
+    step(&mut cpu, &mut bus);
+    assert_eq!(bus.ram[0x01FD], 0xF3);
+}
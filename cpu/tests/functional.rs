@@ -0,0 +1,118 @@
+//! Klaus Dormann's 6502 functional test suite and the runner it relies on.
+//!
+//! The suite binary is not part of the repository. Assemble or download
+//! `6502_functional_test.bin` (built with `load_data_direct = 1`) and run:
+//! ```text
+//!     FUNCTIONAL_TEST_ROM=path/to/6502_functional_test.bin \
+//!         cargo test -p cpu --test functional -- --ignored
+//! ```
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use common::{setup, ORIGIN};
+use cpu::{Bus, Cpu, Jam, RunResult};
+
+/// Where the suite starts executing.
+const ENTRY: u16 = 0x0400;
+/// The `jmp *` the suite reaches once every test has passed.
+const SUCCESS: u16 = 0x3469;
+/// The byte holding the number of the test being run.
+const TEST_CASE: u16 = 0x0200;
+
+/// Instructions to run before the suite is considered hung. A full pass takes
+/// about 30 million.
+const WATCHDOG: u64 = 100_000_000;
+
+/// A flat 64K RAM bus. The suite is too long to log every access.
+struct Ram(Box<[u8; 0x10000]>);
+
+impl Bus for Ram {
+    fn read(&self, address: u16) -> u8 {
+        return self.0[address as usize];
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.0[address as usize] = data;
+    }
+}
+
+#[test]
+fn run_until_pc_stable_stops_at_the_trap() {
+    // ldx #$03; dex; bne -3; jmp *
+    let (mut cpu, mut bus) = setup(&[0xA2, 0x03, 0xCA, 0xD0, 0xFD, 0x4C, 0x05, 0x02]);
+
+    assert_eq!(
+        cpu.run_until_pc_stable(&mut bus, 100),
+        RunResult::Trapped(ORIGIN + 5)
+    );
+    assert_eq!(cpu.registers.x.get(), 0x00);
+}
+
+#[test]
+fn run_until_pc_stable_gives_up_after_the_limit() {
+    // loop: inx; jmp loop
+    let (mut cpu, mut bus) = setup(&[0xE8, 0x4C, 0x00, 0x02]);
+
+    assert_eq!(
+        cpu.run_until_pc_stable(&mut bus, 10),
+        RunResult::LimitReached
+    );
+    assert_eq!(cpu.registers.x.get(), 0x05);
+}
+
+#[test]
+fn run_until_pc_stable_reports_a_jam() {
+    // ldx #$03; jam
+    let (mut cpu, mut bus) = setup(&[0xA2, 0x03, 0x02]);
+
+    let jam = Jam {
+        opcode: 0x02,
+        pc: ORIGIN + 2,
+    };
+    assert_eq!(
+        cpu.run_until_pc_stable(&mut bus, 100),
+        RunResult::Jammed(jam)
+    );
+    assert_eq!(cpu.registers.x.get(), 0x03);
+
+    // a CPU which is already jammed stops straight away
+    assert_eq!(
+        cpu.run_until_pc_stable(&mut bus, 100),
+        RunResult::Jammed(jam)
+    );
+}
+
+#[test]
+#[ignore = "needs the suite binary, see the module docs"]
+fn klaus_dormann_functional_test() {
+    let path = env::var("FUNCTIONAL_TEST_ROM")
+        .unwrap_or_else(|_| "tests/roms/6502_functional_test.bin".to_string());
+    let image = fs::read(&path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+    assert_eq!(image.len(), 0x10000, "{} is not a full 64K image", path);
+
+    let mut ram = Ram(Box::new([0; 0x10000]));
+    ram.0.copy_from_slice(&image);
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut ram);
+    cpu.registers.pc.set(ENTRY);
+
+    match cpu.run_until_pc_stable(&mut ram, WATCHDOG) {
+        RunResult::Trapped(SUCCESS) => {}
+        RunResult::Trapped(trap) => panic!(
+            "test case ${:02x} failed, trapped at ${:04x}\n{:?}",
+            ram.read(TEST_CASE),
+            trap,
+            cpu
+        ),
+        RunResult::Jammed(jam) => panic!("{}, in test case ${:02x}", jam, ram.read(TEST_CASE)),
+        RunResult::LimitReached => panic!(
+            "suite did not finish within {} instructions, in test case ${:02x}",
+            WATCHDOG,
+            ram.read(TEST_CASE)
+        ),
+    }
+}
@@ -0,0 +1,154 @@
+//! Software and hardware interrupt sequences.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, step, Access};
+use cpu::CpuVariant;
+
+#[test]
+fn brk_pushes_state_and_jumps_through_irq_vector() {
+    // brk $ea
+    let (mut cpu, mut bus) = setup(&[0x00, 0xEA]);
+    bus.load(0xFFFE, &[0x00, 0x80]);
+    cpu.status = cpu.status.with_carry(true).with_irq_disable(false);
+    cpu.registers.sp.set(0xFF);
+
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(cpu.registers.pc.get(), 0x8000);
+    assert_eq!(cpu.registers.sp.get(), 0xFC);
+    assert!(cpu.status.get_irq_disable());
+
+    // return address skips the padding byte, status has B and bit 5 set
+    let writes: Vec<Access> = bus
+        .take_log()
+        .into_iter()
+        .filter(|access| matches!(access, Access::Write(..)))
+        .collect();
+    assert_eq!(
+        writes,
+        vec![
+            Access::Write(0x01FF, 0x02),
+            Access::Write(0x01FE, 0x02),
+            Access::Write(0x01FD, 0x31),
+        ]
+    );
+}
+
+#[test]
+fn rti_returns_from_brk() {
+    // brk $ea; nop ... rti
+    let (mut cpu, mut bus) = setup(&[0x00, 0xEA, 0xEA]);
+    bus.load(0xFFFE, &[0x00, 0x80]);
+    bus.ram[0x8000] = 0x40;
+    cpu.status = cpu.status.with_irq_disable(false);
+    cpu.registers.sp.set(0xFF);
+
+    step(&mut cpu, &mut bus);
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.pc.get(), 0x0202);
+    assert_eq!(cpu.registers.sp.get(), 0xFF);
+    assert!(!cpu.status.get_irq_disable());
+}
+
+/// Returns a CPU running a page of NOPs at $0200 with handlers installed at
+/// $8000 (IRQ) and $9000 (NMI).
+fn setup_handlers() -> (cpu::Cpu, common::TestBus) {
+    let (mut cpu, mut bus) = setup(&[0xEA; 0x100]);
+    bus.load(0xFFFA, &[0x00, 0x90]);
+    bus.load(0xFFFE, &[0x00, 0x80]);
+    cpu.registers.sp.set(0xFF);
+    return (cpu, bus);
+}
+
+#[test]
+fn irq_is_serviced_when_enabled() {
+    let (mut cpu, mut bus) = setup_handlers();
+    cpu.status = cpu.status.with_irq_disable(false).with_brk_command(false);
+    step(&mut cpu, &mut bus);
+
+    cpu.set_irq_line(true);
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(cpu.registers.pc.get(), 0x8000);
+    assert!(cpu.status.get_irq_disable());
+
+    // pushed status has the break flag clear
+    assert_eq!(bus.ram[0x01FF], 0x02);
+    assert_eq!(bus.ram[0x01FE], 0x01);
+    assert_eq!(bus.ram[0x01FD], 0x20);
+}
+
+#[test]
+fn irq_is_ignored_when_disabled() {
+    let (mut cpu, mut bus) = setup_handlers();
+    cpu.status = cpu.status.with_irq_disable(true);
+
+    cpu.set_irq_line(true);
+    assert_eq!(step(&mut cpu, &mut bus), 2);
+    assert_eq!(cpu.registers.pc.get(), 0x0201);
+}
+
+#[test]
+fn irq_is_level_triggered() {
+    let (mut cpu, mut bus) = setup_handlers();
+    bus.ram[0x8000] = 0x40; // rti
+    cpu.status = cpu.status.with_irq_disable(false);
+
+    cpu.set_irq_line(true);
+    step(&mut cpu, &mut bus);
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.pc.get(), 0x0200);
+
+    // still asserted after the handler returns
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.pc.get(), 0x8000);
+}
+
+#[test]
+fn nmi_is_edge_triggered() {
+    let (mut cpu, mut bus) = setup_handlers();
+    bus.ram[0x9000] = 0x40; // rti
+    cpu.status = cpu.status.with_irq_disable(true);
+
+    cpu.set_nmi_line(true);
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(cpu.registers.pc.get(), 0x9000);
+
+    // holding the line does not retrigger
+    step(&mut cpu, &mut bus);
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.pc.get(), 0x0201);
+
+    // the line has to be seen released before a new edge is recognized
+    cpu.set_nmi_line(false);
+    step(&mut cpu, &mut bus);
+    cpu.set_nmi_line(true);
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.pc.get(), 0x9000);
+}
+
+#[test]
+fn nmi_takes_priority_over_irq() {
+    let (mut cpu, mut bus) = setup_handlers();
+    cpu.status = cpu.status.with_irq_disable(false);
+
+    cpu.set_irq_line(true);
+    cpu.set_nmi_line(true);
+    step(&mut cpu, &mut bus);
+    assert_eq!(cpu.registers.pc.get(), 0x9000);
+}
+
+#[test]
+fn decimal_flag_is_cleared_on_cmos_only() {
+    // brk $ea
+    let (mut cpu, mut bus) = setup(&[0x00, 0xEA]);
+    cpu.status = cpu.status.with_decimal_mode(true);
+    step(&mut cpu, &mut bus);
+    assert!(cpu.status.get_decimal_mode());
+
+    let (mut cpu, mut bus) = setup(&[0x00, 0xEA]);
+    cpu.set_variant(CpuVariant::Cmos65C02);
+    cpu.status = cpu.status.with_decimal_mode(true);
+    step(&mut cpu, &mut bus);
+    assert!(!cpu.status.get_decimal_mode());
+}
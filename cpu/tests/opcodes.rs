@@ -0,0 +1,63 @@
+//! Lookups in the opcode table.
+#![allow(clippy::needless_return)]
+
+use cpu::{
+    find_opcode, opcode_info, opcodes, opcodes_by_mnemonic, opcodes_by_mode, AddressMode,
+    CpuVariant,
+};
+
+#[test]
+fn counts_documented_opcodes() {
+    let documented = |variant| opcodes(variant).filter(|info| info.documented).count();
+    assert_eq!(documented(CpuVariant::Nmos6502), 151);
+    assert_eq!(documented(CpuVariant::Cmos65C02), 178);
+    assert_eq!(opcodes(CpuVariant::Cmos65C02).count(), 256);
+}
+
+#[test]
+fn looks_up_by_value() {
+    let info = opcode_info(CpuVariant::Nmos6502, 0xEB).unwrap();
+    assert_eq!((info.value, info.mnemonic), (0xEB, "SBC"));
+    assert_eq!(
+        (info.mode, info.bytes, info.cycles),
+        (AddressMode::Immediate, 2, 2)
+    );
+    assert!(!info.documented);
+    assert_eq!(opcode_info(CpuVariant::Nmos6502, 0x12), None);
+    assert!(opcode_info(CpuVariant::Cmos65C02, 0x12).unwrap().documented);
+}
+
+#[test]
+fn looks_up_by_mnemonic() {
+    let values: Vec<u8> = opcodes_by_mnemonic(CpuVariant::Nmos6502, "ldx")
+        .map(|info| info.value)
+        .collect();
+    assert_eq!(values, [0xA2, 0xA6, 0xAE, 0xB6, 0xBE]);
+
+    let sbc = find_opcode(CpuVariant::Nmos6502, "SBC", AddressMode::Immediate).unwrap();
+    assert_eq!(sbc.value, 0xE9);
+    let nop = find_opcode(CpuVariant::Nmos6502, "nop", AddressMode::Implied).unwrap();
+    assert_eq!(nop.value, 0xEA);
+    let slo = find_opcode(CpuVariant::Nmos6502, "slo", AddressMode::ZeroPage).unwrap();
+    assert_eq!((slo.value, slo.documented), (0x07, false));
+    assert_eq!(
+        find_opcode(CpuVariant::Nmos6502, "stz", AddressMode::ZeroPage),
+        None
+    );
+    assert_eq!(
+        find_opcode(CpuVariant::Cmos65C02, "stz", AddressMode::ZeroPage).map(|info| info.value),
+        Some(0x64)
+    );
+}
+
+#[test]
+fn looks_up_by_mode() {
+    let indirect: Vec<u8> = opcodes_by_mode(CpuVariant::Nmos6502, AddressMode::Indirect)
+        .map(|info| info.value)
+        .collect();
+    assert_eq!(indirect, [0x6C]);
+    assert_eq!(
+        opcodes_by_mode(CpuVariant::Cmos65C02, AddressMode::ZeroPageIndirect).count(),
+        8
+    );
+}
@@ -0,0 +1,82 @@
+//! Instruction counts and cycles recorded while profiling.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, step};
+use cpu::ExecutionStats;
+
+// ldx #3; loop: dex; bne loop; nop
+const LOOP: [u8; 6] = [0xA2, 0x03, 0xCA, 0xD0, 0xFD, 0xEA];
+
+fn stats(instructions: u64, cycles: u64) -> ExecutionStats {
+    return ExecutionStats {
+        instructions,
+        cycles,
+    };
+}
+
+#[test]
+fn counts_by_address_and_mnemonic() {
+    let (mut cpu, mut bus) = setup(&LOOP);
+    cpu.start_profiling();
+    let mut cycles = 0;
+    for _ in 0..8 {
+        cycles += step(&mut cpu, &mut bus);
+    }
+
+    let profile = cpu.profile().unwrap();
+    assert_eq!(profile.total(), stats(8, cycles));
+    assert_eq!(
+        profile.by_address(),
+        vec![
+            (0x0203, stats(3, 8)),
+            (0x0202, stats(3, 6)),
+            (0x0200, stats(1, 2)),
+            (0x0205, stats(1, 2)),
+        ]
+    );
+    assert_eq!(
+        profile.by_mnemonic(),
+        vec![
+            ("BNE", stats(3, 8)),
+            ("DEX", stats(3, 6)),
+            ("LDX", stats(1, 2)),
+            ("NOP", stats(1, 2)),
+        ]
+    );
+    assert_eq!(profile.at(0x0201), stats(0, 0));
+}
+
+#[test]
+fn reports_the_hottest_entries() {
+    let (mut cpu, mut bus) = setup(&LOOP);
+    cpu.start_profiling();
+    for _ in 0..8 {
+        step(&mut cpu, &mut bus);
+    }
+
+    assert_eq!(
+        cpu.profile().unwrap().report(2),
+        "address  instructions      cycles       %\n\
+         $0203               3           8    44.4\n\
+         $0202               3           6    33.3\n\
+         \n\
+         mnemonic instructions      cycles       %\n\
+         BNE                 3           8    44.4\n\
+         DEX                 3           6    33.3\n"
+    );
+}
+
+#[test]
+fn profiling_is_optional() {
+    let (mut cpu, mut bus) = setup(&LOOP);
+    step(&mut cpu, &mut bus);
+    assert!(cpu.profile().is_none());
+
+    cpu.start_profiling();
+    step(&mut cpu, &mut bus);
+    let profile = cpu.stop_profiling().unwrap();
+    assert_eq!(profile.total(), stats(1, 2));
+    assert!(cpu.profile().is_none());
+}
@@ -0,0 +1,33 @@
+//! Running the CPU in time slices.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, ORIGIN};
+
+#[test]
+fn run_for_cycles_returns_the_overshoot() {
+    // loop: inx; jmp loop
+    let (mut cpu, mut bus) = setup(&[0xE8, 0x4C, 0x00, 0x02]);
+    let start = cpu.cycles();
+
+    // each iteration is 2 + 3 cycles
+    assert_eq!(cpu.run_for_cycles(&mut bus, 6), 1);
+    assert_eq!(cpu.registers.x.get(), 0x02);
+    assert_eq!(cpu.cycles() - start, 7);
+
+    assert_eq!(cpu.run_for_cycles(&mut bus, 3), 0);
+    assert_eq!(cpu.cycles() - start, 10);
+    assert_eq!(cpu.registers.pc.get(), ORIGIN);
+}
+
+#[test]
+fn run_until_stops_at_an_instruction_boundary() {
+    // loop: inx; jmp loop
+    let (mut cpu, mut bus) = setup(&[0xE8, 0x4C, 0x00, 0x02]);
+
+    let cycles = cpu.run_until(&mut bus, |cpu| cpu.registers.x.get() == 3);
+    assert_eq!(cycles, 12);
+    assert_eq!(cpu.registers.pc.get(), ORIGIN + 1);
+    assert_eq!(cpu.run_until(&mut bus, |_| true), 0);
+}
@@ -0,0 +1,324 @@
+//! A runner for the per-opcode tests of the SingleStepTests project (formerly
+//! Tom Harte's ProcessorTests), which give the state before and after a single
+//! instruction along with every bus access it makes.
+//!
+//! The upstream files are too large for the repository. A few cases of every
+//! NMOS opcode the emulator models are in `tests/single_step/6502` instead,
+//! written in the same format by `tests/single_step/generate.py` from a
+//! reference model of the data sheet timings, and those are run by default. To
+//! run the upstream files, download the `6502/v1` directory and run:
+//! ```text
+//!     SINGLE_STEP_TESTS=path/to/6502/v1 cargo test -p cpu --test single_step
+//! ```
+//! With `SINGLE_STEP_VARIANT=65c02` the files are run on the 65C02 instead,
+//! which is meant for the `wdc65c02/v1` directory. `SINGLE_STEP_OPCODES` limits
+//! the run to a comma separated list of opcodes, like `a9,b1`.
+//!
+//! The run fails if the directory is missing or holds no test files. Only the
+//! NMOS vectors are in the repository, so the 65C02 is only checked against the
+//! upstream files.
+//!
+//! The opcodes the emulator does not model are left out, see [`excluded`].
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use common::{Access, TestBus};
+use cpu::{opcode_info, Cpu, CpuVariant};
+use serde::Deserialize;
+
+/// The vectors which are part of the repository.
+const VECTORS: &str = "tests/single_step/6502";
+
+/// The B and unused bits are not stored by the CPU, so they are not compared.
+const STATUS_MASK: u8 = !0x30;
+
+/// The NMOS opcodes which jam the CPU here: the `KIL` opcodes, and the
+/// undocumented ANC, ALR, ARR, ANE, SHA, TAS, SHY, SHX, LXA, LAS and SBX, which
+/// are not modelled.
+#[rustfmt::skip]
+const NMOS_EXCLUDED: [u8; 25] = [
+    0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2,
+    0x0B, 0x2B, 0x4B, 0x6B, 0x8B, 0x93, 0x9B, 0x9C, 0x9E, 0x9F, 0xAB, 0xBB, 0xCB,
+];
+
+/// Returns whether the tests of an opcode are left out. On the 65C02 those are
+/// the Rockwell and WDC bit instructions in the `$x7` and `$xF` columns and
+/// WDC's `WAI` and `STP`, which are single cycle NOPs here.
+fn excluded(variant: CpuVariant, opcode: u8) -> bool {
+    return match variant {
+        CpuVariant::Nmos6502 => NMOS_EXCLUDED.contains(&opcode),
+        CpuVariant::Cmos65C02 => opcode & 0x07 == 0x07 || opcode == 0xCB || opcode == 0xDB,
+    };
+}
+
+#[derive(Deserialize)]
+struct TestCase {
+    name: String,
+    initial: State,
+    #[serde(rename = "final")]
+    expected: State,
+    cycles: Vec<(u16, u8, String)>,
+}
+
+#[derive(Deserialize)]
+struct State {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+/// Runs one test case and describes the first difference from the expected
+/// state, if any.
+fn run_case(variant: CpuVariant, case: &TestCase) -> Result<(), String> {
+    let mut bus = TestBus::new();
+    for &(address, data) in case.initial.ram.iter() {
+        bus.ram[address as usize] = data;
+    }
+    let mut cpu = Cpu::builder()
+        .a(case.initial.a)
+        .x(case.initial.x)
+        .y(case.initial.y)
+        .sp(case.initial.s)
+        .pc(case.initial.pc)
+        .status(case.initial.p)
+        .variant(variant)
+        .accurate_bus(true)
+        .build();
+
+    let cycles = cpu.step_instruction(&mut bus);
+
+    let expected = &case.expected;
+    let registers = [
+        ("pc", cpu.registers.pc.get(), expected.pc),
+        ("s", cpu.registers.sp.get() as u16, expected.s as u16),
+        ("a", cpu.registers.acc.get() as u16, expected.a as u16),
+        ("x", cpu.registers.x.get() as u16, expected.x as u16),
+        ("y", cpu.registers.y.get() as u16, expected.y as u16),
+        (
+            "p",
+            (cpu.status.get_raw() & STATUS_MASK) as u16,
+            (expected.p & STATUS_MASK) as u16,
+        ),
+    ];
+    for (name, actual, expected) in registers {
+        if actual != expected {
+            return Err(format!(
+                "{} is ${:02x}, expected ${:02x}",
+                name, actual, expected
+            ));
+        }
+    }
+    for &(address, data) in expected.ram.iter() {
+        if bus.ram[address as usize] != data {
+            return Err(format!(
+                "${:04x} is ${:02x}, expected ${:02x}",
+                address, bus.ram[address as usize], data
+            ));
+        }
+    }
+
+    if cycles != case.cycles.len() as u64 {
+        return Err(format!(
+            "took {} cycles, expected {}",
+            cycles,
+            case.cycles.len()
+        ));
+    }
+    let accesses: Vec<Access> = case
+        .cycles
+        .iter()
+        .map(|(address, data, kind)| match kind.as_str() {
+            "write" => Access::Write(*address, *data),
+            _ => Access::Read(*address, *data),
+        })
+        .collect();
+    let log = bus.take_log();
+    if log != accesses {
+        return Err(format!(
+            "bus activity was {:x?}, expected {:x?}",
+            log, accesses
+        ));
+    }
+    return Ok(());
+}
+
+/// Runs every case in a test file and returns the failures.
+fn run_cases(variant: CpuVariant, json: &str) -> Vec<String> {
+    let cases: Vec<TestCase> = serde_json::from_str(json).expect("malformed test file");
+    return cases
+        .iter()
+        .filter_map(|case| {
+            run_case(variant, case)
+                .err()
+                .map(|err| format!("{}: {}", case.name, err))
+        })
+        .collect();
+}
+
+#[test]
+fn runner_accepts_a_matching_case() {
+    // lda ($10),y
+    let json = r#"[{
+        "name": "b1 10",
+        "initial": {"pc": 512, "s": 253, "a": 0, "x": 0, "y": 1, "p": 36,
+                    "ram": [[512, 177], [513, 16], [16, 0], [17, 48], [12289, 66]]},
+        "final": {"pc": 514, "s": 253, "a": 66, "x": 0, "y": 1, "p": 36,
+                  "ram": [[512, 177], [513, 16], [16, 0], [17, 48], [12289, 66]]},
+        "cycles": [[512, 177, "read"], [513, 16, "read"], [16, 0, "read"],
+                   [17, 48, "read"], [12289, 66, "read"]]
+    }]"#;
+
+    assert_eq!(run_cases(CpuVariant::Nmos6502, json), Vec::<String>::new());
+}
+
+#[test]
+fn runner_reports_differences() {
+    // sta $10, expecting the wrong value and one cycle too many
+    let json = r#"[{
+        "name": "85 10",
+        "initial": {"pc": 512, "s": 253, "a": 7, "x": 0, "y": 0, "p": 36,
+                    "ram": [[512, 133], [513, 16]]},
+        "final": {"pc": 514, "s": 253, "a": 7, "x": 0, "y": 0, "p": 36,
+                  "ram": [[16, 8]]},
+        "cycles": [[512, 133, "read"], [513, 16, "read"], [16, 7, "write"]]
+    }, {
+        "name": "85 20",
+        "initial": {"pc": 512, "s": 253, "a": 7, "x": 0, "y": 0, "p": 36,
+                    "ram": [[512, 133], [513, 32]]},
+        "final": {"pc": 514, "s": 253, "a": 7, "x": 0, "y": 0, "p": 36,
+                  "ram": [[32, 7]]},
+        "cycles": [[512, 133, "read"], [513, 32, "read"], [32, 7, "write"], [32, 7, "write"]]
+    }]"#;
+
+    assert_eq!(
+        run_cases(CpuVariant::Nmos6502, json),
+        vec![
+            "85 10: $0010 is $07, expected $08".to_string(),
+            "85 20: took 3 cycles, expected 4".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn runner_runs_decimal_mode_on_either_model() {
+    // sed; adc #$01 with A = $99, which the nmos part leaves with N set and Z clear
+    let nmos = r#"[{
+        "name": "69 01",
+        "initial": {"pc": 512, "s": 253, "a": 153, "x": 0, "y": 0, "p": 44,
+                    "ram": [[512, 105], [513, 1], [514, 234]]},
+        "final": {"pc": 514, "s": 253, "a": 0, "x": 0, "y": 0, "p": 173,
+                  "ram": [[512, 105], [513, 1]]},
+        "cycles": [[512, 105, "read"], [513, 1, "read"]]
+    }]"#;
+    assert_eq!(run_cases(CpuVariant::Nmos6502, nmos), Vec::<String>::new());
+
+    // the 65c02 sets Z from the result and reads the next opcode once more
+    let cmos = r#"[{
+        "name": "69 01",
+        "initial": {"pc": 512, "s": 253, "a": 153, "x": 0, "y": 0, "p": 44,
+                    "ram": [[512, 105], [513, 1], [514, 234]]},
+        "final": {"pc": 514, "s": 253, "a": 0, "x": 0, "y": 0, "p": 47,
+                  "ram": [[512, 105], [513, 1]]},
+        "cycles": [[512, 105, "read"], [513, 1, "read"], [514, 234, "read"]]
+    }]"#;
+    assert_eq!(run_cases(CpuVariant::Cmos65C02, cmos), Vec::<String>::new());
+    assert_eq!(
+        run_cases(CpuVariant::Nmos6502, cmos),
+        vec!["69 01: p is $8d, expected $0f".to_string()]
+    );
+}
+
+#[test]
+fn single_step_tests() {
+    let dir = env::var("SINGLE_STEP_TESTS").unwrap_or_else(|_| VECTORS.to_string());
+    assert!(
+        Path::new(&dir).is_dir(),
+        "{} does not exist, see the module docs",
+        dir
+    );
+    let variant = match env::var("SINGLE_STEP_VARIANT").as_deref() {
+        Ok("65c02") => CpuVariant::Cmos65C02,
+        Ok("6502") | Err(_) => CpuVariant::Nmos6502,
+        Ok(other) => panic!("unknown variant '{}', expected 6502 or 65c02", other),
+    };
+    let opcodes: Vec<u8> = match env::var("SINGLE_STEP_OPCODES") {
+        Ok(list) => list
+            .split(',')
+            .map(|opcode| u8::from_str_radix(opcode.trim(), 16).expect("bad opcode"))
+            .collect(),
+        Err(_) => (0..=0xFF).collect(),
+    };
+
+    let mut ran = 0;
+    let mut failed = vec![];
+    for opcode in opcodes {
+        if excluded(variant, opcode) {
+            continue;
+        }
+        let path = format!("{}/{:02x}.json", dir, opcode);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+        ran += 1;
+        let failures = run_cases(variant, &json);
+        if let Some(first) = failures.first() {
+            eprintln!(
+                "${:02x}: {} failures, first {}",
+                opcode,
+                failures.len(),
+                first
+            );
+            failed.push(opcode);
+        }
+    }
+    assert!(ran > 0, "no test files in {}", dir);
+    assert!(failed.is_empty(), "failing opcodes: {:02x?}", failed);
+}
+
+#[test]
+fn excluded_opcodes_are_the_unmodelled_ones() {
+    for opcode in 0..=0xFF {
+        let nmos = opcode_info(CpuVariant::Nmos6502, opcode);
+        assert_eq!(
+            excluded(CpuVariant::Nmos6502, opcode),
+            nmos.is_none(),
+            "${:02x}",
+            opcode
+        );
+
+        // the 65c02 runs every opcode, but the excluded ones only as a
+        // single cycle nop in place of the real instruction
+        if excluded(CpuVariant::Cmos65C02, opcode) {
+            let cmos = opcode_info(CpuVariant::Cmos65C02, opcode).unwrap();
+            assert_eq!(
+                (cmos.mnemonic, cmos.bytes, cmos.cycles),
+                ("NOP", 1, 1),
+                "${:02x}",
+                opcode
+            );
+        }
+    }
+}
+
+#[test]
+fn vectors_cover_every_modelled_opcode() {
+    for opcode in 0..=0xFF {
+        let path = format!("{}/{:02x}.json", VECTORS, opcode);
+        assert_eq!(
+            Path::new(&path).is_file(),
+            !excluded(CpuVariant::Nmos6502, opcode),
+            "{}",
+            path
+        );
+    }
+}
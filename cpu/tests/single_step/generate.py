@@ -0,0 +1,494 @@
+#!/usr/bin/env python3
+"""Generates test vectors in the SingleStepTests format from a reference model.
+
+The model is written from the cycle tables in John West and Marko Mäkelä's
+"64doc" and Bruce Clark's notes on decimal mode, independently of the emulator.
+It covers the NMOS opcodes the emulator models, one file per opcode:
+
+    python3 generate.py 6502 [cases per opcode]
+
+The upstream files of https://github.com/SingleStepTests/65x02 can be used in
+their place, see the docs of `single_step.rs`.
+"""
+
+import json
+import os
+import random
+import sys
+
+# mode, kind and the operation of every opcode. The kinds are how the operand
+# is accessed: "r"ead, "w"rite or read-modify-"m"odify-write.
+NMOS = {}
+
+
+def op(opcodes, name, mode, kind):
+    for opcode in opcodes:
+        NMOS[opcode] = (name, mode, kind)
+
+
+# loads and alu operations
+for name, base in [("ORA", 0x00), ("AND", 0x20), ("EOR", 0x40), ("ADC", 0x60),
+                   ("LDA", 0xA0), ("CMP", 0xC0), ("SBC", 0xE0)]:
+    op([base + 0x01], name, "izx", "r")
+    op([base + 0x05], name, "zp", "r")
+    op([base + 0x09], name, "imm", "r")
+    op([base + 0x0D], name, "abs", "r")
+    op([base + 0x11], name, "izy", "r")
+    op([base + 0x15], name, "zpx", "r")
+    op([base + 0x19], name, "aby", "r")
+    op([base + 0x1D], name, "abx", "r")
+op([0x81], "STA", "izx", "w")
+op([0x85], "STA", "zp", "w")
+op([0x8D], "STA", "abs", "w")
+op([0x91], "STA", "izy", "w")
+op([0x95], "STA", "zpx", "w")
+op([0x99], "STA", "aby", "w")
+op([0x9D], "STA", "abx", "w")
+op([0xA2], "LDX", "imm", "r")
+op([0xA6], "LDX", "zp", "r")
+op([0xAE], "LDX", "abs", "r")
+op([0xB6], "LDX", "zpy", "r")
+op([0xBE], "LDX", "aby", "r")
+op([0xA0], "LDY", "imm", "r")
+op([0xA4], "LDY", "zp", "r")
+op([0xAC], "LDY", "abs", "r")
+op([0xB4], "LDY", "zpx", "r")
+op([0xBC], "LDY", "abx", "r")
+op([0x86], "STX", "zp", "w")
+op([0x8E], "STX", "abs", "w")
+op([0x96], "STX", "zpy", "w")
+op([0x84], "STY", "zp", "w")
+op([0x8C], "STY", "abs", "w")
+op([0x94], "STY", "zpx", "w")
+op([0xE0], "CPX", "imm", "r")
+op([0xE4], "CPX", "zp", "r")
+op([0xEC], "CPX", "abs", "r")
+op([0xC0], "CPY", "imm", "r")
+op([0xC4], "CPY", "zp", "r")
+op([0xCC], "CPY", "abs", "r")
+op([0x24], "BIT", "zp", "r")
+op([0x2C], "BIT", "abs", "r")
+
+# shifts, increments and decrements
+for name, base in [("ASL", 0x00), ("ROL", 0x20), ("LSR", 0x40), ("ROR", 0x60),
+                   ("DEC", 0xC0), ("INC", 0xE0)]:
+    op([base + 0x06], name, "zp", "m")
+    op([base + 0x0E], name, "abs", "m")
+    op([base + 0x16], name, "zpx", "m")
+    op([base + 0x1E], name, "abx", "m")
+    if base < 0x80:
+        op([base + 0x0A], name, "acc", "r")
+
+# the stable undocumented opcodes
+for name, base in [("SLO", 0x00), ("RLA", 0x20), ("SRE", 0x40), ("RRA", 0x60),
+                   ("DCP", 0xC0), ("ISC", 0xE0)]:
+    op([base + 0x03], name, "izx", "m")
+    op([base + 0x07], name, "zp", "m")
+    op([base + 0x0F], name, "abs", "m")
+    op([base + 0x13], name, "izy", "m")
+    op([base + 0x17], name, "zpx", "m")
+    op([base + 0x1B], name, "aby", "m")
+    op([base + 0x1F], name, "abx", "m")
+op([0xA3], "LAX", "izx", "r")
+op([0xA7], "LAX", "zp", "r")
+op([0xAF], "LAX", "abs", "r")
+op([0xB3], "LAX", "izy", "r")
+op([0xB7], "LAX", "zpy", "r")
+op([0xBF], "LAX", "aby", "r")
+op([0x83], "SAX", "izx", "w")
+op([0x87], "SAX", "zp", "w")
+op([0x8F], "SAX", "abs", "w")
+op([0x97], "SAX", "zpy", "w")
+op([0xEB], "SBC", "imm", "r")
+op([0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xEA, 0xFA], "NOP", "imp", "r")
+op([0x80, 0x82, 0x89, 0xC2, 0xE2], "NOP", "imm", "r")
+op([0x04, 0x44, 0x64], "NOP", "zp", "r")
+op([0x0C], "NOP", "abs", "r")
+op([0x14, 0x34, 0x54, 0x74, 0xD4, 0xF4], "NOP", "zpx", "r")
+op([0x1C, 0x3C, 0x5C, 0x7C, 0xDC, 0xFC], "NOP", "abx", "r")
+
+# implied
+for opcode, name in [(0x18, "CLC"), (0x38, "SEC"), (0x58, "CLI"), (0x78, "SEI"),
+                     (0xB8, "CLV"), (0xD8, "CLD"), (0xF8, "SED"), (0xAA, "TAX"),
+                     (0xA8, "TAY"), (0x8A, "TXA"), (0x98, "TYA"), (0xBA, "TSX"),
+                     (0x9A, "TXS"), (0xE8, "INX"), (0xC8, "INY"), (0xCA, "DEX"),
+                     (0x88, "DEY")]:
+    op([opcode], name, "imp", "r")
+for opcode, name in [(0x10, "BPL"), (0x30, "BMI"), (0x50, "BVC"), (0x70, "BVS"),
+                     (0x90, "BCC"), (0xB0, "BCS"), (0xD0, "BNE"), (0xF0, "BEQ")]:
+    op([opcode], name, "rel", "r")
+for opcode, name in [(0x00, "BRK"), (0x20, "JSR"), (0x40, "RTI"), (0x60, "RTS"),
+                     (0x08, "PHP"), (0x28, "PLP"), (0x48, "PHA"), (0x68, "PLA"),
+                     (0x4C, "JMP"), (0x6C, "JMPI")]:
+    op([opcode], name, "special", "r")
+
+C, Z, I, D, B, U, V, N = 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80
+
+
+class Machine:
+    def __init__(self, rng, pc, s, a, x, y, p):
+        self.rng = rng
+        self.pc, self.s, self.a, self.x, self.y, self.p = pc, s, a, x, y, p
+        self.initial = {}
+        self.ram = {}
+        self.cycles = []
+
+    def peek(self, address):
+        if address not in self.ram:
+            value = self.rng.randrange(256)
+            self.initial[address] = value
+            self.ram[address] = value
+        return self.ram[address]
+
+    def read(self, address):
+        address &= 0xFFFF
+        value = self.peek(address)
+        self.cycles.append([address, value, "read"])
+        return value
+
+    def write(self, address, value):
+        address &= 0xFFFF
+        self.peek(address)
+        self.ram[address] = value & 0xFF
+        self.cycles.append([address, value & 0xFF, "write"])
+
+    def fetch(self):
+        value = self.read(self.pc)
+        self.pc = (self.pc + 1) & 0xFFFF
+        return value
+
+    def push(self, value):
+        self.write(0x100 | self.s, value)
+        self.s = (self.s - 1) & 0xFF
+
+    def pull(self):
+        self.s = (self.s + 1) & 0xFF
+        return self.read(0x100 | self.s)
+
+    def flag(self, mask, value):
+        self.p = (self.p | mask) if value else (self.p & ~mask & 0xFF)
+
+    def nz(self, value):
+        self.flag(Z, value & 0xFF == 0)
+        self.flag(N, value & 0x80)
+        return value & 0xFF
+
+    # arithmetic
+
+    def adc(self, m):
+        a, c = self.a, self.p & C
+        binary = a + m + c
+        if not self.p & D:
+            self.flag(C, binary > 0xFF)
+            self.flag(V, ~(a ^ m) & (a ^ binary) & 0x80)
+            self.a = self.nz(binary)
+            return
+        lo = (a & 0x0F) + (m & 0x0F) + c
+        if lo >= 0x0A:
+            lo = ((lo + 0x06) & 0x0F) + 0x10
+        signed = sign(a & 0xF0) + sign(m & 0xF0) + lo
+        result = (a & 0xF0) + (m & 0xF0) + lo
+        self.flag(N, signed & 0x80)
+        self.flag(V, signed < -128 or signed > 127)
+        self.flag(Z, binary & 0xFF == 0)
+        if result >= 0xA0:
+            result += 0x60
+        self.flag(C, result >= 0x100)
+        self.a = result & 0xFF
+
+    def sbc(self, m):
+        a, c = self.a, self.p & C
+        binary = a - m - (1 - c)
+        self.flag(C, binary >= 0)
+        self.flag(V, (a ^ m) & (a ^ binary) & 0x80)
+        self.nz(binary)
+        if not self.p & D:
+            self.a = binary & 0xFF
+            return
+        lo = (a & 0x0F) - (m & 0x0F) + c - 1
+        if lo < 0:
+            lo = ((lo - 0x06) & 0x0F) - 0x10
+        result = (a & 0xF0) - (m & 0xF0) + lo
+        if result < 0:
+            result -= 0x60
+        self.a = result & 0xFF
+
+    def compare(self, register, m):
+        self.flag(C, register >= m)
+        self.nz(register - m)
+
+    def modify(self, name, m):
+        """Returns the new value of a read-modify-write operand."""
+        if name in ("ASL", "SLO"):
+            self.flag(C, m & 0x80)
+            result = self.nz(m << 1)
+        elif name in ("LSR", "SRE"):
+            self.flag(C, m & 0x01)
+            result = self.nz(m >> 1)
+        elif name in ("ROL", "RLA"):
+            carry = self.p & C
+            self.flag(C, m & 0x80)
+            result = self.nz((m << 1) | carry)
+        elif name in ("ROR", "RRA"):
+            carry = self.p & C
+            self.flag(C, m & 0x01)
+            result = self.nz((m >> 1) | (carry << 7))
+        elif name in ("INC", "ISC"):
+            result = self.nz(m + 1)
+        else:
+            result = self.nz(m - 1)
+
+        if name == "SLO":
+            self.a = self.nz(self.a | result)
+        elif name == "RLA":
+            self.a = self.nz(self.a & result)
+        elif name == "SRE":
+            self.a = self.nz(self.a ^ result)
+        elif name == "RRA":
+            self.adc(result)
+        elif name == "DCP":
+            self.compare(self.a, result)
+        elif name == "ISC":
+            self.sbc(result)
+        return result
+
+    def operate(self, name, m):
+        """Performs a read instruction on its operand."""
+        if name == "ORA":
+            self.a = self.nz(self.a | m)
+        elif name == "AND":
+            self.a = self.nz(self.a & m)
+        elif name == "EOR":
+            self.a = self.nz(self.a ^ m)
+        elif name == "ADC":
+            self.adc(m)
+        elif name == "SBC":
+            self.sbc(m)
+        elif name == "CMP":
+            self.compare(self.a, m)
+        elif name == "CPX":
+            self.compare(self.x, m)
+        elif name == "CPY":
+            self.compare(self.y, m)
+        elif name == "LDA":
+            self.a = self.nz(m)
+        elif name == "LDX":
+            self.x = self.nz(m)
+        elif name == "LDY":
+            self.y = self.nz(m)
+        elif name == "LAX":
+            self.a = self.x = self.nz(m)
+        elif name == "BIT":
+            self.flag(Z, self.a & m == 0)
+            self.flag(N, m & 0x80)
+            self.flag(V, m & 0x40)
+
+    def stored(self, name):
+        return {"STA": self.a, "STX": self.x, "STY": self.y, "SAX": self.a & self.x}[name]
+
+    # addressing
+
+    def address(self, mode, kind):
+        """Runs the addressing cycles and returns the effective address."""
+        if mode == "zp":
+            return self.fetch()
+        if mode in ("zpx", "zpy"):
+            base = self.fetch()
+            self.read(base)
+            return (base + (self.x if mode == "zpx" else self.y)) & 0xFF
+        if mode == "abs":
+            lo = self.fetch()
+            return lo | (self.fetch() << 8)
+        if mode in ("abx", "aby"):
+            lo = self.fetch()
+            hi = self.fetch()
+            index = self.x if mode == "abx" else self.y
+            return self.indexed(lo, hi, index, kind)
+        if mode == "izx":
+            base = self.fetch()
+            self.read(base)
+            pointer = (base + self.x) & 0xFF
+            lo = self.read(pointer)
+            return lo | (self.read((pointer + 1) & 0xFF) << 8)
+        if mode == "izy":
+            pointer = self.fetch()
+            lo = self.read(pointer)
+            hi = self.read((pointer + 1) & 0xFF)
+            return self.indexed(lo, hi, self.y, kind)
+        raise ValueError(mode)
+
+    def indexed(self, lo, hi, index, kind):
+        address = ((hi << 8) | lo) + index & 0xFFFF
+        unfixed = (hi << 8) | ((lo + index) & 0xFF)
+        if kind != "r" or unfixed != address:
+            self.read(unfixed)
+        return address
+
+    def step(self, opcode):
+        name, mode, kind = NMOS[opcode]
+        self.fetch()
+        if mode == "special":
+            return self.special(name)
+        if mode == "rel":
+            return self.branch(name)
+        if mode == "imp":
+            self.read(self.pc)
+            return self.implied(name)
+        if mode == "acc":
+            self.read(self.pc)
+            self.a = self.modify(name, self.a)
+            return
+        if mode == "imm":
+            return self.operate(name, self.fetch())
+
+        address = self.address(mode, kind)
+        if kind == "r":
+            self.operate(name, self.read(address))
+        elif kind == "w":
+            self.write(address, self.stored(name))
+        else:
+            value = self.read(address)
+            self.write(address, value)
+            self.write(address, self.modify(name, value))
+
+    def implied(self, name):
+        if name in ("CLC", "SEC"):
+            self.flag(C, name == "SEC")
+        elif name in ("CLI", "SEI"):
+            self.flag(I, name == "SEI")
+        elif name in ("CLD", "SED"):
+            self.flag(D, name == "SED")
+        elif name == "CLV":
+            self.flag(V, False)
+        elif name == "TAX":
+            self.x = self.nz(self.a)
+        elif name == "TAY":
+            self.y = self.nz(self.a)
+        elif name == "TXA":
+            self.a = self.nz(self.x)
+        elif name == "TYA":
+            self.a = self.nz(self.y)
+        elif name == "TSX":
+            self.x = self.nz(self.s)
+        elif name == "TXS":
+            self.s = self.x
+        elif name == "INX":
+            self.x = self.nz(self.x + 1)
+        elif name == "INY":
+            self.y = self.nz(self.y + 1)
+        elif name == "DEX":
+            self.x = self.nz(self.x - 1)
+        elif name == "DEY":
+            self.y = self.nz(self.y - 1)
+
+    def branch(self, name):
+        offset = self.fetch()
+        flag = {"BPL": N, "BMI": N, "BVC": V, "BVS": V,
+                "BCC": C, "BCS": C, "BNE": Z, "BEQ": Z}[name]
+        taken = bool(self.p & flag) == (name in ("BMI", "BVS", "BCS", "BEQ"))
+        if not taken:
+            return
+        self.read(self.pc)
+        target = (self.pc + offset - (0x100 if offset & 0x80 else 0)) & 0xFFFF
+        if target >> 8 != self.pc >> 8:
+            self.read((self.pc & 0xFF00) | (target & 0xFF))
+        self.pc = target
+
+    def special(self, name):
+        if name == "BRK":
+            self.fetch()
+            self.push(self.pc >> 8)
+            self.push(self.pc & 0xFF)
+            self.push(self.p | B | U)
+            self.flag(I, True)
+            lo = self.read(0xFFFE)
+            self.pc = lo | (self.read(0xFFFF) << 8)
+        elif name == "JSR":
+            lo = self.fetch()
+            self.read(0x100 | self.s)
+            self.push(self.pc >> 8)
+            self.push(self.pc & 0xFF)
+            self.pc = lo | (self.read(self.pc) << 8)
+        elif name == "RTS":
+            self.read(self.pc)
+            self.read(0x100 | self.s)
+            lo = self.pull()
+            self.pc = lo | (self.pull() << 8)
+            self.fetch()
+        elif name == "RTI":
+            self.read(self.pc)
+            self.read(0x100 | self.s)
+            self.p = self.pull()
+            lo = self.pull()
+            self.pc = lo | (self.pull() << 8)
+        elif name in ("PHA", "PHP"):
+            self.read(self.pc)
+            self.push(self.a if name == "PHA" else self.p | B | U)
+        elif name in ("PLA", "PLP"):
+            self.read(self.pc)
+            self.read(0x100 | self.s)
+            value = self.pull()
+            if name == "PLA":
+                self.a = self.nz(value)
+            else:
+                self.p = value
+        elif name == "JMP":
+            lo = self.fetch()
+            self.pc = lo | (self.fetch() << 8)
+        elif name == "JMPI":
+            lo = self.fetch()
+            hi = self.fetch()
+            target = self.read((hi << 8) | lo)
+            # the high order byte is read from the same page
+            self.pc = target | (self.read((hi << 8) | ((lo + 1) & 0xFF)) << 8)
+
+
+def sign(value):
+    return value - 0x100 if value & 0x80 else value
+
+
+def state(machine, ram):
+    return {
+        "pc": machine.pc, "s": machine.s, "a": machine.a,
+        "x": machine.x, "y": machine.y, "p": machine.p,
+        "ram": sorted([address, value] for address, value in ram.items()),
+    }
+
+
+def case(rng, opcode):
+    registers = [rng.randrange(0x10000)] + [rng.randrange(256) for _ in range(5)]
+    registers[5] |= U
+    machine = Machine(rng, *registers)
+    machine.initial[machine.pc] = opcode
+    machine.ram[machine.pc] = opcode
+    before = state(machine, {})
+    machine.step(opcode)
+    before["ram"] = state(machine, machine.initial)["ram"]
+    name = " ".join("%02x" % machine.initial.get((registers[0] + i) & 0xFFFF, 0) for i in range(3))
+    return {
+        "name": name,
+        "initial": before,
+        "final": state(machine, machine.ram),
+        "cycles": machine.cycles,
+    }
+
+
+def main():
+    variant = sys.argv[1] if len(sys.argv) > 1 else "6502"
+    count = int(sys.argv[2]) if len(sys.argv) > 2 else 10
+    if variant != "6502":
+        sys.exit("unknown variant '%s'" % variant)
+
+    directory = os.path.join(os.path.dirname(os.path.abspath(__file__)), variant)
+    os.makedirs(directory, exist_ok=True)
+    for opcode in sorted(NMOS):
+        rng = random.Random(opcode)
+        cases = [case(rng, opcode) for _ in range(count)]
+        with open(os.path.join(directory, "%02x.json" % opcode), "w") as file:
+            file.write("[\n")
+            file.write(",\n".join(json.dumps(c, separators=(",", ":")) for c in cases))
+            file.write("\n]\n")
+
+
+if __name__ == "__main__":
+    main()
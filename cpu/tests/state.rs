@@ -0,0 +1,84 @@
+//! Saving and restoring the state of the CPU.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, step, ORIGIN};
+use cpu::{Cpu, CpuVariant, StateError};
+
+#[test]
+fn state_round_trips_between_instructions() {
+    // ldx #$05; ldy #$06
+    let (mut cpu, mut bus) = setup(&[0xA2, 0x05, 0xA0, 0x06]);
+    cpu.set_variant(CpuVariant::Cmos65C02);
+    step(&mut cpu, &mut bus);
+    let state = cpu.save_state();
+
+    let mut restored = Cpu::new();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+    assert_eq!(restored.registers.x.get(), 0x05);
+    assert_eq!(restored.registers.pc.get(), ORIGIN + 2);
+    assert_eq!(restored.cycles(), cpu.cycles());
+    assert_eq!(restored.variant(), CpuVariant::Cmos65C02);
+}
+
+#[test]
+fn state_resumes_a_partly_executed_instruction() {
+    // lda ($10),y; sta $20
+    let (mut cpu, mut bus) = setup(&[0xB1, 0x10, 0x85, 0x20]);
+    bus.load(0x0010, &[0xFF, 0x30]);
+    bus.ram[0x3100] = 0x42;
+    cpu.registers.y.set(0x01);
+
+    for _ in 0..3 {
+        cpu.step_cycle(&mut bus);
+    }
+    let state = cpu.save_state();
+
+    let mut restored = Cpu::new();
+    restored.load_state(&state).unwrap();
+    for cpu in [&mut cpu, &mut restored] {
+        assert_eq!(step(cpu, &mut bus), 3);
+        assert_eq!(cpu.registers.acc.get(), 0x42);
+        assert_eq!(step(cpu, &mut bus), 3);
+    }
+    assert_eq!(restored.save_state(), cpu.save_state());
+    assert_eq!(bus.ram[0x0020], 0x42);
+}
+
+#[test]
+fn state_keeps_the_cpu_jammed() {
+    // jam
+    let (mut cpu, mut bus) = setup(&[0x12]);
+    step(&mut cpu, &mut bus);
+
+    let mut restored = Cpu::new();
+    restored.load_state(&cpu.save_state()).unwrap();
+    assert_eq!(restored.jammed(), cpu.jammed());
+    assert!(restored.jammed().is_some());
+}
+
+#[test]
+fn invalid_states_are_rejected() {
+    let (cpu, _) = setup(&[]);
+    let state = cpu.save_state();
+    let mut other = Cpu::new();
+
+    assert_eq!(other.load_state(b"nope\x01"), Err(StateError::BadMagic));
+    let mut newer = state.clone();
+    newer[4] = 2;
+    assert_eq!(
+        other.load_state(&newer),
+        Err(StateError::UnsupportedVersion(2))
+    );
+    assert_eq!(
+        other.load_state(&state[..state.len() - 1]),
+        Err(StateError::Truncated)
+    );
+    assert_eq!(
+        other.load_state(&[&state[..], &[0]].concat()),
+        Err(StateError::TrailingData)
+    );
+    assert_eq!(other.registers.pc.get(), 0);
+}
@@ -0,0 +1,108 @@
+//! Symbol files and disassembly with symbols.
+#![allow(clippy::needless_return)]
+
+use cpu::{CpuVariant, InstructionTrace, SymbolError, SymbolTable};
+
+fn trace(pc: u16, opcode: u8, operands: &[u8]) -> InstructionTrace {
+    return InstructionTrace {
+        cycle: 0,
+        variant: CpuVariant::Nmos6502,
+        pc,
+        opcode,
+        operands: operands.to_vec(),
+        a: 0,
+        x: 0,
+        y: 0,
+        sp: 0xFD,
+        status: 0x24,
+    };
+}
+
+#[test]
+fn symbol_files_round_trip() {
+    let text = "; hello.sym\n$1008 print\n\n$1000 start ; entry\n$a000 STDOUT\n";
+    let symbols = SymbolTable::parse(text).unwrap();
+
+    assert_eq!(symbols.len(), 3);
+    assert_eq!(symbols.address("print"), Some(0x1008));
+    assert_eq!(symbols.name(0xA000), Some("STDOUT"));
+    assert_eq!(
+        symbols.to_string(),
+        "$1000 start\n$1008 print\n$a000 STDOUT\n"
+    );
+    assert_eq!(SymbolTable::parse(&symbols.to_string()), Ok(symbols));
+}
+
+#[test]
+fn public_symbols_have_their_own_section() {
+    let text = "$1000 start\n\n[public]\n$a000 IO_BASE ; for the profile\n$0080 ZP_TOP\n";
+    let symbols = SymbolTable::parse(text).unwrap();
+
+    assert_eq!(symbols.len(), 3);
+    assert_eq!(symbols.address("IO_BASE"), Some(0xA000));
+    assert_eq!(symbols.public_address("IO_BASE"), Some(0xA000));
+    assert_eq!(symbols.public_address("start"), None);
+    assert!(!symbols.is_public("start"));
+    assert_eq!(symbols.name(0x0080), Some("ZP_TOP"));
+    assert_eq!(
+        symbols.to_string(),
+        "$1000 start\n\n[public]\n$0080 ZP_TOP\n$a000 IO_BASE\n"
+    );
+    assert_eq!(SymbolTable::parse(&symbols.to_string()), Ok(symbols));
+
+    let mut symbols = SymbolTable::new();
+    symbols.insert_public("IO_BASE", 0xA000);
+    assert_eq!(symbols.to_string(), "[public]\n$a000 IO_BASE\n");
+}
+
+#[test]
+fn malformed_lines_are_reported() {
+    assert_eq!(
+        SymbolTable::parse("$1000 start\n1000 end\n"),
+        Err(SymbolError { line: 2 })
+    );
+    assert_eq!(
+        SymbolTable::parse("$10000 big\n"),
+        Err(SymbolError { line: 1 })
+    );
+    assert_eq!(
+        SymbolTable::parse("$1000 start\n[private]\n"),
+        Err(SymbolError { line: 2 })
+    );
+}
+
+#[test]
+fn redefined_symbols_move() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert("loop", 0x1000);
+    symbols.insert("start", 0x1000);
+    symbols.insert("loop", 0x1010);
+
+    assert_eq!(symbols.name(0x1000), Some("start"));
+    assert_eq!(symbols.name(0x1010), Some("loop"));
+}
+
+#[test]
+fn disassembly_shows_symbol_names() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert("print_char", 0x1008);
+    symbols.insert("ptr", 0x0010);
+    symbols.insert("start", 0x1000);
+
+    assert_eq!(
+        trace(0x1000, 0x20, &[0x08, 0x10]).disassemble_with(&symbols),
+        "JSR print_char"
+    );
+    assert_eq!(
+        trace(0x1003, 0xB1, &[0x10]).disassemble_with(&symbols),
+        "LDA (ptr),Y"
+    );
+    assert_eq!(
+        trace(0x1005, 0xD0, &[0xF9]).disassemble_with(&symbols),
+        "BNE start"
+    );
+    assert_eq!(
+        trace(0x1005, 0xA9, &[0x10]).disassemble_with(&symbols),
+        "LDA #$10"
+    );
+}
@@ -0,0 +1,114 @@
+//! Instruction and bus tracing.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use common::{setup, step};
+use cpu::{InstructionTrace, Trace, TraceLogger};
+
+/// A writer whose contents stay readable after it is handed to the CPU.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        return self.0.lock().unwrap().write(buf);
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        return Ok(());
+    }
+}
+
+/// A cycle, address, data and write flag.
+type Access = (u64, u16, u8, bool);
+
+#[derive(Default)]
+struct BusRecorder {
+    accesses: Arc<Mutex<Vec<Access>>>,
+}
+
+impl Trace for BusRecorder {
+    fn instruction(&mut self, _: &InstructionTrace) {}
+
+    fn bus_access(&mut self, cycle: u64, address: u16, data: u8, write: bool) {
+        self.accesses
+            .lock()
+            .unwrap()
+            .push((cycle, address, data, write));
+    }
+}
+
+#[test]
+fn trace_reports_state_before_each_instruction() {
+    // lda #$01; sta $10; bne -4
+    let (mut cpu, mut bus) = setup(&[0xA9, 0x01, 0x85, 0x10, 0xD0, 0xFA]);
+    let traces = Arc::new(Mutex::new(vec![]));
+    let sink = Arc::clone(&traces);
+    cpu.set_trace(move |trace: &InstructionTrace| sink.lock().unwrap().push(trace.clone()));
+
+    let start = cpu.cycles();
+    for _ in 0..3 {
+        step(&mut cpu, &mut bus);
+    }
+
+    let traces = traces.lock().unwrap();
+    assert_eq!(traces.len(), 3);
+    assert_eq!(traces[0].pc, 0x0200);
+    assert_eq!(traces[0].operands, vec![0x01]);
+    assert_eq!(traces[0].a, 0x00);
+    assert_eq!(traces[0].cycle, start);
+    assert_eq!(traces[1].a, 0x01);
+    assert_eq!(traces[1].cycle, start + 2);
+    assert_eq!(traces[1].disassemble(), "STA $10");
+    assert_eq!(traces[2].disassemble(), "BNE $0200");
+}
+
+#[test]
+fn trace_logger_writes_log_lines() {
+    // lda ($10),y
+    let (mut cpu, mut bus) = setup(&[0xB1, 0x10]);
+    let buffer = SharedBuffer::default();
+    cpu.set_trace(TraceLogger::new(buffer.clone()));
+    cpu.registers.sp.set(0xFD);
+    let status = cpu.status.get_raw();
+
+    step(&mut cpu, &mut bus);
+    let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(
+        log,
+        format!(
+            "0200  B1 10     LDA ($10),Y     A:00 X:00 Y:00 P:{:02X} SP:FD CYC:{}\n",
+            status,
+            cpu.cycles() - 5
+        )
+    );
+}
+
+#[test]
+fn trace_reports_bus_accesses() {
+    // sta $10
+    let (mut cpu, mut bus) = setup(&[0x85, 0x10]);
+    let recorder = BusRecorder::default();
+    let accesses = Arc::clone(&recorder.accesses);
+    cpu.set_trace(recorder);
+    cpu.registers.acc.set(0x42);
+
+    let start = cpu.cycles();
+    step(&mut cpu, &mut bus);
+    assert_eq!(
+        *accesses.lock().unwrap(),
+        vec![
+            (start, 0x0200, 0x85, false),
+            (start + 1, 0x0201, 0x10, false),
+            (start + 2, 0x0010, 0x42, true),
+        ]
+    );
+
+    cpu.clear_trace();
+    step(&mut cpu, &mut bus);
+    assert_eq!(accesses.lock().unwrap().len(), 3);
+}
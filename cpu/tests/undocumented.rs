@@ -0,0 +1,160 @@
+//! The stable undocumented opcodes, the multi-byte NOPs and the handling of
+//! opcodes which are not modelled.
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{setup, step, ORIGIN};
+use cpu::{Jam, RunResult};
+
+//
+// Loads and Stores
+//
+
+#[test]
+fn lax_loads_accumulator_and_x() {
+    // lax $10
+    let (mut cpu, mut bus) = setup(&[0xA7, 0x10]);
+    bus.ram[0x0010] = 0x5A;
+
+    assert_eq!(step(&mut cpu, &mut bus), 3);
+    assert_eq!(cpu.registers.acc.get(), 0x5A);
+    assert_eq!(cpu.registers.x.get(), 0x5A);
+}
+
+#[test]
+fn lax_indirect_y_page_cross_takes_extra_cycle() {
+    // lax ($10),y
+    let (mut cpu, mut bus) = setup(&[0xB3, 0x10]);
+    bus.load(0x0010, &[0xFF, 0x30]);
+    bus.ram[0x3100] = 0x77;
+    cpu.registers.y.set(0x01);
+
+    assert_eq!(step(&mut cpu, &mut bus), 6);
+    assert_eq!(cpu.registers.acc.get(), 0x77);
+    assert_eq!(cpu.registers.x.get(), 0x77);
+}
+
+#[test]
+fn sax_stores_accumulator_and_x() {
+    // sax $10,y
+    let (mut cpu, mut bus) = setup(&[0x97, 0x10]);
+    cpu.registers.acc.set(0xF0);
+    cpu.registers.x.set(0x3C);
+    cpu.registers.y.set(0x02);
+
+    assert_eq!(step(&mut cpu, &mut bus), 4);
+    assert_eq!(bus.ram[0x0012], 0x30);
+    assert_eq!(bus.writes(), vec![0x0012]);
+}
+
+//
+// Read-Modify-Write
+//
+
+#[test]
+fn dcp_decrements_then_compares() {
+    // dcp $10
+    let (mut cpu, mut bus) = setup(&[0xC7, 0x10]);
+    bus.ram[0x0010] = 0x43;
+    cpu.registers.acc.set(0x42);
+
+    assert_eq!(step(&mut cpu, &mut bus), 5);
+    assert_eq!(bus.ram[0x0010], 0x42);
+    assert_eq!(cpu.registers.acc.get(), 0x42);
+    assert!(cpu.status.get_zero());
+}
+
+#[test]
+fn slo_shifts_then_ors() {
+    // slo $1234,y
+    let (mut cpu, mut bus) = setup(&[0x1B, 0x34, 0x12]);
+    bus.ram[0x1236] = 0x81;
+    cpu.registers.acc.set(0x01);
+    cpu.registers.y.set(0x02);
+
+    assert_eq!(step(&mut cpu, &mut bus), 7);
+    assert_eq!(bus.ram[0x1236], 0x02);
+    assert_eq!(cpu.registers.acc.get(), 0x03);
+    assert!(cpu.status.get_carry());
+    assert_eq!(bus.writes(), vec![0x1236, 0x1236]);
+}
+
+#[test]
+fn sre_indirect_x_takes_eight_cycles() {
+    // sre ($10,x)
+    let (mut cpu, mut bus) = setup(&[0x43, 0x10]);
+    bus.load(0x0012, &[0x00, 0x30]);
+    bus.ram[0x3000] = 0x03;
+    cpu.registers.acc.set(0x0F);
+    cpu.registers.x.set(0x02);
+
+    assert_eq!(step(&mut cpu, &mut bus), 8);
+    assert_eq!(bus.ram[0x3000], 0x01);
+    assert_eq!(cpu.registers.acc.get(), 0x0E);
+    assert!(cpu.status.get_carry());
+}
+
+#[test]
+fn isc_indirect_y_takes_eight_cycles() {
+    // isc ($10),y
+    let (mut cpu, mut bus) = setup(&[0xF3, 0x10]);
+    bus.load(0x0010, &[0x00, 0x30]);
+    bus.ram[0x3001] = 0x0F;
+    cpu.registers.acc.set(0x20);
+    cpu.registers.y.set(0x01);
+    cpu.status = cpu.status.with_carry(true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 8);
+    assert_eq!(bus.ram[0x3001], 0x10);
+    assert_eq!(cpu.registers.acc.get(), 0x10);
+    assert_eq!(bus.writes(), vec![0x3001, 0x3001]);
+}
+
+//
+// NOPs
+//
+
+#[test]
+fn multi_byte_nops_skip_their_operands() {
+    // nop #$ff; nop $10; nop $10,x; nop $1234; nop $12ff,x; nop
+    let (mut cpu, mut bus) = setup(&[
+        0x80, 0xFF, 0x04, 0x10, 0x14, 0x10, 0x0C, 0x34, 0x12, 0x1C, 0xFF, 0x12, 0x1A,
+    ]);
+    cpu.registers.x.set(0x01);
+
+    let cycles: Vec<u64> = (0..6).map(|_| step(&mut cpu, &mut bus)).collect();
+    assert_eq!(cycles, vec![2, 3, 4, 4, 5, 2]);
+    assert_eq!(cpu.registers.pc.get(), ORIGIN + 13);
+    assert!(bus.writes().is_empty());
+}
+
+#[test]
+fn unknown_opcode_jams_the_cpu() {
+    // jam
+    let (mut cpu, mut bus) = setup(&[0x02, 0xEA]);
+
+    assert_eq!(step(&mut cpu, &mut bus), 1);
+    assert_eq!(
+        cpu.jammed(),
+        Some(Jam {
+            opcode: 0x02,
+            pc: ORIGIN
+        })
+    );
+
+    // only the clock runs until the cpu is reset
+    cpu.set_nmi_line(true);
+    assert_eq!(step(&mut cpu, &mut bus), 1);
+    assert_eq!(cpu.registers.pc.get(), ORIGIN);
+    assert_eq!(
+        cpu.run_until_pc_stable(&mut bus, 10),
+        RunResult::Jammed(Jam {
+            opcode: 0x02,
+            pc: ORIGIN
+        })
+    );
+
+    cpu.reset(&mut bus);
+    assert_eq!(cpu.jammed(), None);
+}
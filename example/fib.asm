@@ -1,7 +1,9 @@
-#define N  $99
-#define f0 $100
-#define f1 $101
-#define result $104
+        .org $1000
+
+%define N  $99
+%define f0 $100
+%define f1 $101
+%define result $104
 
 fibonacci:
     LDA #$00 ; fib := 0
@@ -14,6 +16,7 @@ fibonacci:
     BMI end  ; finish if N - 1 < 0
 loop:
     ; fib := f0 + f1
+    CLC
     LDA #$00 ; fib := 0
     ADC f0   ; fib += f0
     ADC f1   ; fib += f1
@@ -31,6 +34,5 @@ loop:
 end:
     SED ; this is just to signal completion
 hang:
-    ; hang forever
-    NOP
+    ; hang forever, which is how the emulator sees the end
     JMP hang
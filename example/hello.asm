@@ -1,4 +1,6 @@
-#define STDOUT $A000
+        .org $1000
+
+%define STDOUT $A000
 
     LDA #$48  ; H
     STA STDOUT
@@ -14,6 +16,5 @@
 
     SED ; this is just to signal completion
 hang:
-    ; hang forever
-    NOP
+    ; hang forever, which is how the emulator sees the end
     JMP hang
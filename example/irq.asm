@@ -0,0 +1,29 @@
+        .org $1000
+
+%define STDOUT $A000
+
+; BRK runs the interrupt handler, which returns past the byte after it
+irq_test:
+    LDX #$FF
+    TXS
+    BRK
+    .db 0       ; skipped on return
+    LDA #'2'
+    STA STDOUT
+    LDA #10
+    STA STDOUT
+
+    SED ; this is just to signal completion
+hang:
+    ; hang forever, which is how the emulator sees the end
+    JMP hang
+
+handler:
+    LDA #'1'
+    STA STDOUT
+    RTI
+
+; the vector has its own segment, so the space before it is not filled
+        .segment vectors
+        .org $FFFE
+    .dw handler
@@ -0,0 +1,21 @@
+        .org $1000
+
+%define STDOUT $A000
+
+; prints a null terminated string
+print:
+    LDX #0
+.loop:
+    LDA message,x
+    BEQ .done
+    STA STDOUT
+    INX
+    BNE .loop
+.done:
+    SED ; this is just to signal completion
+hang:
+    ; hang forever, which is how the emulator sees the end
+    JMP hang
+
+message:
+    .db "Hello, world!", 10, 0
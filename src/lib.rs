@@ -0,0 +1,47 @@
+//! A 6502 emulator for embedding: a cycle-stepped CPU, a memory map of RAM and
+//! devices, and whole machines built from the two.
+//! ```text
+//!     let mut system = rs6502::System::builder()
+//!         .device("stdout", StdoutDevice::RANGE, StdoutDevice::new())
+//!         .rom("program", Range::new(0x1000, 0x2000), program)
+//!         .reset_vector(0x1000)
+//!         .build()?;
+//!     system.run_for_cycles(1_000_000);
+//! ```
+//! The [`cpu`] and [`system`] crates this is made of can also be used on their
+//! own, and are re-exported whole for what is not listed here.
+#![allow(clippy::needless_return)]
+
+pub use cpu;
+pub use system;
+
+#[cfg(feature = "tui")]
+pub mod tui;
+
+// the processor
+pub use cpu::{Bus, Cpu, CpuBuilder, CpuVariant, Jam, Pins, Register, Registers, StatusFlags};
+
+// instruction metadata
+pub use cpu::{
+    disassemble, find_opcode, opcode_info, opcodes, opcodes_by_mnemonic, opcodes_by_mode,
+    AddressMode, OpcodeInfo,
+};
+
+// debugging
+pub use cpu::{
+    BreakpointError, Coverage, Debugger, ExecutionStats, Hit, InstructionTrace, Profile,
+    StepResult, SymbolTable, Trace, TraceLogger, Watch,
+};
+pub use system::{hex_dump, BusLog, Change, Highlight, Rewind, Snapshot, Transaction};
+
+// memory and machines
+pub use system::device::{self, Device, ResetKind};
+pub use system::{
+    run_program, ConfigError, Exit, Image, ImageError, Limits, MachineConfig, MachineProfile,
+    Memory, MemoryError, Pacing, ProfileError, Range, RunReport, Speed, System, SystemBuilder,
+    Unmapped,
+};
+pub use system::{DeviceFactory, DeviceRegistry, Settings};
+
+// saved states
+pub use cpu::{StateError, StateReader, StateWriter};
@@ -1,32 +1,203 @@
+#![allow(clippy::needless_return)]
+
 use std::error::Error;
 use std::fs;
 
-use cpu::Cpu;
-use system::{device::StdoutDevice, Bus, Memory};
+use rs6502::device::StdoutDevice;
+use rs6502::{
+    BusLog, Cpu, Debugger, Hit, Image, MachineConfig, Memory, Range, StepResult, SymbolTable,
+};
 
-fn run() -> Result<(), Box<dyn Error>> {
-    let mut mem = Memory::new();
-    mem.register_device(StdoutDevice::new());
+/// The command line.
+struct Options {
+    /// Names to label addresses with.
+    symbols: SymbolTable,
+    /// Holds the breakpoints and watchpoints to stop at.
+    debugger: Debugger,
+    /// The breakpoint file, which the TUI saves to when it quits.
+    breakpoints: Option<String>,
+    /// The program to run instead of hello.o.
+    program: Option<String>,
+    /// The address or symbol the program ends at, if it does not end in a jam
+    /// or a `jmp *` loop.
+    stop: Option<String>,
+    tui: bool,
+}
 
-    let mut rom = fs::File::open("example/hello.o")?;
-    // let mut rom = fs::File::open("example/fib.o")?;
-    mem.load_rom(0x1000, &mut rom)?;
-    mem.write(Cpu::RES_VECTOR, 0x00);
-    mem.write(Cpu::RES_VECTOR + 1, 0x10);
+/// Reads the command line, which may name a symbol file, a breakpoint file, a
+/// stop address and a program, and ask for the TUI when it is built in.
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        symbols: SymbolTable::new(),
+        debugger: Debugger::new(),
+        breakpoints: None,
+        program: None,
+        stop: None,
+        tui: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--symbols" => {
+                let path = args.next().ok_or("missing value for option '--symbols'")?;
+                let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))?;
+                options.symbols =
+                    SymbolTable::parse(&text).map_err(|err| format!("{}: {}", path, err))?;
+            }
+            "--breakpoints" => {
+                let path = args
+                    .next()
+                    .ok_or("missing value for option '--breakpoints'")?;
+                options.breakpoints = Some(path);
+            }
+            "--stop" => {
+                let stop = args.next().ok_or("missing value for option '--stop'")?;
+                options.stop = Some(stop);
+            }
+            "--tui" if cfg!(feature = "tui") => options.tui = true,
+            _ if arg.starts_with('-') || options.program.is_some() => {
+                return Err(format!("unknown argument '{}'", arg).into());
+            }
+            _ => options.program = Some(arg),
+        }
+    }
+
+    // the TUI creates a breakpoint file which does not exist yet
+    if let Some(path) = options.breakpoints.as_ref() {
+        match fs::read_to_string(path) {
+            Ok(text) => options
+                .debugger
+                .load(&text)
+                .map_err(|err| format!("{}: {}", path, err))?,
+            Err(err) if options.tui && err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("{}: {}", path, err).into()),
+        }
+    }
+    return Ok(options);
+}
+
+/// Builds the machine for a program, or for hello.o if none is given, with
+/// `stdout` as its console.
+fn load_machine(
+    program: Option<&str>,
+    stdout: StdoutDevice,
+) -> Result<Memory<'static>, Box<dyn Error>> {
+    if let Some(path) = program {
+        return load_program(path, stdout);
+    }
+
+    let rom = fs::read("example/hello.o")?;
+    // let rom = fs::read("example/fib.o")?;
+    // a flat binary does not say where it starts
+    let config = MachineConfig::new()
+        .device("stdout", StdoutDevice::RANGE, stdout)
+        .rom("hello.o", Range::new(0x1000, 0x2000), rom)
+        .reset_vector(0x1000);
+    return build(config);
+}
+
+/// Builds a machine, printing each error in its configuration.
+fn build(config: MachineConfig<'static>) -> Result<Memory<'static>, Box<dyn Error>> {
+    return match config.build() {
+        Ok(mem) => Ok(mem),
+        Err(errors) => {
+            for error in errors.iter() {
+                eprintln!("config error: {}", error);
+            }
+            let reason = format!("invalid machine configuration ({} errors)", errors.len());
+            Err(reason.into())
+        }
+    };
+}
+
+/// Builds the machine for a program in the Intel HEX, S-record or `.prg` format,
+/// which is loaded into RAM and started from the address it gives.
+fn load_program(path: &str, stdout: StdoutDevice) -> Result<Memory<'static>, Box<dyn Error>> {
+    let image = if path.ends_with(".prg") {
+        let bytes = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+        Image::parse_prg(&bytes)
+    } else {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        if path.ends_with(".srec") || path.ends_with(".s19") {
+            Image::parse_srec(&text)
+        } else {
+            Image::parse_ihex(&text)
+        }
+    }
+    .map_err(|err| format!("{}: {}", path, err))?;
+    let start = image
+        .start
+        .ok_or_else(|| format!("{}: no start address", path))?;
+
+    let config = MachineConfig::new()
+        .device("stdout", StdoutDevice::RANGE, stdout)
+        .reset_vector(start);
+    let mut mem = build(config)?;
+    mem.load_image(&image)
+        .map_err(|err| format!("{}: {}", path, err))?;
+    return Ok(mem);
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let options = parse_args()?;
+    if options.tui {
+        #[cfg(feature = "tui")]
+        return run_tui(options);
+    }
+    let Options {
+        symbols,
+        mut debugger,
+        program,
+        stop,
+        ..
+    } = options;
+    let stop = match stop {
+        Some(stop) => Some(parse_address(&stop, &symbols)?),
+        None => None,
+    };
+    let mem = load_machine(program.as_deref(), StdoutDevice::new())?;
 
     // // set N for fibonacci subroutine
     // let n = 11;
     // mem.write(0x99, n);
 
+    let mut bus = BusLog::new(mem, 200);
+    bus.set_symbol_formatter(move |address: u16| match symbols.name(address) {
+        Some(name) => Some(name.to_string()),
+        None if address == 0xA000 => Some("STDOUT".to_string()),
+        None => None,
+    });
     let mut cpu = Cpu::new();
-    cpu.reset(&mut mem);
+    cpu.power_on(&mut bus);
 
     use std::time::Instant;
     let start = Instant::now();
 
+    if let Some(stop) = stop {
+        debugger.add_breakpoint(stop);
+    }
+
+    // the program ends when it jams, reaches the stop address or loops on one
+    // instruction, such as `jmp *`
+    let mut halted = false;
     for _ in 0..1000 {
-        cpu.step_instruction(&mut mem);
-        if cpu.status.get_decimal_mode() {
+        bus.set_memory_cycle(cpu.cycles());
+        cpu.set_irq_line(bus.inner().irq_asserted());
+        let pc = cpu.registers.pc.get();
+        if let StepResult::Hit(hit) = debugger.step_instruction(&mut cpu, &mut bus) {
+            if stop.map(Hit::Breakpoint) != Some(hit) {
+                println!("stopped: {:?}", hit);
+            }
+            halted = true;
+            break;
+        }
+        if let Some(jam) = cpu.jammed() {
+            println!("{}", jam);
+            halted = true;
+            break;
+        }
+        if cpu.registers.pc.get() == pc {
+            halted = true;
             break;
         }
     }
@@ -35,14 +206,102 @@ fn run() -> Result<(), Box<dyn Error>> {
     let elapsed = end - start;
 
     println!("{:?}\n", cpu);
+    if !halted {
+        println!("program did not halt, last bus cycles:\n{}", bus.dump());
+    }
+    for (range, stats) in bus.inner().device_stats() {
+        println!(
+            "device ${:04x}-${:04x}: {} reads, {} writes",
+            range.start, range.end, stats.reads, stats.writes
+        );
+    }
     // println!("fib({}) = {}", n, mem.read(0x104));
     println!("took {} us", elapsed.as_micros());
     return Ok(());
 }
 
+/// Reads an address given as `$hex` or as a symbol.
+fn parse_address(text: &str, symbols: &SymbolTable) -> Result<u16, Box<dyn Error>> {
+    if let Some(hex) = text.strip_prefix('$') {
+        return u16::from_str_radix(hex, 16).map_err(|_| format!("bad address '{}'", text).into());
+    }
+    return symbols
+        .address(text)
+        .ok_or_else(|| format!("unknown symbol '{}'", text).into());
+}
+
+/// Debugs the program on the terminal, redrawing the screen after each command,
+/// and saves the breakpoints on the way out.
+#[cfg(feature = "tui")]
+fn run_tui(options: Options) -> Result<(), Box<dyn Error>> {
+    use rs6502::tui::{Console, Tui};
+
+    let console = Console::new();
+    let stdout = StdoutDevice::with_output(console.clone());
+    let mut mem = load_machine(options.program.as_deref(), stdout)?;
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    let mut tui = Tui::new(cpu, mem, options.debugger, options.symbols, console);
+
+    let mut terminal = ratatui::init();
+    let result = read_commands(&mut terminal, &mut tui);
+    ratatui::restore();
+    result?;
+
+    if let Some(path) = options.breakpoints {
+        fs::write(&path, tui.debugger().save()).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    return Ok(());
+}
+
+/// Redraws the screen and edits the command line until a command or escape
+/// quits.
+#[cfg(feature = "tui")]
+fn read_commands(
+    terminal: &mut ratatui::DefaultTerminal,
+    tui: &mut rs6502::tui::Tui,
+) -> std::io::Result<()> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::widgets::Paragraph;
+    use rs6502::tui::Flow;
+
+    let mut line = String::new();
+    loop {
+        terminal.draw(|frame| {
+            let [screen, prompt] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+            tui.draw(screen, frame.buffer_mut());
+            frame.render_widget(Paragraph::new(format!("> {}", line)), prompt);
+            let column = prompt.x.saturating_add(2 + line.chars().count() as u16);
+            frame.set_cursor_position((column, prompt.y));
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char(c) => line.push(c),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Enter => match tui.execute(&std::mem::take(&mut line)) {
+                Flow::Quit => return Ok(()),
+                Flow::Continue => {}
+            },
+            _ => {}
+        }
+    }
+}
+
 fn main() {
     match run() {
         Ok(_) => {}
-        Err(err) => panic!("error: {}", err.to_string()),
+        Err(err) => panic!("error: {}", err),
     }
 }
@@ -0,0 +1,440 @@
+//! An interactive step debugger for the terminal, built on [`Debugger`].
+//!
+//! The screen has panes for the disassembly around the PC, the registers and
+//! flags, the stack page, watched memory and the console, and is redrawn after
+//! every command. Commands are typed on the bottom line, and escape quits:
+//! ```text
+//!     s [count]         step instructions, as does an empty line
+//!     c                 continue to a breakpoint or watchpoint
+//!     u <address>       run until the next instruction is at an address
+//!     b <address>       set or clear a breakpoint
+//!     w <address> [r|w] watch reads, writes or both, or stop watching
+//!     q                 quit
+//! ```
+//! Addresses are hex numbers after a `$`, or the names of symbols.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Widget};
+
+use cpu::{disassemble, opcode_info, Cpu, Debugger, Hit, StepResult, SymbolTable, Watch};
+use system::Memory;
+
+/// How many instructions `c` and `u` run before giving up.
+const MAX_INSTRUCTIONS: u64 = 10_000_000;
+/// The width of the column of panes to the right of the disassembly.
+const SIDE_WIDTH: u16 = 30;
+
+/// Output captured for the console pane. Clones share the same buffer, so one
+/// can be given to a device such as [`system::device::StdoutDevice`].
+#[derive(Clone, Debug, Default)]
+pub struct Console(Arc<Mutex<Vec<u8>>>);
+
+impl Console {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns everything written so far.
+    pub fn contents(&self) -> String {
+        return String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned();
+    }
+}
+
+impl Write for Console {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+/// What to do after a command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+/// A machine stopped in the debugger, and what is shown of it.
+pub struct Tui<'a> {
+    cpu: Cpu,
+    memory: Memory<'a>,
+    debugger: Debugger,
+    symbols: SymbolTable,
+    console: Console,
+    /// The outcome of the last command, shown on the bottom line.
+    message: String,
+}
+
+impl<'a> Tui<'a> {
+    /// Debugs a machine whose console writes to `console`.
+    pub fn new(
+        cpu: Cpu,
+        memory: Memory<'a>,
+        debugger: Debugger,
+        symbols: SymbolTable,
+        console: Console,
+    ) -> Self {
+        Self {
+            cpu,
+            memory,
+            debugger,
+            symbols,
+            console,
+            message: String::new(),
+        }
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        return &self.cpu;
+    }
+
+    pub fn memory(&self) -> &Memory<'a> {
+        return &self.memory;
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        return &self.debugger;
+    }
+
+    /// Returns the outcome of the last command.
+    pub fn message(&self) -> &str {
+        return &self.message;
+    }
+
+    /// Runs a command line, see the module documentation.
+    pub fn execute(&mut self, line: &str) -> Flow {
+        let mut words = line.split_whitespace();
+        let result = match words.next() {
+            None => Ok(self.step(1)),
+            Some("s") => match words.next().map(str::parse::<u64>) {
+                None => Ok(self.step(1)),
+                Some(Ok(count)) => Ok(self.step(count)),
+                Some(Err(_)) => Err("expected a number of instructions".to_string()),
+            },
+            Some("c") => {
+                let hit =
+                    self.debugger
+                        .run_with(&mut self.cpu, &mut self.memory, MAX_INSTRUCTIONS, sync);
+                Ok(self.stopped(hit))
+            }
+            Some("u") => self.parse_address(words.next()).map(|address| {
+                let hit = self.debugger.run_to_with(
+                    &mut self.cpu,
+                    &mut self.memory,
+                    address,
+                    MAX_INSTRUCTIONS,
+                    sync,
+                );
+                self.stopped(hit)
+            }),
+            Some("b") => self.parse_address(words.next()).map(|address| {
+                if self.debugger.remove_breakpoint(address) {
+                    return format!("cleared the breakpoint at {}", self.label(address));
+                }
+                self.debugger.add_breakpoint(address);
+                return format!("breakpoint at {}", self.label(address));
+            }),
+            Some("w") => self.parse_address(words.next()).and_then(|address| {
+                let watch = match words.next() {
+                    None => Watch::Access,
+                    Some("r") => Watch::Read,
+                    Some("w") => Watch::Write,
+                    Some(other) => return Err(format!("unknown kind of watch '{}'", other)),
+                };
+                if self
+                    .debugger
+                    .watchpoints()
+                    .any(|(a, w)| a == address && w == watch)
+                {
+                    self.debugger.remove_watchpoint(address, Watch::Access);
+                    return Ok(format!("stopped watching {}", self.label(address)));
+                }
+                self.debugger.remove_watchpoint(address, Watch::Access);
+                self.debugger.add_watchpoint(address, watch);
+                return Ok(format!("watching {}", self.label(address)));
+            }),
+            Some("q") => return Flow::Quit,
+            Some(other) => Err(format!("unknown command '{}'", other)),
+        };
+        self.message = result.unwrap_or_else(|err| format!("error: {}", err));
+        return Flow::Continue;
+    }
+
+    /// Draws the panes, with the outcome of the last command on the bottom
+    /// line.
+    pub fn draw(&self, area: Rect, buf: &mut Buffer) {
+        let console = (area.height / 4).max(4);
+        let [top, console, message] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(console),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+        let side = SIDE_WIDTH.min(area.width / 2);
+        let [main, side] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(side)]).areas(top);
+        let [registers, stack, watch] = Layout::vertical([
+            Constraint::Length(6),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ])
+        .areas(side);
+
+        pane("Disassembly", self.disassembly(rows(main)), main, buf);
+        pane("Registers", self.registers(), registers, buf);
+        pane("Stack", self.stack(rows(stack)), stack, buf);
+        let width = usize::from(watch.width.saturating_sub(2));
+        pane("Watch", self.watches(width), watch, buf);
+        pane("Console", self.console_lines(rows(console)), console, buf);
+        Paragraph::new(self.message.as_str()).render(message, buf);
+    }
+
+    /// Draws the screen as `height` lines of `width` characters.
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let size = |n: usize| u16::try_from(n).unwrap_or(u16::MAX);
+        let area = Rect::new(0, 0, size(width), size(height));
+        let mut buf = Buffer::empty(area);
+        self.draw(area, &mut buf);
+        return (0..area.height)
+            .map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+    }
+
+    //
+
+    fn step(&mut self, count: u64) -> String {
+        for _ in 0..count {
+            sync(&mut self.cpu, &mut self.memory);
+            let result = self
+                .debugger
+                .step_instruction(&mut self.cpu, &mut self.memory);
+            if let StepResult::Hit(hit) = result {
+                return self.describe(hit);
+            }
+            if let Some(jam) = self.cpu.jammed() {
+                return jam.to_string();
+            }
+        }
+        return String::new();
+    }
+
+    /// Describes why running stopped.
+    fn stopped(&self, hit: Option<Hit>) -> String {
+        return match (hit, self.cpu.jammed()) {
+            (Some(hit), _) => self.describe(hit),
+            (None, Some(jam)) => jam.to_string(),
+            (None, None) => format!("still running after {} instructions", MAX_INSTRUCTIONS),
+        };
+    }
+
+    fn describe(&self, hit: Hit) -> String {
+        return match hit {
+            Hit::Breakpoint(address) => format!("breakpoint at {}", self.label(address)),
+            Hit::Read { address, data } => {
+                format!("read ${:02X} from {}", data, self.label(address))
+            }
+            Hit::Write { address, data } => {
+                format!("wrote ${:02X} to {}", data, self.label(address))
+            }
+        };
+    }
+
+    /// Formats an address along with its symbol, if it has one.
+    fn label(&self, address: u16) -> String {
+        return match self.symbols.name(address) {
+            Some(name) => format!("${:04X} ({})", address, name),
+            None => format!("${:04X}", address),
+        };
+    }
+
+    fn parse_address(&self, word: Option<&str>) -> Result<u16, String> {
+        let word = word.ok_or("expected an address")?;
+        if let Some(hex) = word.strip_prefix('$') {
+            return u16::from_str_radix(hex, 16).map_err(|_| format!("bad address '{}'", word));
+        }
+        return self
+            .symbols
+            .address(word)
+            .ok_or_else(|| format!("unknown symbol '{}'", word));
+    }
+
+    /// Decodes the instruction at `address` and returns its bytes, its text and
+    /// its length.
+    fn instruction(&self, address: u16) -> (String, String, u16) {
+        let Some(opcode) = self.memory.peek(address) else {
+            return ("--".to_string(), String::new(), 1);
+        };
+        let variant = self.cpu.variant();
+        let len = opcode_info(variant, opcode).map_or(1, |info| info.bytes.max(1));
+        let operands: Vec<u8> = (1..u16::from(len))
+            .map(|i| self.memory.peek(address.wrapping_add(i)).unwrap_or(0))
+            .collect();
+        let bytes = std::iter::once(opcode)
+            .chain(operands.iter().copied())
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = disassemble(variant, address, opcode, &operands, Some(&self.symbols));
+        return (bytes, text, u16::from(len));
+    }
+
+    /// Finds where to start disassembling so that `count` instructions come
+    /// before `pc`. Code cannot be decoded backwards, so this tries each start
+    /// address before `pc`, furthest first, and keeps one which lines up.
+    fn start_before(&self, pc: u16, count: usize) -> u16 {
+        let mut best = (0, pc);
+        for distance in (1..=count as u16 * 3).rev() {
+            let mut starts = vec![];
+            let mut offset = 0;
+            while offset < distance {
+                starts.push(pc.wrapping_sub(distance - offset));
+                offset += self.instruction(pc.wrapping_sub(distance - offset)).2;
+            }
+            if offset != distance {
+                continue;
+            }
+            if starts.len() >= count {
+                return starts[starts.len() - count];
+            }
+            if starts.len() > best.0 {
+                best = (starts.len(), starts[0]);
+            }
+        }
+        return best.1;
+    }
+
+    fn disassembly(&self, rows: usize) -> Vec<String> {
+        let pc = self.cpu.registers.pc.get();
+        let mut address = self.start_before(pc, rows / 3);
+        let mut lines = vec![];
+        while lines.len() < rows {
+            if let Some(name) = self.symbols.name(address) {
+                lines.push(format!("{:18}{}:", "", name));
+            }
+            let (bytes, text, len) = self.instruction(address);
+            let current = if address == pc { '>' } else { ' ' };
+            let breakpoint = if self.debugger.breakpoints().any(|b| b == address) {
+                '*'
+            } else {
+                ' '
+            };
+            lines.push(format!(
+                "{}{} ${:04X}  {:<8}    {}",
+                current, breakpoint, address, bytes, text
+            ));
+            address = address.wrapping_add(len);
+        }
+        lines.truncate(rows);
+        return lines;
+    }
+
+    fn registers(&self) -> Vec<String> {
+        let registers = &self.cpu.registers;
+        let status = self.cpu.status.get_raw();
+        let flags: String = "NV-BDIZC"
+            .chars()
+            .enumerate()
+            .map(|(bit, flag)| match status & (0x80 >> bit) {
+                0 => '.',
+                _ => flag,
+            })
+            .collect();
+        return vec![
+            format!(
+                "A ${:02X}  X ${:02X}  Y ${:02X}",
+                registers.acc.get(),
+                registers.x.get(),
+                registers.y.get()
+            ),
+            format!(
+                "PC ${:04X}  SP ${:02X}",
+                registers.pc.get(),
+                registers.sp.get()
+            ),
+            format!("P  {} ${:02X}", flags, status),
+            format!("cycle {}", self.cpu.cycles()),
+        ];
+    }
+
+    /// Lists what has been pushed, the top of the stack first.
+    fn stack(&self, rows: usize) -> Vec<String> {
+        let top = 0x0100 + u16::from(self.cpu.registers.sp.get()) + 1;
+        if top > 0x01FF {
+            return vec!["empty".to_string()];
+        }
+        return (top..=0x01FF)
+            .take(rows)
+            .map(|address| match self.memory.peek(address) {
+                Some(byte) => format!("${:04X}  {:02x}", address, byte),
+                None => format!("${:04X}  --", address),
+            })
+            .collect();
+    }
+
+    /// Shows the bytes from each watched address on, as many as fit in `width`.
+    fn watches(&self, width: usize) -> Vec<String> {
+        return self
+            .debugger
+            .watchpoints()
+            .map(|(address, watch)| {
+                let kind = match watch {
+                    Watch::Read => "r",
+                    Watch::Write => "w",
+                    Watch::Access => "rw",
+                };
+                let mut line = format!("${:04X} {:<2} ", address, kind);
+                let mut offset = 0;
+                while line.len() + 3 <= width {
+                    match self.memory.peek(address.wrapping_add(offset)) {
+                        Some(byte) => line.push_str(&format!(" {:02x}", byte)),
+                        None => line.push_str(" --"),
+                    }
+                    offset += 1;
+                }
+                line
+            })
+            .collect();
+    }
+
+    /// Returns the last `rows` lines of the console.
+    fn console_lines(&self, rows: usize) -> Vec<String> {
+        let contents = self.console.contents();
+        let lines: Vec<String> = contents
+            .split('\n')
+            .map(|line| {
+                line.chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .collect()
+            })
+            .collect();
+        return lines[lines.len().saturating_sub(rows)..].to_vec();
+    }
+}
+
+/// Brings the devices up to the CPU's cycle and passes on their interrupts.
+fn sync(cpu: &mut Cpu, memory: &mut Memory) {
+    memory.set_cycle(cpu.cycles());
+    cpu.set_irq_line(memory.irq_asserted());
+}
+
+/// Returns how many lines fit inside a pane.
+fn rows(area: Rect) -> usize {
+    return usize::from(area.height.saturating_sub(2));
+}
+
+/// Draws a box with a title and the lines inside it.
+fn pane(title: &str, lines: Vec<String>, area: Rect, buf: &mut Buffer) {
+    let lines: Vec<Line> = lines.into_iter().map(Line::from).collect();
+    Paragraph::new(lines)
+        .block(Block::bordered().title(format!(" {} ", title)))
+        .render(area, buf);
+}
@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use system::device::{Device, Range};
+use system::{Bus, Memory};
+
+/// A minimal device that latches the last value written to it.
+#[derive(Default)]
+struct Latch {
+    value: u8,
+}
+
+impl Device for Latch {
+    fn read(&self, _: u16) -> u8 {
+        self.value
+    }
+
+    fn write(&mut self, _: u16, data: u8) {
+        self.value = data;
+    }
+}
+
+fn ram_only(c: &mut Criterion) {
+    let mut mem = Memory::new();
+    c.bench_function("ram_only", |b| {
+        b.iter(|| {
+            for address in 0x0000..0x8000u16 {
+                let value = mem.read(black_box(address));
+                mem.write(address, value.wrapping_add(1));
+            }
+        })
+    });
+}
+
+fn devices() -> Memory<'static> {
+    let mut mem = Memory::new();
+    for i in 0..16u16 {
+        let start = 0xA000 + i * 0x10;
+        mem.map(Range::from(start..start + 0x10), Latch::default())
+            .unwrap();
+    }
+    mem.map(Range::new(0xC000, 0xD000), Latch::default())
+        .unwrap();
+    mem
+}
+
+fn touch_devices(mem: &mut Memory) {
+    for i in 0..0x1000u16 {
+        let address = 0xA000 + (i & 0xFF);
+        let value = mem.read(black_box(address));
+        mem.write(address, value.wrapping_add(1));
+        let value = mem.read(black_box(0xC000 + i));
+        mem.write(0xC000 + i, value);
+    }
+}
+
+fn device_heavy(c: &mut Criterion) {
+    let mut mem = devices();
+    c.bench_function("device_heavy", |b| b.iter(|| touch_devices(&mut mem)));
+}
+
+/// The same accesses through the lock a machine shared between threads sits
+/// behind, taken once per batch like a front-end would once per frame.
+fn device_heavy_shared(c: &mut Criterion) {
+    let mem = Arc::new(Mutex::new(devices()));
+    c.bench_function("device_heavy_shared", |b| {
+        b.iter(|| touch_devices(&mut mem.lock().unwrap()))
+    });
+}
+
+/// Mapping many small devices, which only touches the pages they cover.
+fn map_many(c: &mut Criterion) {
+    c.bench_function("map_many", |b| {
+        b.iter(|| {
+            let mut mem = Memory::new();
+            for i in 0..256u16 {
+                let start = 0x8000 + i * 0x10;
+                mem.map(Range::from(start..start + 0x10), Latch::default())
+                    .unwrap();
+            }
+            black_box(mem)
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    ram_only,
+    device_heavy,
+    device_heavy_shared,
+    map_many
+);
+criterion_main!(benches);
@@ -0,0 +1,181 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use cpu::Bus;
+
+use crate::symbols::{HexFormatter, SymbolFormatter};
+use crate::Memory;
+
+/// A single recorded bus cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    pub cycle: u64,
+    pub address: u16,
+    pub data: u8,
+    pub write: bool,
+    /// Set for opcode fetches.
+    pub sync: bool,
+}
+
+impl Transaction {
+    /// Formats the transaction with addresses rendered by `symbols`.
+    pub fn format_with(&self, symbols: &dyn SymbolFormatter) -> String {
+        return format!(
+            "{:>10}  {}  {}  ${:02x}{}",
+            self.cycle,
+            if self.write { "W" } else { "R" },
+            symbols.format_address(self.address),
+            self.data,
+            if self.sync { "  SYNC" } else { "" }
+        );
+    }
+}
+
+impl std::fmt::Display for Transaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}", self.format_with(&HexFormatter));
+    }
+}
+
+/// A function called with each transaction as it happens.
+type Observer = Box<dyn FnMut(&Transaction) + Send>;
+
+/// A bus wrapper which keeps the last few transactions in a fixed-size ring.
+///
+/// Recording is cheap enough to leave on for a whole run, so the ring can be
+/// dumped after something goes wrong. Transactions are stamped with the clock
+/// set by [`BusLog::set_cycle`]; setting it from `Cpu::cycles` before every
+/// `step_cycle` gives exact cycle numbers.
+///
+/// Observers see every transaction as it happens, to spy on a device or compare
+/// two runs cycle by cycle.
+/// ```text
+///     let mut bus = BusLog::new(memory, 0);
+///     bus.add_observer(|t| {
+///         if StdoutDevice::RANGE.contains(t.address) {
+///             println!("{}", t);
+///         }
+///     });
+/// ```
+pub struct BusLog<B: Bus> {
+    bus: B,
+    capacity: usize,
+    cycle: Cell<u64>,
+    ring: RefCell<VecDeque<Transaction>>,
+    symbols: Box<dyn SymbolFormatter + Send>,
+    observers: RefCell<Vec<Observer>>,
+}
+
+impl<B: Bus> BusLog<B> {
+    pub fn new(bus: B, capacity: usize) -> Self {
+        Self {
+            bus,
+            capacity,
+            cycle: Cell::new(0),
+            ring: RefCell::new(VecDeque::with_capacity(capacity)),
+            symbols: Box::new(HexFormatter),
+            observers: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn set_cycle(&mut self, cycle: u64) {
+        self.cycle.set(cycle);
+    }
+
+    /// Returns the recorded transactions, oldest first.
+    pub fn transactions(&self) -> Vec<Transaction> {
+        return self.ring.borrow().iter().copied().collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.ring.borrow_mut().clear();
+    }
+
+    /// Calls `observer` with every transaction from now on, whether or not the
+    /// ring has room to keep it.
+    pub fn add_observer(&mut self, observer: impl FnMut(&Transaction) + Send + 'static) {
+        self.observers.get_mut().push(Box::new(observer));
+    }
+
+    pub fn clear_observers(&mut self) {
+        self.observers.get_mut().clear();
+    }
+
+    /// Sets the formatter used to render addresses in [`BusLog::dump`].
+    pub fn set_symbol_formatter(&mut self, symbols: impl SymbolFormatter + Send + 'static) {
+        self.symbols = Box::new(symbols);
+    }
+
+    /// Formats the recorded transactions one per line, oldest first.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for transaction in self.ring.borrow().iter() {
+            out.push_str(&transaction.format_with(self.symbols.as_ref()));
+            out.push('\n');
+        }
+        return out;
+    }
+
+    pub fn inner(&self) -> &B {
+        return &self.bus;
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        return &mut self.bus;
+    }
+
+    pub fn into_inner(self) -> B {
+        return self.bus;
+    }
+
+    //
+
+    fn record(&self, address: u16, data: u8, write: bool, sync: bool) {
+        let transaction = Transaction {
+            cycle: self.cycle.get(),
+            address,
+            data,
+            write,
+            sync,
+        };
+        for observer in self.observers.borrow_mut().iter_mut() {
+            observer(&transaction);
+        }
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut ring = self.ring.borrow_mut();
+        if ring.len() == self.capacity {
+            ring.pop_front();
+        }
+        ring.push_back(transaction);
+    }
+}
+
+impl<'a> BusLog<Memory<'a>> {
+    /// Sets the clock of both the log and the wrapped memory.
+    pub fn set_memory_cycle(&mut self, cycle: u64) {
+        self.set_cycle(cycle);
+        self.bus.set_cycle(cycle);
+    }
+}
+
+impl<B: Bus> Bus for BusLog<B> {
+    fn read(&self, address: u16) -> u8 {
+        let data = self.bus.read(address);
+        self.record(address, data, false, false);
+        return data;
+    }
+
+    fn fetch(&self, address: u16) -> u8 {
+        let data = self.bus.fetch(address);
+        self.record(address, data, false, true);
+        return data;
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.bus.write(address, data);
+        self.record(address, data, true, false);
+    }
+}
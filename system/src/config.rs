@@ -0,0 +1,366 @@
+use std::error::Error;
+
+use cpu::Cpu;
+
+use crate::device::{Device, RomDevice};
+use crate::{Memory, MemoryError, Range, Unmapped};
+
+/// A problem found while validating a [`MachineConfig`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A region covers no addresses.
+    EmptyRegion { name: String, range: Range },
+    /// Two regions claim the same addresses.
+    Overlap {
+        first: String,
+        second: String,
+        range: Range,
+    },
+    /// A ROM image does not fit in the region it is loaded into.
+    RomTooLarge {
+        name: String,
+        size: usize,
+        range: Range,
+    },
+    /// Nothing provides the reset vector at $FFFC-$FFFD.
+    MissingResetVector,
+    /// The reset vector is stored in RAM, but a region mapped over it is read
+    /// instead.
+    HiddenResetVector { name: String, range: Range },
+    /// A device cannot be mapped at its range, typically because the range is
+    /// too small for it.
+    Rejected { name: String, range: Range },
+    /// A device wants to raise interrupts but the IRQ line is not connected.
+    IrqNotConnected { name: String, range: Range },
+    /// An image does not fit in RAM.
+    Image(MemoryError),
+    /// More RAM than the address space holds.
+    RamTooLarge { size: usize },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::EmptyRegion { name, range } => {
+                write!(f, "{} has an empty range {}", name, fmt_range(range))
+            }
+            ConfigError::Overlap {
+                first,
+                second,
+                range,
+            } => write!(f, "{} overlaps {} at {}", second, first, fmt_range(range)),
+            ConfigError::RomTooLarge { name, size, range } => write!(
+                f,
+                "{} is {} bytes but its region {} only holds {}",
+                name,
+                size,
+                fmt_range(range),
+                range.len()
+            ),
+            ConfigError::Rejected { name, range } => {
+                write!(f, "{} cannot be mapped at {}", name, fmt_range(range))
+            }
+            ConfigError::MissingResetVector => {
+                write!(f, "no rom or reset vector covers ${:04x}", Cpu::RES_VECTOR)
+            }
+            ConfigError::HiddenResetVector { name, range } => write!(
+                f,
+                "the reset vector in ram at ${:04x} is hidden by {} at {}",
+                Cpu::RES_VECTOR,
+                name,
+                fmt_range(range)
+            ),
+            ConfigError::IrqNotConnected { name, range } => write!(
+                f,
+                "{} at {} raises interrupts but the irq line is not connected",
+                name,
+                fmt_range(range)
+            ),
+            ConfigError::Image(err) => write!(f, "image could not be loaded: {}", err),
+            ConfigError::RamTooLarge { size } => write!(
+                f,
+                "ram is {} bytes but the address space only holds {}",
+                size, 0x10000
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// A complete description of the memory map of a machine, which is validated as
+/// a whole before anything is built.
+/// ```text
+///     let mem = MachineConfig::new()
+///         .device("stdout", StdoutDevice::RANGE, StdoutDevice::new())
+///         .rom("program", Range::new(0x1000, 0x2000), program)
+///         .reset_vector(0x1000)
+///         .build()?;
+/// ```
+pub struct MachineConfig<'a> {
+    ram: usize,
+    unmapped: Unmapped,
+    devices: Vec<(String, Range, Box<dyn Device + 'a>)>,
+    roms: Vec<(String, Range, Vec<u8>)>,
+    /// Mirrored ranges and the range each reaches, or `None` for open bus.
+    mirrors: Vec<(Range, Option<Range>)>,
+    reset_vector: Option<u16>,
+    irq_connected: bool,
+}
+
+impl<'a> Default for MachineConfig<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> MachineConfig<'a> {
+    pub fn new() -> Self {
+        Self {
+            ram: 0x10000,
+            unmapped: Unmapped::OpenBus,
+            devices: vec![],
+            roms: vec![],
+            mirrors: vec![],
+            reset_vector: None,
+            irq_connected: true,
+        }
+    }
+
+    /// Sets the size of the RAM at $0000 and what the addresses above it do,
+    /// instead of RAM filling the whole address space.
+    pub fn ram(mut self, size: usize, unmapped: Unmapped) -> Self {
+        self.ram = size;
+        self.unmapped = unmapped;
+        self
+    }
+
+    /// Adds a device mapped at `range`.
+    pub fn device(self, name: &str, range: Range, device: impl Device + 'a) -> Self {
+        return self.device_boxed(name, range, Box::new(device));
+    }
+
+    /// Like [`MachineConfig::device`], for a device which is already boxed.
+    pub fn device_boxed(mut self, name: &str, range: Range, device: Box<dyn Device + 'a>) -> Self {
+        self.devices.push((name.to_string(), range, device));
+        self
+    }
+
+    /// Adds a write-protected ROM at `range` whose image is loaded at its start.
+    pub fn rom(mut self, name: &str, range: Range, image: Vec<u8>) -> Self {
+        self.roms.push((name.to_string(), range, image));
+        self
+    }
+
+    /// Mirrors `to` at `range`, see [`Memory::mirror`].
+    pub fn mirror(mut self, range: Range, to: Range) -> Self {
+        self.mirrors.push((range, Some(to)));
+        self
+    }
+
+    /// Leaves `range` unconnected, see [`Memory::open_bus`].
+    pub fn open_bus(mut self, range: Range) -> Self {
+        self.mirrors.push((range, None));
+        self
+    }
+
+    /// Sets the reset vector explicitly instead of taking it from a ROM.
+    pub fn reset_vector(mut self, address: u16) -> Self {
+        self.reset_vector = Some(address);
+        self
+    }
+
+    /// Sets whether device interrupts reach the CPU.
+    pub fn irq_connected(mut self, connected: bool) -> Self {
+        self.irq_connected = connected;
+        self
+    }
+
+    /// Checks the whole configuration and returns every problem found.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+        if self.ram > 0x10000 {
+            errors.push(ConfigError::RamTooLarge { size: self.ram });
+        }
+
+        let mut regions: Vec<(String, Range)> = vec![];
+        for (name, range, _) in self.devices.iter() {
+            regions.push((name.clone(), *range));
+        }
+        for (name, range, _) in self.roms.iter() {
+            regions.push((name.clone(), *range));
+        }
+        for (range, to) in self.mirrors.iter() {
+            regions.push((mirror_name(to), *range));
+        }
+
+        for (i, (name, range)) in regions.iter().enumerate() {
+            if range.is_empty() {
+                errors.push(ConfigError::EmptyRegion {
+                    name: name.to_string(),
+                    range: *range,
+                });
+                continue;
+            }
+            if range.end > Range::TOP {
+                errors.push(ConfigError::Rejected {
+                    name: name.to_string(),
+                    range: *range,
+                });
+                continue;
+            }
+
+            for (other, other_range) in regions[..i].iter() {
+                let start = range.start.max(other_range.start);
+                let end = range.end.min(other_range.end);
+                if u32::from(start) < end {
+                    errors.push(ConfigError::Overlap {
+                        first: other.to_string(),
+                        second: name.to_string(),
+                        range: Range::new(start, end),
+                    });
+                }
+            }
+        }
+
+        // a mirror can only reach addresses which are decoded as themselves
+        for (range, to) in self.mirrors.iter() {
+            let Some(to) = to else {
+                continue;
+            };
+            if to.is_empty()
+                || to.end > Range::TOP
+                || self.mirrors.iter().any(|(other, _)| other.overlaps(to))
+            {
+                errors.push(ConfigError::Rejected {
+                    name: mirror_name(&Some(*to)),
+                    range: *range,
+                });
+            }
+        }
+
+        for (name, range, image) in self.roms.iter() {
+            if image.len() > range.len() {
+                errors.push(ConfigError::RomTooLarge {
+                    name: name.clone(),
+                    size: image.len(),
+                    range: *range,
+                });
+            }
+        }
+
+        let vector_in_rom = self.roms.iter().any(|(_, range, image)| {
+            let offset = usize::from(Cpu::RES_VECTOR.wrapping_sub(range.start));
+            range.contains(Cpu::RES_VECTOR) && offset + 2 <= image.len()
+        });
+        // an explicit vector is patched into the rom over it, or stored in ram
+        let rom_over_vector = self
+            .roms
+            .iter()
+            .any(|(_, range, _)| range.contains(Cpu::RES_VECTOR));
+        let vector_provided = match self.reset_vector {
+            Some(_) => rom_over_vector || usize::from(Cpu::RES_VECTOR) + 2 <= self.ram,
+            None => vector_in_rom,
+        };
+        if !vector_provided {
+            errors.push(ConfigError::MissingResetVector);
+        }
+        // a vector stored in ram is only read if nothing is mapped over it
+        if self.ram_reset_vector().is_some() && vector_provided {
+            let vector = Range::new(Cpu::RES_VECTOR, u32::from(Cpu::RES_VECTOR) + 2);
+            let hiding = regions.iter().find(|(_, range)| range.overlaps(&vector));
+            if let Some((name, range)) = hiding {
+                errors.push(ConfigError::HiddenResetVector {
+                    name: name.clone(),
+                    range: *range,
+                });
+            }
+        }
+
+        if !self.irq_connected {
+            for (name, range, device) in self.devices.iter() {
+                if device.uses_irq() {
+                    errors.push(ConfigError::IrqNotConnected {
+                        name: name.clone(),
+                        range: *range,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        return Err(errors);
+    }
+
+    /// Returns the explicit reset vector if it is stored in RAM, rather than
+    /// patched into a ROM over it.
+    pub(crate) fn ram_reset_vector(&self) -> Option<u16> {
+        let rom_over_vector = self
+            .roms
+            .iter()
+            .any(|(_, range, _)| range.contains(Cpu::RES_VECTOR));
+        return self.reset_vector.filter(|_| !rom_over_vector);
+    }
+
+    /// Validates the configuration and builds the memory map.
+    pub fn build(self) -> Result<Memory<'a>, Vec<ConfigError>> {
+        self.validate()?;
+
+        // validation has ruled out every way the memory map can reject these
+        let mapped = "validated region was rejected";
+        let mut mem = Memory::with_ram(self.ram, self.unmapped);
+        if let Some(vector) = self.ram_reset_vector() {
+            mem.load(Cpu::RES_VECTOR, &vector.to_le_bytes())
+                .expect(mapped);
+        }
+        let vector = self.reset_vector.map(u16::to_le_bytes);
+        for (_, range, mut image) in self.roms {
+            // an explicit reset vector takes precedence over the image
+            if let Some(vector) = vector.filter(|_| range.contains(Cpu::RES_VECTOR)) {
+                let offset = usize::from(Cpu::RES_VECTOR - range.start);
+                if image.len() < offset + 2 {
+                    image.resize(offset + 2, 0);
+                }
+                image[offset..offset + 2].copy_from_slice(&vector);
+            }
+            mem.map(range, RomDevice::new(&image)).expect(mapped);
+        }
+
+        // only the devices themselves know which ranges they can be mapped at
+        let mut errors = vec![];
+        for (name, range, device) in self.devices {
+            match mem.map_boxed(range, device) {
+                Ok(()) => {}
+                Err(MemoryError::Rejected { range }) => {
+                    errors.push(ConfigError::Rejected { name, range })
+                }
+                Err(err) => panic!("{}: {}", mapped, err),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        for (range, to) in self.mirrors {
+            match to {
+                Some(to) => mem.mirror(range, to).expect(mapped),
+                None => mem.open_bus(range).expect(mapped),
+            }
+        }
+        return Ok(mem);
+    }
+}
+
+/// Returns the name a mirror or open bus range is reported by.
+fn mirror_name(to: &Option<Range>) -> String {
+    return match to {
+        Some(to) => format!("mirror of {}", fmt_range(to)),
+        None => "open bus".to_string(),
+    };
+}
+
+fn fmt_range(range: &Range) -> String {
+    return format!("${:04x}-${:04x}", range.start, range.end);
+}
@@ -0,0 +1,349 @@
+use std::cell::Cell;
+#[cfg(feature = "host")]
+use std::io::{BufReader, Read, Write};
+#[cfg(feature = "host")]
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(feature = "host")]
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+#[cfg(feature = "host")]
+use std::thread;
+
+use cpu::{StateError, StateReader, StateWriter};
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// The other end of the serial line of an [`AciaDevice`].
+pub trait SerialBackend: Send {
+    /// Returns the next byte received, if one has arrived. Must not block.
+    fn receive(&mut self) -> Option<u8>;
+    /// Sends a byte.
+    fn send(&mut self, byte: u8);
+}
+
+/// A serial port modeled on the 6551 ACIA.
+///
+/// The guest sees four registers:
+/// ```text
+///     +0  DATA     (read/write) read takes the received byte, write sends a byte
+///     +1  STATUS   (read)       bit 7 = irq, bit 4 = tx empty, bit 3 = rx full;
+///                               reading clears bit 7
+///                  (write)      programmed reset
+///     +2  COMMAND  (read/write) bit 4 = echo, bits 3-2 = transmitter control,
+///                               bit 1 = rx irq disabled, bit 0 = dtr
+///     +3  CONTROL  (read/write) stop bits, word length and baud rate
+/// ```
+/// The transmitter is off while bits 3-2 of the command register are clear and
+/// the receiver is off while DTR is clear, which is the state after a reset.
+/// Bytes are sent as soon as they are written, so the transmitter is always
+/// empty, and the backend is polled for received bytes every
+/// [`AciaDevice::POLL_CYCLES`] cycles while the receive register is empty, so
+/// there are no overruns. Parity, framing and the baud rate are stored but
+/// otherwise ignored.
+pub struct AciaDevice<B: SerialBackend> {
+    backend: B,
+    data: Cell<u8>,
+    rx_full: Cell<bool>,
+    irq: Cell<bool>,
+    command: u8,
+    control: u8,
+    since_poll: u64,
+}
+
+impl<B: SerialBackend> AciaDevice<B> {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA040,
+        end: 0xA044,
+    };
+
+    /// The cycles between polls of the backend.
+    pub const POLL_CYCLES: u64 = 256;
+
+    const STATE_MAGIC: &'static [u8; 4] = b"6551";
+    const STATE_VERSION: u8 = 1;
+
+    const REG_DATA: u16 = 0;
+    const REG_STATUS: u16 = 1;
+    const REG_COMMAND: u16 = 2;
+    const REG_CONTROL: u16 = 3;
+
+    const STATUS_IRQ: u8 = 0x80;
+    const STATUS_TX_EMPTY: u8 = 0x10;
+    const STATUS_RX_FULL: u8 = 0x08;
+
+    const COMMAND_ECHO: u8 = 0x10;
+    const COMMAND_TX_CONTROL: u8 = 0x0C;
+    const COMMAND_TX_IRQ: u8 = 0x04;
+    const COMMAND_RX_IRQ_DISABLED: u8 = 0x02;
+    const COMMAND_DTR: u8 = 0x01;
+
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            data: Cell::new(0),
+            rx_full: Cell::new(false),
+            irq: Cell::new(false),
+            command: Self::COMMAND_RX_IRQ_DISABLED,
+            control: 0,
+            since_poll: 0,
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        return &self.backend;
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        return &mut self.backend;
+    }
+
+    /// Takes a byte from the backend if the receiver is on and has room for it.
+    fn poll(&mut self) {
+        if self.command & Self::COMMAND_DTR == 0 || self.rx_full.get() {
+            return;
+        }
+        let Some(byte) = self.backend.receive() else {
+            return;
+        };
+
+        self.data.set(byte);
+        self.rx_full.set(true);
+        if self.command & Self::COMMAND_RX_IRQ_DISABLED == 0 {
+            self.irq.set(true);
+        }
+        let echo = self.command & (Self::COMMAND_ECHO | Self::COMMAND_TX_CONTROL);
+        if echo == Self::COMMAND_ECHO {
+            self.backend.send(byte);
+        }
+    }
+
+    /// Raises an interrupt if the transmitter interrupt is enabled, which it is
+    /// whenever the (always empty) transmitter could take a byte.
+    fn tx_empty(&mut self) {
+        let tx_irq = self.command & Self::COMMAND_TX_CONTROL == Self::COMMAND_TX_IRQ;
+        if tx_irq && self.command & Self::COMMAND_DTR != 0 {
+            self.irq.set(true);
+        }
+    }
+}
+
+impl<B: SerialBackend> Device for AciaDevice<B> {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() >= 4;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        match offset {
+            Self::REG_DATA => {
+                self.rx_full.set(false);
+                self.data.get()
+            }
+            Self::REG_STATUS => {
+                let mut status = Self::STATUS_TX_EMPTY;
+                if self.irq.replace(false) {
+                    status |= Self::STATUS_IRQ;
+                }
+                if self.rx_full.get() {
+                    status |= Self::STATUS_RX_FULL;
+                }
+                status
+            }
+            Self::REG_COMMAND => self.command,
+            Self::REG_CONTROL => self.control,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        match offset {
+            Self::REG_DATA if self.command & Self::COMMAND_TX_CONTROL != 0 => {
+                self.backend.send(data);
+                self.tx_empty();
+            }
+            Self::REG_STATUS => {
+                // programmed reset
+                self.command = (self.command & 0xE0) | Self::COMMAND_RX_IRQ_DISABLED;
+            }
+            Self::REG_COMMAND => {
+                self.command = data;
+                self.tx_empty();
+            }
+            Self::REG_CONTROL => self.control = data,
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self, _kind: ResetKind) {
+        // a reset of the machine pulls the reset pin of the ACIA either way
+        self.command = Self::COMMAND_RX_IRQ_DISABLED;
+        self.control = 0;
+        self.rx_full.set(false);
+        self.irq.set(false);
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.since_poll += cycles;
+        if self.since_poll >= Self::POLL_CYCLES {
+            self.since_poll = 0;
+            self.poll();
+        }
+    }
+
+    fn uses_irq(&self) -> bool {
+        true
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq.get()
+    }
+
+    /// Saves the registers. The backend is not saved.
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(Self::STATE_MAGIC, Self::STATE_VERSION);
+        state.u8(self.data.get());
+        state.bool(self.rx_full.get());
+        state.bool(self.irq.get());
+        state.u8(self.command);
+        state.u8(self.control);
+        state.u64(self.since_poll);
+        state.finish()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (mut state, _) = StateReader::new(state, Self::STATE_MAGIC, Self::STATE_VERSION)?;
+        let data = state.u8()?;
+        let rx_full = state.bool()?;
+        let irq = state.bool()?;
+        let command = state.u8()?;
+        let control = state.u8()?;
+        let since_poll = state.u64()?;
+        state.finish()?;
+
+        self.data.set(data);
+        self.rx_full.set(rx_full);
+        self.irq.set(irq);
+        self.command = command;
+        self.control = control;
+        self.since_poll = since_poll;
+        Ok(())
+    }
+}
+
+//
+
+#[cfg(feature = "host")]
+/// Reads from `reader` on a background thread so that receiving never blocks.
+fn spawn_reader(reader: impl Read + Send + 'static) -> Receiver<u8> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for byte in BufReader::new(reader).bytes() {
+            match byte {
+                Ok(byte) if tx.send(byte).is_ok() => {}
+                _ => return,
+            }
+        }
+    });
+    return rx;
+}
+
+#[cfg(feature = "host")]
+/// A serial line to the host's terminal.
+pub struct StdioBackend {
+    input: Receiver<u8>,
+}
+
+#[cfg(feature = "host")]
+impl Default for StdioBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "host")]
+impl StdioBackend {
+    pub fn new() -> Self {
+        Self {
+            input: spawn_reader(std::io::stdin()),
+        }
+    }
+}
+
+#[cfg(feature = "host")]
+impl SerialBackend for StdioBackend {
+    fn receive(&mut self) -> Option<u8> {
+        return self.input.try_recv().ok();
+    }
+
+    fn send(&mut self, byte: u8) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(&[byte]).and_then(|_| stdout.flush());
+    }
+}
+
+#[cfg(feature = "host")]
+/// A serial line over a TCP connection, e.g. to a telnet client.
+///
+/// Once the connection is closed received bytes stop arriving and sent bytes
+/// are dropped.
+pub struct TcpBackend {
+    stream: TcpStream,
+    input: Receiver<u8>,
+}
+
+#[cfg(feature = "host")]
+impl TcpBackend {
+    /// Returns a backend talking over `stream`.
+    pub fn new(stream: TcpStream) -> std::io::Result<Self> {
+        let input = spawn_reader(stream.try_clone()?);
+        return Ok(Self { stream, input });
+    }
+
+    /// Connects to `address`.
+    pub fn connect(address: impl ToSocketAddrs) -> std::io::Result<Self> {
+        return Self::new(TcpStream::connect(address)?);
+    }
+
+    /// Listens on `address` and waits for a single client to connect.
+    pub fn accept(address: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let (stream, _) = TcpListener::bind(address)?.accept()?;
+        return Self::new(stream);
+    }
+}
+
+#[cfg(feature = "host")]
+impl SerialBackend for TcpBackend {
+    fn receive(&mut self) -> Option<u8> {
+        return self.input.try_recv().ok();
+    }
+
+    fn send(&mut self, byte: u8) {
+        let _ = self.stream.write_all(&[byte]);
+    }
+}
+
+/// A serial line over in-memory channels, for tests and for connecting to other
+/// code in the same process.
+pub struct ChannelBackend {
+    rx: Receiver<u8>,
+    tx: Sender<u8>,
+}
+
+impl ChannelBackend {
+    /// Returns a backend which receives the bytes sent over `rx` and sends bytes
+    /// over `tx`.
+    pub fn new(rx: Receiver<u8>, tx: Sender<u8>) -> Self {
+        Self { rx, tx }
+    }
+}
+
+impl SerialBackend for ChannelBackend {
+    fn receive(&mut self) -> Option<u8> {
+        return self.rx.try_recv().ok();
+    }
+
+    fn send(&mut self, byte: u8) {
+        let _ = self.tx.send(byte);
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// A one-bit speaker like the Apple II's, which the guest plays square waves on
+/// by flipping the speaker line at the right times.
+///
+/// The guest sees one register:
+/// ```text
+///     +0  SPEAKER  (read/write)  any access flips the speaker line; reads 0
+/// ```
+/// Flips are timed by the cycles the device is ticked with, so they land on the
+/// instruction they were made in. The host turns them into audio with the
+/// [`Speaker`] handle returned alongside the device.
+pub struct BeeperDevice {
+    speaker: Speaker,
+}
+
+impl BeeperDevice {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA080,
+        end: 0xA081,
+    };
+
+    /// Returns a new device, along with the handle the host pulls samples from.
+    pub fn new() -> (Self, Speaker) {
+        let speaker = Speaker(Arc::new(Mutex::new(SpeakerState {
+            cycle: 0,
+            flips: VecDeque::new(),
+            level: false,
+            cursor: 0.0,
+            cursor_level: false,
+        })));
+        let device = Self {
+            speaker: speaker.clone(),
+        };
+        (device, speaker)
+    }
+}
+
+impl Device for BeeperDevice {
+    fn read(&self, _offset: u16) -> u8 {
+        self.speaker.0.lock().unwrap().flip();
+        return 0;
+    }
+
+    fn write(&mut self, _offset: u16, _data: u8) {
+        self.speaker.0.lock().unwrap().flip();
+    }
+
+    fn peek(&self, _offset: u16) -> Option<u8> {
+        return Some(0);
+    }
+
+    fn reset(&mut self, _kind: ResetKind) {
+        let mut state = self.speaker.0.lock().unwrap();
+        if state.level {
+            state.flip();
+        }
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.speaker.0.lock().unwrap().cycle += cycles;
+    }
+}
+
+/// A host-side handle to the speaker line of a [`BeeperDevice`], which turns
+/// the guest's flips into samples for an audio callback.
+/// ```text
+///     let (beeper, speaker) = BeeperDevice::new();
+///     // in the audio callback
+///     let written = speaker.fill(&mut buffer, 44_100, system.frequency());
+///     buffer[written..].fill(0.0);
+/// ```
+#[derive(Clone)]
+pub struct Speaker(Arc<Mutex<SpeakerState>>);
+
+struct SpeakerState {
+    /// The cycles the device has been ticked by.
+    cycle: u64,
+    /// The cycles the line flipped at which have not been sampled yet.
+    flips: VecDeque<u64>,
+    /// The level of the line now.
+    level: bool,
+    /// The cycle samples have been taken up to, and the level of the line then.
+    cursor: f64,
+    cursor_level: bool,
+}
+
+impl SpeakerState {
+    fn flip(&mut self) {
+        self.level = !self.level;
+        self.flips.push_back(self.cycle);
+        if self.flips.len() > Speaker::MAX_FLIPS {
+            // nobody is listening, so the oldest flips are forgotten
+            let cycle = self.flips.pop_front().unwrap();
+            self.cursor = self.cursor.max(cycle as f64);
+            self.cursor_level = !self.cursor_level;
+        }
+    }
+}
+
+impl Speaker {
+    /// The flips kept for samples which have not been pulled.
+    pub const MAX_FLIPS: usize = 1 << 16;
+
+    /// Returns whether the speaker line is high.
+    pub fn level(&self) -> bool {
+        return self.0.lock().unwrap().level;
+    }
+
+    /// Fills `buffer` with samples at `sample_rate`, for a CPU clocked at
+    /// `frequency` Hz, and returns how many were written. Each sample is the
+    /// share of the cycles it covers that the line was high, so a silent
+    /// speaker, whose line rests low after a reset, gives 0.0. Samples are
+    /// only taken for cycles which have run, so fewer are written when
+    /// emulation falls behind the audio device.
+    pub fn fill(&self, buffer: &mut [f32], sample_rate: u32, frequency: u64) -> usize {
+        assert!(sample_rate > 0, "sample rate must not be zero");
+        assert!(frequency > 0, "clock rate must not be zero");
+        let mut state = self.0.lock().unwrap();
+        let step = frequency as f64 / f64::from(sample_rate);
+
+        for (i, sample) in buffer.iter_mut().enumerate() {
+            let start = state.cursor;
+            let end = start + step;
+            if end > state.cycle as f64 {
+                return i;
+            }
+
+            // the time spent high over the sample
+            let mut high = 0.0;
+            let mut from = start;
+            while let Some(&flip) = state.flips.front() {
+                let at = (flip as f64).max(start);
+                if at >= end {
+                    break;
+                }
+                if state.cursor_level {
+                    high += at - from;
+                }
+                from = at;
+                state.cursor_level = !state.cursor_level;
+                state.flips.pop_front();
+            }
+            if state.cursor_level {
+                high += end - from;
+            }
+
+            *sample = (high / step) as f32;
+            state.cursor = end;
+        }
+        return buffer.len();
+    }
+}
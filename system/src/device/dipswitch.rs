@@ -0,0 +1,136 @@
+use std::sync::{Arc, Mutex};
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// A host-controlled bank of eight DIP switches.
+///
+/// The guest sees two registers:
+/// ```text
+///     +0  SWITCHES  (read-only)  current switch positions
+///     +1  CONTROL   (read/write) bit 0 = IRQ enable, bit 7 = changed
+/// ```
+/// Writing to `CONTROL` updates the IRQ enable bit. Writing a value with bit 7
+/// set acknowledges a change and releases the IRQ line.
+pub struct DipSwitchDevice {
+    switches: DipSwitches,
+}
+
+impl DipSwitchDevice {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA010,
+        end: 0xA012,
+    };
+
+    const REG_SWITCHES: u16 = 0;
+    const REG_CONTROL: u16 = 1;
+
+    const CONTROL_IRQ_ENABLE: u8 = 0x01;
+    const CONTROL_CHANGED: u8 = 0x80;
+
+    /// Returns a new device with the switches set to `value`, along with a handle
+    /// that the host can use to change them after the device is registered.
+    pub fn new(value: u8) -> (Self, DipSwitches) {
+        let switches = DipSwitches::new(value);
+        let device = Self {
+            switches: switches.clone(),
+        };
+        (device, switches)
+    }
+}
+
+impl Device for DipSwitchDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() >= 2;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        let state = self.switches.0.lock().unwrap();
+        match offset {
+            Self::REG_SWITCHES => state.value,
+            Self::REG_CONTROL => {
+                let mut control = 0;
+                if state.irq_enable {
+                    control |= Self::CONTROL_IRQ_ENABLE;
+                }
+                if state.changed {
+                    control |= Self::CONTROL_CHANGED;
+                }
+                control
+            }
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        if offset != Self::REG_CONTROL {
+            // the switches can only be changed by the host
+            return;
+        }
+
+        let mut state = self.switches.0.lock().unwrap();
+        state.irq_enable = (data & Self::CONTROL_IRQ_ENABLE) != 0;
+        if (data & Self::CONTROL_CHANGED) != 0 {
+            state.changed = false;
+        }
+    }
+
+    fn reset(&mut self, _kind: ResetKind) {
+        // the switch positions survive any reset
+        let mut state = self.switches.0.lock().unwrap();
+        state.irq_enable = false;
+        state.changed = false;
+    }
+
+    fn uses_irq(&self) -> bool {
+        return true;
+    }
+
+    fn irq_asserted(&self) -> bool {
+        let state = self.switches.0.lock().unwrap();
+        return state.irq_enable && state.changed;
+    }
+}
+
+/// A host-side handle to the switches of a [`DipSwitchDevice`].
+#[derive(Clone)]
+pub struct DipSwitches(Arc<Mutex<DipSwitchState>>);
+
+struct DipSwitchState {
+    value: u8,
+    irq_enable: bool,
+    changed: bool,
+}
+
+impl DipSwitches {
+    fn new(value: u8) -> Self {
+        Self(Arc::new(Mutex::new(DipSwitchState {
+            value,
+            irq_enable: false,
+            changed: false,
+        })))
+    }
+
+    /// Returns the current switch positions.
+    pub fn get(&self) -> u8 {
+        return self.0.lock().unwrap().value;
+    }
+
+    /// Sets all eight switches at once.
+    pub fn set(&self, value: u8) {
+        let mut state = self.0.lock().unwrap();
+        if state.value != value {
+            state.value = value;
+            state.changed = true;
+        }
+    }
+
+    /// Flips a single switch (0-7) on or off.
+    pub fn set_switch(&self, index: u8, on: bool) {
+        assert!(index < 8);
+        let value = self.get();
+        let mask = 1 << index;
+        self.set(if on { value | mask } else { value & !mask });
+    }
+}
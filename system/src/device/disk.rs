@@ -0,0 +1,148 @@
+#[cfg(feature = "host")]
+use std::fs::OpenOptions;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "host")]
+use std::path::Path;
+
+use cpu::Bus;
+
+use crate::device::Device;
+use crate::Range;
+
+/// The storage behind a [`DiskDevice`].
+trait Backing: Read + Write + Seek + Send {}
+
+impl<T: Read + Write + Seek + Send> Backing for T {}
+
+/// A disk of 256-byte sectors which are copied to and from memory by DMA.
+///
+/// The guest sees five registers:
+/// ```text
+///     +0  SECTOR   (read/write) the sector number, little endian
+///     +2  BUFFER   (read/write) the address of the buffer, little endian
+///     +4  COMMAND  (write-only) 1 = read the sector into the buffer,
+///                               2 = write the buffer to the sector
+///         STATUS   (read-only)  bit 0 = the last command failed
+/// ```
+/// A command completes before the write which issued it, without taking any
+/// cycles. Commands fail for sectors past the end of the image, and also when
+/// the device is written to directly rather than through a memory map, since
+/// it then has no bus to transfer over.
+pub struct DiskDevice {
+    image: Box<dyn Backing>,
+    sectors: u64,
+    sector: u16,
+    buffer: u16,
+    status: u8,
+}
+
+impl DiskDevice {
+    pub const SECTOR_SIZE: usize = 256;
+
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA050,
+        end: 0xA055,
+    };
+
+    const REG_SECTOR_LO: u16 = 0;
+    const REG_SECTOR_HI: u16 = 1;
+    const REG_BUFFER_LO: u16 = 2;
+    const REG_BUFFER_HI: u16 = 3;
+    const REG_COMMAND: u16 = 4;
+
+    const COMMAND_READ: u8 = 1;
+    const COMMAND_WRITE: u8 = 2;
+
+    const STATUS_ERROR: u8 = 0x01;
+
+    /// Returns a disk backed by `image`. Only whole sectors are accessible.
+    pub fn new(mut image: impl Read + Write + Seek + Send + 'static) -> std::io::Result<Self> {
+        let len = image.seek(SeekFrom::End(0))?;
+        return Ok(Self {
+            image: Box::new(image),
+            sectors: len / Self::SECTOR_SIZE as u64,
+            sector: 0,
+            buffer: 0,
+            status: 0,
+        });
+    }
+
+    /// Returns a disk backed by the image file at `path`, which is written to
+    /// by the write command.
+    #[cfg(feature = "host")]
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        return Self::new(file);
+    }
+
+    /// Returns the number of sectors on the disk.
+    pub fn sectors(&self) -> u64 {
+        return self.sectors;
+    }
+
+    fn execute(&mut self, command: u8, bus: &mut dyn Bus) -> std::io::Result<()> {
+        if u64::from(self.sector) >= self.sectors {
+            return Err(Error::new(ErrorKind::InvalidInput, "no such sector"));
+        }
+        let offset = u64::from(self.sector) * Self::SECTOR_SIZE as u64;
+        self.image.seek(SeekFrom::Start(offset))?;
+
+        let mut data = [0u8; Self::SECTOR_SIZE];
+        match command {
+            Self::COMMAND_READ => {
+                self.image.read_exact(&mut data)?;
+                for (i, byte) in data.iter().enumerate() {
+                    bus.write(self.buffer.wrapping_add(i as u16), *byte);
+                }
+            }
+            Self::COMMAND_WRITE => {
+                for (i, byte) in data.iter_mut().enumerate() {
+                    *byte = bus.read(self.buffer.wrapping_add(i as u16));
+                }
+                self.image.write_all(&data)?;
+                self.image.flush()?;
+            }
+            _ => return Err(Error::new(ErrorKind::InvalidInput, "unknown command")),
+        }
+        return Ok(());
+    }
+}
+
+impl Device for DiskDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() >= 5;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        match offset {
+            Self::REG_SECTOR_LO => self.sector.to_le_bytes()[0],
+            Self::REG_SECTOR_HI => self.sector.to_le_bytes()[1],
+            Self::REG_BUFFER_LO => self.buffer.to_le_bytes()[0],
+            Self::REG_BUFFER_HI => self.buffer.to_le_bytes()[1],
+            Self::REG_COMMAND => self.status,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        match offset {
+            Self::REG_SECTOR_LO => self.sector = (self.sector & 0xFF00) | u16::from(data),
+            Self::REG_SECTOR_HI => self.sector = (self.sector & 0x00FF) | u16::from(data) << 8,
+            Self::REG_BUFFER_LO => self.buffer = (self.buffer & 0xFF00) | u16::from(data),
+            Self::REG_BUFFER_HI => self.buffer = (self.buffer & 0x00FF) | u16::from(data) << 8,
+            Self::REG_COMMAND => self.status = Self::STATUS_ERROR,
+            _ => {}
+        }
+    }
+
+    fn write_with_bus(&mut self, offset: u16, data: u8, bus: &mut dyn Bus) {
+        if offset != Self::REG_COMMAND {
+            return self.write(offset, data);
+        }
+        self.status = match self.execute(data, bus) {
+            Ok(()) => 0,
+            Err(_) => Self::STATUS_ERROR,
+        };
+    }
+}
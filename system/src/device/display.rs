@@ -0,0 +1,151 @@
+use std::io::Write;
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// A 40x25 text screen drawn on the host's terminal.
+///
+/// The guest sees the screen as one byte per character, row by row, followed by
+/// a control register:
+/// ```text
+///     +0    CHARS    (read/write) 40 * 25 characters, printable ascii is shown
+///                                 and anything else is drawn as a space
+///     +1000 REFRESH  (write-only) any write draws the screen now
+/// ```
+/// The screen is drawn with ANSI cursor positioning, and only if it has changed
+/// since it was last drawn. Besides the guest asking for it, the host can draw
+/// it with [`TextDisplayDevice::render`] or have it drawn every so many cycles
+/// with [`TextDisplayDevice::refresh_every`].
+pub struct TextDisplayDevice {
+    chars: Vec<u8>,
+    dirty: bool,
+    refresh: Option<u64>,
+    since_refresh: u64,
+    output: Box<dyn Write + Send>,
+}
+
+#[cfg(feature = "host")]
+impl Default for TextDisplayDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextDisplayDevice {
+    pub const COLUMNS: usize = 40;
+    pub const ROWS: usize = 25;
+
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0x8000,
+        end: 0x8000 + Self::REG_REFRESH as u32 + 1,
+    };
+
+    const REG_REFRESH: u16 = (Self::COLUMNS * Self::ROWS) as u16;
+
+    /// Returns a display which draws to the host's stdout.
+    #[cfg(feature = "host")]
+    pub fn new() -> Self {
+        Self::with_output(std::io::stdout())
+    }
+
+    /// Returns a display which draws to `output`.
+    pub fn with_output(output: impl Write + Send + 'static) -> Self {
+        Self {
+            chars: vec![b' '; Self::COLUMNS * Self::ROWS],
+            dirty: true,
+            refresh: None,
+            since_refresh: 0,
+            output: Box::new(output),
+        }
+    }
+
+    /// Draws the screen every `cycles` cycles, e.g. 16_667 for 60 Hz at 1 MHz.
+    pub fn refresh_every(mut self, cycles: u64) -> Self {
+        assert!(cycles > 0, "refresh interval must not be zero");
+        self.refresh = Some(cycles);
+        self
+    }
+
+    /// Returns the screen as rows of text, with trailing spaces removed.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for row in self.chars.chunks(Self::COLUMNS) {
+            let row = row.iter().map(|c| printable(*c)).collect::<String>();
+            text += row.trim_end();
+            text += "\n";
+        }
+        return text;
+    }
+
+    /// Draws the screen if it has changed since it was last drawn.
+    pub fn render(&mut self) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut frame = String::new();
+        for (index, row) in self.chars.chunks(Self::COLUMNS).enumerate() {
+            frame += &format!("\x1b[{};1H", index + 1);
+            frame.extend(row.iter().map(|c| printable(*c)));
+        }
+        self.output.write_all(frame.as_bytes())?;
+        self.output.flush()?;
+        self.dirty = false;
+        return Ok(());
+    }
+}
+
+fn printable(c: u8) -> char {
+    if c.is_ascii_graphic() {
+        return c as char;
+    }
+    return ' ';
+}
+
+impl Device for TextDisplayDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() > usize::from(Self::REG_REFRESH);
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        return self.chars.get(usize::from(offset)).copied().unwrap_or(0);
+    }
+
+    fn peek(&self, offset: u16) -> Option<u8> {
+        return self.chars.get(usize::from(offset)).copied();
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        match offset {
+            Self::REG_REFRESH => {
+                // a terminal which has gone away is no concern of the guest
+                let _ = self.render();
+            }
+            offset => {
+                if let Some(c) = self.chars.get_mut(usize::from(offset)) {
+                    self.dirty |= *c != data;
+                    *c = data;
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self, kind: ResetKind) {
+        if kind == ResetKind::Hard {
+            self.chars.fill(b' ');
+            self.dirty = true;
+        }
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        let Some(refresh) = self.refresh else {
+            return;
+        };
+        self.since_refresh += cycles;
+        if self.since_refresh >= refresh {
+            self.since_refresh %= refresh;
+            let _ = self.render();
+        }
+    }
+}
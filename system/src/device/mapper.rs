@@ -0,0 +1,159 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use cpu::{StateError, StateReader, StateWriter};
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// A cartridge-style mapper which shows one of several banks through a fixed
+/// window, typically 8K or 16K wide.
+///
+/// The device must be mapped at a range of exactly `size` bytes, so a 16K
+/// window fits at $C000-$FFFF. Like the latches on many cartridges, writing a
+/// byte anywhere in the window selects the bank it names, modulo the bank
+/// count. Banks made writable take writes instead, and are switched through a
+/// [`BankRegister`] mapped elsewhere, which any mapper can also be given.
+/// The banks are owned by the device, so the backing store can be much larger
+/// than the address space it is mapped into.
+pub struct BankedDevice {
+    bank_size: usize,
+    banks: Vec<u8>,
+    selected: Arc<AtomicUsize>,
+    writable: bool,
+}
+
+impl BankedDevice {
+    const STATE_MAGIC: &'static [u8; 4] = b"BANK";
+    const STATE_VERSION: u8 = 1;
+
+    /// Returns a mapper which splits `image` into banks of `bank_size` bytes.
+    /// The last bank is padded with zeros.
+    pub fn new(bank_size: usize, image: &[u8]) -> Self {
+        assert!(bank_size > 0, "banks must not be empty");
+
+        let count = image.len().div_ceil(bank_size).max(1);
+        let mut banks = image.to_vec();
+        banks.resize(count * bank_size, 0);
+        Self {
+            bank_size,
+            banks,
+            selected: Arc::new(AtomicUsize::new(0)),
+            writable: false,
+        }
+    }
+
+    /// Makes the banks writable through the window, like banked RAM.
+    pub fn writable(mut self) -> Self {
+        self.writable = true;
+        self
+    }
+
+    /// Returns a one-byte register which selects the bank, to be mapped
+    /// outside the window.
+    pub fn bank_register(&self) -> BankRegister {
+        return BankRegister {
+            selected: self.selected.clone(),
+            count: self.bank_count(),
+        };
+    }
+
+    pub fn bank_count(&self) -> usize {
+        return self.banks.len() / self.bank_size;
+    }
+
+    pub fn selected_bank(&self) -> usize {
+        return self.selected.load(Ordering::Relaxed);
+    }
+
+    fn bank_offset(&self, offset: u16) -> usize {
+        return self.selected_bank() * self.bank_size + usize::from(offset);
+    }
+}
+
+impl Device for BankedDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() == self.bank_size;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        return self.banks[self.bank_offset(offset)];
+    }
+
+    fn peek(&self, offset: u16) -> Option<u8> {
+        return Some(self.read(offset));
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        if self.writable {
+            let offset = self.bank_offset(offset);
+            self.banks[offset] = data;
+        } else {
+            let bank = usize::from(data) % self.bank_count();
+            self.selected.store(bank, Ordering::Relaxed);
+        }
+    }
+
+    fn reset(&mut self, kind: ResetKind) {
+        if kind == ResetKind::Hard {
+            self.selected.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Saves the selected bank, and the banks themselves if they are writable.
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(Self::STATE_MAGIC, Self::STATE_VERSION);
+        state.u32(self.selected_bank() as u32);
+        if self.writable {
+            state.bytes(&self.banks);
+        }
+        return state.finish();
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (mut state, _) = StateReader::new(state, Self::STATE_MAGIC, Self::STATE_VERSION)?;
+        let selected = state.u32()? as usize;
+        if selected >= self.bank_count() {
+            return Err(StateError::Invalid("bank"));
+        }
+        let banks = match self.writable {
+            true => Some(state.bytes(self.banks.len())?),
+            false => None,
+        };
+        state.finish()?;
+
+        self.selected.store(selected, Ordering::Relaxed);
+        if let Some(banks) = banks {
+            self.banks.copy_from_slice(banks);
+        }
+        return Ok(());
+    }
+}
+
+/// The bank select register of a [`BankedDevice`].
+/// ```text
+///     +0  BANK  (read/write)  the selected bank number, modulo the bank count
+/// ```
+pub struct BankRegister {
+    selected: Arc<AtomicUsize>,
+    count: usize,
+}
+
+impl Device for BankRegister {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() == 1;
+    }
+
+    fn read(&self, _offset: u16) -> u8 {
+        return self.selected.load(Ordering::Relaxed) as u8;
+    }
+
+    fn peek(&self, offset: u16) -> Option<u8> {
+        return Some(self.read(offset));
+    }
+
+    fn write(&mut self, _offset: u16, data: u8) {
+        self.selected
+            .store(usize::from(data) % self.count, Ordering::Relaxed);
+    }
+}
@@ -1,12 +1,100 @@
+use cpu::{Bus, StateError};
+
+mod acia;
+mod beeper;
+mod dipswitch;
+mod disk;
+mod display;
+mod mapper;
+mod random;
+mod region;
+mod seriallink;
+mod stdin;
 mod stdout;
+mod via;
 
 pub use crate::Range;
+pub use acia::{AciaDevice, ChannelBackend, SerialBackend};
+#[cfg(feature = "host")]
+pub use acia::{StdioBackend, TcpBackend};
+pub use beeper::{BeeperDevice, Speaker};
+pub use dipswitch::{DipSwitchDevice, DipSwitches};
+pub use disk::DiskDevice;
+pub use display::TextDisplayDevice;
+pub use mapper::{BankRegister, BankedDevice};
+pub use random::RandomDevice;
+pub use region::{RamDevice, RomDevice, RomWrites};
+pub use seriallink::SerialLinkDevice;
+pub use stdin::StdinDevice;
 pub use stdout::StdoutDevice;
+pub use via::{PortHandler, ViaDevice};
+
+/// The kind of reset being signaled to a device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetKind {
+    /// A power-on reset. Devices should return to their default state.
+    Hard,
+    /// A reset that only restarts the CPU. Devices may keep their state.
+    Soft,
+}
+
+/// Something mapped into the address space with [`crate::Memory::map`].
+///
+/// Devices are addressed by the offset from the start of the range they are
+/// mapped at, so the same device can be mapped anywhere. Devices are `Send` so
+/// that a whole machine can be handed to another thread.
+pub trait Device: Send {
+    /// Called when the device is mapped at `range`. Returns false if the device
+    /// cannot be mapped there, typically because the range is too small.
+    fn mount(&mut self, _range: Range) -> bool {
+        true
+    }
+
+    fn read(&self, offset: u16) -> u8;
+    fn write(&mut self, offset: u16, data: u8);
+
+    /// Returns the byte at `offset` without any of the side effects of a read,
+    /// or `None` if the device cannot tell. Used to inspect memory.
+    fn peek(&self, _offset: u16) -> Option<u8> {
+        None
+    }
+
+    /// Like [`Device::write`], but with access to the rest of the bus for
+    /// devices which transfer data by DMA. The device's own range reads as open
+    /// bus through `bus`.
+    fn write_with_bus(&mut self, offset: u16, data: u8, _bus: &mut dyn Bus) {
+        self.write(offset, data);
+    }
+
+    /// Called when the machine is reset.
+    fn reset(&mut self, _kind: ResetKind) {}
+
+    /// Advances the device by the given number of elapsed CPU cycles.
+    fn tick(&mut self, _cycles: u64) {}
+
+    /// Returns whether the device is able to raise interrupts at all.
+    fn uses_irq(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the device is currently pulling the IRQ line.
+    fn irq_asserted(&self) -> bool {
+        false
+    }
 
-pub trait Device {
-    fn get_range(&self) -> Range;
-    fn set_range(&mut self, range: Range) -> bool;
+    /// Returns the state of the device to save along with RAM, see
+    /// [`crate::Memory::save_state`]. Devices with no state of their own, or
+    /// whose state lives outside the machine, save nothing.
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
 
-    fn read(&self, address: u16) -> u8;
-    fn write(&mut self, address: u16, data: u8);
+    /// Restores a state saved with [`Device::save_state`]. The device is left
+    /// untouched if the state cannot be restored.
+    fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        if !state.is_empty() {
+            return Err(StateError::TrailingData);
+        }
+        Ok(())
+    }
 }
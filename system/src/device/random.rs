@@ -0,0 +1,130 @@
+use std::cell::Cell;
+
+use cpu::{StateError, StateReader, StateWriter};
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// A source of random bytes which are the same on every run with the same seed,
+/// so that programs which want randomness can still be tested.
+///
+/// The guest sees five registers:
+/// ```text
+///     +0     DATA  (read-only)  read returns the next random byte
+///     +1..+4 SEED  (read/write) the seed, little endian, writing a byte of it
+///                               starts the sequence over from the new seed
+/// ```
+/// The bytes come from a xorshift generator, which is fast rather than good. A
+/// hard reset starts the sequence over from the seed.
+pub struct RandomDevice {
+    seed: u32,
+    state: Cell<u32>,
+}
+
+impl RandomDevice {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA060,
+        end: 0xA065,
+    };
+
+    const STATE_MAGIC: &'static [u8; 4] = b"RAND";
+    const STATE_VERSION: u8 = 1;
+
+    const REG_DATA: u16 = 0;
+    const REG_SEED: u16 = 1;
+
+    /// Returns a device which generates the sequence for `seed`.
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            state: Cell::new(start(seed)),
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        return self.seed;
+    }
+
+    /// Returns which byte of the seed the register at `offset` holds.
+    fn seed_index(offset: u16) -> Option<usize> {
+        let registers = Self::REG_SEED..Self::REG_SEED + 4;
+        return registers
+            .contains(&offset)
+            .then(|| (offset - Self::REG_SEED) as usize);
+    }
+}
+
+impl Device for RandomDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() >= 5;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        match offset {
+            Self::REG_DATA => {
+                let state = next(self.state.get());
+                self.state.set(state);
+                (state >> 24) as u8
+            }
+            _ => self.peek(offset).unwrap_or(0),
+        }
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        let Some(index) = Self::seed_index(offset) else {
+            return;
+        };
+        let mut seed = self.seed.to_le_bytes();
+        seed[index] = data;
+        self.seed = u32::from_le_bytes(seed);
+        self.state.set(start(self.seed));
+    }
+
+    fn peek(&self, offset: u16) -> Option<u8> {
+        if offset == Self::REG_DATA {
+            return Some((next(self.state.get()) >> 24) as u8);
+        }
+        return Self::seed_index(offset).map(|index| self.seed.to_le_bytes()[index]);
+    }
+
+    fn reset(&mut self, kind: ResetKind) {
+        if kind == ResetKind::Hard {
+            self.state.set(start(self.seed));
+        }
+    }
+
+    /// Saves the seed and how far along its sequence the device is.
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(Self::STATE_MAGIC, Self::STATE_VERSION);
+        state.u32(self.seed);
+        state.u32(self.state.get());
+        return state.finish();
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (mut state, _) = StateReader::new(state, Self::STATE_MAGIC, Self::STATE_VERSION)?;
+        let seed = state.u32()?;
+        let generator = state.u32()?;
+        state.finish()?;
+        if generator == 0 {
+            return Err(StateError::Invalid("generator state"));
+        }
+        self.seed = seed;
+        self.state.set(generator);
+        return Ok(());
+    }
+}
+
+/// Returns the generator state for `seed`. Xorshift never leaves a state of
+/// zero, so that seed starts from another.
+fn start(seed: u32) -> u32 {
+    return if seed == 0 { 0x2545_F491 } else { seed };
+}
+
+fn next(mut state: u32) -> u32 {
+    state ^= state << 13;
+    state ^= state >> 17;
+    state ^= state << 5;
+    return state;
+}
@@ -0,0 +1,129 @@
+use cpu::{StateError, StateReader, StateWriter};
+
+use crate::device::Device;
+use crate::Range;
+
+/// What a [`RomDevice`] does when the guest writes to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RomWrites {
+    /// The write is dropped, like on real hardware.
+    #[default]
+    Ignore,
+    /// The write panics, to catch programs which scribble over their own code.
+    Trap,
+}
+
+/// A write-protected region backed by an image.
+///
+/// Images shorter than the range the ROM is mapped at are padded with zeros.
+pub struct RomDevice {
+    range: Range,
+    data: Vec<u8>,
+    writes: RomWrites,
+}
+
+impl RomDevice {
+    /// Returns a ROM which holds `image`.
+    pub fn new(image: &[u8]) -> Self {
+        Self {
+            range: Range::new(0, 0),
+            data: image.to_vec(),
+            writes: RomWrites::Ignore,
+        }
+    }
+
+    /// Sets what happens when the guest writes to the ROM.
+    pub fn on_write(mut self, writes: RomWrites) -> Self {
+        self.writes = writes;
+        self
+    }
+}
+
+impl Device for RomDevice {
+    /// Fails if the image does not fit in `range`.
+    fn mount(&mut self, range: Range) -> bool {
+        let size = range.len();
+        if self.data.len() > size {
+            return false;
+        }
+        self.data.resize(size, 0);
+        self.range = range;
+        return true;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        return self.data[usize::from(offset)];
+    }
+
+    fn peek(&self, offset: u16) -> Option<u8> {
+        return Some(self.read(offset));
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        if self.writes == RomWrites::Trap {
+            let address = self.range.start.wrapping_add(offset);
+            panic!("write of ${:02x} to rom at ${:04x}", data, address);
+        }
+    }
+}
+
+/// A writable region with its own backing store, sized to the range it is
+/// mapped at.
+#[derive(Default)]
+pub struct RamDevice {
+    data: Vec<u8>,
+}
+
+impl RamDevice {
+    const STATE_MAGIC: &'static [u8; 4] = b"RAMD";
+    const STATE_VERSION: u8 = 1;
+
+    /// Returns a RAM which is zeroed when it is mapped.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current contents of the RAM.
+    pub fn data(&self) -> &[u8] {
+        return &self.data;
+    }
+}
+
+impl Device for RamDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        self.data = vec![0; range.len()];
+        return true;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        return self.data[usize::from(offset)];
+    }
+
+    fn peek(&self, offset: u16) -> Option<u8> {
+        return Some(self.read(offset));
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        self.data[usize::from(offset)] = data;
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(Self::STATE_MAGIC, Self::STATE_VERSION);
+        state.u32(self.data.len() as u32);
+        state.bytes(&self.data);
+        return state.finish();
+    }
+
+    /// Fails if the RAM was saved while mapped at a range of another size.
+    fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (mut state, _) = StateReader::new(state, Self::STATE_MAGIC, Self::STATE_VERSION)?;
+        let size = state.u32()? as usize;
+        if size != self.data.len() {
+            return Err(StateError::Invalid("ram size"));
+        }
+        let data = state.bytes(size)?;
+        state.finish()?;
+        self.data.copy_from_slice(data);
+        return Ok(());
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+type Channel = Arc<Mutex<VecDeque<(u64, u8)>>>;
+
+/// One end of an in-process serial link between two machines.
+///
+/// The guest sees two registers:
+/// ```text
+///     +0  DATA    (read/write) read pops a received byte, write sends a byte
+///     +1  STATUS  (read-only)  bit 0 = byte available, bit 1 = ready to send
+/// ```
+/// Sent bytes become visible to the other end `latency` cycles later, measured
+/// against each end's own clock. Both machines are expected to be run in
+/// lockstep so that their clocks stay close.
+pub struct SerialLinkDevice {
+    latency: u64,
+    clock: u64,
+    rx: Channel,
+    tx: Channel,
+}
+
+impl SerialLinkDevice {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA020,
+        end: 0xA022,
+    };
+
+    const REG_DATA: u16 = 0;
+    const REG_STATUS: u16 = 1;
+
+    const STATUS_RX_READY: u8 = 0x01;
+    const STATUS_TX_READY: u8 = 0x02;
+
+    /// Returns both ends of a new link with the given latency in cycles.
+    pub fn pair(latency: u64) -> (Self, Self) {
+        let a_to_b = Channel::default();
+        let b_to_a = Channel::default();
+        let a = Self::new(latency, Arc::clone(&b_to_a), Arc::clone(&a_to_b));
+        let b = Self::new(latency, a_to_b, b_to_a);
+        (a, b)
+    }
+
+    fn new(latency: u64, rx: Channel, tx: Channel) -> Self {
+        Self {
+            latency,
+            clock: 0,
+            rx,
+            tx,
+        }
+    }
+
+    fn rx_ready(&self) -> bool {
+        let rx = self.rx.lock().unwrap();
+        return matches!(rx.front(), Some((ready, _)) if *ready <= self.clock);
+    }
+}
+
+impl Device for SerialLinkDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() >= 2;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        match offset {
+            Self::REG_DATA => {
+                if !self.rx_ready() {
+                    return 0;
+                }
+                let (_, byte) = self.rx.lock().unwrap().pop_front().unwrap();
+                byte
+            }
+            Self::REG_STATUS => {
+                let mut status = Self::STATUS_TX_READY;
+                if self.rx_ready() {
+                    status |= Self::STATUS_RX_READY;
+                }
+                status
+            }
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        if offset == Self::REG_DATA {
+            let ready = self.clock + self.latency;
+            self.tx.lock().unwrap().push_back((ready, data));
+        }
+    }
+
+    fn reset(&mut self, kind: ResetKind) {
+        if kind == ResetKind::Hard {
+            // drop anything still in flight towards this end
+            self.rx.lock().unwrap().clear();
+            self.clock = 0;
+        }
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.clock += cycles;
+    }
+}
@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+#[cfg(feature = "host")]
+use std::io::Read;
+#[cfg(feature = "host")]
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+#[cfg(feature = "host")]
+use std::thread;
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// A keyboard which delivers the bytes typed on the host's stdin.
+///
+/// The guest sees two registers:
+/// ```text
+///     +0  DATA    (read-only)  read pops the next byte, or 0 if there is none
+///     +1  STATUS  (read-only)  bit 0 = byte available
+/// ```
+/// Input is read on a background thread, so reading the registers never blocks
+/// and programs are expected to poll the status register.
+pub struct StdinDevice {
+    input: Receiver<u8>,
+    pending: RefCell<VecDeque<u8>>,
+}
+
+#[cfg(feature = "host")]
+impl Default for StdinDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StdinDevice {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA030,
+        end: 0xA032,
+    };
+
+    const REG_DATA: u16 = 0;
+    const REG_STATUS: u16 = 1;
+
+    const STATUS_RX_READY: u8 = 0x01;
+
+    /// Returns a device which reads from the host's stdin.
+    #[cfg(feature = "host")]
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in std::io::stdin().lock().bytes() {
+                match byte {
+                    Ok(byte) if tx.send(byte).is_ok() => {}
+                    _ => return,
+                }
+            }
+        });
+        Self::from_receiver(rx)
+    }
+
+    /// Returns a device which delivers the bytes sent over `input`.
+    pub fn from_receiver(input: Receiver<u8>) -> Self {
+        Self {
+            input,
+            pending: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn rx_ready(&self) -> bool {
+        let mut pending = self.pending.borrow_mut();
+        pending.extend(self.input.try_iter());
+        return !pending.is_empty();
+    }
+}
+
+impl Device for StdinDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() >= 2;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        match offset {
+            Self::REG_DATA => {
+                if !self.rx_ready() {
+                    return 0;
+                }
+                self.pending.borrow_mut().pop_front().unwrap()
+            }
+            Self::REG_STATUS if self.rx_ready() => Self::STATUS_RX_READY,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, _: u16, _: u8) {
+        // writes not supported
+    }
+
+    fn reset(&mut self, kind: ResetKind) {
+        if kind == ResetKind::Hard {
+            // drop anything typed before the power cycle
+            self.rx_ready();
+            self.pending.get_mut().clear();
+        }
+    }
+}
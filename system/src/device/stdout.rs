@@ -1,41 +1,51 @@
+use std::io::Write;
+
 use crate::device::Device;
 
 use crate::Range;
 
+/// A console which writes each byte stored to it as a character.
 pub struct StdoutDevice {
-    range: Range,
+    output: Box<dyn Write + Send>,
+}
+
+#[cfg(feature = "host")]
+impl Default for StdoutDevice {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StdoutDevice {
-    const MMIO_RANGE: Range = Range {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
         start: 0xA000,
         end: 0xA001,
     };
 
+    /// Returns a console which prints to the host's stdout.
+    #[cfg(feature = "host")]
     pub fn new() -> Self {
+        Self::with_output(std::io::stdout())
+    }
+
+    /// Returns a console which writes to `output`, e.g. a buffer the host shows
+    /// somewhere other than a terminal.
+    pub fn with_output(output: impl Write + Send + 'static) -> Self {
         Self {
-            range: Self::MMIO_RANGE,
+            output: Box::new(output),
         }
     }
 }
 
 impl Device for StdoutDevice {
-    fn get_range(&self) -> Range {
-        return self.range;
-    }
-
-    fn set_range(&mut self, range: Range) -> bool {
-        self.range = range;
-        return true;
-    }
-
     fn read(&self, _: u16) -> u8 {
         // reads not supported
         return 0;
     }
 
-    fn write(&mut self, address: u16, data: u8) {
-        assert!(address == self.range.start);
-        print!("{}", data as char);
+    fn write(&mut self, offset: u16, data: u8) {
+        assert!(offset == 0);
+        let _ = write!(self.output, "{}", data as char);
     }
 }
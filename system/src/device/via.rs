@@ -0,0 +1,556 @@
+use std::cell::Cell;
+
+use cpu::{StateError, StateReader, StateWriter};
+
+use crate::device::{Device, ResetKind};
+use crate::Range;
+
+/// The outside world of one port of a [`ViaDevice`]. Every method has a default
+/// for a port with nothing connected, whose lines are pulled high.
+pub trait PortHandler: Send {
+    /// Returns the levels the pins are driven to from outside. Only the pins
+    /// which are set as inputs are read.
+    fn read(&self) -> u8 {
+        0xFF
+    }
+
+    /// Called when the output or data direction register of the port is
+    /// written, with the levels of the pins and which of them are outputs.
+    fn write(&mut self, _output: u8, _direction: u8) {}
+
+    /// Returns the level of the port's first control line (CA1 or CB1), whose
+    /// edges set an interrupt flag.
+    fn control(&self) -> bool {
+        true
+    }
+
+    /// Returns the next byte shifted in over CB2. Only called for port B.
+    fn shift_in(&mut self) -> u8 {
+        0xFF
+    }
+
+    /// Called with each byte shifted out over CB2. Only called for port B.
+    fn shift_out(&mut self, _byte: u8) {}
+}
+
+/// A port with nothing connected to it.
+struct Unconnected;
+
+impl PortHandler for Unconnected {}
+
+/// A 6522 Versatile Interface Adapter: two 8-bit ports, two timers and a shift
+/// register.
+///
+/// The guest sees sixteen registers:
+/// ```text
+///     +0  ORB/IRB  port B, reading or writing clears the CB1 and CB2 flags
+///     +1  ORA/IRA  port A, reading or writing clears the CA1 and CA2 flags
+///     +2  DDRB     port B direction, 1 = output
+///     +3  DDRA     port A direction, 1 = output
+///     +4  T1C-L    read: counter low, clears the T1 flag   write: latch low
+///     +5  T1C-H    read: counter high   write: latch high, loads and starts T1
+///     +6  T1L-L    latch low
+///     +7  T1L-H    latch high, writing clears the T1 flag
+///     +8  T2C-L    read: counter low, clears the T2 flag   write: latch low
+///     +9  T2C-H    read: counter high   write: loads and starts T2
+///     +A  SR       shift register, reading or writing starts a shift
+///     +B  ACR      bit 7 = T1 drives PB7, bit 6 = T1 free-running,
+///                  bit 5 = T2 counts PB6 pulses, bits 4-2 = shift mode,
+///                  bit 1 = latch port B, bit 0 = latch port A
+///     +C  PCR      bit 4 = CB1 rising edge, bit 0 = CA1 rising edge
+///     +D  IFR      bit 7 = any enabled flag, 6 = T1, 5 = T2, 4 = CB1,
+///                  3 = CB2, 2 = SR, 1 = CA1, 0 = CA2; writing 1s clears them
+///     +E  IER      writing with bit 7 set enables, clear disables
+///     +F  ORA/IRA  port A without touching the flags
+/// ```
+/// The IRQ line is pulled while any flag is set and enabled. A one-shot T1
+/// interrupts once, [`ViaDevice::T1_EXTRA_CYCLES`] cycles after the latch would
+/// suggest, and a free-running one every latch + 2 cycles. Edges on CA1 and CB1
+/// are seen when the device is ticked, and latch the port they belong to if
+/// latching is on.
+///
+/// The shift register moves a whole byte at a time through
+/// [`PortHandler::shift_in`] and [`PortHandler::shift_out`] of port B, once
+/// eight bits would have been shifted at the rate the mode selects: every 2
+/// cycles under φ2, and every 2 T2 timeouts under T2. Modes clocked by CB1 and
+/// counting pulses on PB6 never advance, and CA2 and CB2 never raise their
+/// flags.
+pub struct ViaDevice {
+    port_a: Box<dyn PortHandler>,
+    port_b: Box<dyn PortHandler>,
+    ora: u8,
+    orb: u8,
+    ddra: u8,
+    ddrb: u8,
+    /// The inputs as they were at the last active edge of CA1 and CB1.
+    ira_latch: u8,
+    irb_latch: u8,
+    /// The levels of CA1 and CB1 when last seen.
+    ca1: bool,
+    cb1: bool,
+    t1: u16,
+    t1_latch: u16,
+    /// Whether T1 interrupts when it next passes zero.
+    t1_armed: bool,
+    /// Whether T1 is in the cycle between passing zero and being reloaded.
+    t1_reload: bool,
+    /// The level T1 drives PB7 to.
+    pb7: bool,
+    t2: u16,
+    t2_latch_low: u8,
+    t2_armed: bool,
+    sr: Cell<u8>,
+    /// The cycles left until the shift in progress finishes, if one is.
+    sr_remaining: Cell<Option<u64>>,
+    acr: u8,
+    pcr: u8,
+    ifr: Cell<u8>,
+    ier: u8,
+}
+
+impl Default for ViaDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ViaDevice {
+    /// Where the device is usually mapped.
+    pub const RANGE: Range = Range {
+        start: 0xA070,
+        end: 0xA080,
+    };
+
+    /// The cycles a one-shot T1 takes beyond the value it is loaded with.
+    pub const T1_EXTRA_CYCLES: u64 = 1;
+
+    const STATE_MAGIC: &'static [u8; 4] = b"6522";
+    const STATE_VERSION: u8 = 1;
+
+    const REG_ORB: u16 = 0x0;
+    const REG_ORA: u16 = 0x1;
+    const REG_DDRB: u16 = 0x2;
+    const REG_DDRA: u16 = 0x3;
+    const REG_T1C_L: u16 = 0x4;
+    const REG_T1C_H: u16 = 0x5;
+    const REG_T1L_L: u16 = 0x6;
+    const REG_T1L_H: u16 = 0x7;
+    const REG_T2C_L: u16 = 0x8;
+    const REG_T2C_H: u16 = 0x9;
+    const REG_SR: u16 = 0xA;
+    const REG_ACR: u16 = 0xB;
+    const REG_PCR: u16 = 0xC;
+    const REG_IFR: u16 = 0xD;
+    const REG_IER: u16 = 0xE;
+    const REG_ORA_NH: u16 = 0xF;
+
+    const IRQ_CA2: u8 = 0x01;
+    const IRQ_CA1: u8 = 0x02;
+    const IRQ_SR: u8 = 0x04;
+    const IRQ_CB2: u8 = 0x08;
+    const IRQ_CB1: u8 = 0x10;
+    const IRQ_T2: u8 = 0x20;
+    const IRQ_T1: u8 = 0x40;
+    const IRQ_ANY: u8 = 0x80;
+
+    const ACR_LATCH_A: u8 = 0x01;
+    const ACR_LATCH_B: u8 = 0x02;
+    const ACR_SR_MODE: u8 = 0x1C;
+    const ACR_T2_PULSES: u8 = 0x20;
+    const ACR_T1_FREE_RUN: u8 = 0x40;
+    const ACR_T1_PB7: u8 = 0x80;
+
+    const PCR_CA1_RISING: u8 = 0x01;
+    const PCR_CB1_RISING: u8 = 0x10;
+
+    /// Returns a VIA with nothing connected to its ports.
+    pub fn new() -> Self {
+        Self {
+            port_a: Box::new(Unconnected),
+            port_b: Box::new(Unconnected),
+            ora: 0,
+            orb: 0,
+            ddra: 0,
+            ddrb: 0,
+            ira_latch: 0,
+            irb_latch: 0,
+            ca1: true,
+            cb1: true,
+            t1: 0xFFFF,
+            t1_latch: 0xFFFF,
+            t1_armed: false,
+            t1_reload: false,
+            pb7: true,
+            t2: 0xFFFF,
+            t2_latch_low: 0xFF,
+            t2_armed: false,
+            sr: Cell::new(0),
+            sr_remaining: Cell::new(None),
+            acr: 0,
+            pcr: 0,
+            ifr: Cell::new(0),
+            ier: 0,
+        }
+    }
+
+    /// Connects `handler` to port A.
+    pub fn with_port_a(mut self, handler: impl PortHandler + 'static) -> Self {
+        self.port_a = Box::new(handler);
+        self.ca1 = self.port_a.control();
+        self
+    }
+
+    /// Connects `handler` to port B.
+    pub fn with_port_b(mut self, handler: impl PortHandler + 'static) -> Self {
+        self.port_b = Box::new(handler);
+        self.cb1 = self.port_b.control();
+        self
+    }
+
+    /// Returns the value of a register without any side effects.
+    fn register(&self, offset: u16) -> u8 {
+        match offset {
+            Self::REG_ORB => {
+                let input = match self.acr & Self::ACR_LATCH_B {
+                    0 => self.port_b.read(),
+                    _ => self.irb_latch,
+                };
+                let (output, direction) = self.port_b_pins();
+                (output & direction) | (input & !direction)
+            }
+            Self::REG_ORA | Self::REG_ORA_NH => {
+                let input = match self.acr & Self::ACR_LATCH_A {
+                    0 => self.port_a.read(),
+                    _ => self.ira_latch,
+                };
+                (self.ora & self.ddra) | (input & !self.ddra)
+            }
+            Self::REG_DDRB => self.ddrb,
+            Self::REG_DDRA => self.ddra,
+            Self::REG_T1C_L => self.t1 as u8,
+            Self::REG_T1C_H => (self.t1 >> 8) as u8,
+            Self::REG_T1L_L => self.t1_latch as u8,
+            Self::REG_T1L_H => (self.t1_latch >> 8) as u8,
+            Self::REG_T2C_L => self.t2 as u8,
+            Self::REG_T2C_H => (self.t2 >> 8) as u8,
+            Self::REG_SR => self.sr.get(),
+            Self::REG_ACR => self.acr,
+            Self::REG_PCR => self.pcr,
+            Self::REG_IFR => match self.irq_asserted() {
+                true => self.ifr.get() | Self::IRQ_ANY,
+                false => self.ifr.get(),
+            },
+            Self::REG_IER => self.ier | 0x80,
+            _ => 0,
+        }
+    }
+
+    /// Returns the levels of port B's outputs and which pins are outputs, with
+    /// PB7 taken over by T1 if it drives it.
+    fn port_b_pins(&self) -> (u8, u8) {
+        if self.acr & Self::ACR_T1_PB7 == 0 {
+            return (self.orb, self.ddrb);
+        }
+        let pb7 = if self.pb7 { 0x80 } else { 0 };
+        return ((self.orb & 0x7F) | pb7, self.ddrb | 0x80);
+    }
+
+    fn clear_flags(&self, flags: u8) {
+        self.ifr.set(self.ifr.get() & !flags);
+    }
+
+    fn set_flags(&self, flags: u8) {
+        self.ifr.set(self.ifr.get() | flags);
+    }
+
+    fn write_port_a(&mut self) {
+        self.port_a.write(self.ora, self.ddra);
+    }
+
+    fn write_port_b(&mut self) {
+        let (output, direction) = self.port_b_pins();
+        self.port_b.write(output, direction);
+    }
+
+    /// Returns the shift mode, from bits 4-2 of the ACR.
+    fn sr_mode(&self) -> u8 {
+        return (self.acr & Self::ACR_SR_MODE) >> 2;
+    }
+
+    /// Returns the cycles eight bits take to shift in the current mode, or
+    /// `None` if the mode is off or clocked from outside.
+    fn sr_period(&self) -> Option<u64> {
+        let t2_timeout = u64::from(self.t2_latch_low) + 2;
+        return match self.sr_mode() {
+            0b001 | 0b100 | 0b101 => Some(8 * 2 * t2_timeout),
+            0b010 | 0b110 => Some(8 * 2),
+            _ => None,
+        };
+    }
+
+    /// Starts shifting a byte, as reading or writing the shift register does.
+    fn start_shift(&self) {
+        self.clear_flags(Self::IRQ_SR);
+        self.sr_remaining.set(self.sr_period());
+    }
+
+    fn tick_t1(&mut self, mut cycles: u64) {
+        while cycles > 0 {
+            if self.t1_reload {
+                self.t1_reload = false;
+                self.t1 = self.t1_latch;
+                cycles -= 1;
+                continue;
+            }
+            // the cycles until the counter passes zero
+            let until = u64::from(self.t1) + 1;
+            if cycles < until {
+                self.t1 -= cycles as u16;
+                return;
+            }
+            cycles -= until;
+            self.t1 = 0xFFFF;
+
+            let free_run = self.acr & Self::ACR_T1_FREE_RUN != 0;
+            if self.t1_armed {
+                self.set_flags(Self::IRQ_T1);
+                self.pb7 = !self.pb7 || !free_run;
+                self.t1_armed = free_run;
+                if self.acr & Self::ACR_T1_PB7 != 0 {
+                    self.write_port_b();
+                }
+            }
+            self.t1_reload = free_run;
+        }
+    }
+
+    fn tick_t2(&mut self, cycles: u64) {
+        if self.acr & Self::ACR_T2_PULSES != 0 {
+            return;
+        }
+        if self.t2_armed && cycles > u64::from(self.t2) {
+            self.set_flags(Self::IRQ_T2);
+            self.t2_armed = false;
+        }
+        // the counter keeps rolling over once it has passed zero
+        self.t2 = (u64::from(self.t2) + 0x10000 - cycles % 0x10000) as u16;
+    }
+
+    fn tick_sr(&mut self, mut cycles: u64) {
+        while let Some(remaining) = self.sr_remaining.get() {
+            if cycles < remaining {
+                self.sr_remaining.set(Some(remaining - cycles));
+                return;
+            }
+            cycles -= remaining;
+            self.sr_remaining.set(None);
+
+            let mode = self.sr_mode();
+            if mode & 0b100 == 0 {
+                self.sr.set(self.port_b.shift_in());
+            } else {
+                self.port_b.shift_out(self.sr.get());
+            }
+            if mode == 0b100 {
+                // free-running output shifts the same byte forever
+                self.sr_remaining.set(self.sr_period());
+            } else {
+                self.set_flags(Self::IRQ_SR);
+            }
+        }
+    }
+
+    /// Looks for active edges on CA1 and CB1.
+    fn poll_control(&mut self) {
+        let ca1 = self.port_a.control();
+        if ca1 != self.ca1 && ca1 == (self.pcr & Self::PCR_CA1_RISING != 0) {
+            self.set_flags(Self::IRQ_CA1);
+            self.ira_latch = self.port_a.read();
+        }
+        self.ca1 = ca1;
+
+        let cb1 = self.port_b.control();
+        if cb1 != self.cb1 && cb1 == (self.pcr & Self::PCR_CB1_RISING != 0) {
+            self.set_flags(Self::IRQ_CB1);
+            self.irb_latch = self.port_b.read();
+        }
+        self.cb1 = cb1;
+    }
+}
+
+impl Device for ViaDevice {
+    fn mount(&mut self, range: Range) -> bool {
+        return range.len() >= 16;
+    }
+
+    fn read(&self, offset: u16) -> u8 {
+        let value = self.register(offset);
+        match offset {
+            Self::REG_ORB => self.clear_flags(Self::IRQ_CB1 | Self::IRQ_CB2),
+            Self::REG_ORA => self.clear_flags(Self::IRQ_CA1 | Self::IRQ_CA2),
+            Self::REG_T1C_L => self.clear_flags(Self::IRQ_T1),
+            Self::REG_T2C_L => self.clear_flags(Self::IRQ_T2),
+            Self::REG_SR => self.start_shift(),
+            _ => {}
+        }
+        return value;
+    }
+
+    fn write(&mut self, offset: u16, data: u8) {
+        match offset {
+            Self::REG_ORB => {
+                self.orb = data;
+                self.clear_flags(Self::IRQ_CB1 | Self::IRQ_CB2);
+                self.write_port_b();
+            }
+            Self::REG_ORA | Self::REG_ORA_NH => {
+                self.ora = data;
+                if offset == Self::REG_ORA {
+                    self.clear_flags(Self::IRQ_CA1 | Self::IRQ_CA2);
+                }
+                self.write_port_a();
+            }
+            Self::REG_DDRB => {
+                self.ddrb = data;
+                self.write_port_b();
+            }
+            Self::REG_DDRA => {
+                self.ddra = data;
+                self.write_port_a();
+            }
+            Self::REG_T1C_L | Self::REG_T1L_L => {
+                self.t1_latch = (self.t1_latch & 0xFF00) | u16::from(data);
+            }
+            Self::REG_T1C_H => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | (u16::from(data) << 8);
+                self.t1 = self.t1_latch;
+                self.t1_armed = true;
+                self.t1_reload = false;
+                self.clear_flags(Self::IRQ_T1);
+                if self.acr & Self::ACR_T1_PB7 != 0 {
+                    self.pb7 = false;
+                    self.write_port_b();
+                }
+            }
+            Self::REG_T1L_H => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | (u16::from(data) << 8);
+                self.clear_flags(Self::IRQ_T1);
+            }
+            Self::REG_T2C_L => self.t2_latch_low = data,
+            Self::REG_T2C_H => {
+                self.t2 = (u16::from(data) << 8) | u16::from(self.t2_latch_low);
+                self.t2_armed = true;
+                self.clear_flags(Self::IRQ_T2);
+            }
+            Self::REG_SR => {
+                self.sr.set(data);
+                self.start_shift();
+            }
+            Self::REG_ACR => {
+                self.acr = data;
+                self.write_port_b();
+            }
+            Self::REG_PCR => self.pcr = data,
+            Self::REG_IFR => self.clear_flags(data & 0x7F),
+            Self::REG_IER => match data & 0x80 {
+                0 => self.ier &= !data,
+                _ => self.ier |= data & 0x7F,
+            },
+            _ => {}
+        }
+    }
+
+    fn peek(&self, offset: u16) -> Option<u8> {
+        return Some(self.register(offset));
+    }
+
+    fn reset(&mut self, _kind: ResetKind) {
+        // the timers, their latches and the shift register are not reset
+        self.ora = 0;
+        self.orb = 0;
+        self.ddra = 0;
+        self.ddrb = 0;
+        self.acr = 0;
+        self.pcr = 0;
+        self.ier = 0;
+        self.ifr.set(0);
+        self.t1_armed = false;
+        self.t2_armed = false;
+        self.sr_remaining.set(None);
+        self.write_port_a();
+        self.write_port_b();
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.tick_t1(cycles);
+        self.tick_t2(cycles);
+        self.tick_sr(cycles);
+        self.poll_control();
+    }
+
+    fn uses_irq(&self) -> bool {
+        return true;
+    }
+
+    fn irq_asserted(&self) -> bool {
+        return self.ifr.get() & self.ier & 0x7F != 0;
+    }
+
+    /// Saves the registers, timers and shift in progress. What is connected to
+    /// the ports is not saved, but sees the outputs again when they are loaded.
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(Self::STATE_MAGIC, Self::STATE_VERSION);
+        for register in [self.ora, self.orb, self.ddra, self.ddrb] {
+            state.u8(register);
+        }
+        state.u8(self.ira_latch);
+        state.u8(self.irb_latch);
+        state.bool(self.ca1);
+        state.bool(self.cb1);
+        state.u16(self.t1);
+        state.u16(self.t1_latch);
+        state.bool(self.t1_armed);
+        state.bool(self.t1_reload);
+        state.bool(self.pb7);
+        state.u16(self.t2);
+        state.u8(self.t2_latch_low);
+        state.bool(self.t2_armed);
+        state.u8(self.sr.get());
+        state.bool(self.sr_remaining.get().is_some());
+        state.u64(self.sr_remaining.get().unwrap_or(0));
+        for register in [self.acr, self.pcr, self.ifr.get(), self.ier] {
+            state.u8(register);
+        }
+        return state.finish();
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (mut state, _) = StateReader::new(state, Self::STATE_MAGIC, Self::STATE_VERSION)?;
+        let ports = [state.u8()?, state.u8()?, state.u8()?, state.u8()?];
+        let latches = (state.u8()?, state.u8()?);
+        let controls = (state.bool()?, state.bool()?);
+        let t1 = (state.u16()?, state.u16()?, state.bool()?, state.bool()?);
+        let pb7 = state.bool()?;
+        let t2 = (state.u16()?, state.u8()?, state.bool()?);
+        let sr = state.u8()?;
+        let shifting = state.bool()?;
+        let sr_remaining = state.u64()?;
+        let registers = [state.u8()?, state.u8()?, state.u8()?, state.u8()?];
+        state.finish()?;
+
+        [self.ora, self.orb, self.ddra, self.ddrb] = ports;
+        (self.ira_latch, self.irb_latch) = latches;
+        (self.ca1, self.cb1) = controls;
+        (self.t1, self.t1_latch, self.t1_armed, self.t1_reload) = t1;
+        self.pb7 = pb7;
+        (self.t2, self.t2_latch_low, self.t2_armed) = t2;
+        self.sr.set(sr);
+        self.sr_remaining.set(shifting.then_some(sr_remaining));
+        let [acr, pcr, ifr, ier] = registers;
+        (self.acr, self.pcr, self.ier) = (acr, pcr, ier);
+        self.ifr.set(ifr);
+        self.write_port_a();
+        self.write_port_b();
+        return Ok(());
+    }
+}
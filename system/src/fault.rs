@@ -0,0 +1,176 @@
+use std::cell::{Cell, RefCell};
+
+use cpu::Bus;
+
+use crate::{Memory, Range};
+
+/// A fault that can be injected by a [`FaultInjector`].
+#[derive(Clone, Copy, Debug)]
+pub enum Fault {
+    /// Flips one random bit of a byte read from `range`.
+    BitFlip { range: Range, probability: f64 },
+    /// Replaces a byte read from `range` with a random value.
+    Corrupt { range: Range, probability: f64 },
+    /// Hides an asserted IRQ line from the CPU.
+    DropIrq { probability: f64 },
+}
+
+/// A record of a single injected fault. `fault` is the index of the fault in
+/// the order it was added to the injector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultEvent {
+    /// A read from `address` observed `injected` instead of `original`.
+    Read {
+        fault: usize,
+        address: u16,
+        original: u8,
+        injected: u8,
+    },
+    /// An asserted IRQ line was hidden from the CPU.
+    DroppedIrq { fault: usize },
+}
+
+impl FaultEvent {
+    /// Returns the index of the fault which caused the event.
+    pub fn fault(&self) -> usize {
+        return match *self {
+            FaultEvent::Read { fault, .. } | FaultEvent::DroppedIrq { fault } => fault,
+        };
+    }
+}
+
+/// A bus wrapper which injects faults into reads and interrupts.
+///
+/// All randomness comes from a seeded generator so that a run can be reproduced
+/// exactly by reusing the same seed.
+pub struct FaultInjector<B: Bus> {
+    bus: B,
+    faults: Vec<Fault>,
+    rng: Cell<u64>,
+    events: RefCell<Vec<FaultEvent>>,
+}
+
+impl<B: Bus> FaultInjector<B> {
+    pub fn new(bus: B, seed: u64) -> Self {
+        Self {
+            bus,
+            faults: vec![],
+            // xorshift state must never be zero
+            rng: Cell::new(seed | 1),
+            events: RefCell::new(vec![]),
+        }
+    }
+
+    /// Adds a fault and returns its index.
+    pub fn add_fault(&mut self, fault: Fault) -> usize {
+        self.faults.push(fault);
+        return self.faults.len() - 1;
+    }
+
+    /// Removes all events that were recorded so far and returns them.
+    pub fn take_events(&self) -> Vec<FaultEvent> {
+        return self.events.take();
+    }
+
+    pub fn inner(&self) -> &B {
+        return &self.bus;
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        return &mut self.bus;
+    }
+
+    pub fn into_inner(self) -> B {
+        return self.bus;
+    }
+
+    /// Passes an IRQ line state through the `DropIrq` faults.
+    pub fn filter_irq(&self, asserted: bool) -> bool {
+        if !asserted {
+            return false;
+        }
+
+        for (index, fault) in self.faults.iter().enumerate() {
+            if let Fault::DropIrq { probability } = *fault {
+                if self.chance(probability) {
+                    self.record(FaultEvent::DroppedIrq { fault: index });
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    //
+
+    fn next_random(&self) -> u64 {
+        // xorshift64*
+        let mut x = self.rng.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.rng.set(x);
+        return x.wrapping_mul(0x2545F4914F6CDD1D);
+    }
+
+    fn chance(&self, probability: f64) -> bool {
+        let sample = (self.next_random() >> 11) as f64 / (1u64 << 53) as f64;
+        return sample < probability;
+    }
+
+    fn record(&self, event: FaultEvent) {
+        self.events.borrow_mut().push(event);
+    }
+
+    /// Applies the read faults to a byte read from `address`.
+    fn inject(&self, address: u16, original: u8) -> u8 {
+        let mut value = original;
+
+        for (index, fault) in self.faults.iter().enumerate() {
+            let injected = match *fault {
+                Fault::BitFlip { range, probability }
+                    if range.contains(address) && self.chance(probability) =>
+                {
+                    value ^ (1 << (self.next_random() % 8))
+                }
+                Fault::Corrupt { range, probability }
+                    if range.contains(address) && self.chance(probability) =>
+                {
+                    self.next_random() as u8
+                }
+                _ => continue,
+            };
+
+            self.record(FaultEvent::Read {
+                fault: index,
+                address,
+                original: value,
+                injected,
+            });
+            value = injected;
+        }
+        return value;
+    }
+}
+
+impl<'a> FaultInjector<Memory<'a>> {
+    /// Returns whether any device is asserting the IRQ line, after applying the
+    /// `DropIrq` faults.
+    pub fn irq_asserted(&self) -> bool {
+        return self.filter_irq(self.bus.irq_asserted());
+    }
+}
+
+impl<B: Bus> Bus for FaultInjector<B> {
+    fn read(&self, address: u16) -> u8 {
+        return self.inject(address, self.bus.read(address));
+    }
+
+    fn fetch(&self, address: u16) -> u8 {
+        return self.inject(address, self.bus.fetch(address));
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.bus.write(address, data);
+    }
+}
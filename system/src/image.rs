@@ -0,0 +1,283 @@
+//! Program images in the Intel HEX and Motorola S-record formats, and `.prg`
+//! files.
+//!
+//! Unlike a flat binary, both hex formats carry the address every byte is loaded
+//! at and usually the address execution starts from. A `.prg` file is a flat
+//! binary preceded by the address it is loaded at.
+
+use std::error::Error;
+
+use crate::MemoryError;
+
+/// A problem found while reading or loading an image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImageError {
+    /// A record could not be read. Lines are numbered from 1.
+    Malformed { line: usize },
+    /// A record does not match its checksum.
+    Checksum { line: usize },
+    /// A record addresses memory above $FFFF.
+    Address { line: usize },
+    /// A file is too short to hold its header.
+    Truncated,
+    /// The image does not fit in memory.
+    Memory(MemoryError),
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::Malformed { line } => write!(f, "malformed record on line {}", line),
+            ImageError::Checksum { line } => write!(f, "bad checksum on line {}", line),
+            ImageError::Address { line } => {
+                write!(f, "record on line {} is outside of the address space", line)
+            }
+            ImageError::Truncated => write!(f, "image is too short for its header"),
+            ImageError::Memory(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+impl From<MemoryError> for ImageError {
+    fn from(err: MemoryError) -> Self {
+        return ImageError::Memory(err);
+    }
+}
+
+/// The data bytes in each record written.
+const RECORD_SIZE: usize = 16;
+
+/// The contents of an image: runs of bytes with the address they belong at.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Image {
+    pub segments: Vec<(u16, Vec<u8>)>,
+    /// Where execution starts, if the image says.
+    pub start: Option<u16>,
+}
+
+impl Image {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an image of `bytes` loaded at `address`.
+    pub fn from_bytes(address: u16, bytes: &[u8]) -> Self {
+        Self {
+            segments: vec![(address, bytes.to_vec())],
+            start: None,
+        }
+    }
+
+    /// Sets where execution starts.
+    pub fn start(mut self, address: u16) -> Self {
+        self.start = Some(address);
+        self
+    }
+
+    /// Reads an Intel HEX file.
+    ///
+    /// Extended address records are accepted as long as they stay within the first
+    /// 64K, and either start address record sets [`Image::start`].
+    pub fn parse_ihex(text: &str) -> Result<Self, ImageError> {
+        let mut image = Self::new();
+        let mut base = 0u32;
+        for (index, line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let malformed = ImageError::Malformed { line: line_no };
+            let bytes = line
+                .strip_prefix(':')
+                .and_then(decode_hex)
+                .ok_or(malformed.clone())?;
+            if bytes.len() < 5 || bytes.len() != usize::from(bytes[0]) + 5 {
+                return Err(malformed);
+            }
+            if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+                return Err(ImageError::Checksum { line: line_no });
+            }
+
+            let address = u32::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+            let data = &bytes[4..bytes.len() - 1];
+            match (bytes[3], data.len()) {
+                (0x00, _) => {
+                    let address = base + address;
+                    if address + data.len() as u32 > 0x10000 {
+                        return Err(ImageError::Address { line: line_no });
+                    }
+                    image.push(address as u16, data);
+                }
+                (0x01, 0) => break,
+                (0x02, 2) => base = u32::from(u16::from_be_bytes([data[0], data[1]])) << 4,
+                (0x04, 2) => base = u32::from(u16::from_be_bytes([data[0], data[1]])) << 16,
+                (0x03 | 0x05, 4) => {
+                    let start = match bytes[3] {
+                        // CS:IP
+                        0x03 => {
+                            (u32::from(u16::from_be_bytes([data[0], data[1]])) << 4)
+                                + u32::from(u16::from_be_bytes([data[2], data[3]]))
+                        }
+                        _ => u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+                    };
+                    let start =
+                        u16::try_from(start).map_err(|_| ImageError::Address { line: line_no })?;
+                    image.start = Some(start);
+                }
+                _ => return Err(malformed),
+            }
+        }
+        return Ok(image);
+    }
+
+    /// Reads a Motorola S-record file.
+    ///
+    /// Header and count records are skipped, and the termination record sets
+    /// [`Image::start`].
+    pub fn parse_srec(text: &str) -> Result<Self, ImageError> {
+        let mut image = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let malformed = ImageError::Malformed { line: line_no };
+            let mut chars = line.chars();
+            let kind = match (chars.next(), chars.next()) {
+                (Some('S'), Some(kind)) => kind,
+                _ => return Err(malformed),
+            };
+            let bytes = decode_hex(chars.as_str()).ok_or(malformed.clone())?;
+            if bytes.len() < 2 || bytes.len() != usize::from(bytes[0]) + 1 {
+                return Err(malformed);
+            }
+            if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0xFF {
+                return Err(ImageError::Checksum { line: line_no });
+            }
+
+            let address_len = match kind {
+                '0' | '1' | '5' | '9' => 2,
+                '2' | '6' | '8' => 3,
+                '3' | '7' => 4,
+                _ => return Err(malformed),
+            };
+            if bytes.len() < address_len + 2 {
+                return Err(malformed);
+            }
+            let address = bytes[1..=address_len]
+                .iter()
+                .fold(0u32, |address, b| address << 8 | u32::from(*b));
+            let data = &bytes[address_len + 1..bytes.len() - 1];
+            match kind {
+                '1' | '2' | '3' => {
+                    if address + data.len() as u32 > 0x10000 {
+                        return Err(ImageError::Address { line: line_no });
+                    }
+                    image.push(address as u16, data);
+                }
+                '7' | '8' | '9' => {
+                    let start = u16::try_from(address)
+                        .map_err(|_| ImageError::Address { line: line_no })?;
+                    image.start = Some(start);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        return Ok(image);
+    }
+
+    /// Reads a `.prg` file, whose first two bytes are the little endian address
+    /// the rest is loaded at. The format has no entry point, so execution is
+    /// taken to start at the load address.
+    pub fn parse_prg(bytes: &[u8]) -> Result<Self, ImageError> {
+        let [lo, hi, data @ ..] = bytes else {
+            return Err(ImageError::Truncated);
+        };
+        let address = u16::from_le_bytes([*lo, *hi]);
+        return Ok(Self::from_bytes(address, data).start(address));
+    }
+
+    /// Writes the image as an Intel HEX file.
+    pub fn to_ihex(&self) -> String {
+        let mut text = String::new();
+        for (address, data) in self.records() {
+            let [hi, lo] = address.to_be_bytes();
+            text += &ihex_record(&[&[data.len() as u8, hi, lo, 0x00], data].concat());
+        }
+        if let Some(start) = self.start {
+            let start = u32::from(start).to_be_bytes();
+            text += &ihex_record(&[&[0x04, 0x00, 0x00, 0x05][..], &start].concat());
+        }
+        text += &ihex_record(&[0x00, 0x00, 0x00, 0x01]);
+        return text;
+    }
+
+    /// Writes the image as a Motorola S-record file.
+    pub fn to_srec(&self) -> String {
+        let mut text = srec_record('0', 0, &[]);
+        for (address, data) in self.records() {
+            text += &srec_record('1', address, data);
+        }
+        text += &srec_record('9', self.start.unwrap_or(0), &[]);
+        return text;
+    }
+
+    //
+
+    /// Adds bytes at `address`, extending the previous segment if they follow on.
+    fn push(&mut self, address: u16, data: &[u8]) {
+        match self.segments.last_mut() {
+            Some((start, bytes)) if usize::from(*start) + bytes.len() == usize::from(address) => {
+                bytes.extend_from_slice(data);
+            }
+            _ => self.segments.push((address, data.to_vec())),
+        }
+    }
+
+    /// Splits the segments into records of at most [`RECORD_SIZE`] bytes.
+    fn records(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        return self.segments.iter().flat_map(|(start, bytes)| {
+            bytes
+                .chunks(RECORD_SIZE)
+                .enumerate()
+                .map(move |(index, chunk)| {
+                    (start.wrapping_add((index * RECORD_SIZE) as u16), chunk)
+                })
+        });
+    }
+}
+
+/// Formats the length, address, type and data bytes of a record and appends the
+/// checksum.
+fn ihex_record(bytes: &[u8]) -> String {
+    let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    return format!(":{}{:02X}\n", encode_hex(bytes), sum.wrapping_neg());
+}
+
+fn srec_record(kind: char, address: u16, data: &[u8]) -> String {
+    let bytes = [&[data.len() as u8 + 3], &address.to_be_bytes()[..], data].concat();
+    let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    return format!("S{}{}{:02X}\n", kind, encode_hex(&bytes), !sum);
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|b| format!("{:02X}", b)).collect();
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    return (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect();
+}
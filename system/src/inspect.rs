@@ -0,0 +1,109 @@
+//! Copies of memory for inspection, and hex dumps of them.
+//!
+//! A dump has 16 bytes per line, with the bytes changed since an earlier
+//! snapshot highlighted:
+//! ```text
+//!     $0200  a9 01 8d 00 a0*02 -- -- 00 00 00 00 00 00 00 00  |................|
+//! ```
+//! Bytes which could not be peeked at are shown as `--`.
+
+use std::fmt::Write;
+
+use crate::Range;
+
+/// The contents of a range of memory at some point in time, taken with
+/// [`crate::Memory::snapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    range: Range,
+    bytes: Vec<Option<u8>>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(range: Range, bytes: impl Iterator<Item = Option<u8>>) -> Self {
+        Self {
+            range,
+            bytes: bytes.collect(),
+        }
+    }
+
+    pub fn range(&self) -> Range {
+        return self.range;
+    }
+
+    /// Returns the byte at `address`, or `None` if it is outside of the snapshot
+    /// or could not be peeked at.
+    pub fn get(&self, address: u16) -> Option<u8> {
+        if !self.range.contains(address) {
+            return None;
+        }
+        return self.bytes[usize::from(address - self.range.start)];
+    }
+}
+
+/// A byte which differs between a snapshot and memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub address: u16,
+    pub before: Option<u8>,
+    pub after: Option<u8>,
+}
+
+/// How changed bytes are highlighted in a hex dump.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Highlight {
+    /// Changed bytes are preceded by a `*` instead of a space.
+    #[default]
+    Marker,
+    /// Changed bytes are shown in reverse video with ANSI escapes.
+    Ansi,
+}
+
+/// The bytes on each line of a hex dump.
+const LINE_SIZE: u16 = 16;
+
+/// Formats `snapshot` as a hex dump, highlighting the bytes in `changed`.
+///
+/// Lines start at multiples of 16 so that dumps of different ranges line up,
+/// and addresses before or after the snapshot are left blank.
+pub fn hex_dump(snapshot: &Snapshot, changed: &[Change], highlight: Highlight) -> String {
+    let mut text = String::new();
+    let range = snapshot.range();
+    if range.is_empty() {
+        return text;
+    }
+
+    let first = range.start / LINE_SIZE;
+    let last = range.last().unwrap() / LINE_SIZE;
+    for line in first..=last {
+        let start = line * LINE_SIZE;
+        let mut ascii = String::new();
+        write!(text, "${:04x} ", start).unwrap();
+        for address in start..start + LINE_SIZE {
+            let is_changed = changed.iter().any(|change| change.address == address);
+            let separator = match highlight {
+                Highlight::Marker if is_changed => '*',
+                _ => ' ',
+            };
+            let byte = match snapshot.get(address) {
+                _ if !range.contains(address) => "  ".to_string(),
+                Some(byte) => format!("{:02x}", byte),
+                None => "--".to_string(),
+            };
+            match highlight {
+                Highlight::Ansi if is_changed => write!(text, " \x1b[7m{}\x1b[0m", byte),
+                _ => write!(text, "{}{}", separator, byte),
+            }
+            .unwrap();
+
+            ascii.push(match snapshot.get(address) {
+                Some(byte) if byte.is_ascii_graphic() => byte as char,
+                Some(_) => '.',
+                None if range.contains(address) => '.',
+                None => ' ',
+            });
+        }
+        writeln!(text, "  |{}|", ascii).unwrap();
+    }
+    return text;
+}
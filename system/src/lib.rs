@@ -1,30 +1,135 @@
+#![allow(clippy::needless_return)]
+
+mod buslog;
+mod config;
 pub mod device;
+mod fault;
+mod image;
+mod inspect;
 mod memory;
+#[cfg(feature = "host")]
+mod profile;
+#[cfg(feature = "host")]
+mod registry;
+mod rewind;
+mod run;
+mod symbols;
+mod system;
+mod testrom;
+mod timeline;
 
-pub use crate::memory::Memory;
+pub use crate::buslog::{BusLog, Transaction};
+pub use crate::config::{ConfigError, MachineConfig};
+pub use crate::fault::{Fault, FaultEvent, FaultInjector};
+pub use crate::image::{Image, ImageError};
+pub use crate::inspect::{hex_dump, Change, Highlight, Snapshot};
+pub use crate::memory::{DeviceStats, Memory, MemoryError, Unmapped};
+#[cfg(feature = "host")]
+pub use crate::profile::{MachineProfile, ProfileError, Settings};
+#[cfg(feature = "host")]
+pub use crate::registry::{DeviceFactory, DeviceRegistry};
+pub use crate::rewind::Rewind;
+pub use crate::run::{run_program, Exit, Limits, RunReport};
+pub use crate::symbols::{HexFormatter, SymbolFormatter};
+pub use crate::system::{Pacing, Speed, System, SystemBuilder, DEFAULT_FREQUENCY};
+pub use crate::testrom::{Recognizer, RunOutcome, TestRom};
 pub use cpu::Bus;
 
-#[derive(Clone, Copy)]
+/// A half-open range of addresses: `start` is included and `end` is not.
+///
+/// The end is wider than an address so that a range can take in $FFFF, by
+/// ending at $10000. A range whose end is not past its start is empty. The
+/// constructors keep the end at or below [`Range::TOP`], and the memory map
+/// rejects a range built past it from its fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Range {
     pub start: u16,
-    pub end: u16,
+    pub end: u32,
 }
 
 impl Range {
-    pub fn new(start: u16, end: u16) -> Self {
-        Self { start, end }
+    /// The end of a range which runs to the top of the address space.
+    pub const TOP: u32 = 0x10000;
+
+    /// Returns the range from `start` up to but not including `end`.
+    ///
+    /// Panics if `end` is past [`Range::TOP`], see [`Range::checked`] for ends
+    /// which are not known to be in the address space.
+    pub fn new(start: u16, end: u32) -> Self {
+        return match Self::checked(start, end) {
+            Some(range) => range,
+            None => panic!("range end ${:x} is past ${:x}", end, Self::TOP),
+        };
+    }
+
+    /// Like [`Range::new`], but returns `None` if `end` is past [`Range::TOP`].
+    pub fn checked(start: u16, end: u32) -> Option<Self> {
+        if end > Self::TOP {
+            return None;
+        }
+        return Some(Self { start, end });
     }
 
     pub fn contains(&self, point: u16) -> bool {
-        self.start >= point && point < self.end
+        self.start <= point && u32::from(point) < self.end
+    }
+
+    /// Returns the number of addresses in the range.
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(u32::from(self.start)) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        u32::from(self.start) >= self.end
+    }
+
+    /// Returns the last address in the range, unless it is empty.
+    pub fn last(&self) -> Option<u16> {
+        if self.is_empty() {
+            return None;
+        }
+        return u16::try_from(self.end - 1).ok();
+    }
+
+    /// Returns whether any address is in both ranges.
+    pub fn overlaps(&self, other: &Range) -> bool {
+        !self.is_empty()
+            && !other.is_empty()
+            && u32::from(self.start) < other.end
+            && u32::from(other.start) < self.end
+    }
+
+    /// Returns the addresses in the range, in order.
+    pub fn iter(&self) -> std::ops::RangeInclusive<u16> {
+        (*self).into_iter()
     }
 }
 
-impl Into<std::ops::Range<u16>> for Range {
-    fn into(self) -> std::ops::Range<u16> {
-        std::ops::Range {
-            start: self.start,
-            end: self.end,
+impl IntoIterator for Range {
+    type Item = u16;
+    type IntoIter = std::ops::RangeInclusive<u16>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.last() {
+            Some(last) => self.start..=last,
+            None => {
+                // an inclusive range can only be emptied by running it out
+                let mut empty = 0..=0;
+                empty.next();
+                empty
+            }
         }
     }
 }
+
+impl From<std::ops::Range<u16>> for Range {
+    fn from(val: std::ops::Range<u16>) -> Self {
+        Self::new(val.start, u32::from(val.end))
+    }
+}
+
+impl From<std::ops::RangeInclusive<u16>> for Range {
+    fn from(val: std::ops::RangeInclusive<u16>) -> Self {
+        Self::new(*val.start(), u32::from(*val.end()) + 1)
+    }
+}
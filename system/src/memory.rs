@@ -1,112 +1,644 @@
+use std::cell::Cell;
 use std::error::Error;
+#[cfg(feature = "host")]
 use std::fs;
+#[cfg(feature = "host")]
 use std::io::Read;
-use std::ops::Range;
-use std::{cell::RefCell, iter::FromIterator, rc::Rc};
 
-use cpu::Bus;
-use intervaltree::{Element, IntervalTree};
+use cpu::{Bus, Cpu, StateError, StateReader, StateWriter};
 
-use crate::device::Device;
+use crate::device::{Device, ResetKind};
+use crate::image::{Image, ImageError};
+use crate::inspect::{Change, Snapshot};
+use crate::Range;
 
-type RcRefBox<T> = Rc<RefCell<Box<T>>>;
+/// A mapped device, which is empty while the device is being written to.
+type Slot<'a> = Option<Box<dyn Device + 'a>>;
+
+/// Access counters for a single mapped device.
+///
+/// Timestamps are taken from the clock set with [`Memory::set_cycle`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeviceStats {
+    pub reads: u64,
+    pub writes: u64,
+    pub first_access: Option<u64>,
+    pub last_read: Option<u64>,
+    pub last_write: Option<u64>,
+}
+
+/// What the bus does for addresses past the end of the installed RAM which no
+/// device is mapped to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unmapped {
+    /// Reads return the last value seen on the data bus and writes are dropped.
+    #[default]
+    OpenBus,
+    /// The address wraps around the installed RAM.
+    Mirror,
+    /// Any access panics, to catch stray pointers.
+    Panic,
+}
+
+/// A problem found while changing the memory map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MemoryError {
+    /// The bytes being loaded do not fit in RAM.
+    OutOfRange { address: u16, len: usize },
+    /// A device was mapped over addresses another device owns.
+    Overlap { range: crate::Range },
+    /// A device cannot be mapped at a range, or the range is empty.
+    Rejected { range: crate::Range },
+}
+
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemoryError::OutOfRange { address, len } => {
+                write!(f, "{} bytes at ${:04x} do not fit in ram", len, address)
+            }
+            MemoryError::Overlap { range } => write!(
+                f,
+                "requested range ${:04x}-${:04x} overlaps with an existing device",
+                range.start, range.end
+            ),
+            MemoryError::Rejected { range } => write!(
+                f,
+                "device cannot be mapped at ${:04x}-${:04x}",
+                range.start, range.end
+            ),
+        }
+    }
+}
+
+impl Error for MemoryError {}
+
+/// How the addresses of a range are decoded, for ranges which do not reach the
+/// RAM or device at their own address.
+#[derive(Clone)]
+enum Decode {
+    /// The addresses reach those of another range, wrapping around it.
+    Mirror(Range),
+    /// Nothing answers, so reads return the last value on the data bus.
+    OpenBus,
+}
+
+/// The handler for a single 256-byte page of the address space.
+#[derive(Clone)]
+enum Page {
+    /// The whole page is backed by RAM.
+    Ram,
+    /// The whole page belongs to the device at the given index.
+    Device(usize),
+    /// The page is shared, so each byte is resolved individually.
+    Split(Box<[Option<usize>; 256]>),
+}
 
 pub struct Memory<'a> {
     size: usize,
     data: Vec<u8>,
-    devices: Vec<(Range<u16>, RcRefBox<dyn Device + 'a>)>,
-    mapped: IntervalTree<u16, RcRefBox<dyn Device + 'a>>,
+    unmapped: Unmapped,
+    /// The last value driven on the data bus.
+    bus_value: Cell<u8>,
+    cycle: u64,
+    /// Every mapped device. A device is taken out of its slot while it is
+    /// being written to, so that it can be given the bus.
+    devices: Vec<(Range, Slot<'a>)>,
+    stats: Vec<Cell<DeviceStats>>,
+    pages: Vec<Page>,
+    /// The mirrored and open bus ranges, which are decoded before anything else.
+    decoders: Vec<(Range, Decode)>,
+    /// The index and previous value of every RAM byte written, while a
+    /// [`crate::Rewind`] is recording.
+    journal: Option<Vec<(usize, u8)>>,
+}
+
+impl<'a> Default for Memory<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> Memory<'a> {
+    const STATE_MAGIC: &'static [u8; 4] = b"RAM ";
+    /// Version 2 adds the state of each device after RAM.
+    const STATE_VERSION: u8 = 2;
+
+    /// Returns a memory map with RAM across the whole address space.
     pub fn new() -> Self {
-        let iter = std::iter::empty::<Element<u16, RcRefBox<dyn Device>>>();
-        let size = usize::from(u16::MAX);
+        return Self::with_ram(0x10000, Unmapped::OpenBus);
+    }
+
+    /// Returns a memory map with `size` bytes of RAM starting at $0000. What
+    /// happens above it is decided by `unmapped`.
+    pub fn with_ram(size: usize, unmapped: Unmapped) -> Self {
+        assert!(size <= 0x10000, "ram is larger than the address space");
         Self {
             size,
             data: vec![0; size],
+            unmapped,
+            bus_value: Cell::new(0),
+            cycle: 0,
             devices: vec![],
-            mapped: IntervalTree::from_iter(iter),
+            stats: vec![],
+            pages: vec![Page::Ram; 256],
+            decoders: vec![],
+            journal: None,
         }
     }
 
-    pub fn load_rom(&mut self, at_address: u16, rom: &mut fs::File) -> Result<(), Box<dyn Error>> {
-        let addr = at_address as usize;
-        if addr > self.size {
-            return Err(format!("cannot load rom at address {:#04x}", at_address).into());
+    /// Copies the file `rom` into RAM at `at_address`, or at the address in its
+    /// first two bytes if `None`, as in a `.prg` file. Returns the address it was
+    /// loaded at.
+    #[cfg(feature = "host")]
+    pub fn load_rom(
+        &mut self,
+        at_address: Option<u16>,
+        rom: &mut fs::File,
+    ) -> Result<u16, Box<dyn Error>> {
+        let mut buffer = Vec::new();
+        rom.read_to_end(&mut buffer)?;
+        let image = match at_address {
+            Some(address) => Image::from_bytes(address, &buffer),
+            None => Image::parse_prg(&buffer)?,
+        };
+        let address = image.segments[0].0;
+        self.load_image(&image)?;
+        return Ok(address);
+    }
+
+    /// Points the reset vector at `address` by writing it into RAM, where the CPU
+    /// finds it unless a device is mapped over it.
+    pub fn set_reset_vector(&mut self, address: u16) -> Result<(), MemoryError> {
+        return self.load(Cpu::RES_VECTOR, &address.to_le_bytes());
+    }
+
+    /// Points the IRQ/BRK vector at `address` by writing it into RAM, where the
+    /// CPU finds it unless a device is mapped over it.
+    pub fn set_irq_vector(&mut self, address: u16) -> Result<(), MemoryError> {
+        return self.load(Cpu::IRQ_VECTOR, &address.to_le_bytes());
+    }
+
+    /// Copies `bytes` into RAM starting at `address`, bypassing any devices.
+    pub fn load(&mut self, address: u16, bytes: &[u8]) -> Result<(), MemoryError> {
+        let addr = usize::from(address);
+        if addr + bytes.len() > self.size {
+            return Err(MemoryError::OutOfRange {
+                address,
+                len: bytes.len(),
+            });
         }
+        self.data[addr..(addr + bytes.len())].copy_from_slice(bytes);
+        return Ok(());
+    }
 
-        let metadata = rom.metadata()?;
-        if metadata.len() > ((self.size - addr) as u64) {
-            return Err("rom size exceeds available memory".into());
+    /// Copies every segment of `image` into RAM and returns where execution
+    /// starts, if the image says.
+    pub fn load_image(&mut self, image: &Image) -> Result<Option<u16>, MemoryError> {
+        for (address, bytes) in image.segments.iter() {
+            self.load(*address, bytes)?;
         }
+        return Ok(image.start);
+    }
 
-        let mut buffer = Vec::new();
-        rom.read_to_end(&mut buffer)?;
-        (&mut self.data[addr..(addr + buffer.len())]).copy_from_slice(&buffer);
-        println!("loaded {} bytes at address ${:04x}", buffer.len(), addr);
+    /// Loads an Intel HEX file into RAM and returns its start address, if any.
+    pub fn load_ihex(&mut self, text: &str) -> Result<Option<u16>, ImageError> {
+        let image = Image::parse_ihex(text)?;
+        return Ok(self.load_image(&image)?);
+    }
+
+    /// Loads a Motorola S-record file into RAM and returns its start address, if
+    /// any.
+    pub fn load_srec(&mut self, text: &str) -> Result<Option<u16>, ImageError> {
+        let image = Image::parse_srec(text)?;
+        return Ok(self.load_image(&image)?);
+    }
+
+    /// Simulates a power cycle.
+    ///
+    /// RAM is filled by repeating `pattern` (or cleared if the pattern is empty)
+    /// and every registered device receives a hard reset.
+    pub fn power_on(&mut self, pattern: &[u8]) {
+        if pattern.is_empty() {
+            self.data.fill(0);
+        } else {
+            for (byte, value) in self.data.iter_mut().zip(pattern.iter().cycle()) {
+                *byte = *value;
+            }
+        }
+        self.reset_devices(ResetKind::Hard);
+    }
+
+    /// Simulates a soft reset.
+    ///
+    /// RAM is preserved and every registered device receives a soft reset.
+    pub fn reset(&mut self) {
+        self.reset_devices(ResetKind::Soft);
+    }
+
+    /// Saves the contents of RAM, the device clock and the state of each device,
+    /// see [`Device::save_state`].
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(Self::STATE_MAGIC, Self::STATE_VERSION);
+        state.u64(self.cycle);
+        state.u32(self.data.len() as u32);
+        state.bytes(&self.data);
+        state.u32(self.devices.len() as u32);
+        for (_, device) in self.devices.iter() {
+            let device = device.as_ref().map(|d| d.save_state()).unwrap_or_default();
+            state.u32(device.len() as u32);
+            state.bytes(&device);
+        }
+        return state.finish();
+    }
+
+    /// Restores a state saved with [`Memory::save_state`] by a machine with the
+    /// same devices mapped in the same order. Memory and the devices are left
+    /// untouched if the state cannot be restored. States saved before devices
+    /// were part of them leave the devices as they are.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (mut state, version) = StateReader::new(state, Self::STATE_MAGIC, Self::STATE_VERSION)?;
+        let cycle = state.u64()?;
+        let size = state.u32()? as usize;
+        if size != self.size {
+            return Err(StateError::Invalid("ram size"));
+        }
+        let data = state.bytes(size)?;
+        let mut devices = vec![];
+        if version >= 2 {
+            let count = state.u32()? as usize;
+            if count != self.devices.len() {
+                return Err(StateError::Invalid("device count"));
+            }
+            for _ in 0..count {
+                let len = state.u32()? as usize;
+                devices.push(state.bytes(len)?);
+            }
+        }
+        state.finish()?;
+
+        // a device which fails undoes the ones loaded before it
+        let mut loaded: Vec<(usize, Vec<u8>)> = vec![];
+        for (index, device_state) in devices.into_iter().enumerate() {
+            let Some(device) = self.devices[index].1.as_mut() else {
+                continue;
+            };
+            let previous = device.save_state();
+            if let Err(err) = device.load_state(device_state) {
+                for (index, previous) in loaded {
+                    let device = self.devices[index].1.as_mut().unwrap();
+                    device
+                        .load_state(&previous)
+                        .expect("a device loads its own state");
+                }
+                return Err(err);
+            }
+            loaded.push((index, previous));
+        }
+
+        self.cycle = cycle;
+        self.data.copy_from_slice(data);
         return Ok(());
     }
 
-    pub fn register_device(&mut self, device: impl Device + 'a) {
-        // device.get_range()
+    /// Returns the byte at `address` without any side effects, or `None` for
+    /// open bus and devices which cannot be peeked at.
+    pub fn peek(&self, address: u16) -> Option<u8> {
+        let address = self.decode(address)?;
+        if let Some(index) = self.get_device_or_none(address) {
+            let (range, device) = &self.devices[index];
+            return device.as_ref()?.peek(address - range.start);
+        }
+
+        let index = usize::from(address);
+        return match self.unmapped {
+            _ if index < self.size => Some(self.data[index]),
+            Unmapped::Mirror if self.size > 0 => Some(self.data[index % self.size]),
+            _ => None,
+        };
+    }
+
+    /// Copies `range` for inspection, see [`Memory::peek`].
+    pub fn snapshot(&self, range: crate::Range) -> Snapshot {
+        return Snapshot::new(range, range.iter().map(|address| self.peek(address)));
+    }
+
+    /// Returns every byte in `range` which differs from `prev`. Addresses
+    /// outside of `prev` are not compared.
+    pub fn diff(&self, prev: &Snapshot, range: crate::Range) -> Vec<Change> {
+        return range
+            .iter()
+            .filter(|address| prev.range().contains(*address))
+            .filter_map(|address| {
+                let before = prev.get(address);
+                let after = self.peek(address);
+                (before != after).then_some(Change {
+                    address,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+    }
+
+    /// Returns whether any registered device is asserting the IRQ line.
+    pub fn irq_asserted(&self) -> bool {
+        return self
+            .devices
+            .iter()
+            .filter_map(|(_, device)| device.as_ref())
+            .any(|device| device.irq_asserted());
+    }
 
-        let iter = self.mapped.query(device.get_range().into());
-        if iter.peekable().peek().is_some() {
-            panic!("requested range overlaps with an existing device");
+    /// Maps a device at `range`. The device owns every address in the range, so
+    /// the RAM underneath is neither read nor written, and sees them as offsets
+    /// from the start of the range.
+    pub fn map(
+        &mut self,
+        range: crate::Range,
+        device: impl Device + 'a,
+    ) -> Result<(), MemoryError> {
+        return self.map_boxed(range, Box::new(device));
+    }
+
+    pub(crate) fn map_boxed(
+        &mut self,
+        range: crate::Range,
+        mut device: Box<dyn Device + 'a>,
+    ) -> Result<(), MemoryError> {
+        if self.is_claimed(range) {
+            return Err(MemoryError::Overlap { range });
+        }
+        if range.is_empty() || range.end > crate::Range::TOP || !device.mount(range) {
+            return Err(MemoryError::Rejected { range });
+        }
+        self.devices.push((range, Some(device)));
+        self.stats.push(Cell::new(DeviceStats::default()));
+        self.map_device(range, self.devices.len() - 1);
+        return Ok(());
+    }
+
+    /// Mirrors `to` at `range`, so that each address in `range` reaches the one as
+    /// far into `to`, wrapping around it when `range` is larger. This is how a
+    /// machine which does not decode every address line repeats its RAM or the
+    /// registers of a device.
+    /// ```text
+    ///     mem.mirror(Range::new(0x0800, 0x2000), Range::new(0x0000, 0x0800))?;
+    /// ```
+    /// `to` cannot be mirrored or open bus itself.
+    pub fn mirror(&mut self, range: crate::Range, to: crate::Range) -> Result<(), MemoryError> {
+        if self.is_claimed(range) {
+            return Err(MemoryError::Overlap { range });
+        }
+        let decoded = self
+            .decoders
+            .iter()
+            .any(|(decoded, _)| decoded.overlaps(&to));
+        if range.is_empty() || to.is_empty() || to.overlaps(&range) || decoded {
+            return Err(MemoryError::Rejected { range });
         }
+        if range.end > crate::Range::TOP || to.end > crate::Range::TOP {
+            return Err(MemoryError::Rejected { range });
+        }
+        self.decoders.push((range, Decode::Mirror(to)));
+        return Ok(());
+    }
+
+    /// Leaves `range` unconnected, so that reads return the last value seen on
+    /// the data bus and writes are dropped, as in the gaps of a real machine's
+    /// memory map.
+    pub fn open_bus(&mut self, range: crate::Range) -> Result<(), MemoryError> {
+        if self.is_claimed(range) {
+            return Err(MemoryError::Overlap { range });
+        }
+        if range.is_empty() || range.end > crate::Range::TOP {
+            return Err(MemoryError::Rejected { range });
+        }
+        self.decoders.push((range, Decode::OpenBus));
+        return Ok(());
+    }
 
-        let range = device.get_range();
-        self.devices
-            .push((range.into(), Rc::new(RefCell::new(Box::new(device)))));
-        self.mapped = IntervalTree::from_iter(self.devices.iter_mut().map(|t| Element {
-            range: t.0.clone(),
-            value: Rc::clone(&t.1),
-        }));
+    /// Sets the clock used to timestamp device accesses, typically the CPU cycle
+    /// counter. Every device is ticked by the number of cycles elapsed since the
+    /// previous call.
+    pub fn set_cycle(&mut self, cycle: u64) {
+        let elapsed = cycle.saturating_sub(self.cycle);
+        self.cycle = cycle;
+        if elapsed > 0 {
+            for (_, device) in self.devices.iter_mut() {
+                if let Some(device) = device {
+                    device.tick(elapsed);
+                }
+            }
+        }
+    }
+
+    /// Returns whether any mapped device keeps state of its own, which undoing
+    /// instructions with a [`crate::Rewind`] leaves as it is.
+    pub fn has_device_state(&self) -> bool {
+        return self
+            .devices
+            .iter()
+            .any(|(_, device)| device.as_ref().is_some_and(|d| !d.save_state().is_empty()));
+    }
+
+    /// Returns the access counters of every registered device along with the
+    /// range it is mapped to.
+    pub fn device_stats(&self) -> Vec<(crate::Range, DeviceStats)> {
+        return self
+            .devices
+            .iter()
+            .zip(self.stats.iter())
+            .map(|((range, _), stats)| (*range, stats.get()))
+            .collect();
+    }
+
+    /// Clears the access counters of every registered device.
+    pub fn clear_device_stats(&mut self) {
+        for stats in self.stats.iter() {
+            stats.set(DeviceStats::default());
+        }
+    }
+
+    /// Starts recording what RAM held before each write.
+    pub(crate) fn start_journal(&mut self) {
+        self.journal = Some(vec![]);
+    }
+
+    /// Stops recording and returns the writes recorded, oldest first.
+    pub(crate) fn take_journal(&mut self) -> Vec<(usize, u8)> {
+        return self.journal.take().unwrap_or_default();
+    }
+
+    /// Puts back what RAM held before the writes in `journal`.
+    pub(crate) fn undo_journal(&mut self, journal: &[(usize, u8)]) {
+        for (index, data) in journal.iter().rev() {
+            self.data[*index] = *data;
+        }
     }
 
     //
 
-    fn get_device_or_none(&self, address: u16) -> Option<RcRefBox<dyn Device + 'a>> {
-        let range = Range {
-            start: address,
-            end: address + 1,
-        };
+    fn reset_devices(&mut self, kind: ResetKind) {
+        for (_, device) in self.devices.iter_mut() {
+            if let Some(device) = device {
+                device.reset(kind);
+            }
+        }
+    }
+
+    /// Updates the page table so that every address in `range` dispatches to the
+    /// device at `index`.
+    fn map_device(&mut self, range: Range, index: usize) {
+        let pages = usize::from(range.start >> 8)..=usize::from(range.last().unwrap() >> 8);
+        for address in range {
+            let page = &mut self.pages[usize::from(address >> 8)];
+            let offset = usize::from(address & 0xFF);
+            match page {
+                Page::Ram => {
+                    let mut table = Box::new([None; 256]);
+                    table[offset] = Some(index);
+                    *page = Page::Split(table);
+                }
+                Page::Split(table) => table[offset] = Some(index),
+                Page::Device(_) => unreachable!("page is already mapped"),
+            }
+        }
 
-        let devices = self
-            .mapped
-            .query(range)
-            .map(|v| Rc::clone(&v.value))
-            .collect::<Vec<_>>();
-        assert!(devices.len() <= 1);
-        return devices.first().map(|v| Rc::clone(v));
+        // collapse pages that are owned entirely by this device
+        for page in self.pages[pages].iter_mut() {
+            if let Page::Split(table) = page {
+                if table.iter().all(|entry| *entry == Some(index)) {
+                    *page = Page::Device(index);
+                }
+            }
+        }
     }
 
-    fn read_mem(&self, address: u16) -> u8 {
+    /// Returns whether a device, mirror or open bus range has any address in
+    /// `range`.
+    fn is_claimed(&self, range: crate::Range) -> bool {
+        let decoded = self
+            .decoders
+            .iter()
+            .any(|(decoded, _)| decoded.overlaps(&range));
+        return decoded
+            || range
+                .iter()
+                .any(|address| self.get_device_or_none(address).is_some());
+    }
+
+    /// Returns the address `address` reaches once mirrors are followed, or
+    /// `None` if it is open bus.
+    #[inline]
+    fn decode(&self, address: u16) -> Option<u16> {
+        if self.decoders.is_empty() {
+            return Some(address);
+        }
+        for (range, decode) in self.decoders.iter() {
+            if range.contains(address) {
+                return match decode {
+                    Decode::Mirror(to) => {
+                        let offset = usize::from(address - range.start) % to.len();
+                        Some(to.start + offset as u16)
+                    }
+                    Decode::OpenBus => None,
+                };
+            }
+        }
+        return Some(address);
+    }
+
+    #[inline]
+    fn get_device_or_none(&self, address: u16) -> Option<usize> {
+        match &self.pages[usize::from(address >> 8)] {
+            Page::Ram => None,
+            Page::Device(index) => Some(*index),
+            Page::Split(table) => table[usize::from(address & 0xFF)],
+        }
+    }
+
+    fn count_access(&self, index: usize, write: bool) {
+        let cell = &self.stats[index];
+        let mut stats = cell.get();
+        if write {
+            stats.writes += 1;
+            stats.last_write = Some(self.cycle);
+        } else {
+            stats.reads += 1;
+            stats.last_read = Some(self.cycle);
+        }
+        stats.first_access.get_or_insert(self.cycle);
+        cell.set(stats);
+    }
+
+    /// Returns the index into RAM backing `address`, if any.
+    fn ram_index(&self, address: u16) -> Option<usize> {
         let index = usize::from(address);
-        assert!(index <= self.size - 1);
-        return self.data[index];
+        if index < self.size {
+            return Some(index);
+        }
+        return match self.unmapped {
+            Unmapped::OpenBus => None,
+            Unmapped::Mirror if self.size > 0 => Some(index % self.size),
+            Unmapped::Mirror => None,
+            Unmapped::Panic => panic!("access to unmapped address ${:04x}", address),
+        };
+    }
+
+    fn read_mem(&self, address: u16) -> u8 {
+        return match self.ram_index(address) {
+            Some(index) => self.data[index],
+            None => self.bus_value.get(),
+        };
     }
 
     fn write_mem(&mut self, address: u16, data: u8) {
-        let index = usize::from(address);
-        assert!(index <= self.size - 1);
-        self.data[index] = data;
+        if let Some(index) = self.ram_index(address) {
+            if let Some(journal) = self.journal.as_mut() {
+                journal.push((index, self.data[index]));
+            }
+            self.data[index] = data;
+        }
     }
 }
 
 impl<'a> Bus for Memory<'a> {
     fn read(&self, address: u16) -> u8 {
-        if let Some(device) = self.get_device_or_none(address) {
-            return device.borrow().read(address);
-        }
-        return self.read_mem(address);
+        let Some(address) = self.decode(address) else {
+            return self.bus_value.get();
+        };
+        let data = match self.get_device_or_none(address) {
+            Some(index) => {
+                self.count_access(index, false);
+                match &self.devices[index] {
+                    (range, Some(device)) => device.read(address - range.start),
+                    // the device is reading itself by DMA
+                    (_, None) => self.bus_value.get(),
+                }
+            }
+            None => self.read_mem(address),
+        };
+        self.bus_value.set(data);
+        return data;
     }
 
     fn write(&mut self, address: u16, data: u8) {
-        if let Some(device) = self.get_device_or_none(address) {
-            (*device).borrow_mut().write(address, data);
+        self.bus_value.set(data);
+        let Some(address) = self.decode(address) else {
+            return;
+        };
+        match self.get_device_or_none(address) {
+            Some(index) => {
+                self.count_access(index, true);
+                let offset = address - self.devices[index].0.start;
+                if let Some(mut device) = self.devices[index].1.take() {
+                    device.write_with_bus(offset, data, self);
+                    self.devices[index].1 = Some(device);
+                }
+            }
+            None => self.write_mem(address, data),
         }
-        self.write_mem(address, data);
     }
 }
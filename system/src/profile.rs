@@ -0,0 +1,485 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use cpu::{CpuVariant, SymbolTable};
+use serde::Deserialize;
+use toml::{Spanned, Value};
+
+use crate::{DeviceRegistry, Pacing, Range, SystemBuilder, Unmapped};
+
+/// A problem found while loading a [`MachineProfile`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProfileError {
+    /// The profile or a file it names could not be read.
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// The symbol file the profile names is malformed.
+    Symbols {
+        path: PathBuf,
+        error: cpu::SymbolError,
+    },
+    /// A line is not understood, or a setting is missing, unknown or has a
+    /// value which cannot be used.
+    Invalid { line: usize, message: String },
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ProfileError::Symbols { path, error } => write!(f, "{}: {}", path.display(), error),
+            ProfileError::Invalid { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ProfileError {}
+
+/// A whole machine described in a TOML file, for targets which are set up
+/// without writing Rust.
+/// ```text
+///     [machine]
+///     cpu = "65c02"
+///     frequency = 1_022_727
+///     ram = 0x8000            # bytes of ram at $0000
+///     unmapped = "open-bus"   # or "mirror" or "panic"
+///     symbols = "guest.sym"   # written by `asm -s`
+///
+///     [[rom]]
+///     file = "kernal.bin"     # relative to the profile
+///     start = 0xE000          # ends where the image does, or at `end`
+///
+///     [[device]]
+///     kind = "stdout"         # mapped at StdoutDevice::RANGE
+///
+///     [[device]]
+///     kind = "ram"
+///     name = "expansion"
+///     start = "EXPANSION"     # a public symbol
+///     end = 0xA000
+///
+///     [[mirror]]
+///     start = 0xA100
+///     end = 0xA200
+///     to_start = 0xA000
+///     to_end = 0xA001
+///
+///     [[open-bus]]
+///     start = 0xB000
+///     end = 0xC000
+/// ```
+/// The `[machine]` table also takes `pacing`, `reset_vector` and
+/// `irq_connected`. Devices are made by the factory registered for their kind
+/// in a [`DeviceRegistry`], and are mapped at the range they are usually found
+/// at unless they are given one. Settings which are not used are errors, so
+/// that misspelled ones are caught.
+///
+/// An address can be given as the name of a public symbol in the symbol file,
+/// which are those the guest program exports, so the program and the machine
+/// agree on where things are.
+pub struct MachineProfile {
+    tables: Vec<Settings>,
+}
+
+impl MachineProfile {
+    /// Parses a profile whose files are relative to the working directory.
+    pub fn parse(text: &str) -> Result<Self, ProfileError> {
+        return Ok(Self {
+            tables: parse_tables(text)?,
+        });
+    }
+
+    /// Reads the profile at `path`, whose files are relative to it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProfileError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|error| ProfileError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let mut profile = Self::parse(&text)?;
+        for table in profile.tables.iter_mut() {
+            table.base = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        }
+        return Ok(profile);
+    }
+
+    /// Reads the files the profile names and returns a builder for the machine,
+    /// whose memory map is validated when it is built. Devices can be of the
+    /// built-in kinds.
+    pub fn builder(&self) -> Result<SystemBuilder<'static>, ProfileError> {
+        return self.builder_with(&DeviceRegistry::new());
+    }
+
+    /// Like [`MachineProfile::builder`], with devices made by `registry`.
+    pub fn builder_with(
+        &self,
+        registry: &DeviceRegistry,
+    ) -> Result<SystemBuilder<'static>, ProfileError> {
+        let symbols = Rc::new(self.symbols()?);
+        for table in self.tables.iter() {
+            table.symbols.replace(Rc::clone(&symbols));
+        }
+
+        let mut builder = SystemBuilder::new();
+        for table in self.tables.iter() {
+            builder = match table.name.as_str() {
+                "machine" => machine(builder, table)?,
+                "rom" => {
+                    let file = table.required(table.string("file")?, "file")?;
+                    let path = table.required(table.path("file")?, "file")?;
+                    let image =
+                        std::fs::read(&path).map_err(|error| ProfileError::Io { path, error })?;
+                    let start = table.required(table.address("start")?, "start")?;
+                    let end = match table.end("end")? {
+                        Some(end) => end,
+                        None => match u32::from(start) + image.len() as u32 {
+                            end if end <= Range::TOP => end,
+                            _ => return Err(table.invalid("rom runs past $ffff")),
+                        },
+                    };
+                    let name = table.string("name")?.unwrap_or(file);
+                    builder.rom(name, Range::new(start, end), image)
+                }
+                "device" => {
+                    let kind = table.required(table.string("kind")?, "kind")?;
+                    let (device, usual) = registry.create(kind, table)?;
+                    let range = match table.range("start", "end")?.or(usual) {
+                        Some(range) => range,
+                        None => return Err(table.missing("start")),
+                    };
+                    let name = table.string("name")?.unwrap_or(kind);
+                    builder.device_boxed(name, range, device)
+                }
+                "mirror" => {
+                    let range = table.required(table.range("start", "end")?, "start")?;
+                    let to = table.required(table.range("to_start", "to_end")?, "to_start")?;
+                    builder.mirror(range, to)
+                }
+                "open-bus" => {
+                    let range = table.required(table.range("start", "end")?, "start")?;
+                    builder.open_bus(range)
+                }
+                _ => unreachable!("tables are checked when parsed"),
+            };
+            table.finish()?;
+        }
+        return Ok(builder);
+    }
+
+    /// Reads the symbol file named in the `[machine]` table.
+    fn symbols(&self) -> Result<SymbolTable, ProfileError> {
+        let machine = self.tables.iter().find(|table| table.name == "machine");
+        let Some(path) = machine
+            .map(|table| table.path("symbols"))
+            .transpose()?
+            .flatten()
+        else {
+            return Ok(SymbolTable::new());
+        };
+        let text = std::fs::read_to_string(&path).map_err(|error| ProfileError::Io {
+            path: path.clone(),
+            error,
+        })?;
+        return SymbolTable::parse(&text).map_err(|error| ProfileError::Symbols { path, error });
+    }
+}
+
+/// The settings of one table in a profile, e.g. one `[[device]]`, which a
+/// device factory reads the settings of its kind from.
+///
+/// Each getter returns `None` if the setting is not given and an error if it
+/// has the wrong type.
+pub struct Settings {
+    name: String,
+    /// The line of the table's header.
+    line: usize,
+    entries: Vec<Entry>,
+    /// The directory the files a profile names are relative to.
+    base: PathBuf,
+    /// The symbols addresses can be given by, which are read when the machine
+    /// is built.
+    symbols: RefCell<Rc<SymbolTable>>,
+}
+
+impl Settings {
+    fn get(&self, key: &str) -> Option<&Entry> {
+        let entry = self.entries.iter().find(|entry| entry.key == key)?;
+        entry.used.set(true);
+        return Some(entry);
+    }
+
+    pub fn string(&self, key: &str) -> Result<Option<&str>, ProfileError> {
+        return match self.get(key) {
+            Some(Entry {
+                value: Value::String(value),
+                ..
+            }) => Ok(Some(value)),
+            Some(entry) => Err(wrong_type(entry, "a string")),
+            None => Ok(None),
+        };
+    }
+
+    pub fn integer(&self, key: &str) -> Result<Option<i64>, ProfileError> {
+        return match self.get(key) {
+            Some(Entry {
+                value: Value::Integer(value),
+                ..
+            }) => Ok(Some(*value)),
+            Some(entry) => Err(wrong_type(entry, "an integer")),
+            None => Ok(None),
+        };
+    }
+
+    pub fn boolean(&self, key: &str) -> Result<Option<bool>, ProfileError> {
+        return match self.get(key) {
+            Some(Entry {
+                value: Value::Boolean(value),
+                ..
+            }) => Ok(Some(*value)),
+            Some(entry) => Err(wrong_type(entry, "true or false")),
+            None => Ok(None),
+        };
+    }
+
+    /// Returns an address, which is a number or the name of a public symbol.
+    pub fn address(&self, key: &str) -> Result<Option<u16>, ProfileError> {
+        if let Some(address) = self.symbol(key)? {
+            return Ok(Some(address));
+        }
+        return self.bounded(key, "an address", u16::try_from);
+    }
+
+    /// Returns the end of a range, which is just past its last address and so
+    /// may be 0x10000.
+    pub fn end(&self, key: &str) -> Result<Option<u32>, ProfileError> {
+        if let Some(address) = self.symbol(key)? {
+            return Ok(Some(u32::from(address)));
+        }
+        return self.bounded(key, "an address or 0x10000", |value| {
+            match u32::try_from(value) {
+                Ok(end) if end <= Range::TOP => Ok(end),
+                _ => Err(()),
+            }
+        });
+    }
+
+    pub fn byte(&self, key: &str) -> Result<Option<u8>, ProfileError> {
+        return self.bounded(key, "a byte", u8::try_from);
+    }
+
+    /// Returns the address of the public symbol a setting names, if it is a
+    /// string.
+    fn symbol(&self, key: &str) -> Result<Option<u16>, ProfileError> {
+        let Some(entry) = self.get(key) else {
+            return Ok(None);
+        };
+        let Value::String(name) = &entry.value else {
+            return Ok(None);
+        };
+        return match self.symbols.borrow().public_address(name) {
+            Some(address) => Ok(Some(address)),
+            None => Err(ProfileError::Invalid {
+                line: entry.line,
+                message: format!(
+                    "'{}' names '{}', which is not a public symbol",
+                    entry.key, name
+                ),
+            }),
+        };
+    }
+
+    fn bounded<T, E>(
+        &self,
+        key: &str,
+        expected: &str,
+        convert: fn(i64) -> Result<T, E>,
+    ) -> Result<Option<T>, ProfileError> {
+        let Some(value) = self.integer(key)? else {
+            return Ok(None);
+        };
+        return match convert(value) {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(wrong_type(self.get(key).unwrap(), expected)),
+        };
+    }
+
+    /// Returns the path a string names, relative to the profile.
+    pub fn path(&self, key: &str) -> Result<Option<PathBuf>, ProfileError> {
+        return Ok(self.string(key)?.map(|file| self.base.join(file)));
+    }
+
+    /// Returns the range between an address and an end, which are given
+    /// together or not at all.
+    pub fn range(&self, start: &str, end: &str) -> Result<Option<Range>, ProfileError> {
+        return match (self.address(start)?, self.end(end)?) {
+            (Some(start), Some(end)) => Ok(Some(Range::new(start, end))),
+            (None, None) => Ok(None),
+            (Some(_), None) => Err(self.missing(end)),
+            (None, Some(_)) => Err(self.missing(start)),
+        };
+    }
+
+    /// Returns the value of a setting which must be given.
+    pub fn required<T>(&self, value: Option<T>, key: &str) -> Result<T, ProfileError> {
+        return value.ok_or_else(|| self.missing(key));
+    }
+
+    pub fn missing(&self, key: &str) -> ProfileError {
+        return self.invalid(&format!("missing setting '{}'", key));
+    }
+
+    /// Returns an error about the table, e.g. for a value out of range.
+    pub fn invalid(&self, message: &str) -> ProfileError {
+        return ProfileError::Invalid {
+            line: self.line,
+            message: format!("{} in [{}]", message, self.name),
+        };
+    }
+
+    /// Returns an error for the first setting which was never used.
+    fn finish(&self) -> Result<(), ProfileError> {
+        return match self.entries.iter().find(|entry| !entry.used.get()) {
+            Some(entry) => Err(ProfileError::Invalid {
+                line: entry.line,
+                message: format!("unknown setting '{}' in [{}]", entry.key, self.name),
+            }),
+            None => Ok(()),
+        };
+    }
+}
+
+//
+
+/// The tables a profile can have, each of which but `[machine]` is an array of
+/// tables.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    machine: Option<Spanned<Table>>,
+    #[serde(default)]
+    rom: Vec<Spanned<Table>>,
+    #[serde(default)]
+    device: Vec<Spanned<Table>>,
+    #[serde(default)]
+    mirror: Vec<Spanned<Table>>,
+    #[serde(default, rename = "open-bus")]
+    open_bus: Vec<Spanned<Table>>,
+}
+
+type Table = BTreeMap<Spanned<String>, Spanned<Value>>;
+
+/// A setting, which is marked once it has been used.
+struct Entry {
+    key: String,
+    value: Value,
+    line: usize,
+    used: Cell<bool>,
+}
+
+/// Applies the `[machine]` table.
+fn machine(
+    mut builder: SystemBuilder<'static>,
+    table: &Settings,
+) -> Result<SystemBuilder<'static>, ProfileError> {
+    if let Some(cpu) = table.string("cpu")? {
+        let variant = match cpu.to_ascii_lowercase().as_str() {
+            "6502" => CpuVariant::Nmos6502,
+            "65c02" => CpuVariant::Cmos65C02,
+            _ => return Err(table.invalid(&format!("unknown cpu '{}'", cpu))),
+        };
+        builder = builder.variant(variant);
+    }
+    if let Some(frequency) = table.integer("frequency")? {
+        match u64::try_from(frequency) {
+            Ok(frequency) if frequency > 0 => builder = builder.frequency(frequency),
+            _ => return Err(table.invalid("frequency must be positive")),
+        }
+    }
+    if let Some(pacing) = table.string("pacing")? {
+        let pacing = match pacing {
+            "unlimited" => Pacing::Unlimited,
+            "realtime" => Pacing::Realtime,
+            _ => return Err(table.invalid(&format!("unknown pacing '{}'", pacing))),
+        };
+        builder = builder.pacing(pacing);
+    }
+    let unmapped = match table.string("unmapped")? {
+        None | Some("open-bus") => Unmapped::OpenBus,
+        Some("mirror") => Unmapped::Mirror,
+        Some("panic") => Unmapped::Panic,
+        Some(unmapped) => {
+            return Err(table.invalid(&format!("unknown unmapped policy '{}'", unmapped)))
+        }
+    };
+    if let Some(size) = table.integer("ram")? {
+        let size = usize::try_from(size).map_err(|_| table.invalid("ram is out of range"))?;
+        builder = builder.ram(size, unmapped);
+    }
+    if let Some(vector) = table.address("reset_vector")? {
+        builder = builder.reset_vector(vector);
+    }
+    if let Some(connected) = table.boolean("irq_connected")? {
+        builder = builder.irq_connected(connected);
+    }
+    return Ok(builder);
+}
+
+fn wrong_type(entry: &Entry, expected: &str) -> ProfileError {
+    return ProfileError::Invalid {
+        line: entry.line,
+        message: format!("'{}' must be {}", entry.key, expected),
+    };
+}
+
+/// Returns the tables of a profile in the order they are written.
+fn parse_tables(text: &str) -> Result<Vec<Settings>, ProfileError> {
+    let line = |offset: usize| text[..offset].matches('\n').count() + 1;
+    let document: Document = toml::from_str(text).map_err(|error| ProfileError::Invalid {
+        line: error.span().map_or(1, |span| line(span.start)),
+        message: error.message().trim_end().to_string(),
+    })?;
+
+    let named = |name: &str, tables: Vec<Spanned<Table>>| {
+        return tables
+            .into_iter()
+            .map(move |table| (name.to_string(), table))
+            .collect::<Vec<_>>();
+    };
+    let mut tables = named("machine", document.machine.into_iter().collect());
+    tables.extend(named("rom", document.rom));
+    tables.extend(named("device", document.device));
+    tables.extend(named("mirror", document.mirror));
+    tables.extend(named("open-bus", document.open_bus));
+    tables.sort_by_key(|(_, table)| table.span().start);
+
+    return Ok(tables
+        .into_iter()
+        .map(|(name, table)| {
+            let header = line(table.span().start);
+            let mut entries: Vec<Entry> = table
+                .into_inner()
+                .into_iter()
+                .map(|(key, value)| Entry {
+                    line: line(key.span().start),
+                    key: key.into_inner(),
+                    value: value.into_inner(),
+                    used: Cell::new(false),
+                })
+                .collect();
+            entries.sort_by_key(|entry| entry.line);
+            return Settings {
+                name,
+                line: header,
+                entries,
+                base: PathBuf::new(),
+                symbols: RefCell::default(),
+            };
+        })
+        .collect());
+}
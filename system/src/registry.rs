@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::device::{
+    AciaDevice, Device, DipSwitchDevice, DiskDevice, RamDevice, RandomDevice, StdinDevice,
+    StdioBackend, StdoutDevice, TextDisplayDevice, ViaDevice,
+};
+use crate::{ProfileError, Range, Settings};
+
+/// Makes devices of one kind from their settings in a [`crate::MachineProfile`].
+///
+/// Any `Fn(&Settings)` with the same result is a factory.
+pub trait DeviceFactory: Send + Sync {
+    /// Returns a new device, along with the range it is usually mapped at if it
+    /// has one. The settings every device has, `kind`, `name`, `start` and
+    /// `end`, are read by the profile.
+    fn create(&self, settings: &Settings)
+        -> Result<(Box<dyn Device>, Option<Range>), ProfileError>;
+}
+
+impl<F> DeviceFactory for F
+where
+    F: Fn(&Settings) -> Result<(Box<dyn Device>, Option<Range>), ProfileError> + Send + Sync,
+{
+    fn create(
+        &self,
+        settings: &Settings,
+    ) -> Result<(Box<dyn Device>, Option<Range>), ProfileError> {
+        return self(settings);
+    }
+}
+
+/// The device kinds a [`crate::MachineProfile`] can name, so that crates with
+/// devices of their own can have profiles use them.
+/// ```text
+///     let mut registry = DeviceRegistry::new();
+///     registry.register("sid", |settings: &Settings| {
+///         let model = settings.integer("model")?.unwrap_or(6581);
+///         Ok((Box::new(Sid::new(model)) as Box<dyn Device>, Some(Sid::RANGE)))
+///     });
+///     let system = MachineProfile::load("c64.toml")?.builder_with(&registry)?;
+/// ```
+/// A new registry has the built-in kinds, with the settings each takes:
+/// ```text
+///     ram         start, end
+///     stdout
+///     stdin
+///     display
+///     random      seed
+///     dipswitch   value
+///     acia        the host's terminal
+///     disk        file
+///     via         nothing connected to its ports
+/// ```
+pub struct DeviceRegistry {
+    factories: HashMap<String, Box<dyn DeviceFactory>>,
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeviceRegistry {
+    /// Returns a registry with the built-in kinds.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register("ram", |_: &Settings| -> Result<_, ProfileError> {
+            return Ok((Box::new(RamDevice::new()) as Box<dyn Device>, None));
+        });
+        registry.register("stdout", |_: &Settings| -> Result<_, ProfileError> {
+            return Ok((
+                Box::new(StdoutDevice::new()) as Box<dyn Device>,
+                Some(StdoutDevice::RANGE),
+            ));
+        });
+        registry.register("stdin", |_: &Settings| -> Result<_, ProfileError> {
+            return Ok((
+                Box::new(StdinDevice::new()) as Box<dyn Device>,
+                Some(StdinDevice::RANGE),
+            ));
+        });
+        registry.register("display", |_: &Settings| -> Result<_, ProfileError> {
+            return Ok((
+                Box::new(TextDisplayDevice::new()) as Box<dyn Device>,
+                Some(TextDisplayDevice::RANGE),
+            ));
+        });
+        registry.register("random", |settings: &Settings| {
+            let seed = settings.integer("seed")?.unwrap_or(1);
+            let seed = u32::try_from(seed).map_err(|_| settings.invalid("seed is out of range"))?;
+            return Ok((
+                Box::new(RandomDevice::new(seed)) as Box<dyn Device>,
+                Some(RandomDevice::RANGE),
+            ));
+        });
+        registry.register("dipswitch", |settings: &Settings| {
+            let (device, _) = DipSwitchDevice::new(settings.byte("value")?.unwrap_or(0));
+            return Ok((
+                Box::new(device) as Box<dyn Device>,
+                Some(DipSwitchDevice::RANGE),
+            ));
+        });
+        registry.register("acia", |_: &Settings| -> Result<_, ProfileError> {
+            return Ok((
+                Box::new(AciaDevice::new(StdioBackend::new())) as Box<dyn Device>,
+                Some(AciaDevice::<StdioBackend>::RANGE),
+            ));
+        });
+        registry.register("disk", |settings: &Settings| {
+            let path = settings.required(settings.path("file")?, "file")?;
+            let device =
+                DiskDevice::open(&path).map_err(|error| ProfileError::Io { path, error })?;
+            return Ok((Box::new(device) as Box<dyn Device>, Some(DiskDevice::RANGE)));
+        });
+        registry.register("via", |_: &Settings| -> Result<_, ProfileError> {
+            return Ok((
+                Box::new(ViaDevice::new()) as Box<dyn Device>,
+                Some(ViaDevice::RANGE),
+            ));
+        });
+        return registry;
+    }
+
+    /// Returns a registry without any kinds.
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers the factory for devices of `kind`, replacing any there was.
+    pub fn register(&mut self, kind: &str, factory: impl DeviceFactory + 'static) {
+        self.factories.insert(kind.to_string(), Box::new(factory));
+    }
+
+    /// Returns whether there is a factory for `kind`.
+    pub fn contains(&self, kind: &str) -> bool {
+        return self.factories.contains_key(kind);
+    }
+
+    /// Returns the registered kinds, in alphabetical order.
+    pub fn kinds(&self) -> Vec<&str> {
+        let mut kinds = self
+            .factories
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        kinds.sort();
+        return kinds;
+    }
+
+    /// Makes a device of `kind` with the factory registered for it.
+    pub fn create(
+        &self,
+        kind: &str,
+        settings: &Settings,
+    ) -> Result<(Box<dyn Device>, Option<Range>), ProfileError> {
+        return match self.factories.get(kind) {
+            Some(factory) => factory.create(settings),
+            None => Err(settings.invalid(&format!("unknown device kind '{}'", kind))),
+        };
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use cpu::Cpu;
+
+use crate::Memory;
+
+/// What it takes to undo one recorded step.
+struct Undo {
+    /// The cycle the step started on.
+    cycle: u64,
+    /// The state of the CPU before the step.
+    cpu: Vec<u8>,
+    /// The RAM bytes the step overwrote, oldest first.
+    writes: Vec<(usize, u8)>,
+}
+
+/// The last few instructions run through it, kept so that they can be undone to
+/// step a program backwards in a debugger.
+///
+/// Each instruction is kept as the state of the CPU before it ran and the bytes
+/// of RAM it overwrote, so it costs a few dozen bytes rather than a copy of
+/// memory. Devices keep their own state and are not rewound, though RAM they
+/// write to by DMA is.
+/// ```text
+///     let mut rewind = Rewind::new(1000);
+///     rewind.record(&mut cpu, &mut mem, |cpu, mem| debugger.step_instruction(cpu, mem));
+///     rewind.step_back(&mut cpu, &mut mem, 1);
+/// ```
+pub struct Rewind {
+    capacity: usize,
+    history: VecDeque<Undo>,
+}
+
+impl Rewind {
+    /// Returns a history which keeps the last `capacity` steps.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Runs `step` and remembers how to undo it. A step is usually a single
+    /// instruction, run through a debugger or not.
+    pub fn record<R>(
+        &mut self,
+        cpu: &mut Cpu,
+        memory: &mut Memory,
+        step: impl FnOnce(&mut Cpu, &mut Memory) -> R,
+    ) -> R {
+        let cycle = cpu.cycles();
+        let state = cpu.save_state();
+        memory.start_journal();
+        let result = step(cpu, memory);
+        let writes = memory.take_journal();
+
+        if self.capacity > 0 {
+            if self.history.len() == self.capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back(Undo {
+                cycle,
+                cpu: state,
+                writes,
+            });
+        }
+        return result;
+    }
+
+    /// Runs one instruction, see [`Rewind::record`].
+    pub fn step_instruction(&mut self, cpu: &mut Cpu, memory: &mut Memory) -> u64 {
+        return self.record(cpu, memory, |cpu, memory| cpu.step_instruction(memory));
+    }
+
+    /// Undoes up to `count` of the latest steps, newest first, and returns how
+    /// many were undone.
+    pub fn step_back(&mut self, cpu: &mut Cpu, memory: &mut Memory, count: usize) -> usize {
+        for undone in 0..count {
+            let Some(undo) = self.history.pop_back() else {
+                return undone;
+            };
+            memory.undo_journal(&undo.writes);
+            cpu.load_state(&undo.cpu)
+                .expect("the cpu loads its own state");
+        }
+        return count;
+    }
+
+    /// Returns the cycle the oldest step which can be undone started on.
+    pub(crate) fn earliest_cycle(&self) -> Option<u64> {
+        return self.history.front().map(|undo| undo.cycle);
+    }
+
+    /// Returns the number of steps which can be undone.
+    pub fn len(&self) -> usize {
+        return self.history.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.history.is_empty();
+    }
+
+    /// Forgets every step, for when the machine has changed in a way which was
+    /// not recorded.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+}
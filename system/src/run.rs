@@ -0,0 +1,131 @@
+use cpu::{Cpu, Jam};
+
+use crate::{Memory, MemoryError};
+
+/// The opcode of `BRK`.
+const BRK: u8 = 0x00;
+
+/// When [`run_program`] gives up on a program which has not exited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub instructions: u64,
+    pub cycles: u64,
+}
+
+impl Default for Limits {
+    /// Ten million cycles, which is ten seconds at 1 MHz.
+    fn default() -> Self {
+        Self {
+            instructions: u64::MAX,
+            cycles: 10_000_000,
+        }
+    }
+}
+
+impl Limits {
+    /// Returns limits of `instructions` instructions and no cycle limit.
+    pub fn instructions(instructions: u64) -> Self {
+        Self {
+            instructions,
+            cycles: u64::MAX,
+        }
+    }
+
+    /// Returns limits of `cycles` cycles and no instruction limit.
+    pub fn cycles(cycles: u64) -> Self {
+        Self {
+            instructions: u64::MAX,
+            cycles,
+        }
+    }
+}
+
+/// Why [`run_program`] stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// The next instruction was a `BRK` at `pc`, which was not executed.
+    Brk {
+        pc: u16,
+    },
+    /// The instruction at `pc` jumped or branched to itself.
+    Loop {
+        pc: u16,
+    },
+    /// An undefined opcode locked up the CPU.
+    Jammed(Jam),
+    InstructionLimit,
+    CycleLimit,
+}
+
+/// The state a program was left in by [`run_program`].
+pub struct RunReport {
+    pub exit: Exit,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub pc: u16,
+    /// The status register, `NV-BDIZC` from bit 7 down.
+    pub status: u8,
+    pub cycles: u64,
+    pub instructions: u64,
+    /// The memory the program ran against, to check its results in.
+    pub memory: Memory<'static>,
+}
+
+/// Loads `rom` into RAM at `load_addr` and runs it from `entry` on a powered on
+/// NMOS CPU until it exits or reaches one of `limits`.
+///
+/// There are no devices, so a run only depends on its arguments. A program exits
+/// when it reaches a `BRK`, when it ends in a `jmp *` (or branch-to-self) loop,
+/// or when it jams the CPU. The cycles of the reset sequence are not counted.
+pub fn run_program(
+    rom: &[u8],
+    load_addr: u16,
+    entry: u16,
+    limits: Limits,
+) -> Result<RunReport, MemoryError> {
+    let mut memory = Memory::new();
+    memory.load(load_addr, rom)?;
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut memory);
+    cpu.registers.pc.set(entry);
+    let start = cpu.cycles();
+
+    let mut instructions = 0;
+    let exit = loop {
+        let pc = cpu.registers.pc.get();
+        if memory.peek(pc) == Some(BRK) {
+            break Exit::Brk { pc };
+        }
+        if instructions >= limits.instructions {
+            break Exit::InstructionLimit;
+        }
+        if cpu.cycles() - start >= limits.cycles {
+            break Exit::CycleLimit;
+        }
+
+        cpu.step_instruction(&mut memory);
+        instructions += 1;
+        if let Some(jam) = cpu.jammed() {
+            break Exit::Jammed(jam);
+        }
+        if cpu.registers.pc.get() == pc {
+            break Exit::Loop { pc };
+        }
+    };
+
+    return Ok(RunReport {
+        exit,
+        a: cpu.registers.acc.get(),
+        x: cpu.registers.x.get(),
+        y: cpu.registers.y.get(),
+        sp: cpu.registers.sp.get(),
+        pc: cpu.registers.pc.get(),
+        status: cpu.status.get_raw(),
+        cycles: cpu.cycles() - start,
+        instructions,
+        memory,
+    });
+}
@@ -0,0 +1,38 @@
+/// Renders addresses for traces, dumps and other debugging output.
+///
+/// Everything that prints an address goes through this hook so that a project
+/// can apply its own conventions, like `bank:symbol+offset` on banked systems.
+/// Any `Fn(u16) -> Option<String>` closure is a formatter, with `None` falling
+/// back to plain hex.
+pub trait SymbolFormatter {
+    fn format_address(&self, address: u16) -> String;
+}
+
+/// The default formatter, which prints addresses as `$xxxx`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HexFormatter;
+
+impl SymbolFormatter for HexFormatter {
+    fn format_address(&self, address: u16) -> String {
+        return format!("${:04x}", address);
+    }
+}
+
+impl<F> SymbolFormatter for F
+where
+    F: Fn(u16) -> Option<String>,
+{
+    fn format_address(&self, address: u16) -> String {
+        return self(address).unwrap_or_else(|| HexFormatter.format_address(address));
+    }
+}
+
+/// Shows the names from a symbol file, falling back to plain hex.
+impl SymbolFormatter for cpu::SymbolTable {
+    fn format_address(&self, address: u16) -> String {
+        return match self.name(address) {
+            Some(name) => name.to_string(),
+            None => HexFormatter.format_address(address),
+        };
+    }
+}
@@ -0,0 +1,494 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cpu::{Cpu, CpuVariant};
+
+use crate::device::Device;
+use crate::timeline::Timeline;
+use crate::{ConfigError, Image, MachineConfig, Memory, Range, Rewind, Unmapped};
+
+/// The clock rate durations are converted to cycles at, unless one is set with
+/// [`SystemBuilder::frequency`].
+pub const DEFAULT_FREQUENCY: u64 = 1_000_000;
+
+/// The emulated time run between checks against the host clock.
+const SLICE: Duration = Duration::from_millis(1);
+/// How far a paced system may fall behind the host before it stops trying to
+/// catch up.
+const MAX_LAG: Duration = Duration::from_millis(100);
+/// The most cycles one instruction takes, which is what the undocumented
+/// read-modify-write opcodes take with indirect addressing. Seeking runs whole
+/// instructions until it is this close.
+const LONGEST_STEP: u64 = 8;
+
+/// How a [`System`] keeps time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pacing {
+    /// Run as fast as the host allows.
+    #[default]
+    Unlimited,
+    /// Run at the clock rate in step with the host clock, sleeping whenever
+    /// emulation gets ahead. Without the `host` feature there is no host clock
+    /// and this runs as fast as [`Pacing::Unlimited`].
+    Realtime,
+}
+
+/// The speed a [`System`] actually ran at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Speed {
+    pub cycles: u64,
+    /// The host time the cycles took, including any time spent sleeping.
+    pub elapsed: Duration,
+}
+
+impl Speed {
+    /// Returns the effective clock rate in Hz.
+    pub fn hz(&self) -> f64 {
+        return self.cycles as f64 / self.elapsed.as_secs_f64();
+    }
+}
+
+impl std::fmt::Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{:.3} MHz", self.hz() / 1_000_000.0);
+    }
+}
+
+/// A whole machine: a CPU and the memory map it runs against.
+///
+/// Stepping the system keeps the two in sync, so devices are ticked by the
+/// cycles the CPU spends and the IRQ line follows the devices. With
+/// [`Pacing::Realtime`] the system is also held to its clock rate: emulated time
+/// is measured from a fixed point on the host clock, so oversleeping in one slice
+/// is made up in the next rather than accumulating as drift.
+/// ```text
+///     let mut system = System::builder()
+///         .device("stdout", StdoutDevice::RANGE, StdoutDevice::new())
+///         .rom("program", Range::new(0x1000, 0x2000), program)
+///         .reset_vector(0x1000)
+///         .frequency(1_022_727)
+///         .pacing(Pacing::Realtime)
+///         .build()?;
+///     system.run_for(Duration::from_millis(10));
+///     println!("running at {}", system.speed().unwrap());
+/// ```
+pub struct System<'a> {
+    cpu: Cpu,
+    memory: Memory<'a>,
+    frequency: u64,
+    pacing: Pacing,
+    /// The host instant and cycle count paced time is measured from.
+    anchor: Option<(Instant, u64)>,
+    speed: Option<Speed>,
+    rewind: Option<Rewind>,
+    timeline: Option<Timeline>,
+    /// What is loaded into RAM at power-on: the images the system was built
+    /// with, and the reset vector if RAM holds it.
+    boot: Vec<Image>,
+}
+
+impl<'a> System<'a> {
+    pub fn builder() -> SystemBuilder<'a> {
+        return SystemBuilder::new();
+    }
+
+    /// Returns a system made of an existing CPU and memory map, which are used
+    /// as they are.
+    pub fn new(cpu: Cpu, memory: Memory<'a>) -> Self {
+        Self {
+            cpu,
+            memory,
+            frequency: DEFAULT_FREQUENCY,
+            pacing: Pacing::Unlimited,
+            anchor: None,
+            speed: None,
+            rewind: None,
+            timeline: None,
+            boot: vec![],
+        }
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        return &self.cpu;
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        return &mut self.cpu;
+    }
+
+    pub fn memory(&self) -> &Memory<'a> {
+        return &self.memory;
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory<'a> {
+        return &mut self.memory;
+    }
+
+    /// Returns the clock rate in Hz.
+    pub fn frequency(&self) -> u64 {
+        return self.frequency;
+    }
+
+    /// Sets the clock rate in Hz, which must not be zero.
+    pub fn set_frequency(&mut self, frequency: u64) {
+        assert!(frequency > 0, "clock rate must not be zero");
+        self.frequency = frequency;
+        self.anchor = None;
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        return self.pacing;
+    }
+
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        self.pacing = pacing;
+        self.anchor = None;
+    }
+
+    /// Returns the speed the last call to [`System::run_for`] or
+    /// [`System::run_for_cycles`] ran at. Always `None` without the `host`
+    /// feature, which the host clock is part of.
+    pub fn speed(&self) -> Option<Speed> {
+        return self.speed;
+    }
+
+    /// Keeps the last `capacity` instructions run, so that they can be undone
+    /// with [`System::step_back`]. A capacity of zero stops keeping them.
+    pub fn set_rewind(&mut self, capacity: usize) {
+        self.rewind = (capacity > 0).then(|| Rewind::new(capacity));
+    }
+
+    /// Undoes up to `count` of the latest instructions and returns how many were
+    /// undone. Devices are not rewound, see [`Rewind`].
+    pub fn step_back(&mut self, count: usize) -> usize {
+        return match self.rewind.as_mut() {
+            Some(rewind) => rewind.step_back(&mut self.cpu, &mut self.memory, count),
+            None => 0,
+        };
+    }
+
+    /// Saves the state of the machine every `interval` cycles, keeping the last
+    /// `capacity` of them, so that [`System::seek_to_cycle`] can go back to the
+    /// cycles they cover. Each holds a copy of RAM. An interval or capacity of
+    /// zero stops saving them.
+    pub fn set_snapshots(&mut self, interval: u64, capacity: usize) {
+        self.timeline = (interval > 0 && capacity > 0).then(|| Timeline::new(interval, capacity));
+    }
+
+    /// Moves the machine to `cycle`, which may be in the middle of an
+    /// instruction, and returns false if it cannot go back that far.
+    ///
+    /// Going forward runs the machine. Going back undoes instructions if the
+    /// rewind history reaches far enough, or else restores the latest saved
+    /// state before `cycle`, and then replays up to it. Saved states include
+    /// the devices, see [`Memory::save_state`], but undone instructions leave
+    /// them as they are, see [`Rewind`], so the rewind history is only used
+    /// when no mapped device keeps state of its own.
+    pub fn seek_to_cycle(&mut self, cycle: u64) -> bool {
+        if cycle < self.cpu.cycles() {
+            let stateless = !self.memory.has_device_state();
+            let rewound = match self.rewind.as_mut() {
+                Some(rewind)
+                    if stateless && rewind.earliest_cycle().is_some_and(|c| c <= cycle) =>
+                {
+                    while self.cpu.cycles() > cycle {
+                        rewind.step_back(&mut self.cpu, &mut self.memory, 1);
+                    }
+                    true
+                }
+                _ => false,
+            };
+            if !rewound {
+                let Some(timeline) = self.timeline.as_mut() else {
+                    return false;
+                };
+                if !timeline.restore(cycle, &mut self.cpu, &mut self.memory) {
+                    return false;
+                }
+                if let Some(rewind) = self.rewind.as_mut() {
+                    rewind.clear();
+                }
+            }
+            if let Some(timeline) = self.timeline.as_mut() {
+                timeline.forget_after(self.cpu.cycles());
+            }
+        }
+
+        while self.cpu.cycles() + LONGEST_STEP <= cycle {
+            self.step();
+        }
+        // the rest a cycle at a time, so as not to run past it
+        while self.cpu.cycles() < cycle {
+            if self.cpu.at_instruction_boundary() {
+                if let Some(timeline) = self.timeline.as_mut() {
+                    timeline.record(&self.cpu, &self.memory);
+                }
+                self.memory.set_cycle(self.cpu.cycles());
+                self.cpu.set_irq_line(self.memory.irq_asserted());
+            }
+            let run = |cpu: &mut Cpu, memory: &mut Memory| loop {
+                cpu.step_cycle(memory);
+                if cpu.at_instruction_boundary() || cpu.cycles() >= cycle {
+                    break;
+                }
+            };
+            match self.rewind.as_mut() {
+                Some(rewind) => rewind.record(&mut self.cpu, &mut self.memory, run),
+                None => run(&mut self.cpu, &mut self.memory),
+            }
+        }
+        return true;
+    }
+
+    /// Simulates a power cycle of the whole machine.
+    ///
+    /// RAM is filled by repeating `pattern` and the devices receive a hard reset,
+    /// see [`Memory::power_on`]. The images the system was built with are then
+    /// loaded again, as is a reset vector stored in RAM, and the CPU is powered
+    /// on. Anything else in RAM is lost, and so is the rewind history and every
+    /// saved state, as the cycle count starts over.
+    pub fn power_on(&mut self, pattern: &[u8]) {
+        self.memory.power_on(pattern);
+        self.memory.set_cycle(0);
+        for image in self.boot.iter() {
+            self.memory
+                .load_image(image)
+                .expect("boot images were loaded when the system was built");
+        }
+        self.cpu.power_on(&mut self.memory);
+
+        self.anchor = None;
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.clear();
+        }
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.clear();
+        }
+    }
+
+    /// Simulates a soft reset of the devices and the CPU. RAM is preserved.
+    pub fn reset(&mut self) {
+        self.memory.reset();
+        self.cpu.reset(&mut self.memory);
+    }
+
+    /// Runs one instruction and returns the number of cycles it took.
+    pub fn step(&mut self) -> u64 {
+        if let Some(timeline) = self.timeline.as_mut() {
+            if self.cpu.at_instruction_boundary() {
+                timeline.record(&self.cpu, &self.memory);
+            }
+        }
+        self.memory.set_cycle(self.cpu.cycles());
+        self.cpu.set_irq_line(self.memory.irq_asserted());
+        return match self.rewind.as_mut() {
+            Some(rewind) => rewind.step_instruction(&mut self.cpu, &mut self.memory),
+            None => self.cpu.step_instruction(&mut self.memory),
+        };
+    }
+
+    /// Runs whole instructions until at least `cycles` cycles have elapsed and
+    /// returns by how many cycles the budget was overshot, like
+    /// [`Cpu::run_for_cycles`].
+    ///
+    /// Time spent outside of the run loop counts against a paced system, which
+    /// runs without sleeping until it has caught up, unless it has fallen too
+    /// far behind.
+    pub fn run_for_cycles(&mut self, cycles: u64) -> u64 {
+        let started = host_now();
+        let start = self.cpu.cycles();
+        let end = start + cycles;
+        if self.pacing == Pacing::Realtime && self.anchor.is_none() {
+            self.anchor = started.map(|started| (started, start));
+        }
+
+        let slice = self.cycles_in(SLICE).max(1);
+        while self.cpu.cycles() < end {
+            let slice_end = match self.pacing {
+                Pacing::Unlimited => end,
+                Pacing::Realtime => end.min(self.cpu.cycles() + slice),
+            };
+            while self.cpu.cycles() < slice_end {
+                self.step();
+            }
+            if self.pacing == Pacing::Realtime {
+                self.pace();
+            }
+        }
+
+        self.speed = started.map(|started| Speed {
+            cycles: self.cpu.cycles() - start,
+            elapsed: started.elapsed(),
+        });
+        return self.cpu.cycles() - end;
+    }
+
+    /// Runs for the number of cycles that fit in `duration` at the clock rate and
+    /// returns the overshoot in cycles. This is emulated time, not time on the
+    /// host.
+    pub fn run_for(&mut self, duration: Duration) -> u64 {
+        return self.run_for_cycles(self.cycles_in(duration));
+    }
+
+    //
+
+    /// Returns the number of cycles that fit in `duration` at the clock rate.
+    fn cycles_in(&self, duration: Duration) -> u64 {
+        return (duration.as_nanos() * u128::from(self.frequency) / 1_000_000_000) as u64;
+    }
+
+    /// Sleeps until the host clock catches up with emulated time, or moves the
+    /// anchor forward if the host is too far ahead.
+    fn pace(&mut self) {
+        let (Some((instant, cycle)), Some(now)) = (self.anchor, host_now()) else {
+            return;
+        };
+        let emulated = u128::from(self.cpu.cycles() - cycle) * 1_000_000_000;
+        let target = instant + Duration::from_nanos((emulated / u128::from(self.frequency)) as u64);
+
+        if target > now {
+            thread::sleep(target - now);
+        } else if now - target > MAX_LAG {
+            self.anchor = Some((now, self.cpu.cycles()));
+        }
+    }
+}
+
+/// Returns the time on the host clock, or `None` when built without the `host`
+/// feature since `Instant::now` panics on targets without a clock.
+fn host_now() -> Option<Instant> {
+    #[cfg(feature = "host")]
+    return Some(Instant::now());
+    #[cfg(not(feature = "host"))]
+    return None;
+}
+
+/// Describes a [`System`], whose memory map is validated as a whole like a
+/// [`MachineConfig`].
+pub struct SystemBuilder<'a> {
+    config: MachineConfig<'a>,
+    images: Vec<Image>,
+    reset_vector: Option<u16>,
+    variant: CpuVariant,
+    frequency: u64,
+    pacing: Pacing,
+}
+
+impl<'a> Default for SystemBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> SystemBuilder<'a> {
+    pub fn new() -> Self {
+        Self {
+            config: MachineConfig::new(),
+            images: vec![],
+            reset_vector: None,
+            variant: CpuVariant::default(),
+            frequency: DEFAULT_FREQUENCY,
+            pacing: Pacing::Unlimited,
+        }
+    }
+
+    /// Sets the size of the RAM at $0000 and what the addresses above it do.
+    pub fn ram(mut self, size: usize, unmapped: Unmapped) -> Self {
+        self.config = self.config.ram(size, unmapped);
+        self
+    }
+
+    /// Adds a device mapped at `range`.
+    pub fn device(mut self, name: &str, range: Range, device: impl Device + 'a) -> Self {
+        self.config = self.config.device(name, range, device);
+        self
+    }
+
+    /// Like [`SystemBuilder::device`], for a device which is already boxed.
+    pub fn device_boxed(mut self, name: &str, range: Range, device: Box<dyn Device + 'a>) -> Self {
+        self.config = self.config.device_boxed(name, range, device);
+        self
+    }
+
+    /// Adds a write-protected ROM at `range` whose image is loaded at its start.
+    pub fn rom(mut self, name: &str, range: Range, image: Vec<u8>) -> Self {
+        self.config = self.config.rom(name, range, image);
+        self
+    }
+
+    /// Loads an image into RAM. If the image has a start address it is used as
+    /// the reset vector, unless one is set explicitly.
+    pub fn image(mut self, image: Image) -> Self {
+        self.images.push(image);
+        self
+    }
+
+    /// Sets the reset vector explicitly instead of taking it from a ROM or image.
+    pub fn reset_vector(mut self, address: u16) -> Self {
+        self.reset_vector = Some(address);
+        self
+    }
+
+    /// Mirrors `to` at `range`, see [`Memory::mirror`].
+    pub fn mirror(mut self, range: Range, to: Range) -> Self {
+        self.config = self.config.mirror(range, to);
+        self
+    }
+
+    /// Leaves `range` unconnected, see [`Memory::open_bus`].
+    pub fn open_bus(mut self, range: Range) -> Self {
+        self.config = self.config.open_bus(range);
+        self
+    }
+
+    /// Sets whether device interrupts reach the CPU.
+    pub fn irq_connected(mut self, connected: bool) -> Self {
+        self.config = self.config.irq_connected(connected);
+        self
+    }
+
+    pub fn variant(mut self, variant: CpuVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// Sets the clock rate in Hz, which must not be zero.
+    pub fn frequency(mut self, frequency: u64) -> Self {
+        assert!(frequency > 0, "clock rate must not be zero");
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Validates the memory map, builds the system and powers it on.
+    pub fn build(self) -> Result<System<'a>, Vec<ConfigError>> {
+        let start = self.images.iter().find_map(|image| image.start);
+        let mut config = self.config;
+        if let Some(vector) = self.reset_vector.or(start) {
+            config = config.reset_vector(vector);
+        }
+
+        let ram_vector = config.ram_reset_vector();
+        let mut memory = config.build()?;
+        for image in self.images.iter() {
+            memory
+                .load_image(image)
+                .map_err(|err| vec![ConfigError::Image(err)])?;
+        }
+
+        let mut system = System::new(Cpu::with_variant(self.variant), memory);
+        if let Some(vector) = ram_vector {
+            let vector = Image::from_bytes(Cpu::RES_VECTOR, &vector.to_le_bytes());
+            system.boot.push(vector);
+        }
+        system.boot.extend(self.images);
+        system.frequency = self.frequency;
+        system.pacing = self.pacing;
+        system.power_on(&[]);
+        return Ok(system);
+    }
+}
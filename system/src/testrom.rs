@@ -0,0 +1,137 @@
+use cpu::{Bus, Cpu};
+
+/// The result of running a test ROM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The ROM signaled success.
+    Passed,
+    /// The ROM signaled failure. The meaning of `code` depends on the recognizer
+    /// that detected it (a trap address or the value written to a status byte).
+    Failed { code: u16 },
+    /// The instruction limit was reached before the ROM signaled anything.
+    Timeout,
+}
+
+/// An idiom used by a test ROM to report its result.
+#[derive(Clone, Copy, Debug)]
+pub enum Recognizer {
+    /// The ROM ends in a tight `jmp *` (or branch-to-self) loop. Trapping at
+    /// `success` means the ROM passed, trapping anywhere else is a failure with
+    /// the trap address as the code.
+    TrapAt { success: u16 },
+    /// The ROM writes a status byte to `address`. Writing `pass` means the ROM
+    /// passed, any other value is a failure with the value as the code.
+    WriteTo { address: u16, pass: u8 },
+}
+
+/// A description of how to run a third-party test ROM and detect its result.
+/// ```text
+///     let outcome = TestRom::new(100_000_000)
+///         .trap_at(0x3469)
+///         .run(&mut cpu, &mut mem);
+/// ```
+pub struct TestRom {
+    max_instructions: u64,
+    recognizers: Vec<Recognizer>,
+}
+
+impl TestRom {
+    /// Returns a new test description which gives up after `max_instructions`.
+    pub fn new(max_instructions: u64) -> Self {
+        Self {
+            max_instructions,
+            recognizers: vec![],
+        }
+    }
+
+    /// Adds a [`Recognizer::TrapAt`] recognizer.
+    pub fn trap_at(mut self, success: u16) -> Self {
+        self.recognizers.push(Recognizer::TrapAt { success });
+        self
+    }
+
+    /// Adds a [`Recognizer::WriteTo`] recognizer.
+    pub fn write_to(mut self, address: u16, pass: u8) -> Self {
+        self.recognizers.push(Recognizer::WriteTo { address, pass });
+        self
+    }
+
+    /// Runs the CPU from its current state until a recognizer fires or the
+    /// instruction limit is reached.
+    pub fn run(&self, cpu: &mut Cpu, bus: &mut dyn Bus) -> RunOutcome {
+        for _ in 0..self.max_instructions {
+            let pc = cpu.registers.pc.get();
+            let mut watched = WatchedBus {
+                bus: &mut *bus,
+                recognizers: &self.recognizers,
+                outcome: None,
+            };
+            cpu.step_instruction(&mut watched);
+            if let Some(outcome) = watched.outcome {
+                return outcome;
+            }
+
+            if cpu.registers.pc.get() == pc {
+                if let Some(outcome) = self.check_trap(pc) {
+                    return outcome;
+                }
+            }
+        }
+        return RunOutcome::Timeout;
+    }
+
+    fn check_trap(&self, pc: u16) -> Option<RunOutcome> {
+        for recognizer in self.recognizers.iter() {
+            if let Recognizer::TrapAt { success } = *recognizer {
+                if pc == success {
+                    return Some(RunOutcome::Passed);
+                }
+                return Some(RunOutcome::Failed { code: pc });
+            }
+        }
+        return None;
+    }
+}
+
+/// A bus wrapper which checks every write against the `WriteTo` recognizers.
+struct WatchedBus<'a> {
+    bus: &'a mut dyn Bus,
+    recognizers: &'a [Recognizer],
+    outcome: Option<RunOutcome>,
+}
+
+impl Bus for WatchedBus<'_> {
+    fn read(&self, address: u16) -> u8 {
+        return self.bus.read(address);
+    }
+
+    fn fetch(&self, address: u16) -> u8 {
+        return self.bus.fetch(address);
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.bus.write(address, data);
+        if self.outcome.is_some() {
+            return;
+        }
+
+        for recognizer in self.recognizers {
+            if let Recognizer::WriteTo {
+                address: watched,
+                pass,
+            } = *recognizer
+            {
+                if address != watched {
+                    continue;
+                }
+
+                self.outcome = if data == pass {
+                    Some(RunOutcome::Passed)
+                } else {
+                    Some(RunOutcome::Failed { code: data as u16 })
+                };
+                return;
+            }
+        }
+    }
+}
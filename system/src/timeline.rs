@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+
+use cpu::Cpu;
+
+use crate::Memory;
+
+/// The saved state of a machine at an instruction boundary.
+struct Keyframe {
+    cycle: u64,
+    cpu: Vec<u8>,
+    memory: Vec<u8>,
+}
+
+/// Saved states of a machine taken every so many cycles, which seeking back
+/// restores the nearest of and replays from.
+pub(crate) struct Timeline {
+    interval: u64,
+    capacity: usize,
+    keyframes: VecDeque<Keyframe>,
+}
+
+impl Timeline {
+    /// Returns a timeline which keeps the last `capacity` states, taken at
+    /// least `interval` cycles apart.
+    pub(crate) fn new(interval: u64, capacity: usize) -> Self {
+        Self {
+            interval,
+            capacity,
+            keyframes: VecDeque::new(),
+        }
+    }
+
+    /// Saves the state of the machine if `interval` cycles have passed since
+    /// the last one was.
+    pub(crate) fn record(&mut self, cpu: &Cpu, memory: &Memory) {
+        let cycle = cpu.cycles();
+        if let Some(last) = self.keyframes.back() {
+            if cycle < last.cycle + self.interval {
+                return;
+            }
+        }
+        if self.keyframes.len() == self.capacity {
+            self.keyframes.pop_front();
+        }
+        self.keyframes.push_back(Keyframe {
+            cycle,
+            cpu: cpu.save_state(),
+            memory: memory.save_state(),
+        });
+    }
+
+    /// Restores the latest state saved at or before `cycle`, and returns false
+    /// if there is none.
+    pub(crate) fn restore(&mut self, cycle: u64, cpu: &mut Cpu, memory: &mut Memory) -> bool {
+        let Some(keyframe) = self.keyframes.iter().rev().find(|k| k.cycle <= cycle) else {
+            return false;
+        };
+        cpu.load_state(&keyframe.cpu)
+            .expect("the cpu loads its own state");
+        memory
+            .load_state(&keyframe.memory)
+            .expect("memory loads its own state");
+        return true;
+    }
+
+    /// Forgets every saved state.
+    pub(crate) fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    /// Forgets the states saved after `cycle`, which the machine may no longer
+    /// pass through.
+    pub(crate) fn forget_after(&mut self, cycle: u64) {
+        while self.keyframes.back().is_some_and(|k| k.cycle > cycle) {
+            self.keyframes.pop_back();
+        }
+    }
+}
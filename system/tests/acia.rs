@@ -0,0 +1,77 @@
+//! The 6551 ACIA talking over an in-memory serial line.
+#![allow(clippy::needless_return)]
+
+use std::sync::mpsc;
+
+use system::device::{AciaDevice, ChannelBackend};
+use system::{Bus, Image, Memory, System};
+
+//        lda #$0b
+//        sta $a042
+// poll:  lda $a041
+//        and #$08
+//        beq poll
+//        lda $a040
+//        sta $a040
+//        jmp poll
+const ECHO: [u8; 21] = [
+    0xA9, 0x0B, 0x8D, 0x42, 0xA0, 0xAD, 0x41, 0xA0, 0x29, 0x08, 0xF0, 0xF9, 0xAD, 0x40, 0xA0, 0x8D,
+    0x40, 0xA0, 0x4C, 0x05, 0x02,
+];
+
+#[test]
+fn program_echoes_received_bytes() {
+    let (host_tx, rx) = mpsc::channel();
+    let (tx, host_rx) = mpsc::channel();
+    let mut system = System::builder()
+        .device(
+            "acia",
+            AciaDevice::<ChannelBackend>::RANGE,
+            AciaDevice::new(ChannelBackend::new(rx, tx)),
+        )
+        .image(Image::from_bytes(0x0200, &ECHO).start(0x0200))
+        .build()
+        .unwrap();
+
+    for byte in b"hello" {
+        host_tx.send(*byte).unwrap();
+    }
+    system.run_for_cycles(10 * AciaDevice::<ChannelBackend>::POLL_CYCLES);
+    assert_eq!(host_rx.try_iter().collect::<Vec<_>>(), b"hello");
+}
+
+#[test]
+fn received_bytes_raise_interrupts() {
+    let (host_tx, rx) = mpsc::channel();
+    let (tx, host_rx) = mpsc::channel();
+    let mut mem = Memory::new();
+    mem.map(
+        AciaDevice::<ChannelBackend>::RANGE,
+        AciaDevice::new(ChannelBackend::new(rx, tx)),
+    )
+    .unwrap();
+
+    // the receiver is off until dtr is set
+    host_tx.send(b'a').unwrap();
+    mem.set_cycle(1000);
+    assert_eq!(mem.read(0xA041), 0x10);
+
+    // dtr, receiver interrupts on and the transmitter off
+    mem.write(0xA042, 0x01);
+    mem.set_cycle(2000);
+    assert!(mem.irq_asserted());
+    assert_eq!(mem.read(0xA041), 0x98);
+    assert!(!mem.irq_asserted());
+    assert_eq!(mem.read(0xA041), 0x18);
+    assert_eq!(mem.read(0xA040), b'a');
+    assert_eq!(mem.read(0xA041), 0x10);
+
+    mem.write(0xA040, b'b');
+    assert!(host_rx.try_recv().is_err());
+
+    // a programmed reset leaves the control register alone
+    mem.write(0xA043, 0x1F);
+    mem.write(0xA041, 0x00);
+    assert_eq!(mem.read(0xA042), 0x02);
+    assert_eq!(mem.read(0xA043), 0x1F);
+}
@@ -0,0 +1,49 @@
+//! The beeper and the samples the host pulls from it.
+#![allow(clippy::needless_return)]
+
+use system::device::{BeeperDevice, Device};
+use system::{Image, System};
+
+#[test]
+fn samples_follow_the_speaker_line() {
+    let (mut beeper, speaker) = BeeperDevice::new();
+    beeper.tick(100);
+    beeper.write(0, 0);
+    beeper.tick(150);
+    beeper.read(0);
+    beeper.tick(150);
+    assert!(!speaker.level());
+
+    // a sample every 100 cycles
+    let mut buffer = [9.0; 8];
+    assert_eq!(speaker.fill(&mut buffer, 1, 100), 4);
+    assert_eq!(buffer[..4], [0.0, 1.0, 0.5, 0.0]);
+
+    // nothing more has run
+    assert_eq!(speaker.fill(&mut buffer, 1, 100), 0);
+    beeper.tick(100);
+    assert_eq!(speaker.fill(&mut buffer, 1, 100), 1);
+}
+
+#[test]
+fn programs_play_square_waves() {
+    // loop: sta $a080; ldx #$13; delay: dex; bne delay; jmp loop
+    let program = [
+        0x8D, 0x80, 0xA0, 0xA2, 0x13, 0xCA, 0xD0, 0xFD, 0x4C, 0x00, 0x02,
+    ];
+    let (beeper, speaker) = BeeperDevice::new();
+    let mut system = System::builder()
+        .device("beeper", BeeperDevice::RANGE, beeper)
+        .image(Image::from_bytes(0x0200, &program).start(0x0200))
+        .frequency(1_000_000)
+        .build()
+        .unwrap();
+    system.run_for_cycles(100_000);
+    system.step();
+
+    // 100 ms at 10 kHz, with the line high half the time
+    let mut buffer = vec![0.0; 2000];
+    assert_eq!(speaker.fill(&mut buffer, 10_000, system.frequency()), 1000);
+    let mean = buffer[..1000].iter().sum::<f32>() / 1000.0;
+    assert!((mean - 0.5).abs() < 0.01, "{}", mean);
+}
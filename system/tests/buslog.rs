@@ -0,0 +1,70 @@
+//! Watching the transactions on a bus.
+#![allow(clippy::needless_return)]
+
+use std::sync::{Arc, Mutex};
+
+use cpu::Cpu;
+use system::{BusLog, Memory, Transaction};
+
+// lda #$01; sta $0300; lda #$02; sta $0300
+const PROGRAM: [u8; 10] = [0xA9, 0x01, 0x8D, 0x00, 0x03, 0xA9, 0x02, 0x8D, 0x00, 0x03];
+
+fn boot() -> (Cpu, BusLog<Memory<'static>>) {
+    let mut mem = Memory::new();
+    mem.load(0x0200, &PROGRAM).unwrap();
+    mem.set_reset_vector(0x0200).unwrap();
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    return (cpu, BusLog::new(mem, 0));
+}
+
+/// Steps the CPU a cycle at a time through the whole program.
+fn run(cpu: &mut Cpu, bus: &mut BusLog<Memory<'static>>) {
+    let mut step = |cpu: &mut Cpu| {
+        bus.set_memory_cycle(cpu.cycles());
+        cpu.step_cycle(bus);
+    };
+    while cpu.registers.pc.get() < 0x020A {
+        step(cpu);
+    }
+    // the last store writes after its operand is read
+    step(cpu);
+}
+
+#[test]
+fn observers_see_every_transaction() {
+    let (mut cpu, mut bus) = boot();
+    let seen = Arc::new(Mutex::new(Vec::<Transaction>::new()));
+    let log = Arc::clone(&seen);
+    bus.add_observer(move |t| log.lock().unwrap().push(*t));
+    run(&mut cpu, &mut bus);
+
+    // the ring keeps nothing, but the observer saw all of it
+    assert!(bus.transactions().is_empty());
+    let seen = seen.lock().unwrap();
+    let fetches = seen.iter().filter(|t| t.sync).map(|t| t.address);
+    assert_eq!(
+        fetches.collect::<Vec<_>>(),
+        [0x0200, 0x0202, 0x0205, 0x0207]
+    );
+    assert!(seen.windows(2).all(|pair| pair[0].cycle <= pair[1].cycle));
+}
+
+#[test]
+fn observers_spy_on_an_address() {
+    let (mut cpu, mut bus) = boot();
+    let writes = Arc::new(Mutex::new(vec![]));
+    let log = Arc::clone(&writes);
+    bus.add_observer(move |t| {
+        if t.write && t.address == 0x0300 {
+            log.lock().unwrap().push((t.cycle, t.data));
+        }
+    });
+    run(&mut cpu, &mut bus);
+
+    let writes = writes.lock().unwrap();
+    assert_eq!(writes.len(), 2);
+    assert_eq!((writes[0].1, writes[1].1), (0x01, 0x02));
+    // lda #imm and sta abs take six cycles between the stores
+    assert_eq!(writes[1].0 - writes[0].0, 6);
+}
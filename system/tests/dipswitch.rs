@@ -0,0 +1,66 @@
+//! DIP switches set by the host and read by the guest.
+#![allow(clippy::needless_return)]
+
+use system::device::{Device, DipSwitchDevice, ResetKind};
+use system::{Bus, Memory, Range};
+
+const SWITCHES: u16 = DipSwitchDevice::RANGE.start;
+const CONTROL: u16 = DipSwitchDevice::RANGE.start + 1;
+
+#[test]
+fn guests_read_what_the_host_sets() {
+    let (mut device, switches) = DipSwitchDevice::new(0x81);
+    assert!(!device.mount(Range::new(0xA010, 0xA011)));
+    assert_eq!(device.read(0), 0x81);
+
+    switches.set(0x0F);
+    switches.set_switch(7, true);
+    switches.set_switch(0, false);
+    assert_eq!(switches.get(), 0x8E);
+    assert_eq!(device.read(0), 0x8E);
+
+    // the guest cannot move the switches
+    device.write(0, 0x00);
+    assert_eq!(switches.get(), 0x8E);
+}
+
+#[test]
+fn changes_raise_an_irq_until_acknowledged() {
+    let (device, switches) = DipSwitchDevice::new(0x00);
+    let mut mem = Memory::new();
+    mem.map(DipSwitchDevice::RANGE, device).unwrap();
+
+    // a change with the irq disabled is only flagged
+    switches.set(0x01);
+    assert_eq!(mem.read(CONTROL), 0x80);
+    assert!(!mem.irq_asserted());
+
+    mem.write(CONTROL, 0x01);
+    assert_eq!(mem.read(CONTROL), 0x81);
+    assert!(mem.irq_asserted());
+
+    // acknowledging keeps the irq enabled but releases the line
+    mem.write(CONTROL, 0x81);
+    assert_eq!(mem.read(CONTROL), 0x01);
+    assert!(!mem.irq_asserted());
+
+    // setting the same positions again is not a change
+    switches.set(0x01);
+    assert!(!mem.irq_asserted());
+    switches.set_switch(1, true);
+    assert!(mem.irq_asserted());
+    assert_eq!(mem.read(SWITCHES), 0x03);
+}
+
+#[test]
+fn resets_keep_the_switch_positions() {
+    let (mut device, switches) = DipSwitchDevice::new(0x00);
+    device.write(1, 0x01);
+    switches.set(0x42);
+    assert!(device.irq_asserted());
+
+    device.reset(ResetKind::Hard);
+    assert!(!device.irq_asserted());
+    assert_eq!(device.read(1), 0x00);
+    assert_eq!(device.read(0), 0x42);
+}
@@ -0,0 +1,69 @@
+//! Sector transfers between the disk device and memory.
+#![allow(clippy::needless_return)]
+
+use std::io::Cursor;
+
+use system::device::{Device, DiskDevice};
+use system::{Bus, Memory};
+
+/// Issues a command for `sector` with the buffer at `buffer` and returns the
+/// status.
+fn command(mem: &mut Memory, command: u8, sector: u16, buffer: u16) -> u8 {
+    for (offset, byte) in [sector.to_le_bytes(), buffer.to_le_bytes()]
+        .concat()
+        .iter()
+        .enumerate()
+    {
+        mem.write(0xA050 + offset as u16, *byte);
+    }
+    mem.write(0xA054, command);
+    return mem.read(0xA054);
+}
+
+#[test]
+fn sectors_are_copied_by_dma() {
+    let image = (0..512).map(|i| (i / 2) as u8).collect::<Vec<_>>();
+    let mut mem = Memory::new();
+    let disk = DiskDevice::new(Cursor::new(image)).unwrap();
+    mem.map(DiskDevice::RANGE, disk).unwrap();
+
+    assert_eq!(command(&mut mem, 1, 1, 0x0300), 0x00);
+    assert_eq!(mem.read(0x0300), 0x80);
+    assert_eq!(mem.read(0x03FF), 0xFF);
+    assert_eq!(mem.read(0xA052), 0x00);
+    assert_eq!(mem.read(0xA053), 0x03);
+
+    mem.load(0x0400, &[0x42; 256]).unwrap();
+    assert_eq!(command(&mut mem, 2, 0, 0x0400), 0x00);
+    assert_eq!(command(&mut mem, 1, 0, 0x0500), 0x00);
+    assert_eq!(mem.read(0x05FF), 0x42);
+
+    // past the end of the disk, and an unknown command
+    assert_eq!(command(&mut mem, 1, 2, 0x0300), 0x01);
+    assert_eq!(command(&mut mem, 3, 0, 0x0300), 0x01);
+}
+
+#[test]
+fn commands_need_a_bus() {
+    let mut disk = DiskDevice::new(Cursor::new(vec![0; 256])).unwrap();
+    assert_eq!(disk.sectors(), 1);
+    disk.write(4, 1);
+    assert_eq!(disk.read(4), 0x01);
+}
+
+#[test]
+fn image_files_are_written_back() {
+    let path = std::env::temp_dir().join(format!("rs6502-disk-{}.img", std::process::id()));
+    std::fs::write(&path, vec![0; 512]).unwrap();
+
+    let mut mem = Memory::new();
+    mem.map(DiskDevice::RANGE, DiskDevice::open(&path).unwrap())
+        .unwrap();
+    mem.load(0x0300, &[0x5A; 256]).unwrap();
+    assert_eq!(command(&mut mem, 2, 1, 0x0300), 0x00);
+
+    let image = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(image[..256], [0; 256]);
+    assert_eq!(image[256..], [0x5A; 256]);
+}
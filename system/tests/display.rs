@@ -0,0 +1,68 @@
+//! Drawing the text display on a captured terminal.
+#![allow(clippy::needless_return)]
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use system::device::{Device, TextDisplayDevice};
+use system::{Bus, Memory};
+
+/// A terminal which keeps everything written to it.
+#[derive(Clone, Default)]
+struct Terminal(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        return Ok(());
+    }
+}
+
+impl Terminal {
+    fn take(&self) -> String {
+        let output = std::mem::take(&mut *self.0.lock().unwrap());
+        return String::from_utf8(output).unwrap();
+    }
+}
+
+#[test]
+fn screen_is_drawn_when_it_changes() {
+    let terminal = Terminal::default();
+    let mut display = TextDisplayDevice::with_output(terminal.clone());
+    display.write(40, b'h');
+    display.write(41, b'i');
+    display.write(42, 0x07);
+    assert_eq!(display.read(41), b'i');
+    assert!(display.text().starts_with("\nhi\n\n"));
+
+    display.render().unwrap();
+    let frame = terminal.take();
+    assert!(frame.starts_with(&format!("\x1b[1;1H{:40}\x1b[2;1Hhi{:38}", "", "")));
+    assert_eq!(frame.matches("\x1b[").count(), 25);
+
+    // nothing has changed since
+    display.write(41, b'i');
+    display.render().unwrap();
+    assert_eq!(terminal.take(), "");
+}
+
+#[test]
+fn screen_is_refreshed_by_the_guest_and_the_clock() {
+    let terminal = Terminal::default();
+    let mut mem = Memory::new();
+    let display = TextDisplayDevice::with_output(terminal.clone()).refresh_every(1000);
+    mem.map(TextDisplayDevice::RANGE, display).unwrap();
+
+    mem.write(0x83E8, 0x00);
+    assert!(terminal.take().contains("\x1b[25;1H"));
+
+    mem.write(0x8000, b'x');
+    mem.set_cycle(999);
+    assert_eq!(terminal.take(), "");
+    mem.set_cycle(1000);
+    assert!(terminal.take().starts_with("\x1b[1;1Hx"));
+}
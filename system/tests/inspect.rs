@@ -0,0 +1,70 @@
+//! Snapshots, diffs and hex dumps of memory.
+#![allow(clippy::needless_return)]
+
+use system::device::{RomDevice, StdoutDevice};
+use system::{hex_dump, Bus, Change, Highlight, Memory, Range};
+
+#[test]
+fn diffs_report_changed_bytes() {
+    let mut mem = Memory::new();
+    mem.load(0x0200, b"Hello").unwrap();
+    let before = mem.snapshot(Range::new(0x0200, 0x0210));
+    assert_eq!(before.get(0x0200), Some(b'H'));
+    assert_eq!(before.get(0x0210), None);
+
+    mem.write(0x0201, b'a');
+    mem.write(0x0210, 0xFF);
+    assert_eq!(
+        mem.diff(&before, Range::new(0x0000, 0x1000)),
+        [Change {
+            address: 0x0201,
+            before: Some(b'e'),
+            after: Some(b'a'),
+        }]
+    );
+}
+
+#[test]
+fn snapshots_do_not_touch_devices() {
+    let mut mem = Memory::new();
+    mem.map(Range::new(0x1000, 0x1004), RomDevice::new(&[1, 2, 3]))
+        .unwrap();
+    mem.map(StdoutDevice::RANGE, StdoutDevice::new()).unwrap();
+
+    let snapshot = mem.snapshot(Range::new(0x0FFF, 0x1005));
+    assert_eq!(snapshot.get(0x1001), Some(2));
+    assert_eq!(snapshot.get(0x1003), Some(0));
+    assert_eq!(mem.peek(StdoutDevice::RANGE.start), None);
+    assert_eq!(mem.device_stats()[0].1.reads, 0);
+}
+
+#[test]
+fn dumps_highlight_changes() {
+    let mut mem = Memory::new();
+    mem.map(Range::new(0x0206, 0x0208), StdoutDevice::new())
+        .unwrap();
+    mem.load(0x0200, &[0xA9, 0x01, 0x8D, 0x00, 0xA0]).unwrap();
+    let before = mem.snapshot(Range::new(0x0200, 0x0210));
+    mem.write(0x0205, b'!');
+    let changed = mem.diff(&before, before.range());
+    let after = mem.snapshot(Range::new(0x0202, 0x0209));
+
+    assert_eq!(
+        hex_dump(&after, &changed, Highlight::Marker),
+        "$0200        8d 00 a0*21 -- -- 00                       |  ...!...       |\n"
+    );
+    assert_eq!(
+        hex_dump(&after, &changed, Highlight::Ansi),
+        "$0200        8d 00 a0 \x1b[7m21\x1b[0m -- -- 00                       |  ...!...       |\n"
+    );
+    assert_eq!(
+        hex_dump(
+            &mem.snapshot(Range::new(0x00F8, 0x0108)),
+            &[],
+            Highlight::Marker
+        )
+        .lines()
+        .count(),
+        2
+    );
+}
@@ -0,0 +1,244 @@
+//! Dispatch between RAM, devices and unmapped addresses.
+#![allow(clippy::needless_return)]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use cpu::Cpu;
+use system::device::{Device, RamDevice, StdinDevice, StdoutDevice};
+use system::{Bus, Image, ImageError, Memory, MemoryError, Range, Unmapped};
+
+#[test]
+fn devices_own_their_window() {
+    let mut mem = Memory::with_ram(0x1000, Unmapped::Mirror);
+    mem.map(Range::new(0x1000, 0x1100), RamDevice::new())
+        .unwrap();
+
+    mem.write(0x1000, 0x42);
+    assert_eq!(mem.read(0x1000), 0x42);
+    assert_eq!(mem.read(0x0000), 0x00);
+}
+
+#[test]
+fn unmapped_reads_follow_the_policy() {
+    let mut mirrored = Memory::with_ram(0x0800, Unmapped::Mirror);
+    mirrored.write(0x0801, 0x11);
+    assert_eq!(mirrored.read(0x0001), 0x11);
+    assert_eq!(mirrored.read(0x1801), 0x11);
+
+    let mut open = Memory::with_ram(0x0800, Unmapped::OpenBus);
+    open.write(0x0010, 0x22);
+    assert_eq!(open.read(0x0010), 0x22);
+    open.write(0x4000, 0x33);
+    assert_eq!(open.read(0x4000), 0x33);
+    open.read(0x0000);
+    assert_eq!(open.read(0x4000), 0x00);
+}
+
+#[test]
+#[should_panic(expected = "access to unmapped address $4000")]
+fn unmapped_access_can_panic() {
+    let mem = Memory::with_ram(0x0800, Unmapped::Panic);
+    mem.read(0x4000);
+}
+
+#[test]
+fn invalid_changes_return_errors() {
+    let mut mem = Memory::with_ram(0x0800, Unmapped::OpenBus);
+    assert_eq!(
+        mem.load(0x07FF, &[0, 0]),
+        Err(MemoryError::OutOfRange {
+            address: 0x07FF,
+            len: 2
+        })
+    );
+
+    mem.map(StdoutDevice::RANGE, StdoutDevice::new()).unwrap();
+    assert_eq!(
+        mem.map(StdoutDevice::RANGE, StdoutDevice::new()),
+        Err(MemoryError::Overlap {
+            range: Range::new(0xA000, 0xA001)
+        })
+    );
+    assert_eq!(
+        mem.map(Range::new(0xB000, 0xB001), StdinDevice::new()),
+        Err(MemoryError::Rejected {
+            range: Range::new(0xB000, 0xB001)
+        })
+    );
+}
+
+/// A device which counts the cycles it has been ticked by.
+struct Timer {
+    elapsed: Arc<AtomicU64>,
+}
+
+impl Device for Timer {
+    fn read(&self, _: u16) -> u8 {
+        return 0;
+    }
+
+    fn write(&mut self, _: u16, _: u8) {}
+
+    fn tick(&mut self, cycles: u64) {
+        self.elapsed.fetch_add(cycles, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn devices_are_ticked_by_elapsed_cycles() {
+    let elapsed = Arc::new(AtomicU64::new(0));
+    let mut mem = Memory::new();
+    mem.map(
+        Range::new(0xB000, 0xB001),
+        Timer {
+            elapsed: Arc::clone(&elapsed),
+        },
+    )
+    .unwrap();
+
+    mem.set_cycle(7);
+    mem.set_cycle(7);
+    assert_eq!(elapsed.load(Ordering::Relaxed), 7);
+    mem.set_cycle(12);
+    assert_eq!(elapsed.load(Ordering::Relaxed), 12);
+}
+
+#[test]
+fn hex_images_load_at_their_address() {
+    let image = Image::from_bytes(0x1000, &[0xA9, 0x48, 0x8D, 0x00, 0xA0]).start(0x1000);
+
+    let mut mem = Memory::new();
+    assert_eq!(mem.load_ihex(&image.to_ihex()), Ok(Some(0x1000)));
+    assert_eq!(mem.read(0x1000), 0xA9);
+    assert_eq!(mem.read(0x1004), 0xA0);
+
+    let mut mem = Memory::new();
+    assert_eq!(mem.load_srec(&image.to_srec()), Ok(Some(0x1000)));
+    assert_eq!(mem.read(0x1001), 0x48);
+}
+
+#[test]
+fn hex_records_are_checked() {
+    let mut mem = Memory::new();
+    let ihex = ":0300300002337A1E\n:00000001FF\n";
+    assert_eq!(mem.load_ihex(ihex), Ok(None));
+    assert_eq!(mem.read(0x0031), 0x33);
+
+    assert_eq!(
+        mem.load_ihex(":0300300002337A1F\n"),
+        Err(ImageError::Checksum { line: 1 })
+    );
+    assert_eq!(
+        mem.load_srec("S1130000285F\n"),
+        Err(ImageError::Malformed { line: 1 })
+    );
+    assert_eq!(
+        mem.load_ihex(":020000040001F9\n:01000000FF00\n"),
+        Err(ImageError::Address { line: 2 })
+    );
+}
+
+#[test]
+fn prg_files_load_at_their_header() {
+    let path = std::env::temp_dir().join(format!("rs6502-{}.prg", std::process::id()));
+    std::fs::write(&path, [0x00, 0x10, 0xA9, 0x48]).unwrap();
+
+    let mut mem = Memory::new();
+    let mut file = std::fs::File::open(&path).unwrap();
+    assert_eq!(mem.load_rom(None, &mut file).unwrap(), 0x1000);
+    assert_eq!(mem.read(0x1000), 0xA9);
+    assert_eq!(mem.read(0x1001), 0x48);
+
+    // without the header the whole file is loaded where it is asked to be
+    let mut file = std::fs::File::open(&path).unwrap();
+    assert_eq!(mem.load_rom(Some(0x2000), &mut file).unwrap(), 0x2000);
+    assert_eq!(mem.read(0x2002), 0xA9);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(Image::parse_prg(&[0x00]), Err(ImageError::Truncated));
+    let image = Image::parse_prg(&[0xFF, 0xFF, 0x01, 0x02]).unwrap();
+    assert_eq!(
+        mem.load_image(&image),
+        Err(MemoryError::OutOfRange {
+            address: 0xFFFF,
+            len: 2
+        })
+    );
+}
+
+#[test]
+fn vectors_can_be_set_in_ram() {
+    let mut mem = Memory::new();
+    mem.set_reset_vector(0x1234).unwrap();
+    mem.set_irq_vector(0x5678).unwrap();
+    assert_eq!((mem.read(0xFFFC), mem.read(0xFFFD)), (0x34, 0x12));
+    assert_eq!((mem.read(0xFFFE), mem.read(0xFFFF)), (0x78, 0x56));
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    assert_eq!(cpu.registers.pc.get(), 0x1234);
+
+    let mut mem = Memory::with_ram(0x8000, Unmapped::OpenBus);
+    assert!(mem.set_reset_vector(0x1234).is_err());
+}
+
+#[test]
+fn mirrors_wrap_around_their_target() {
+    let mut mem = Memory::new();
+    mem.mirror(Range::new(0x0800, 0x2000), Range::new(0x0000, 0x0800))
+        .unwrap();
+    mem.map(Range::new(0x4000, 0x4008), RamDevice::new())
+        .unwrap();
+    mem.mirror(Range::new(0x4008, 0x5000), Range::new(0x4000, 0x4008))
+        .unwrap();
+
+    mem.write(0x1801, 0x11);
+    assert_eq!(mem.read(0x0001), 0x11);
+    assert_eq!(mem.read(0x0801), 0x11);
+    // the device sees the address its register is mirrored from
+    mem.write(0x4fff, 0x22);
+    assert_eq!(mem.read(0x4007), 0x22);
+    assert_eq!(mem.peek(0x400f), Some(0x22));
+}
+
+#[test]
+fn open_bus_returns_the_last_value() {
+    let mut mem = Memory::new();
+    mem.open_bus(Range::new(0x5000, 0x6000)).unwrap();
+
+    mem.write(0x0010, 0x42);
+    mem.write(0x5000, 0x99);
+    assert_eq!(mem.read(0x5000), 0x99);
+    mem.read(0x0010);
+    assert_eq!(mem.read(0x5fff), 0x42);
+    assert_eq!(mem.peek(0x5000), None);
+}
+
+#[test]
+fn mirrors_cannot_overlap() {
+    let mut mem = Memory::new();
+    mem.map(StdoutDevice::RANGE, StdoutDevice::new()).unwrap();
+    mem.mirror(Range::new(0x0800, 0x1000), Range::new(0x0000, 0x0800))
+        .unwrap();
+
+    let range = Range::new(0x9000, 0xB000);
+    assert_eq!(
+        mem.mirror(range, Range::new(0x0000, 0x0100)),
+        Err(MemoryError::Overlap { range })
+    );
+    let range = Range::new(0x0c00, 0x0d00);
+    assert_eq!(mem.open_bus(range), Err(MemoryError::Overlap { range }));
+    // a mirror cannot reach another
+    let range = Range::new(0x2000, 0x2800);
+    assert_eq!(
+        mem.mirror(range, Range::new(0x0800, 0x1000)),
+        Err(MemoryError::Rejected { range })
+    );
+    assert_eq!(
+        mem.map(Range::new(0x0f00, 0x1000), RamDevice::new()),
+        Err(MemoryError::Overlap {
+            range: Range::new(0x0f00, 0x1000)
+        })
+    );
+}
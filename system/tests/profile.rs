@@ -0,0 +1,248 @@
+//! Machines described by TOML profiles.
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::path::PathBuf;
+
+use cpu::CpuVariant;
+use system::device::Device;
+use system::{Bus, ConfigError, DeviceRegistry, MachineProfile, Range, Settings};
+
+// lda #$42; sta $4200; sed
+const PROGRAM: [u8; 6] = [0xA9, 0x42, 0x8D, 0x00, 0x42, 0xF8];
+
+const PROFILE: &str = r#"
+[machine]
+cpu = "65c02"
+frequency = 1_022_727   # an apple ii
+ram = 0x4000
+reset_vector = 0xF000
+
+[[rom]]
+name = "kernal"
+file = "kernal.bin"
+start = 0xF000
+
+[[device]]
+kind = "random"
+seed = 7
+
+[[mirror]]
+start = 0x4000
+end = 0x8000
+to_start = 0x0000
+to_end = 0x4000
+"#;
+
+/// A third-party device which reads back a constant.
+struct Latch {
+    value: u8,
+}
+
+impl Device for Latch {
+    fn read(&self, _: u16) -> u8 {
+        return self.value;
+    }
+
+    fn write(&mut self, _: u16, _: u8) {}
+}
+
+/// Returns a directory of its own for each test to write files into.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("profile-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+fn error(text: &str) -> String {
+    let profile = match MachineProfile::parse(text) {
+        Ok(profile) => profile,
+        Err(err) => return err.to_string(),
+    };
+    return profile.builder().err().unwrap().to_string();
+}
+
+#[test]
+fn profiles_build_a_whole_machine() {
+    let dir = scratch("machine");
+    let mut kernal = vec![0; 0x1000];
+    kernal[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+    fs::write(dir.join("kernal.bin"), kernal).unwrap();
+    fs::write(dir.join("machine.toml"), PROFILE).unwrap();
+
+    let profile = MachineProfile::load(dir.join("machine.toml")).unwrap();
+    let mut system = profile.builder().unwrap().build().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(system.cpu().variant(), CpuVariant::Cmos65C02);
+    assert_eq!(system.frequency(), 1_022_727);
+    while !system.cpu().status.get_decimal_mode() {
+        system.step();
+    }
+    // the store reached ram through the mirror
+    assert_eq!(system.memory_mut().read(0x0200), 0x42);
+    assert_eq!(system.memory_mut().read(0xF000), 0xA9);
+    assert_eq!(system.memory_mut().read(0xFFFD), 0xF0);
+}
+
+#[test]
+fn roms_end_where_their_image_does() {
+    let dir = scratch("rom-end");
+    fs::write(dir.join("kernal.bin"), vec![0; 0x1001]).unwrap();
+    let text = "[[rom]]\nfile = \"kernal.bin\"\nstart = 0xF000";
+    fs::write(dir.join("machine.toml"), text).unwrap();
+    let err = MachineProfile::load(dir.join("machine.toml"))
+        .unwrap()
+        .builder()
+        .err()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(err.to_string(), "line 1: rom runs past $ffff in [rom]");
+
+    assert_eq!(
+        error("[[device]]\nkind = \"ram\"\nstart = 0xF000\nend = 0x10001"),
+        "line 4: 'end' must be an address or 0x10000"
+    );
+}
+
+#[test]
+fn profiles_report_the_line_of_a_problem() {
+    assert_eq!(
+        error("[machine]\ncpu = \"z80\""),
+        "line 1: unknown cpu 'z80' in [machine]"
+    );
+    assert_eq!(
+        error("[machine]\nfrequncy = 1000"),
+        "line 2: unknown setting 'frequncy' in [machine]"
+    );
+    assert_eq!(
+        error("[[device]]\nkind = \"ram\"\nstart = true"),
+        "line 3: 'start' must be an integer"
+    );
+    assert_eq!(
+        error("[[device]]\nkind = \"ram\"\nstart = 0x10000\nend = 0"),
+        "line 3: 'start' must be an address"
+    );
+    assert_eq!(
+        error("[[device]]\nkind = \"ram\""),
+        "line 1: missing setting 'start' in [device]"
+    );
+    assert_eq!(
+        error("[devices]"),
+        "line 1: unknown field `devices`, expected one of `machine`, `rom`, `device`, `mirror`, `open-bus`"
+    );
+    assert_eq!(
+        error("[device]"),
+        "line 1: invalid type: map, expected a sequence"
+    );
+    assert_eq!(
+        error("[machine]\nram = 1\nram = 2"),
+        "line 3: duplicate key"
+    );
+    assert_eq!(
+        error("[machine]\nram = 1.5"),
+        "line 2: 'ram' must be an integer"
+    );
+}
+
+#[test]
+fn addresses_can_name_public_symbols() {
+    let dir = scratch("symbols");
+    let symbols = "$1000 start\n\n[public]\n$8000 EXPANSION\n$a000 EXPANSION_END\n$f000 KERNAL\n";
+    fs::write(dir.join("guest.sym"), symbols).unwrap();
+    fs::write(dir.join("kernal.bin"), vec![0; 0x1000]).unwrap();
+    let text = "[machine]\nram = 0x1000\nreset_vector = \"KERNAL\"\nsymbols = \"guest.sym\"\n\n\
+                [[rom]]\nfile = \"kernal.bin\"\nstart = \"KERNAL\"\n\n\
+                [[device]]\nkind = \"ram\"\nstart = \"EXPANSION\"\nend = \"EXPANSION_END\"";
+    fs::write(dir.join("machine.toml"), text).unwrap();
+    let builder = MachineProfile::load(dir.join("machine.toml"))
+        .unwrap()
+        .builder();
+
+    let private = "[machine]\nsymbols = \"guest.sym\"\n\n[[device]]\nkind = \"ram\"\nstart = \"start\"\nend = 0x2000";
+    fs::write(dir.join("private.toml"), private).unwrap();
+    let err = MachineProfile::load(dir.join("private.toml"))
+        .unwrap()
+        .builder()
+        .err()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let mut system = builder.unwrap().build().unwrap();
+    system.memory_mut().write(0x9FFF, 0x42);
+    assert_eq!(system.memory_mut().read(0x9FFF), 0x42);
+    assert_eq!(system.memory_mut().read(0xFFFD), 0xF0);
+    assert_eq!(
+        err.to_string(),
+        "line 6: 'start' names 'start', which is not a public symbol"
+    );
+    assert_eq!(
+        error("[[device]]\nkind = \"ram\"\nstart = \"low\"\nend = 0x2000"),
+        "line 3: 'start' names 'low', which is not a public symbol"
+    );
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let text = "[[rom]]\nfile = \"missing.bin\"\nstart = 0xF000";
+    let err = MachineProfile::parse(text)
+        .unwrap()
+        .builder()
+        .err()
+        .unwrap();
+    assert!(err.to_string().starts_with("missing.bin: "));
+}
+
+#[test]
+fn profiles_are_validated_when_built() {
+    let text = "[machine]\nram = 0x20000\nreset_vector = 0x1000\n\n[[open-bus]]\nstart = 0xF000\nend = 0xF100";
+    let errors = MachineProfile::parse(text)
+        .unwrap()
+        .builder()
+        .unwrap()
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(errors, vec![ConfigError::RamTooLarge { size: 0x20000 }]);
+
+    let text = "[[device]]\nkind = \"ram\"\nstart = 0x1000\nend = 0x2000\n\n[[mirror]]\nstart = 0x1800\nend = 0x1900\nto_start = 0\nto_end = 0x100";
+    let errors = MachineProfile::parse(text)
+        .unwrap()
+        .builder()
+        .unwrap()
+        .reset_vector(0x1000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![ConfigError::Overlap {
+            first: "ram".to_string(),
+            second: "mirror of $0000-$0100".to_string(),
+            range: Range::new(0x1800, 0x1900)
+        }]
+    );
+}
+
+#[test]
+fn registered_kinds_can_be_named() {
+    let mut registry = DeviceRegistry::new();
+    registry.register("custom-io", |settings: &Settings| {
+        let value = settings.required(settings.byte("value")?, "value")?;
+        let device = Box::new(Latch { value }) as Box<dyn Device>;
+        return Ok((device, Some(Range::new(0xC000, 0xC001))));
+    });
+    assert!(registry.contains("custom-io") && registry.contains("stdout"));
+
+    let text = "[machine]\nreset_vector = 0x1000\n\n[[device]]\nkind = \"custom-io\"\nvalue = 0x5a";
+    let profile = MachineProfile::parse(text).unwrap();
+    let mut system = profile.builder_with(&registry).unwrap().build().unwrap();
+    assert_eq!(system.memory_mut().read(0xC000), 0x5A);
+
+    // the built-in registry does not know the kind
+    assert_eq!(
+        profile.builder().err().unwrap().to_string(),
+        "line 4: unknown device kind 'custom-io' in [device]"
+    );
+    assert_eq!(DeviceRegistry::empty().kinds(), Vec::<&str>::new());
+}
@@ -0,0 +1,73 @@
+//! Reproducible random numbers for guest programs.
+#![allow(clippy::needless_return)]
+
+use system::device::{Device, RandomDevice, ResetKind};
+use system::{Bus, Memory};
+
+const DATA: u16 = RandomDevice::RANGE.start;
+const SEED: u16 = RandomDevice::RANGE.start + 1;
+
+fn sequence(mem: &mut Memory, len: usize) -> Vec<u8> {
+    return (0..len).map(|_| mem.read(DATA)).collect();
+}
+
+#[test]
+fn the_same_seed_gives_the_same_bytes() {
+    let mut first = Memory::new();
+    first
+        .map(RandomDevice::RANGE, RandomDevice::new(1234))
+        .unwrap();
+    let mut second = Memory::new();
+    second
+        .map(RandomDevice::RANGE, RandomDevice::new(1234))
+        .unwrap();
+    let mut other = Memory::new();
+    other
+        .map(RandomDevice::RANGE, RandomDevice::new(4321))
+        .unwrap();
+
+    let bytes = sequence(&mut first, 64);
+    assert_eq!(bytes, sequence(&mut second, 64));
+    assert_ne!(bytes, sequence(&mut other, 64));
+    // not stuck on a single value
+    assert!(bytes.iter().any(|byte| *byte != bytes[0]));
+}
+
+#[test]
+fn the_guest_can_read_and_change_the_seed() {
+    let mut mem = Memory::new();
+    mem.map(RandomDevice::RANGE, RandomDevice::new(0x1234_5678))
+        .unwrap();
+    assert_eq!(
+        [
+            mem.read(SEED),
+            mem.read(SEED + 1),
+            mem.read(SEED + 2),
+            mem.read(SEED + 3)
+        ],
+        [0x78, 0x56, 0x34, 0x12]
+    );
+    let bytes = sequence(&mut mem, 8);
+
+    // writing the seed back starts the sequence over
+    mem.write(SEED, 0x78);
+    assert_eq!(sequence(&mut mem, 8), bytes);
+    mem.write(SEED + 3, 0x00);
+    assert_eq!(mem.read(SEED + 3), 0x00);
+    assert_ne!(sequence(&mut mem, 8), bytes);
+}
+
+#[test]
+fn peeks_do_not_advance_and_hard_resets_start_over() {
+    let mut device = RandomDevice::new(0);
+    let first = device.peek(0).unwrap();
+    assert_eq!(device.peek(0), Some(first));
+    assert_eq!(device.read(0), first);
+
+    let second = device.read(0);
+    device.reset(ResetKind::Soft);
+    assert_ne!(device.read(0), second);
+    device.reset(ResetKind::Hard);
+    assert_eq!(device.read(0), first);
+    assert_eq!(device.seed(), 0);
+}
@@ -0,0 +1,115 @@
+//! Address ranges, which every device mapping is described by.
+#![allow(clippy::needless_return)]
+
+use system::{Bus, ConfigError, MachineConfig, Range};
+
+#[test]
+fn ranges_are_half_open() {
+    let range = Range::new(0x1000, 0x1100);
+    assert!(range.contains(0x1000));
+    assert!(range.contains(0x10FF));
+    assert!(!range.contains(0x1100));
+    assert!(!range.contains(0x0FFF));
+    assert_eq!(range.len(), 0x100);
+    assert!(!range.is_empty());
+}
+
+#[test]
+fn empty_ranges_contain_nothing() {
+    for range in [Range::new(0x1000, 0x1000), Range::new(0x2000, 0x1000)] {
+        assert!(range.is_empty());
+        assert_eq!(range.len(), 0);
+        assert!(!range.contains(0x1000));
+        assert_eq!(range.iter().count(), 0);
+        assert!(!range.overlaps(&Range::new(0x0000, 0xFFFF)));
+    }
+}
+
+#[test]
+fn overlaps_need_a_shared_address() {
+    let range = Range::new(0x1000, 0x2000);
+    assert!(range.overlaps(&Range::new(0x1FFF, 0x3000)));
+    assert!(range.overlaps(&Range::new(0x0000, 0x1001)));
+    assert!(range.overlaps(&Range::new(0x1800, 0x1801)));
+    assert!(range.overlaps(&range));
+    assert!(!range.overlaps(&Range::new(0x2000, 0x3000)));
+    assert!(!range.overlaps(&Range::new(0x0000, 0x1000)));
+}
+
+#[test]
+fn ranges_convert_and_iterate() {
+    let range = Range::from(0xFFFD..0xFFFF);
+    assert_eq!(range, Range::new(0xFFFD, 0xFFFF));
+    assert_eq!(range.iter().collect::<Vec<_>>(), [0xFFFD, 0xFFFE]);
+    assert_eq!(range.into_iter().last(), Some(0xFFFE));
+    assert_eq!(Range::from(0xFFFD..=0xFFFE), range);
+}
+
+#[test]
+fn ranges_can_take_in_the_last_address() {
+    let range = Range::from(0xFFFD..=0xFFFF);
+    assert_eq!(range, Range::new(0xFFFD, Range::TOP));
+    assert!(range.contains(0xFFFF));
+    assert_eq!(range.len(), 3);
+    assert_eq!(range.last(), Some(0xFFFF));
+    assert_eq!(range.iter().collect::<Vec<_>>(), [0xFFFD, 0xFFFE, 0xFFFF]);
+    assert!(range.overlaps(&Range::new(0xFFFF, Range::TOP)));
+    assert_eq!(Range::new(0x0000, Range::TOP).len(), 0x10000);
+}
+
+#[test]
+fn roms_can_be_mapped_across_the_last_address() {
+    let mut image = vec![0; 0x1000];
+    image[0xFFC..].copy_from_slice(&[0x00, 0xF0, 0x34, 0x12]);
+    let mem = MachineConfig::new()
+        .rom("kernal", Range::new(0xF000, Range::TOP), image)
+        .build()
+        .unwrap();
+
+    assert_eq!(mem.read(0xFFFE), 0x34);
+    assert_eq!(mem.read(0xFFFF), 0x12);
+}
+
+#[test]
+fn ranges_cannot_be_made_past_the_last_address() {
+    assert_eq!(
+        Range::checked(0xF000, Range::TOP),
+        Some(Range::new(0xF000, Range::TOP))
+    );
+    assert_eq!(Range::checked(0xF000, Range::TOP + 1), None);
+    assert_eq!(
+        Range::checked(0x2000, 0x1000),
+        Some(Range::new(0x2000, 0x1000))
+    );
+}
+
+#[test]
+#[should_panic(expected = "range end $10001 is past $10000")]
+fn new_ranges_past_the_last_address_panic() {
+    Range::new(0xF000, Range::TOP + 1);
+}
+
+#[test]
+fn ranges_past_the_last_address_are_rejected() {
+    // the fields can still describe one
+    let past = Range {
+        start: 0xF000,
+        end: Range::TOP + 1,
+    };
+    assert!(!past.is_empty());
+    assert_eq!(past.last(), None);
+
+    let errors = MachineConfig::new()
+        .rom("kernal", past, vec![0; 0x1000])
+        .reset_vector(0xF000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![ConfigError::Rejected {
+            name: "kernal".to_string(),
+            range: past
+        }]
+    );
+}
@@ -0,0 +1,256 @@
+//! ROM, RAM and bank-switched regions mapped as devices.
+#![allow(clippy::needless_return)]
+
+use cpu::Cpu;
+use system::device::{BankedDevice, Device, RamDevice, RomDevice, RomWrites};
+use system::{Bus, ConfigError, MachineConfig, Memory, Range};
+
+// lda #$42; sta $1000; sta $4000; sed
+const PROGRAM: [u8; 9] = [0xA9, 0x42, 0x8D, 0x00, 0x10, 0x8D, 0x00, 0x40, 0xF8];
+
+fn run(config: MachineConfig<'static>) -> Memory<'static> {
+    let mut mem = config.build().unwrap();
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    while !cpu.status.get_decimal_mode() {
+        cpu.step_instruction(&mut mem);
+    }
+    return mem;
+}
+
+#[test]
+fn rom_ignores_writes_and_ram_keeps_them() {
+    let mem = run(MachineConfig::new()
+        .rom("program", Range::new(0x1000, 0x2000), PROGRAM.to_vec())
+        .device("ram", Range::new(0x4000, 0x8000), RamDevice::new())
+        .reset_vector(0x1000));
+
+    assert_eq!(mem.read(0x1000), 0xA9);
+    assert_eq!(mem.read(0x4000), 0x42);
+}
+
+#[test]
+fn reset_vector_can_live_in_rom() {
+    let mut image = vec![0; 0x1000];
+    image[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+    image[0xFFC..0xFFE].copy_from_slice(&[0x00, 0xF0]);
+    let mem = run(MachineConfig::new()
+        .rom("kernal", Range::new(0xF000, Range::TOP), image)
+        .device("ram", Range::new(0x4000, 0x8000), RamDevice::new()));
+
+    assert_eq!(mem.read(Cpu::RES_VECTOR), 0x00);
+    assert_eq!(mem.read(0x4000), 0x42);
+}
+
+#[test]
+fn reset_vector_in_ram_cannot_be_hidden() {
+    let build = |config: MachineConfig<'static>| config.reset_vector(0x1000).build().err();
+    let errors =
+        build(MachineConfig::new().device("io", Range::new(0xFFF0, Range::TOP), RamDevice::new()));
+    assert_eq!(
+        errors,
+        Some(vec![ConfigError::HiddenResetVector {
+            name: "io".to_string(),
+            range: Range::new(0xFFF0, Range::TOP)
+        }])
+    );
+
+    let errors = build(MachineConfig::new().open_bus(Range::new(0xFF00, 0xFFFD)));
+    assert_eq!(
+        errors,
+        Some(vec![ConfigError::HiddenResetVector {
+            name: "open bus".to_string(),
+            range: Range::new(0xFF00, 0xFFFD)
+        }])
+    );
+
+    let errors = build(
+        MachineConfig::new().mirror(Range::new(0xF000, Range::TOP), Range::new(0x0000, 0x1000)),
+    );
+    assert_eq!(
+        errors,
+        Some(vec![ConfigError::HiddenResetVector {
+            name: "mirror of $0000-$1000".to_string(),
+            range: Range::new(0xF000, Range::TOP)
+        }])
+    );
+
+    // a rom over the vector has it patched in instead
+    let image = PROGRAM.to_vec();
+    let mem = run(MachineConfig::new()
+        .rom("program", Range::new(0xF000, Range::TOP), image)
+        .reset_vector(0xF000));
+    assert_eq!(mem.read(Cpu::RES_VECTOR), 0x00);
+    assert_eq!(mem.read(0x4000), 0x42);
+}
+
+#[test]
+fn roms_must_fit_their_region() {
+    let errors = MachineConfig::new()
+        .rom("kernal", Range::new(0xF000, Range::TOP), vec![0; 0x1001])
+        .reset_vector(0xF000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![ConfigError::RomTooLarge {
+            name: "kernal".to_string(),
+            size: 0x1001,
+            range: Range::new(0xF000, Range::TOP)
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "write of $42 to rom at $1000")]
+fn trapping_rom_panics_on_write() {
+    let rom = RomDevice::new(&PROGRAM).on_write(RomWrites::Trap);
+    run(MachineConfig::new()
+        .device("program", Range::new(0x1000, 0x2000), rom)
+        .reset_vector(0x1000));
+}
+
+#[test]
+fn mapper_switches_banks_on_writes_to_its_window() {
+    let image = [[0x11; 0x2000], [0x22; 0x2000], [0x33; 0x2000]].concat();
+    let mut mapper = BankedDevice::new(0x2000, &image);
+    assert!(!mapper.mount(Range::new(0x8000, 0xA001)));
+    assert!(mapper.mount(Range::new(0x8000, 0xA000)));
+    assert_eq!(mapper.bank_count(), 3);
+
+    assert_eq!(mapper.read(0x1FFF), 0x11);
+    mapper.write(0x1FFF, 2);
+    assert_eq!(mapper.read(0x0000), 0x33);
+    mapper.write(0x0000, 4);
+    assert_eq!(mapper.selected_bank(), 1);
+    assert_eq!(mapper.read(0x0000), 0x22);
+}
+
+#[test]
+fn mapper_fills_the_top_of_memory() {
+    // lda #$01; sta $c000 switches to the bank which goes on with sed
+    let mut image = vec![0; 0x8000];
+    image[..5].copy_from_slice(&[0xA9, 0x01, 0x8D, 0x00, 0xC0]);
+    image[0x4005] = 0xF8;
+    for bank in [0x0000, 0x4000] {
+        image[bank + 0x3FFC..bank + 0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+    }
+    let mut mem = Memory::new();
+    mem.map(
+        Range::new(0xC000, Range::TOP),
+        BankedDevice::new(0x4000, &image),
+    )
+    .unwrap();
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    while !cpu.status.get_decimal_mode() {
+        cpu.step_instruction(&mut mem);
+    }
+
+    assert_eq!(mem.read(0xC005), 0xF8);
+    assert_eq!(mem.read(0xC000), 0x00);
+}
+
+#[test]
+fn writable_mapper_keeps_each_bank_separate() {
+    // lda #$01; sta $a000; lda #$42; sta $8000; lda #$00; sta $a000; sed
+    let program = [
+        0xA9, 0x01, 0x8D, 0x00, 0xA0, 0xA9, 0x42, 0x8D, 0x00, 0x80, 0xA9, 0x00, 0x8D, 0x00, 0xA0,
+        0xF8,
+    ];
+    let mapper = BankedDevice::new(0x2000, &[0; 0x4000]).writable();
+    let register = mapper.bank_register();
+    let mut mem = run(MachineConfig::new()
+        .rom("program", Range::new(0x1000, 0x2000), program.to_vec())
+        .device("mapper", Range::new(0x8000, 0xA000), mapper)
+        .device("bank", Range::new(0xA000, 0xA001), register)
+        .reset_vector(0x1000));
+
+    assert_eq!(mem.read(0x8000), 0x00);
+    assert_eq!(mem.read(0xA000), 0x00);
+    mem.write(0xA000, 1);
+    assert_eq!(mem.read(0x8000), 0x42);
+    assert_eq!(mem.read(0xA000), 0x01);
+}
+
+#[test]
+fn devices_can_refuse_their_range() {
+    let errors = MachineConfig::new()
+        .device(
+            "mapper",
+            Range::new(0x8000, 0xA001),
+            BankedDevice::new(0x2000, &[]),
+        )
+        .reset_vector(0x1000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![ConfigError::Rejected {
+            name: "mapper".to_string(),
+            range: Range::new(0x8000, 0xA001)
+        }]
+    );
+}
+
+#[test]
+fn mirrors_reach_ram_below_them() {
+    let mem = run(MachineConfig::new()
+        .rom("program", Range::new(0x1000, 0x2000), PROGRAM.to_vec())
+        .mirror(Range::new(0x4000, 0x8000), Range::new(0x0000, 0x0800))
+        .open_bus(Range::new(0x8000, 0x9000))
+        .reset_vector(0x1000));
+
+    assert_eq!(mem.read(0x0000), 0x42);
+    assert_eq!(mem.read(0x7800), 0x42);
+    assert_eq!(mem.peek(0x8000), None);
+}
+
+#[test]
+fn mirrors_are_validated_with_the_regions() {
+    let errors = MachineConfig::new()
+        .device("ram", Range::new(0x4000, 0x8000), RamDevice::new())
+        .mirror(Range::new(0x7000, 0x9000), Range::new(0x0000, 0x0800))
+        .mirror(Range::new(0xA000, 0xB000), Range::new(0x8000, 0x8100))
+        .reset_vector(0x1000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            ConfigError::Overlap {
+                first: "ram".to_string(),
+                second: "mirror of $0000-$0800".to_string(),
+                range: Range::new(0x7000, 0x8000)
+            },
+            ConfigError::Rejected {
+                name: "mirror of $8000-$8100".to_string(),
+                range: Range::new(0xA000, 0xB000)
+            }
+        ]
+    );
+}
+
+#[test]
+fn mirror_targets_must_be_in_the_address_space() {
+    let to = Range {
+        start: 0xFF00,
+        end: Range::TOP + 0x100,
+    };
+    let errors = MachineConfig::new()
+        .mirror(Range::new(0x4000, 0x4200), to)
+        .reset_vector(0x1000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![ConfigError::Rejected {
+            name: "mirror of $ff00-$10100".to_string(),
+            range: Range::new(0x4000, 0x4200)
+        }]
+    );
+}
@@ -0,0 +1,94 @@
+//! Stepping programs backwards.
+#![allow(clippy::needless_return)]
+
+use cpu::{Cpu, Debugger, StepResult};
+use system::{Bus, Memory, Rewind, System};
+
+// lda #$01; sta $0300; lda #$02; sta $0300
+const PROGRAM: [u8; 10] = [0xA9, 0x01, 0x8D, 0x00, 0x03, 0xA9, 0x02, 0x8D, 0x00, 0x03];
+
+fn boot() -> (Cpu, Memory<'static>) {
+    let mut mem = Memory::new();
+    mem.load(0x0200, &PROGRAM).unwrap();
+    mem.set_reset_vector(0x0200).unwrap();
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    return (cpu, mem);
+}
+
+#[test]
+fn undoes_registers_and_writes() {
+    let (mut cpu, mut mem) = boot();
+    let start = cpu.cycles();
+    let mut rewind = Rewind::new(16);
+    for _ in 0..4 {
+        rewind.step_instruction(&mut cpu, &mut mem);
+    }
+    assert_eq!(mem.read(0x0300), 0x02);
+
+    assert_eq!(rewind.step_back(&mut cpu, &mut mem, 1), 1);
+    assert_eq!(mem.read(0x0300), 0x01);
+    assert_eq!(cpu.registers.pc.get(), 0x0207);
+    assert_eq!(cpu.registers.acc.get(), 0x02);
+
+    assert_eq!(rewind.step_back(&mut cpu, &mut mem, 1), 1);
+    assert_eq!(cpu.registers.pc.get(), 0x0205);
+    assert_eq!(cpu.registers.acc.get(), 0x01);
+
+    // only two steps are left to undo
+    assert_eq!(rewind.step_back(&mut cpu, &mut mem, 5), 2);
+    assert!(rewind.is_empty());
+    assert_eq!(mem.read(0x0300), 0x00);
+    assert_eq!(cpu.registers.pc.get(), 0x0200);
+    assert_eq!(cpu.cycles(), start);
+}
+
+#[test]
+fn keeps_only_the_latest_steps() {
+    let (mut cpu, mut mem) = boot();
+    let mut rewind = Rewind::new(2);
+    for _ in 0..4 {
+        rewind.step_instruction(&mut cpu, &mut mem);
+    }
+
+    assert_eq!(rewind.len(), 2);
+    assert_eq!(rewind.step_back(&mut cpu, &mut mem, 4), 2);
+    assert_eq!(cpu.registers.pc.get(), 0x0205);
+    assert_eq!(mem.read(0x0300), 0x01);
+}
+
+#[test]
+fn records_steps_through_a_debugger() {
+    let (mut cpu, mut mem) = boot();
+    let mut rewind = Rewind::new(16);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x0205);
+
+    let mut step = |cpu: &mut Cpu, mem: &mut Memory| {
+        return rewind.record(cpu, mem, |cpu, mem| debugger.step_instruction(cpu, mem));
+    };
+    assert_eq!(step(&mut cpu, &mut mem), StepResult::Completed(2));
+    assert_eq!(step(&mut cpu, &mut mem), StepResult::Completed(4));
+    assert!(matches!(step(&mut cpu, &mut mem), StepResult::Hit(_)));
+
+    // a step which hit a breakpoint undoes to where it was
+    assert_eq!(rewind.step_back(&mut cpu, &mut mem, 2), 2);
+    assert_eq!(cpu.registers.pc.get(), 0x0202);
+    assert_eq!(mem.read(0x0300), 0x00);
+}
+
+#[test]
+fn systems_step_back_when_enabled() {
+    let mut system = System::builder().reset_vector(0x0200).build().unwrap();
+    system.memory_mut().load(0x0200, &PROGRAM).unwrap();
+    system.step();
+    assert_eq!(system.step_back(1), 0);
+
+    system.set_rewind(8);
+    system.step();
+    system.step();
+    assert_eq!(system.memory().read(0x0300), 0x01);
+    assert_eq!(system.step_back(2), 2);
+    assert_eq!(system.memory().read(0x0300), 0x00);
+    assert_eq!(system.cpu().registers.pc.get(), 0x0202);
+}
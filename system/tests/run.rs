@@ -0,0 +1,63 @@
+//! Headless runs of raw programs.
+#![allow(clippy::needless_return)]
+
+use system::{run_program, Bus, Exit, Limits, MemoryError};
+
+/// Counts X down from five, then stores $2a at $0300 and stops at a `BRK`.
+const COUNTDOWN: [u8; 12] = [
+    0xA2, 0x05, // ldx #5
+    0xCA, // loop: dex
+    0xD0, 0xFD, // bne loop
+    0xA9, 0x2A, // lda #$2a
+    0x8D, 0x00, 0x03, // sta $0300
+    0x00, // brk
+    0x00,
+];
+
+#[test]
+fn runs_until_brk() {
+    let report = run_program(&COUNTDOWN, 0x0200, 0x0200, Limits::default()).unwrap();
+
+    assert_eq!(report.exit, Exit::Brk { pc: 0x020A });
+    assert_eq!((report.a, report.x, report.pc), (0x2A, 0, 0x020A));
+    assert_eq!(report.instructions, 13);
+    assert_eq!(report.cycles, 32);
+    assert_eq!(report.memory.read(0x0300), 0x2A);
+}
+
+#[test]
+fn runs_until_jmp_to_self() {
+    // ldy #7; jmp *
+    let program = [0xA0, 0x07, 0x4C, 0x02, 0x80];
+    let report = run_program(&program, 0x8000, 0x8000, Limits::default()).unwrap();
+
+    assert_eq!(report.exit, Exit::Loop { pc: 0x8002 });
+    assert_eq!(report.y, 7);
+    assert_eq!(report.cycles, 5);
+}
+
+#[test]
+fn stops_at_limits() {
+    // loop: inx; jmp loop
+    let program = [0xE8, 0x4C, 0x00, 0x02];
+
+    let report = run_program(&program, 0x0200, 0x0200, Limits::instructions(10)).unwrap();
+    assert_eq!(report.exit, Exit::InstructionLimit);
+    assert_eq!((report.instructions, report.x), (10, 5));
+
+    let report = run_program(&program, 0x0200, 0x0200, Limits::cycles(100)).unwrap();
+    assert_eq!(report.exit, Exit::CycleLimit);
+    assert_eq!(report.cycles, 100);
+    assert_eq!(report.x, 20);
+}
+
+#[test]
+fn rejects_programs_past_the_end_of_memory() {
+    assert!(matches!(
+        run_program(&COUNTDOWN, 0xFFF8, 0xFFF8, Limits::default()),
+        Err(MemoryError::OutOfRange {
+            address: 0xFFF8,
+            ..
+        })
+    ));
+}
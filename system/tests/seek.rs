@@ -0,0 +1,254 @@
+//! Seeking a machine to a cycle, backward and forward.
+#![allow(clippy::needless_return)]
+
+use cpu::Cpu;
+use system::device::ViaDevice;
+use system::{Bus, MachineConfig, Memory, Range, System};
+
+/// Reads a machine's memory without side effects and throws writes away.
+struct Scratch<'a>(&'a Memory<'a>);
+
+impl Bus for Scratch<'_> {
+    fn read(&self, address: u16) -> u8 {
+        return self.0.peek(address).unwrap_or(0);
+    }
+
+    fn write(&mut self, _: u16, _: u8) {}
+}
+
+//        ldx #$00
+// loop:  inx
+//        txa
+//        sta $0300,x
+//        inc $10
+//        bne loop
+//        inc $11
+//        jmp loop
+const PROGRAM: [u8; 16] = [
+    0xA2, 0x00, 0xE8, 0x8A, 0x9D, 0x00, 0x03, 0xE6, 0x10, 0xD0, 0xF7, 0xE6, 0x11, 0x4C, 0x02, 0x10,
+];
+
+/// The RAM the program writes to, and a byte it does not.
+const RAM: Range = Range {
+    start: 0x0000,
+    end: 0x0400,
+};
+
+fn power_on() -> (Cpu, Memory<'static>) {
+    let mut mem = MachineConfig::new()
+        .rom("program", Range::new(0x1000, 0x2000), PROGRAM.to_vec())
+        .reset_vector(0x1000)
+        .build()
+        .unwrap();
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    return (cpu, mem);
+}
+
+fn boot() -> System<'static> {
+    let (cpu, mem) = power_on();
+    return System::new(cpu, mem);
+}
+
+/// Runs a machine a cycle at a time from power on, the slow way to `cycle`.
+fn run_to(cycle: u64) -> (Cpu, Memory<'static>) {
+    let (mut cpu, mut mem) = power_on();
+    while cpu.cycles() < cycle {
+        if cpu.at_instruction_boundary() {
+            mem.set_cycle(cpu.cycles());
+        }
+        cpu.step_cycle(&mut mem);
+    }
+    return (cpu, mem);
+}
+
+/// Returns the state of a cpu with the micro-ops which take no cycle at the end
+/// of its instruction run, so a cpu stepped a cycle at a time compares equal to
+/// one stepped an instruction at a time.
+fn settled(cpu: &Cpu, mem: &Memory) -> Vec<u8> {
+    let state = cpu.save_state();
+    if cpu.at_instruction_boundary() {
+        return state;
+    }
+    let mut settled = Cpu::new();
+    settled.load_state(&state).unwrap();
+    settled.step_cycle(&mut Scratch(mem));
+    if settled.cycles() != cpu.cycles() || !settled.at_instruction_boundary() {
+        return state;
+    }
+    return settled.save_state();
+}
+
+/// Checks that the cpu and ram are where running from power on to the same
+/// cycle leaves them.
+fn assert_same(cpu: &Cpu, mem: &Memory) {
+    let (expected_cpu, expected_mem) = run_to(cpu.cycles());
+    assert_eq!(
+        settled(cpu, mem),
+        settled(&expected_cpu, &expected_mem),
+        "at cycle {}",
+        cpu.cycles()
+    );
+    assert_eq!(
+        mem.diff(&expected_mem.snapshot(RAM), RAM),
+        vec![],
+        "at cycle {}",
+        cpu.cycles()
+    );
+}
+
+/// Checks that the system is where running from power on to its cycle leaves
+/// a machine.
+fn assert_replayed(system: &System) {
+    assert_same(system.cpu(), system.memory());
+}
+
+#[test]
+fn seeks_forward_to_the_exact_cycle() {
+    for cycle in [7, 8, 100, 101, 102, 103, 104, 105, 2000] {
+        let mut system = boot();
+        assert!(system.seek_to_cycle(cycle));
+        assert_eq!(system.cpu().cycles(), cycle);
+        assert_replayed(&system);
+    }
+
+    // seeking to where it is does nothing
+    let mut system = boot();
+    system.seek_to_cycle(1000);
+    assert!(system.seek_to_cycle(1000));
+    assert_eq!(system.cpu().cycles(), 1000);
+}
+
+#[test]
+fn seeks_back_through_snapshots() {
+    let mut system = boot();
+    system.set_snapshots(500, 100);
+    assert!(system.seek_to_cycle(20_000));
+    assert_replayed(&system);
+
+    for cycle in [15_003, 7_777, 1_001, 19_999, 12_345, 12_340, 40] {
+        assert!(system.seek_to_cycle(cycle), "cycle {}", cycle);
+        assert_eq!(system.cpu().cycles(), cycle);
+        assert_replayed(&system);
+    }
+
+    // and on from there as if nothing happened
+    for _ in 0..100 {
+        system.step();
+    }
+    assert_replayed(&system);
+}
+
+#[test]
+fn seeks_back_through_the_rewind_history() {
+    let mut system = boot();
+    system.set_rewind(1000);
+    assert!(system.seek_to_cycle(3001));
+
+    for cycle in [2500, 2999, 2990, 2991, 3500] {
+        assert!(system.seek_to_cycle(cycle), "cycle {}", cycle);
+        assert_eq!(system.cpu().cycles(), cycle);
+        assert_replayed(&system);
+    }
+
+    // steps which stopped in the middle of an instruction undo too
+    system.seek_to_cycle(3003);
+    system.step_back(2);
+    assert!(system.cpu().at_instruction_boundary());
+    assert_replayed(&system);
+}
+
+#[test]
+fn cannot_seek_back_past_what_was_kept() {
+    let mut system = boot();
+    assert!(system.seek_to_cycle(5000));
+    assert!(!system.seek_to_cycle(100));
+    assert_eq!(system.cpu().cycles(), 5000);
+
+    // only the last two snapshots are kept
+    let mut system = boot();
+    system.set_snapshots(1000, 2);
+    system.seek_to_cycle(10_000);
+    assert!(!system.seek_to_cycle(1000));
+    assert_eq!(system.cpu().cycles(), 10_000);
+    assert!(system.seek_to_cycle(9500));
+    assert_replayed(&system);
+}
+
+#[test]
+fn snapshots_after_the_cycle_sought_are_forgotten() {
+    let mut system = boot();
+    system.set_snapshots(100, 1000);
+    system.seek_to_cycle(5000);
+    system.seek_to_cycle(2000);
+
+    // the machine takes another path from here
+    system.memory_mut().write(0x0200, 0xAA);
+    system.seek_to_cycle(4000);
+    assert!(system.seek_to_cycle(3000));
+    assert_eq!(system.memory().read(0x0200), 0xAA);
+}
+
+//        lda #$ff
+//        sta $a074
+//        sta $a075
+//        ldx #$00
+// loop:  lda $a074
+//        sta $0300,x
+//        inx
+//        jmp loop
+const TIMER_PROGRAM: [u8; 20] = [
+    0xA9, 0xFF, 0x8D, 0x74, 0xA0, 0x8D, 0x75, 0xA0, 0xA2, 0x00, 0xAD, 0x74, 0xA0, 0x9D, 0x00, 0x03,
+    0xE8, 0x4C, 0x0A, 0x10,
+];
+
+/// Returns a machine which keeps reading a running VIA timer into RAM.
+fn boot_timer() -> System<'static> {
+    let mut mem = MachineConfig::new()
+        .rom(
+            "program",
+            Range::new(0x1000, 0x2000),
+            TIMER_PROGRAM.to_vec(),
+        )
+        .device("via", ViaDevice::RANGE, ViaDevice::new())
+        .reset_vector(0x1000)
+        .build()
+        .unwrap();
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    return System::new(cpu, mem);
+}
+
+#[test]
+fn seeking_back_restores_the_devices() {
+    let mut system = boot_timer();
+    system.set_rewind(1000);
+    system.set_snapshots(500, 100);
+    assert!(system.seek_to_cycle(3000));
+
+    // the rewind history reaches back, but would leave the timer running ahead
+    for cycle in [2500, 2999, 1234] {
+        assert!(system.seek_to_cycle(cycle), "cycle {}", cycle);
+        let mut expected = boot_timer();
+        expected.seek_to_cycle(cycle);
+        assert_eq!(
+            settled(system.cpu(), system.memory()),
+            settled(expected.cpu(), expected.memory()),
+            "at cycle {}",
+            cycle
+        );
+        assert_eq!(
+            system.memory().save_state(),
+            expected.memory().save_state(),
+            "at cycle {}",
+            cycle
+        );
+    }
+
+    // without saved states there is nothing to go back to
+    let mut system = boot_timer();
+    system.set_rewind(1000);
+    system.seek_to_cycle(3000);
+    assert!(!system.seek_to_cycle(2500));
+    assert_eq!(system.cpu().cycles(), 3000);
+}
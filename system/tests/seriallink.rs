@@ -0,0 +1,67 @@
+//! In-process serial links between two machines.
+#![allow(clippy::needless_return)]
+
+use system::device::{Device, ResetKind, SerialLinkDevice};
+
+const DATA: u16 = 0;
+const STATUS: u16 = 1;
+
+#[test]
+fn bytes_arrive_after_the_latency() {
+    let (mut a, mut b) = SerialLinkDevice::pair(10);
+    assert_eq!(a.read(STATUS), 0x02);
+
+    a.write(DATA, 0x41);
+    b.tick(9);
+    assert_eq!(b.read(STATUS), 0x02);
+    assert_eq!(b.read(DATA), 0x00);
+
+    b.tick(1);
+    assert_eq!(b.read(STATUS), 0x03);
+    assert_eq!(b.read(DATA), 0x41);
+    assert_eq!(b.read(STATUS), 0x02);
+}
+
+#[test]
+fn latency_is_measured_from_the_senders_clock() {
+    let (mut a, mut b) = SerialLinkDevice::pair(5);
+    a.tick(100);
+    a.write(DATA, 0x01);
+    a.tick(3);
+    a.write(DATA, 0x02);
+
+    // the bytes are due at 105 and 108 on the receiver's clock
+    b.tick(104);
+    assert_eq!(b.read(STATUS) & 0x01, 0);
+    b.tick(1);
+    assert_eq!(b.read(DATA), 0x01);
+    assert_eq!(b.read(STATUS) & 0x01, 0);
+    b.tick(3);
+    assert_eq!(b.read(DATA), 0x02);
+}
+
+#[test]
+fn each_end_sends_to_the_other() {
+    let (mut a, mut b) = SerialLinkDevice::pair(0);
+    a.write(DATA, 0xAA);
+    b.write(DATA, 0xBB);
+    b.write(DATA, 0xCC);
+
+    assert_eq!(b.read(DATA), 0xAA);
+    assert_eq!(a.read(DATA), 0xBB);
+    assert_eq!(a.read(DATA), 0xCC);
+    assert_eq!(a.read(STATUS), 0x02);
+}
+
+#[test]
+fn hard_resets_drop_bytes_in_flight() {
+    let (mut a, mut b) = SerialLinkDevice::pair(0);
+    a.write(DATA, 0x01);
+    b.reset(ResetKind::Soft);
+    assert_eq!(b.read(STATUS), 0x03);
+
+    b.reset(ResetKind::Hard);
+    assert_eq!(b.read(STATUS), 0x02);
+    a.write(DATA, 0x02);
+    assert_eq!(b.read(DATA), 0x02);
+}
@@ -0,0 +1,131 @@
+//! Saving and restoring a whole machine part way through a program.
+#![allow(clippy::needless_return)]
+
+use std::fs;
+
+use cpu::{Cpu, StateError, StateWriter};
+use system::device::{RamDevice, RandomDevice, ViaDevice};
+use system::{Bus, Memory, Range};
+
+/// Loads fib.o at $1000 with the reset vector pointing at it.
+fn boot() -> (Cpu, Memory<'static>) {
+    let mut mem = Memory::new();
+    mem.load(0x1000, &fs::read("../example/fib.o").unwrap())
+        .unwrap();
+    mem.load(Cpu::RES_VECTOR, &[0x00, 0x10]).unwrap();
+    mem.write(0x99, 11);
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    return (cpu, mem);
+}
+
+fn run_to_completion(cpu: &mut Cpu, mem: &mut Memory) {
+    while !cpu.status.get_decimal_mode() {
+        cpu.step_instruction(mem);
+    }
+}
+
+#[test]
+fn restored_machine_finishes_the_same_way() {
+    let (mut cpu, mut mem) = boot();
+    for _ in 0..50 {
+        cpu.step_cycle(&mut mem);
+    }
+    let cpu_state = cpu.save_state();
+    let mem_state = mem.save_state();
+
+    run_to_completion(&mut cpu, &mut mem);
+    assert_eq!(mem.read(0x104), 89);
+
+    let (mut restored_cpu, mut restored_mem) = boot();
+    restored_cpu.load_state(&cpu_state).unwrap();
+    restored_mem.load_state(&mem_state).unwrap();
+    run_to_completion(&mut restored_cpu, &mut restored_mem);
+
+    assert_eq!(restored_cpu.save_state(), cpu.save_state());
+    assert_eq!(restored_mem.save_state(), mem.save_state());
+}
+
+#[test]
+fn memory_rejects_a_cpu_state() {
+    let (cpu, mut mem) = boot();
+    assert_eq!(mem.load_state(&cpu.save_state()), Err(StateError::BadMagic));
+}
+
+/// A machine with devices which keep state of their own: RAM at $8000, a VIA
+/// and a random number generator.
+fn with_devices() -> Memory<'static> {
+    let mut mem = Memory::new();
+    mem.map(Range::new(0x8000, 0x8100), RamDevice::new())
+        .unwrap();
+    mem.map(ViaDevice::RANGE, ViaDevice::new()).unwrap();
+    mem.map(RandomDevice::RANGE, RandomDevice::new(7)).unwrap();
+    return mem;
+}
+
+#[test]
+fn devices_are_part_of_the_saved_state() {
+    let mut mem = with_devices();
+    mem.write(0x8010, 0x42);
+    // a one-shot T1 of $1000 cycles which interrupts
+    mem.write(0xA07E, 0xC0);
+    mem.write(0xA074, 0x00);
+    mem.write(0xA075, 0x10);
+    mem.set_cycle(0x800);
+    mem.read(0xA060);
+    let state = mem.save_state();
+
+    let mut restored = with_devices();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.peek(0x8010), Some(0x42));
+    assert_eq!(restored.peek(0xA075), Some(0x08));
+    assert_eq!(restored.peek(0xA060), mem.peek(0xA060));
+
+    // and both go on the same way
+    for mem in [&mut mem, &mut restored] {
+        assert!(!mem.irq_asserted());
+        mem.set_cycle(0x1002);
+        assert!(mem.irq_asserted());
+        mem.read(0xA060);
+    }
+    assert_eq!(restored.save_state(), mem.save_state());
+}
+
+#[test]
+fn a_device_which_cannot_load_changes_nothing() {
+    let state = with_devices().save_state();
+
+    // ram where the random number generator was
+    let mut other = Memory::new();
+    other
+        .map(Range::new(0x8000, 0x8100), RamDevice::new())
+        .unwrap();
+    other.map(ViaDevice::RANGE, ViaDevice::new()).unwrap();
+    other.map(RandomDevice::RANGE, RamDevice::new()).unwrap();
+    other.write(0x0010, 0x55);
+    other.write(0x8010, 0x99);
+    let before = other.save_state();
+    assert_eq!(other.load_state(&state), Err(StateError::BadMagic));
+    assert_eq!(other.save_state(), before);
+
+    let mut fewer = Memory::new();
+    assert_eq!(
+        fewer.load_state(&state),
+        Err(StateError::Invalid("device count"))
+    );
+}
+
+#[test]
+fn states_saved_without_devices_still_load() {
+    let mut old = StateWriter::new(b"RAM ", 1);
+    old.u64(100);
+    old.u32(0x10000);
+    old.bytes(&[0xEA; 0x10000]);
+
+    let mut mem = with_devices();
+    mem.write(0x8010, 0x42);
+    mem.load_state(&old.finish()).unwrap();
+    assert_eq!(mem.peek(0x0010), Some(0xEA));
+    assert_eq!(mem.peek(0x8010), Some(0x42));
+}
@@ -0,0 +1,42 @@
+//! A guest program polling the keyboard device.
+#![allow(clippy::needless_return)]
+
+use std::sync::mpsc;
+
+use cpu::Cpu;
+use system::device::StdinDevice;
+use system::{Bus, Memory};
+
+// poll:  lda $a031
+//        and #$01
+//        beq poll
+//        lda $a030
+//        sta $10
+//        sed
+const ECHO: [u8; 13] = [
+    0xAD, 0x31, 0xA0, 0x29, 0x01, 0xF0, 0xF9, 0xAD, 0x30, 0xA0, 0x85, 0x10, 0xF8,
+];
+
+#[test]
+fn program_polls_until_a_byte_arrives() {
+    let (tx, rx) = mpsc::channel();
+    let mut mem = Memory::new();
+    mem.map(StdinDevice::RANGE, StdinDevice::from_receiver(rx))
+        .unwrap();
+    mem.load(0x0200, &ECHO).unwrap();
+    mem.load(Cpu::RES_VECTOR, &[0x00, 0x02]).unwrap();
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+    for _ in 0..20 {
+        cpu.step_instruction(&mut mem);
+    }
+    assert!(cpu.registers.pc.get() < 0x0207);
+
+    tx.send(b'k').unwrap();
+    while !cpu.status.get_decimal_mode() {
+        cpu.step_instruction(&mut mem);
+    }
+    assert_eq!(mem.read(0x10), b'k');
+    assert_eq!(mem.read(0xA031), 0x00);
+}
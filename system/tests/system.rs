@@ -0,0 +1,122 @@
+//! Running a whole machine through the System facade.
+#![allow(clippy::needless_return)]
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use system::device::StdoutDevice;
+use system::{Bus, ConfigError, Image, MemoryError, Pacing, System};
+
+/// `loop: inx; jmp loop` at $0200.
+fn counter() -> Image {
+    return Image::from_bytes(0x0200, &[0xE8, 0x4C, 0x00, 0x02]).start(0x0200);
+}
+
+#[test]
+fn images_set_the_reset_vector() {
+    let mut system = System::builder().image(counter()).build().unwrap();
+    assert_eq!(system.cpu().registers.pc.get(), 0x0200);
+
+    assert_eq!(system.step(), 2);
+    assert_eq!(system.step(), 3);
+    assert_eq!(system.cpu().registers.x.get(), 1);
+    assert_eq!(system.cpu().registers.pc.get(), 0x0200);
+
+    system.step();
+    system.reset();
+    assert_eq!(system.cpu().registers.pc.get(), 0x0200);
+    assert_eq!(system.memory_mut().read(0x0200), 0xE8);
+}
+
+#[test]
+fn warm_start_signature_survives_reset_but_not_power_on() {
+    let mut system = System::builder().image(counter()).build().unwrap();
+    assert_eq!(system.memory_mut().read(0x0300), 0x00);
+    system.memory_mut().write(0x0300, 0xA5);
+    system.memory_mut().write(0x0301, 0x5A);
+    system.run_for_cycles(50);
+
+    system.reset();
+    assert_eq!(system.memory_mut().read(0x0300), 0xA5);
+    assert_eq!(system.memory_mut().read(0x0301), 0x5A);
+
+    system.power_on(&[0xFF, 0x00]);
+    assert_eq!(system.memory_mut().read(0x0300), 0xFF);
+    assert_eq!(system.memory_mut().read(0x0301), 0x00);
+    assert_eq!(system.cpu().registers.x.get(), 0);
+    assert_eq!(system.cpu().registers.pc.get(), 0x0200);
+    assert_eq!(system.memory_mut().read(0x0200), 0xE8);
+
+    assert_eq!(system.step(), 2);
+    assert_eq!(system.cpu().registers.x.get(), 1);
+}
+
+#[test]
+fn durations_run_at_the_clock_rate() {
+    let mut system = System::builder()
+        .image(counter())
+        .frequency(2_000_000)
+        .build()
+        .unwrap();
+
+    let start = system.cpu().cycles();
+    let overshoot = system.run_for(Duration::from_micros(10));
+    assert_eq!(system.cpu().cycles() - start, 20 + overshoot);
+    assert_eq!(system.cpu().registers.x.get(), 4);
+
+    let overshoot = system.run_for_cycles(15 - overshoot);
+    assert_eq!(overshoot, 0);
+    assert_eq!(system.cpu().registers.x.get(), 7);
+}
+
+#[test]
+fn images_must_fit_in_ram() {
+    let image = Image::from_bytes(0xFFFF, &[0x00, 0x00]).start(0x0200);
+    let errors = System::builder().image(image).build().err().unwrap();
+    assert_eq!(
+        errors,
+        vec![ConfigError::Image(MemoryError::OutOfRange {
+            address: 0xFFFF,
+            len: 2
+        })]
+    );
+}
+
+#[test]
+fn realtime_pacing_holds_the_clock_rate() {
+    let mut system = System::builder()
+        .image(counter())
+        .frequency(100_000)
+        .pacing(Pacing::Realtime)
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    let overshoot = system.run_for(Duration::from_millis(20));
+    let speed = system.speed().unwrap();
+    assert_eq!(speed.cycles, 2_000 + overshoot);
+    assert!(started.elapsed() >= Duration::from_millis(20));
+    assert!(speed.hz() <= 100_000.0 * 1.01);
+
+    system.set_pacing(Pacing::Unlimited);
+    system.run_for(Duration::from_millis(20));
+    assert!(system.speed().unwrap().elapsed < Duration::from_millis(20));
+}
+
+#[test]
+fn systems_can_move_between_threads() {
+    let mut system = System::builder()
+        .device("stdout", StdoutDevice::RANGE, StdoutDevice::new())
+        .image(counter())
+        .build()
+        .unwrap();
+    system.step();
+
+    let system = thread::spawn(move || {
+        system.run_for_cycles(100);
+        system
+    })
+    .join()
+    .unwrap();
+    assert!(system.cpu().registers.x.get() > 1);
+}
@@ -0,0 +1,85 @@
+//! Third-party test ROMs run until they report a result.
+#![allow(clippy::needless_return)]
+
+use cpu::Cpu;
+use system::{Bus, Memory, RunOutcome, TestRom};
+
+/// Returns a CPU about to run `program` from $0200.
+fn setup(program: &[u8]) -> (Cpu, Memory<'static>) {
+    let mut mem = Memory::new();
+    mem.load(0x0200, program).unwrap();
+    let mut cpu = Cpu::new();
+    cpu.registers.pc.set(0x0200);
+    return (cpu, mem);
+}
+
+/// Stores `status` at $6000, then waits in a `jmp *` loop.
+fn report(status: u8) -> [u8; 8] {
+    return [0xA9, status, 0x8D, 0x00, 0x60, 0x4C, 0x05, 0x02];
+}
+
+#[test]
+fn status_writes_pass_or_fail() {
+    let rom = TestRom::new(1000).write_to(0x6000, 0x00);
+
+    let (mut cpu, mut mem) = setup(&report(0x00));
+    assert_eq!(rom.run(&mut cpu, &mut mem), RunOutcome::Passed);
+    assert_eq!(cpu.registers.pc.get(), 0x0205);
+
+    let (mut cpu, mut mem) = setup(&report(0x81));
+    assert_eq!(
+        rom.run(&mut cpu, &mut mem),
+        RunOutcome::Failed { code: 0x81 }
+    );
+}
+
+#[test]
+fn other_writes_are_not_a_status() {
+    // sta $6001; lda #$01; sta $6000
+    let program = [0x8D, 0x01, 0x60, 0xA9, 0x01, 0x8D, 0x00, 0x60];
+    let (mut cpu, mut mem) = setup(&program);
+    let outcome = TestRom::new(1000)
+        .write_to(0x6000, 0x01)
+        .run(&mut cpu, &mut mem);
+    assert_eq!(outcome, RunOutcome::Passed);
+    assert_eq!(mem.read(0x6001), 0x00);
+}
+
+#[test]
+fn traps_pass_at_the_success_address() {
+    // ldx #3; dex; bne *-1; jmp *
+    let program = [0xA2, 0x03, 0xCA, 0xD0, 0xFD, 0x4C, 0x05, 0x02];
+    let (mut cpu, mut mem) = setup(&program);
+    let outcome = TestRom::new(1000).trap_at(0x0205).run(&mut cpu, &mut mem);
+    assert_eq!(outcome, RunOutcome::Passed);
+    assert_eq!(cpu.registers.x.get(), 0);
+}
+
+#[test]
+fn traps_elsewhere_fail_with_their_address() {
+    // lda #0; beq *
+    let program = [0xA9, 0x00, 0xF0, 0xFE];
+    let (mut cpu, mut mem) = setup(&program);
+    let outcome = TestRom::new(1000).trap_at(0x3469).run(&mut cpu, &mut mem);
+    assert_eq!(outcome, RunOutcome::Failed { code: 0x0202 });
+}
+
+#[test]
+fn roms_which_never_report_time_out() {
+    // loop: inx; jmp loop
+    let program = [0xE8, 0x4C, 0x00, 0x02];
+    let (mut cpu, mut mem) = setup(&program);
+    let outcome = TestRom::new(10)
+        .trap_at(0x0200)
+        .write_to(0x6000, 0x00)
+        .run(&mut cpu, &mut mem);
+    assert_eq!(outcome, RunOutcome::Timeout);
+    assert_eq!(cpu.registers.x.get(), 5);
+
+    // with no recognizers even a trap is not a result
+    let (mut cpu, mut mem) = setup(&report(0x00));
+    assert_eq!(
+        TestRom::new(10).run(&mut cpu, &mut mem),
+        RunOutcome::Timeout
+    );
+}
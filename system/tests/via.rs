@@ -0,0 +1,221 @@
+//! The 6522 VIA's timers, ports and shift register.
+#![allow(clippy::needless_return)]
+
+use std::sync::{Arc, Mutex};
+
+use system::device::{Device, PortHandler, ViaDevice};
+use system::{Bus, Image, System};
+
+const ORB: u16 = 0x0;
+const ORA: u16 = 0x1;
+const DDRB: u16 = 0x2;
+const DDRA: u16 = 0x3;
+const T1C_L: u16 = 0x4;
+const T1C_H: u16 = 0x5;
+const T2C_L: u16 = 0x8;
+const T2C_H: u16 = 0x9;
+const SR: u16 = 0xA;
+const ACR: u16 = 0xB;
+const PCR: u16 = 0xC;
+const IFR: u16 = 0xD;
+const IER: u16 = 0xE;
+
+/// What a port has seen from the VIA, and what it drives the VIA's inputs to.
+#[derive(Default)]
+struct Wires {
+    input: u8,
+    control: bool,
+    writes: Vec<(u8, u8)>,
+    shifted: Vec<u8>,
+}
+
+#[derive(Clone, Default)]
+struct Port(Arc<Mutex<Wires>>);
+
+impl PortHandler for Port {
+    fn read(&self) -> u8 {
+        return self.0.lock().unwrap().input;
+    }
+
+    fn write(&mut self, output: u8, direction: u8) {
+        self.0.lock().unwrap().writes.push((output, direction));
+    }
+
+    fn control(&self) -> bool {
+        return self.0.lock().unwrap().control;
+    }
+
+    fn shift_in(&mut self) -> u8 {
+        return self.0.lock().unwrap().input;
+    }
+
+    fn shift_out(&mut self, byte: u8) {
+        self.0.lock().unwrap().shifted.push(byte);
+    }
+}
+
+/// Returns the cycles after which T1 sets its flag, within `limit`.
+fn t1_flags(via: &mut ViaDevice, limit: u64) -> Vec<u64> {
+    let mut flags = vec![];
+    for cycle in 1..=limit {
+        via.tick(1);
+        if via.peek(IFR).unwrap() & 0x40 != 0 {
+            flags.push(cycle);
+            via.write(IFR, 0x40);
+        }
+    }
+    return flags;
+}
+
+#[test]
+fn one_shot_timer_interrupts_once() {
+    let mut via = ViaDevice::new();
+    via.write(T1C_L, 10);
+    via.write(T1C_H, 0);
+    assert_eq!(
+        t1_flags(&mut via, 100),
+        vec![10 + ViaDevice::T1_EXTRA_CYCLES]
+    );
+
+    // the flag only pulls the irq line once it is enabled
+    via.write(T1C_H, 0);
+    via.tick(20);
+    assert!(!via.irq_asserted());
+    via.write(IER, 0xC0);
+    assert!(via.irq_asserted());
+    assert_eq!(via.read(IFR), 0xC0);
+    via.read(T1C_L);
+    assert!(!via.irq_asserted());
+}
+
+#[test]
+fn free_running_timer_toggles_pb7() {
+    let port = Port::default();
+    let mut via = ViaDevice::new().with_port_b(port.clone());
+    via.write(ACR, 0xC0);
+    via.write(T1C_L, 4);
+    via.write(T1C_H, 0);
+    assert_eq!(t1_flags(&mut via, 20), vec![5, 11, 17]);
+
+    let pb7 = port
+        .0
+        .lock()
+        .unwrap()
+        .writes
+        .iter()
+        .map(|(output, _)| output >> 7)
+        .collect::<Vec<_>>();
+    assert_eq!(pb7, vec![1, 0, 1, 0, 1]);
+}
+
+#[test]
+fn second_timer_counts_down_once() {
+    let mut via = ViaDevice::new();
+    via.write(T2C_L, 0x10);
+    via.write(T2C_H, 0x00);
+    via.tick(0x10);
+    assert_eq!(via.read(IFR) & 0x20, 0);
+    via.tick(1);
+    assert_eq!(via.read(IFR) & 0x20, 0x20);
+    via.read(T2C_L);
+    via.tick(0x20000);
+    assert_eq!(via.read(IFR) & 0x20, 0);
+}
+
+#[test]
+fn second_timer_rolls_over_on_long_ticks() {
+    let mut via = ViaDevice::new();
+    via.write(T2C_L, 0x34);
+    via.write(T2C_H, 0x12);
+
+    // one tick of more than a whole turn of the counter
+    via.tick(0x1_0005);
+    assert_eq!(via.read(IFR) & 0x20, 0x20);
+    assert_eq!(via.read(T2C_H), 0x12);
+    assert_eq!(via.read(T2C_L), 0x2F);
+
+    via.tick(0x3_1230);
+    assert_eq!(via.read(T2C_L), 0xFF);
+    assert_eq!(via.read(T2C_H), 0xFF);
+}
+
+#[test]
+fn ports_follow_their_direction() {
+    let port = Port::default();
+    port.0.lock().unwrap().input = 0b1010_1010;
+    let mut via = ViaDevice::new().with_port_a(port.clone());
+    via.write(DDRA, 0x0F);
+    via.write(ORA, 0xFF);
+
+    assert_eq!(via.read(ORA), 0b1010_1111);
+    assert_eq!(port.0.lock().unwrap().writes.last(), Some(&(0xFF, 0x0F)));
+    assert_eq!(via.read(DDRB), 0);
+    assert_eq!(via.read(ORB), 0xFF);
+}
+
+#[test]
+fn control_edges_set_flags_and_latch() {
+    let port = Port::default();
+    port.0.lock().unwrap().control = true;
+    let mut via = ViaDevice::new().with_port_a(port.clone());
+    via.write(ACR, 0x01);
+    via.write(PCR, 0x00);
+
+    // falling edges are active, rising ones are not
+    port.0.lock().unwrap().control = false;
+    port.0.lock().unwrap().input = 0x42;
+    via.tick(1);
+    assert_eq!(via.read(IFR) & 0x02, 0x02);
+    port.0.lock().unwrap().input = 0x99;
+    port.0.lock().unwrap().control = true;
+    via.tick(1);
+    // the port reads as it was at the edge, and reading clears the flag
+    assert_eq!(via.read(ORA), 0x42);
+    assert_eq!(via.read(IFR) & 0x02, 0);
+}
+
+#[test]
+fn shift_register_moves_bytes() {
+    let port = Port::default();
+    port.0.lock().unwrap().input = 0x5A;
+    let mut via = ViaDevice::new().with_port_b(port.clone());
+
+    // shift out under φ2
+    via.write(ACR, 0b110 << 2);
+    via.write(SR, 0xC3);
+    via.tick(15);
+    assert!(port.0.lock().unwrap().shifted.is_empty());
+    via.tick(1);
+    assert_eq!(port.0.lock().unwrap().shifted, vec![0xC3]);
+    assert_eq!(via.read(IFR) & 0x04, 0x04);
+
+    // shift in under φ2, started by reading
+    via.write(ACR, 0b010 << 2);
+    via.read(SR);
+    assert_eq!(via.read(IFR) & 0x04, 0);
+    via.tick(16);
+    assert_eq!(via.read(SR), 0x5A);
+}
+
+#[test]
+fn timer_interrupts_reach_the_cpu() {
+    // lda #$40; sta $a07b; lda #$c0; sta $a07e; lda #$fe; sta $a074
+    // lda #$00; sta $a075; cli; loop: jmp loop
+    let program = [
+        0xA9, 0x40, 0x8D, 0x7B, 0xA0, 0xA9, 0xC0, 0x8D, 0x7E, 0xA0, 0xA9, 0xFE, 0x8D, 0x74, 0xA0,
+        0xA9, 0x00, 0x8D, 0x75, 0xA0, 0x58, 0x4C, 0x15, 0x02,
+    ];
+    // inc $10; lda $a074; rti
+    let handler = [0xE6, 0x10, 0xAD, 0x74, 0xA0, 0x40];
+    let mut system = System::builder()
+        .device("via", ViaDevice::RANGE, ViaDevice::new())
+        .image(Image::from_bytes(0x0200, &program).start(0x0200))
+        .image(Image::from_bytes(0x0300, &handler))
+        .build()
+        .unwrap();
+    system.memory_mut().set_irq_vector(0x0300).unwrap();
+
+    // a period of $fe + 2 cycles
+    system.run_for_cycles(256 * 10);
+    assert!((9..=10).contains(&system.memory_mut().read(0x10)));
+}
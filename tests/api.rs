@@ -0,0 +1,32 @@
+//! Embeds the emulator through the top-level crate alone.
+#![allow(clippy::needless_return)]
+
+use rs6502::device::RamDevice;
+use rs6502::{disassemble, opcode_info, AddressMode, Bus, CpuVariant, Range, System};
+
+#[test]
+fn runs_a_machine() {
+    // lda #$2a; sta $0300; jmp *
+    let program = vec![0xA9, 0x2A, 0x8D, 0x00, 0x03, 0x4C, 0x05, 0x10];
+    let mut system = System::builder()
+        .device("ram", Range::new(0x0300, 0x0400), RamDevice::new())
+        .rom("program", Range::new(0x1000, 0x2000), program)
+        .reset_vector(0x1000)
+        .build()
+        .unwrap();
+    system.run_for_cycles(20);
+
+    assert_eq!(system.memory().read(0x0300), 0x2A);
+    assert_eq!(system.cpu().registers.pc.get(), 0x1005);
+}
+
+#[test]
+fn describes_instructions() {
+    let info = opcode_info(CpuVariant::Nmos6502, 0xB1).unwrap();
+    assert_eq!((info.mnemonic, info.mode), ("LDA", AddressMode::IndirectY));
+    assert_eq!((info.bytes, info.cycles), (2, 5));
+    assert_eq!(opcode_info(CpuVariant::Nmos6502, 0x02), None);
+
+    let text = disassemble(CpuVariant::Nmos6502, 0x1000, 0xB1, &[0x10], None);
+    assert_eq!(text, "LDA ($10),Y");
+}
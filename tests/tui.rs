@@ -0,0 +1,145 @@
+//! The terminal debugger, driven by commands and checked through its panes.
+#![cfg(feature = "tui")]
+#![allow(clippy::needless_return)]
+
+use rs6502::device::StdoutDevice;
+use rs6502::tui::{Console, Flow, Tui};
+use rs6502::{Cpu, Debugger, MachineConfig, Range, SymbolTable};
+
+// start: lda #'H'; sta $a000; jsr sub
+// hang:  jmp hang
+//        brk
+// sub:   lda #'i'; sta $a000; rts
+const PROGRAM: [u8; 18] = [
+    0xA9, 0x48, 0x8D, 0x00, 0xA0, 0x20, 0x0C, 0x10, 0x4C, 0x08, 0x10, 0x00, 0xA9, 0x69, 0x8D, 0x00,
+    0xA0, 0x60,
+];
+
+fn boot() -> Tui<'static> {
+    let console = Console::new();
+    let mut mem = MachineConfig::new()
+        .device(
+            "stdout",
+            StdoutDevice::RANGE,
+            StdoutDevice::with_output(console.clone()),
+        )
+        .rom("program", Range::new(0x1000, 0x2000), PROGRAM.to_vec())
+        .reset_vector(0x1000)
+        .build()
+        .unwrap();
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut mem);
+
+    let symbols = SymbolTable::parse("$1000 start\n$1008 hang\n$100c sub\n").unwrap();
+    return Tui::new(cpu, mem, Debugger::new(), symbols, console);
+}
+
+/// Returns the rendered line which contains `text`.
+fn line_with(tui: &Tui, text: &str) -> String {
+    let lines = tui.render(100, 30);
+    return lines
+        .iter()
+        .find(|line| line.contains(text))
+        .unwrap_or_else(|| panic!("no line contains '{}':\n{}", text, lines.join("\n")))
+        .clone();
+}
+
+#[test]
+fn the_screen_has_every_pane() {
+    let tui = boot();
+    let lines = tui.render(100, 30);
+
+    assert_eq!(lines.len(), 30);
+    assert!(lines.iter().all(|line| line.chars().count() == 100));
+    for title in ["Disassembly", "Registers", "Stack", "Watch", "Console"] {
+        line_with(&tui, &format!(" {} ", title));
+    }
+    assert!(line_with(&tui, "start:").starts_with(&format!("│{:18}start:", "")));
+    assert!(line_with(&tui, ">  $1000").contains("a9 48       LDA #$48"));
+    assert!(line_with(&tui, "$1005").contains("JSR sub"));
+    line_with(&tui, "PC $1000  SP $FD");
+    line_with(&tui, "A $00  X $00  Y $00");
+}
+
+#[test]
+fn stepping_and_running_move_the_pc() {
+    let mut tui = boot();
+    assert_eq!(tui.execute("s 2"), Flow::Continue);
+    assert_eq!(tui.cpu().registers.pc.get(), 0x1005);
+    tui.execute("");
+    assert_eq!(tui.cpu().registers.pc.get(), 0x100C);
+
+    // the return address of the jsr is on the stack
+    line_with(&tui, "$01FC  07");
+    line_with(&tui, "$01FD  10");
+    assert!(line_with(&tui, ">  $100C").contains("LDA #$69"));
+
+    tui.execute("u hang");
+    assert_eq!(tui.message(), "breakpoint at $1008 (hang)");
+    assert_eq!(tui.cpu().registers.pc.get(), 0x1008);
+    assert_eq!(tui.debugger().temporary_breakpoints().count(), 0);
+    line_with(&tui, "│Hi ");
+    line_with(&tui, "breakpoint at $1008 (hang)");
+}
+
+#[test]
+fn breakpoints_are_marked_and_saved() {
+    let mut tui = boot();
+    tui.execute("b sub");
+    assert_eq!(tui.message(), "breakpoint at $100C (sub)");
+    assert!(line_with(&tui, "$100C").starts_with("│ * $100C"));
+
+    tui.execute("c");
+    assert_eq!(tui.message(), "breakpoint at $100C (sub)");
+    assert_eq!(tui.debugger().save(), "break $100c\n");
+
+    tui.execute("b $100c");
+    assert_eq!(tui.message(), "cleared the breakpoint at $100C (sub)");
+    assert_eq!(tui.debugger().breakpoints().count(), 0);
+}
+
+#[test]
+fn watched_memory_is_shown_and_stops_execution() {
+    let mut tui = boot();
+    tui.execute("w $a000 w");
+    assert_eq!(tui.message(), "watching $A000");
+    line_with(&tui, "$A000 w   --");
+
+    tui.execute("c");
+    assert_eq!(tui.message(), "wrote $48 to $A000");
+    tui.execute("c");
+    assert_eq!(tui.message(), "wrote $69 to $A000");
+    assert_eq!(tui.cpu().registers.pc.get(), 0x1011);
+
+    tui.execute("w $a000 w");
+    assert_eq!(tui.message(), "stopped watching $A000");
+    assert_eq!(tui.debugger().watchpoints().count(), 0);
+}
+
+#[test]
+fn bad_commands_are_reported() {
+    let mut tui = boot();
+    tui.execute("x");
+    assert_eq!(tui.message(), "error: unknown command 'x'");
+    tui.execute("b nowhere");
+    assert_eq!(tui.message(), "error: unknown symbol 'nowhere'");
+    tui.execute("u $zz");
+    assert_eq!(tui.message(), "error: bad address '$zz'");
+    tui.execute("w $10 x");
+    assert_eq!(tui.message(), "error: unknown kind of watch 'x'");
+    tui.execute("s many");
+    assert_eq!(tui.message(), "error: expected a number of instructions");
+    assert_eq!(tui.cpu().registers.pc.get(), 0x1000);
+
+    assert_eq!(tui.execute("q"), Flow::Quit);
+}
+
+#[test]
+fn small_screens_do_not_panic() {
+    let tui = boot();
+    for (width, height) in [(0, 0), (1, 1), (10, 3), (40, 8)] {
+        let lines = tui.render(width, height);
+        assert_eq!(lines.len(), height);
+        assert!(lines.iter().all(|line| line.chars().count() == width));
+    }
+}
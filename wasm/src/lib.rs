@@ -0,0 +1,161 @@
+//! JavaScript bindings for running rs6502 in a browser, built with
+//! `wasm-pack build wasm --target web`.
+//! ```text
+//!     const emulator = new Emulator();
+//!     emulator.load(program, 0x1000);
+//!     function frame() {
+//!         emulator.run(16_667);
+//!         console.textContent += emulator.take_output();
+//!         draw(emulator.framebuffer());
+//!         requestAnimationFrame(frame);
+//!     }
+//! ```
+//! The machine is 64K of RAM with a console and a text screen mapped over it,
+//! at the addresses the native examples use.
+#![allow(clippy::needless_return)]
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use system::device::{StdoutDevice, TextDisplayDevice};
+use system::{Bus, System};
+use wasm_bindgen::prelude::*;
+
+/// Collects what the guest writes to the console until JavaScript takes it.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        return Ok(());
+    }
+}
+
+/// The CPU registers at some point in time.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Registers {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub pc: u16,
+    /// The status register, `NV-BDIZC` from bit 7 down.
+    pub status: u8,
+    /// The cycles run since power on.
+    pub cycles: u64,
+}
+
+/// A 6502 machine which runs whenever JavaScript asks it to.
+#[wasm_bindgen]
+pub struct Emulator {
+    system: System<'static>,
+    output: Output,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let output = Output::default();
+        let system = System::builder()
+            .device(
+                "console",
+                StdoutDevice::RANGE,
+                StdoutDevice::with_output(output.clone()),
+            )
+            .device(
+                "screen",
+                TextDisplayDevice::RANGE,
+                TextDisplayDevice::with_output(std::io::sink()),
+            )
+            // until a program is loaded
+            .reset_vector(0x0000)
+            .build()
+            .expect("the memory map is valid");
+        return Self { system, output };
+    }
+
+    /// Copies `program` into RAM at `address`, points the reset vector at it
+    /// and resets the machine, so that [`Emulator::reset`] starts the program
+    /// over.
+    pub fn load(&mut self, program: &[u8], address: u16) -> Result<(), JsError> {
+        let memory = self.system.memory_mut();
+        memory.load(address, program)?;
+        memory.set_reset_vector(address)?;
+        self.system.reset();
+        return Ok(());
+    }
+
+    /// Resets the CPU and the devices. RAM is preserved.
+    pub fn reset(&mut self) {
+        self.system.reset();
+    }
+
+    /// Runs one instruction and returns the number of cycles it took.
+    pub fn step(&mut self) -> u32 {
+        return self.system.step() as u32;
+    }
+
+    /// Runs whole instructions until at least `cycles` cycles have elapsed and
+    /// returns by how many cycles the budget was overshot.
+    pub fn run(&mut self, cycles: u32) -> u32 {
+        return self.system.run_for_cycles(u64::from(cycles)) as u32;
+    }
+
+    pub fn registers(&self) -> Registers {
+        let cpu = self.system.cpu();
+        return Registers {
+            a: cpu.registers.acc.get(),
+            x: cpu.registers.x.get(),
+            y: cpu.registers.y.get(),
+            sp: cpu.registers.sp.get(),
+            pc: cpu.registers.pc.get(),
+            status: cpu.status.get_raw(),
+            cycles: cpu.cycles(),
+        };
+    }
+
+    /// Returns whether an undefined opcode has locked up the CPU.
+    pub fn jammed(&self) -> bool {
+        return self.system.cpu().jammed().is_some();
+    }
+
+    /// Returns the byte at `address` without side effects, or `undefined` if
+    /// the device there cannot tell.
+    pub fn peek(&self, address: u16) -> Option<u8> {
+        return self.system.memory().peek(address);
+    }
+
+    /// Stores `data` at `address` as the CPU would.
+    pub fn poke(&mut self, address: u16, data: u8) {
+        self.system.memory_mut().write(address, data);
+    }
+
+    /// Returns what the program has written to the console since the last call.
+    pub fn take_output(&mut self) -> String {
+        let bytes = std::mem::take(&mut *self.output.0.lock().unwrap());
+        return String::from_utf8_lossy(&bytes).into_owned();
+    }
+
+    /// Returns the text screen, 40 characters by 25 rows, one byte per
+    /// character row by row.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        let start = TextDisplayDevice::RANGE.start;
+        let len = (TextDisplayDevice::COLUMNS * TextDisplayDevice::ROWS) as u16;
+        return (start..start + len)
+            .map(|address| self.system.memory().peek(address).unwrap_or(b' '))
+            .collect();
+    }
+}
@@ -0,0 +1,53 @@
+//! Drives the bindings the way a page would, natively.
+#![allow(clippy::needless_return)]
+
+use rs6502_wasm::Emulator;
+
+// lda #'h'; sta $a000; lda #'i'; sta $a000; lda #'A'; sta $8000; jmp *
+const PROGRAM: [u8; 18] = [
+    0xA9, 0x68, 0x8D, 0x00, 0xA0, 0xA9, 0x69, 0x8D, 0x00, 0xA0, 0xA9, 0x41, 0x8D, 0x00, 0x80, 0x4C,
+    0x0F, 0x10,
+];
+
+#[test]
+fn runs_a_loaded_program() {
+    let mut emulator = Emulator::new();
+    emulator.load(&PROGRAM, 0x1000).unwrap();
+    assert_eq!(emulator.registers().pc, 0x1000);
+
+    assert_eq!(emulator.step(), 2);
+    assert_eq!(emulator.registers().a, b'h');
+    emulator.run(100);
+
+    let registers = emulator.registers();
+    assert_eq!((registers.a, registers.pc), (b'A', 0x100F));
+    assert_eq!(emulator.take_output(), "hi");
+    assert_eq!(emulator.take_output(), "");
+    assert!(!emulator.jammed());
+}
+
+#[test]
+fn shows_the_screen() {
+    let mut emulator = Emulator::new();
+    emulator.load(&PROGRAM, 0x1000).unwrap();
+    emulator.run(100);
+
+    let framebuffer = emulator.framebuffer();
+    assert_eq!(framebuffer.len(), 40 * 25);
+    assert_eq!(framebuffer[0], b'A');
+    assert!(framebuffer[1..].iter().all(|&c| c == b' '));
+    assert_eq!(emulator.peek(0x8000), Some(b'A'));
+}
+
+#[test]
+fn resets_to_the_program() {
+    let mut emulator = Emulator::new();
+    emulator.load(&PROGRAM, 0x1000).unwrap();
+    emulator.run(100);
+    emulator.poke(0x1001, b'y');
+
+    emulator.reset();
+    assert_eq!(emulator.registers().pc, 0x1000);
+    emulator.run(100);
+    assert_eq!(emulator.take_output(), "hiyi");
+}